@@ -34,8 +34,8 @@ fn vertex(vertex: Vertex) -> VertexOutput {
 
 @fragment
 fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
-    let vertex_mine = (mesh.packed_block >> 9u & 1u) == 1u;
-    let vertex_blue = (mesh.packed_block >> 10u & 1u) == 1u;
+    let vertex_mine = (mesh.packed_block >> 13u & 1u) == 1u;
+    let vertex_blue = (mesh.packed_block >> 14u & 1u) == 1u;
 
     let ox = f32(texture_idx % texture_count);
     let oy = f32(texture_idx / texture_count);
@@ -47,11 +47,13 @@ fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
     var outc = color * textureSample(texture, texture_sampler, uv);
 
     if (vertex_blue) {
-        outc[2] = 0.25;
+        let stripe = fract((mesh.position.x + mesh.position.z) * 2.0);
+        let strength = select(0.35, 0.75, stripe < 0.5);
+        outc = mix(outc, vec4(0.3, 0.55, 1.0, 1.0), strength);
     }
 
     if (vertex_mine) {
-        outc[0] = 0.05;
+        outc = mix(outc, vec4(1.0, 0.45, 0.15, 1.0), 0.4);
         let axe_texture_idx = 60u;
         let axe_ox = f32(axe_texture_idx % texture_count);
         let axe_oy = f32(axe_texture_idx / texture_count);
@@ -5,12 +5,14 @@
 @group(2) @binding(2) var<uniform> color: vec4<f32>;
 @group(2) @binding(3) var<uniform> texture_count: u32;
 @group(2) @binding(4) var<uniform> terrain_slice_y: u32;
+@group(2) @binding(5) var<uniform> ambient_light: f32;
+@group(2) @binding(6) var<uniform> sun_intensity: f32;
+@group(2) @binding(7) var<uniform> sun_color: vec4<f32>;
 
 struct Vertex {
     @builtin(instance_index) instance_index: u32,
     @location(0) position: vec3<f32>,
     @location(1) packed_block: u32,
-    @location(2) light: u32,
 }
 
 struct VertexOutput {
@@ -20,7 +22,7 @@ struct VertexOutput {
     @location(2) position_world: vec4<f32>,
     @location(4) vertex_index: u32,
     @location(5) ao: f32,
-    @location(6) light: f32,
+    @location(6) light: vec3<f32>,
 };
 
 @vertex 
@@ -41,7 +43,7 @@ fn vertex(vertex: Vertex) -> VertexOutput {
 
     out.vertex_index = vertex.instance_index;
 
-    let vertex_ao = vertex.packed_block >> 7u & 3u;
+    let vertex_ao = vertex.packed_block >> 11u & 3u;
 
     switch vertex_ao {
         case 0u: {
@@ -61,20 +63,26 @@ fn vertex(vertex: Vertex) -> VertexOutput {
         }
     }
 
-    let torch = vertex.light & 0xf;
-    let sun = (vertex.light >> 4) & 0xf;
-    out.light = f32(max(sun, torch)) / 15.0 + 0.1055;
+    let torch = vertex.packed_block >> 15u & 15u;
+    let sun = vertex.packed_block >> 19u & 15u;
+    let sun_light = (f32(sun) / 15.0) * sun_intensity * sun_color.rgb;
+    let torch_light = (f32(torch) / 15.0) * vec3(1.0, 0.91, 0.56);
+    let light_level = max(sun_light, torch_light);
+    out.light = ambient_light + (1.0 - ambient_light) * light_level;
 
     return out;
 }
 
 @fragment
 fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
-    let block_type = mesh.packed_block & 15u;
-    let block_face = mesh.packed_block >> 4u & 7u;
-    let vertex_ao = mesh.packed_block >> 7u & 3u;
-    let vertex_mine = (mesh.packed_block >> 9u & 1u) == 1u;
-    let vertex_blue = (mesh.packed_block >> 10u & 1u) == 1u;
+    let block_type = mesh.packed_block & 255u;
+    let block_face = mesh.packed_block >> 8u & 7u;
+    let vertex_ao = mesh.packed_block >> 11u & 3u;
+    let vertex_mine = (mesh.packed_block >> 13u & 1u) == 1u;
+    let vertex_blue = (mesh.packed_block >> 14u & 1u) == 1u;
+    let vertex_transparent = (mesh.packed_block >> 23u & 1u) == 1u;
+    let vertex_cracks = mesh.packed_block >> 24u & 3u;
+    let vertex_emissive = (mesh.packed_block >> 26u & 1u) == 1u;
     let vert = mesh.vertex_index % 4;
 
     var uv: vec2<f32>;
@@ -135,16 +143,36 @@ fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
 
     uv = uv / f32(texture_count);
     let tex = textureSample(texture, texture_sampler, uv);
-    var outc = light * tex * mesh.ao * (mesh.light * vec4(1.0, 0.91, 0.56, 1.0));
+    var outc = light * tex * mesh.ao * vec4(mesh.light, 1.0);
 
-    outc[3] = 1.0;
+    // glowing blocks skip face shading, ao, and lighting
+    if (vertex_emissive) {
+        outc = tex;
+    }
+
+    outc[3] = select(1.0, 0.4, vertex_transparent);
     
+    // blueprints get a blue diagonal hatch
     if (vertex_blue) {
-        outc[2] = 0.25;
+        let stripe = fract((mesh.position_world.x + mesh.position_world.y + mesh.position_world.z) * 2.0);
+        let strength = select(0.35, 0.75, stripe < 0.5);
+        outc = mix(outc, vec4(0.3, 0.55, 1.0, 1.0), strength);
+    }
+
+    // partly mined blocks get dark cracks that widen with each damage stage
+    if (vertex_cracks > 0u) {
+        let crack_a = abs(fract(uv_px_offset.x * 2.0 + uv_px_offset.y * 3.0) - 0.5);
+        let crack_b = abs(fract(uv_px_offset.x * 3.0 - uv_px_offset.y * 2.0) - 0.5);
+        let width = 0.03 * f32(vertex_cracks);
+
+        if (crack_a < width || crack_b < width) {
+            outc = vec4(outc.rgb * 0.35, outc[3]);
+        }
     }
 
+    // mine designations get an orange tint with the pickaxe icon on top
     if (vertex_mine) {
-        outc[0] = 0.1;
+        outc = mix(outc, vec4(1.0, 0.45, 0.15, 1.0), 0.4);
         let axe_texture_idx = 60u;
         let axe_ox = f32(axe_texture_idx % texture_count);
         let axe_oy = f32(axe_texture_idx / texture_count);
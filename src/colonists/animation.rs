@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use bevy::{
+    animation::{AnimationClip, AnimationPlayer},
+    asset::Handle,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{Added, Changed, Has, Or, With},
+        system::{Commands, Query, Res, Resource},
+    },
+    hierarchy::{HierarchyQueryExt, Parent},
+    utils::HashSet,
+};
+
+use super::{ActorRef, BlockMove, Colonist, TaskChop, TaskMineBlock};
+
+const ANIMATION_TRANSITION: Duration = Duration::from_millis(200);
+
+#[derive(Component, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Walking,
+    Mining,
+}
+
+#[derive(Resource)]
+pub struct ColonistAnimationClips {
+    pub idle: Handle<AnimationClip>,
+    pub walking: Handle<AnimationClip>,
+    pub mining: Handle<AnimationClip>,
+}
+
+impl ColonistAnimationClips {
+    pub fn get(&self, state: AnimationState) -> &Handle<AnimationClip> {
+        match state {
+            AnimationState::Idle => &self.idle,
+            AnimationState::Walking => &self.walking,
+            AnimationState::Mining => &self.mining,
+        }
+    }
+}
+
+/// Points a colonist at the AnimationPlayer spawned somewhere inside its gltf scene.
+#[derive(Component)]
+pub struct ColonistAnimator {
+    pub player: Entity,
+}
+
+pub fn link_colonist_animators(
+    mut cmd: Commands,
+    clips: Res<ColonistAnimationClips>,
+    mut q_players: Query<(Entity, &mut AnimationPlayer), Added<AnimationPlayer>>,
+    q_parents: Query<&Parent>,
+    q_colonists: Query<&AnimationState, With<Colonist>>,
+) {
+    for (player_entity, mut player) in q_players.iter_mut() {
+        for ancestor in q_parents.iter_ancestors(player_entity) {
+            let Ok(state) = q_colonists.get(ancestor) else {
+                continue;
+            };
+
+            player.play(clips.get(*state).clone_weak()).repeat();
+            cmd.entity(ancestor).insert(ColonistAnimator {
+                player: player_entity,
+            });
+            break;
+        }
+    }
+}
+
+pub fn tick_animation_state(
+    q_tasks: Query<&ActorRef, Or<(With<TaskMineBlock>, With<TaskChop>)>>,
+    mut q_colonists: Query<(Entity, &mut AnimationState, Has<BlockMove>), With<Colonist>>,
+) {
+    let miners = q_tasks
+        .iter()
+        .map(|ActorRef(actor)| *actor)
+        .collect::<HashSet<_>>();
+
+    for (entity, mut state, is_moving) in q_colonists.iter_mut() {
+        let next = if miners.contains(&entity) {
+            AnimationState::Mining
+        } else if is_moving {
+            AnimationState::Walking
+        } else {
+            AnimationState::Idle
+        };
+
+        if *state != next {
+            *state = next;
+        }
+    }
+}
+
+pub fn play_animation_state(
+    clips: Res<ColonistAnimationClips>,
+    q_colonists: Query<(&AnimationState, &ColonistAnimator), Changed<AnimationState>>,
+    mut q_players: Query<&mut AnimationPlayer>,
+) {
+    for (state, animator) in q_colonists.iter() {
+        let Ok(mut player) = q_players.get_mut(animator.player) else {
+            continue;
+        };
+
+        player
+            .play_with_transition(clips.get(*state).clone_weak(), ANIMATION_TRANSITION)
+            .repeat();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{system::RunSystemOnce, world::World};
+
+    use super::*;
+
+    fn step() -> BlockMove {
+        BlockMove {
+            speed: 1.,
+            target: [1, 0, 0],
+            look_at: true,
+        }
+    }
+
+    fn state(world: &mut World, colonist: Entity) -> AnimationState {
+        world.run_system_once(tick_animation_state);
+        *world.get::<AnimationState>(colonist).unwrap()
+    }
+
+    #[test]
+    fn walking_stops_when_the_step_is_done() {
+        let mut world = World::new();
+        let colonist = world
+            .spawn((Colonist::default(), AnimationState::default(), step()))
+            .id();
+
+        assert_eq!(state(&mut world, colonist), AnimationState::Walking);
+
+        world.entity_mut(colonist).remove::<BlockMove>();
+        assert_eq!(state(&mut world, colonist), AnimationState::Idle);
+    }
+
+    #[test]
+    fn mining_overrides_walking() {
+        let mut world = World::new();
+        let colonist = world
+            .spawn((Colonist::default(), AnimationState::default(), step()))
+            .id();
+
+        let task = world.spawn((ActorRef(colonist), TaskMineBlock)).id();
+        assert_eq!(state(&mut world, colonist), AnimationState::Mining);
+
+        world.despawn(task);
+        assert_eq!(state(&mut world, colonist), AnimationState::Walking);
+
+        world.spawn((
+            ActorRef(colonist),
+            TaskChop {
+                target: [0, 0, 0],
+                progress: 0.,
+            },
+        ));
+        assert_eq!(state(&mut world, colonist), AnimationState::Mining);
+    }
+}
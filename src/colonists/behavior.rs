@@ -1,15 +1,93 @@
 use std::sync::Arc;
 
-use bevy::ecs::{
-    component::Component,
-    entity::Entity,
-    system::{Commands, EntityCommands, Query},
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventWriter,
+        query::Without,
+        system::{Commands, EntityCommands, Query, Res, ResMut},
+    },
+    math::Vec3,
+    time::{Time, Timer, TimerMode},
+    transform::components::Transform,
 };
 
+use crate::{Terrain, WorldClock};
+
+use super::{
+    drop_held_item, Blackboard, BlockMove, InInventory, Inventory, Item, Job, JobAssignment,
+    MovedEvent, NavigationGraph, Path,
+};
+
+/// How many times its estimate a task may run before it is reported
+const ESTIMATE_OVERRUN_FACTOR: f32 = 2.;
+
 pub trait TaskBuilder: Send + Sync {
     fn insert(&self, cmd: &mut EntityCommands);
     fn remove(&self, cmd: &mut EntityCommands);
     fn label(&self) -> String;
+    /// Rough seconds the task will take, `None` when it can't be known up
+    /// front, e.g. searches. Tasks opt in with `#[estimated_duration(fn)]`.
+    fn estimated_duration(&self, _blackboard: &Blackboard) -> Option<f32> {
+        None
+    }
+    /// Fail the task if it is still executing after `duration_s` seconds
+    fn with_timeout(self, duration_s: f32) -> TaskWithTimeout
+    where
+        Self: Sized + 'static,
+    {
+        TaskWithTimeout {
+            task: Arc::new(self),
+            duration_s,
+        }
+    }
+}
+
+/// Wraps a task so it fails instead of hanging forever, see `TaskTimeout`
+#[derive(Clone)]
+pub struct TaskWithTimeout {
+    task: Arc<dyn TaskBuilder>,
+    duration_s: f32,
+}
+
+impl TaskBuilder for TaskWithTimeout {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        self.task.insert(cmd);
+        cmd.insert(TaskTimeout {
+            remaining: Timer::from_seconds(self.duration_s, TimerMode::Once),
+            label: self.task.label(),
+        });
+    }
+
+    fn remove(&self, cmd: &mut EntityCommands) {
+        self.task.remove(cmd);
+        cmd.remove::<TaskTimeout>();
+    }
+
+    fn label(&self) -> String {
+        self.task.label()
+    }
+
+    fn estimated_duration(&self, blackboard: &Blackboard) -> Option<f32> {
+        self.task.estimated_duration(blackboard)
+    }
+}
+
+/// Fails the task it sits alongside once the timer runs out
+#[derive(Component)]
+pub struct TaskTimeout {
+    pub remaining: Timer,
+    pub label: String,
+}
+
+/// Put alongside a task that knows roughly how long it takes, so
+/// `tick_task_estimates` can point out the ones running far over
+#[derive(Component)]
+pub struct TaskEstimate {
+    pub label: String,
+    pub estimate_s: f32,
+    pub elapsed_s: f32,
 }
 
 #[derive(Component, Clone, Copy, PartialEq)]
@@ -17,6 +95,9 @@ pub enum TaskState {
     Executing,
     Success,
     Failed,
+    /// The behavior was interrupted, tasks get one tick to clean up before
+    /// it is torn down, see `interrupt_behaviors`
+    Cancelled,
 }
 
 #[derive(Component, Clone)]
@@ -30,6 +111,27 @@ pub struct HasBehavior {
 #[derive(Component, Debug, Clone, Copy)]
 pub struct ActorRef(pub Entity);
 
+/// Put on an actor to drop whatever behavior it is running, for something
+/// more urgent. The replacement starts once the old behavior is cleaned up,
+/// without one the actor picks its next behavior as usual.
+#[derive(Component, Clone)]
+pub struct InterruptBehavior {
+    pub new_behavior: Option<Behavior>,
+    pub reason: String,
+    /// Set once the running behavior was told to clean up
+    is_cancelling: bool,
+}
+
+impl InterruptBehavior {
+    pub fn new(reason: &str, new_behavior: Option<Behavior>) -> Self {
+        Self {
+            new_behavior,
+            reason: String::from(reason),
+            is_cancelling: false,
+        }
+    }
+}
+
 #[derive(Component, Clone)]
 pub struct Behavior {
     pub label: String,
@@ -60,6 +162,12 @@ pub enum BehaviorNode {
     Sequence(Vec<BehaviorNode>),
     /// Visit children sequentially, until one succeeds, or they all fail
     Select(Vec<BehaviorNode>),
+    /// Run the child again each time it succeeds, the given number of times,
+    /// or with `None` until it fails, which then counts as success. Each run
+    /// starts on a new tick, and zero times succeeds without running it.
+    Repeat(Option<u32>, Box<BehaviorNode>),
+    /// Run the child, then succeed whatever it returned
+    Succeed(Box<BehaviorNode>),
 }
 
 #[derive(Clone)]
@@ -75,6 +183,8 @@ pub enum BehaviorNodeState {
     Not(NodeState, Box<BehaviorNodeState>),
     Sequence(NodeState, Vec<BehaviorNodeState>, usize),
     Select(NodeState, Vec<BehaviorNodeState>, usize),
+    Repeat(NodeState, Option<u32>, Box<BehaviorNodeState>, u32),
+    Succeed(NodeState, Box<BehaviorNodeState>),
 }
 
 #[derive(Clone, PartialEq)]
@@ -94,7 +204,10 @@ impl BehaviorNodeState {
                 Box::new(BehaviorNodeState::new(*node)),
                 Box::new(BehaviorNodeState::new(*catch)),
             ),
-            BehaviorNode::Not(node) => BehaviorNodeState::new(*node),
+            BehaviorNode::Not(node) => BehaviorNodeState::Not(
+                NodeState::NotStarted,
+                Box::new(BehaviorNodeState::new(*node)),
+            ),
             BehaviorNode::Sequence(seq) => BehaviorNodeState::Sequence(
                 NodeState::NotStarted,
                 seq.iter()
@@ -115,6 +228,16 @@ impl BehaviorNodeState {
                     .collect(),
                 0,
             ),
+            BehaviorNode::Repeat(limit, node) => BehaviorNodeState::Repeat(
+                NodeState::NotStarted,
+                limit,
+                Box::new(BehaviorNodeState::new(*node)),
+                0,
+            ),
+            BehaviorNode::Succeed(node) => BehaviorNodeState::Succeed(
+                NodeState::NotStarted,
+                Box::new(BehaviorNodeState::new(*node)),
+            ),
         }
     }
 
@@ -148,6 +271,15 @@ impl BehaviorNodeState {
                 seq.iter_mut().for_each(|node| node.reset());
                 *idx = 0;
             }
+            BehaviorNodeState::Repeat(s, _, node, count) => {
+                *s = NodeState::NotStarted;
+                node.reset();
+                *count = 0;
+            }
+            BehaviorNodeState::Succeed(s, node) => {
+                *s = NodeState::NotStarted;
+                node.reset();
+            }
         }
     }
 
@@ -159,14 +291,59 @@ impl BehaviorNodeState {
             BehaviorNodeState::Sequence(s, _, _) => s,
             BehaviorNodeState::Select(s, _, _) => s,
             BehaviorNodeState::IfElse(s, _, _, _) => s,
+            BehaviorNodeState::Repeat(s, _, _, _) => s,
+            BehaviorNodeState::Succeed(s, _) => s,
+        }
+    }
+
+    /// Label of the task currently executing, only one ever is at a time
+    pub fn current_task_label(&self) -> Option<String> {
+        match self {
+            BehaviorNodeState::Task(s, task) => {
+                if *s == NodeState::Executing {
+                    Some(task.label())
+                } else {
+                    None
+                }
+            }
+            BehaviorNodeState::Try(_, node, catch) => node
+                .current_task_label()
+                .or_else(|| catch.current_task_label()),
+            BehaviorNodeState::IfElse(_, condition, if_node, else_node) => condition
+                .current_task_label()
+                .or_else(|| if_node.current_task_label())
+                .or_else(|| else_node.current_task_label()),
+            BehaviorNodeState::Not(_, node) => node.current_task_label(),
+            BehaviorNodeState::Sequence(_, seq, _) => {
+                seq.iter().find_map(|node| node.current_task_label())
+            }
+            BehaviorNodeState::Select(_, seq, _) => {
+                seq.iter().find_map(|node| node.current_task_label())
+            }
+            BehaviorNodeState::Repeat(_, _, node, _) => node.current_task_label(),
+            BehaviorNodeState::Succeed(_, node) => node.current_task_label(),
         }
     }
 
-    fn run(&mut self, cmd: &mut EntityCommands, task_state: TaskState) -> NodeState {
+    fn run(
+        &mut self,
+        cmd: &mut EntityCommands,
+        blackboard: &Blackboard,
+        task_state: TaskState,
+    ) -> NodeState {
         match self {
             BehaviorNodeState::Task(s, task) => match *s {
                 NodeState::NotStarted => {
                     task.insert(cmd);
+
+                    if let Some(estimate_s) = task.estimated_duration(blackboard) {
+                        cmd.insert(TaskEstimate {
+                            label: task.label(),
+                            estimate_s,
+                            elapsed_s: 0.,
+                        });
+                    }
+
                     *s = NodeState::Executing;
                     NodeState::Executing
                 }
@@ -174,11 +351,13 @@ impl BehaviorNodeState {
                     TaskState::Executing => NodeState::Executing,
                     TaskState::Success => {
                         task.remove(cmd);
+                        cmd.remove::<TaskEstimate>();
                         *s = NodeState::Success;
                         NodeState::Success
                     }
-                    TaskState::Failed => {
+                    TaskState::Failed | TaskState::Cancelled => {
                         task.remove(cmd);
+                        cmd.remove::<TaskEstimate>();
                         *s = NodeState::Failed;
                         NodeState::Failed
                     }
@@ -202,8 +381,8 @@ impl BehaviorNodeState {
                     }
                     NodeState::Executing => {
                         *s = NodeState::Executing;
-                        if NodeState::Executing != catch.run(cmd, task_state) {
-                            self.run(cmd, task_state)
+                        if NodeState::Executing != catch.run(cmd, blackboard, task_state) {
+                            self.run(cmd, blackboard, task_state)
                         } else {
                             *s = NodeState::Executing;
                             NodeState::Executing
@@ -211,8 +390,8 @@ impl BehaviorNodeState {
                     }
                     NodeState::NotStarted => {
                         *s = NodeState::Executing;
-                        if NodeState::Executing != catch.run(cmd, task_state) {
-                            self.run(cmd, task_state)
+                        if NodeState::Executing != catch.run(cmd, blackboard, task_state) {
+                            self.run(cmd, blackboard, task_state)
                         } else {
                             *s = NodeState::Executing;
                             NodeState::Executing
@@ -221,8 +400,8 @@ impl BehaviorNodeState {
                 },
                 NodeState::Executing => {
                     *s = NodeState::Executing;
-                    if NodeState::Executing != node.run(cmd, task_state) {
-                        self.run(cmd, task_state)
+                    if NodeState::Executing != node.run(cmd, blackboard, task_state) {
+                        self.run(cmd, blackboard, task_state)
                     } else {
                         *s = NodeState::Executing;
                         NodeState::Executing
@@ -230,8 +409,8 @@ impl BehaviorNodeState {
                 }
                 NodeState::NotStarted => {
                     *s = NodeState::Executing;
-                    if NodeState::Executing != node.run(cmd, task_state) {
-                        self.run(cmd, task_state)
+                    if NodeState::Executing != node.run(cmd, blackboard, task_state) {
+                        self.run(cmd, blackboard, task_state)
                     } else {
                         *s = NodeState::Executing;
                         NodeState::Executing
@@ -241,24 +420,24 @@ impl BehaviorNodeState {
             BehaviorNodeState::IfElse(s, condition, if_node, else_node) => {
                 match condition.state().clone() {
                     NodeState::Success => {
-                        *s = if_node.run(cmd, task_state);
+                        *s = if_node.run(cmd, blackboard, task_state);
                         s.clone()
                     }
                     NodeState::Failed => {
-                        *s = else_node.run(cmd, task_state);
+                        *s = else_node.run(cmd, blackboard, task_state);
                         s.clone()
                     }
                     NodeState::Executing => {
-                        if NodeState::Executing != condition.run(cmd, task_state) {
-                            self.run(cmd, task_state)
+                        if NodeState::Executing != condition.run(cmd, blackboard, task_state) {
+                            self.run(cmd, blackboard, task_state)
                         } else {
                             *s = NodeState::Executing;
                             NodeState::Executing
                         }
                     }
                     NodeState::NotStarted => {
-                        if NodeState::Executing != condition.run(cmd, task_state) {
-                            self.run(cmd, task_state)
+                        if NodeState::Executing != condition.run(cmd, blackboard, task_state) {
+                            self.run(cmd, blackboard, task_state)
                         } else {
                             *s = NodeState::Executing;
                             NodeState::Executing
@@ -276,16 +455,16 @@ impl BehaviorNodeState {
                     NodeState::Success
                 }
                 NodeState::Executing => {
-                    if NodeState::Executing != node.run(cmd, task_state) {
-                        self.run(cmd, task_state)
+                    if NodeState::Executing != node.run(cmd, blackboard, task_state) {
+                        self.run(cmd, blackboard, task_state)
                     } else {
                         *s = NodeState::Executing;
                         NodeState::Executing
                     }
                 }
                 NodeState::NotStarted => {
-                    if NodeState::Executing != node.run(cmd, task_state) {
-                        self.run(cmd, task_state)
+                    if NodeState::Executing != node.run(cmd, blackboard, task_state) {
+                        self.run(cmd, blackboard, task_state)
                     } else {
                         *s = NodeState::Executing;
                         NodeState::Executing
@@ -301,7 +480,7 @@ impl BehaviorNodeState {
                         return NodeState::Failed;
                     };
 
-                    match current.run(cmd, task_state).clone() {
+                    match current.run(cmd, blackboard, task_state).clone() {
                         NodeState::NotStarted => {
                             println!("Run was called on a child node for sequence, but it did not start! {}", *idx);
                             *s = NodeState::Failed;
@@ -314,7 +493,7 @@ impl BehaviorNodeState {
                                 *s = NodeState::Success;
                                 NodeState::Success
                             } else {
-                                self.run(cmd, task_state)
+                                self.run(cmd, blackboard, task_state)
                             }
                         }
                         NodeState::Failed => {
@@ -326,7 +505,7 @@ impl BehaviorNodeState {
                 NodeState::NotStarted => {
                     *idx = 0;
                     *s = NodeState::Executing;
-                    self.run(cmd, task_state)
+                    self.run(cmd, blackboard, task_state)
                 }
             },
             BehaviorNodeState::Select(s, seq, idx) => match s {
@@ -338,7 +517,7 @@ impl BehaviorNodeState {
                         return NodeState::Failed;
                     };
 
-                    match current.run(cmd, task_state).clone() {
+                    match current.run(cmd, blackboard, task_state).clone() {
                         NodeState::NotStarted => {
                             println!("Run was called on a child node for select, but it did not start! {}", *idx);
                             *s = NodeState::Failed;
@@ -356,7 +535,7 @@ impl BehaviorNodeState {
                                 *s = NodeState::Failed;
                                 NodeState::Failed
                             } else {
-                                self.run(cmd, task_state)
+                                self.run(cmd, blackboard, task_state)
                             }
                         }
                     }
@@ -364,36 +543,270 @@ impl BehaviorNodeState {
                 NodeState::NotStarted => {
                     *idx = 0;
                     *s = NodeState::Executing;
-                    self.run(cmd, task_state)
+                    self.run(cmd, blackboard, task_state)
                 }
             },
+            BehaviorNodeState::Repeat(s, limit, node, count) => match s {
+                NodeState::Success => NodeState::Success,
+                NodeState::Failed => NodeState::Failed,
+                NodeState::Executing => match node.run(cmd, blackboard, task_state) {
+                    NodeState::NotStarted => {
+                        println!(
+                            "Run was called on a child node for repeat, but it did not start!"
+                        );
+                        *s = NodeState::Failed;
+                        NodeState::Failed
+                    }
+                    NodeState::Executing => NodeState::Executing,
+                    NodeState::Success => {
+                        *count += 1;
+                        if matches!(limit, Some(n) if *count >= *n) {
+                            *s = NodeState::Success;
+                            NodeState::Success
+                        } else {
+                            // the next iteration starts next tick, a child
+                            // that succeeds without running a task would
+                            // otherwise spin here forever
+                            node.reset();
+                            NodeState::Executing
+                        }
+                    }
+                    NodeState::Failed => {
+                        *s = if limit.is_none() {
+                            NodeState::Success
+                        } else {
+                            NodeState::Failed
+                        };
+                        s.clone()
+                    }
+                },
+                NodeState::NotStarted => {
+                    *count = 0;
+
+                    if *limit == Some(0) {
+                        *s = NodeState::Success;
+                        return NodeState::Success;
+                    }
+
+                    *s = NodeState::Executing;
+                    self.run(cmd, blackboard, task_state)
+                }
+            },
+            BehaviorNodeState::Succeed(s, node) => match node.run(cmd, blackboard, task_state) {
+                NodeState::Executing => {
+                    *s = NodeState::Executing;
+                    NodeState::Executing
+                }
+                _ => {
+                    *s = NodeState::Success;
+                    NodeState::Success
+                }
+            },
+        }
+    }
+}
+
+pub fn tick_task_timeouts(
+    mut cmd: Commands,
+    time: Res<Time>,
+    clock: Res<WorldClock>,
+    mut q_timeouts: Query<(&ActorRef, &mut TaskTimeout, &mut TaskState)>,
+) {
+    if clock.is_paused {
+        return;
+    }
+
+    for (ActorRef(actor), mut timeout, mut state) in q_timeouts.iter_mut() {
+        if *state != TaskState::Executing {
+            continue;
+        }
+
+        if !timeout.remaining.tick(time.delta()).finished() {
+            continue;
+        }
+
+        println!("Task {} timed out!", timeout.label);
+        // a stale path would be picked up by the next move
+        cmd.entity(*actor).remove::<Path>();
+        *state = TaskState::Failed;
+    }
+}
+
+pub fn tick_task_estimates(
+    mut cmd: Commands,
+    time: Res<Time>,
+    clock: Res<WorldClock>,
+    mut q_estimates: Query<(Entity, &mut TaskEstimate, &TaskState)>,
+) {
+    if clock.is_paused {
+        return;
+    }
+
+    for (entity, mut estimate, state) in q_estimates.iter_mut() {
+        if *state != TaskState::Executing {
+            continue;
+        }
+
+        estimate.elapsed_s += time.delta_seconds();
+
+        if estimate.elapsed_s <= estimate.estimate_s * ESTIMATE_OVERRUN_FACTOR {
+            continue;
+        }
+
+        println!(
+            "Task {} is taking {:.1}s, estimated {:.1}s",
+            estimate.label, estimate.elapsed_s, estimate.estimate_s
+        );
+        // once is enough, the task keeps running
+        cmd.entity(entity).remove::<TaskEstimate>();
+    }
+}
+
+/// Interrupts run in two steps. First the running behavior is marked
+/// `TaskState::Cancelled`, which lets its current task clean up after itself
+/// during the next update. The tick after, whatever the behavior still holds
+/// is let go of: its job goes back to the queue, the blackboard item is
+/// unreserved or put down, and the path is dropped. A step under way is cut
+/// short at the block it was headed for. Then the behavior is despawned and
+/// the replacement, if any, is started.
+#[allow(clippy::type_complexity)]
+pub fn interrupt_behaviors(
+    mut cmd: Commands,
+    terrain: Res<Terrain>,
+    mut graph: ResMut<NavigationGraph>,
+    mut q_interrupts: Query<(
+        Entity,
+        &mut InterruptBehavior,
+        Option<&HasBehavior>,
+        &mut Transform,
+        Option<&BlockMove>,
+        Option<&mut Inventory>,
+    )>,
+    mut q_behaviors: Query<(&Blackboard, &mut TaskState)>,
+    mut q_jobs: Query<&mut Job>,
+    mut q_items: Query<
+        (&mut Transform, &mut Item, Option<&InInventory>),
+        Without<InterruptBehavior>,
+    >,
+    mut ev_moved: EventWriter<MovedEvent>,
+) {
+    for (actor, mut interrupt, has_behavior, mut transform, block_move, inventory) in
+        q_interrupts.iter_mut()
+    {
+        if let Some(has_behavior) = has_behavior {
+            let Ok((blackboard, mut state)) = q_behaviors.get_mut(has_behavior.behavior_entity)
+            else {
+                cmd.entity(actor).remove::<HasBehavior>();
+                continue;
+            };
+
+            // tasks that don't know about cancelling may have written over
+            // it, keep `behavior_system` off the tree until it is gone
+            *state = TaskState::Cancelled;
+
+            if !interrupt.is_cancelling {
+                println!("Interrupting behavior, {}", interrupt.reason);
+                interrupt.is_cancelling = true;
+                continue;
+            }
+
+            if let Some(mut job) = blackboard.job.and_then(|job| q_jobs.get_mut(job).ok()) {
+                if job.assignee == Some(actor) {
+                    job.assignee = None;
+                    cmd.entity(actor).remove::<JobAssignment>();
+                }
+            }
+
+            if let Some((mut item_transform, mut item_data, held)) =
+                blackboard.item.and_then(|item| q_items.get_mut(item).ok())
+            {
+                if item_data.reserved == Some(actor) {
+                    item_data.reserved = None;
+                }
+
+                if let (Some(mut inventory), true) =
+                    (inventory, held.is_some_and(|held| held.holder == actor))
+                {
+                    drop_held_item(
+                        &mut cmd,
+                        &terrain,
+                        &mut graph,
+                        blackboard.item.unwrap(),
+                        &item_data,
+                        &mut item_transform,
+                        &transform,
+                        &mut inventory,
+                    );
+                }
+            }
+
+            cmd.entity(has_behavior.behavior_entity).despawn();
+        }
+
+        if let Some(block_move) = block_move {
+            let [x, y, z] = block_move.target;
+            transform.translation = Vec3::new(x as f32 + 0.5, y as f32, z as f32 + 0.5);
+            ev_moved.send(MovedEvent {
+                entity: actor,
+                position: [x as u32, y as u32, z as u32],
+            });
+        }
+
+        cmd.entity(actor)
+            .remove::<(InterruptBehavior, HasBehavior, Path, BlockMove)>();
+
+        if let Some(behavior) = interrupt.new_behavior.clone() {
+            let behavior_entity = cmd
+                .spawn((
+                    Blackboard::default(),
+                    TaskState::Success,
+                    ActorRef(actor),
+                    behavior,
+                ))
+                .id();
+
+            cmd.entity(actor).insert(HasBehavior { behavior_entity });
         }
     }
 }
 
 pub fn behavior_system(
     mut cmd: Commands,
-    mut q_behaviors: Query<(Entity, &ActorRef, &mut Behavior, &mut TaskState)>,
+    mut q_behaviors: Query<(
+        Entity,
+        &ActorRef,
+        &Blackboard,
+        &mut Behavior,
+        &mut TaskState,
+    )>,
     q_has_behavior: Query<&HasBehavior>,
 ) {
-    for (entity, ActorRef(actor), mut behavior, mut state) in q_behaviors.iter_mut() {
+    for (entity, ActorRef(actor), blackboard, mut behavior, mut state) in q_behaviors.iter_mut() {
         let Ok(has_behavior) = q_has_behavior.get(*actor) else {
             println!("Detached behavior detected? Despawning it.");
             cmd.entity(entity).despawn();
             continue;
         };
 
-        if *state == TaskState::Executing {
+        // cancelled behaviors are torn down by `interrupt_behaviors`
+        if *state == TaskState::Executing || *state == TaskState::Cancelled {
             continue;
         }
 
-        let node_state = behavior
-            .tree
-            .run(&mut cmd.entity(has_behavior.behavior_entity), *state);
+        let node_state = behavior.tree.run(
+            &mut cmd.entity(has_behavior.behavior_entity),
+            blackboard,
+            *state,
+        );
 
         *state = match node_state {
             NodeState::Success => TaskState::Success,
             NodeState::Failed => TaskState::Failed,
+            // between two iterations of a `Repeat`, nothing would ever end
+            // the tick, so run the tree again next tick
+            NodeState::Executing if behavior.tree.current_task_label().is_none() => {
+                TaskState::Success
+            }
             NodeState::Executing => TaskState::Executing,
             NodeState::NotStarted => TaskState::Success,
         };
@@ -417,3 +830,342 @@ pub fn behavior_system(
         // }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{
+        event::Events,
+        query::With,
+        schedule::{IntoSystemConfigs, Schedule},
+        system::Resource,
+        world::World,
+    };
+    use task_derive::TaskBuilder;
+
+    use super::*;
+    use crate::colonists::{sync_job_queue, JobQueue, JobType, TaskIdle};
+
+    /// Times `TaskPass` ran
+    #[derive(Resource, Default)]
+    struct Ran(u32);
+
+    #[derive(Component, Clone, TaskBuilder)]
+    struct TaskPass;
+
+    fn task_pass(mut ran: ResMut<Ran>, mut q_behavior: Query<&mut TaskState, With<TaskPass>>) {
+        for mut state in q_behavior.iter_mut() {
+            ran.0 += 1;
+            *state = TaskState::Success;
+        }
+    }
+
+    /// Succeeds the moment it is run, without a task
+    fn instant_success() -> BehaviorNode {
+        BehaviorNode::Not(Box::new(BehaviorNode::Sequence(vec![])))
+    }
+
+    fn spawn_behavior(world: &mut World, tree: BehaviorNode) -> (Entity, Entity) {
+        world.init_resource::<Ran>();
+
+        let actor = world.spawn_empty().id();
+        let behavior_entity = world
+            .spawn((
+                Blackboard::default(),
+                TaskState::Success,
+                ActorRef(actor),
+                Behavior::new("test", tree),
+            ))
+            .id();
+
+        world
+            .entity_mut(actor)
+            .insert(HasBehavior { behavior_entity });
+
+        (actor, behavior_entity)
+    }
+
+    fn schedule() -> Schedule {
+        let mut schedule = Schedule::default();
+        schedule.add_systems((behavior_system, task_pass).chain());
+        schedule
+    }
+
+    fn repeat_count(world: &World, behavior_entity: Entity) -> u32 {
+        match world.get::<Behavior>(behavior_entity).unwrap().tree {
+            BehaviorNodeState::Repeat(_, _, _, count) => count,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn repeat_runs_one_iteration_per_tick() {
+        let mut world = World::new();
+        let tree = BehaviorNode::Repeat(None, Box::new(instant_success()));
+        let (_, behavior_entity) = spawn_behavior(&mut world, tree);
+        let mut schedule = schedule();
+
+        for tick in 1..=5 {
+            schedule.run(&mut world);
+            assert_eq!(repeat_count(&world, behavior_entity), tick);
+        }
+    }
+
+    #[test]
+    fn repeat_runs_tasks_the_given_number_of_times() {
+        let mut world = World::new();
+        let tree = BehaviorNode::Repeat(Some(3), Box::new(BehaviorNode::Task(Arc::new(TaskPass))));
+        let (actor, behavior_entity) = spawn_behavior(&mut world, tree);
+        let mut schedule = schedule();
+
+        for _ in 0..20 {
+            schedule.run(&mut world);
+        }
+
+        assert_eq!(world.resource::<Ran>().0, 3);
+        assert!(world.get_entity(behavior_entity).is_none());
+        assert!(!world.entity(actor).contains::<HasBehavior>());
+    }
+
+    #[test]
+    fn repeat_zero_times_succeeds_at_once() {
+        let mut world = World::new();
+        let tree = BehaviorNode::Sequence(vec![
+            BehaviorNode::Repeat(Some(0), Box::new(BehaviorNode::Task(Arc::new(TaskPass)))),
+            instant_success(),
+        ]);
+        let (actor, behavior_entity) = spawn_behavior(&mut world, tree);
+
+        schedule().run(&mut world);
+
+        assert_eq!(world.resource::<Ran>().0, 0);
+        assert!(world.get_entity(behavior_entity).is_none());
+        assert!(!world.entity(actor).contains::<HasBehavior>());
+    }
+
+    /// Writes a target to the blackboard
+    #[derive(Component, Clone, TaskBuilder)]
+    struct TaskMark;
+
+    #[derive(Component, Clone, TaskBuilder)]
+    struct TaskFail;
+
+    /// The target the blackboard held when `TaskSeen` ran
+    #[derive(Resource, Default)]
+    struct Seen(Option<Option<[u32; 3]>>);
+
+    #[derive(Component, Clone, TaskBuilder)]
+    struct TaskSeen;
+
+    fn task_mark(mut q_behavior: Query<(&mut TaskState, &mut Blackboard), With<TaskMark>>) {
+        for (mut state, mut blackboard) in q_behavior.iter_mut() {
+            blackboard.target_block = Some([1, 2, 3]);
+            *state = TaskState::Success;
+        }
+    }
+
+    fn task_fail(mut q_behavior: Query<&mut TaskState, With<TaskFail>>) {
+        for mut state in q_behavior.iter_mut() {
+            *state = TaskState::Failed;
+        }
+    }
+
+    fn task_seen(
+        mut seen: ResMut<Seen>,
+        mut q_behavior: Query<(&mut TaskState, &Blackboard), With<TaskSeen>>,
+    ) {
+        for (mut state, blackboard) in q_behavior.iter_mut() {
+            seen.0 = Some(blackboard.target_block);
+            *state = TaskState::Success;
+        }
+    }
+
+    #[test]
+    fn select_fallback_sees_the_failed_branch_writes() {
+        let mut world = World::new();
+        world.init_resource::<Seen>();
+        let tree = BehaviorNode::Select(vec![
+            BehaviorNode::Sequence(vec![
+                BehaviorNode::Task(Arc::new(TaskMark)),
+                BehaviorNode::Task(Arc::new(TaskFail)),
+            ]),
+            BehaviorNode::Task(Arc::new(TaskSeen)),
+        ]);
+        let (actor, behavior_entity) = spawn_behavior(&mut world, tree);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((behavior_system, task_mark, task_fail, task_seen).chain());
+
+        for _ in 0..10 {
+            schedule.run(&mut world);
+        }
+
+        // the blackboard is shared by the whole tree, a failed branch does
+        // not roll back what it wrote
+        assert_eq!(world.resource::<Seen>().0, Some(Some([1, 2, 3])));
+        assert!(world.get_entity(behavior_entity).is_none());
+        assert!(!world.entity(actor).contains::<HasBehavior>());
+    }
+
+    /// Never finishes, though it claims to take a second
+    #[derive(Component, Clone, TaskBuilder)]
+    #[estimated_duration(one_second)]
+    struct TaskStuck;
+
+    impl TaskStuck {
+        fn one_second(&self, _blackboard: &Blackboard) -> Option<f32> {
+            Some(1.)
+        }
+    }
+
+    #[test]
+    fn overrunning_tasks_are_reported_once() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<WorldClock>();
+        let tree = BehaviorNode::Task(Arc::new(TaskStuck));
+        let (_, behavior_entity) = spawn_behavior(&mut world, tree);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((behavior_system, tick_task_estimates).chain());
+
+        let mut tick = |world: &mut World| {
+            world
+                .resource_mut::<Time>()
+                .advance_by(std::time::Duration::from_millis(500));
+            schedule.run(world);
+        };
+
+        tick(&mut world);
+        let estimate = world.get::<TaskEstimate>(behavior_entity).unwrap();
+        assert_eq!(estimate.label, "TaskStuck");
+        assert_eq!(estimate.estimate_s, 1.);
+
+        // twice the estimate is still fine
+        for _ in 0..3 {
+            tick(&mut world);
+        }
+        assert!(world.entity(behavior_entity).contains::<TaskEstimate>());
+
+        tick(&mut world);
+        assert!(!world.entity(behavior_entity).contains::<TaskEstimate>());
+        assert!(world.entity(behavior_entity).contains::<TaskStuck>());
+    }
+
+    #[test]
+    fn estimates_end_with_their_task() {
+        let mut world = World::new();
+        let tree = BehaviorNode::Sequence(vec![
+            BehaviorNode::Task(Arc::new(TaskIdle {
+                progress: 0.,
+                duration_s: 3.,
+            })),
+            BehaviorNode::Task(Arc::new(TaskStuck)),
+        ]);
+        let (_, behavior_entity) = spawn_behavior(&mut world, tree);
+
+        schedule().run(&mut world);
+        assert_eq!(
+            world
+                .get::<TaskEstimate>(behavior_entity)
+                .unwrap()
+                .estimate_s,
+            3.
+        );
+
+        world.entity_mut(behavior_entity).insert(TaskState::Success);
+        schedule().run(&mut world);
+        assert_eq!(
+            world
+                .get::<TaskEstimate>(behavior_entity)
+                .unwrap()
+                .estimate_s,
+            1.
+        );
+
+        world.entity_mut(behavior_entity).insert(TaskState::Failed);
+        schedule().run(&mut world);
+        assert!(world.get_entity(behavior_entity).is_none());
+    }
+
+    #[test]
+    fn interrupting_a_haul_hands_back_the_job_and_item() {
+        let mut world = World::new();
+        world.insert_resource(Terrain::new(1, 1, 1, 8).unwrap());
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<JobQueue>();
+        world.init_resource::<Events<MovedEvent>>();
+
+        let actor = world
+            .spawn((
+                Transform::from_xyz(1.5, 1., 1.2),
+                Inventory::default(),
+                BlockMove {
+                    speed: 1.,
+                    target: [1, 1, 2],
+                    look_at: false,
+                },
+            ))
+            .id();
+        let item = world
+            .spawn((
+                Item {
+                    tags: vec![],
+                    reserved: Some(actor),
+                },
+                Transform::from_xyz(5.5, 1., 5.5),
+            ))
+            .id();
+        let job = world
+            .spawn(Job {
+                job_type: JobType::Haul,
+                assignee: Some(actor),
+                deadline: None,
+                waiting_for_material: false,
+                faction_id: None,
+            })
+            .id();
+
+        // walking over to pick the item up
+        let behavior_entity = world
+            .spawn((
+                Blackboard {
+                    job: Some(job),
+                    item: Some(item),
+                    ..Default::default()
+                },
+                TaskState::Executing,
+                ActorRef(actor),
+                Behavior::new("Haul", BehaviorNode::Task(Arc::new(TaskPass))),
+            ))
+            .id();
+        world.entity_mut(actor).insert((
+            HasBehavior { behavior_entity },
+            JobAssignment,
+            InterruptBehavior::new("test", None),
+        ));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((interrupt_behaviors, sync_job_queue).chain());
+
+        schedule.run(&mut world);
+        assert!(*world.get::<TaskState>(behavior_entity).unwrap() == TaskState::Cancelled);
+
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Item>(item).unwrap().reserved, None);
+        assert_eq!(world.get::<Job>(job).unwrap().assignee, None);
+        assert_eq!(
+            world.resource::<JobQueue>().candidates(JobType::Haul, 0.),
+            vec![job]
+        );
+        assert!(world.get_entity(behavior_entity).is_none());
+
+        let actor = world.entity(actor);
+        assert!(!actor.contains::<HasBehavior>());
+        assert!(!actor.contains::<JobAssignment>());
+        assert!(!actor.contains::<BlockMove>());
+        assert_eq!(
+            actor.get::<Transform>().unwrap().translation,
+            Vec3::new(1.5, 1., 2.5)
+        );
+    }
+}
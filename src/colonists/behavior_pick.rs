@@ -13,6 +13,9 @@ use super::{
     TaskCheckHasItem, TaskFindNearestItem, TaskIdle, TaskMoveTo, TaskPickUpItem, TaskState,
 };
 
+/// Scratch space shared by every task of a behavior. A behavior starts with
+/// an empty one, and nothing is rolled back when a branch fails, so the
+/// next branch sees whatever the failed one wrote.
 #[derive(Component, Default)]
 pub struct Blackboard {
     pub job: Option<Entity>,
@@ -5,27 +5,29 @@ use bevy::{
         component::Component,
         entity::Entity,
         query::{With, Without},
-        system::{EntityCommands, Query, Res},
+        system::{EntityCommands, Query, Res, ResMut},
     },
+    time::Time,
     transform::components::Transform,
 };
 
 use crate::{
     colonists::{
         is_reachable, job_access_points, test_item_tags, tree_aquire_item, Actor, ActorRef,
-        Behavior, BehaviorNode, HasBehavior, InInventory, Inventory, IsJobAccessible,
-        IsJobCancelled, IsJobCompleted, Item, ItemTag, Job, JobBuild, JobLocation, NavigationFlags,
-        NavigationGraph, PartitionPathRequest, Score, ScorerBuilder, TaskAssignJob, TaskBuildBlock,
-        TaskGetJobLocation, TaskIsTargetEmpty, TaskJobCancel, TaskJobComplete, TaskJobUnassign,
-        TaskMoveTo,
+        Behavior, BehaviorNode, BlueprintWall, FactionId, HasBehavior, InInventory, Inventory,
+        IsJobAccessible, IsJobCancelled, IsJobCompleted, Item, Job, JobBuild, JobLocation,
+        JobQueue, JobType, NavigationFlags, NavigationGraph, PartitionPathRequest, Score,
+        ScorerBuilder, TaskAssignJob, TaskBuildBlock, TaskGetJobLocation, TaskIsTargetEmpty,
+        TaskJobCancel, TaskJobComplete, TaskJobUnassign, TaskMoveTo, TaskReleaseItem,
     },
     common::Distance,
-    BlockType, Terrain,
+    Terrain,
 };
 
 #[derive(Component, Clone, Default)]
 pub struct ScorerBuild {
     job: Option<Entity>,
+    blueprint: Option<BlueprintWall>,
 }
 
 impl ScorerBuilder for ScorerBuild {
@@ -38,6 +40,9 @@ impl ScorerBuilder for ScorerBuild {
     }
 
     fn build(&self) -> Behavior {
+        let blueprint = self.blueprint.unwrap();
+        let material = blueprint.material().unwrap();
+
         Behavior::new(
             "Build",
             BehaviorNode::Try(
@@ -46,13 +51,13 @@ impl ScorerBuilder for ScorerBuild {
                     BehaviorNode::IfElse(
                         Box::new(BehaviorNode::Task(Arc::new(TaskIsTargetEmpty))),
                         Box::new(BehaviorNode::Sequence(vec![
-                            tree_aquire_item(vec![ItemTag::Stone]),
+                            tree_aquire_item(vec![material]),
                             BehaviorNode::Sequence(vec![
                                 BehaviorNode::Task(Arc::new(TaskGetJobLocation)),
                                 BehaviorNode::Task(Arc::new(TaskMoveTo)),
                                 BehaviorNode::Task(Arc::new(TaskBuildBlock {
                                     progress: 0.,
-                                    block: BlockType::STONE,
+                                    block: blueprint.block_type,
                                 })),
                                 BehaviorNode::Task(Arc::new(TaskJobComplete)),
                             ]),
@@ -60,17 +65,23 @@ impl ScorerBuilder for ScorerBuild {
                         Box::new(BehaviorNode::Task(Arc::new(TaskJobCancel))),
                     ),
                 ])),
-                Box::new(BehaviorNode::Task(Arc::new(TaskJobUnassign))),
+                // drop the material before handing the job back
+                Box::new(BehaviorNode::Sequence(vec![
+                    BehaviorNode::Task(Arc::new(TaskReleaseItem)),
+                    BehaviorNode::Task(Arc::new(TaskJobUnassign)),
+                ])),
             ),
         )
     }
 }
 
 pub fn score_build(
+    time: Res<Time>,
     terrain: Res<Terrain>,
     graph: Res<NavigationGraph>,
+    mut queue: ResMut<JobQueue>,
     q_jobs: Query<
-        (Entity, &Job, &JobLocation),
+        (&Job, &JobLocation, &BlueprintWall),
         (
             With<JobBuild>,
             With<IsJobAccessible>,
@@ -81,13 +92,13 @@ pub fn score_build(
     q_items: Query<&Item>,
     q_free_items: Query<(&Item, &Transform), Without<InInventory>>,
     q_actors: Query<
-        (&Inventory, &Transform, &NavigationFlags),
+        (&Inventory, &Transform, &NavigationFlags, Option<&FactionId>),
         (With<Actor>, Without<HasBehavior>),
     >,
     mut q_behaviors: Query<(&ActorRef, &mut Score, &mut ScorerBuild)>,
 ) {
     for (ActorRef(actor), mut score, mut scorer) in q_behaviors.iter_mut() {
-        let Ok((inventory, transform, flags)) = q_actors.get(*actor) else {
+        let Ok((inventory, transform, flags, faction)) = q_actors.get(*actor) else {
             *score = Score(0.);
             continue;
         };
@@ -100,9 +111,16 @@ pub fn score_build(
 
         let mut best = None;
         let mut best_dist = 100000.;
+        let now = time.elapsed_seconds_f64();
 
-        for (e, job, job_location) in q_jobs.iter() {
-            if job.assignee.is_some() {
+        for e in queue.candidates(JobType::BuildWall, now) {
+            let Ok((job, job_location, blueprint)) = q_jobs.get(e) else {
+                // not accessible right now, let the rest through
+                queue.defer(JobType::BuildWall, e, now);
+                continue;
+            };
+
+            if job.assignee.is_some() || !job.is_open_to(faction) {
                 continue;
             }
 
@@ -114,6 +132,7 @@ pub fn score_build(
             };
 
             if !is_reachable(&request, &terrain, &graph) {
+                queue.defer(JobType::BuildWall, e, now);
                 continue;
             }
 
@@ -127,7 +146,7 @@ pub fn score_build(
             );
 
             if job_distance < best_dist {
-                best = Some(e);
+                best = Some((e, *blueprint));
                 best_dist = job_distance;
                 if job_distance < 2. {
                     break;
@@ -135,16 +154,22 @@ pub fn score_build(
             }
         }
 
-        if best.is_none() {
+        let Some((job, blueprint)) = best else {
             *score = Score(0.);
             continue;
         };
 
-        scorer.job = best;
+        scorer.job = Some(job);
+        scorer.blueprint = Some(blueprint);
+
+        let Some(material) = blueprint.material() else {
+            *score = Score(0.);
+            continue;
+        };
 
-        let item_tags = &[ItemTag::Stone];
+        let item_tags = &[material];
 
-        let has_stone = inventory.items.iter().any(|e| {
+        let has_material = inventory.items.iter().any(|e| {
             let Ok(item) = q_items.get(*e) else {
                 return false;
             };
@@ -152,16 +177,21 @@ pub fn score_build(
             test_item_tags(&item.tags, item_tags)
         });
 
-        // if we have stone, score is higher
-        if has_stone {
+        // if we have the material, score is higher
+        if has_material {
             *score = Score(0.6);
             continue;
         }
 
         // check if any of the items are unreserved and accessible
-        if q_free_items.iter().any(|(i, t)| {
-            test_item_tags(&i.tags, item_tags)
-                && i.reserved.is_none()
+        let free_material = graph.items_of_type(item_tags, usize::MAX);
+
+        if free_material.iter().any(|(_, e)| {
+            let Ok((i, t)) = q_free_items.get(*e) else {
+                return false;
+            };
+
+            i.reserved.is_none()
                 && is_reachable(
                     &PartitionPathRequest {
                         start: pos,
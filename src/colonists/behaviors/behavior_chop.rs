@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{With, Without},
+        system::{EntityCommands, Query, Res},
+    },
+    transform::components::Transform,
+};
+
+use crate::{
+    colonists::{
+        is_reachable, job_access_points, Actor, ActorRef, Behavior, BehaviorNode, FactionId,
+        HasBehavior, IsJobAccessible, IsJobCancelled, IsJobCompleted, Job, JobLocation, JobMine,
+        NavigationFlags, NavigationGraph, PartitionPathRequest, Score, ScorerBuilder,
+        TaskAssignJob, TaskChop, TaskGetJobLocation, TaskJobComplete, TaskJobUnassign, TaskMoveTo,
+    },
+    common::Distance,
+    BlockType, Terrain,
+};
+
+/// Takes the mine jobs placed on a tree trunk and fells the whole tree with
+/// `TaskChop`, instead of digging out the one log
+#[derive(Component, Clone, Default)]
+pub struct ScorerChop {
+    job: Option<(Entity, [u32; 3])>,
+}
+
+impl ScorerBuilder for ScorerChop {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        cmd.insert(self.clone());
+    }
+
+    fn label(&self) -> String {
+        "Chop".to_string()
+    }
+
+    fn build(&self) -> Behavior {
+        let (job, target) = self.job.unwrap();
+
+        Behavior::new(
+            "Chop",
+            BehaviorNode::Try(
+                Box::new(BehaviorNode::Sequence(vec![
+                    BehaviorNode::Task(Arc::new(TaskAssignJob(job))),
+                    BehaviorNode::Task(Arc::new(TaskGetJobLocation)),
+                    BehaviorNode::Task(Arc::new(TaskMoveTo)),
+                    BehaviorNode::Task(Arc::new(TaskChop {
+                        target,
+                        progress: 0.,
+                    })),
+                    BehaviorNode::Task(Arc::new(TaskJobComplete)),
+                ])),
+                Box::new(BehaviorNode::Task(Arc::new(TaskJobUnassign))),
+            ),
+        )
+    }
+}
+
+pub fn score_chop(
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    q_jobs: Query<
+        (Entity, &Job, &JobLocation),
+        (
+            With<JobMine>,
+            With<IsJobAccessible>,
+            Without<IsJobCancelled>,
+            Without<IsJobCompleted>,
+        ),
+    >,
+    q_actors: Query<
+        (&Transform, &NavigationFlags, Option<&FactionId>),
+        (With<Actor>, Without<HasBehavior>),
+    >,
+    mut q_behaviors: Query<(&ActorRef, &mut Score, &mut ScorerChop)>,
+) {
+    for (ActorRef(actor), mut score, mut scorer) in q_behaviors.iter_mut() {
+        let Ok((transform, flags, faction)) = q_actors.get(*actor) else {
+            *score = Score(0.);
+            continue;
+        };
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        let best = q_jobs
+            .iter()
+            .filter(|(_, job, location)| {
+                let [x, y, z] = location.pos;
+
+                job.assignee.is_none()
+                    && job.is_open_to(faction)
+                    && terrain.get_block(x, y, z).block == BlockType::LOG
+            })
+            .filter(|(_, job, location)| {
+                is_reachable(
+                    &PartitionPathRequest {
+                        start: pos,
+                        goals: job_access_points(location.pos, job.job_type),
+                        flags: *flags,
+                    },
+                    &terrain,
+                    &graph,
+                )
+            })
+            .min_by_key(|(_, _, location)| {
+                Distance::manhattan(
+                    [
+                        location.pos[0] as i32,
+                        location.pos[1] as i32,
+                        location.pos[2] as i32,
+                    ],
+                    [pos[0] as i32, pos[1] as i32, pos[2] as i32],
+                ) as u32
+            });
+
+        let Some((job, _, location)) = best else {
+            *score = Score(0.);
+            continue;
+        };
+
+        scorer.job = Some((job, location.pos));
+        *score = Score(0.5);
+    }
+}
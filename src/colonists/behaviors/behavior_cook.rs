@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use bevy::{
+    ecs::{
+        component::Component,
+        query::{With, Without},
+        system::{EntityCommands, Query, Res},
+    },
+    transform::components::Transform,
+};
+
+use crate::{
+    colonists::{
+        is_reachable, test_item_tags, tree_aquire_item, Actor, ActorRef, Behavior, BehaviorNode,
+        HasBehavior, InInventory, Inventory, Item, ItemTag, NavigationFlags, NavigationGraph,
+        PartitionPathRequest, Recipe, Score, ScorerBuilder, TaskCraft, TaskFindNearestCampfire,
+        TaskMoveTo,
+    },
+    Terrain,
+};
+
+#[derive(Component, Clone)]
+pub struct ScorerCook;
+
+impl ScorerBuilder for ScorerCook {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        cmd.insert(self.clone());
+    }
+
+    fn label(&self) -> String {
+        "Cook".to_string()
+    }
+
+    fn build(&self) -> Behavior {
+        Behavior::new(
+            "Cook",
+            BehaviorNode::Sequence(vec![
+                tree_aquire_item(vec![ItemTag::RawFood]),
+                BehaviorNode::Task(Arc::new(TaskFindNearestCampfire)),
+                BehaviorNode::Task(Arc::new(TaskMoveTo)),
+                BehaviorNode::Task(Arc::new(TaskCraft {
+                    recipe: Recipe::cook_food(),
+                    progress: 0.,
+                })),
+            ]),
+        )
+    }
+}
+
+pub fn score_cook(
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    q_items: Query<&Item>,
+    q_free_items: Query<(&Item, &Transform), Without<InInventory>>,
+    q_actors: Query<
+        (&Inventory, &Transform, &NavigationFlags),
+        (With<Actor>, Without<HasBehavior>),
+    >,
+    mut q_behaviors: Query<(&ActorRef, &mut Score), With<ScorerCook>>,
+) {
+    let item_tags = &[ItemTag::RawFood];
+
+    for (ActorRef(actor), mut score) in q_behaviors.iter_mut() {
+        let Ok((inventory, transform, flags)) = q_actors.get(*actor) else {
+            *score = Score(0.);
+            continue;
+        };
+
+        if terrain.heat_sources.is_empty() {
+            *score = Score(0.);
+            continue;
+        }
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        let has_raw_food = inventory.items.iter().any(|e| {
+            q_items
+                .get(*e)
+                .is_ok_and(|item| test_item_tags(&item.tags, item_tags))
+        });
+
+        if has_raw_food {
+            *score = Score(0.5);
+            continue;
+        }
+
+        // check if any raw food is unreserved and accessible
+        if q_free_items.iter().any(|(i, t)| {
+            test_item_tags(&i.tags, item_tags)
+                && i.reserved.is_none()
+                && is_reachable(
+                    &PartitionPathRequest {
+                        start: pos,
+                        goals: vec![[
+                            t.translation.x as u32,
+                            t.translation.y as u32,
+                            t.translation.z as u32,
+                        ]],
+                        flags: *flags,
+                    },
+                    &terrain,
+                    &graph,
+                )
+        }) {
+            *score = Score(0.3);
+        } else {
+            *score = Score(0.);
+        }
+    }
+}
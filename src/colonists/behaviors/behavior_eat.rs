@@ -0,0 +1,235 @@
+use std::sync::Arc;
+
+use bevy::{
+    ecs::{
+        component::Component,
+        query::{With, Without},
+        system::{EntityCommands, Query, Res},
+    },
+    transform::components::Transform,
+};
+
+use crate::{
+    colonists::{
+        is_reachable, test_item_tags, tree_aquire_item, Actor, ActorRef, Behavior, BehaviorNode,
+        HasBehavior, Hunger, InInventory, Inventory, Item, ItemTag, NavigationFlags,
+        NavigationGraph, PartitionPathRequest, Score, ScorerBuilder, TaskEat,
+    },
+    Terrain,
+};
+
+#[derive(Component, Clone)]
+pub struct ScorerEat;
+
+impl ScorerBuilder for ScorerEat {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        cmd.insert(self.clone());
+    }
+
+    fn label(&self) -> String {
+        "Eat".to_string()
+    }
+
+    fn build(&self) -> Behavior {
+        Behavior::new(
+            "Eat",
+            BehaviorNode::Sequence(vec![
+                tree_aquire_item(vec![ItemTag::Food]),
+                BehaviorNode::Task(Arc::new(TaskEat)),
+            ]),
+        )
+    }
+}
+
+pub fn score_eat(
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    q_items: Query<&Item>,
+    q_free_items: Query<(&Item, &Transform), Without<InInventory>>,
+    q_actors: Query<
+        (&Hunger, &Inventory, &Transform, &NavigationFlags),
+        (With<Actor>, Without<HasBehavior>),
+    >,
+    mut q_behaviors: Query<(&ActorRef, &mut Score), With<ScorerEat>>,
+) {
+    let item_tags = &[ItemTag::Food];
+
+    for (ActorRef(actor), mut score) in q_behaviors.iter_mut() {
+        let Ok((hunger, inventory, transform, flags)) = q_actors.get(*actor) else {
+            *score = Score(0.);
+            continue;
+        };
+
+        if !hunger.is_hungry() {
+            *score = Score(0.);
+            continue;
+        }
+
+        // starving colonists drop everything else
+        let urgency = if hunger.is_starving() { 0.2 } else { 0. };
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        let has_food = inventory.items.iter().any(|e| {
+            q_items
+                .get(*e)
+                .is_ok_and(|item| test_item_tags(&item.tags, item_tags))
+        });
+
+        if has_food {
+            *score = Score(0.75 + urgency);
+            continue;
+        }
+
+        // check if any food is unreserved and accessible
+        if q_free_items.iter().any(|(i, t)| {
+            test_item_tags(&i.tags, item_tags)
+                && i.reserved.is_none()
+                && is_reachable(
+                    &PartitionPathRequest {
+                        start: pos,
+                        goals: vec![[
+                            t.translation.x as u32,
+                            t.translation.y as u32,
+                            t.translation.z as u32,
+                        ]],
+                        flags: *flags,
+                    },
+                    &terrain,
+                    &graph,
+                )
+        }) {
+            *score = Score(0.7 + urgency);
+        } else {
+            *score = Score(0.);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{
+        entity::Entity,
+        event::Events,
+        schedule::{IntoSystemConfigs, Schedule},
+        system::RunSystemOnce,
+        world::World,
+    };
+
+    use crate::{
+        colonists::{
+            partition, partition_orphaned_items, task_find_nearest_item, Blackboard,
+            PartitionEvent, TaskFindNearestItem, TaskScheduler, TaskState, HUNGRY, STARVING,
+        },
+        BlockType,
+    };
+
+    use super::*;
+
+    /// A partitioned stone floor with one mushroom lying on it
+    fn mushroom_world() -> (World, Entity) {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.fill_region([0, 0, 0], [7, 0, 7], BlockType::STONE);
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<TaskScheduler>();
+        world.init_resource::<Events<PartitionEvent>>();
+
+        let mushroom = world
+            .spawn((
+                Transform::from_xyz(5.5, 1., 5.5),
+                Item {
+                    tags: vec![ItemTag::Food],
+                    reserved: None,
+                },
+            ))
+            .id();
+
+        world.send_event(PartitionEvent { chunk_idx: 0 });
+        let mut schedule = Schedule::default();
+        schedule.add_systems((partition, partition_orphaned_items).chain());
+        schedule.run(&mut world);
+
+        (world, mushroom)
+    }
+
+    fn spawn_colonist(world: &mut World, hunger: f32) -> Entity {
+        world
+            .spawn((
+                Actor,
+                Hunger(hunger),
+                Inventory::default(),
+                Transform::from_xyz(1.5, 1., 1.5),
+                NavigationFlags::COLONIST,
+            ))
+            .id()
+    }
+
+    fn eat_scores(world: &mut World, hungers: &[f32]) -> Vec<f32> {
+        let scorers = hungers
+            .iter()
+            .map(|hunger| {
+                let actor = spawn_colonist(world, *hunger);
+                world.spawn((ActorRef(actor), Score(0.), ScorerEat)).id()
+            })
+            .collect::<Vec<_>>();
+
+        world.run_system_once(score_eat);
+
+        scorers
+            .iter()
+            .map(|scorer| world.get::<Score>(*scorer).unwrap().0)
+            .collect()
+    }
+
+    #[test]
+    fn eating_waits_for_hunger() {
+        let (mut world, _) = mushroom_world();
+
+        let scores = eat_scores(&mut world, &[HUNGRY - 1., HUNGRY, STARVING]);
+        assert_eq!(scores, vec![0., 0.7, 0.9]);
+    }
+
+    #[test]
+    fn two_hungry_colonists_cannot_both_claim_one_mushroom() {
+        let (mut world, mushroom) = mushroom_world();
+
+        // both look for food in the same frame
+        let tasks = [HUNGRY, HUNGRY].map(|hunger| {
+            let actor = spawn_colonist(&mut world, hunger);
+            world
+                .spawn((
+                    ActorRef(actor),
+                    TaskState::Executing,
+                    Blackboard::default(),
+                    TaskFindNearestItem(vec![ItemTag::Food]),
+                ))
+                .id()
+        });
+        world.run_system_once(task_find_nearest_item);
+
+        let states = tasks.map(|task| *world.get::<TaskState>(task).unwrap());
+        let claimed = tasks.map(|task| world.get::<Blackboard>(task).unwrap().item);
+        assert_eq!(
+            states
+                .iter()
+                .filter(|state| **state == TaskState::Success)
+                .count(),
+            1
+        );
+        assert!(states.contains(&TaskState::Failed));
+        assert!(claimed.contains(&Some(mushroom)) && claimed.contains(&None));
+
+        // and the reserved mushroom no longer tempts anyone else
+        let winner = claimed.iter().position(|item| item.is_some()).unwrap();
+        let ActorRef(actor) = *world.get::<ActorRef>(tasks[winner]).unwrap();
+        assert_eq!(world.get::<Item>(mushroom).unwrap().reserved, Some(actor));
+        assert_eq!(eat_scores(&mut world, &[STARVING]), vec![0.]);
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use bevy::{
+    ecs::{
+        self,
+        component::Component,
+        entity::Entity,
+        query::{With, Without},
+        system::{Query, Res, ResMut},
+    },
+    time::Time,
+    transform::components::Transform,
+};
+
+use crate::{
+    colonists::{
+        is_reachable, job_access_points, Actor, ActorRef, Behavior, BehaviorNode, ColonistFlags,
+        FactionId, HasBehavior, IsJobAccessible, IsJobCancelled, Job, JobFarm, JobLocation,
+        JobQueue, JobType, NavigationFlags, NavigationGraph, PartitionPathRequest, Score,
+        ScorerBuilder, TaskAssignJob, TaskFarm, TaskGetJobLocation, TaskJobComplete,
+        TaskJobUnassign, TaskMoveTo,
+    },
+    common::Distance,
+    Terrain,
+};
+
+#[derive(Component, Clone, Default)]
+pub struct ScorerFarm {
+    job: Option<Entity>,
+}
+
+impl ScorerBuilder for ScorerFarm {
+    fn insert(&self, cmd: &mut ecs::system::EntityCommands) {
+        cmd.insert(self.clone());
+    }
+
+    fn label(&self) -> String {
+        "Farm".to_string()
+    }
+
+    fn build(&self) -> Behavior {
+        Behavior::new(
+            "Farm",
+            BehaviorNode::Try(
+                Box::new(BehaviorNode::Sequence(vec![
+                    BehaviorNode::Task(Arc::new(TaskAssignJob(self.job.unwrap()))),
+                    BehaviorNode::Task(Arc::new(TaskGetJobLocation)),
+                    BehaviorNode::Task(Arc::new(TaskMoveTo)),
+                    BehaviorNode::Task(Arc::new(TaskFarm { progress: 0. })),
+                    BehaviorNode::Task(Arc::new(TaskJobComplete)),
+                ])),
+                Box::new(BehaviorNode::Task(Arc::new(TaskJobUnassign))),
+            ),
+        )
+    }
+}
+
+pub fn score_farm(
+    time: Res<Time>,
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    mut queue: ResMut<JobQueue>,
+    q_jobs: Query<
+        (&Job, &JobLocation),
+        (
+            With<JobFarm>,
+            With<IsJobAccessible>,
+            Without<IsJobCancelled>,
+        ),
+    >,
+    q_actors: Query<
+        (
+            &Transform,
+            &NavigationFlags,
+            &ColonistFlags,
+            Option<&FactionId>,
+        ),
+        (With<Actor>, Without<HasBehavior>),
+    >,
+    mut q_behaviors: Query<(&ActorRef, &mut Score, &mut ScorerFarm)>,
+) {
+    for (ActorRef(actor), mut score, mut scorer) in q_behaviors.iter_mut() {
+        let Ok((transform, flags, colonist_flags, faction)) = q_actors.get(*actor) else {
+            *score = Score(0.);
+            continue;
+        };
+
+        if !colonist_flags.contains(ColonistFlags::CAN_FARM) {
+            *score = Score(0.);
+            continue;
+        }
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        let now = time.elapsed_seconds_f64();
+        let mut nearest: Option<(Entity, f32)> = None;
+
+        for e in queue.candidates(JobType::Farm, now) {
+            let Ok((job, job_location)) = q_jobs.get(e) else {
+                // not accessible right now, let the rest through
+                queue.defer(JobType::Farm, e, now);
+                continue;
+            };
+
+            if job.assignee.is_some() || !job.is_open_to(faction) {
+                continue;
+            }
+
+            let request = PartitionPathRequest {
+                start: pos,
+                goals: job_access_points(job_location.pos, job.job_type),
+                flags: *flags,
+            };
+
+            if !is_reachable(&request, &terrain, &graph) {
+                queue.defer(JobType::Farm, e, now);
+                continue;
+            }
+
+            let distance = Distance::manhattan(
+                [
+                    job_location.pos[0] as i32,
+                    job_location.pos[1] as i32,
+                    job_location.pos[2] as i32,
+                ],
+                [pos[0] as i32, pos[1] as i32, pos[2] as i32],
+            );
+
+            if nearest.is_none_or(|(_, best)| distance < best) {
+                nearest = Some((e, distance));
+            }
+        }
+
+        let Some((job, _)) = nearest else {
+            *score = Score(0.);
+            continue;
+        };
+
+        scorer.job = Some(job);
+        *score = Score(0.5);
+    }
+}
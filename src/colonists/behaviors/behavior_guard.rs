@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use bevy::ecs::{
+    component::Component,
+    query::{With, Without},
+    system::{EntityCommands, Query},
+};
+
+use crate::colonists::{
+    Actor, ActorRef, Behavior, BehaviorNode, HasBehavior, Score, ScorerBuilder, TaskGuard,
+};
+
+/// Where a guard stands watch
+#[derive(Component)]
+pub struct GuardPost {
+    pub pos: [u32; 3],
+}
+
+#[derive(Component, Clone, Default)]
+pub struct ScorerGuard {
+    post: [u32; 3],
+}
+
+impl ScorerBuilder for ScorerGuard {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        cmd.insert(self.clone());
+    }
+
+    fn label(&self) -> String {
+        "Guard".to_string()
+    }
+
+    fn build(&self) -> Behavior {
+        Behavior::new("Guard", BehaviorNode::Task(Arc::new(TaskGuard(self.post))))
+    }
+}
+
+pub fn score_guard(
+    q_actors: Query<&GuardPost, (With<Actor>, Without<HasBehavior>)>,
+    mut q_behaviors: Query<(&ActorRef, &mut Score, &mut ScorerGuard)>,
+) {
+    for (ActorRef(actor), mut score, mut scorer) in q_behaviors.iter_mut() {
+        let Ok(post) = q_actors.get(*actor) else {
+            *score = Score(0.);
+            continue;
+        };
+
+        scorer.post = post.pos;
+        *score = Score(0.2);
+    }
+}
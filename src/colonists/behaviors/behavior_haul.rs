@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use bevy::{
+    ecs::{
+        self,
+        component::Component,
+        entity::Entity,
+        query::{With, Without},
+        system::{Query, Res},
+    },
+    transform::components::Transform,
+};
+
+use crate::{
+    colonists::{
+        is_faction_allowed, is_reachable, Actor, ActorRef, Behavior, BehaviorNode, CarryCapacity,
+        FactionId, HasBehavior, InInventory, IsJobAccessible, IsJobCancelled, Item, Job, JobHaul,
+        JobLocation, NavigationFlags, NavigationGraph, PartitionPathRequest, Score, ScorerBuilder,
+        TaskAssignJob, TaskBuilder, TaskFindHaulItem, TaskGetJobLocation, TaskHaul,
+        TaskJobComplete, TaskJobUnassign, TaskMoveTo, TaskPickUpItem, TaskReleaseItem,
+        MOVE_TIMEOUT_S,
+    },
+    common::Distance,
+    Terrain,
+};
+
+#[derive(Component, Clone, Default)]
+pub struct ScorerHaul {
+    job: Option<Entity>,
+}
+
+impl ScorerBuilder for ScorerHaul {
+    fn insert(&self, cmd: &mut ecs::system::EntityCommands) {
+        cmd.insert(self.clone());
+    }
+
+    fn label(&self) -> String {
+        "Haul".to_string()
+    }
+
+    fn build(&self) -> Behavior {
+        Behavior::new(
+            "Haul",
+            BehaviorNode::Try(
+                Box::new(BehaviorNode::Sequence(vec![
+                    BehaviorNode::Task(Arc::new(TaskAssignJob(self.job.unwrap()))),
+                    BehaviorNode::Task(Arc::new(TaskFindHaulItem)),
+                    BehaviorNode::Task(Arc::new(TaskMoveTo.with_timeout(MOVE_TIMEOUT_S))),
+                    BehaviorNode::Task(Arc::new(TaskPickUpItem)),
+                    BehaviorNode::Task(Arc::new(TaskGetJobLocation)),
+                    BehaviorNode::Task(Arc::new(TaskMoveTo.with_timeout(MOVE_TIMEOUT_S))),
+                    BehaviorNode::Task(Arc::new(TaskHaul)),
+                    BehaviorNode::Task(Arc::new(TaskJobComplete)),
+                ])),
+                // let go of the item before handing the job back
+                Box::new(BehaviorNode::Sequence(vec![
+                    BehaviorNode::Task(Arc::new(TaskReleaseItem)),
+                    BehaviorNode::Task(Arc::new(TaskJobUnassign)),
+                ])),
+            ),
+        )
+    }
+}
+
+pub fn score_haul(
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    q_jobs: Query<
+        (Entity, &Job, &JobHaul, &JobLocation),
+        (With<IsJobAccessible>, Without<IsJobCancelled>),
+    >,
+    q_items: Query<(&Item, &Transform, Option<&FactionId>), Without<InInventory>>,
+    q_actors: Query<
+        (
+            &Transform,
+            &NavigationFlags,
+            &CarryCapacity,
+            Option<&FactionId>,
+        ),
+        (With<Actor>, Without<HasBehavior>),
+    >,
+    mut q_behaviors: Query<(&ActorRef, &mut Score, &mut ScorerHaul)>,
+) {
+    for (ActorRef(actor), mut score, mut scorer) in q_behaviors.iter_mut() {
+        let Ok((transform, flags, capacity, faction)) = q_actors.get(*actor) else {
+            *score = Score(0.);
+            continue;
+        };
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        let nearest = q_jobs
+            .iter()
+            .filter_map(|(e, job, haul, job_location)| {
+                if job.assignee.is_some() || !job.is_open_to(faction) {
+                    return None;
+                }
+
+                let (item, item_transform, owner) = q_items.get(haul.item).ok()?;
+
+                if item.reserved.is_some()
+                    || item.weight() > capacity.remaining()
+                    || !is_faction_allowed(owner, faction)
+                {
+                    return None;
+                }
+
+                let item_pos = [
+                    item_transform.translation.x as u32,
+                    item_transform.translation.y as u32,
+                    item_transform.translation.z as u32,
+                ];
+
+                // the item has to be reachable, and the stockpile from the item
+                let can_reach = is_reachable(
+                    &PartitionPathRequest {
+                        start: pos,
+                        goals: vec![item_pos],
+                        flags: *flags,
+                    },
+                    &terrain,
+                    &graph,
+                ) && is_reachable(
+                    &PartitionPathRequest {
+                        start: item_pos,
+                        goals: vec![job_location.pos],
+                        flags: *flags,
+                    },
+                    &terrain,
+                    &graph,
+                );
+
+                if !can_reach {
+                    return None;
+                }
+
+                let distance = Distance::manhattan(
+                    [item_pos[0] as i32, item_pos[1] as i32, item_pos[2] as i32],
+                    [pos[0] as i32, pos[1] as i32, pos[2] as i32],
+                );
+
+                Some((e, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let Some((job, _)) = nearest else {
+            *score = Score(0.);
+            continue;
+        };
+
+        scorer.job = Some(job);
+        *score = Score(0.25);
+    }
+}
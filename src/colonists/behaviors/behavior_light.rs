@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use bevy::{
+    ecs::{
+        component::Component,
+        query::{With, Without},
+        system::{EntityCommands, Query, Res},
+    },
+    transform::components::Transform,
+};
+
+use crate::{
+    colonists::{
+        get_block_flags, is_reachable, job_access_points, test_item_tags, tree_aquire_item, Actor,
+        ActorRef, Behavior, BehaviorNode, HasBehavior, InInventory, Inventory, Item, ItemTag,
+        JobType, NavigationFlags, NavigationGraph, PartitionPathRequest, Recipe, Score,
+        ScorerBuilder, TaskCraft, TaskFindNearestCampfire, TaskMoveTo, TaskPlaceTorch,
+        TaskSetMoveGoals,
+    },
+    BlockType, Terrain,
+};
+
+/// At or below this much light a colonist puts up a torch
+const DARK_LIGHT_LEVEL: u8 = 3;
+
+#[derive(Component, Clone, Default)]
+pub struct ScorerLight {
+    spot: [u32; 3],
+}
+
+impl ScorerBuilder for ScorerLight {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        cmd.insert(self.clone());
+    }
+
+    fn label(&self) -> String {
+        "Light".to_string()
+    }
+
+    fn build(&self) -> Behavior {
+        Behavior::new(
+            "Light",
+            BehaviorNode::Sequence(vec![
+                BehaviorNode::Select(vec![
+                    tree_aquire_item(vec![ItemTag::Torch]),
+                    tree_craft_torch(),
+                ]),
+                BehaviorNode::Task(Arc::new(TaskSetMoveGoals(job_access_points(
+                    self.spot,
+                    JobType::Mine,
+                )))),
+                BehaviorNode::Task(Arc::new(TaskMoveTo)),
+                BehaviorNode::Task(Arc::new(TaskPlaceTorch { target: self.spot })),
+            ]),
+        )
+    }
+}
+
+/// Craft a torch at the nearest campfire and pick it up, it is dropped at
+/// the crafter's feet
+fn tree_craft_torch() -> BehaviorNode {
+    BehaviorNode::Sequence(vec![
+        tree_aquire_item(vec![ItemTag::Wood]),
+        tree_aquire_item(vec![ItemTag::Coal]),
+        BehaviorNode::Task(Arc::new(TaskFindNearestCampfire)),
+        BehaviorNode::Task(Arc::new(TaskMoveTo)),
+        BehaviorNode::Task(Arc::new(TaskCraft {
+            recipe: Recipe::torch(),
+            progress: 0.,
+        })),
+        tree_aquire_item(vec![ItemTag::Torch]),
+    ])
+}
+
+/// An empty cell next to or above the colonist where a torch is out of
+/// everyone's way, nobody stands in it or has their head in it
+fn find_torch_spot(terrain: &Terrain, pos: [u32; 3]) -> Option<[u32; 3]> {
+    let [x, y, z] = [pos[0] as i32, pos[1] as i32, pos[2] as i32];
+
+    [
+        [x, y + 2, z],
+        [x + 1, y + 1, z],
+        [x - 1, y + 1, z],
+        [x, y + 1, z + 1],
+        [x, y + 1, z - 1],
+    ]
+    .into_iter()
+    .find(|[x, y, z]| {
+        terrain.get_block_i32(*x, *y, *z).block == BlockType::EMPTY
+            && get_block_flags(terrain, *x, *y, *z) == NavigationFlags::NONE
+            && get_block_flags(terrain, *x, *y - 1, *z) == NavigationFlags::NONE
+    })
+    .map(|[x, y, z]| [x as u32, y as u32, z as u32])
+}
+
+pub fn score_light(
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    q_items: Query<&Item>,
+    q_free_items: Query<(&Item, &Transform), Without<InInventory>>,
+    q_actors: Query<
+        (&Inventory, &Transform, &NavigationFlags),
+        (With<Actor>, Without<HasBehavior>),
+    >,
+    mut q_behaviors: Query<(&ActorRef, &mut Score, &mut ScorerLight)>,
+) {
+    for (ActorRef(actor), mut score, mut scorer) in q_behaviors.iter_mut() {
+        let Ok((inventory, transform, flags)) = q_actors.get(*actor) else {
+            *score = Score(0.);
+            continue;
+        };
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        let light = terrain
+            .get_sunlight_xyz(pos[0], pos[1], pos[2])
+            .max(terrain.get_torchlight_xyz(pos[0], pos[1], pos[2]));
+
+        if light > DARK_LIGHT_LEVEL {
+            *score = Score(0.);
+            continue;
+        }
+
+        let Some(spot) = find_torch_spot(&terrain, pos) else {
+            *score = Score(0.);
+            continue;
+        };
+
+        // carried, or lying around unreserved somewhere the colonist can walk
+        let is_available = |tags: &[ItemTag]| {
+            inventory.items.iter().any(|e| {
+                q_items
+                    .get(*e)
+                    .is_ok_and(|item| test_item_tags(&item.tags, tags))
+            }) || q_free_items.iter().any(|(item, t)| {
+                test_item_tags(&item.tags, tags)
+                    && item.reserved.is_none()
+                    && is_reachable(
+                        &PartitionPathRequest {
+                            start: pos,
+                            goals: vec![[
+                                t.translation.x as u32,
+                                t.translation.y as u32,
+                                t.translation.z as u32,
+                            ]],
+                            flags: *flags,
+                        },
+                        &terrain,
+                        &graph,
+                    )
+            })
+        };
+
+        let can_craft = !terrain.heat_sources.is_empty()
+            && is_available(&[ItemTag::Wood])
+            && is_available(&[ItemTag::Coal]);
+
+        if !is_available(&[ItemTag::Torch]) && !can_craft {
+            *score = Score(0.);
+            continue;
+        }
+
+        scorer.spot = spot;
+        *score = Score(0.15);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stone box with a stone floor, open `height` blocks above the floor
+    fn room(height: u32) -> Terrain {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+
+        for x in 0..8 {
+            for y in 0..8 {
+                for z in 0..8 {
+                    let is_inside =
+                        (1..7).contains(&x) && (1..=height).contains(&y) && (1..7).contains(&z);
+
+                    if !is_inside {
+                        terrain.set_block(x, y, z, BlockType::STONE);
+                    }
+                }
+            }
+        }
+
+        terrain
+    }
+
+    #[test]
+    fn torches_go_overhead_in_tall_rooms() {
+        let terrain = room(3);
+        assert_eq!(find_torch_spot(&terrain, [3, 1, 3]), Some([3, 3, 3]));
+    }
+
+    #[test]
+    fn torches_never_block_a_tunnel() {
+        let mut terrain = room(2);
+
+        // a one wide corridor along x
+        for x in 1..7 {
+            for y in 1..3 {
+                for z in [1, 2, 4, 5, 6] {
+                    terrain.set_block(x, y, z, BlockType::STONE);
+                }
+            }
+        }
+
+        assert_eq!(find_torch_spot(&terrain, [3, 1, 3]), None);
+    }
+}
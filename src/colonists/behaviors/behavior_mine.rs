@@ -6,21 +6,23 @@ use bevy::{
         component::Component,
         entity::Entity,
         query::{With, Without},
-        system::{Query, Res},
+        system::{Query, Res, ResMut},
     },
+    time::Time,
     transform::components::Transform,
 };
 
 use crate::{
     colonists::{
         is_reachable, job_access_points, test_item_tags, tree_aquire_item, Actor, ActorRef,
-        Behavior, BehaviorNode, HasBehavior, InInventory, Inventory, IsJobAccessible,
-        IsJobCancelled, Item, ItemTag, Job, JobLocation, JobMine, NavigationFlags, NavigationGraph,
-        PartitionPathRequest, Score, ScorerBuilder, TaskAssignJob, TaskGetJobLocation,
-        TaskJobComplete, TaskJobUnassign, TaskMineBlock, TaskMoveTo,
+        Behavior, BehaviorNode, FactionId, HasBehavior, InInventory, Inventory, IsJobAccessible,
+        IsJobCancelled, Item, ItemTag, Job, JobLocation, JobMine, JobQueue, JobType,
+        NavigationFlags, NavigationGraph, PartitionPathRequest, Score, ScorerBuilder, SelectorTask,
+        TaskAssignJob, TaskClearRubble, TaskGetJobLocation, TaskJobComplete, TaskJobUnassign,
+        TaskMineBlock, TaskMoveTo,
     },
     common::Distance,
-    Terrain,
+    BlockType, Terrain,
 };
 
 #[derive(Component, Clone, Default)]
@@ -43,13 +45,15 @@ impl ScorerBuilder for ScorerMine {
             BehaviorNode::Try(
                 Box::new(BehaviorNode::Sequence(vec![
                     BehaviorNode::Task(Arc::new(TaskAssignJob(self.job.unwrap()))),
-                    tree_aquire_item(vec![ItemTag::Pickaxe]),
-                    BehaviorNode::Sequence(vec![
-                        BehaviorNode::Task(Arc::new(TaskGetJobLocation)),
-                        BehaviorNode::Task(Arc::new(TaskMoveTo)),
-                        BehaviorNode::Task(Arc::new(TaskMineBlock { progress: 0. })),
-                        BehaviorNode::Task(Arc::new(TaskJobComplete)),
+                    BehaviorNode::Select(vec![
+                        BehaviorNode::Sequence(vec![
+                            tree_aquire_item(vec![ItemTag::Pickaxe]),
+                            tree_mine_target(),
+                        ]),
+                        // no pickaxe to be had, dig bare-handed instead
+                        tree_mine_target(),
                     ]),
+                    BehaviorNode::Task(Arc::new(TaskJobComplete)),
                 ])),
                 Box::new(BehaviorNode::Task(Arc::new(TaskJobUnassign))),
             ),
@@ -57,11 +61,24 @@ impl ScorerBuilder for ScorerMine {
     }
 }
 
+fn tree_mine_target() -> BehaviorNode {
+    BehaviorNode::Sequence(vec![
+        BehaviorNode::Task(Arc::new(TaskGetJobLocation)),
+        BehaviorNode::Task(Arc::new(TaskMoveTo)),
+        BehaviorNode::Task(Arc::new(SelectorTask::new(vec![
+            Arc::new(TaskClearRubble),
+            Arc::new(TaskMineBlock),
+        ]))),
+    ])
+}
+
 pub fn score_mine(
+    time: Res<Time>,
     terrain: Res<Terrain>,
     graph: Res<NavigationGraph>,
+    mut queue: ResMut<JobQueue>,
     q_jobs: Query<
-        (Entity, &Job, &JobLocation),
+        (&Job, &JobLocation),
         (
             With<JobMine>,
             With<IsJobAccessible>,
@@ -72,13 +89,13 @@ pub fn score_mine(
     q_items: Query<&Item>,
     q_free_items: Query<(&Item, &Transform), Without<InInventory>>,
     q_actors: Query<
-        (&Inventory, &Transform, &NavigationFlags),
+        (&Inventory, &Transform, &NavigationFlags, Option<&FactionId>),
         (With<Actor>, Without<HasBehavior>),
     >,
     mut q_behaviors: Query<(&ActorRef, &mut Score, &mut ScorerMine)>,
 ) {
     for (ActorRef(actor), mut score, mut scorer) in q_behaviors.iter_mut() {
-        let Ok((inventory, transform, flags)) = q_actors.get(*actor) else {
+        let Ok((inventory, transform, flags, faction)) = q_actors.get(*actor) else {
             *score = Score(0.);
             continue;
         };
@@ -91,9 +108,22 @@ pub fn score_mine(
 
         let mut best = None;
         let mut best_dist = 100000.;
+        let now = time.elapsed_seconds_f64();
+
+        for e in queue.candidates(JobType::Mine, now) {
+            let Ok((job, job_location)) = q_jobs.get(e) else {
+                // not accessible right now, let the rest through
+                queue.defer(JobType::Mine, e, now);
+                continue;
+            };
+
+            if job.assignee.is_some() || !job.is_open_to(faction) {
+                continue;
+            }
 
-        for (e, job, job_location) in q_jobs.iter() {
-            if job.assignee.is_some() {
+            // trees are felled whole, see `score_chop`
+            let [x, y, z] = job_location.pos;
+            if terrain.get_block(x, y, z).block == BlockType::LOG {
                 continue;
             }
 
@@ -105,6 +135,7 @@ pub fn score_mine(
             };
 
             if !is_reachable(&request, &terrain, &graph) {
+                queue.defer(JobType::Mine, e, now);
                 continue;
             }
 
@@ -170,7 +201,82 @@ pub fn score_mine(
             *score = Score(0.2);
             continue;
         } else {
-            *score = Score(0.0);
+            // digging bare-handed is slow, but still beats wandering around
+            *score = Score(0.15);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{event::Events, system::RunSystemOnce, world::World};
+
+    use crate::colonists::{
+        partition, sync_job_queue, PartitionEvent, PLAYER_FACTION, RIVAL_FACTION,
+    };
+
+    use super::*;
+
+    /// How each faction, and a colonist without one, scores a mine job
+    /// made for `job_faction` on a partitioned stone floor
+    fn mine_scores(job_faction: Option<FactionId>) -> Vec<f32> {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.fill_region([0, 0, 0], [7, 0, 7], BlockType::STONE);
+        terrain.set_block(4, 1, 4, BlockType::STONE);
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<Time>();
+        world.init_resource::<JobQueue>();
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<Events<PartitionEvent>>();
+        world.send_event(PartitionEvent { chunk_idx: 0 });
+        world.run_system_once(partition);
+
+        world.spawn((
+            Job {
+                job_type: JobType::Mine,
+                assignee: None,
+                deadline: None,
+                waiting_for_material: false,
+                faction_id: job_faction,
+            },
+            JobMine,
+            IsJobAccessible,
+            JobLocation { pos: [4, 1, 4] },
+        ));
+        world.run_system_once(sync_job_queue);
+
+        let scorers = [Some(PLAYER_FACTION), Some(RIVAL_FACTION), None].map(|faction| {
+            let mut actor = world.spawn((
+                Actor,
+                Inventory::default(),
+                Transform::from_xyz(1.5, 1., 1.5),
+                NavigationFlags::COLONIST,
+            ));
+            if let Some(faction) = faction {
+                actor.insert(faction);
+            }
+            let actor = actor.id();
+
+            world
+                .spawn((ActorRef(actor), Score(0.), ScorerMine::default()))
+                .id()
+        });
+
+        world.run_system_once(score_mine);
+
+        scorers
+            .iter()
+            .map(|scorer| world.get::<Score>(*scorer).unwrap().0)
+            .collect()
+    }
+
+    #[test]
+    fn faction_jobs_are_invisible_to_other_factions() {
+        // player, rival, no faction
+        assert_eq!(mine_scores(Some(RIVAL_FACTION)), vec![0., 0.15, 0.]);
+        assert_eq!(mine_scores(Some(PLAYER_FACTION)), vec![0.15, 0., 0.]);
+        assert_eq!(mine_scores(None), vec![0.15, 0.15, 0.15]);
+    }
+}
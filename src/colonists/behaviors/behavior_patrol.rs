@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use bevy::ecs::{
+    component::Component,
+    query::{With, Without},
+    system::{EntityCommands, Query},
+};
+
+use crate::colonists::{
+    Actor, ActorRef, Behavior, BehaviorNode, HasBehavior, Score, ScorerBuilder, TaskPatrol,
+};
+
+/// Waypoints a guard walks between, in order
+#[derive(Component, Default)]
+pub struct PatrolRoute {
+    pub waypoints: Vec<[u32; 3]>,
+}
+
+#[derive(Component, Clone, Default)]
+pub struct ScorerPatrol {
+    waypoints: Vec<[u32; 3]>,
+}
+
+impl ScorerBuilder for ScorerPatrol {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        cmd.insert(self.clone());
+    }
+
+    fn label(&self) -> String {
+        "Patrol".to_string()
+    }
+
+    fn build(&self) -> Behavior {
+        Behavior::new(
+            "Patrol",
+            BehaviorNode::Task(Arc::new(TaskPatrol::new(self.waypoints.clone()))),
+        )
+    }
+}
+
+pub fn score_patrol(
+    q_actors: Query<&PatrolRoute, (With<Actor>, Without<HasBehavior>)>,
+    mut q_behaviors: Query<(&ActorRef, &mut Score, &mut ScorerPatrol)>,
+) {
+    for (ActorRef(actor), mut score, mut scorer) in q_behaviors.iter_mut() {
+        let Ok(route) = q_actors.get(*actor) else {
+            *score = Score(0.);
+            continue;
+        };
+
+        if route.waypoints.len() < 2 {
+            *score = Score(0.);
+            continue;
+        }
+
+        scorer.waypoints = route.waypoints.clone();
+        *score = Score(0.15);
+    }
+}
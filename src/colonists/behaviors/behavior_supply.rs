@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use bevy::{
+    ecs::{
+        component::Component,
+        query::{With, Without},
+        system::{EntityCommands, Query, Res},
+    },
+    transform::components::Transform,
+};
+
+use crate::{
+    colonists::{
+        is_item_available, is_reachable, job_access_points, Actor, ActorRef, Behavior,
+        BehaviorNode, BlueprintWall, CarryCapacity, FactionId, HasBehavior, InInventory,
+        IsJobAccessible, IsJobCancelled, IsJobCompleted, Item, ItemTag, Job, JobBuild, JobLocation,
+        NavigationFlags, NavigationGraph, PartitionPathRequest, Score, ScorerBuilder,
+        StockpileZone, TaskDeliverItem,
+    },
+    Terrain,
+};
+
+/// Carries build material out of a stockpile to the partition of a build
+/// site that has none lying around yet, so builders find it close by
+#[derive(Component, Clone, Default)]
+pub struct ScorerSupply {
+    delivery: Option<(ItemTag, u32, u32)>,
+}
+
+impl ScorerBuilder for ScorerSupply {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        cmd.insert(self.clone());
+    }
+
+    fn label(&self) -> String {
+        "Supply".to_string()
+    }
+
+    fn build(&self) -> Behavior {
+        let (material, from, to) = self.delivery.unwrap();
+
+        Behavior::new(
+            "Supply",
+            BehaviorNode::Task(Arc::new(TaskDeliverItem::new(vec![material], from, to))),
+        )
+    }
+}
+
+pub fn score_supply(
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    q_jobs: Query<
+        (&Job, &JobLocation, &BlueprintWall),
+        (
+            With<JobBuild>,
+            With<IsJobAccessible>,
+            Without<IsJobCancelled>,
+            Without<IsJobCompleted>,
+        ),
+    >,
+    q_zones: Query<&StockpileZone>,
+    q_items: Query<&Item, Without<InInventory>>,
+    q_actors: Query<
+        (
+            &Transform,
+            &NavigationFlags,
+            &CarryCapacity,
+            Option<&FactionId>,
+        ),
+        (With<Actor>, Without<HasBehavior>),
+    >,
+    mut q_behaviors: Query<(&ActorRef, &mut Score, &mut ScorerSupply)>,
+) {
+    for (ActorRef(actor), mut score, mut scorer) in q_behaviors.iter_mut() {
+        let Ok((transform, flags, capacity, faction)) = q_actors.get(*actor) else {
+            *score = Score(0.);
+            continue;
+        };
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        let has_item = |partition_id: u32, material: ItemTag| {
+            graph.get_partition(&partition_id).is_some_and(|p| {
+                p.items.iter().any(|e| {
+                    q_items.get(*e).is_ok_and(|item| {
+                        is_item_available(item, &[material], capacity.remaining())
+                    })
+                })
+            })
+        };
+
+        let delivery = q_jobs
+            .iter()
+            .filter(|(job, _, _)| job.assignee.is_none() && job.is_open_to(faction))
+            .find_map(|(job, location, blueprint)| {
+                let material = blueprint.material()?;
+                let to = job_access_points(location.pos, job.job_type)
+                    .into_iter()
+                    .find_map(|[x, y, z]| terrain.get_partition_id_u32(x, y, z))?;
+
+                if has_item(to, material) {
+                    return None;
+                }
+
+                let from = q_zones
+                    .iter()
+                    .filter(|zone| zone.accepts_tags(&[material]))
+                    .flat_map(|zone| zone.blocks.iter())
+                    .find(|[x, y, z]| {
+                        terrain
+                            .get_partition_id_u32(*x, *y, *z)
+                            .is_some_and(|id| id != to && has_item(id, material))
+                            && is_reachable(
+                                &PartitionPathRequest {
+                                    start: pos,
+                                    goals: vec![[*x, *y, *z]],
+                                    flags: *flags,
+                                },
+                                &terrain,
+                                &graph,
+                            )
+                    })
+                    .and_then(|[x, y, z]| terrain.get_partition_id_u32(*x, *y, *z))?;
+
+                Some((material, from, to))
+            });
+
+        let Some(delivery) = delivery else {
+            *score = Score(0.);
+            continue;
+        };
+
+        scorer.delivery = Some(delivery);
+        *score = Score(0.1);
+    }
+}
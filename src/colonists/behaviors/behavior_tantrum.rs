@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use bevy::ecs::{
+    component::Component,
+    query::{With, Without},
+    system::{EntityCommands, Query},
+};
+
+use crate::colonists::{
+    Actor, ActorRef, Behavior, BehaviorNode, HasBehavior, Mood, Score, ScorerBuilder,
+    TaskMineBlock, TaskMoveTo, TaskTantrum,
+};
+
+#[derive(Component, Clone)]
+pub struct ScorerTantrum;
+
+impl ScorerBuilder for ScorerTantrum {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        cmd.insert(self.clone());
+    }
+
+    fn label(&self) -> String {
+        "Tantrum".to_string()
+    }
+
+    fn build(&self) -> Behavior {
+        Behavior::new(
+            "Tantrum",
+            BehaviorNode::Sequence(vec![
+                BehaviorNode::Task(Arc::new(TaskTantrum)),
+                BehaviorNode::Task(Arc::new(TaskMoveTo)),
+                BehaviorNode::Task(Arc::new(TaskMineBlock)),
+            ]),
+        )
+    }
+}
+
+/// A miserable colonist drops everything else to go break furniture
+pub fn score_tantrum(
+    q_actors: Query<&Mood, (With<Actor>, Without<HasBehavior>)>,
+    mut q_behaviors: Query<(&ActorRef, &mut Score), With<ScorerTantrum>>,
+) {
+    for (ActorRef(actor), mut score) in q_behaviors.iter_mut() {
+        let is_tantrum = q_actors.get(*actor).is_ok_and(|mood| mood.is_tantrum());
+
+        *score = Score(if is_tantrum { 0.9 } else { 0. });
+    }
+}
@@ -7,7 +7,8 @@ use bevy::ecs::{
 };
 
 use crate::colonists::{
-    Behavior, BehaviorNode, Score, ScorerBuilder, TaskIdle, TaskMoveTo, TaskPickRandomSpot,
+    Behavior, BehaviorNode, Score, ScorerBuilder, SequenceTask, TaskIdle, TaskMoveTo,
+    TaskPickRandomSpot,
 };
 
 #[derive(Component, Clone)]
@@ -25,14 +26,14 @@ impl ScorerBuilder for ScorerWander {
     fn build(&self) -> Behavior {
         Behavior::new(
             "Wander",
-            BehaviorNode::Sequence(vec![
-                BehaviorNode::Task(Arc::new(TaskPickRandomSpot)),
-                BehaviorNode::Task(Arc::new(TaskMoveTo)),
-                BehaviorNode::Task(Arc::new(TaskIdle {
+            BehaviorNode::Task(Arc::new(SequenceTask::new(vec![
+                Arc::new(TaskPickRandomSpot),
+                Arc::new(TaskMoveTo),
+                Arc::new(TaskIdle {
                     duration_s: 1.,
                     progress: 0.,
-                })),
-            ]),
+                }),
+            ]))),
         )
     }
 }
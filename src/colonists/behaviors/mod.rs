@@ -1,7 +1,27 @@
 mod behavior_build;
+mod behavior_chop;
+mod behavior_cook;
+mod behavior_eat;
+mod behavior_farm;
+mod behavior_guard;
+mod behavior_haul;
+mod behavior_light;
 mod behavior_mine;
+mod behavior_patrol;
+mod behavior_supply;
+mod behavior_tantrum;
 mod behavior_wander;
 
 pub use behavior_build::*;
+pub use behavior_chop::*;
+pub use behavior_cook::*;
+pub use behavior_eat::*;
+pub use behavior_farm::*;
+pub use behavior_guard::*;
+pub use behavior_haul::*;
+pub use behavior_light::*;
 pub use behavior_mine::*;
+pub use behavior_patrol::*;
+pub use behavior_supply::*;
+pub use behavior_tantrum::*;
 pub use behavior_wander::*;
@@ -6,34 +6,51 @@ use bevy::{
     ecs::{
         component::Component,
         event::{Event, EventReader},
-        query::With,
-        system::{Commands, Query, Res, ResMut},
+        system::{Commands, Res, ResMut},
     },
-    gltf::{Gltf, GltfMesh},
-    pbr::{Material, MaterialMeshBundle, PbrBundle, StandardMaterial},
+    pbr::StandardMaterial,
     prelude::default,
-    render::{
-        color::Color,
-        mesh::{skinning::SkinnedMesh, Mesh},
-        texture::Image,
-    },
+    render::{color::Color, texture::Image},
     scene::{Scene, SceneBundle},
     transform::components::Transform,
 };
 
+use bitflags::bitflags;
+
 use crate::HumanGltf;
 
 use super::{
-    Actor, Faller, Fatigue, Inventory, NavigationFlags, ScorerBuild, ScorerMine, ScorerWander,
+    Actor, AnimationState, CarryCapacity, FactionId, Faller, Fatigue, Health, Hunger, Inventory,
+    Mood, MovementStats, NavigationFlags, Relationships, SavedInventory, SavedRelationships,
+    ScorerBuild, ScorerChop, ScorerCook, ScorerEat, ScorerFarm, ScorerGuard, ScorerHaul,
+    ScorerLight, ScorerMine, ScorerPatrol, ScorerSupply, ScorerTantrum, ScorerWander, Skills,
     Thinker,
 };
 
 #[derive(Component, Default)]
 pub struct Colonist {}
 
+/// The colonist last clicked with the info tool
+#[derive(Component)]
+pub struct Selected;
+
+bitflags! {
+    /// Kinds of work a colonist is willing to do
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Component)]
+    pub struct ColonistFlags: u8 {
+        const NONE = 0;
+        const CAN_FARM = 1;
+    }
+}
+
 #[derive(Event)]
 pub struct SpawnColonistEvent {
     pub pos: [u32; 3],
+    pub faction: FactionId,
+    /// Opinions to restore, for colonists spawned from a save
+    pub relationships: Option<SavedRelationships>,
+    /// Carried items to restore, for colonists spawned from a save
+    pub inventory: Option<SavedInventory>,
 }
 
 pub fn on_spawn_colonist(
@@ -49,50 +66,74 @@ pub fn on_spawn_colonist(
         return;
     };
     for ev in ev_spawn_colonist.read() {
-        for i in 0..1 {
-            let texture: Handle<Image> = asset_server.load("textures/colonist.png");
+        let texture: Handle<Image> = asset_server.load("textures/colonist.png");
 
-            for material_handle in scene
-                .world
-                .query::<&Handle<StandardMaterial>>()
-                .iter(&scene.world)
-            {
-                let Some(material) = materials.get_mut(material_handle) else {
-                    continue;
-                };
-                material.unlit = true;
-                material.base_color = Color::WHITE;
-                material.base_color_texture = Some(texture.clone());
-            }
+        for material_handle in scene
+            .world
+            .query::<&Handle<StandardMaterial>>()
+            .iter(&scene.world)
+        {
+            let Some(material) = materials.get_mut(material_handle) else {
+                continue;
+            };
+            material.unlit = true;
+            material.base_color = Color::WHITE;
+            material.base_color_texture = Some(texture.clone());
+        }
+
+        let mut ecmd = cmd.spawn((
+            Name::new("Colonist"),
+            SceneBundle {
+                scene: human_gltf.0.clone(),
+                transform: Transform::from_xyz(
+                    ev.pos[0] as f32 + 0.5,
+                    ev.pos[1] as f32,
+                    ev.pos[2] as f32 + 0.5,
+                ),
+                ..default()
+            },
+            Fatigue {
+                value: 30.,
+                per_second: 5.,
+            },
+            Health::new(100.),
+            Actor,
+            Inventory::default(),
+            CarryCapacity::default(),
+            Skills::default(),
+            MovementStats::default(),
+            (Mood::default(), Colonist::default()),
+            ColonistFlags::CAN_FARM,
+            AnimationState::default(),
+            Thinker {
+                score_builders: vec![
+                    Arc::new(ScorerWander),
+                    Arc::new(ScorerMine::default()),
+                    Arc::new(ScorerChop::default()),
+                    Arc::new(ScorerBuild::default()),
+                    Arc::new(ScorerCook),
+                    Arc::new(ScorerEat),
+                    Arc::new(ScorerFarm::default()),
+                    Arc::new(ScorerPatrol::default()),
+                    Arc::new(ScorerGuard::default()),
+                    Arc::new(ScorerHaul::default()),
+                    Arc::new(ScorerSupply::default()),
+                    Arc::new(ScorerLight::default()),
+                    Arc::new(ScorerTantrum),
+                ],
+            },
+            Faller,
+            NavigationFlags::COLONIST,
+        ));
+
+        ecmd.insert((ev.faction, Relationships::default(), Hunger::default()));
+
+        if let Some(saved) = ev.relationships.clone() {
+            ecmd.insert(saved);
+        }
 
-            cmd.spawn((
-                Name::new("Colonist"),
-                SceneBundle {
-                    scene: human_gltf.0.clone(),
-                    transform: Transform::from_xyz(
-                        ev.pos[0] as f32 + 0.5,
-                        ev.pos[1] as f32,
-                        ev.pos[2] as f32 + 0.5,
-                    ),
-                    ..default()
-                },
-                Fatigue {
-                    value: 30.,
-                    per_second: 5.,
-                },
-                Actor,
-                Inventory::default(),
-                Colonist::default(),
-                Thinker {
-                    score_builders: vec![
-                        Arc::new(ScorerWander),
-                        Arc::new(ScorerMine::default()),
-                        Arc::new(ScorerBuild::default()),
-                    ],
-                },
-                Faller,
-                NavigationFlags::COLONIST,
-            ));
+        if let Some(saved) = ev.inventory.clone() {
+            ecmd.insert(saved);
         }
     }
 }
@@ -0,0 +1,49 @@
+use bevy::{
+    ecs::{component::Component, system::Resource},
+    utils::hashbrown::HashSet,
+};
+
+/// The side a colonist is on. Items get one too once a colonist of the
+/// faction stockpiles them, and jobs can be made for a single faction.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FactionId(pub u32);
+
+/// Colonists spawned by the player
+pub const PLAYER_FACTION: FactionId = FactionId(0);
+
+/// Colonists spawned with shift held, at war with the player's
+pub const RIVAL_FACTION: FactionId = FactionId(1);
+
+/// Whether something owned by `owner` may be used by `user`. Anything
+/// without an owner is fair game, owned things only go to their own faction.
+pub fn is_faction_allowed(owner: Option<&FactionId>, user: Option<&FactionId>) -> bool {
+    match owner {
+        Some(owner) => user == Some(owner),
+        None => true,
+    }
+}
+
+/// Pairs of factions that fight on sight, see `task_guard`. Factions are
+/// at peace unless set otherwise.
+#[derive(Resource, Default)]
+pub struct FactionRelations {
+    hostile: HashSet<(u32, u32)>,
+}
+
+fn key(a: FactionId, b: FactionId) -> (u32, u32) {
+    (a.0.min(b.0), a.0.max(b.0))
+}
+
+impl FactionRelations {
+    pub fn set_hostile(&mut self, a: FactionId, b: FactionId, is_hostile: bool) {
+        if is_hostile {
+            self.hostile.insert(key(a, b));
+        } else {
+            self.hostile.remove(&key(a, b));
+        }
+    }
+
+    pub fn is_hostile(&self, a: FactionId, b: FactionId) -> bool {
+        a != b && self.hostile.contains(&key(a, b))
+    }
+}
@@ -49,9 +49,7 @@ pub fn apply_falling(
         // remove the item from whatever partition it is in
         if let Some(in_partition) = opt_in_partition {
             let partition_id = in_partition.partition_id;
-            if let Some(partition) = graph.get_partition_mut(&partition_id) {
-                partition.items.remove(&entity);
-            }
+            graph.remove_item(&partition_id, &entity);
             ecmd.remove::<InPartition>();
         }
 
@@ -0,0 +1,369 @@
+use std::sync::Arc;
+
+use bevy::{
+    asset::{AssetServer, Assets, Handle},
+    core::Name,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::{With, Without},
+        system::{Commands, Local, Query, Res, ResMut, Resource},
+    },
+    hierarchy::DespawnRecursiveExt,
+    pbr::{MaterialMeshBundle, StandardMaterial},
+    prelude::default,
+    render::{color::Color, mesh::Mesh, view::Visibility},
+    time::Time,
+    transform::components::Transform,
+};
+
+use crate::{BlockType, Terrain};
+
+use super::{
+    Behavior, BehaviorNode, Colonist, Faller, HasBehavior, InInventory, InPartition,
+    InterruptBehavior, Inventory, Item, Job, NavigationGraph, TaskMoveTo, TaskPickRandomSpot,
+};
+
+const DAMAGE_TICK_S: f32 = 1.;
+/// Health regained each second out of harm's way, up to the maximum
+const HEAL_PER_S: f32 = 1.;
+
+#[derive(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+}
+
+/// Damage per second taken while standing in each kind of hostile block.
+#[derive(Resource)]
+pub struct EnvironmentalDamage {
+    pub magma: f32,
+    pub fire: f32,
+}
+
+impl Default for EnvironmentalDamage {
+    fn default() -> Self {
+        Self {
+            magma: 10.,
+            fire: 5.,
+        }
+    }
+}
+
+impl EnvironmentalDamage {
+    pub fn get(&self, block: BlockType) -> f32 {
+        match block {
+            BlockType::MAGMA => self.magma,
+            BlockType::CAMPFIRE => self.fire,
+            _ => 0.,
+        }
+    }
+}
+
+/// Guards attack these on sight
+#[derive(Component)]
+pub struct HostileEntity;
+
+#[derive(Event)]
+pub struct SpawnHostileEvent {
+    pub pos: [u32; 3],
+}
+
+/// Hostile creatures don't do anything yet besides getting in the way of
+/// guards, they are spawned with the hostile tool
+pub fn on_spawn_hostile(
+    mut cmd: Commands,
+    mut ev_spawn_hostile: EventReader<SpawnHostileEvent>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    let mesh: Handle<Mesh> = asset_server.load("meshes/sphere.obj");
+    let material = materials.add(StandardMaterial {
+        base_color: Color::RED,
+        unlit: true,
+        ..default()
+    });
+
+    for ev in ev_spawn_hostile.read() {
+        cmd.spawn((
+            Name::new("Hostile"),
+            MaterialMeshBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_xyz(
+                    ev.pos[0] as f32 + 0.5,
+                    ev.pos[1] as f32,
+                    ev.pos[2] as f32 + 0.5,
+                ),
+                ..default()
+            },
+            HostileEntity,
+            Health::new(30.),
+            Faller,
+        ));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct DeathCount(pub u32);
+
+/// Sent each time a block hurts something standing in or on it
+#[derive(Event)]
+pub struct DamagedByBlockEvent {
+    pub entity: Entity,
+    pub block: BlockType,
+    pub amount: f32,
+}
+
+#[derive(Event)]
+pub struct ColonistDiedEvent {
+    pub entity: Entity,
+    pub pos: [u32; 3],
+}
+
+pub fn apply_environmental_damage(
+    time: Res<Time>,
+    terrain: Res<Terrain>,
+    damage: Res<EnvironmentalDamage>,
+    mut timer: Local<f32>,
+    mut q_health: Query<(Entity, &Transform, &mut Health)>,
+    mut ev_damaged: EventWriter<DamagedByBlockEvent>,
+) {
+    *timer += time.delta_seconds();
+
+    if *timer < DAMAGE_TICK_S {
+        return;
+    }
+
+    *timer -= DAMAGE_TICK_S;
+
+    for (entity, transform, mut health) in q_health.iter_mut() {
+        let x = transform.translation.x as u32;
+        let y = transform.translation.y as u32;
+        let z = transform.translation.z as u32;
+
+        // standing in the block, or on top of it
+        let inside = terrain.get_block(x, y, z).block;
+        let below = if y > 0 {
+            terrain.get_block(x, y - 1, z).block
+        } else {
+            BlockType::OOB
+        };
+
+        let block = if damage.get(inside) >= damage.get(below) {
+            inside
+        } else {
+            below
+        };
+        let amount = damage.get(block) * DAMAGE_TICK_S;
+
+        if amount > 0. {
+            health.current -= amount;
+            ev_damaged.send(DamagedByBlockEvent {
+                entity,
+                block,
+                amount,
+            });
+        } else {
+            health.current = (health.current + HEAL_PER_S * DAMAGE_TICK_S).min(health.max);
+        }
+    }
+}
+
+/// Colonists hurt by a block drop what they are doing and get out of there
+pub fn flee_hazards(
+    mut cmd: Commands,
+    q_colonists: Query<Option<&HasBehavior>, (With<Colonist>, Without<InterruptBehavior>)>,
+    q_behaviors: Query<&Behavior>,
+    mut ev_damaged: EventReader<DamagedByBlockEvent>,
+) {
+    for ev in ev_damaged.read() {
+        let Ok(has_behavior) = q_colonists.get(ev.entity) else {
+            continue;
+        };
+
+        let is_fleeing = has_behavior
+            .and_then(|has_behavior| q_behaviors.get(has_behavior.behavior_entity).ok())
+            .is_some_and(|behavior| behavior.label == "Flee");
+
+        if is_fleeing {
+            continue;
+        }
+
+        let flee = Behavior::new(
+            "Flee",
+            BehaviorNode::Sequence(vec![
+                BehaviorNode::Task(Arc::new(TaskPickRandomSpot)),
+                BehaviorNode::Task(Arc::new(TaskMoveTo)),
+            ]),
+        );
+
+        cmd.entity(ev.entity).insert(InterruptBehavior::new(
+            &format!("hurt by {} for {:.0}", ev.block.name(), ev.amount),
+            Some(flee),
+        ));
+    }
+}
+
+pub fn colonist_death(
+    mut cmd: Commands,
+    terrain: Res<Terrain>,
+    mut graph: ResMut<NavigationGraph>,
+    mut deaths: ResMut<DeathCount>,
+    q_colonists: Query<
+        (
+            Entity,
+            &Transform,
+            &Health,
+            Option<&Inventory>,
+            Option<&HasBehavior>,
+        ),
+        With<Colonist>,
+    >,
+    mut q_items: Query<(&mut Transform, &mut Item, Option<&InInventory>), Without<Colonist>>,
+    mut q_jobs: Query<&mut Job>,
+    mut ev_died: EventWriter<ColonistDiedEvent>,
+) {
+    for (entity, transform, health, inventory, behavior) in q_colonists.iter() {
+        if health.current > 0. {
+            continue;
+        }
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        println!("colonist {} died", entity.index());
+
+        let partition_id = terrain.get_partition_id_u32(pos[0], pos[1], pos[2]);
+
+        // drop everything that was carried where the colonist fell
+        for item in inventory.iter().flat_map(|i| i.items.iter()) {
+            let Ok((mut item_transform, item_data, Some(_))) = q_items.get_mut(*item) else {
+                continue;
+            };
+
+            item_transform.translation = transform.translation;
+
+            let mut ecmd = cmd.entity(*item);
+            ecmd.remove::<InInventory>();
+            ecmd.insert(Visibility::Visible);
+
+            let Some(partition_id) = partition_id else {
+                continue;
+            };
+
+            if graph.add_item(&partition_id, *item, &item_data.tags) {
+                ecmd.insert(InPartition { partition_id });
+            }
+        }
+
+        // whatever it had claimed is up for grabs again
+        for (_, mut item, _) in q_items.iter_mut() {
+            if item.reserved == Some(entity) {
+                item.reserved = None;
+            }
+        }
+
+        for mut job in q_jobs.iter_mut() {
+            if job.assignee == Some(entity) {
+                job.assignee = None;
+            }
+        }
+
+        if let Some(behavior) = behavior {
+            cmd.entity(behavior.behavior_entity).despawn_recursive();
+        }
+
+        cmd.entity(entity).despawn_recursive();
+        deaths.0 += 1;
+        ev_died.send(ColonistDiedEvent { entity, pos });
+    }
+}
+
+pub fn hostile_death(mut cmd: Commands, q_hostiles: Query<(Entity, &Health), With<HostileEntity>>) {
+    for (entity, health) in q_hostiles.iter() {
+        if health.current <= 0. {
+            println!("hostile {} died", entity.index());
+            cmd.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{event::Events, schedule::IntoSystemConfigs, schedule::Schedule, world::World};
+
+    use crate::colonists::JobType;
+
+    use super::*;
+
+    #[test]
+    fn colonist_on_magma_dies_and_lets_go() {
+        let mut world = World::new();
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.set_block(2, 0, 2, BlockType::MAGMA);
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<Time>();
+        world.init_resource::<EnvironmentalDamage>();
+        world.init_resource::<DeathCount>();
+        world.init_resource::<Events<DamagedByBlockEvent>>();
+        world.init_resource::<Events<ColonistDiedEvent>>();
+
+        let health = Health::new(30.);
+        let seconds = (health.max / world.resource::<EnvironmentalDamage>().magma).ceil() as u32;
+        let colonist = world
+            .spawn((
+                Colonist::default(),
+                health,
+                Transform::from_xyz(2.5, 1., 2.5),
+            ))
+            .id();
+
+        let item = world
+            .spawn((
+                Item {
+                    tags: vec![],
+                    reserved: Some(colonist),
+                },
+                Transform::default(),
+            ))
+            .id();
+        let job = world
+            .spawn(Job {
+                job_type: JobType::Mine,
+                assignee: Some(colonist),
+                deadline: None,
+                waiting_for_material: false,
+                faction_id: None,
+            })
+            .id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((apply_environmental_damage, colonist_death).chain());
+
+        for _ in 0..seconds {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(DAMAGE_TICK_S));
+            schedule.run(&mut world);
+        }
+
+        assert!(world.get_entity(colonist).is_none());
+        assert_eq!(world.resource::<DeathCount>().0, 1);
+        assert_eq!(world.get::<Item>(item).unwrap().reserved, None);
+        assert_eq!(world.get::<Job>(job).unwrap().assignee, None);
+    }
+}
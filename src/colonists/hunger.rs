@@ -0,0 +1,131 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        system::{Query, Res},
+    },
+    time::Time,
+};
+
+use super::MovementStats;
+
+/// How much hunger rises each second, out of 100
+const HUNGER_PER_S: f32 = 0.1;
+/// Past this, eating beats most work, see `score_eat`
+pub const HUNGRY: f32 = 50.;
+/// Past this, the colonist is too weak to move at full speed
+pub const STARVING: f32 = 80.;
+/// Speed while starving
+const STARVING_SPEED_FACTOR: f32 = 0.5;
+const HUNGER_MAX: f32 = 100.;
+
+/// From 0, just ate, to 100, starved
+#[derive(Component, Default)]
+pub struct Hunger(pub f32);
+
+impl Hunger {
+    pub fn is_hungry(&self) -> bool {
+        self.0 >= HUNGRY
+    }
+
+    pub fn is_starving(&self) -> bool {
+        self.0 >= STARVING
+    }
+
+    pub fn eat(&mut self, amount: f32) {
+        self.0 = (self.0 - amount).max(0.);
+    }
+}
+
+/// Sent once each time a colonist's hunger runs all the way out
+#[derive(Event)]
+pub struct ColonistStarvingEvent {
+    pub entity: Entity,
+}
+
+pub fn tick_hunger(
+    time: Res<Time>,
+    mut q_hungers: Query<(Entity, &mut Hunger, &mut MovementStats)>,
+    mut ev_starving: EventWriter<ColonistStarvingEvent>,
+) {
+    for (entity, mut hunger, mut stats) in q_hungers.iter_mut() {
+        let was_starved = hunger.0 >= HUNGER_MAX;
+
+        hunger.0 = (hunger.0 + HUNGER_PER_S * time.delta_seconds()).min(HUNGER_MAX);
+
+        if !was_starved && hunger.0 >= HUNGER_MAX {
+            println!("colonist {} is starving!", entity.index());
+            ev_starving.send(ColonistStarvingEvent { entity });
+        }
+
+        stats.hunger_factor = if hunger.is_starving() {
+            STARVING_SPEED_FACTOR
+        } else {
+            1.
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{event::Events, system::RunSystemOnce, world::World};
+
+    use super::*;
+
+    /// Ticks hunger for `seconds`, returning the hunger, the speed factor and
+    /// how many starving events went out on the last tick
+    fn tick(world: &mut World, entity: Entity, seconds: f32) -> (f32, f32, usize) {
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        world.run_system_once(tick_hunger);
+
+        let starved = world
+            .resource_mut::<Events<ColonistStarvingEvent>>()
+            .drain()
+            .count();
+
+        (
+            world.get::<Hunger>(entity).unwrap().0,
+            world.get::<MovementStats>(entity).unwrap().hunger_factor,
+            starved,
+        )
+    }
+
+    #[test]
+    fn hunger_crosses_its_thresholds() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<ColonistStarvingEvent>>();
+        let colonist = world
+            .spawn((Hunger(HUNGRY - 1.), MovementStats::default()))
+            .id();
+
+        let (hunger, factor, _) = tick(&mut world, colonist, 5.);
+        assert!(!Hunger(hunger).is_hungry() && factor == 1.);
+
+        let (hunger, factor, _) = tick(&mut world, colonist, 5.);
+        assert!(Hunger(hunger).is_hungry() && !Hunger(hunger).is_starving());
+        assert_eq!(factor, 1.);
+
+        // on to starving, which slows the colonist down
+        let to_starving = (STARVING - hunger) / HUNGER_PER_S;
+        let (_, factor, starved) = tick(&mut world, colonist, to_starving + 1.);
+        assert_eq!(factor, STARVING_SPEED_FACTOR);
+        assert_eq!(starved, 0);
+
+        // running all the way out is announced once
+        let (hunger, _, starved) = tick(&mut world, colonist, 1000.);
+        assert_eq!((hunger, starved), (HUNGER_MAX, 1));
+        let (_, _, starved) = tick(&mut world, colonist, 10.);
+        assert_eq!(starved, 0);
+
+        // a meal brings the speed back
+        world.get_mut::<Hunger>(colonist).unwrap().eat(40.);
+        let (hunger, factor, _) = tick(&mut world, colonist, 0.);
+        assert!(Hunger(hunger).is_hungry() && factor == 1.);
+    }
+}
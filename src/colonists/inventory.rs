@@ -5,9 +5,13 @@ use bevy::{
         component::Component,
         entity::Entity,
         event::{Event, EventReader},
+        query::{Changed, Without},
         system::{Commands, Query, ResMut},
     },
     hierarchy::DespawnRecursiveExt,
+    render::view::Visibility,
+    transform::components::Transform,
+    utils::HashSet,
 };
 
 use super::{InPartition, NavigationGraph};
@@ -17,21 +21,103 @@ pub struct Inventory {
     pub items: Vec<Entity>,
 }
 
+#[derive(Debug)]
+pub struct InventoryFull;
+
+impl Inventory {
+    /// Add the item unless it would take the holder over its carry capacity
+    pub fn add_item(
+        &mut self,
+        item: Entity,
+        weight: u32,
+        capacity: &mut CarryCapacity,
+    ) -> std::result::Result<(), InventoryFull> {
+        if weight > capacity.remaining() {
+            return Err(InventoryFull);
+        }
+
+        self.items.push(item);
+        capacity.current += weight;
+
+        Ok(())
+    }
+}
+
+/// Total item weight a colonist can carry. `current` is recomputed from the
+/// inventory whenever it changes.
+#[derive(Component)]
+pub struct CarryCapacity {
+    pub max: u32,
+    pub current: u32,
+}
+
+impl CarryCapacity {
+    pub fn new(max: u32) -> Self {
+        Self { max, current: 0 }
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.max.saturating_sub(self.current)
+    }
+}
+
+impl Default for CarryCapacity {
+    fn default() -> Self {
+        Self::new(10)
+    }
+}
+
 #[derive(Component)]
 pub struct Item {
     pub tags: Vec<ItemTag>,
     pub reserved: Option<Entity>,
 }
 
+impl Item {
+    pub fn weight(&self) -> u32 {
+        self.tags.iter().map(|tag| tag.weight()).sum()
+    }
+}
+
 #[derive(Component)]
 pub struct InInventory {
     pub holder: Entity,
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum ItemTag {
     Pickaxe,
     Stone,
+    Wood,
+    RawFood,
+    CookedFood,
+    Coal,
+    Torch,
+    IronOre,
+    GoldOre,
+    /// Anything a colonist can eat, carried next to `RawFood` or `CookedFood`
+    Food,
+}
+
+impl ItemTag {
+    /// The maximum number of items with this tag a single inventory can hold
+    pub fn max_stack(&self) -> Option<u32> {
+        match self {
+            ItemTag::Wood => Some(5),
+            _ => None,
+        }
+    }
+
+    pub fn weight(&self) -> u32 {
+        match self {
+            ItemTag::Stone | ItemTag::IronOre | ItemTag::GoldOre => 4,
+            ItemTag::Pickaxe => 3,
+            ItemTag::Wood => 2,
+            ItemTag::RawFood | ItemTag::CookedFood | ItemTag::Coal | ItemTag::Torch => 1,
+            // only ever next to another tag that has the weight
+            ItemTag::Food => 0,
+        }
+    }
 }
 
 impl Display for ItemTag {
@@ -44,6 +130,10 @@ pub fn test_item_tags(all: &[ItemTag], test: &[ItemTag]) -> bool {
     test.iter().all(|tag| all.contains(tag))
 }
 
+pub fn get_max_stack(tags: &[ItemTag]) -> Option<u32> {
+    tags.iter().filter_map(|tag| tag.max_stack()).min()
+}
+
 #[derive(Event)]
 pub struct DestroyItemEvent {
     pub entity: Entity,
@@ -52,24 +142,169 @@ pub struct DestroyItemEvent {
 pub fn destroy_items(
     mut graph: ResMut<NavigationGraph>,
     mut cmd: Commands,
-    q_items: Query<&InPartition>,
+    q_items: Query<(Option<&InPartition>, Option<&InInventory>)>,
+    mut q_holders: Query<&mut Inventory>,
     mut ev_destroy_item: EventReader<DestroyItemEvent>,
 ) {
     for ev in ev_destroy_item.read() {
         println!("destroying item {}", ev.entity.index());
         cmd.entity(ev.entity).despawn_recursive();
 
-        let Ok(in_partition) = q_items.get(ev.entity) else {
+        let Ok((in_partition, in_inventory)) = q_items.get(ev.entity) else {
+            continue;
+        };
+
+        // used up while carried, like the material of a build
+        if let Some(in_inventory) = in_inventory {
+            if let Ok(mut inventory) = q_holders.get_mut(in_inventory.holder) {
+                inventory.items.retain(|e| *e != ev.entity);
+            }
+        }
+
+        let Some(in_partition) = in_partition else {
             continue;
         };
 
-        let Some(partition) = graph.get_partition_mut(&in_partition.partition_id) else {
+        if graph.get_partition(&in_partition.partition_id).is_none() {
             panic!("Missing partition!? {}", in_partition.partition_id);
         };
 
         println!("Removing item from partition");
-        if !partition.items.remove(&ev.entity) {
+        if !graph.remove_item(&in_partition.partition_id, &ev.entity) {
             println!("Item not here!");
         }
     }
 }
+
+pub fn update_carry_capacity(
+    q_items: Query<&Item>,
+    mut q_holders: Query<(&Inventory, &mut CarryCapacity), Changed<Inventory>>,
+) {
+    for (inventory, mut capacity) in q_holders.iter_mut() {
+        capacity.current = inventory
+            .items
+            .iter()
+            .filter_map(|e| q_items.get(*e).ok())
+            .map(|item| item.weight())
+            .sum();
+    }
+}
+
+/// Items a colonist carried when the world was saved, by their tags. They
+/// are spawned at `pos` when the save loads, and `restore_inventories` puts
+/// them back into the colonist's inventory.
+#[derive(Component, Clone)]
+pub struct SavedInventory {
+    pub pos: [u32; 3],
+    pub items: Vec<Vec<ItemTag>>,
+}
+
+pub fn restore_inventories(
+    mut cmd: Commands,
+    mut graph: ResMut<NavigationGraph>,
+    mut q_saved: Query<(Entity, &mut SavedInventory, &mut Inventory)>,
+    q_items: Query<(Entity, &Transform, &Item, Option<&InPartition>), Without<InInventory>>,
+) {
+    // inserted components only show up once the commands run
+    let mut claimed = HashSet::new();
+
+    for (holder, mut saved, mut inventory) in q_saved.iter_mut() {
+        let pos = saved.pos;
+
+        saved.items.retain(|tags| {
+            let found = q_items.iter().find(|(item, transform, item_data, _)| {
+                let translation = transform.translation;
+                let item_pos = [
+                    translation.x as u32,
+                    translation.y as u32,
+                    translation.z as u32,
+                ];
+
+                item_pos == pos
+                    && item_data.tags == *tags
+                    && item_data.reserved.is_none()
+                    && !claimed.contains(item)
+            });
+
+            // not spawned yet, try again next frame
+            let Some((item, _, _, in_partition)) = found else {
+                return true;
+            };
+
+            if let Some(in_partition) = in_partition {
+                graph.remove_item(&in_partition.partition_id, &item);
+            }
+
+            claimed.insert(item);
+            inventory.items.push(item);
+            cmd.entity(item)
+                .remove::<InPartition>()
+                .insert((Visibility::Hidden, InInventory { holder }));
+
+            false
+        });
+
+        if saved.items.is_empty() {
+            cmd.entity(holder).remove::<SavedInventory>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{system::RunSystemOnce, world::World};
+
+    use super::*;
+
+    fn spawn_item(world: &mut World, pos: [f32; 3], tags: Vec<ItemTag>) -> Entity {
+        world
+            .spawn((
+                Transform::from_xyz(pos[0], pos[1], pos[2]),
+                Item {
+                    tags,
+                    reserved: None,
+                },
+            ))
+            .id()
+    }
+
+    #[test]
+    fn saved_items_go_back_into_the_inventory() {
+        let mut world = World::new();
+        world.init_resource::<NavigationGraph>();
+
+        let colonist = world
+            .spawn((
+                Inventory::default(),
+                SavedInventory {
+                    pos: [3, 1, 3],
+                    items: vec![vec![ItemTag::Pickaxe], vec![ItemTag::Stone]],
+                },
+            ))
+            .id();
+
+        // one carried item, and lookalikes that aren't
+        let pickaxe = spawn_item(&mut world, [3.5, 1., 3.5], vec![ItemTag::Pickaxe]);
+        let elsewhere = spawn_item(&mut world, [5.5, 1., 3.5], vec![ItemTag::Stone]);
+
+        world.run_system_once(restore_inventories);
+
+        assert_eq!(
+            world.get::<Inventory>(colonist).unwrap().items,
+            vec![pickaxe]
+        );
+        assert_eq!(world.get::<InInventory>(pickaxe).unwrap().holder, colonist);
+        assert!(world.get::<InInventory>(elsewhere).is_none());
+        // still waiting for the stone
+        assert!(world.get::<SavedInventory>(colonist).is_some());
+
+        let stone = spawn_item(&mut world, [3.5, 1., 3.5], vec![ItemTag::Stone]);
+        world.run_system_once(restore_inventories);
+
+        assert_eq!(
+            world.get::<Inventory>(colonist).unwrap().items,
+            vec![pickaxe, stone]
+        );
+        assert!(world.get::<SavedInventory>(colonist).is_none());
+    }
+}
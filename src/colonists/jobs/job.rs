@@ -2,18 +2,25 @@ use bevy::{
     ecs::{
         component::Component,
         entity::Entity,
+        event::{Event, EventReader, EventWriter},
         query::{With, Without},
         system::{Commands, Query, Res},
     },
     hierarchy::DespawnRecursiveExt,
+    time::Time,
 };
 
-use crate::Terrain;
+use crate::{
+    colonists::{is_faction_allowed, FactionId, InterruptBehavior},
+    BlockType, Terrain,
+};
 
-#[derive(Clone, Debug, Copy, PartialEq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
 pub enum JobType {
     Mine,
     BuildWall,
+    Farm,
+    Haul,
 }
 
 #[derive(Component, Clone, Copy)]
@@ -22,10 +29,26 @@ pub struct JobMine;
 #[derive(Component, Clone, Copy)]
 pub struct JobBuild;
 
+#[derive(Component, Clone, Copy)]
+pub struct JobFarm;
+
 #[derive(Component, Clone, Copy)]
 pub struct Job {
     pub job_type: JobType,
     pub assignee: Option<Entity>,
+    /// Game time (in elapsed seconds) after which the job is given up on.
+    pub deadline: Option<f64>,
+    /// Set while none of the material needed for the job exists in the
+    /// world. The job is not offered to colonists until some shows up.
+    pub waiting_for_material: bool,
+    /// Only colonists of this faction may take the job, anyone if unset
+    pub faction_id: Option<FactionId>,
+}
+
+impl Job {
+    pub fn is_open_to(&self, faction: Option<&FactionId>) -> bool {
+        is_faction_allowed(self.faction_id.as_ref(), faction)
+    }
 }
 
 #[derive(Component)]
@@ -42,11 +65,16 @@ pub struct IsJobCancelled;
 #[derive(Component)]
 pub struct IsJobCompleted;
 
-#[derive(Component)]
-pub struct JobAssignment {
+#[derive(Event)]
+pub struct JobExpiredEvent {
     pub job: Entity,
+    pub assignee: Option<Entity>,
 }
 
+/// On a colonist while it holds a job
+#[derive(Component)]
+pub struct JobAssignment;
+
 pub fn job_accessibility(
     mut cmd: Commands,
     terrain: Res<Terrain>,
@@ -63,24 +91,25 @@ pub fn job_accessibility(
             .iter()
             .any(|g| terrain.get_partition_id_u32(g[0], g[1], g[2]).is_some());
 
-        let is_filled = !terrain
-            .get_block(
-                job_location.pos[0],
-                job_location.pos[1],
-                job_location.pos[2],
-            )
-            .is_empty();
+        let block = terrain.get_block(
+            job_location.pos[0],
+            job_location.pos[1],
+            job_location.pos[2],
+        );
+        let is_filled = !block.is_empty();
 
         let is_cancelled = match job.job_type {
+            // an undone designation clears the flag
             JobType::Mine => {
-                if !is_filled {
+                if !is_filled || !block.flag_mine {
                     cmd.entity(entity).try_insert(IsJobCancelled);
                     true
                 } else {
                     false
                 }
             }
-            JobType::BuildWall => {
+            // a haul is also off once something is built on the stockpile spot
+            JobType::BuildWall | JobType::Haul => {
                 if is_filled {
                     cmd.entity(entity).try_insert(IsJobCancelled);
                     true
@@ -88,9 +117,17 @@ pub fn job_accessibility(
                     false
                 }
             }
+            JobType::Farm => {
+                if block.block != BlockType::SOIL_RIPE {
+                    cmd.entity(entity).try_insert(IsJobCancelled);
+                    true
+                } else {
+                    false
+                }
+            }
         };
 
-        if !is_cancelled && is_accessible {
+        if !is_cancelled && is_accessible && !job.waiting_for_material {
             cmd.entity(entity).insert(IsJobAccessible);
         } else {
             cmd.entity(entity).remove::<IsJobAccessible>();
@@ -98,6 +135,53 @@ pub fn job_accessibility(
     }
 }
 
+pub fn check_job_deadlines(
+    mut cmd: Commands,
+    time: Res<Time>,
+    mut q_jobs: Query<(Entity, &mut Job), (Without<IsJobCancelled>, Without<IsJobCompleted>)>,
+    mut ev_job_expired: EventWriter<JobExpiredEvent>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for (entity, mut job) in q_jobs.iter_mut() {
+        let Some(deadline) = job.deadline else {
+            continue;
+        };
+
+        if deadline >= now {
+            continue;
+        }
+
+        ev_job_expired.send(JobExpiredEvent {
+            job: entity,
+            assignee: job.assignee,
+        });
+
+        if let Some(assignee) = job.assignee {
+            cmd.entity(assignee).remove::<JobAssignment>();
+            // drop whatever tree is still working on this job
+            cmd.entity(assignee)
+                .try_insert(InterruptBehavior::new("job expired", None));
+        }
+
+        job.assignee = None;
+        cmd.entity(entity).insert(IsJobCancelled);
+    }
+}
+
+pub fn log_expired_jobs(mut ev_job_expired: EventReader<JobExpiredEvent>) {
+    for ev in ev_job_expired.read() {
+        match ev.assignee {
+            Some(assignee) => println!(
+                "job {} expired while colonist {} held it",
+                ev.job.index(),
+                assignee.index()
+            ),
+            None => println!("job {} expired before anyone took it", ev.job.index()),
+        }
+    }
+}
+
 pub fn job_despawn_complete(mut cmd: Commands, q_jobs: Query<Entity, With<IsJobCompleted>>) {
     for e in q_jobs.iter() {
         cmd.entity(e).despawn_recursive();
@@ -114,7 +198,7 @@ pub fn job_access_points(pos: [u32; 3], job: JobType) -> Vec<[u32; 3]> {
     let [x, y, z] = pos;
 
     match job {
-        JobType::Mine => {
+        JobType::Mine | JobType::Farm => {
             let mut goals = vec![
                 [x + 1, y, z],
                 [x, y, z + 1],
@@ -190,5 +274,61 @@ pub fn job_access_points(pos: [u32; 3], job: JobType) -> Vec<[u32; 3]> {
 
             goals
         }
+        JobType::Haul => vec![pos],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{event::Events, system::RunSystemOnce, world::World};
+
+    use crate::colonists::{SpawnJobHaulEvent, HAUL_DEADLINE_S};
+
+    use super::*;
+
+    #[test]
+    fn expired_job_is_released_the_same_frame() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<SpawnJobHaulEvent>>();
+        world.init_resource::<Events<JobExpiredEvent>>();
+
+        let item = world.spawn_empty().id();
+        world.send_event(SpawnJobHaulEvent {
+            item,
+            pos: [1, 1, 1],
+        });
+        world.run_system_once(crate::colonists::on_spawn_job_haul);
+
+        let job = world.query_filtered::<Entity, With<Job>>().single(&world);
+        let colonist = world.spawn(JobAssignment).id();
+        world.get_mut::<Job>(job).unwrap().assignee = Some(colonist);
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f64(HAUL_DEADLINE_S - 1.));
+        world.run_system_once(check_job_deadlines);
+
+        assert_eq!(world.get::<Job>(job).unwrap().assignee, Some(colonist));
+        assert!(world.get::<IsJobCancelled>(job).is_none());
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(2));
+        world.run_system_once(check_job_deadlines);
+
+        assert_eq!(world.get::<Job>(job).unwrap().assignee, None);
+        assert!(world.get::<IsJobCancelled>(job).is_some());
+        assert!(world.get::<JobAssignment>(colonist).is_none());
+        assert!(world.get::<InterruptBehavior>(colonist).is_some());
+
+        let expired = world.resource::<Events<JobExpiredEvent>>();
+        let mut reader = expired.get_reader();
+        let events = reader.read(expired).collect::<Vec<_>>();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].job, job);
+        assert_eq!(events[0].assignee, Some(colonist));
     }
 }
@@ -1,20 +1,51 @@
 use bevy::ecs::{
+    component::Component,
     event::{Event, EventReader},
-    system::{Commands, ResMut},
+    query::{Added, With},
+    system::{Commands, Query, Res, ResMut},
 };
 
-use crate::{BlockType, Terrain};
+use crate::{
+    colonists::{InPartition, Item, ItemTag, NavigationGraph},
+    BlockType, Terrain,
+};
 
 use super::{Job, JobBuild, JobLocation, JobType};
 
 #[derive(Event)]
 pub struct SpawnJobBuildEvent {
     pub pos: [u32; 3],
+    pub block: BlockType,
+}
+
+/// The block a build job will place once the material is brought over.
+#[derive(Component, Clone, Copy)]
+pub struct BlueprintWall {
+    pub block_type: BlockType,
+}
+
+impl BlueprintWall {
+    /// The item consumed to build this block
+    pub fn material(&self) -> Option<ItemTag> {
+        match self.block_type {
+            BlockType::STONE | BlockType::ASHLAR | BlockType::ASHLAR_LARGE => Some(ItemTag::Stone),
+            BlockType::LOG | BlockType::WOOD | BlockType::LADDER => Some(ItemTag::Wood),
+            stairs if stairs.is_stairs() => Some(ItemTag::Stone),
+            BlockType::REBAR_STONE => Some(ItemTag::IronOre),
+            _ => None,
+        }
+    }
+
+    pub fn is_material_available(&self, graph: &NavigationGraph) -> bool {
+        self.material()
+            .is_some_and(|tag| !graph.items_of_type(&[tag], 1).is_empty())
+    }
 }
 
 pub fn on_spawn_job_build(
     mut cmd: Commands,
     mut terrain: ResMut<Terrain>,
+    graph: Res<NavigationGraph>,
     mut ev_spawn_job_mine: EventReader<SpawnJobBuildEvent>,
 ) {
     for ev in ev_spawn_job_mine.read() {
@@ -25,15 +56,104 @@ pub fn on_spawn_job_build(
             continue;
         }
 
-        terrain.set_block_type(ev.pos[0], ev.pos[1], ev.pos[2], BlockType::STONE);
+        terrain.set_block(ev.pos[0], ev.pos[1], ev.pos[2], ev.block);
+
+        let blueprint = BlueprintWall {
+            block_type: ev.block,
+        };
 
         cmd.spawn((
             Job {
                 job_type: JobType::BuildWall,
                 assignee: None,
+                deadline: None,
+                waiting_for_material: !blueprint.is_material_available(&graph),
+                faction_id: None,
             },
             JobBuild,
             JobLocation { pos: ev.pos },
+            blueprint,
         ));
     }
 }
+
+/// Wakes up build jobs that were waiting on material once new items land in
+/// the world.
+pub fn check_blueprint_materials(
+    graph: Res<NavigationGraph>,
+    q_new_items: Query<(), (With<Item>, Added<InPartition>)>,
+    mut q_jobs: Query<(&mut Job, &BlueprintWall)>,
+) {
+    if q_new_items.is_empty() {
+        return;
+    }
+
+    for (mut job, blueprint) in q_jobs.iter_mut() {
+        if job.waiting_for_material && blueprint.is_material_available(&graph) {
+            job.waiting_for_material = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{entity::Entity, event::Events, system::RunSystemOnce, world::World};
+
+    use crate::colonists::{InPartition, NavigationFlags};
+
+    use super::*;
+
+    fn build_jobs(world: &mut World) -> Vec<(Entity, bool)> {
+        world
+            .query::<(Entity, &Job)>()
+            .iter(world)
+            .map(|(e, job)| (e, job.waiting_for_material))
+            .collect()
+    }
+
+    #[test]
+    fn blueprint_waits_for_stone() {
+        let mut world = World::new();
+        world.insert_resource(Terrain::new(1, 1, 1, 4).unwrap());
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<Events<SpawnJobBuildEvent>>();
+
+        world.send_event(SpawnJobBuildEvent {
+            pos: [1, 1, 1],
+            block: BlockType::STONE,
+        });
+        world.run_system_once(on_spawn_job_build);
+
+        // no stone anywhere, so nobody should pick the job up yet
+        let jobs = build_jobs(&mut world);
+        assert_eq!(jobs.len(), 1);
+        assert!(jobs[0].1);
+
+        // stone that isn't in a partition doesn't count
+        world.spawn(Item {
+            tags: vec![ItemTag::Stone],
+            reserved: None,
+        });
+        world.run_system_once(check_blueprint_materials);
+        assert!(build_jobs(&mut world)[0].1);
+
+        let mut graph = world.resource_mut::<NavigationGraph>();
+        let region_id = graph.create_region(NavigationFlags::COLONIST);
+        let partition_id = graph.create_partition(region_id, 0, NavigationFlags::COLONIST);
+        let stone = world
+            .spawn((
+                Item {
+                    tags: vec![ItemTag::Stone],
+                    reserved: None,
+                },
+                InPartition { partition_id },
+            ))
+            .id();
+        world
+            .resource_mut::<NavigationGraph>()
+            .add_item(&partition_id, stone, &[ItemTag::Stone]);
+
+        world.run_system_once(check_blueprint_materials);
+        assert!(!build_jobs(&mut world)[0].1);
+    }
+}
@@ -0,0 +1,27 @@
+use bevy::ecs::{
+    event::{Event, EventReader},
+    system::Commands,
+};
+
+use super::{Job, JobFarm, JobLocation, JobType};
+
+#[derive(Event)]
+pub struct SpawnJobFarmEvent {
+    pub pos: [u32; 3],
+}
+
+pub fn on_spawn_job_farm(mut cmd: Commands, mut ev_spawn_job_farm: EventReader<SpawnJobFarmEvent>) {
+    for ev in ev_spawn_job_farm.read() {
+        cmd.spawn((
+            Job {
+                job_type: JobType::Farm,
+                assignee: None,
+                deadline: None,
+                waiting_for_material: false,
+                faction_id: None,
+            },
+            JobFarm,
+            JobLocation { pos: ev.pos },
+        ));
+    }
+}
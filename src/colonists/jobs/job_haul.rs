@@ -0,0 +1,50 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader},
+        system::{Commands, Res},
+    },
+    time::Time,
+};
+
+use super::{Job, JobLocation, JobType};
+
+/// Seconds a haul job stays open. A haul nobody gets done by then is given
+/// up on, and `scan_stockpiles` hands the item out again, maybe to someone
+/// closer or to a spot that opened up since.
+pub const HAUL_DEADLINE_S: f64 = 120.;
+
+/// Carry `item` to the stockpile position in the job's `JobLocation`
+#[derive(Component, Clone, Copy)]
+pub struct JobHaul {
+    pub item: Entity,
+}
+
+#[derive(Event)]
+pub struct SpawnJobHaulEvent {
+    pub item: Entity,
+    pub pos: [u32; 3],
+}
+
+pub fn on_spawn_job_haul(
+    mut cmd: Commands,
+    time: Res<Time>,
+    mut ev_spawn_job_haul: EventReader<SpawnJobHaulEvent>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for ev in ev_spawn_job_haul.read() {
+        cmd.spawn((
+            Job {
+                job_type: JobType::Haul,
+                assignee: None,
+                deadline: Some(now + HAUL_DEADLINE_S),
+                waiting_for_material: false,
+                faction_id: None,
+            },
+            JobHaul { item: ev.item },
+            JobLocation { pos: ev.pos },
+        ));
+    }
+}
@@ -1,17 +1,65 @@
-use bevy::ecs::{
-    event::{Event, EventReader},
-    system::{Commands, ResMut},
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader},
+        query::With,
+        system::{Commands, Local, Query, Res, ResMut},
+    },
+    gizmos::gizmos::Gizmos,
+    math::Vec3,
+    render::color::Color,
+    time::Time,
+    transform::components::Transform,
 };
 
 use crate::Terrain;
 
-use super::{Job, JobLocation, JobMine, JobType};
+use super::{job_access_points, Job, JobLocation, JobMine, JobType};
+
+const MINE_AREA_TICK_S: f32 = 0.5;
+/// Open mine jobs per designated area at a time, so colonists spread over
+/// the area instead of all queueing up for one corner
+const MAX_MINE_AREA_JOBS: usize = 4;
 
 #[derive(Event)]
 pub struct SpawnJobMineEvent {
     pub pos: [u32; 3],
 }
 
+/// Flag every solid block in the box for mining, in one terrain edit
+#[derive(Event)]
+pub struct DesignateMineEvent {
+    pub min: [u32; 3],
+    pub max: [u32; 3],
+}
+
+/// A box designated for mining, from `min` to `max` inclusive. Its blocks
+/// are handed out as mine jobs a few at a time, see `tick_mine_areas`.
+#[derive(Component)]
+pub struct TaskMineArea(pub [u32; 3], pub [u32; 3]);
+
+/// Blocks of a `TaskMineArea` that are not mined yet, out of all of them
+#[derive(Component)]
+pub struct MineAreaProgress {
+    pub remaining: u32,
+    pub total: u32,
+}
+
+impl MineAreaProgress {
+    fn color(&self) -> Color {
+        let done = 1. - self.remaining as f32 / self.total.max(1) as f32;
+        Color::rgb(1. - done, done, 0.)
+    }
+}
+
+#[derive(Component)]
+pub struct MineAreaQueue {
+    /// Flagged blocks without a job yet, sorted by y so the top is dug first
+    pub pending: Vec<[u32; 3]>,
+    pub jobs: Vec<Entity>,
+}
+
 pub fn on_spawn_job_mine(
     mut terrain: ResMut<Terrain>,
     mut cmd: Commands,
@@ -28,9 +76,135 @@ pub fn on_spawn_job_mine(
             Job {
                 job_type: JobType::Mine,
                 assignee: None,
+                deadline: None,
+                waiting_for_material: false,
+                faction_id: None,
             },
             JobMine,
             JobLocation { pos: ev.pos },
         ));
     }
 }
+
+pub fn on_designate_mine(
+    mut terrain: ResMut<Terrain>,
+    mut cmd: Commands,
+    mut ev_designate_mine: EventReader<DesignateMineEvent>,
+) {
+    for ev in ev_designate_mine.read() {
+        let positions =
+            terrain.record_batch(|terrain| terrain.set_mine_flag_region(ev.min, ev.max, true));
+
+        spawn_mine_area(&mut cmd, ev.min, ev.max, positions);
+    }
+}
+
+/// Hand out the flagged `positions` of the box as mine jobs, see
+/// `tick_mine_areas`
+pub fn spawn_mine_area(
+    cmd: &mut Commands,
+    min: [u32; 3],
+    max: [u32; 3],
+    mut positions: Vec<[u32; 3]>,
+) {
+    if positions.is_empty() {
+        return;
+    }
+
+    positions.sort_by_key(|pos| pos[1]);
+
+    let total = positions.len() as u32;
+
+    cmd.spawn((
+        TaskMineArea(min, max),
+        MineAreaProgress {
+            remaining: total,
+            total,
+        },
+        MineAreaQueue {
+            pending: positions,
+            jobs: vec![],
+        },
+    ));
+}
+
+/// Keeps a few mine jobs open for each designated area, topmost blocks
+/// first. Blocks with nowhere to stand stay queued and are retried once
+/// digging (or anything else) opens up a partition next to them. Blocks
+/// that lost their flag, e.g. through undo, are dropped.
+pub fn tick_mine_areas(
+    mut cmd: Commands,
+    time: Res<Time>,
+    terrain: Res<Terrain>,
+    mut timer: Local<f32>,
+    q_jobs: Query<(), With<Job>>,
+    mut q_areas: Query<(Entity, &mut MineAreaQueue, &mut MineAreaProgress)>,
+) {
+    *timer += time.delta_seconds();
+
+    if *timer < MINE_AREA_TICK_S {
+        return;
+    }
+
+    *timer = 0.;
+
+    for (entity, mut queue, mut progress) in q_areas.iter_mut() {
+        queue.jobs.retain(|job| q_jobs.contains(*job));
+        queue.pending.retain(|&[x, y, z]| {
+            let block = terrain.get_block(x, y, z);
+            !block.is_empty() && block.flag_mine
+        });
+
+        let mut idx = queue.pending.len();
+
+        while idx > 0 && queue.jobs.len() < MAX_MINE_AREA_JOBS {
+            idx -= 1;
+
+            let pos = queue.pending[idx];
+            let is_accessible = job_access_points(pos, JobType::Mine)
+                .iter()
+                .any(|g| terrain.get_partition_id_u32(g[0], g[1], g[2]).is_some());
+
+            if !is_accessible {
+                continue;
+            }
+
+            queue.pending.remove(idx);
+
+            let job = cmd
+                .spawn((
+                    Job {
+                        job_type: JobType::Mine,
+                        assignee: None,
+                        deadline: None,
+                        waiting_for_material: false,
+                        faction_id: None,
+                    },
+                    JobMine,
+                    JobLocation { pos },
+                ))
+                .id();
+
+            queue.jobs.push(job);
+        }
+
+        progress.remaining = (queue.pending.len() + queue.jobs.len()) as u32;
+
+        if progress.remaining == 0 {
+            cmd.entity(entity).despawn();
+        }
+    }
+}
+
+/// Areas are drawn red, turning green as their blocks are mined out
+pub fn mine_area_gizmos(mut gizmos: Gizmos, q_areas: Query<(&TaskMineArea, &MineAreaProgress)>) {
+    for (TaskMineArea(min, max), progress) in q_areas.iter() {
+        let min = Vec3::new(min[0] as f32, min[1] as f32, min[2] as f32);
+        let max = Vec3::new(max[0] as f32, max[1] as f32, max[2] as f32) + Vec3::ONE;
+
+        gizmos.cuboid(
+            Transform::from_translation((min + max) / 2.).with_scale(max - min),
+            progress.color(),
+        );
+    }
+}
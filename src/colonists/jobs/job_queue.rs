@@ -0,0 +1,310 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    ecs::{
+        entity::Entity,
+        query::{Added, Changed, Or},
+        removal_detection::RemovedComponents,
+        system::{Query, ResMut, Resource},
+    },
+    utils::hashbrown::HashMap,
+};
+
+use super::{IsJobCancelled, IsJobCompleted, Job, JobType};
+
+/// Jobs a scorer looks at from the front of its bucket. The nearest of
+/// these wins, so the cost of scoring no longer grows with the job count.
+pub const JOB_CANDIDATES_PER_SCORE: usize = 32;
+/// Seconds a job that an actor could not reach sits out before it is
+/// offered again
+const JOB_UNREACHABLE_COOLDOWN_S: f64 = 2.;
+
+/// Open jobs, one bucket per job type, oldest first. Only jobs nobody holds
+/// are queued, taking a job out of the queue is what `sync_job_queue` does
+/// when its `Job::assignee` is set.
+#[derive(Resource, Default)]
+pub struct JobQueue {
+    buckets: HashMap<JobType, VecDeque<Entity>>,
+    /// Jobs skipped as unreachable, and the time they are offered again
+    cooldowns: HashMap<Entity, f64>,
+}
+
+impl JobQueue {
+    fn push(&mut self, job_type: JobType, job: Entity) {
+        let bucket = self.buckets.entry(job_type).or_default();
+
+        if !bucket.contains(&job) {
+            bucket.push_back(job);
+        }
+    }
+
+    fn remove(&mut self, job: Entity) {
+        for bucket in self.buckets.values_mut() {
+            bucket.retain(|queued| *queued != job);
+        }
+
+        self.cooldowns.remove(&job);
+    }
+
+    /// The first few jobs of a type that aren't cooling down, oldest first
+    pub fn candidates(&self, job_type: JobType, now: f64) -> Vec<Entity> {
+        let Some(bucket) = self.buckets.get(&job_type) else {
+            return vec![];
+        };
+
+        bucket
+            .iter()
+            .filter(|job| self.cooldowns.get(*job).is_none_or(|until| *until <= now))
+            .take(JOB_CANDIDATES_PER_SCORE)
+            .copied()
+            .collect()
+    }
+
+    /// Sends an unreachable job to the back of its bucket and keeps it out
+    /// of `candidates` for a while, so it doesn't crowd out reachable ones.
+    pub fn defer(&mut self, job_type: JobType, job: Entity, now: f64) {
+        let Some(bucket) = self.buckets.get_mut(&job_type) else {
+            return;
+        };
+
+        let Some(idx) = bucket.iter().position(|queued| *queued == job) else {
+            return;
+        };
+
+        bucket.remove(idx);
+        bucket.push_back(job);
+        self.cooldowns.insert(job, now + JOB_UNREACHABLE_COOLDOWN_S);
+    }
+}
+
+/// Keeps the queue in step with the jobs. New jobs and jobs handed back by
+/// `task_job_unassign` are queued, jobs that got an assignee, were finished
+/// or cancelled, or were despawned leave it.
+#[allow(clippy::type_complexity)]
+pub fn sync_job_queue(
+    mut queue: ResMut<JobQueue>,
+    q_changed: Query<
+        (
+            Entity,
+            &Job,
+            Option<&IsJobCancelled>,
+            Option<&IsJobCompleted>,
+        ),
+        Or<(Changed<Job>, Added<IsJobCancelled>, Added<IsJobCompleted>)>,
+    >,
+    mut removed: RemovedComponents<Job>,
+) {
+    for (entity, job, cancelled, completed) in q_changed.iter() {
+        if job.assignee.is_none() && cancelled.is_none() && completed.is_none() {
+            queue.push(job.job_type, entity);
+        } else {
+            queue.remove(entity);
+        }
+    }
+
+    for entity in removed.read() {
+        queue.remove(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use bevy::{
+        ecs::{schedule::Schedule, world::World},
+        utils::hashbrown::HashSet,
+    };
+
+    use crate::{colonists::JobLocation, common::Distance};
+
+    use super::*;
+
+    const JOB_TYPES: [JobType; 3] = [JobType::Mine, JobType::BuildWall, JobType::Farm];
+
+    fn job(job_type: JobType) -> Job {
+        Job {
+            job_type,
+            assignee: None,
+            deadline: None,
+            waiting_for_material: false,
+            faction_id: None,
+        }
+    }
+
+    /// 2000 open jobs of mixed types spread over a 64x64 area
+    fn spawn_jobs(world: &mut World) -> Vec<Entity> {
+        (0..2000u32)
+            .map(|i| {
+                world
+                    .spawn((
+                        job(JOB_TYPES[i as usize % JOB_TYPES.len()]),
+                        JobLocation {
+                            pos: [i * 7 % 64, 10, i * 13 % 64],
+                        },
+                    ))
+                    .id()
+            })
+            .collect()
+    }
+
+    /// A world with an empty queue, and a schedule that syncs it. Kept
+    /// across runs so change detection only sees what changed in between.
+    fn queue_world() -> (World, Schedule) {
+        let mut world = World::new();
+        world.init_resource::<JobQueue>();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(sync_job_queue);
+
+        (world, schedule)
+    }
+
+    /// Every job is in at most one bucket, once, and a job is queued exactly
+    /// when it's open: no assignee, not cancelled and not completed.
+    fn assert_invariants(world: &mut World) {
+        let queue = world.resource::<JobQueue>();
+        let mut queued = HashSet::new();
+
+        for (job_type, bucket) in queue.buckets.iter() {
+            for job in bucket.iter() {
+                assert!(queued.insert(*job), "{:?} queued twice", job);
+
+                let entity = world.entity(*job);
+                assert_eq!(entity.get::<Job>().unwrap().job_type, *job_type);
+            }
+        }
+
+        let mut q_jobs = world.query::<(
+            Entity,
+            &Job,
+            Option<&IsJobCancelled>,
+            Option<&IsJobCompleted>,
+        )>();
+
+        for (entity, job, cancelled, completed) in q_jobs.iter(world) {
+            let is_open = job.assignee.is_none() && cancelled.is_none() && completed.is_none();
+            assert_eq!(queued.contains(&entity), is_open, "{:?}", entity);
+        }
+
+        assert!(queued.iter().all(|job| world.get_entity(*job).is_some()));
+    }
+
+    #[test]
+    fn queue_follows_the_jobs() {
+        let (mut world, mut sync) = queue_world();
+        let jobs = spawn_jobs(&mut world);
+        let colonist = world.spawn_empty().id();
+
+        sync.run(&mut world);
+        assert_invariants(&mut world);
+
+        // hand out every third job
+        for job in jobs.iter().step_by(3) {
+            world.get_mut::<Job>(*job).unwrap().assignee = Some(colonist);
+        }
+        sync.run(&mut world);
+        assert_invariants(&mut world);
+
+        // cancel and complete some, assigned or not
+        for job in jobs.iter().step_by(5) {
+            world.entity_mut(*job).insert(IsJobCancelled);
+        }
+        for job in jobs.iter().skip(1).step_by(7) {
+            world.entity_mut(*job).insert(IsJobCompleted);
+        }
+        sync.run(&mut world);
+        assert_invariants(&mut world);
+
+        // hand back half of the assigned jobs, like `task_job_unassign`
+        for job in jobs.iter().step_by(6) {
+            world.get_mut::<Job>(*job).unwrap().assignee = None;
+        }
+        sync.run(&mut world);
+        assert_invariants(&mut world);
+
+        // despawn the finished ones
+        for job in jobs.iter().skip(1).step_by(7) {
+            world.despawn(*job);
+        }
+        sync.run(&mut world);
+        assert_invariants(&mut world);
+    }
+
+    #[test]
+    fn deferred_jobs_sit_out() {
+        let (mut world, mut sync) = queue_world();
+        let jobs = (0..3)
+            .map(|_| world.spawn(job(JobType::Mine)).id())
+            .collect::<Vec<_>>();
+        sync.run(&mut world);
+
+        let mut queue = world.resource_mut::<JobQueue>();
+        assert_eq!(queue.candidates(JobType::Mine, 0.), jobs);
+
+        queue.defer(JobType::Mine, jobs[0], 0.);
+        assert_eq!(queue.candidates(JobType::Mine, 1.), jobs[1..]);
+
+        // offered again after the cooldown, from the back of the bucket
+        assert_eq!(
+            queue.candidates(JobType::Mine, JOB_UNREACHABLE_COOLDOWN_S),
+            [jobs[1], jobs[2], jobs[0]]
+        );
+    }
+
+    /// `cargo test job_queue_bench -- --ignored --nocapture`
+    ///
+    /// 50 idle colonists each picking the nearest open mine job out of 2000
+    /// jobs, by scanning every job entity like the scorers used to, and from
+    /// the queue's candidates.
+    #[test]
+    #[ignore]
+    fn job_queue_bench() {
+        let (mut world, mut sync) = queue_world();
+        spawn_jobs(&mut world);
+        sync.run(&mut world);
+
+        let colonists = (0..50u32)
+            .map(|i| [i * 11 % 64, 10, i * 5 % 64])
+            .collect::<Vec<_>>();
+        let mut q_jobs = world.query::<(Entity, &Job, &JobLocation)>();
+
+        let nearest = |[x, y, z]: [u32; 3], jobs: &mut dyn Iterator<Item = (Entity, [u32; 3])>| {
+            jobs.map(|(e, [jx, jy, jz])| {
+                let distance = Distance::diagonal(
+                    [x as i32, y as i32, z as i32],
+                    [jx as i32, jy as i32, jz as i32],
+                );
+                (distance, e)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, e)| e)
+        };
+
+        let start = Instant::now();
+        for pos in colonists.iter() {
+            let mut jobs = q_jobs
+                .iter(&world)
+                .filter(|(_, job, _)| job.job_type == JobType::Mine && job.assignee.is_none())
+                .map(|(e, _, location)| (e, location.pos));
+            assert!(nearest(*pos, &mut jobs).is_some());
+        }
+        let scan = start.elapsed();
+
+        let queue = world.resource::<JobQueue>();
+        let start = Instant::now();
+        for pos in colonists.iter() {
+            let mut jobs = queue
+                .candidates(JobType::Mine, 0.)
+                .into_iter()
+                .map(|e| (e, world.get::<JobLocation>(e).unwrap().pos));
+            assert!(nearest(*pos, &mut jobs).is_some());
+        }
+        let queued = start.elapsed();
+
+        println!(
+            "2000 jobs, 50 colonists: scan {:?}, queue {:?}",
+            scan, queued
+        );
+    }
+}
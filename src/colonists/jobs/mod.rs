@@ -1,7 +1,13 @@
 mod job;
 mod job_build;
+mod job_farm;
+mod job_haul;
 mod job_mine;
+mod job_queue;
 
 pub use job::*;
 pub use job_build::*;
+pub use job_farm::*;
+pub use job_haul::*;
 pub use job_mine::*;
+pub use job_queue::*;
@@ -1,29 +1,53 @@
+mod animation;
 mod behavior;
 mod behavior_pick;
 mod behaviors;
 mod colonist;
+mod faction;
 mod falling;
 mod fatigue;
+mod health;
+mod hunger;
 mod inventory;
 mod jobs;
+mod mood;
 mod movement;
 mod partition;
 mod partitioning;
+mod path_cache;
 mod pathfinding;
+mod relationships;
+mod room;
 mod scorer;
+mod skills;
+mod stockpile;
+mod task_scheduler;
 mod tasks;
+mod thought_bubble;
 
+pub use animation::*;
 pub use behavior::*;
 pub use behavior_pick::*;
 pub use behaviors::*;
 pub use colonist::*;
+pub use faction::*;
 pub use falling::*;
 pub use fatigue::*;
+pub use health::*;
+pub use hunger::*;
 pub use inventory::*;
 pub use jobs::*;
+pub use mood::*;
 pub use movement::*;
 pub use partition::*;
 pub use partitioning::*;
+pub use path_cache::*;
 pub use pathfinding::*;
+pub use relationships::*;
+pub use room::*;
 pub use scorer::*;
+pub use skills::*;
+pub use stockpile::*;
+pub use task_scheduler::*;
 pub use tasks::*;
+pub use thought_bubble::*;
@@ -0,0 +1,224 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::With,
+        system::{Local, Query, Res},
+    },
+    time::Time,
+    transform::components::Transform,
+};
+
+use crate::{BlockType, Terrain};
+
+use super::{
+    Colonist, ColonistDiedEvent, ColonistStarvingEvent, Fatigue, Hunger, MovementStats,
+    Relationships, Rooms,
+};
+
+const MOOD_TICK_S: f32 = 1.;
+/// Fraction of the way mood moves toward its target each tick
+const MOOD_DRIFT: f32 = 0.2;
+/// Colonists this close keep each other company
+const SOCIAL_RANGE: f32 = 4.;
+/// Mood added to company per point of average opinion of everyone in
+/// `SOCIAL_RANGE`, disliked company takes it away
+const OPINION_MOOD: f32 = 0.2;
+/// Mood lost by every colonist when one of them dies
+const GRIEF: f32 = 0.3;
+/// Mood lost by a colonist whose hunger runs all the way out
+const STARVED: f32 = 0.5;
+/// Rotten blocks this close to a colonist spoil their mood
+const ROT_RANGE: i32 = 2;
+/// Mood lost per rotten block in range, and the most it can cost in total
+const ROT_MOOD: f32 = 0.1;
+const ROT_MOOD_MAX: f32 = 0.3;
+/// Below this a colonist throws a tantrum
+pub const MOOD_TANTRUM: f32 = -0.8;
+
+/// How a colonist feels, from -1 (miserable) to 1 (happy)
+#[derive(Component, Default, Clone, Copy)]
+pub struct Mood {
+    pub value: f32,
+}
+
+impl Mood {
+    /// Multiplier on movement and work speed, 0.75 when miserable and 1.25
+    /// when happy
+    pub fn speed_factor(&self) -> f32 {
+        1. + self.value * 0.25
+    }
+
+    pub fn is_tantrum(&self) -> bool {
+        self.value < MOOD_TANTRUM
+    }
+}
+
+/// Moves each colonist's mood toward a target made up of how rested and fed
+/// they are, what kind of room they are in and how warm it is, whether anyone is
+/// nearby and what they think of them, and whether anything is rotting
+/// nearby. Deaths knock everyone's mood down right away, starving only the
+/// starving colonist's.
+pub fn tick_mood(
+    time: Res<Time>,
+    mut timer: Local<f32>,
+    terrain: Res<Terrain>,
+    rooms: Res<Rooms>,
+    mut ev_died: EventReader<ColonistDiedEvent>,
+    mut ev_starving: EventReader<ColonistStarvingEvent>,
+    q_others: Query<(Entity, &Transform), With<Colonist>>,
+    mut q_colonists: Query<
+        (
+            Entity,
+            &Transform,
+            &Fatigue,
+            Option<&Hunger>,
+            Option<&Relationships>,
+            &mut Mood,
+            &mut MovementStats,
+        ),
+        With<Colonist>,
+    >,
+) {
+    let deaths = ev_died.read().count();
+
+    if deaths > 0 {
+        for (_, _, _, _, _, mut mood, _) in q_colonists.iter_mut() {
+            mood.value = (mood.value - GRIEF * deaths as f32).max(-1.);
+        }
+    }
+
+    for ev in ev_starving.read() {
+        if let Ok((_, _, _, _, _, mut mood, _)) = q_colonists.get_mut(ev.entity) {
+            mood.value = (mood.value - STARVED).max(-1.);
+        }
+    }
+
+    *timer += time.delta_seconds();
+
+    if *timer < MOOD_TICK_S {
+        return;
+    }
+
+    *timer = 0.;
+
+    for (entity, transform, fatigue, hunger, relationships, mut mood, mut stats) in
+        q_colonists.iter_mut()
+    {
+        let [x, y, z] = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        let rest = 0.4 - fatigue.value / 100. * 0.8;
+        let fed = hunger.map_or(0., |hunger| 0.2 - hunger.0 / 100. * 0.4);
+
+        let shelter = rooms
+            .get_room_type(&terrain, [x, y, z])
+            .map_or(0., |room_type| room_type.mood());
+        let warmth = if rooms.get_temperature(&terrain, [x, y, z]) > 0 {
+            0.1
+        } else {
+            0.
+        };
+
+        let company = q_others.iter().filter_map(|(other, other_transform)| {
+            let is_near = other != entity
+                && other_transform.translation.distance(transform.translation) <= SOCIAL_RANGE;
+
+            is_near.then_some(other)
+        });
+        let social = match relationships {
+            Some(relationships) => relationships.average_opinion(company),
+            None => (company.count() > 0).then_some(0.),
+        }
+        .map_or(0., |opinion| 0.2 + opinion * OPINION_MOOD);
+
+        let stench = (count_rot_nearby(&terrain, [x, y, z]) as f32 * ROT_MOOD).min(ROT_MOOD_MAX);
+
+        let target = (rest + fed + shelter + warmth + social - stench).clamp(-1., 1.);
+
+        mood.value += (target - mood.value) * MOOD_DRIFT;
+        stats.mood_factor = mood.speed_factor();
+    }
+}
+
+fn count_rot_nearby(terrain: &Terrain, [x, y, z]: [u32; 3]) -> u32 {
+    let [x, y, z] = [x as i32, y as i32, z as i32];
+    let mut count = 0;
+
+    for dx in -ROT_RANGE..=ROT_RANGE {
+        for dy in -ROT_RANGE..=ROT_RANGE {
+            for dz in -ROT_RANGE..=ROT_RANGE {
+                if terrain.get_block_i32(x + dx, y + dy, z + dz).block == BlockType::ROTTEN {
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{event::Events, system::RunSystemOnce, world::World};
+
+    use super::*;
+
+    /// Runs `ticks` mood ticks for one colonist alone in open air
+    fn mood_after(ticks: u32, fatigue: f32, hunger: f32) -> (f32, f32) {
+        let mut world = World::new();
+        world.insert_resource(Terrain::new(1, 1, 1, 4).unwrap());
+        world.init_resource::<Rooms>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<ColonistDiedEvent>>();
+        world.init_resource::<Events<ColonistStarvingEvent>>();
+
+        let colonist = world
+            .spawn((
+                Colonist {},
+                Transform::from_xyz(4., 4., 4.),
+                Fatigue {
+                    value: fatigue,
+                    per_second: 0.,
+                },
+                Hunger(hunger),
+                Mood::default(),
+                MovementStats::default(),
+            ))
+            .id();
+
+        for _ in 0..ticks {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(MOOD_TICK_S));
+            world.run_system_once(tick_mood);
+        }
+
+        let mood = world.get::<Mood>(colonist).unwrap().value;
+        let factor = world.get::<MovementStats>(colonist).unwrap().mood_factor;
+
+        (mood, factor)
+    }
+
+    #[test]
+    fn fed_and_rested_colonist_gets_happy() {
+        let (mood, factor) = mood_after(10, 0., 0.);
+
+        assert!(mood > 0.5, "mood {mood}");
+        assert!(factor > 1.);
+    }
+
+    #[test]
+    fn hungry_and_tired_colonist_gets_miserable() {
+        let (mood, factor) = mood_after(10, 100., 100.);
+
+        assert!(mood < -0.5, "mood {mood}");
+        assert!(factor < 1.);
+    }
+}
@@ -12,7 +12,7 @@ use bevy::{
 
 use crate::Terrain;
 
-use super::{InInventory, InPartition, Item, NavigationGraph};
+use super::{InInventory, InPartition, Item, NavigationFlags, NavigationGraph};
 
 #[derive(Event)]
 pub struct MovedEvent {
@@ -35,9 +35,7 @@ pub fn update_item_partition(
         // remove the item from whatever partition it is in
         if let Ok(in_partition) = q_in_partition.get(ev.entity) {
             let partition_id = in_partition.partition_id;
-            if let Some(partition) = graph.get_partition_mut(&partition_id) {
-                partition.items.remove(&ev.entity);
-            }
+            graph.remove_item(&partition_id, &ev.entity);
             ecmd.remove::<InPartition>();
         };
 
@@ -45,17 +43,17 @@ pub fn update_item_partition(
             continue;
         }
 
-        if q_items.contains(ev.entity) {
+        if let Ok(item) = q_items.get(ev.entity) {
             let [x, y, z] = ev.position;
             let Some(new_partition_id) = terrain.get_partition_id_u32(x, y, z) else {
                 println!("doh! item not in a partition? {}", ev.entity.index());
                 continue;
             };
-            let Some(new_partition) = graph.get_partition_mut(&new_partition_id) else {
+
+            if !graph.add_item(&new_partition_id, ev.entity, &item.tags) {
                 continue;
-            };
+            }
 
-            new_partition.items.insert(ev.entity);
             ecmd.insert(InPartition {
                 partition_id: new_partition_id,
             });
@@ -63,6 +61,39 @@ pub fn update_item_partition(
     }
 }
 
+/// How fast an actor walks its path, in blocks per second.
+#[derive(Component, Clone, Copy)]
+pub struct MovementStats {
+    pub speed: f32,
+    pub ladder_speed_factor: f32,
+    /// Set from the actor's mood, scales every speed
+    pub mood_factor: f32,
+    /// Set from the actor's hunger, scales every speed
+    pub hunger_factor: f32,
+}
+
+impl Default for MovementStats {
+    fn default() -> Self {
+        Self {
+            speed: 4.,
+            ladder_speed_factor: 0.5,
+            mood_factor: 1.,
+            hunger_factor: 1.,
+        }
+    }
+}
+
+impl MovementStats {
+    /// Speed when moving into a block with the given navigation flags
+    pub fn get_speed(&self, flags: NavigationFlags) -> f32 {
+        if flags.contains(NavigationFlags::LADDER) {
+            self.speed * self.ladder_speed_factor * self.mood_factor * self.hunger_factor
+        } else {
+            self.speed * self.mood_factor * self.hunger_factor
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct BlockMove {
     pub speed: f32,
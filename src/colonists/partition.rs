@@ -1,6 +1,9 @@
-use bevy::ecs::event::Event;
+use bevy::ecs::{
+    event::{Event, EventWriter},
+    system::ResMut,
+};
 
-use crate::{BlockType, Terrain};
+use crate::{Block, BlockType, Terrain};
 
 use super::NavigationFlags;
 
@@ -9,8 +12,73 @@ pub struct PartitionEvent {
     pub chunk_idx: u32,
 }
 
+/// Sends a `PartitionEvent` for every chunk queued up by `Terrain::set_block`
+pub fn flush_partition_updates(
+    mut terrain: ResMut<Terrain>,
+    mut ev_partition: EventWriter<PartitionEvent>,
+) {
+    for update in terrain.take_partition_updates() {
+        ev_partition.send(PartitionEvent {
+            chunk_idx: update.chunk_idx,
+        });
+    }
+}
+
 pub fn get_block_flags(terrain: &Terrain, x: i32, y: i32, z: i32) -> NavigationFlags {
-    let block = terrain.get_block_i32(x, y, z);
+    let get_block = |x, y, z| terrain.get_block_i32(x, y, z);
+    let flags = get_movement_flags(get_block, x, y, z);
+
+    if flags == NavigationFlags::NONE {
+        return flags;
+    }
+
+    let is_hazard = [
+        [x, y, z],
+        [x - 1, y, z],
+        [x + 1, y, z],
+        [x, y - 1, z],
+        [x, y + 1, z],
+        [x, y, z - 1],
+        [x, y, z + 1],
+    ]
+    .iter()
+    .any(|[nx, ny, nz]| get_block(*nx, *ny, *nz).block.properties().is_hazard);
+
+    if is_hazard {
+        flags | NavigationFlags::HAZARD
+    } else {
+        flags
+    }
+}
+
+/// Whether an actor may step between two neighboring cells as far as stairs
+/// are concerned. A stair cell only joins the cell on its low side and the
+/// cell above it, so it is walked up and down in its own direction and never
+/// entered from the sides, from behind, or diagonally.
+pub fn is_stair_move_allowed(
+    get_block: impl Fn(i32, i32, i32) -> Block,
+    a: [i32; 3],
+    b: [i32; 3],
+) -> bool {
+    [(a, b), (b, a)].iter().all(|(stair, other)| {
+        let Some([dx, dz]) = get_block(stair[0], stair[1], stair[2]).stair_direction() else {
+            return true;
+        };
+
+        let low_side = [stair[0] - dx, stair[1], stair[2] - dz];
+        let above = [stair[0], stair[1] + 1, stair[2]];
+
+        *other == low_side || *other == above
+    })
+}
+
+fn get_movement_flags(
+    get_block: impl Fn(i32, i32, i32) -> Block,
+    x: i32,
+    y: i32,
+    z: i32,
+) -> NavigationFlags {
+    let block = get_block(x, y, z);
 
     let mut flags = NavigationFlags::NONE;
 
@@ -18,11 +86,20 @@ pub fn get_block_flags(terrain: &Terrain, x: i32, y: i32, z: i32) -> NavigationF
         return NavigationFlags::LADDER;
     }
 
-    if !block.is_empty() {
+    // standing on the low step, the cell above is where the high step is
+    if block.stair_direction().is_some() {
+        if get_block(x, y + 1, z).is_passable() {
+            return NavigationFlags::SOLID_GROUND | NavigationFlags::TALL;
+        }
+
+        return NavigationFlags::SOLID_GROUND;
+    }
+
+    if !block.is_passable() {
         return NavigationFlags::NONE;
     }
 
-    let nblock_below = terrain.get_block_i32(x, y - 1, z);
+    let nblock_below = get_block(x, y - 1, z);
 
     if nblock_below.block == BlockType::LADDER {
         return NavigationFlags::LADDER;
@@ -31,25 +108,25 @@ pub fn get_block_flags(terrain: &Terrain, x: i32, y: i32, z: i32) -> NavigationF
     if nblock_below.is_walkable() {
         flags |= NavigationFlags::SOLID_GROUND;
 
-        let nblock_above = terrain.get_block_i32(x, y + 1, z);
+        let nblock_above = get_block(x, y + 1, z);
 
-        if nblock_above.is_empty() {
+        if nblock_above.is_passable() {
             flags |= NavigationFlags::TALL;
         }
     } else if nblock_below.is_empty() {
-        let nblock_below2 = terrain.get_block_i32(x, y - 2, z);
-        let nblock_above = terrain.get_block_i32(x, y + 1, z);
+        let nblock_below2 = get_block(x, y - 2, z);
+        let nblock_above = get_block(x, y + 1, z);
 
         if nblock_below2.is_walkable() && nblock_above.is_empty() {
-            let left = terrain.get_block_i32(x - 1, y, z);
-            let right = terrain.get_block_i32(x + 1, y, z);
-            let fwd = terrain.get_block_i32(x, y, z - 1);
-            let back = terrain.get_block_i32(x, y, z + 1);
-
-            let below_left = terrain.get_block_i32(x - 1, y - 1, z);
-            let below_right = terrain.get_block_i32(x + 1, y - 1, z);
-            let below_fwd = terrain.get_block_i32(x, y - 1, z - 1);
-            let below_back = terrain.get_block_i32(x, y - 1, z + 1);
+            let left = get_block(x - 1, y, z);
+            let right = get_block(x + 1, y, z);
+            let fwd = get_block(x, y, z - 1);
+            let back = get_block(x, y, z + 1);
+
+            let below_left = get_block(x - 1, y - 1, z);
+            let below_right = get_block(x + 1, y - 1, z);
+            let below_fwd = get_block(x, y - 1, z - 1);
+            let below_back = get_block(x, y - 1, z + 1);
 
             if (left.is_empty() && below_left.is_walkable())
                 || (right.is_empty() && below_right.is_walkable())
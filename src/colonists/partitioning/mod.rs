@@ -4,6 +4,7 @@ mod navigation_group;
 mod partition;
 mod partition_debug;
 mod partition_extents;
+mod partition_validate;
 mod partitioner;
 mod region;
 
@@ -13,5 +14,6 @@ pub use navigation_group::*;
 pub use partition::*;
 pub use partition_debug::*;
 pub use partition_extents::*;
+pub use partition_validate::*;
 pub use partitioner::*;
 pub use region::*;
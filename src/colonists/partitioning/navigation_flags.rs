@@ -11,6 +11,9 @@ bitflags! {
         const LADDER = 2;
         const TALL = 4;
         const CLIMB = 8;
+        /// Next to a hazard block like magma. Still walkable, but paths
+        /// that don't ask for it treat it as costly, see `HAZARD_COST`
+        const HAZARD = 16;
         const COLONIST = Self::TALL.bits() | Self::LADDER.bits() | Self::CLIMB.bits();
         const CAT = Self::SOLID_GROUND.bits() | Self::CLIMB.bits();
     }
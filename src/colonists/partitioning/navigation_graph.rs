@@ -1,20 +1,44 @@
 use bevy::{
-    ecs::system::Resource,
+    ecs::{entity::Entity, system::Resource},
     utils::hashbrown::{HashMap, HashSet},
 };
 
-use crate::{common::flood_fill, Terrain};
+use crate::{
+    colonists::{partition_step_cost, ItemTag},
+    common::{astar, flood_fill, AStarSettings, Distance},
+    Terrain,
+};
 
 use super::{NavigationFlags, NavigationGroup, Partition, Region};
 
+/// Lengths kept in `path_lengths` before it is emptied
+const PATH_LENGTH_CACHE_MAX_ENTRIES: usize = 1000;
+/// Partitions `shortest_path_length` visits before giving up
+const PATH_LENGTH_MAX_DEPTH: u32 = 500;
+
 #[derive(Resource)]
 pub struct NavigationGraph {
     partitions: HashMap<u32, Partition>,
+    /// the ids of the partitions in each chunk, kept in step with `partitions`
+    chunk_to_partitions: HashMap<u32, HashSet<u32>>,
     regions: HashMap<u32, Region>,
     groups: HashMap<u32, NavigationGroup>,
 
     group_types: HashSet<NavigationFlags>,
 
+    /// the neighbors of each partition along with their flags, kept in step
+    /// with `Partition::neighbor_ids` so searches don't look every neighbor
+    /// up again to check whether it can be walked
+    adjacency: HashMap<u32, Vec<(u32, NavigationFlags)>>,
+
+    /// every item sitting in a partition, by tag
+    item_index: HashMap<ItemTag, HashSet<Entity>>,
+    /// the partition and tags each indexed item was added with
+    item_locations: HashMap<Entity, (u32, Vec<ItemTag>)>,
+
+    /// `shortest_path_length` results, dropped whenever partitions change
+    path_lengths: HashMap<(u32, u32), Option<f32>>,
+
     cur_partition_id: u32,
     cur_region_id: u32,
     cur_group_id: u32,
@@ -24,9 +48,14 @@ impl Default for NavigationGraph {
     fn default() -> Self {
         Self {
             partitions: HashMap::new(),
+            chunk_to_partitions: HashMap::new(),
             regions: HashMap::new(),
             groups: HashMap::new(),
             group_types: HashSet::from([NavigationFlags::COLONIST, NavigationFlags::CAT]),
+            adjacency: HashMap::new(),
+            item_index: HashMap::new(),
+            item_locations: HashMap::new(),
+            path_lengths: HashMap::new(),
             cur_partition_id: 0,
             cur_region_id: 0,
             cur_group_id: 0,
@@ -45,6 +74,10 @@ impl NavigationGraph {
         let partition_id = self.cur_partition_id;
         let partition = Partition::new(partition_id, region_id, chunk_idx, flags);
         self.partitions.insert(partition_id, partition);
+        self.chunk_to_partitions
+            .entry(chunk_idx)
+            .or_default()
+            .insert(partition_id);
         let region = self.get_region_mut(&region_id).unwrap();
         region.partition_ids.insert(partition_id);
         partition_id
@@ -87,14 +120,187 @@ impl NavigationGraph {
         group_ids
     }
 
+    /// Walking distance in blocks between two partitions for a colonist,
+    /// measured between partition centers the same way colonists path, for
+    /// showing e.g. "~45 steps away". `None` when unreachable, or too far
+    /// for the short search.
+    pub fn shortest_path_length(&mut self, a: u32, b: u32) -> Option<f32> {
+        if let Some(length) = self.path_lengths.get(&(a, b)) {
+            return *length;
+        }
+
+        let length = self.find_path_length(a, b, NavigationFlags::COLONIST);
+
+        if self.path_lengths.len() >= PATH_LENGTH_CACHE_MAX_ENTRIES {
+            self.path_lengths.clear();
+        }
+
+        self.path_lengths.insert((a, b), length);
+
+        length
+    }
+
+    fn find_path_length(&self, a: u32, b: u32, flags: NavigationFlags) -> Option<f32> {
+        let [bx, by, bz] = self.get_partition(&b)?.extents.center();
+        self.get_partition(&a)?;
+
+        let result = astar(AStarSettings {
+            start: a,
+            is_goal: |p| p == b,
+            max_depth: PATH_LENGTH_MAX_DEPTH,
+            neighbors_cached: true,
+            neighbors: |v| self.neighbors_with_flags(v, flags),
+            heuristic: |v| {
+                let [x, y, z] = self.get_partition(&v).unwrap().extents.center();
+
+                Distance::diagonal(
+                    [x as i32, y as i32, z as i32],
+                    [bx as i32, by as i32, bz as i32],
+                )
+            },
+            cost: |from, to| partition_step_cost(self, from, to, flags),
+        });
+
+        if !result.is_success {
+            return None;
+        }
+
+        // A* skips the step into the goal, so add the steps up again
+        let length = result
+            .path
+            .windows(2)
+            .map(|step| partition_step_cost(self, step[1], step[0], flags))
+            .sum();
+
+        Some(length)
+    }
+
+    fn clear_path_lengths(&mut self) {
+        if !self.path_lengths.is_empty() {
+            self.path_lengths.clear();
+        }
+    }
+
     pub fn get_partition(&self, id: &u32) -> Option<&Partition> {
         self.partitions.get(id)
     }
 
+    pub fn get_center(&self, partition_id: &u32) -> Option<[u32; 3]> {
+        self.get_partition(partition_id)
+            .map(|partition| partition.extents.center())
+    }
+
+    pub fn partitions(&self) -> impl Iterator<Item = (&u32, &Partition)> {
+        self.partitions.iter()
+    }
+
+    pub fn partition_count(&self) -> usize {
+        self.partitions.len()
+    }
+
+    pub fn region_count(&self) -> usize {
+        self.regions.len()
+    }
+
     pub fn get_partition_mut(&mut self, id: &u32) -> Option<&mut Partition> {
         self.partitions.get_mut(id)
     }
 
+    /// Put an item into a partition and the item index. Returns false if the
+    /// partition does not exist.
+    pub fn add_item(&mut self, partition_id: &u32, item: Entity, tags: &[ItemTag]) -> bool {
+        let Some(partition) = self.partitions.get_mut(partition_id) else {
+            return false;
+        };
+
+        partition.items.insert(item);
+
+        if let Some((old_partition_id, _)) = self.item_locations.get(&item) {
+            if old_partition_id != partition_id {
+                let old_partition_id = *old_partition_id;
+                self.remove_item(&old_partition_id, &item);
+            }
+        }
+
+        for tag in tags.iter() {
+            self.item_index.entry(*tag).or_default().insert(item);
+        }
+
+        self.item_locations
+            .insert(item, (*partition_id, tags.to_vec()));
+
+        true
+    }
+
+    /// Take an item out of a partition and the item index. Returns false if
+    /// the item was not in the partition.
+    pub fn remove_item(&mut self, partition_id: &u32, item: &Entity) -> bool {
+        if self
+            .item_locations
+            .get(item)
+            .is_some_and(|(id, _)| id == partition_id)
+        {
+            self.forget_item(item);
+        }
+
+        self.partitions
+            .get_mut(partition_id)
+            .is_some_and(|partition| partition.items.remove(item))
+    }
+
+    fn forget_item(&mut self, item: &Entity) {
+        let Some((_, tags)) = self.item_locations.remove(item) else {
+            return;
+        };
+
+        for tag in tags.iter() {
+            if let Some(items) = self.item_index.get_mut(tag) {
+                items.remove(item);
+
+                if items.is_empty() {
+                    self.item_index.remove(tag);
+                }
+            }
+        }
+    }
+
+    /// Find up to `max_results` items anywhere in the world that have all of
+    /// the given tags, along with the partition each one is in.
+    pub fn items_of_type(&self, tags: &[ItemTag], max_results: usize) -> Vec<(u32, Entity)> {
+        let mut sets = Vec::with_capacity(tags.len());
+
+        for tag in tags.iter() {
+            let Some(items) = self.item_index.get(tag) else {
+                return vec![];
+            };
+
+            sets.push(items);
+        }
+
+        // walk the smallest set and check the rest against it
+        sets.sort_by_key(|items| items.len());
+
+        let Some((smallest, rest)) = sets.split_first() else {
+            return self
+                .item_locations
+                .iter()
+                .take(max_results)
+                .map(|(item, (partition_id, _))| (*partition_id, *item))
+                .collect();
+        };
+
+        smallest
+            .iter()
+            .filter(|item| rest.iter().all(|items| items.contains(*item)))
+            .filter_map(|item| {
+                self.item_locations
+                    .get(item)
+                    .map(|(partition_id, _)| (*partition_id, *item))
+            })
+            .take(max_results)
+            .collect()
+    }
+
     pub fn get_region(&self, id: &u32) -> Option<&Region> {
         self.regions.get(id)
     }
@@ -134,15 +340,26 @@ impl NavigationGraph {
     /// if applicable, or merges regions if applicable. If the regions are
     /// merged, the new region ID will be returned.
     pub fn set_partition_neighbors(&mut self, a_id: &u32, b_id: &u32) -> Option<u32> {
-        let [a_partition, b_partition] = self.partitions.get_many_mut([a_id, b_id]).unwrap();
-        a_partition.neighbor_ids.insert(*b_id);
-        b_partition.neighbor_ids.insert(*a_id);
+        self.clear_path_lengths();
 
-        let a_region_id = a_partition.region_id;
-        let b_region_id = b_partition.region_id;
+        let [a_partition, b_partition] = self.partitions.get_many_mut([a_id, b_id]).unwrap();
+        let (a_region_id, a_flags) = (a_partition.region_id, a_partition.flags);
+        let (b_region_id, b_flags) = (b_partition.region_id, b_partition.flags);
+
+        if a_partition.neighbor_ids.insert(*b_id) {
+            b_partition.neighbor_ids.insert(*a_id);
+            self.adjacency
+                .entry(*a_id)
+                .or_default()
+                .push((*b_id, b_flags));
+            self.adjacency
+                .entry(*b_id)
+                .or_default()
+                .push((*a_id, a_flags));
+        }
 
         if a_region_id != b_region_id {
-            if a_partition.flags == b_partition.flags {
+            if a_flags == b_flags {
                 let region_id = self.merge_regions(&a_region_id, &b_region_id);
                 return Some(region_id);
             } else {
@@ -223,6 +440,8 @@ impl NavigationGraph {
         block_pos: [u32; 3],
         terrain: &mut Terrain,
     ) {
+        self.clear_path_lengths();
+
         let partition = self.get_partition_mut(partition_id).unwrap();
         partition.assign_block(block_idx, block_pos);
         terrain.set_partition_id(partition.chunk_idx, block_idx, *partition_id);
@@ -258,7 +477,16 @@ impl NavigationGraph {
     }
 
     pub fn delete_partition(&mut self, partition_id: &u32) -> Partition {
+        self.clear_path_lengths();
+
         let partition = self.partitions.remove(partition_id).unwrap();
+        self.forget_chunk_partition(partition.chunk_idx, partition_id);
+        self.forget_adjacency(partition_id);
+
+        // the items get re-added once their new partition is known
+        for item in partition.items.iter() {
+            self.forget_item(item);
+        }
 
         // Remove this partition from neighbors
         for neighbor_id in partition.neighbor_ids.iter() {
@@ -375,21 +603,60 @@ impl NavigationGraph {
         }
     }
 
-    fn get_partition_ids_for_chunk(&self, chunk_idx: u32) -> Vec<u32> {
-        self.partitions
-            .iter()
-            .filter_map(|(partition_id, partition)| {
-                if partition.chunk_idx == chunk_idx {
-                    Some(*partition_id)
-                } else {
-                    None
-                }
+    fn forget_adjacency(&mut self, partition_id: &u32) {
+        let Some(neighbors) = self.adjacency.remove(partition_id) else {
+            return;
+        };
+
+        for (neighbor_id, _) in neighbors.iter() {
+            if let Some(list) = self.adjacency.get_mut(neighbor_id) {
+                list.retain(|(id, _)| id != partition_id);
+            }
+        }
+    }
+
+    /// Neighbors of the partition that can be walked with any of `flags`
+    pub fn neighbors_with_flags(
+        &self,
+        partition_id: u32,
+        flags: NavigationFlags,
+    ) -> impl Iterator<Item = u32> + '_ {
+        self.adjacency
+            .get(&partition_id)
+            .into_iter()
+            .flatten()
+            .filter(move |(_, neighbor_flags)| neighbor_flags.intersects(flags))
+            .map(|(neighbor_id, _)| *neighbor_id)
+    }
+
+    fn forget_chunk_partition(&mut self, chunk_idx: u32, partition_id: &u32) {
+        let Some(partition_ids) = self.chunk_to_partitions.get_mut(&chunk_idx) else {
+            return;
+        };
+
+        partition_ids.remove(partition_id);
+
+        if partition_ids.is_empty() {
+            self.chunk_to_partitions.remove(&chunk_idx);
+        }
+    }
+
+    pub fn get_all_partitions_in_chunk(&self, chunk_idx: u32) -> Vec<u32> {
+        self.chunk_to_partitions
+            .get(&chunk_idx)
+            .map_or(vec![], |partition_ids| {
+                partition_ids.iter().copied().collect()
             })
-            .collect::<Vec<_>>()
+    }
+
+    pub fn chunk_partition_count(&self, chunk_idx: u32) -> usize {
+        self.chunk_to_partitions
+            .get(&chunk_idx)
+            .map_or(0, |partition_ids| partition_ids.len())
     }
 
     pub fn delete_partitions_for_chunk(&mut self, chunk_idx: u32) -> Vec<Partition> {
-        let partition_ids = self.get_partition_ids_for_chunk(chunk_idx);
+        let partition_ids = self.get_all_partitions_in_chunk(chunk_idx);
 
         partition_ids
             .iter()
@@ -404,7 +671,16 @@ impl NavigationGraph {
         b_id: &u32,
         terrain: &mut Terrain,
     ) -> (u32, u32) {
+        self.clear_path_lengths();
+
         let b_partition = self.partitions.remove(b_id).unwrap();
+        self.forget_chunk_partition(b_partition.chunk_idx, b_id);
+        self.forget_adjacency(b_id);
+
+        for item in b_partition.items.iter() {
+            self.forget_item(item);
+        }
+
         let b_region_id = b_partition.region_id;
         let b_neighbor_ids = b_partition.neighbor_ids;
         let block_idxs = b_partition.blocks.clone();
@@ -414,23 +690,23 @@ impl NavigationGraph {
         a_partition.is_computed = a_partition.is_computed && b_partition.is_computed;
 
         for block_idx in block_idxs {
-            let block_pos = terrain.get_block_world_pos(a_partition.chunk_idx, block_idx);
-            a_partition.assign_block(block_idx, block_pos);
+            a_partition.blocks.insert(block_idx);
             terrain.set_partition_id(a_partition.chunk_idx, block_idx, *a_id);
         }
 
+        a_partition.extents = a_partition.extents.union(&b_partition.extents);
+
         if a_partition.is_computed {
             a_partition.extents.update_traversal_distance();
         }
 
         for neighor_id in b_neighbor_ids.iter() {
-            if neighor_id == a_id {
-                continue;
-            }
-
             if let Some(neighbor) = self.get_partition_mut(neighor_id) {
                 neighbor.neighbor_ids.remove(b_id);
-                self.set_partition_neighbors(a_id, neighor_id);
+
+                if neighor_id != a_id {
+                    self.set_partition_neighbors(a_id, neighor_id);
+                }
             }
         }
 
@@ -500,3 +776,223 @@ impl NavigationGraph {
         (smaller_region.id, bigger_region.id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use crate::common::AStarResult;
+
+    use super::*;
+
+    fn sorted(mut ids: Vec<u32>) -> Vec<u32> {
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn chunk_index_follows_partitions() {
+        let mut terrain = Terrain::new(2, 1, 1, 4).unwrap();
+        let mut graph = NavigationGraph::default();
+        let flags = NavigationFlags::COLONIST;
+        let region_id = graph.create_region(flags);
+
+        let a = graph.create_partition(region_id, 0, flags);
+        let b = graph.create_partition(region_id, 0, flags);
+        let c = graph.create_partition(region_id, 0, flags);
+        let other = graph.create_partition(region_id, 1, flags);
+
+        graph.assign_block(&a, 0, [0, 0, 0], &mut terrain);
+        graph.assign_block(&b, 1, [1, 0, 0], &mut terrain);
+        graph.assign_block(&c, 2, [2, 0, 0], &mut terrain);
+        graph.assign_block(&other, 0, [4, 0, 0], &mut terrain);
+
+        assert_eq!(sorted(graph.get_all_partitions_in_chunk(0)), vec![a, b, c]);
+        assert_eq!(graph.get_all_partitions_in_chunk(1), vec![other]);
+        assert_eq!(graph.chunk_partition_count(0), 3);
+
+        graph.merge_partitions(&a, &b, &mut terrain);
+        assert_eq!(sorted(graph.get_all_partitions_in_chunk(0)), vec![a, c]);
+        assert_eq!(terrain.get_partition_id(0, 1), Some(a));
+
+        let deleted = graph.delete_partitions_for_chunk(0);
+        assert_eq!(
+            sorted(deleted.iter().map(|partition| partition.id).collect()),
+            vec![a, c]
+        );
+        assert!(graph.get_all_partitions_in_chunk(0).is_empty());
+        assert_eq!(graph.chunk_partition_count(0), 0);
+
+        // the neighboring chunk is left alone
+        assert_eq!(graph.get_all_partitions_in_chunk(1), vec![other]);
+        assert_eq!(graph.partition_count(), 1);
+    }
+
+    /// A 20 by 10 grid of one block tall partitions, with walls every few columns
+    /// that have a single gap, so searches have to go around
+    fn grid_graph(terrain: &mut Terrain) -> (NavigationGraph, Vec<u32>) {
+        let mut graph = NavigationGraph::default();
+        let flags = NavigationFlags::TALL;
+        let mut ids = vec![];
+
+        for x in 0..20 {
+            for z in 0..10 {
+                let [chunk_idx, block_idx] = terrain.get_block_indexes(x, 0, z);
+                let region_id = graph.create_region(flags);
+                let id = graph.create_partition(region_id, chunk_idx, flags);
+                graph.assign_block(&id, block_idx, [x, 0, z], terrain);
+                ids.push(id);
+            }
+        }
+
+        let at = |x: u32, z: u32| ids[(x * 10 + z) as usize];
+
+        for x in 0..20 {
+            for z in 0..10 {
+                let is_wall = x % 4 == 3 && z != (x * 3) % 10;
+
+                if x + 1 < 20 && !is_wall {
+                    graph.set_partition_neighbors(&at(x, z), &at(x + 1, z));
+                }
+                if z + 1 < 10 {
+                    graph.set_partition_neighbors(&at(x, z), &at(x, z + 1));
+                }
+            }
+        }
+
+        (graph, ids)
+    }
+
+    /// Searches like `get_partition_path`, counting every partition lookup.
+    /// Neighbors come from the adjacency list, or from each partition's
+    /// `neighbor_ids` with a lookup per neighbor to check its flags.
+    fn counted_search(
+        graph: &NavigationGraph,
+        start: u32,
+        goal: u32,
+        use_adjacency: bool,
+    ) -> (AStarResult<u32>, u32) {
+        let flags = NavigationFlags::COLONIST;
+        let lookups = Cell::new(0);
+        let get = |id: &u32| {
+            lookups.set(lookups.get() + 1);
+            graph.get_partition(id)
+        };
+        let [gx, gy, gz] = graph.get_center(&goal).unwrap();
+
+        let result = astar(AStarSettings {
+            start,
+            is_goal: |p| p == goal,
+            max_depth: 2000,
+            neighbors_cached: false,
+            neighbors: |v| {
+                if use_adjacency {
+                    lookups.set(lookups.get() + 1);
+                    return graph.neighbors_with_flags(v, flags).collect::<Vec<_>>();
+                }
+
+                get(&v).map_or(vec![], |p| {
+                    p.neighbor_ids
+                        .iter()
+                        .filter(|n| get(n).is_some_and(|n_p| n_p.flags.intersects(flags)))
+                        .copied()
+                        .collect()
+                })
+            },
+            heuristic: |v| {
+                let [x, y, z] = get(&v).unwrap().extents.center();
+                Distance::diagonal(
+                    [x as i32, y as i32, z as i32],
+                    [gx as i32, gy as i32, gz as i32],
+                )
+            },
+            cost: |a, b| {
+                lookups.set(lookups.get() + 2);
+                partition_step_cost(graph, a, b, flags)
+            },
+        });
+
+        (result, lookups.get())
+    }
+
+    #[test]
+    fn adjacency_follows_neighbors() {
+        let mut terrain = Terrain::new(3, 1, 2, 8).unwrap();
+        let (mut graph, ids) = grid_graph(&mut terrain);
+
+        let neighbors = |graph: &NavigationGraph, id: u32| {
+            sorted(
+                graph
+                    .neighbors_with_flags(id, NavigationFlags::COLONIST)
+                    .collect(),
+            )
+        };
+
+        for id in ids.iter() {
+            let expected = sorted(
+                graph
+                    .get_partition(id)
+                    .unwrap()
+                    .neighbor_ids
+                    .iter()
+                    .copied()
+                    .collect(),
+            );
+            assert_eq!(neighbors(&graph, *id), expected);
+        }
+
+        assert!(graph
+            .neighbors_with_flags(ids[0], NavigationFlags::SOLID_GROUND)
+            .next()
+            .is_none());
+
+        // merging takes over the neighbors, deleting drops them
+        let (a, b) = (ids[0], ids[1]);
+        graph.merge_partitions(&a, &b, &mut terrain);
+        assert_eq!(neighbors(&graph, a), sorted(vec![ids[2], ids[10], ids[11]]));
+
+        graph.delete_partition(&ids[11]);
+        assert_eq!(neighbors(&graph, a), sorted(vec![ids[2], ids[10]]));
+        assert_eq!(
+            neighbors(&graph, ids[12]),
+            sorted(vec![ids[2], ids[13], ids[22]])
+        );
+    }
+
+    /// `cargo test adjacency_lookup_bench -- --nocapture`
+    ///
+    /// Searches across a 200 partition graph with neighbors from the
+    /// adjacency list and looked up one by one. Lookups are counted rather
+    /// than timed, so this runs with the other tests.
+    #[test]
+    fn adjacency_lookup_bench() {
+        let mut terrain = Terrain::new(3, 1, 2, 8).unwrap();
+        let (graph, ids) = grid_graph(&mut terrain);
+        let (mut closure_lookups, mut adjacency_lookups) = (0, 0);
+
+        for i in 0..50 {
+            let start = ids[i * 7 % 200];
+            let goal = ids[(i * 13 + 101) % 200];
+
+            let (by_closure, lookups) = counted_search(&graph, start, goal, false);
+            closure_lookups += lookups;
+            let (by_adjacency, lookups) = counted_search(&graph, start, goal, true);
+            adjacency_lookups += lookups;
+
+            assert!(by_closure.is_success && by_adjacency.is_success);
+            // neighbors come in another order than from the hash set, so
+            // ties between equally short paths may go the other way
+            assert_eq!(by_closure.cost, by_adjacency.cost);
+            assert_eq!(by_closure.path.len(), by_adjacency.path.len());
+        }
+
+        let saved = 1. - adjacency_lookups as f32 / closure_lookups as f32;
+        println!(
+            "closure {} lookups, adjacency {} lookups, {:.0}% fewer",
+            closure_lookups,
+            adjacency_lookups,
+            saved * 100.
+        );
+        assert!(saved >= 0.15);
+    }
+}
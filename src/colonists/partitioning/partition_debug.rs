@@ -1,19 +1,27 @@
 use bevy::{
     ecs::system::{Res, ResMut, Resource},
     gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, ButtonInput},
     math::Vec3,
     render::color::Color,
 };
 
-use crate::Terrain;
+use crate::{controls::CursorHit, Terrain, TerrainSlice, MAX_LIGHT};
 
 use super::{NavigationGraph, Partition};
 
 #[derive(Resource, Default)]
 pub struct PartitionDebug {
     pub partition_id: Option<u32>,
+    /// Draw the partition-level route of every path
+    pub show_path: bool,
+    /// Draw the light levels of the top slice layer around the cursor
+    pub show_light: bool,
 }
 
+/// Cells around the cursor, in each direction, that get their light drawn
+const LIGHT_DEBUG_RADIUS: i32 = 12;
+
 pub fn partition_debug(
     terrain: Res<Terrain>,
     graph: Res<NavigationGraph>,
@@ -58,6 +66,90 @@ pub fn partition_debug(
             debug_partition(part, &terrain, &mut gizmos, Color::BLUE, Color::BLUE);
         }
     }
+
+    // partitions of unrelated regions sharing the space, like a floor above
+    for (_, part) in graph.partitions() {
+        if part.region_id == region.id
+            || region.neighbor_ids.contains(&part.region_id)
+            || !part.extents.overlaps(&partition.extents)
+        {
+            continue;
+        }
+
+        debug_partition(part, &terrain, &mut gizmos, Color::PURPLE, Color::PURPLE);
+    }
+}
+
+pub fn toggle_light_debug(
+    mut debug: ResMut<PartitionDebug>,
+    input_keys: Res<ButtonInput<KeyCode>>,
+) {
+    if input_keys.just_pressed(KeyCode::F7) {
+        debug.show_light = !debug.show_light;
+    }
+}
+
+/// Draws two squares on every open cell of the top visible layer near the
+/// cursor, sunlight in yellow and torchlight in orange, each sized by its
+/// level. Unlit cells draw nothing.
+pub fn light_debug(
+    terrain: Res<Terrain>,
+    debug: Res<PartitionDebug>,
+    terrain_slice: Res<TerrainSlice>,
+    cursor_hit: Res<CursorHit>,
+    mut gizmos: Gizmos,
+) {
+    if !debug.show_light || !cursor_hit.is_hit {
+        return;
+    }
+
+    let Some(y) = terrain_slice.get_value().checked_sub(1) else {
+        return;
+    };
+
+    let [cx, _, cz] = cursor_hit.hit_pos;
+
+    for dx in -LIGHT_DEBUG_RADIUS..=LIGHT_DEBUG_RADIUS {
+        for dz in -LIGHT_DEBUG_RADIUS..=LIGHT_DEBUG_RADIUS {
+            let x = cx as i32 + dx;
+            let z = cz as i32 + dz;
+
+            if terrain.is_oob(x, y as i32, z) {
+                continue;
+            }
+
+            let (x, z) = (x as u32, z as u32);
+
+            if terrain.get_block(x, y, z).is_opaque() {
+                continue;
+            }
+
+            let center = Vec3::new(x as f32 + 0.5, y as f32 + 0.05, z as f32 + 0.5);
+            let sunlight = terrain.get_sunlight_xyz(x, y, z);
+            let torchlight = terrain.get_torchlight_xyz(x, y, z);
+
+            debug_light_square(&mut gizmos, center, sunlight, Color::YELLOW);
+            debug_light_square(&mut gizmos, center, torchlight, Color::ORANGE);
+        }
+    }
+}
+
+fn debug_light_square(gizmos: &mut Gizmos, center: Vec3, level: u8, color: Color) {
+    if level == 0 {
+        return;
+    }
+
+    let half = 0.45 * level as f32 / MAX_LIGHT as f32;
+    let corners = [
+        center + Vec3::new(-half, 0., -half),
+        center + Vec3::new(half, 0., -half),
+        center + Vec3::new(half, 0., half),
+        center + Vec3::new(-half, 0., half),
+    ];
+
+    for i in 0..4 {
+        gizmos.line(corners[i], corners[(i + 1) % 4], color);
+    }
 }
 
 fn debug_partition(
@@ -1,6 +1,6 @@
 use crate::common::{max_3, Distance};
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 pub struct PartitionExtents {
     is_init: bool,
     pub min_x: u32,
@@ -41,6 +41,54 @@ impl PartitionExtents {
         self.max_z = pos[2].max(self.max_z);
     }
 
+    pub fn overlaps(&self, other: &PartitionExtents) -> bool {
+        if !self.is_init || !other.is_init {
+            return false;
+        }
+
+        self.min_x <= other.max_x
+            && self.max_x >= other.min_x
+            && self.min_y <= other.max_y
+            && self.max_y >= other.min_y
+            && self.min_z <= other.max_z
+            && self.max_z >= other.min_z
+    }
+
+    pub fn contains_point(&self, x: i32, y: i32, z: i32) -> bool {
+        if !self.is_init {
+            return false;
+        }
+
+        x >= self.min_x as i32
+            && x <= self.max_x as i32
+            && y >= self.min_y as i32
+            && y <= self.max_y as i32
+            && z >= self.min_z as i32
+            && z <= self.max_z as i32
+    }
+
+    /// Returns the smallest extents that contain both of these extents
+    pub fn union(&self, other: &PartitionExtents) -> PartitionExtents {
+        if !other.is_init {
+            return *self;
+        }
+
+        if !self.is_init {
+            return *other;
+        }
+
+        PartitionExtents {
+            is_init: true,
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            min_z: self.min_z.min(other.min_z),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+            max_z: self.max_z.max(other.max_z),
+            traversal_distance: 0.,
+        }
+    }
+
     pub fn distance_to_edge(&self, x: i32, _y: i32, z: i32) -> f32 {
         // TODO: this only works in 2D space
         let dx = max_3(self.min_x as i32 - x, 0, x - self.max_x as i32).abs();
@@ -57,3 +105,85 @@ impl PartitionExtents {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extents(min: [u32; 3], max: [u32; 3]) -> PartitionExtents {
+        let mut extents = PartitionExtents::default();
+        extents.extend(min);
+        extents.extend(max);
+        extents
+    }
+
+    #[test]
+    fn overlaps_known_boxes() {
+        let a = extents([0, 0, 0], [3, 3, 3]);
+
+        assert!(a.overlaps(&a));
+        assert!(a.overlaps(&extents([2, 2, 2], [5, 5, 5])));
+        assert!(a.overlaps(&extents([1, 1, 1], [2, 2, 2])));
+        assert!(extents([1, 1, 1], [2, 2, 2]).overlaps(&a));
+
+        // extents are inclusive, so boxes sharing an edge block overlap
+        assert!(a.overlaps(&extents([3, 0, 0], [6, 3, 3])));
+        assert!(a.overlaps(&extents([3, 3, 3], [4, 4, 4])));
+
+        // disjoint along each axis
+        assert!(!a.overlaps(&extents([4, 0, 0], [6, 3, 3])));
+        assert!(!a.overlaps(&extents([0, 4, 0], [3, 6, 3])));
+        assert!(!a.overlaps(&extents([0, 0, 4], [3, 3, 6])));
+        assert!(!extents([4, 4, 4], [6, 6, 6]).overlaps(&a));
+
+        assert!(!a.overlaps(&PartitionExtents::default()));
+        assert!(!PartitionExtents::default().overlaps(&a));
+    }
+
+    #[test]
+    fn contains_point_known_boxes() {
+        let a = extents([2, 2, 2], [4, 4, 4]);
+
+        assert!(a.contains_point(3, 3, 3));
+        assert!(a.contains_point(2, 2, 2));
+        assert!(a.contains_point(4, 4, 4));
+        assert!(a.contains_point(2, 4, 3));
+
+        assert!(!a.contains_point(1, 3, 3));
+        assert!(!a.contains_point(5, 3, 3));
+        assert!(!a.contains_point(3, 1, 3));
+        assert!(!a.contains_point(3, 5, 3));
+        assert!(!a.contains_point(3, 3, 1));
+        assert!(!a.contains_point(3, 3, 5));
+        assert!(!a.contains_point(-1, 3, 3));
+
+        assert!(!PartitionExtents::default().contains_point(0, 0, 0));
+    }
+
+    #[test]
+    fn union_known_boxes() {
+        let a = extents([0, 1, 2], [3, 4, 5]);
+        let b = extents([2, 0, 6], [7, 2, 8]);
+        let u = a.union(&b);
+
+        assert_eq!(
+            [u.min_x, u.min_y, u.min_z, u.max_x, u.max_y, u.max_z],
+            [0, 0, 2, 7, 4, 8]
+        );
+        assert!(u.overlaps(&a) && u.overlaps(&b));
+        assert!(u.contains_point(7, 0, 2));
+
+        // touching boxes make one box without a gap
+        let c = extents([4, 1, 2], [6, 4, 5]);
+        let touching = a.union(&c);
+        assert_eq!([touching.min_x, touching.max_x], [0, 6]);
+        assert!(touching.contains_point(4, 3, 3));
+
+        let empty = PartitionExtents::default();
+        let u = a.union(&empty);
+        assert_eq!([u.min_x, u.max_z], [0, 5]);
+        let u = empty.union(&b);
+        assert_eq!([u.min_x, u.max_z], [2, 8]);
+        assert!(!empty.union(&empty).contains_point(0, 0, 0));
+    }
+}
@@ -0,0 +1,278 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::{
+    ecs::system::Res,
+    input::{keyboard::KeyCode, ButtonInput},
+};
+
+use crate::Terrain;
+
+use super::NavigationGraph;
+
+/// An inconsistency between partitions, their regions, and the partition ids
+/// stored on the terrain.
+#[derive(Debug)]
+pub enum PartitionError {
+    MissingNeighbor {
+        partition_id: u32,
+        neighbor_id: u32,
+    },
+    OneWayNeighbor {
+        partition_id: u32,
+        neighbor_id: u32,
+    },
+    MissingRegion {
+        partition_id: u32,
+        region_id: u32,
+    },
+    NotInRegion {
+        partition_id: u32,
+        region_id: u32,
+    },
+    BlockMismatch {
+        partition_id: u32,
+        chunk_idx: u32,
+        block_idx: u32,
+        found: Option<u32>,
+    },
+    ComputedWithoutBlocks(u32),
+}
+
+impl PartitionError {
+    /// What would put the graph back together.
+    pub fn repair(&self) -> &'static str {
+        match self {
+            Self::MissingNeighbor { .. } => "remove the stale neighbor id",
+            Self::OneWayNeighbor { .. } => "add the missing back link",
+            Self::MissingRegion { .. } | Self::NotInRegion { .. } => {
+                "reassign the partition to a region"
+            }
+            Self::BlockMismatch { .. } => "repartition the chunk",
+            Self::ComputedWithoutBlocks(_) => "delete the partition",
+        }
+    }
+}
+
+impl Display for PartitionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingNeighbor {
+                partition_id,
+                neighbor_id,
+            } => write!(
+                f,
+                "partition {} has neighbor {} which does not exist",
+                partition_id, neighbor_id
+            ),
+            Self::OneWayNeighbor {
+                partition_id,
+                neighbor_id,
+            } => write!(
+                f,
+                "partition {} has neighbor {} which does not list it back",
+                partition_id, neighbor_id
+            ),
+            Self::MissingRegion {
+                partition_id,
+                region_id,
+            } => write!(
+                f,
+                "partition {} belongs to region {} which does not exist",
+                partition_id, region_id
+            ),
+            Self::NotInRegion {
+                partition_id,
+                region_id,
+            } => write!(
+                f,
+                "partition {} is missing from its region {}",
+                partition_id, region_id
+            ),
+            Self::BlockMismatch {
+                partition_id,
+                chunk_idx,
+                block_idx,
+                found,
+            } => write!(
+                f,
+                "partition {} holds block {} of chunk {}, but the terrain has it in {:?}",
+                partition_id, block_idx, chunk_idx, found
+            ),
+            Self::ComputedWithoutBlocks(partition_id) => {
+                write!(
+                    f,
+                    "partition {} is computed but has no blocks",
+                    partition_id
+                )
+            }
+        }
+    }
+}
+
+impl NavigationGraph {
+    /// Check every partition against its neighbors, its region, and the
+    /// terrain. An empty result means the graph is consistent.
+    pub fn validate(&self, terrain: &Terrain) -> Vec<PartitionError> {
+        let mut errors = vec![];
+
+        for (partition_id, partition) in self.partitions() {
+            let partition_id = *partition_id;
+
+            for neighbor_id in partition.neighbor_ids.iter() {
+                let neighbor_id = *neighbor_id;
+
+                match self.get_partition(&neighbor_id) {
+                    None => errors.push(PartitionError::MissingNeighbor {
+                        partition_id,
+                        neighbor_id,
+                    }),
+                    Some(neighbor) if !neighbor.neighbor_ids.contains(&partition_id) => errors
+                        .push(PartitionError::OneWayNeighbor {
+                            partition_id,
+                            neighbor_id,
+                        }),
+                    Some(_) => {}
+                }
+            }
+
+            let region_id = partition.region_id;
+
+            match self.get_region(&region_id) {
+                None => errors.push(PartitionError::MissingRegion {
+                    partition_id,
+                    region_id,
+                }),
+                Some(region) if !region.partition_ids.contains(&partition_id) => {
+                    errors.push(PartitionError::NotInRegion {
+                        partition_id,
+                        region_id,
+                    })
+                }
+                Some(_) => {}
+            }
+
+            for block_idx in partition.blocks.iter() {
+                let found = terrain.get_partition_id(partition.chunk_idx, *block_idx);
+
+                if found != Some(partition_id) {
+                    errors.push(PartitionError::BlockMismatch {
+                        partition_id,
+                        chunk_idx: partition.chunk_idx,
+                        block_idx: *block_idx,
+                        found,
+                    });
+                }
+            }
+
+            if partition.is_computed && partition.blocks.is_empty() {
+                errors.push(PartitionError::ComputedWithoutBlocks(partition_id));
+            }
+        }
+
+        errors
+    }
+}
+
+pub fn validate_partitions_key(
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    input_keys: Res<ButtonInput<KeyCode>>,
+) {
+    if !input_keys.just_pressed(KeyCode::F6) {
+        return;
+    }
+
+    let errors = graph.validate(&terrain);
+
+    if errors.is_empty() {
+        println!("partition graph is consistent");
+        return;
+    }
+
+    for error in errors.iter() {
+        println!("{}, {}", error, error.repair());
+    }
+
+    println!("{} partition errors", errors.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{event::Events, system::RunSystemOnce, world::World};
+
+    use crate::{
+        colonists::{partition, PartitionEvent},
+        BlockType,
+    };
+
+    use super::*;
+
+    /// Two chunks side by side with a stone floor, partitioned
+    fn partitioned_world() -> World {
+        let mut terrain = Terrain::new(2, 1, 1, 4).unwrap();
+
+        for x in 0..8 {
+            for z in 0..4 {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+            }
+        }
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<Events<PartitionEvent>>();
+        repartition(&mut world, &[0, 1]);
+        world
+    }
+
+    fn repartition(world: &mut World, chunks: &[u32]) {
+        for chunk_idx in chunks.iter() {
+            world.send_event(PartitionEvent {
+                chunk_idx: *chunk_idx,
+            });
+        }
+        world.run_system_once(partition);
+    }
+
+    fn errors(world: &World) -> Vec<PartitionError> {
+        world
+            .resource::<NavigationGraph>()
+            .validate(world.resource::<Terrain>())
+    }
+
+    #[test]
+    fn repartitioning_keeps_the_graph_consistent() {
+        let mut world = partitioned_world();
+        assert!(world.resource::<NavigationGraph>().partition_count() > 0);
+        assert!(errors(&world).is_empty());
+
+        world
+            .resource_mut::<Terrain>()
+            .set_block(2, 1, 2, BlockType::STONE);
+        repartition(&mut world, &[0]);
+        assert!(errors(&world).is_empty(), "{:?}", errors(&world));
+    }
+
+    #[test]
+    fn stale_neighbors_are_reported() {
+        let mut world = partitioned_world();
+        let mut graph = world.resource_mut::<NavigationGraph>();
+        let partition_id = graph.get_all_partitions_in_chunk(0)[0];
+        graph
+            .get_partition_mut(&partition_id)
+            .unwrap()
+            .neighbor_ids
+            .insert(9999);
+
+        let errors = errors(&world);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            PartitionError::MissingNeighbor {
+                neighbor_id: 9999,
+                ..
+            }
+        ));
+        assert_eq!(errors[0].repair(), "remove the stale neighbor id");
+    }
+}
@@ -3,8 +3,8 @@ use bevy::{
         component::Component,
         entity::Entity,
         event::EventReader,
-        query::With,
-        system::{Commands, Query, ResMut},
+        query::Without,
+        system::{Commands, Local, Query, Res, ResMut},
     },
     transform::components::Transform,
     utils::hashbrown::HashSet,
@@ -12,8 +12,7 @@ use bevy::{
 use ndshape::AbstractShape;
 
 use crate::{
-    colonists::{get_block_flags, Item, PartitionEvent},
-    common::flood_fill_i32,
+    colonists::{get_block_flags, is_stair_move_allowed, InInventory, Item, PartitionEvent},
     Terrain,
 };
 
@@ -29,7 +28,7 @@ pub fn partition(
     mut partition_ev: EventReader<PartitionEvent>,
     mut graph: ResMut<NavigationGraph>,
     mut terrain: ResMut<Terrain>,
-    q_items: Query<&Transform, With<Item>>,
+    q_items: Query<(&Transform, &Item)>,
 ) {
     for ev in partition_ev.read() {
         let chunk_idx = ev.chunk_idx;
@@ -80,94 +79,85 @@ pub fn partition(
 
             let mut region_id = graph.get_partition(&partition_id).unwrap().region_id;
 
-            flood_fill_i32([x as i32, y as i32, z as i32], |[nx, ny, nz]| {
-                if terrain.is_oob(nx, ny, nz) {
-                    return false;
-                }
-
-                let [nchunk_idx, nblock_idx] =
-                    terrain.get_block_indexes(nx as u32, ny as u32, nz as u32);
-
-                // this is the seed block
-                if nblock_idx == block_idx && nchunk_idx == chunk_idx {
-                    return true;
-                }
-
-                if let Some(npartition_id) = terrain.get_partition_id(nchunk_idx, nblock_idx) {
-                    // already assigned to this partition
-                    if npartition_id == partition_id {
+            // connected blocks with the same flags in this chunk make up the
+            // partition, the blocks around it are its neighbors
+            let mut borders = vec![];
+            let blocks = {
+                let terrain: &Terrain = &terrain;
+                let max_blocks = terrain.chunk_shape.size() as usize;
+
+                terrain.flood_fill_steps([x, y, z], max_blocks, |from, to, _| {
+                    let from = [from[0] as i32, from[1] as i32, from[2] as i32];
+                    let [nx, ny, nz] = [to[0] as i32, to[1] as i32, to[2] as i32];
+
+                    // stairs only connect along their own direction
+                    if from != [nx, ny, nz]
+                        && !is_stair_move_allowed(
+                            |x, y, z| terrain.get_block_i32(x, y, z),
+                            from,
+                            [nx, ny, nz],
+                        )
+                    {
                         return false;
                     }
 
-                    let nblock_flags = get_block_flags(&terrain, nx, ny, nz);
+                    let nblock_flags = get_block_flags(terrain, nx, ny, nz);
 
                     if nblock_flags.is_empty() {
                         return false;
                     }
 
-                    let flag_diff = nblock_flags != block_flags;
-                    let chunk_diff = nchunk_idx != chunk_idx;
-
-                    if flag_diff || chunk_diff {
-                        if let Some(new_region_id) =
-                            graph.set_partition_neighbors(&partition_id, &npartition_id)
-                        {
-                            region_id = new_region_id;
-                        };
+                    let [nchunk_idx, _] = terrain.get_block_indexes(to[0], to[1], to[2]);
 
+                    if nblock_flags != block_flags || nchunk_idx != chunk_idx {
+                        borders.push((to, nblock_flags));
                         return false;
                     }
 
-                    (partition_id, region_id) =
-                        graph.merge_partitions(&partition_id, &npartition_id, &mut terrain);
+                    true
+                })
+            };
+
+            for pos in blocks {
+                let [_, nblock_idx] = terrain.get_block_indexes(pos[0], pos[1], pos[2]);
 
-                    return true;
+                match terrain.get_partition_id(chunk_idx, nblock_idx) {
+                    Some(npartition_id) if npartition_id == partition_id => {}
+                    // seeded by an earlier fill that ran into it
+                    Some(npartition_id) => {
+                        (partition_id, region_id) =
+                            graph.merge_partitions(&partition_id, &npartition_id, &mut terrain);
+                    }
+                    None => graph.assign_block(&partition_id, nblock_idx, pos, &mut terrain),
                 }
+            }
 
-                let nblock_flags = get_block_flags(&terrain, nx, ny, nz);
+            for (pos, nblock_flags) in borders {
+                let [nchunk_idx, nblock_idx] = terrain.get_block_indexes(pos[0], pos[1], pos[2]);
 
-                if nblock_flags.is_empty() {
-                    return false;
-                }
+                if let Some(npartition_id) = terrain.get_partition_id(nchunk_idx, nblock_idx) {
+                    if let Some(new_region_id) =
+                        graph.set_partition_neighbors(&partition_id, &npartition_id)
+                    {
+                        region_id = new_region_id;
+                    }
 
-                let flag_diff = nblock_flags != block_flags;
-                let chunk_diff = nchunk_idx != chunk_idx;
-
-                if flag_diff || chunk_diff {
-                    // if flags are the same, we add to existing region, otherwise we make
-                    // a new region and add it as a neighbor.
-                    let nregion_id = if flag_diff {
-                        let new_region_id = graph.create_region(nblock_flags);
-                        graph.set_region_neighbors(&region_id, &new_region_id);
-                        new_region_id
-                    } else {
-                        region_id
-                    };
-
-                    let npartition_id =
-                        graph.create_partition(nregion_id, nchunk_idx, nblock_flags);
-
-                    terrain.set_partition_id(nchunk_idx, nblock_idx, npartition_id);
-                    graph.assign_block(
-                        &npartition_id,
-                        nblock_idx,
-                        [nx as u32, ny as u32, nz as u32],
-                        &mut terrain,
-                    );
-
-                    return false;
+                    continue;
                 }
 
-                terrain.set_partition_id(nchunk_idx, nblock_idx, partition_id);
-                graph.assign_block(
-                    &partition_id,
-                    nblock_idx,
-                    [nx as u32, ny as u32, nz as u32],
-                    &mut terrain,
-                );
-
-                true
-            });
+                // if flags are the same, we add to existing region, otherwise we make
+                // a new region and add it as a neighbor.
+                let nregion_id = if nblock_flags != block_flags {
+                    let new_region_id = graph.create_region(nblock_flags);
+                    graph.set_region_neighbors(&region_id, &new_region_id);
+                    new_region_id
+                } else {
+                    region_id
+                };
+
+                let npartition_id = graph.create_partition(nregion_id, nchunk_idx, nblock_flags);
+                graph.assign_block(&npartition_id, nblock_idx, pos, &mut terrain);
+            }
 
             let partition = graph.get_partition_mut(&partition_id).unwrap();
             partition.is_computed = true;
@@ -175,7 +165,7 @@ pub fn partition(
         }
 
         for item in items {
-            let Ok(transform) = q_items.get(item) else {
+            let Ok((transform, item_data)) = q_items.get(item) else {
                 println!("Item does not exist anymore. {}", item.index());
                 continue;
             };
@@ -193,12 +183,67 @@ pub fn partition(
                 continue;
             };
 
-            let partition = graph.get_partition_mut(&item_partition_id).unwrap();
-
-            partition.items.insert(item);
+            graph.add_item(&item_partition_id, item, &item_data.tags);
             ecmd.insert(InPartition {
                 partition_id: item_partition_id,
             });
         }
     }
 }
+
+/// Prints the size of the world once the first partitioning pass is done, so
+/// a misconfigured world size shows up right away
+/// Puts loose items that are in no partition into the one they lie in, once
+/// it exists. Items spawned in a cell that was solid until this frame, like
+/// the drops of a mined block, only get a partition after the chunk is
+/// partitioned again.
+pub fn partition_orphaned_items(
+    mut cmd: Commands,
+    terrain: Res<Terrain>,
+    mut graph: ResMut<NavigationGraph>,
+    q_orphans: Query<(Entity, &Transform, &Item), (Without<InPartition>, Without<InInventory>)>,
+) {
+    for (entity, transform, item) in q_orphans.iter() {
+        let x = transform.translation.x as u32;
+        let y = transform.translation.y as u32;
+        let z = transform.translation.z as u32;
+
+        let Some(partition_id) = terrain.get_partition_id_u32(x, y, z) else {
+            continue;
+        };
+
+        if graph.add_item(&partition_id, entity, &item.tags) {
+            cmd.entity(entity).insert(InPartition { partition_id });
+        }
+    }
+}
+
+pub fn log_world_stats(
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    mut logged: Local<bool>,
+) {
+    if *logged || graph.partition_count() == 0 {
+        return;
+    }
+
+    *logged = true;
+
+    let (bytes, _) = terrain.chunk_memory();
+    let busiest_chunk = (0..terrain.chunk_count)
+        .map(|chunk_idx| graph.chunk_partition_count(chunk_idx))
+        .max()
+        .unwrap_or(0);
+
+    println!(
+        "world {}x{}x{}, {} blocks, chunk storage {} KiB, {} partitions ({} in the busiest chunk), {} regions",
+        terrain.world_size_x(),
+        terrain.world_size_y(),
+        terrain.world_size_z(),
+        terrain.total_block_count(),
+        bytes / 1024,
+        graph.partition_count(),
+        busiest_chunk,
+        graph.region_count(),
+    );
+}
@@ -0,0 +1,128 @@
+use bevy::{
+    ecs::{
+        event::EventReader,
+        system::{ResMut, Resource},
+    },
+    utils::hashbrown::HashMap,
+};
+
+use super::{NavigationFlags, PartitionEvent};
+
+const PATH_CACHE_MAX_ENTRIES: usize = 1000;
+
+struct CachedPath {
+    /// Partition the path starts in, it is stored goal first like A* returns it
+    from: u32,
+    /// Chunks of both partitions, repartitioning either drops the path
+    chunks: [u32; 2],
+    flags: NavigationFlags,
+    blocks: Vec<[i32; 3]>,
+    last_used: u64,
+}
+
+/// Block paths between neighboring partitions, shared by every actor. A path
+/// found from one partition to the other is reused in either direction by
+/// anyone who steps onto it.
+#[derive(Resource, Default)]
+pub struct PathCache {
+    map: HashMap<(u32, u32), CachedPath>,
+    clock: u64,
+    /// Lookups answered from the cache, and lookups that had to run A*
+    pub hits: u64,
+    pub misses: u64,
+}
+
+fn key(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+impl PathCache {
+    /// A cached path from `start` into `goal_partition_id`, cut down to begin
+    /// at `start`. Blocks are goal first, the same as `get_granular_path`.
+    pub fn get(
+        &mut self,
+        start: [i32; 3],
+        start_partition_id: u32,
+        goal_partition_id: u32,
+        flags: NavigationFlags,
+    ) -> Option<Vec<[i32; 3]>> {
+        self.clock += 1;
+
+        let cached = self
+            .map
+            .get_mut(&key(start_partition_id, goal_partition_id))
+            .filter(|cached| cached.flags == flags);
+
+        let Some(cached) = cached else {
+            self.misses += 1;
+            return None;
+        };
+
+        let mut blocks = cached.blocks.clone();
+
+        if cached.from != start_partition_id {
+            blocks.reverse();
+        }
+
+        let Some(start_idx) = blocks.iter().position(|b| *b == start).filter(|i| *i > 0) else {
+            self.misses += 1;
+            return None;
+        };
+
+        cached.last_used = self.clock;
+        self.hits += 1;
+        blocks.truncate(start_idx + 1);
+
+        Some(blocks)
+    }
+
+    pub fn insert(
+        &mut self,
+        from: u32,
+        to: u32,
+        chunks: [u32; 2],
+        flags: NavigationFlags,
+        blocks: Vec<[i32; 3]>,
+    ) {
+        if self.map.len() >= PATH_CACHE_MAX_ENTRIES {
+            self.evict_oldest();
+        }
+
+        self.map.insert(
+            key(from, to),
+            CachedPath {
+                from,
+                chunks,
+                flags,
+                blocks,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .map
+            .iter()
+            .min_by_key(|(_, cached)| cached.last_used)
+            .map(|(key, _)| *key);
+
+        if let Some(key) = oldest {
+            self.map.remove(&key);
+        }
+    }
+
+    pub fn invalidate_chunk(&mut self, chunk_idx: u32) {
+        self.map
+            .retain(|_, cached| !cached.chunks.contains(&chunk_idx));
+    }
+}
+
+pub fn invalidate_path_cache(
+    mut cache: ResMut<PathCache>,
+    mut ev_partition: EventReader<PartitionEvent>,
+) {
+    for ev in ev_partition.read() {
+        cache.invalidate_chunk(ev.chunk_idx);
+    }
+}
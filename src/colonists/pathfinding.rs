@@ -7,7 +7,35 @@ use crate::{
     Terrain,
 };
 
-use super::{get_block_flags, NavigationFlags, NavigationGraph};
+use super::{get_block_flags, is_stair_move_allowed, NavigationFlags, NavigationGraph};
+
+/// Steps into a `HAZARD` cell cost this many times more, so a path beside
+/// magma is only taken when the detour is much longer. Requests that include
+/// `HAZARD` in their flags don't pay it.
+pub const HAZARD_COST: f32 = 8.;
+
+fn hazard_cost(cell_flags: NavigationFlags, request_flags: NavigationFlags) -> f32 {
+    if cell_flags.contains(NavigationFlags::HAZARD)
+        && !request_flags.contains(NavigationFlags::HAZARD)
+    {
+        HAZARD_COST
+    } else {
+        1.
+    }
+}
+
+/// Cost of walking from partition `a` into its neighbor `b`, center to
+/// center
+pub fn partition_step_cost(graph: &NavigationGraph, a: u32, b: u32, flags: NavigationFlags) -> f32 {
+    let [ax, ay, az] = graph.get_partition(&a).unwrap().extents.center();
+    let b_partition = graph.get_partition(&b).unwrap();
+    let [bx, by, bz] = b_partition.extents.center();
+
+    Distance::diagonal(
+        [ax as i32, ay as i32, az as i32],
+        [bx as i32, by as i32, bz as i32],
+    ) * hazard_cost(b_partition.flags, flags)
+}
 
 #[derive(Component, Default)]
 pub struct Path {
@@ -35,9 +63,6 @@ pub struct GranularPathRequest {
 
 pub struct GranularPath {
     pub blocks: Vec<[i32; 3]>,
-    pub flags: NavigationFlags,
-    pub goals: Vec<[u32; 3]>,
-    pub goal_partition_id: u32,
 }
 
 pub fn get_granular_path(
@@ -74,17 +99,24 @@ pub fn get_granular_path(
                     .iter()
                     .any(|g| p[0] == g[0] && p[1] == g[1] && p[2] == g[2])
             } else {
-                let [chunk_idx, block_idx] =
-                    terrain.get_block_indexes(p[0] as u32, p[1] as u32, p[2] as u32);
+                if !goal_partition.extents.contains_point(p[0], p[1], p[2]) {
+                    return false;
+                }
 
-                let Some(partition_id) = terrain.get_partition_id(chunk_idx, block_idx) else {
+                let Some(partition_id) = terrain.get_block_i32(p[0], p[1], p[2]).partition_id
+                else {
                     return false;
                 };
 
                 partition_id == request.goal_partition_id
             }
         },
-        cost: |a, b| Distance::diagonal([a[0], a[1], a[2]], [b[0], b[1], b[2]]),
+        cost: |a, b| {
+            let flags = get_block_flags(terrain, b[0], b[1], b[2]);
+
+            Distance::diagonal([a[0], a[1], a[2]], [b[0], b[1], b[2]])
+                * hazard_cost(flags, request.flags)
+        },
         heuristic: |v| {
             if is_last_partition {
                 goal_positions
@@ -138,11 +170,9 @@ pub fn get_granular_path(
 
             edges
                 .iter()
+                .filter(|p| is_stair_move_allowed(|x, y, z| terrain.get_block_i32(x, y, z), v, **p))
                 .filter_map(|p| {
-                    let [chunk_idx, block_idx] =
-                        terrain.get_block_indexes(p[0] as u32, p[1] as u32, p[2] as u32);
-
-                    let partition_id = terrain.get_partition_id(chunk_idx, block_idx)?;
+                    let partition_id = terrain.get_block_i32(p[0], p[1], p[2]).partition_id?;
                     let partition = graph.get_partition(&partition_id)?;
 
                     if partition.flags & request.flags != NavigationFlags::NONE {
@@ -151,9 +181,10 @@ pub fn get_granular_path(
                         None
                     }
                 })
-                .collect()
+                .collect::<Vec<_>>()
         },
         max_depth: 3000,
+        neighbors_cached: false,
     });
 
     if !result.is_success {
@@ -162,9 +193,6 @@ pub fn get_granular_path(
 
     Some(GranularPath {
         blocks: result.path,
-        flags: request.flags,
-        goals: request.goals.clone(),
-        goal_partition_id: request.goal_partition_id,
     })
 }
 
@@ -189,7 +217,6 @@ impl Path {
 pub struct PartitionPath {
     pub path: Vec<u32>,
     pub goals: Vec<[u32; 3]>,
-    pub flags: NavigationFlags,
 }
 
 pub fn is_reachable(
@@ -263,7 +290,6 @@ pub fn get_partition_path(
         return Some(PartitionPath {
             path: vec![starting_partition_id],
             goals: request.goals.clone(),
-            flags: request.flags,
         });
     }
 
@@ -271,22 +297,8 @@ pub fn get_partition_path(
         start: starting_partition_id,
         is_goal: |p| goal_partition_ids.contains(&p),
         max_depth: 2000,
-        neighbors: |v| {
-            if let Some(p) = graph.get_partition(&v) {
-                return p
-                    .neighbor_ids
-                    .iter()
-                    .filter(|n| {
-                        let Some(n_p) = graph.get_partition(n) else {
-                            return false;
-                        };
-                        n_p.flags & request.flags != NavigationFlags::NONE
-                    })
-                    .copied()
-                    .collect();
-            }
-            vec![]
-        },
+        neighbors_cached: true,
+        neighbors: |v| graph.neighbors_with_flags(v, request.flags),
         heuristic: |a| {
             let [ax, ay, az] = graph.get_partition(&a).unwrap().extents.center();
 
@@ -302,15 +314,7 @@ pub fn get_partition_path(
                 .unwrap()
                 .0
         },
-        cost: |a, b| {
-            let [ax, ay, az] = graph.get_partition(&a).unwrap().extents.center();
-            let [bx, by, bz] = graph.get_partition(&b).unwrap().extents.center();
-
-            Distance::diagonal(
-                [ax as i32, ay as i32, az as i32],
-                [bx as i32, by as i32, bz as i32],
-            )
-        },
+        cost: |a, b| partition_step_cost(graph, a, b, request.flags),
     });
 
     if !partition_path.is_success {
@@ -320,6 +324,116 @@ pub fn get_partition_path(
     Some(PartitionPath {
         path: partition_path.path,
         goals: request.goals.clone(),
-        flags: request.flags,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{event::Events, system::RunSystemOnce, world::World};
+
+    use crate::{
+        colonists::{partition, PartitionEvent},
+        BlockType,
+    };
+
+    use super::*;
+
+    const START: [u32; 3] = [2, 1, 2];
+    const GOAL: [u32; 3] = [12, 1, 2];
+    /// The tunnel cell beside the magma
+    const BESIDE_MAGMA: [i32; 3] = [7, 1, 2];
+
+    /// Two rooms dug out of stone, joined by a straight tunnel with magma in
+    /// its wall halfway along
+    fn magma_tunnel() -> Terrain {
+        let mut terrain = Terrain::new(1, 1, 1, 16).unwrap();
+        terrain.fill_region([0, 0, 0], [15, 2, 15], BlockType::STONE);
+        terrain.fill_region([1, 1, 1], [3, 2, 3], BlockType::EMPTY);
+        terrain.fill_region([11, 1, 1], [13, 2, 3], BlockType::EMPTY);
+        terrain.fill_region([4, 1, 2], [10, 2, 2], BlockType::EMPTY);
+        terrain.set_block(7, 1, 1, BlockType::MAGMA);
+        terrain
+    }
+
+    /// Partitions the terrain and walks a colonist's way from one room to
+    /// the other one partition at a time, the way `task_move_to` does.
+    /// Both paths come back goal first.
+    fn route(terrain: Terrain) -> Vec<[i32; 3]> {
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<Events<PartitionEvent>>();
+        world.send_event(PartitionEvent { chunk_idx: 0 });
+        world.run_system_once(partition);
+
+        let terrain = world.resource::<Terrain>();
+        let graph = world.resource::<NavigationGraph>();
+        let flags = NavigationFlags::COLONIST;
+        let partition_path = get_partition_path(
+            &PartitionPathRequest {
+                start: START,
+                goals: vec![GOAL],
+                flags,
+            },
+            terrain,
+            graph,
+        )
+        .unwrap()
+        .path;
+
+        let mut pos = START;
+        let mut blocks = vec![];
+
+        while pos != GOAL {
+            let partition_id = terrain
+                .get_partition_id_u32(pos[0], pos[1], pos[2])
+                .unwrap();
+            let idx = partition_path
+                .iter()
+                .position(|id| *id == partition_id)
+                .unwrap();
+
+            let granular = get_granular_path(
+                graph,
+                terrain,
+                &GranularPathRequest {
+                    start: pos,
+                    goals: vec![GOAL],
+                    goal_partition_id: partition_path[idx.saturating_sub(1)],
+                    flags,
+                },
+            )
+            .unwrap();
+
+            let [x, y, z] = granular.blocks[0];
+            pos = [x as u32, y as u32, z as u32];
+            blocks.extend(granular.blocks.into_iter().rev());
+        }
+
+        blocks
+    }
+
+    #[test]
+    fn only_route_passes_beside_magma() {
+        let terrain = magma_tunnel();
+        let [x, y, z] = BESIDE_MAGMA;
+        assert!(get_block_flags(&terrain, x, y, z).contains(NavigationFlags::HAZARD));
+
+        assert!(route(terrain).contains(&BESIDE_MAGMA));
+    }
+
+    #[test]
+    fn dug_detour_avoids_magma() {
+        // a few blocks longer, out of the corner of the first room, along
+        // z 5 and into the corner of the second
+        let mut terrain = magma_tunnel();
+        terrain.fill_region([3, 1, 4], [3, 2, 5], BlockType::EMPTY);
+        terrain.fill_region([4, 1, 5], [10, 2, 5], BlockType::EMPTY);
+        terrain.fill_region([11, 1, 4], [11, 2, 5], BlockType::EMPTY);
+
+        let route = route(terrain);
+
+        assert!(!route.contains(&BESIDE_MAGMA));
+        assert!(route.contains(&[7, 1, 5]));
+    }
+}
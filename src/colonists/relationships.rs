@@ -0,0 +1,211 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::With,
+        system::{Commands, Query, Res},
+    },
+    math::Vec3,
+    time::Time,
+    transform::components::Transform,
+    utils::hashbrown::HashMap,
+};
+
+use super::{Colonist, ColonistDiedEvent};
+
+/// Colonists this close are working together
+const TOGETHER_RANGE: f32 = 3.;
+/// Seconds two colonists have to stay together before they warm to each other
+const TOGETHER_DELAY_S: f32 = 5.;
+/// Opinion gained per second spent together after the delay
+const OPINION_GAIN_PER_S: f32 = 0.01;
+/// Colonists this close to a death witness it
+const WITNESS_RANGE: f32 = 8.;
+/// Seconds a witnessed death sours a colonist on everyone around them, and
+/// by how much
+const TRAUMA_S: f32 = 30.;
+const TRAUMA_OPINION: f32 = 0.3;
+
+/// What a colonist thinks of the others, from -1 (hates) to 1 (loves).
+/// Colonists they never spent time with are not in the map.
+#[derive(Component, Default)]
+pub struct Relationships {
+    pub opinions: HashMap<Entity, f32>,
+    /// Seconds spent within `TOGETHER_RANGE` of each colonist, without a break
+    together_s: HashMap<Entity, f32>,
+    /// Seconds left of the trauma of a witnessed death
+    trauma_s: f32,
+}
+
+impl Relationships {
+    /// The opinion of `other`, lowered for a while after a witnessed death
+    pub fn opinion_of(&self, other: Entity) -> f32 {
+        let opinion = self.opinions.get(&other).copied().unwrap_or(0.);
+
+        if self.trauma_s > 0. {
+            (opinion - TRAUMA_OPINION).max(-1.)
+        } else {
+            opinion
+        }
+    }
+
+    /// Average opinion of the given colonists, None if there are none
+    pub fn average_opinion(&self, others: impl Iterator<Item = Entity>) -> Option<f32> {
+        let (sum, count) = others.fold((0., 0), |(sum, count), other| {
+            (sum + self.opinion_of(other), count + 1)
+        });
+
+        if count == 0 {
+            None
+        } else {
+            Some(sum / count as f32)
+        }
+    }
+}
+
+/// Opinions read from a save, keyed by each colonist's place in the save
+/// file. Swapped for entities by `restore_relationships` once every loaded
+/// colonist is spawned.
+#[derive(Component, Clone)]
+pub struct SavedRelationships {
+    pub save_idx: u32,
+    pub opinions: Vec<(u32, f32)>,
+}
+
+pub fn tick_relationships(
+    time: Res<Time>,
+    mut ev_died: EventReader<ColonistDiedEvent>,
+    mut q_colonists: Query<(Entity, &Transform, &mut Relationships), With<Colonist>>,
+) {
+    let dt = time.delta_seconds();
+
+    let positions = q_colonists
+        .iter()
+        .map(|(entity, transform, _)| (entity, transform.translation))
+        .collect::<Vec<_>>();
+
+    for ev in ev_died.read() {
+        let died_at = Vec3::new(ev.pos[0] as f32, ev.pos[1] as f32, ev.pos[2] as f32);
+
+        for (_, transform, mut relationships) in q_colonists.iter_mut() {
+            relationships.opinions.remove(&ev.entity);
+            relationships.together_s.remove(&ev.entity);
+
+            if transform.translation.distance(died_at) <= WITNESS_RANGE {
+                relationships.trauma_s = TRAUMA_S;
+            }
+        }
+    }
+
+    for (entity, transform, mut relationships) in q_colonists.iter_mut() {
+        relationships.trauma_s = (relationships.trauma_s - dt).max(0.);
+
+        // apart, the streak starts over
+        relationships.together_s.retain(|other, _| {
+            positions
+                .iter()
+                .any(|(e, pos)| e == other && pos.distance(transform.translation) <= TOGETHER_RANGE)
+        });
+
+        for (other, pos) in positions.iter() {
+            if *other == entity || pos.distance(transform.translation) > TOGETHER_RANGE {
+                continue;
+            }
+
+            let together_s = relationships.together_s.entry(*other).or_insert(0.);
+            *together_s += dt;
+
+            if *together_s <= TOGETHER_DELAY_S {
+                continue;
+            }
+
+            let opinion = relationships.opinions.entry(*other).or_insert(0.);
+            *opinion = (*opinion + OPINION_GAIN_PER_S * dt).min(1.);
+        }
+    }
+}
+
+pub fn restore_relationships(
+    mut cmd: Commands,
+    q_saved: Query<(Entity, &SavedRelationships)>,
+    mut q_relationships: Query<&mut Relationships>,
+) {
+    if q_saved.is_empty() {
+        return;
+    }
+
+    let by_idx = q_saved
+        .iter()
+        .map(|(entity, saved)| (saved.save_idx, entity))
+        .collect::<HashMap<_, _>>();
+
+    for (entity, saved) in q_saved.iter() {
+        cmd.entity(entity).remove::<SavedRelationships>();
+
+        let Ok(mut relationships) = q_relationships.get_mut(entity) else {
+            continue;
+        };
+
+        for (other_idx, opinion) in saved.opinions.iter() {
+            if let Some(other) = by_idx.get(other_idx) {
+                relationships.opinions.insert(*other, *opinion);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{event::Events, system::RunSystemOnce, world::World};
+
+    use super::*;
+
+    /// Spawns two colonists `apart` blocks from each other and runs
+    /// `seconds` of ticks, returning what the first thinks of the second
+    fn opinion_after(apart: f32, seconds: u32) -> f32 {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<ColonistDiedEvent>>();
+
+        let a = world
+            .spawn((
+                Colonist {},
+                Transform::from_xyz(0., 0., 0.),
+                Relationships::default(),
+            ))
+            .id();
+        let b = world
+            .spawn((
+                Colonist {},
+                Transform::from_xyz(apart, 0., 0.),
+                Relationships::default(),
+            ))
+            .id();
+
+        for _ in 0..seconds * 10 {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_millis(100));
+            world.run_system_once(tick_relationships);
+        }
+
+        world.get::<Relationships>(a).unwrap().opinion_of(b)
+    }
+
+    #[test]
+    fn colonists_together_warm_to_each_other() {
+        let opinion = opinion_after(1., 10);
+
+        // 5s of streak, then 5s at OPINION_GAIN_PER_S
+        assert!(opinion > 0., "opinion {opinion}");
+        assert!((opinion - 0.05).abs() < 0.002, "opinion {opinion}");
+    }
+
+    #[test]
+    fn colonists_apart_stay_strangers() {
+        assert_eq!(opinion_after(TOGETHER_RANGE + 1., 10), 0.);
+    }
+}
@@ -0,0 +1,176 @@
+use bevy::{
+    ecs::{
+        event::EventReader,
+        system::{Res, ResMut, Resource},
+    },
+    utils::hashbrown::HashMap,
+};
+
+use crate::Terrain;
+
+use super::{NavigationGraph, PartitionEvent};
+
+/// How far above the floor a ceiling still counts as a roof
+const ROOF_MAX_HEIGHT: u32 = 4;
+/// Extra warmth held in by walls and a roof
+const INTERIOR_WARMTH: u8 = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoomType {
+    /// Every block has a roof over it, e.g. a dug out tunnel
+    Interior,
+    /// Some of it is open to the sky
+    Exterior,
+}
+
+impl RoomType {
+    /// Mood gained from spending time here
+    pub fn mood(&self) -> f32 {
+        match self {
+            Self::Interior => 0.2,
+            Self::Exterior => -0.1,
+        }
+    }
+
+    pub fn warmth(&self) -> u8 {
+        match self {
+            Self::Interior => INTERIOR_WARMTH,
+            Self::Exterior => 0,
+        }
+    }
+}
+
+/// Whether the navigable blocks of one partition are roofed over
+pub struct Room {
+    pub chunk_idx: u32,
+    pub room_type: RoomType,
+}
+
+/// Rooms keyed by the partition they were detected from
+#[derive(Resource, Default)]
+pub struct Rooms {
+    rooms: HashMap<u32, Room>,
+}
+
+impl Rooms {
+    pub fn get(&self, partition_id: &u32) -> Option<&Room> {
+        self.rooms.get(partition_id)
+    }
+
+    /// Room type of the partition a block is in, if it has been detected
+    pub fn get_room_type(&self, terrain: &Terrain, [x, y, z]: [u32; 3]) -> Option<RoomType> {
+        let partition_id = terrain.get_partition_id_u32(x, y, z)?;
+        self.get(&partition_id).map(|room| room.room_type)
+    }
+
+    /// Block temperature plus whatever warmth the room holds in
+    pub fn get_temperature(&self, terrain: &Terrain, [x, y, z]: [u32; 3]) -> u8 {
+        let warmth = self
+            .get_room_type(terrain, [x, y, z])
+            .map_or(0, |room_type| room_type.warmth());
+
+        terrain.get_temperature_xyz(x, y, z).saturating_add(warmth)
+    }
+}
+
+/// Re-detects the rooms of every chunk that was just repartitioned. A
+/// partition is an interior room when every block in it has a filled block
+/// somewhere in the few blocks above it.
+pub fn detect_rooms(
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    mut rooms: ResMut<Rooms>,
+    mut ev_partition: EventReader<PartitionEvent>,
+) {
+    for ev in ev_partition.read() {
+        rooms.rooms.retain(|partition_id, room| {
+            room.chunk_idx != ev.chunk_idx && graph.get_partition(partition_id).is_some()
+        });
+
+        for (partition_id, partition) in graph.partitions() {
+            if partition.chunk_idx != ev.chunk_idx {
+                continue;
+            }
+
+            let has_roof = partition.blocks.iter().all(|block_idx| {
+                let [x, y, z] = terrain.get_block_world_pos(partition.chunk_idx, *block_idx);
+                has_roof(&terrain, [x, y, z])
+            });
+
+            let room_type = if has_roof {
+                RoomType::Interior
+            } else {
+                RoomType::Exterior
+            };
+
+            rooms.rooms.insert(
+                *partition_id,
+                Room {
+                    chunk_idx: partition.chunk_idx,
+                    room_type,
+                },
+            );
+        }
+    }
+}
+
+fn has_roof(terrain: &Terrain, [x, y, z]: [u32; 3]) -> bool {
+    (y + 1..=y + ROOF_MAX_HEIGHT).any(|ry| {
+        let block = terrain.get_block(x, ry, z);
+        !block.is_oob() && block.block.properties().is_filled
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{
+        event::Events,
+        schedule::{IntoSystemConfigs, Schedule},
+        world::World,
+    };
+
+    use crate::{colonists::partition, BlockType};
+
+    use super::*;
+
+    /// Runs partitioning and room detection over the whole chunk
+    fn detect(world: &mut World) {
+        world.send_event(PartitionEvent { chunk_idx: 0 });
+        let mut schedule = Schedule::default();
+        schedule.add_systems((partition, detect_rooms).chain());
+        schedule.run(world);
+    }
+
+    fn room_type(world: &World, pos: [u32; 3]) -> Option<RoomType> {
+        world
+            .resource::<Rooms>()
+            .get_room_type(world.resource::<Terrain>(), pos)
+    }
+
+    #[test]
+    fn dug_tunnel_is_an_interior_room() {
+        // solid up to y 12, with a two high tunnel dug in at y 5
+        let mut terrain = Terrain::new(1, 1, 1, 16).unwrap();
+        terrain.fill_region([0, 0, 0], [15, 12, 15], BlockType::STONE);
+        terrain.fill_region([2, 5, 2], [10, 6, 3], BlockType::EMPTY);
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<Rooms>();
+        world.init_resource::<Events<PartitionEvent>>();
+        detect(&mut world);
+
+        assert_eq!(room_type(&world, [5, 5, 2]), Some(RoomType::Interior));
+        assert_eq!(room_type(&world, [10, 5, 3]), Some(RoomType::Interior));
+        assert_eq!(room_type(&world, [5, 13, 5]), Some(RoomType::Exterior));
+
+        // a shaft up to the sky opens the whole tunnel up
+        world
+            .resource_mut::<Terrain>()
+            .fill_region([5, 7, 2], [5, 12, 2], BlockType::EMPTY);
+        detect(&mut world);
+
+        assert_eq!(room_type(&world, [10, 5, 3]), Some(RoomType::Exterior));
+    }
+}
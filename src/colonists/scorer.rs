@@ -11,7 +11,10 @@ use bevy::{
     prelude::App,
 };
 
-use crate::colonists::{ScorerBuild, ScorerMine, ScorerWander};
+use crate::colonists::{
+    ScorerBuild, ScorerChop, ScorerCook, ScorerFarm, ScorerGuard, ScorerHaul, ScorerLight,
+    ScorerMine, ScorerPatrol, ScorerSupply, ScorerTantrum, ScorerWander,
+};
 
 use super::{ActorRef, Behavior};
 
@@ -28,9 +31,6 @@ pub struct Scorers {
     pub scorers: Vec<Entity>,
 }
 
-#[derive(Component)]
-pub struct ScoreBuilderRef(pub usize);
-
 #[bevy_trait_query::queryable]
 pub trait ScorerBuilder: Send + Sync {
     fn insert(&self, cmd: &mut EntityCommands);
@@ -45,8 +45,17 @@ impl Plugin for ScorerPlugin {
         use bevy_trait_query::RegisterExt;
 
         app.register_component_as::<dyn ScorerBuilder, ScorerMine>()
+            .register_component_as::<dyn ScorerBuilder, ScorerChop>()
             .register_component_as::<dyn ScorerBuilder, ScorerBuild>()
             .register_component_as::<dyn ScorerBuilder, ScorerWander>()
+            .register_component_as::<dyn ScorerBuilder, ScorerCook>()
+            .register_component_as::<dyn ScorerBuilder, ScorerFarm>()
+            .register_component_as::<dyn ScorerBuilder, ScorerPatrol>()
+            .register_component_as::<dyn ScorerBuilder, ScorerGuard>()
+            .register_component_as::<dyn ScorerBuilder, ScorerHaul>()
+            .register_component_as::<dyn ScorerBuilder, ScorerSupply>()
+            .register_component_as::<dyn ScorerBuilder, ScorerLight>()
+            .register_component_as::<dyn ScorerBuilder, ScorerTantrum>()
             .add_systems(PreUpdate, spawn_scorers);
     }
 }
@@ -59,11 +68,8 @@ pub fn spawn_scorers(
         let scorers = thinker
             .score_builders
             .iter()
-            .enumerate()
-            .map(|(idx, builder)| {
-                let scorer = cmd
-                    .spawn((ActorRef(actor), ScoreBuilderRef(idx), Score(0.)))
-                    .id();
+            .map(|builder| {
+                let scorer = cmd.spawn((ActorRef(actor), Score(0.))).id();
                 let mut e_cmd = cmd.entity(scorer);
 
                 builder.insert(&mut e_cmd);
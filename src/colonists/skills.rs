@@ -0,0 +1,6 @@
+use bevy::ecs::component::Component;
+
+#[derive(Component, Default)]
+pub struct Skills {
+    pub woodcutting: f32,
+}
@@ -0,0 +1,486 @@
+use bevy::{
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut},
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::{Added, Changed, Has, Or, With, Without},
+        removal_detection::RemovedComponents,
+        system::{Commands, Local, Query, Res},
+    },
+    time::Time,
+    transform::components::Transform,
+    utils::hashbrown::{HashMap, HashSet},
+};
+
+use crate::{common::Distance, BlockChangedEvent, Terrain};
+
+use super::{
+    DestroyItemEvent, InInventory, IsJobCancelled, IsJobCompleted, Item, ItemTag, Job, JobHaul,
+    JobLocation, NavigationGraph, SpawnJobHaulEvent,
+};
+
+/// Seconds between scans for items to haul, changing a zone scans right away
+const STOCKPILE_SCAN_S: f32 = 2.;
+
+/// Floor positions items get hauled to. An empty `accepts` takes any item,
+/// otherwise an item needs one of the tags.
+#[derive(Component)]
+pub struct StockpileZone {
+    pub blocks: Vec<[u32; 3]>,
+    pub accepts: Vec<ItemTag>,
+    /// The loose item lying on each position that has one, whether or not
+    /// the zone accepts it. Kept up to date by `track_stockpile_occupancy`.
+    pub occupied: HashMap<[u32; 3], Entity>,
+}
+
+impl StockpileZone {
+    pub fn new(blocks: Vec<[u32; 3]>, accepts: Vec<ItemTag>) -> Self {
+        Self {
+            blocks,
+            accepts,
+            occupied: HashMap::new(),
+        }
+    }
+
+    pub fn accepts_item(&self, item: &Item) -> bool {
+        self.accepts_tags(&item.tags)
+    }
+
+    pub fn accepts_tags(&self, tags: &[ItemTag]) -> bool {
+        self.accepts.is_empty() || self.accepts.iter().any(|tag| tags.contains(tag))
+    }
+
+    pub fn is_free(&self, pos: &[u32; 3]) -> bool {
+        self.blocks.contains(pos) && !self.occupied.contains_key(pos)
+    }
+
+    /// The unoccupied position closest to `near`, if the zone takes items
+    /// with these tags at all
+    pub fn find_free_cell(&self, near: [u32; 3], tags: &[ItemTag]) -> Option<[u32; 3]> {
+        if !self.accepts_tags(tags) {
+            return None;
+        }
+
+        let near = [near[0] as i32, near[1] as i32, near[2] as i32];
+
+        self.blocks
+            .iter()
+            .filter(|pos| !self.occupied.contains_key(*pos))
+            .min_by_key(|[x, y, z]| {
+                Distance::manhattan([*x as i32, *y as i32, *z as i32], near) as u32
+            })
+            .copied()
+    }
+
+    /// Grow the zone by every position in the box it does not have yet
+    pub fn extend(&mut self, min: [u32; 3], max: [u32; 3]) {
+        for pos in box_positions(min, max) {
+            if !self.blocks.contains(&pos) {
+                self.blocks.push(pos);
+            }
+        }
+    }
+
+    /// Shrink the zone by every position in the box
+    pub fn remove_box(&mut self, min: [u32; 3], max: [u32; 3]) {
+        self.retain(|[x, y, z]| {
+            !(min[0]..=max[0]).contains(x)
+                || !(min[1]..=max[1]).contains(y)
+                || !(min[2]..=max[2]).contains(z)
+        });
+    }
+
+    pub fn retain(&mut self, keep: impl Fn(&[u32; 3]) -> bool) {
+        self.blocks.retain(|pos| keep(pos));
+        self.occupied.retain(|pos, _| keep(pos));
+    }
+
+    /// Moves `item` to `pos` in the occupancy, or out of it for None
+    fn place_item(&mut self, item: Entity, pos: Option<[u32; 3]>) {
+        self.occupied.retain(|_, occupant| *occupant != item);
+
+        if let Some(pos) = pos.filter(|pos| self.blocks.contains(pos)) {
+            self.occupied.entry(pos).or_insert(item);
+        }
+    }
+}
+
+/// Somewhere an item can be put down, an empty cell on walkable ground
+fn is_open_cell(terrain: &Terrain, [x, y, z]: [u32; 3]) -> bool {
+    y > 0 && terrain.get_block(x, y, z).is_empty() && terrain.get_block(x, y - 1, z).is_walkable()
+}
+
+fn box_positions(min: [u32; 3], max: [u32; 3]) -> Vec<[u32; 3]> {
+    let mut positions = vec![];
+
+    for x in min[0]..=max[0] {
+        for y in min[1]..=max[1] {
+            for z in min[2]..=max[2] {
+                positions.push([x, y, z]);
+            }
+        }
+    }
+
+    positions
+}
+
+fn to_block(transform: &Transform) -> [u32; 3] {
+    [
+        transform.translation.x as u32,
+        transform.translation.y as u32,
+        transform.translation.z as u32,
+    ]
+}
+
+#[derive(Event)]
+pub struct DesignateStockpileEvent {
+    pub min: [u32; 3],
+    pub max: [u32; 3],
+    pub accepts: Vec<ItemTag>,
+}
+
+/// A box overlapping a zone with the same filter extends that zone, anything
+/// else becomes a new zone.
+pub fn on_designate_stockpile(
+    mut cmd: Commands,
+    mut ev_designate_stockpile: EventReader<DesignateStockpileEvent>,
+    mut q_zones: Query<&mut StockpileZone>,
+) {
+    for ev in ev_designate_stockpile.read() {
+        let positions = box_positions(ev.min, ev.max);
+
+        let existing = q_zones.iter_mut().find(|zone| {
+            zone.accepts == ev.accepts && positions.iter().any(|pos| zone.blocks.contains(pos))
+        });
+
+        if let Some(mut zone) = existing {
+            zone.extend(ev.min, ev.max);
+            continue;
+        }
+
+        cmd.spawn(StockpileZone::new(positions, ev.accepts.clone()));
+    }
+}
+
+#[derive(Event)]
+pub struct UndesignateStockpileEvent {
+    pub min: [u32; 3],
+    pub max: [u32; 3],
+}
+
+/// Takes the box out of every zone, zones left without positions are removed
+pub fn on_undesignate_stockpile(
+    mut cmd: Commands,
+    mut ev_undesignate_stockpile: EventReader<UndesignateStockpileEvent>,
+    mut q_zones: Query<(Entity, &mut StockpileZone)>,
+) {
+    for ev in ev_undesignate_stockpile.read() {
+        for (entity, mut zone) in q_zones.iter_mut() {
+            zone.remove_box(ev.min, ev.max);
+
+            if zone.blocks.is_empty() {
+                cmd.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+/// Drops positions that can no longer hold an item, because something was
+/// built in them or the floor under them is gone.
+pub fn prune_stockpiles(
+    mut cmd: Commands,
+    terrain: Res<Terrain>,
+    mut ev_block_changed: EventReader<BlockChangedEvent>,
+    mut q_zones: Query<(Entity, &mut StockpileZone)>,
+) {
+    let changed = ev_block_changed
+        .read()
+        .flat_map(|ev| {
+            let [x, y, z] = ev.pos;
+            [[x, y, z], [x, y + 1, z]]
+        })
+        .filter(|pos| !is_open_cell(&terrain, *pos))
+        .collect::<HashSet<_>>();
+
+    if changed.is_empty() {
+        return;
+    }
+
+    for (entity, mut zone) in q_zones.iter_mut() {
+        if !zone.blocks.iter().any(|pos| changed.contains(pos)) {
+            continue;
+        }
+
+        zone.retain(|pos| !changed.contains(pos));
+
+        if zone.blocks.is_empty() {
+            cmd.entity(entity).despawn();
+        }
+    }
+}
+
+/// Keeps `StockpileZone::occupied` in step with the items. Items that are
+/// put down or moved take up their position, items that are picked up or
+/// destroyed free it. New and grown zones are filled in from scratch.
+#[allow(clippy::type_complexity)]
+pub fn track_stockpile_occupancy(
+    mut q_zones: Query<&mut StockpileZone>,
+    q_items: Query<(Entity, &Transform, Has<InInventory>), With<Item>>,
+    q_moved: Query<
+        (Entity, &Transform, Has<InInventory>),
+        (With<Item>, Or<(Changed<Transform>, Added<InInventory>)>),
+    >,
+    mut removed_in_inventory: RemovedComponents<InInventory>,
+    mut ev_destroy_item: EventReader<DestroyItemEvent>,
+) {
+    // destroyed items go first, so an item put down on the cell of one
+    // destroyed in the same frame still takes it
+    let mut moves = ev_destroy_item
+        .read()
+        .map(|ev| (ev.entity, None))
+        .collect::<Vec<_>>();
+
+    moves.extend(
+        q_moved
+            .iter()
+            .map(|(item, transform, held)| (item, (!held).then(|| to_block(transform)))),
+    );
+
+    for item in removed_in_inventory.read() {
+        if let Ok((_, transform, false)) = q_items.get(item) {
+            moves.push((item, Some(to_block(transform))));
+        }
+    }
+
+    for mut zone in q_zones.iter_mut() {
+        // occupancy doesn't count as a change to the zone itself
+        let is_changed = zone.is_changed();
+        let zone = zone.bypass_change_detection();
+
+        if is_changed {
+            zone.occupied.clear();
+
+            for (item, transform, held) in q_items.iter() {
+                if !held {
+                    zone.place_item(item, Some(to_block(transform)));
+                }
+            }
+
+            continue;
+        }
+
+        for (item, pos) in moves.iter() {
+            zone.place_item(*item, *pos);
+        }
+    }
+}
+
+/// Pairs free stockpile positions with loose items the zone accepts, and
+/// spawns a haul job for each pair. Items already sitting on a zone that
+/// accepts them are left alone.
+pub fn scan_stockpiles(
+    mut cmd: Commands,
+    time: Res<Time>,
+    mut timer: Local<f32>,
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    q_zones: Query<&StockpileZone>,
+    q_changed: Query<(), Changed<StockpileZone>>,
+    q_items: Query<(&Item, &Transform, Option<&InInventory>)>,
+    q_hauls: Query<
+        (Entity, &Job, &JobHaul, &JobLocation),
+        (Without<IsJobCancelled>, Without<IsJobCompleted>),
+    >,
+    mut ev_spawn_job_haul: EventWriter<SpawnJobHaulEvent>,
+) {
+    *timer += time.delta_seconds();
+
+    if *timer < STOCKPILE_SCAN_S && q_changed.is_empty() {
+        return;
+    }
+
+    *timer = 0.;
+
+    let mut targeted_items = HashSet::new();
+    let mut targeted_positions = HashSet::new();
+
+    for (entity, job, haul, location) in q_hauls.iter() {
+        // the item is gone, or a failed haul left it in someone's inventory
+        let is_stale = q_items.get(haul.item).map_or(true, |(_, _, held)| {
+            held.is_some() && job.assignee.is_none()
+        });
+
+        // the position was taken out of its stockpile, or built over
+        let is_dropped = job.assignee.is_none()
+            && !q_zones
+                .iter()
+                .any(|zone| zone.blocks.contains(&location.pos));
+
+        if is_stale || is_dropped {
+            cmd.entity(entity).insert(IsJobCancelled);
+            continue;
+        }
+
+        targeted_items.insert(haul.item);
+        targeted_positions.insert(location.pos);
+    }
+
+    let is_stored = |item: &Item, pos: &[u32; 3]| {
+        q_zones
+            .iter()
+            .any(|zone| zone.blocks.contains(pos) && zone.accepts_item(item))
+    };
+
+    for zone in q_zones.iter() {
+        let mut open = zone
+            .blocks
+            .iter()
+            .filter(|pos| {
+                zone.is_free(pos)
+                    && !targeted_positions.contains(*pos)
+                    && is_open_cell(&terrain, **pos)
+            })
+            .copied()
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        let candidates = if zone.accepts.is_empty() {
+            graph.items_of_type(&[], usize::MAX)
+        } else {
+            zone.accepts
+                .iter()
+                .flat_map(|tag| graph.items_of_type(std::slice::from_ref(tag), usize::MAX))
+                .collect()
+        };
+
+        for (_, item_entity) in candidates {
+            if targeted_items.contains(&item_entity) {
+                continue;
+            }
+
+            let Ok((item, transform, held)) = q_items.get(item_entity) else {
+                continue;
+            };
+
+            if held.is_some()
+                || item.reserved.is_some()
+                || !zone.accepts_item(item)
+                || is_stored(item, &to_block(transform))
+            {
+                continue;
+            }
+
+            let Some(pos) = open.next() else {
+                break;
+            };
+
+            targeted_items.insert(item_entity);
+            targeted_positions.insert(pos);
+
+            ev_spawn_job_haul.send(SpawnJobHaulEvent {
+                item: item_entity,
+                pos,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{event::Events, schedule::Schedule, world::World};
+
+    use super::*;
+
+    fn stone() -> Item {
+        Item {
+            tags: vec![ItemTag::Stone],
+            reserved: None,
+        }
+    }
+
+    fn drop_at(world: &mut World, item: Entity, [x, y, z]: [u32; 3]) {
+        world.entity_mut(item).remove::<InInventory>();
+        world.get_mut::<Transform>(item).unwrap().translation =
+            Transform::from_xyz(x as f32 + 0.5, y as f32, z as f32 + 0.5).translation;
+    }
+
+    fn occupied(world: &World, zone: Entity) -> Vec<([u32; 3], Entity)> {
+        let zone = world.get::<StockpileZone>(zone).unwrap();
+        let mut occupied = zone
+            .occupied
+            .iter()
+            .map(|(pos, item)| (*pos, *item))
+            .collect::<Vec<_>>();
+        occupied.sort_by_key(|(pos, _)| *pos);
+        occupied
+    }
+
+    #[test]
+    fn occupancy_follows_several_hauls_at_once() {
+        let mut world = World::new();
+        world.init_resource::<Events<DestroyItemEvent>>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(track_stockpile_occupancy);
+
+        let zone = world
+            .spawn(StockpileZone::new(
+                box_positions([1, 1, 1], [2, 1, 2]),
+                vec![],
+            ))
+            .id();
+        let loose = world
+            .spawn((stone(), Transform::from_xyz(2.5, 1., 2.5)))
+            .id();
+        let hauler = world.spawn_empty().id();
+        let carried = (0..4)
+            .map(|_| {
+                world
+                    .spawn((
+                        stone(),
+                        Transform::from_xyz(6.5, 1., 6.5),
+                        InInventory { holder: hauler },
+                    ))
+                    .id()
+            })
+            .collect::<Vec<_>>();
+
+        schedule.run(&mut world);
+        assert_eq!(occupied(&world, zone), vec![([2, 1, 2], loose)]);
+
+        // three hauls finish while the loose stone is picked up
+        drop_at(&mut world, carried[0], [1, 1, 1]);
+        drop_at(&mut world, carried[1], [1, 1, 2]);
+        drop_at(&mut world, carried[2], [2, 1, 1]);
+        world
+            .entity_mut(loose)
+            .insert(InInventory { holder: hauler });
+        schedule.run(&mut world);
+
+        assert_eq!(
+            occupied(&world, zone),
+            vec![
+                ([1, 1, 1], carried[0]),
+                ([1, 1, 2], carried[1]),
+                ([2, 1, 1], carried[2]),
+            ]
+        );
+        let free = world
+            .get::<StockpileZone>(zone)
+            .unwrap()
+            .find_free_cell([0, 1, 0], &[ItemTag::Stone]);
+        assert_eq!(free, Some([2, 1, 2]));
+
+        // a stone is used up in the same frame another is hauled onto its
+        // cell, and one is carried off the zone
+        world.send_event(DestroyItemEvent { entity: carried[0] });
+        drop_at(&mut world, carried[3], [1, 1, 1]);
+        drop_at(&mut world, carried[1], [5, 1, 5]);
+        schedule.run(&mut world);
+
+        assert_eq!(
+            occupied(&world, zone),
+            vec![([1, 1, 1], carried[3]), ([2, 1, 1], carried[2])]
+        );
+    }
+}
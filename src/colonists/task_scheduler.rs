@@ -0,0 +1,65 @@
+use std::any::TypeId;
+
+use bevy::{
+    ecs::{
+        schedule::SystemSet,
+        system::{ResMut, Resource},
+    },
+    utils::hashbrown::HashMap,
+};
+
+use super::{TaskFindNearestItem, TaskMoveTo};
+
+/// Item searches started per frame, each one walks the partition graph
+const FIND_NEAREST_ITEM_LIMIT: usize = 5;
+/// Paths requested per frame by `TaskMoveTo`
+const MOVE_TO_PATH_LIMIT: usize = 3;
+
+/// Runs before any task system that asks the `TaskScheduler` for a slot
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TaskSchedulerSet;
+
+/// Caps how many tasks of a type do their expensive work in one frame. A
+/// task that doesn't get a slot stays `Executing` and tries again next frame,
+/// so being deferred never fails it. Task types without a limit always run.
+#[derive(Resource)]
+pub struct TaskScheduler {
+    limits: HashMap<TypeId, usize>,
+    running: HashMap<TypeId, usize>,
+}
+
+impl Default for TaskScheduler {
+    fn default() -> Self {
+        Self {
+            limits: HashMap::from([
+                (TypeId::of::<TaskFindNearestItem>(), FIND_NEAREST_ITEM_LIMIT),
+                (TypeId::of::<TaskMoveTo>(), MOVE_TO_PATH_LIMIT),
+            ]),
+            running: HashMap::new(),
+        }
+    }
+}
+
+impl TaskScheduler {
+    /// Takes one of this frame's slots for `T`, false if they are used up
+    pub fn try_run<T: 'static>(&mut self) -> bool {
+        let type_id = TypeId::of::<T>();
+
+        let Some(limit) = self.limits.get(&type_id) else {
+            return true;
+        };
+
+        let running = self.running.entry(type_id).or_insert(0);
+
+        if *running >= *limit {
+            return false;
+        }
+
+        *running += 1;
+        true
+    }
+}
+
+pub fn reset_task_scheduler(mut scheduler: ResMut<TaskScheduler>) {
+    scheduler.running.clear();
+}
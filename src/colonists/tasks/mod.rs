@@ -1,10 +1,20 @@
 mod task_assign_job;
 mod task_build;
 mod task_check_has_item;
+mod task_chop;
+mod task_clear_rubble;
+mod task_craft;
 mod task_debug;
+mod task_deliver_item;
+mod task_eat;
+mod task_farm;
 mod task_find_bed;
+mod task_find_campfire;
+mod task_find_haul_item;
 mod task_find_nearest_item;
 mod task_get_job_location;
+mod task_guard;
+mod task_haul;
 mod task_idle;
 mod task_is_target_empty;
 mod task_job_cancel;
@@ -12,17 +22,34 @@ mod task_job_complete;
 mod task_job_unassign;
 mod task_mine_block;
 mod task_move_to;
+mod task_patrol;
 mod task_pick_random_spot;
 mod task_pick_up_item;
+mod task_place_torch;
+mod task_release_item;
+mod task_remove_rot;
+mod task_sequence;
+mod task_set_move_goals;
 mod task_sleep;
+mod task_tantrum;
 
 pub use task_assign_job::*;
 pub use task_build::*;
 pub use task_check_has_item::*;
+pub use task_chop::*;
+pub use task_clear_rubble::*;
+pub use task_craft::*;
 pub use task_debug::*;
+pub use task_deliver_item::*;
+pub use task_eat::*;
+pub use task_farm::*;
 pub use task_find_bed::*;
+pub use task_find_campfire::*;
+pub use task_find_haul_item::*;
 pub use task_find_nearest_item::*;
 pub use task_get_job_location::*;
+pub use task_guard::*;
+pub use task_haul::*;
 pub use task_idle::*;
 pub use task_is_target_empty::*;
 pub use task_job_cancel::*;
@@ -30,6 +57,13 @@ pub use task_job_complete::*;
 pub use task_job_unassign::*;
 pub use task_mine_block::*;
 pub use task_move_to::*;
+pub use task_patrol::*;
 pub use task_pick_random_spot::*;
 pub use task_pick_up_item::*;
+pub use task_place_torch::*;
+pub use task_release_item::*;
+pub use task_remove_rot::*;
+pub use task_sequence::*;
+pub use task_set_move_goals::*;
 pub use task_sleep::*;
+pub use task_tantrum::*;
@@ -38,7 +38,7 @@ pub fn task_assign_job(
         }
 
         job.assignee = Some(*actor);
-        cmd.entity(*actor).insert(JobAssignment { job: task.0 });
+        cmd.entity(*actor).insert(JobAssignment);
 
         blackboard.job = Some(task.0);
         *state = TaskState::Success;
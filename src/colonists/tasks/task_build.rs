@@ -2,28 +2,39 @@ use bevy::{
     ecs::{
         component::Component,
         event::EventWriter,
+        query::With,
         system::{Query, Res, ResMut},
     },
     time::Time,
+    transform::components::Transform,
 };
 use task_derive::TaskBuilder;
 
 use crate::{
-    colonists::{Blackboard, DestroyItemEvent, TaskBuilder, TaskState},
-    BlockType, Terrain,
+    colonists::{Actor, Blackboard, DestroyItemEvent, TaskBuilder, TaskState},
+    BlockChangedEvent, BlockType, Terrain,
 };
 
 #[derive(Component, Clone, TaskBuilder)]
+#[estimated_duration(remaining_s)]
 pub struct TaskBuildBlock {
     pub progress: f32,
     pub block: BlockType,
 }
 
+impl TaskBuildBlock {
+    fn remaining_s(&self, _blackboard: &Blackboard) -> Option<f32> {
+        Some((1. - self.progress).max(0.))
+    }
+}
+
 pub fn task_build_block(
     time: Res<Time>,
     mut terrain: ResMut<Terrain>,
+    q_actors: Query<&Transform, With<Actor>>,
     mut q_behavior: Query<(&mut TaskState, &Blackboard, &mut TaskBuildBlock)>,
     mut ev_destroy_item: EventWriter<DestroyItemEvent>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
 ) {
     for (mut state, blackboard, mut task) in q_behavior.iter_mut() {
         let Some([x, y, z]) = blackboard.target_block else {
@@ -45,6 +56,23 @@ pub fn task_build_block(
             continue;
         }
 
+        // an actor stands in the cell, or has its head in it
+        let is_occupied = q_actors.iter().any(|transform| {
+            let pos = [
+                transform.translation.x as u32,
+                transform.translation.y as u32,
+                transform.translation.z as u32,
+            ];
+
+            pos == [x, y, z] || (y > 0 && pos == [x, y - 1, z])
+        });
+
+        if is_occupied {
+            println!("Someone is standing in the blueprint, cannot build!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
         if blackboard.item.is_none() {
             println!("Blackboard is missing item, cannot place!");
             *state = TaskState::Failed;
@@ -53,7 +81,8 @@ pub fn task_build_block(
 
         if task.progress >= 1. {
             terrain.set_flag_blueprint(x, y, z, false);
-            terrain.set_block_type(x, y, z, task.block);
+            let change = terrain.set_block(x, y, z, task.block);
+            ev_block_changed.send(change.into());
 
             let item = blackboard.item.unwrap();
             ev_destroy_item.send(DestroyItemEvent { entity: item });
@@ -65,3 +94,180 @@ pub fn task_build_block(
         task.progress += time.delta_seconds();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{
+        entity::Entity,
+        event::Events,
+        schedule::{IntoSystemConfigs, Schedule},
+        system::RunSystemOnce,
+        world::World,
+    };
+
+    use crate::colonists::{
+        destroy_items, on_spawn_job_build, ActorRef, InInventory, Inventory, Item, ItemTag,
+        NavigationGraph, SpawnJobBuildEvent,
+    };
+
+    use super::*;
+
+    const WALL: [[u32; 3]; 3] = [[2, 1, 2], [3, 1, 2], [4, 1, 2]];
+
+    /// A stone floor with a three block wall designated on it
+    fn designated_wall() -> World {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+
+        for x in 0..8 {
+            for z in 0..8 {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+            }
+        }
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<Time>();
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<Events<SpawnJobBuildEvent>>();
+        world.init_resource::<Events<DestroyItemEvent>>();
+        world.init_resource::<Events<BlockChangedEvent>>();
+
+        for pos in WALL {
+            world.send_event(SpawnJobBuildEvent {
+                pos,
+                block: BlockType::STONE,
+            });
+        }
+        world.run_system_once(on_spawn_job_build);
+
+        world
+    }
+
+    /// A builder at `pos` carrying one stone per wall block, and a build task
+    /// for each block using up one of them
+    fn spawn_builder(world: &mut World, pos: [f32; 3]) -> (Entity, Vec<Entity>, Vec<Entity>) {
+        let builder = world
+            .spawn((Actor, Transform::from_xyz(pos[0], pos[1], pos[2])))
+            .id();
+
+        let stones = WALL
+            .iter()
+            .map(|_| {
+                world
+                    .spawn((
+                        Item {
+                            tags: vec![ItemTag::Stone],
+                            reserved: Some(builder),
+                        },
+                        InInventory { holder: builder },
+                    ))
+                    .id()
+            })
+            .collect::<Vec<_>>();
+
+        world.entity_mut(builder).insert(Inventory {
+            items: stones.clone(),
+        });
+
+        let tasks = WALL
+            .iter()
+            .zip(stones.iter())
+            .map(|(pos, stone)| {
+                world
+                    .spawn((
+                        ActorRef(builder),
+                        TaskState::Executing,
+                        Blackboard {
+                            item: Some(*stone),
+                            target_block: Some(*pos),
+                            ..Blackboard::default()
+                        },
+                        TaskBuildBlock {
+                            progress: 0.,
+                            block: BlockType::STONE,
+                        },
+                    ))
+                    .id()
+            })
+            .collect();
+
+        (builder, stones, tasks)
+    }
+
+    /// Ticks until every task is done, taking finished tasks off like
+    /// `behavior_system` would
+    fn run(world: &mut World, schedule: &mut Schedule, tasks: &[Entity]) {
+        for _ in 0..20 {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(0.25));
+            schedule.run(world);
+
+            for task in tasks.iter() {
+                if *world.get::<TaskState>(*task).unwrap() != TaskState::Executing {
+                    world.entity_mut(*task).remove::<TaskBuildBlock>();
+                }
+            }
+        }
+    }
+
+    fn build_schedule() -> Schedule {
+        let mut schedule = Schedule::default();
+        schedule.add_systems((task_build_block, destroy_items).chain());
+        schedule
+    }
+
+    #[test]
+    fn wall_is_built_from_carried_stone() {
+        let mut world = designated_wall();
+        let (builder, stones, tasks) = spawn_builder(&mut world, [3.5, 1., 4.5]);
+        let mut schedule = build_schedule();
+
+        for pos in WALL {
+            let block = world
+                .resource::<Terrain>()
+                .get_block(pos[0], pos[1], pos[2]);
+            assert!(block.flag_blueprint);
+        }
+
+        run(&mut world, &mut schedule, &tasks);
+
+        for task in tasks {
+            assert!(*world.get::<TaskState>(task).unwrap() == TaskState::Success);
+        }
+
+        let terrain = world.resource::<Terrain>();
+        for [x, y, z] in WALL {
+            let block = terrain.get_block(x, y, z);
+            assert_eq!(block.block, BlockType::STONE);
+            assert!(!block.flag_blueprint);
+        }
+
+        // every stone went into the wall
+        for stone in stones {
+            assert!(world.get_entity(stone).is_none());
+        }
+        assert!(world.get::<Inventory>(builder).unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn occupied_blueprint_is_not_built() {
+        let mut world = designated_wall();
+        // standing in the middle block of the wall
+        let (_, stones, tasks) = spawn_builder(&mut world, [3.5, 1., 2.5]);
+        let mut schedule = build_schedule();
+
+        run(&mut world, &mut schedule, &tasks);
+
+        assert!(*world.get::<TaskState>(tasks[1]).unwrap() == TaskState::Failed);
+        assert!(
+            world
+                .resource::<Terrain>()
+                .get_block(3, 1, 2)
+                .flag_blueprint
+        );
+        assert!(world.get_entity(stones[1]).is_some());
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    ecs::{
+        component::Component,
+        event::EventWriter,
+        system::{Query, Res, ResMut},
+    },
+    time::Time,
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{job_access_points, ActorRef, Blackboard, JobType, Skills, TaskBuilder, TaskState},
+    items::SpawnWoodEvent,
+    BlockChangedEvent, BlockType, Terrain,
+};
+
+#[derive(Component, Clone, TaskBuilder)]
+#[estimated_duration(remaining_s)]
+pub struct TaskChop {
+    pub target: [u32; 3],
+    pub progress: f32,
+}
+
+impl TaskChop {
+    fn remaining_s(&self, _blackboard: &Blackboard) -> Option<f32> {
+        Some((1. - self.progress).max(0.))
+    }
+}
+
+pub fn task_chop(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    mut q_actors: Query<(&Transform, &mut Skills)>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &mut TaskChop)>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+    mut ev_spawn_wood: EventWriter<SpawnWoodEvent>,
+) {
+    for (ActorRef(actor), mut state, mut task) in q_behavior.iter_mut() {
+        let [x, y, z] = task.target;
+
+        if terrain.get_block(x, y, z).block != BlockType::LOG {
+            println!("Target is not a log, cannot chop!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        let Ok((transform, mut skills)) = q_actors.get_mut(*actor) else {
+            println!("Actor is missing transform or skills, cannot chop!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        if !job_access_points(task.target, JobType::Mine).contains(&pos) {
+            println!("Actor is not next to the log, cannot chop!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        if task.progress < 1. {
+            task.progress += time.delta_seconds();
+            continue;
+        }
+
+        for [cx, cy, cz] in get_tree_blocks(&terrain, task.target) {
+            let change = terrain.set_block(cx, cy, cz, BlockType::EMPTY);
+            ev_block_changed.send(change.into());
+        }
+
+        terrain.set_flag_mine(x, y, z, false);
+        ev_spawn_wood.send(SpawnWoodEvent { pos: task.target });
+        skills.woodcutting += 1.;
+
+        *state = TaskState::Success;
+    }
+}
+
+/// Leaves further than this many steps from the trunk are left hanging
+const MAX_LEAF_STEPS: u32 = 4;
+
+/// Returns the trunk above (and including) the given log, plus the leaves
+/// reachable from it through other leaves within `MAX_LEAF_STEPS` steps.
+/// Leaves touching a log of another tree belong to that tree and stop the
+/// fill, so neighboring canopies are not cut down along with this one.
+fn get_tree_blocks(terrain: &Terrain, base: [u32; 3]) -> Vec<[u32; 3]> {
+    let [bx, by, bz] = base;
+    let mut trunk = vec![];
+
+    for y in by..terrain.world_size_y() {
+        if terrain.get_block(bx, y, bz).block != BlockType::LOG {
+            break;
+        }
+
+        trunk.push([bx, y, bz]);
+    }
+
+    let neighbors = |[x, y, z]: [u32; 3]| {
+        [
+            [1, 0, 0],
+            [-1, 0, 0],
+            [0, 1, 0],
+            [0, -1, 0],
+            [0, 0, 1],
+            [0, 0, -1],
+        ]
+        .into_iter()
+        .map(move |[dx, dy, dz]| [x as i32 + dx, y as i32 + dy, z as i32 + dz])
+        .filter(|[x, y, z]| !terrain.is_oob(*x, *y, *z))
+        .map(|[x, y, z]| [x as u32, y as u32, z as u32])
+    };
+
+    let mut blocks = trunk.clone();
+    let mut queue: VecDeque<([u32; 3], u32)> = trunk.iter().map(|pos| (*pos, 0)).collect();
+
+    while let Some((pos, steps)) = queue.pop_front() {
+        if steps >= MAX_LEAF_STEPS {
+            continue;
+        }
+
+        for next in neighbors(pos) {
+            if blocks.contains(&next)
+                || terrain.get_block(next[0], next[1], next[2]).block != BlockType::LEAVES
+            {
+                continue;
+            }
+
+            let is_other_tree = neighbors(next).any(|n| {
+                terrain.get_block(n[0], n[1], n[2]).block == BlockType::LOG && !trunk.contains(&n)
+            });
+
+            if is_other_tree {
+                continue;
+            }
+
+            blocks.push(next);
+            queue.push_back((next, steps + 1));
+        }
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plant_trunk(terrain: &mut Terrain, x: u32, z: u32) {
+        for y in 1..=3 {
+            terrain.set_block(x, y, z, BlockType::LOG);
+        }
+    }
+
+    #[test]
+    fn felling_takes_only_connected_leaves() {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+
+        plant_trunk(&mut terrain, 2, 2);
+        plant_trunk(&mut terrain, 6, 2);
+
+        // a canopy bridge from one tree to the other along x, and a long
+        // branch along z
+        for x in 2..=6 {
+            terrain.set_block(x, 4, 2, BlockType::LEAVES);
+        }
+        for z in 3..=7 {
+            terrain.set_block(2, 4, z, BlockType::LEAVES);
+        }
+        // a loose leaf within the old box radius, touching nothing
+        terrain.set_block(4, 6, 4, BlockType::LEAVES);
+
+        let blocks = get_tree_blocks(&terrain, [2, 1, 2]);
+
+        for y in 1..=3 {
+            assert!(blocks.contains(&[2, y, 2]));
+            assert!(!blocks.contains(&[6, y, 2]));
+        }
+
+        for x in 2..=5 {
+            assert!(blocks.contains(&[x, 4, 2]));
+        }
+        // sits on the other trunk
+        assert!(!blocks.contains(&[6, 4, 2]));
+
+        for z in 3..=5 {
+            assert!(blocks.contains(&[2, 4, z]));
+        }
+        // more than four steps out from the trunk
+        assert!(!blocks.contains(&[2, 4, 6]));
+        assert!(!blocks.contains(&[2, 4, 7]));
+
+        assert!(!blocks.contains(&[4, 6, 4]));
+        assert_eq!(blocks.len(), 3 + 4 + 3);
+    }
+}
@@ -0,0 +1,50 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        event::EventWriter,
+        query::With,
+        system::{Query, Res, ResMut},
+    },
+    time::Time,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{ActorRef, Blackboard, Mood, TaskBuilder, TaskState},
+    BlockChangedEvent, BlockType, Terrain,
+};
+
+/// Shovel the rubble left by a cave-in out of the target block. Fails on
+/// anything that isn't rubble, so mining falls through to `TaskMineBlock`.
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskClearRubble;
+
+pub fn task_clear_rubble(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    q_moods: Query<&Mood>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &Blackboard), With<TaskClearRubble>>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+) {
+    for (ActorRef(actor), mut state, blackboard) in q_behavior.iter_mut() {
+        let Some([x, y, z]) = blackboard.target_block else {
+            println!("Blackboard is missing target_block, cannot clear rubble!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        if terrain.get_block(x, y, z).block != BlockType::RUBBLE {
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        let mood_factor = q_moods.get(*actor).map_or(1., |mood| mood.speed_factor());
+
+        if terrain.add_block_damage(x, y, z, time.delta_seconds() * mood_factor) {
+            let change = terrain.set_block(x, y, z, BlockType::EMPTY);
+            terrain.set_flag_mine(x, y, z, false);
+            ev_block_changed.send(change.into());
+            *state = TaskState::Success;
+        }
+    }
+}
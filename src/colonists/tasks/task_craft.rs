@@ -0,0 +1,158 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        event::EventWriter,
+        system::{Query, Res},
+    },
+    time::Time,
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{
+        job_access_points, test_item_tags, ActorRef, Blackboard, DestroyItemEvent, Inventory, Item,
+        ItemTag, JobType, TaskBuilder, TaskState,
+    },
+    items::{SpawnFoodEvent, SpawnTorchEvent},
+    BlockType, Terrain,
+};
+
+#[derive(Clone)]
+pub struct Recipe {
+    pub inputs: Vec<ItemTag>,
+    pub outputs: Vec<ItemTag>,
+    pub station: BlockType,
+    pub duration_s: f32,
+}
+
+impl Recipe {
+    pub fn cook_food() -> Self {
+        Self {
+            inputs: vec![ItemTag::RawFood],
+            outputs: vec![ItemTag::CookedFood],
+            station: BlockType::CAMPFIRE,
+            duration_s: 3.,
+        }
+    }
+
+    pub fn torch() -> Self {
+        Self {
+            inputs: vec![ItemTag::Wood, ItemTag::Coal],
+            outputs: vec![ItemTag::Torch],
+            station: BlockType::CAMPFIRE,
+            duration_s: 2.,
+        }
+    }
+}
+
+#[derive(Component, Clone, TaskBuilder)]
+#[estimated_duration(remaining_s)]
+pub struct TaskCraft {
+    pub recipe: Recipe,
+    pub progress: f32,
+}
+
+impl TaskCraft {
+    fn remaining_s(&self, _blackboard: &Blackboard) -> Option<f32> {
+        Some((self.recipe.duration_s - self.progress).max(0.))
+    }
+}
+
+pub fn task_craft(
+    time: Res<Time>,
+    terrain: Res<Terrain>,
+    q_items: Query<&Item>,
+    mut q_actors: Query<(&Transform, &mut Inventory)>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &Blackboard, &mut TaskCraft)>,
+    mut ev_destroy_item: EventWriter<DestroyItemEvent>,
+    mut ev_spawn_food: EventWriter<SpawnFoodEvent>,
+    mut ev_spawn_torch: EventWriter<SpawnTorchEvent>,
+) {
+    for (ActorRef(actor), mut state, blackboard, mut task) in q_behavior.iter_mut() {
+        let Some([x, y, z]) = blackboard.target_block else {
+            println!("Blackboard is missing target_block, cannot craft!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        if terrain.get_block(x, y, z).block != task.recipe.station {
+            println!(
+                "Target is not a {}, cannot craft!",
+                task.recipe.station.name()
+            );
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        let Ok((transform, mut inventory)) = q_actors.get_mut(*actor) else {
+            println!("Actor is missing transform or inventory, cannot craft!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        if !job_access_points([x, y, z], JobType::Mine).contains(&pos) {
+            println!("Actor is not next to the station, cannot craft!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        // every input is a separate item
+        let mut inputs: Vec<usize> = vec![];
+
+        for tag in task.recipe.inputs.iter() {
+            let input = inventory.items.iter().enumerate().position(|(idx, e)| {
+                !inputs.contains(&idx)
+                    && q_items
+                        .get(*e)
+                        .is_ok_and(|item| test_item_tags(&item.tags, &[*tag]))
+            });
+
+            if let Some(idx) = input {
+                inputs.push(idx);
+            }
+        }
+
+        if inputs.len() < task.recipe.inputs.len() {
+            println!("Actor is missing recipe inputs, cannot craft!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        if task.progress < task.recipe.duration_s {
+            task.progress += time.delta_seconds();
+            continue;
+        }
+
+        // remove from the back so the other indexes stay valid
+        inputs.sort_unstable_by(|a, b| b.cmp(a));
+
+        for idx in inputs {
+            let entity = inventory.items.remove(idx);
+            ev_destroy_item.send(DestroyItemEvent { entity });
+        }
+
+        for output in task.recipe.outputs.iter() {
+            match output {
+                ItemTag::CookedFood => {
+                    ev_spawn_food.send(SpawnFoodEvent {
+                        pos,
+                        is_cooked: true,
+                    });
+                }
+                ItemTag::Torch => {
+                    ev_spawn_torch.send(SpawnTorchEvent { pos });
+                }
+                _ => println!("Don't know how to spawn {}", output),
+            }
+        }
+
+        *state = TaskState::Success;
+    }
+}
@@ -0,0 +1,249 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{With, Without},
+        system::{Commands, Query, Res, ResMut},
+    },
+    render::view::Visibility,
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{
+        is_item_available, request_path, step_path, Actor, ActorRef, BlockMove, CarryCapacity,
+        InInventory, InPartition, Inventory, Item, ItemTag, MovementStats, NavigationFlags,
+        NavigationGraph, Path, PathCache, PathStep, TaskBuilder, TaskState,
+    },
+    Terrain,
+};
+
+/// Carry one item with matching tags out of `from_partition` and put it down
+/// in `to_partition`, e.g. stone from a mine stockpile to a build site. Fails
+/// as soon as either partition is gone after a repartition.
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskDeliverItem {
+    pub item_tags: Vec<ItemTag>,
+    pub from_partition: u32,
+    pub to_partition: u32,
+    /// The reserved item, carried once `is_carrying` is set
+    item: Option<Entity>,
+    is_carrying: bool,
+}
+
+impl TaskDeliverItem {
+    pub fn new(item_tags: Vec<ItemTag>, from_partition: u32, to_partition: u32) -> Self {
+        Self {
+            item_tags,
+            from_partition,
+            to_partition,
+            item: None,
+            is_carrying: false,
+        }
+    }
+}
+
+/// Every block of a partition, used as path goals to walk into it
+fn partition_goals(terrain: &Terrain, graph: &NavigationGraph, partition_id: u32) -> Vec<[u32; 3]> {
+    let Some(partition) = graph.get_partition(&partition_id) else {
+        return vec![];
+    };
+
+    partition
+        .blocks
+        .iter()
+        .map(|block_idx| terrain.get_block_world_pos(partition.chunk_idx, *block_idx))
+        .collect()
+}
+
+pub fn task_deliver_item(
+    mut cmd: Commands,
+    terrain: Res<Terrain>,
+    mut graph: ResMut<NavigationGraph>,
+    mut path_cache: ResMut<PathCache>,
+    mut q_paths: Query<&mut Path, With<Actor>>,
+    q_movers: Query<&BlockMove, With<Actor>>,
+    mut q_actors: Query<
+        (
+            &Transform,
+            Option<&MovementStats>,
+            &mut Inventory,
+            &mut CarryCapacity,
+        ),
+        With<Actor>,
+    >,
+    mut q_items: Query<(&mut Transform, &mut Item), Without<Actor>>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &mut TaskDeliverItem)>,
+) {
+    for (ActorRef(actor), mut state, mut task) in q_behavior.iter_mut() {
+        let Ok((transform, stats, mut inventory, mut capacity)) = q_actors.get_mut(*actor) else {
+            println!("no inventory on actor, cannot deliver!");
+            cmd.entity(*actor).remove::<Path>();
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        if graph.get_partition(&task.from_partition).is_none()
+            || graph.get_partition(&task.to_partition).is_none()
+        {
+            println!("delivery partition is gone!");
+
+            if let Some((_, mut item)) = task.item.and_then(|e| q_items.get_mut(e).ok()) {
+                if !task.is_carrying {
+                    item.reserved = None;
+                }
+            }
+
+            cmd.entity(*actor).remove::<Path>();
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        if q_movers.contains(*actor) {
+            continue;
+        }
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+        let stats = stats.copied().unwrap_or_default();
+
+        let item = match task.item {
+            Some(item) => item,
+            None => {
+                let max_weight = capacity.remaining();
+                let from = graph.get_partition(&task.from_partition).unwrap();
+
+                let found = from.items.iter().copied().find(|e| {
+                    q_items
+                        .get(*e)
+                        .is_ok_and(|(_, item)| is_item_available(item, &task.item_tags, max_weight))
+                });
+
+                let Some(found) = found else {
+                    println!("nothing to deliver in partition {}", task.from_partition);
+                    *state = TaskState::Failed;
+                    continue;
+                };
+
+                if let Ok((_, mut item)) = q_items.get_mut(found) {
+                    item.reserved = Some(*actor);
+                }
+
+                task.item = Some(found);
+                found
+            }
+        };
+
+        let Ok((mut item_transform, mut item_data)) = q_items.get_mut(item) else {
+            println!("delivery item no longer exists!");
+            cmd.entity(*actor).remove::<Path>();
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        if let Ok(mut path) = q_paths.get_mut(*actor) {
+            if let PathStep::Stranded = step_path(
+                &mut cmd,
+                &terrain,
+                &graph,
+                &mut path_cache,
+                *actor,
+                pos,
+                &stats,
+                &mut path,
+            ) {
+                if !task.is_carrying {
+                    item_data.reserved = None;
+                }
+                *state = TaskState::Failed;
+            }
+            continue;
+        }
+
+        if !task.is_carrying {
+            let item_pos = [
+                item_transform.translation.x as u32,
+                item_transform.translation.y as u32,
+                item_transform.translation.z as u32,
+            ];
+
+            if pos != item_pos {
+                match request_path(
+                    &terrain,
+                    &graph,
+                    pos,
+                    vec![item_pos],
+                    NavigationFlags::COLONIST,
+                ) {
+                    Some(path) => {
+                        cmd.entity(*actor).insert(path);
+                    }
+                    None => {
+                        println!("delivery item is unreachable!");
+                        item_data.reserved = None;
+                        *state = TaskState::Failed;
+                    }
+                }
+                continue;
+            }
+
+            if !graph.remove_item(&task.from_partition, &item)
+                || inventory
+                    .add_item(item, item_data.weight(), &mut capacity)
+                    .is_err()
+            {
+                println!("cannot pick up delivery item!");
+                item_data.reserved = None;
+                *state = TaskState::Failed;
+                continue;
+            }
+
+            let mut ecmd = cmd.entity(item);
+            ecmd.remove::<InPartition>();
+            ecmd.insert(Visibility::Hidden);
+            ecmd.insert(InInventory { holder: *actor });
+
+            task.is_carrying = true;
+            continue;
+        }
+
+        if terrain.get_partition_id_u32(pos[0], pos[1], pos[2]) != Some(task.to_partition) {
+            let goals = partition_goals(&terrain, &graph, task.to_partition);
+
+            match request_path(&terrain, &graph, pos, goals, NavigationFlags::COLONIST) {
+                Some(path) => {
+                    cmd.entity(*actor).insert(path);
+                }
+                None => {
+                    println!("delivery destination is unreachable!");
+                    *state = TaskState::Failed;
+                }
+            }
+            continue;
+        }
+
+        let [x, y, z] = pos;
+
+        inventory.items.retain(|e| *e != item);
+        item_data.reserved = None;
+        item_transform.translation.x = x as f32 + 0.5;
+        item_transform.translation.y = y as f32;
+        item_transform.translation.z = z as f32 + 0.5;
+
+        let mut ecmd = cmd.entity(item);
+        ecmd.remove::<InInventory>();
+        ecmd.insert(Visibility::Visible);
+
+        if graph.add_item(&task.to_partition, item, &item_data.tags) {
+            ecmd.insert(InPartition {
+                partition_id: task.to_partition,
+            });
+        }
+
+        *state = TaskState::Success;
+    }
+}
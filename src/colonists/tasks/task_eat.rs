@@ -0,0 +1,58 @@
+use bevy::ecs::{component::Component, event::EventWriter, query::With, system::Query};
+use task_derive::TaskBuilder;
+
+use crate::colonists::{
+    ActorRef, Blackboard, DestroyItemEvent, Hunger, Inventory, Item, ItemTag, TaskBuilder,
+    TaskState,
+};
+
+/// Hunger taken away by a cooked meal, raw food fills half as much
+const COOKED_FOOD_VALUE: f32 = 60.;
+
+/// Eat the blackboard item, which has to be food in the actor's inventory
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskEat;
+
+pub fn task_eat(
+    q_items: Query<&Item>,
+    mut q_actors: Query<(&mut Inventory, &mut Hunger)>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &Blackboard), With<TaskEat>>,
+    mut ev_destroy_item: EventWriter<DestroyItemEvent>,
+) {
+    for (ActorRef(actor), mut state, blackboard) in q_behavior.iter_mut() {
+        let Some(item) = blackboard.item else {
+            println!("No item on blackboard, cannot eat!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let Ok((mut inventory, mut hunger)) = q_actors.get_mut(*actor) else {
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let Ok(item_data) = q_items.get(item) else {
+            println!("Food does not exist, cannot eat!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        if !item_data.tags.contains(&ItemTag::Food) || !inventory.items.contains(&item) {
+            println!("Not holding any food, cannot eat!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        let value = if item_data.tags.contains(&ItemTag::CookedFood) {
+            COOKED_FOOD_VALUE
+        } else {
+            COOKED_FOOD_VALUE / 2.
+        };
+
+        inventory.items.retain(|e| *e != item);
+        ev_destroy_item.send(DestroyItemEvent { entity: item });
+        hunger.eat(value);
+
+        *state = TaskState::Success;
+    }
+}
@@ -0,0 +1,62 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        event::EventWriter,
+        system::{Query, Res, ResMut},
+    },
+    time::Time,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{Blackboard, TaskBuilder, TaskState},
+    items::SpawnFoodEvent,
+    BlockType, Terrain,
+};
+
+/// Harvests ripe farm soil, leaving bare soil behind to be replanted.
+#[derive(Component, Clone, TaskBuilder)]
+#[estimated_duration(remaining_s)]
+pub struct TaskFarm {
+    pub progress: f32,
+}
+
+impl TaskFarm {
+    fn remaining_s(&self, _blackboard: &Blackboard) -> Option<f32> {
+        Some((1. - self.progress).max(0.))
+    }
+}
+
+pub fn task_farm(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    mut q_behavior: Query<(&mut TaskState, &Blackboard, &mut TaskFarm)>,
+    mut ev_spawn_food: EventWriter<SpawnFoodEvent>,
+) {
+    for (mut state, blackboard, mut task) in q_behavior.iter_mut() {
+        let Some([x, y, z]) = blackboard.target_block else {
+            println!("Blackboard is missing target_block, cannot farm!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        if terrain.get_block(x, y, z).block != BlockType::SOIL_RIPE {
+            println!("Nothing to harvest!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        if task.progress >= 1. {
+            terrain.set_block(x, y, z, BlockType::FARM_SOIL);
+            ev_spawn_food.send(SpawnFoodEvent {
+                pos: [x, y + 1, z],
+                is_cooked: false,
+            });
+
+            *state = TaskState::Success;
+            continue;
+        }
+
+        task.progress += time.delta_seconds();
+    }
+}
@@ -0,0 +1,60 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        query::With,
+        system::{Query, Res},
+    },
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{job_access_points, ActorRef, Blackboard, JobType, TaskBuilder, TaskState},
+    common::Distance,
+    BlockType, Terrain,
+};
+
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskFindNearestCampfire;
+
+pub fn task_find_nearest_campfire(
+    terrain: Res<Terrain>,
+    q_transforms: Query<&Transform>,
+    mut q_behavior: Query<
+        (&ActorRef, &mut Blackboard, &mut TaskState),
+        With<TaskFindNearestCampfire>,
+    >,
+) {
+    for (ActorRef(actor), mut blackboard, mut state) in q_behavior.iter_mut() {
+        let Ok(transform) = q_transforms.get(*actor) else {
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let pos = [
+            transform.translation.x as i32,
+            transform.translation.y as i32,
+            transform.translation.z as i32,
+        ];
+
+        let nearest = terrain
+            .heat_sources
+            .iter()
+            .filter(|[x, y, z]| terrain.get_block(*x, *y, *z).block == BlockType::CAMPFIRE)
+            .map(|[x, y, z]| {
+                let distance = Distance::manhattan(pos, [*x as i32, *y as i32, *z as i32]);
+                ([*x, *y, *z], distance)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let Some((campfire, _)) = nearest else {
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        blackboard.target_block = Some(campfire);
+        blackboard.move_goals = job_access_points(campfire, JobType::Mine);
+
+        *state = TaskState::Success;
+    }
+}
@@ -0,0 +1,52 @@
+use bevy::{
+    ecs::{component::Component, query::Without, system::Query},
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::colonists::{ActorRef, Blackboard, InInventory, Item, JobHaul, TaskBuilder, TaskState};
+
+/// Reserve the item of the assigned haul job and head for it
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskFindHaulItem;
+
+pub fn task_find_haul_item(
+    q_jobs: Query<&JobHaul>,
+    mut q_items: Query<(&Transform, &mut Item), Without<InInventory>>,
+    mut q_behavior: Query<(
+        &ActorRef,
+        &mut TaskState,
+        &mut Blackboard,
+        &TaskFindHaulItem,
+    )>,
+) {
+    for (ActorRef(actor), mut state, mut blackboard, _) in q_behavior.iter_mut() {
+        let Some(haul) = blackboard.job.and_then(|job| q_jobs.get(job).ok()) else {
+            println!("No haul job assigned, cannot find the item!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let Ok((transform, mut item)) = q_items.get_mut(haul.item) else {
+            println!("Haul item is gone or already carried!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        if item.reserved.is_some_and(|holder| holder != *actor) {
+            println!("Haul item is reserved by someone else!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        item.reserved = Some(*actor);
+
+        blackboard.item = Some(haul.item);
+        blackboard.move_goals = vec![[
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ]];
+        *state = TaskState::Success;
+    }
+}
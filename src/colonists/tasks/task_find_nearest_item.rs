@@ -5,7 +5,7 @@ use bevy::{
         component::Component,
         entity::Entity,
         query::With,
-        system::{Query, Res},
+        system::{Query, Res, ResMut},
     },
     transform::components::Transform,
     utils::hashbrown::HashSet,
@@ -14,8 +14,8 @@ use task_derive::TaskBuilder;
 
 use crate::{
     colonists::{
-        test_item_tags, Actor, ActorRef, Blackboard, Item, ItemTag, NavigationGraph, TaskBuilder,
-        TaskState,
+        is_faction_allowed, test_item_tags, Actor, ActorRef, Blackboard, CarryCapacity, FactionId,
+        Item, ItemTag, NavigationGraph, TaskBuilder, TaskScheduler, TaskState,
     },
     Terrain,
 };
@@ -26,8 +26,9 @@ pub struct TaskFindNearestItem(pub Vec<ItemTag>);
 pub fn task_find_nearest_item(
     terrain: Res<Terrain>,
     graph: Res<NavigationGraph>,
-    mut q_items: Query<(&Transform, &mut Item)>,
-    q_actors: Query<&Transform, With<Actor>>,
+    mut scheduler: ResMut<TaskScheduler>,
+    mut q_items: Query<(&Transform, &mut Item, Option<&FactionId>)>,
+    q_actors: Query<(&Transform, Option<&CarryCapacity>, Option<&FactionId>), With<Actor>>,
     mut q_behavior: Query<(
         &ActorRef,
         &mut TaskState,
@@ -36,9 +37,13 @@ pub fn task_find_nearest_item(
     )>,
 ) {
     for (ActorRef(actor), mut state, mut blackboard, task) in q_behavior.iter_mut() {
+        if !scheduler.try_run::<TaskFindNearestItem>() {
+            continue;
+        }
+
         blackboard.item = None;
 
-        let Ok(transform) = q_actors.get(*actor) else {
+        let Ok((transform, capacity, faction)) = q_actors.get(*actor) else {
             *state = TaskState::Failed;
             continue;
         };
@@ -53,7 +58,16 @@ pub fn task_find_nearest_item(
             continue;
         };
 
-        let Some(items) = find_nearest(start_id, task.0.clone(), &graph, &q_items) else {
+        let max_weight = capacity.map_or(u32::MAX, |c| c.remaining());
+
+        let Some(items) = find_nearest(
+            start_id,
+            task.0.clone(),
+            max_weight,
+            faction.copied(),
+            &graph,
+            &q_items,
+        ) else {
             println!("No nearby item with matching tags");
             for tag in task.0.clone() {
                 println!("- tag {}", tag);
@@ -64,7 +78,7 @@ pub fn task_find_nearest_item(
 
         let item_entity = items.first().unwrap();
 
-        let Ok((item_tansform, mut item)) = q_items.get_mut(*item_entity) else {
+        let Ok((item_tansform, mut item, _)) = q_items.get_mut(*item_entity) else {
             println!("Item without transform? Or stale item data");
             *state = TaskState::Failed;
             continue;
@@ -84,11 +98,15 @@ pub fn task_find_nearest_item(
     }
 }
 
+/// Items owned by a faction other than `faction_filter` are passed over, an
+/// unset filter only takes items nobody owns.
 fn find_nearest(
     start_id: u32,
     tags: Vec<ItemTag>,
+    max_weight: u32,
+    faction_filter: Option<FactionId>,
     graph: &NavigationGraph,
-    q_items: &Query<(&Transform, &mut Item)>,
+    q_items: &Query<(&Transform, &mut Item, Option<&FactionId>)>,
 ) -> Option<Vec<Entity>> {
     let mut visited = HashSet::new();
     let mut queue = VecDeque::new();
@@ -106,15 +124,10 @@ fn find_nearest(
             .items
             .iter()
             .filter(|i| {
-                let Ok((_, item)) = q_items.get(**i) else {
-                    return false;
-                };
-
-                if item.reserved.is_some() {
-                    return false;
-                }
-
-                test_item_tags(&item.tags, &tags)
+                q_items.get(**i).is_ok_and(|(_, item, owner)| {
+                    is_item_available(item, &tags, max_weight)
+                        && is_faction_allowed(owner, faction_filter.as_ref())
+                })
             })
             .cloned()
             .collect();
@@ -132,3 +145,95 @@ fn find_nearest(
 
     None
 }
+
+/// An unreserved item with the tags that the seeker is able to carry
+pub fn is_item_available(item: &Item, tags: &[ItemTag], max_weight: u32) -> bool {
+    item.reserved.is_none() && item.weight() <= max_weight && test_item_tags(&item.tags, tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{
+        event::Events,
+        schedule::{IntoSystemConfigs, Schedule},
+        system::RunSystemOnce,
+        world::World,
+    };
+
+    use crate::{
+        colonists::{
+            partition, partition_orphaned_items, PartitionEvent, PLAYER_FACTION, RIVAL_FACTION,
+        },
+        BlockType,
+    };
+
+    use super::*;
+
+    /// Sends a colonist of each faction, and one without, looking for the
+    /// only stone around, owned by `owner`. Returns whether each found it.
+    fn finds_stone(owner: Option<FactionId>) -> Vec<bool> {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.fill_region([0, 0, 0], [7, 0, 7], BlockType::STONE);
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<TaskScheduler>();
+        world.init_resource::<Events<PartitionEvent>>();
+
+        let mut stone = world.spawn((
+            Transform::from_xyz(5.5, 1., 5.5),
+            Item {
+                tags: vec![ItemTag::Stone],
+                reserved: None,
+            },
+        ));
+        if let Some(owner) = owner {
+            stone.insert(owner);
+        }
+        let stone = stone.id();
+
+        world.send_event(PartitionEvent { chunk_idx: 0 });
+        let mut schedule = Schedule::default();
+        schedule.add_systems((partition, partition_orphaned_items).chain());
+        schedule.run(&mut world);
+
+        [Some(PLAYER_FACTION), Some(RIVAL_FACTION), None]
+            .map(|faction| {
+                let mut actor = world.spawn((Actor, Transform::from_xyz(1.5, 1., 1.5)));
+                if let Some(faction) = faction {
+                    actor.insert(faction);
+                }
+                let actor = actor.id();
+
+                // one at a time, so an earlier find doesn't reserve it away
+                world.get_mut::<Item>(stone).unwrap().reserved = None;
+                let task = world
+                    .spawn((
+                        ActorRef(actor),
+                        TaskState::Executing,
+                        Blackboard::default(),
+                        TaskFindNearestItem(vec![ItemTag::Stone]),
+                    ))
+                    .id();
+                world.run_system_once(task_find_nearest_item);
+                let task = world.entity_mut(task);
+
+                let found = task.get::<Blackboard>().unwrap().item == Some(stone);
+                let state = *task.get::<TaskState>().unwrap();
+                assert!(found == (state == TaskState::Success));
+                task.despawn();
+
+                found
+            })
+            .to_vec()
+    }
+
+    #[test]
+    fn faction_items_are_invisible_to_other_factions() {
+        // player, rival, no faction
+        assert_eq!(finds_stone(Some(RIVAL_FACTION)), vec![false, true, false]);
+        assert_eq!(finds_stone(Some(PLAYER_FACTION)), vec![true, false, false]);
+        assert_eq!(finds_stone(None), vec![true, true, true]);
+    }
+}
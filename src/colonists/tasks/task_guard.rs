@@ -0,0 +1,315 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{Has, With},
+        system::{Commands, Query, Res, ResMut},
+    },
+    math::Vec3,
+    time::Time,
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{
+        request_path, step_path, Actor, ActorRef, BlockMove, FactionId, FactionRelations, Health,
+        HostileEntity, MovementStats, NavigationFlags, NavigationGraph, Path, PathCache, PathStep,
+        TaskBuilder, TaskState,
+    },
+    Terrain,
+};
+
+/// Enemies closer than this to the guard post get chased down
+const GUARD_RANGE: f32 = 8.;
+/// Close enough to hit the target
+const ATTACK_RANGE: f32 = 1.5;
+const GUARD_DAMAGE_PER_SECOND: f32 = 10.;
+
+/// Stand at the post and fight off any hostile that comes near it. Succeeds
+/// once the guard is back at the post with nothing left in range.
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskGuard(pub [u32; 3]);
+
+/// Inserted next to a `TaskGuard` while it is fighting. Removed when the
+/// target dies, and the guard walks back to the post.
+#[derive(Component)]
+pub struct TaskAttack {
+    pub target: Entity,
+    pub damage_per_second: f32,
+}
+
+/// Hostile creatures, and colonists of a faction at war with the guard's
+fn is_enemy(
+    relations: &FactionRelations,
+    guard_faction: Option<&FactionId>,
+    faction: Option<&FactionId>,
+    is_hostile: bool,
+) -> bool {
+    if is_hostile {
+        return true;
+    }
+
+    match (guard_faction, faction) {
+        (Some(a), Some(b)) => relations.is_hostile(*a, *b),
+        _ => false,
+    }
+}
+
+fn to_block(transform: &Transform) -> [u32; 3] {
+    [
+        transform.translation.x as u32,
+        transform.translation.y as u32,
+        transform.translation.z as u32,
+    ]
+}
+
+pub fn task_guard(
+    mut cmd: Commands,
+    time: Res<Time>,
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    relations: Res<FactionRelations>,
+    mut path_cache: ResMut<PathCache>,
+    mut q_paths: Query<&mut Path, With<Actor>>,
+    q_movers: Query<&BlockMove, With<Actor>>,
+    q_transforms: Query<(&Transform, Option<&MovementStats>, Option<&FactionId>), With<Actor>>,
+    mut q_hostiles: Query<(
+        Entity,
+        &Transform,
+        &mut Health,
+        Option<&FactionId>,
+        Has<HostileEntity>,
+    )>,
+    mut q_behavior: Query<(
+        Entity,
+        &ActorRef,
+        &mut TaskState,
+        &TaskGuard,
+        Option<&TaskAttack>,
+    )>,
+) {
+    for (entity, ActorRef(actor), mut state, TaskGuard(post), attack) in q_behavior.iter_mut() {
+        let Ok((transform, stats, faction)) = q_transforms.get(*actor) else {
+            println!("no transform on actor, cannot guard!");
+            cmd.entity(*actor).remove::<Path>();
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        if q_movers.contains(*actor) {
+            continue;
+        }
+
+        let pos = to_block(transform);
+        let stats = stats.copied().unwrap_or_default();
+
+        if let Some(attack) = attack {
+            let target = q_hostiles
+                .get_mut(attack.target)
+                .ok()
+                .filter(|(_, _, health, _, _)| health.current > 0.);
+
+            let Some((_, target_transform, mut health, _, _)) = target else {
+                // target is dead or gone, head back to the post
+                cmd.entity(entity).remove::<TaskAttack>();
+                cmd.entity(*actor).remove::<Path>();
+                continue;
+            };
+
+            let distance = transform.translation.distance(target_transform.translation);
+
+            if distance <= ATTACK_RANGE {
+                health.current -= attack.damage_per_second * time.delta_seconds();
+                cmd.entity(*actor).remove::<Path>();
+                continue;
+            }
+
+            let target_pos = to_block(target_transform);
+
+            let Ok(mut path) = q_paths.get_mut(*actor) else {
+                match request_path(
+                    &terrain,
+                    &graph,
+                    pos,
+                    vec![target_pos],
+                    NavigationFlags::COLONIST,
+                ) {
+                    Some(path) => {
+                        cmd.entity(*actor).insert(path);
+                    }
+                    None => {
+                        println!("hostile is unreachable, returning to post");
+                        cmd.entity(entity).remove::<TaskAttack>();
+                    }
+                }
+                continue;
+            };
+
+            // the target moved, chase it from here
+            if !path.goals.contains(&target_pos) {
+                cmd.entity(*actor).remove::<Path>();
+                continue;
+            }
+
+            step_path(
+                &mut cmd,
+                &terrain,
+                &graph,
+                &mut path_cache,
+                *actor,
+                pos,
+                &stats,
+                &mut path,
+            );
+            continue;
+        }
+
+        let Ok(mut path) = q_paths.get_mut(*actor) else {
+            if pos != *post {
+                let Some(path) = request_path(
+                    &terrain,
+                    &graph,
+                    pos,
+                    vec![*post],
+                    NavigationFlags::COLONIST,
+                ) else {
+                    println!("guard post {:?} is unreachable!", post);
+                    *state = TaskState::Failed;
+                    continue;
+                };
+
+                cmd.entity(*actor).insert(path);
+                continue;
+            }
+
+            // only engage once standing at the post
+            let post_center = Vec3::new(post[0] as f32, post[1] as f32, post[2] as f32);
+            let hostile = q_hostiles
+                .iter()
+                .filter(|(_, _, health, other_faction, is_hostile)| {
+                    health.current > 0.
+                        && is_enemy(&relations, faction, *other_faction, *is_hostile)
+                })
+                .map(|(e, t, _, _, _)| (e, t.translation.distance(post_center)))
+                .filter(|(_, d)| *d <= GUARD_RANGE)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+
+            match hostile {
+                Some((target, _)) => {
+                    cmd.entity(entity).insert(TaskAttack {
+                        target,
+                        damage_per_second: GUARD_DAMAGE_PER_SECOND,
+                    });
+                }
+                None => *state = TaskState::Success,
+            }
+
+            continue;
+        };
+
+        match step_path(
+            &mut cmd,
+            &terrain,
+            &graph,
+            &mut path_cache,
+            *actor,
+            pos,
+            &stats,
+            &mut path,
+        ) {
+            PathStep::Stranded => *state = TaskState::Failed,
+            PathStep::Arrived | PathStep::Moving | PathStep::Repath => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{
+        event::Events,
+        schedule::{IntoSystemConfigs, Schedule},
+        system::RunSystemOnce,
+        world::World,
+    };
+
+    use crate::{
+        colonists::{block_move_system, hostile_death, partition, MovedEvent, PartitionEvent},
+        BlockType,
+    };
+
+    use super::*;
+
+    const POST: [u32; 3] = [3, 1, 3];
+
+    /// A partitioned stone floor with a guard at the corner and a hostile
+    /// within range of the post
+    fn guarded_world() -> (World, Entity, Entity, Entity) {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+
+        for x in 0..8 {
+            for z in 0..8 {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+            }
+        }
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<FactionRelations>();
+        world.init_resource::<PathCache>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<PartitionEvent>>();
+        world.init_resource::<Events<MovedEvent>>();
+        world.send_event(PartitionEvent { chunk_idx: 0 });
+        world.run_system_once(partition);
+
+        let guard = world.spawn((Actor, Transform::from_xyz(0.5, 1., 0.5))).id();
+        let hostile = world
+            .spawn((
+                HostileEntity,
+                Health::new(5.),
+                Transform::from_xyz(6.5, 1., 6.5),
+            ))
+            .id();
+        let task = world
+            .spawn((ActorRef(guard), TaskState::Executing, TaskGuard(POST)))
+            .id();
+
+        (world, guard, hostile, task)
+    }
+
+    #[test]
+    fn guard_reaches_the_post_before_fighting() {
+        let (mut world, guard, hostile, task) = guarded_world();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((task_guard, hostile_death, block_move_system).chain());
+
+        let mut reached_post = false;
+
+        for _ in 0..200 {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(0.25));
+            schedule.run(&mut world);
+
+            reached_post |= to_block(world.get::<Transform>(guard).unwrap()) == POST;
+
+            if world.entity(task).contains::<TaskAttack>() {
+                assert!(reached_post, "engaged before standing at the post");
+            }
+
+            if *world.get::<TaskState>(task).unwrap() != TaskState::Executing {
+                break;
+            }
+        }
+
+        // the hostile is dead and the guard is back at the post
+        assert!(world.get_entity(hostile).is_none());
+        assert!(*world.get::<TaskState>(task).unwrap() == TaskState::Success);
+        assert_eq!(to_block(world.get::<Transform>(guard).unwrap()), POST);
+    }
+}
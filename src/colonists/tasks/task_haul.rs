@@ -0,0 +1,109 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        query::{With, Without},
+        system::{Commands, Query, Res, ResMut},
+    },
+    render::view::Visibility,
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{
+        Actor, ActorRef, Blackboard, FactionId, InInventory, InPartition, Inventory, Item,
+        JobLocation, NavigationGraph, StockpileZone, TaskBuilder, TaskState,
+    },
+    Terrain,
+};
+
+/// Put the carried haul item down on its stockpile position. Unlike a pick
+/// up, the item goes back into the world and the navigation graph. When
+/// another item got there first, the nearest free position of the same
+/// stockpile is used instead.
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskHaul;
+
+pub fn task_haul(
+    mut cmd: Commands,
+    terrain: Res<Terrain>,
+    mut graph: ResMut<NavigationGraph>,
+    q_jobs: Query<&JobLocation>,
+    q_zones: Query<&StockpileZone>,
+    mut q_items: Query<(&mut Transform, &mut Item), (With<InInventory>, Without<Actor>)>,
+    mut q_actors: Query<(&mut Inventory, Option<&FactionId>), With<Actor>>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &Blackboard), With<TaskHaul>>,
+) {
+    for (ActorRef(actor), mut state, blackboard) in q_behavior.iter_mut() {
+        let Some(location) = blackboard.job.and_then(|job| q_jobs.get(job).ok()) else {
+            println!("No stockpile position to haul to!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let Some(item) = blackboard.item else {
+            println!("No item in blackboard, nothing to haul!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let Ok((mut inventory, faction)) = q_actors.get_mut(*actor) else {
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let Ok((mut item_transform, mut item_data)) = q_items.get_mut(item) else {
+            println!("Haul item is not being carried!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let zone = q_zones
+            .iter()
+            .find(|zone| zone.blocks.contains(&location.pos));
+        let is_taken = zone.is_some_and(|zone| {
+            zone.occupied
+                .get(&location.pos)
+                .is_some_and(|occupant| *occupant != item)
+        });
+
+        let pos = if is_taken {
+            let free = zone.and_then(|zone| zone.find_free_cell(location.pos, &item_data.tags));
+
+            let Some(free) = free else {
+                println!("Stockpile is full, cannot haul!");
+                *state = TaskState::Failed;
+                continue;
+            };
+
+            free
+        } else {
+            location.pos
+        };
+
+        let [x, y, z] = pos;
+
+        inventory.items.retain(|e| *e != item);
+        item_data.reserved = None;
+        item_transform.translation.x = x as f32 + 0.5;
+        item_transform.translation.y = y as f32;
+        item_transform.translation.z = z as f32 + 0.5;
+
+        let mut ecmd = cmd.entity(item);
+        ecmd.remove::<InInventory>();
+        ecmd.insert(Visibility::Visible);
+
+        // stockpiling an item claims it for the hauler's faction
+        if let Some(faction) = faction {
+            ecmd.insert(*faction);
+        }
+
+        if let Some(partition_id) = terrain.get_partition_id_u32(x, y, z) {
+            if graph.add_item(&partition_id, item, &item_data.tags) {
+                ecmd.insert(InPartition { partition_id });
+            }
+        }
+
+        *state = TaskState::Success;
+    }
+}
@@ -7,14 +7,21 @@ use bevy::{
 };
 use task_derive::TaskBuilder;
 
-use crate::colonists::{TaskBuilder, TaskState};
+use crate::colonists::{Blackboard, TaskBuilder, TaskState};
 
 #[derive(Component, Clone, TaskBuilder)]
+#[estimated_duration(remaining_s)]
 pub struct TaskIdle {
     pub progress: f32,
     pub duration_s: f32,
 }
 
+impl TaskIdle {
+    fn remaining_s(&self, _blackboard: &Blackboard) -> Option<f32> {
+        Some((self.duration_s - self.progress).max(0.))
+    }
+}
+
 pub fn task_idle(time: Res<Time>, mut q_behavior: Query<(&mut TaskState, &mut TaskIdle)>) {
     for (mut state, mut task) in q_behavior.iter_mut() {
         if task.progress >= task.duration_s {
@@ -2,6 +2,7 @@ use bevy::{
     ecs::{
         component::Component,
         event::EventWriter,
+        query::With,
         system::{Query, Res, ResMut},
     },
     time::Time,
@@ -9,48 +10,337 @@ use bevy::{
 use task_derive::TaskBuilder;
 
 use crate::{
-    colonists::{Blackboard, TaskBuilder, TaskState},
+    colonists::{
+        test_item_tags, ActorRef, Blackboard, Inventory, Item, ItemTag, Mood, TaskBuilder,
+        TaskState,
+    },
     common::Rand,
-    items::SpawnStoneEvent,
-    BlockType, Terrain,
+    items::{SpawnCoalEvent, SpawnOreEvent, SpawnStoneEvent, SpawnWoodEvent},
+    BlockChangedEvent, BlockType, Terrain,
 };
 
+/// Mining speed without a pickaxe in hand
+const BARE_HANDED_FACTOR: f32 = 0.25;
+
+/// Work on the target block until it breaks. Progress is kept on the block,
+/// see `Terrain::add_block_damage`, so whoever mines it next picks up where
+/// the last colonist left off.
 #[derive(Component, Clone, TaskBuilder)]
-pub struct TaskMineBlock {
-    pub progress: f32,
-}
+pub struct TaskMineBlock;
 
 pub fn task_mine_block(
     time: Res<Time>,
     mut terrain: ResMut<Terrain>,
-    mut q_behavior: Query<(&mut TaskState, &Blackboard, &mut TaskMineBlock)>,
+    q_moods: Query<&Mood>,
+    q_inventories: Query<&Inventory>,
+    q_items: Query<&Item>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &Blackboard), With<TaskMineBlock>>,
     mut ev_spawn_stone: EventWriter<SpawnStoneEvent>,
+    mut ev_spawn_wood: EventWriter<SpawnWoodEvent>,
+    mut ev_spawn_coal: EventWriter<SpawnCoalEvent>,
+    mut ev_spawn_ore: EventWriter<SpawnOreEvent>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
     mut rand: ResMut<Rand>,
 ) {
-    for (mut state, blackboard, mut task) in q_behavior.iter_mut() {
+    for (ActorRef(actor), mut state, blackboard) in q_behavior.iter_mut() {
+        // interrupted, leave the block be. The damage done so far stays on
+        // it for whoever mines it next
+        if *state == TaskState::Cancelled {
+            continue;
+        }
+
         let Some([x, y, z]) = blackboard.target_block else {
             println!("Blackboard is missing target_block, cannot mine!");
             *state = TaskState::Failed;
             continue;
         };
 
-        if terrain.get_block(x, y, z).is_empty() {
+        let block = terrain.get_block(x, y, z);
+
+        if block.is_empty() {
             *state = TaskState::Success;
             continue;
         }
 
-        if task.progress >= 1. {
-            terrain.set_block_type(x, y, z, BlockType::EMPTY);
+        let properties = block.block.properties();
+        let mood_factor = q_moods.get(*actor).map_or(1., |mood| mood.speed_factor());
+        let has_pickaxe = q_inventories.get(*actor).is_ok_and(|inventory| {
+            inventory.items.iter().any(|e| {
+                q_items
+                    .get(*e)
+                    .is_ok_and(|item| test_item_tags(&item.tags, &[ItemTag::Pickaxe]))
+            })
+        });
+        let tool_factor = if has_pickaxe { 1. } else { BARE_HANDED_FACTOR };
+
+        if terrain.add_block_damage(x, y, z, time.delta_seconds() * mood_factor * tool_factor) {
+            let change = terrain.set_block(x, y, z, BlockType::EMPTY);
             terrain.set_flag_mine(x, y, z, false);
+            ev_block_changed.send(change.into());
 
-            if rand.bool(0.25) {
-                ev_spawn_stone.send(SpawnStoneEvent { pos: [x, y, z] });
+            if let Some((tag, chance)) = &properties.drops {
+                if rand.bool(*chance) {
+                    let pos = [x, y, z];
+                    match tag {
+                        ItemTag::Stone => {
+                            ev_spawn_stone.send(SpawnStoneEvent { pos });
+                        }
+                        ItemTag::Wood => {
+                            ev_spawn_wood.send(SpawnWoodEvent { pos });
+                        }
+                        ItemTag::Coal => {
+                            ev_spawn_coal.send(SpawnCoalEvent { pos });
+                        }
+                        ItemTag::IronOre | ItemTag::GoldOre => {
+                            ev_spawn_ore.send(SpawnOreEvent { pos, ore: *tag });
+                        }
+                        _ => {}
+                    }
+                }
             }
 
             *state = TaskState::Success;
-            continue;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{
+        app::App,
+        asset::{AssetApp, AssetPlugin},
+        ecs::{
+            entity::Entity,
+            event::Events,
+            query::Without,
+            schedule::{IntoSystemConfigs, Schedule},
+            world::World,
+        },
+        pbr::StandardMaterial,
+        render::mesh::Mesh,
+        transform::components::Transform,
+        MinimalPlugins,
+    };
+
+    use crate::{
+        colonists::{
+            is_reachable, partition, partition_orphaned_items, InPartition, NavigationFlags,
+            NavigationGraph, PartitionEvent, PartitionPathRequest,
+        },
+        items::on_spawn_stone,
+    };
+
+    use super::*;
+
+    const WALL: [[u32; 3]; 3] = [[2, 1, 2], [3, 1, 2], [4, 1, 2]];
+
+    /// A partitioned stone floor with a three block stone wall on it. The
+    /// item spawners need an asset server, so this is a minimal app rather
+    /// than a bare world
+    fn walled_app() -> App {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+
+        for x in 0..8 {
+            for z in 0..8 {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+            }
+        }
+
+        for [x, y, z] in WALL {
+            terrain.set_block(x, y, z, BlockType::STONE);
+        }
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<StandardMaterial>()
+            .insert_resource(terrain)
+            .insert_resource(Rand::seed(606))
+            .init_resource::<NavigationGraph>()
+            .add_event::<PartitionEvent>()
+            .add_event::<SpawnStoneEvent>()
+            .add_event::<SpawnWoodEvent>()
+            .add_event::<SpawnCoalEvent>()
+            .add_event::<SpawnOreEvent>()
+            .add_event::<BlockChangedEvent>();
+
+        repartition(&mut app.world);
+
+        app
+    }
+
+    fn repartition(world: &mut World) {
+        world.send_event(PartitionEvent { chunk_idx: 0 });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((partition, partition_orphaned_items).chain());
+        schedule.run(world);
+    }
+
+    #[test]
+    fn mined_wall_drops_reachable_stone() {
+        let mut app = walled_app();
+        let world = &mut app.world;
+
+        let miner = world.spawn(Transform::from_xyz(1.5, 1., 1.5)).id();
+        for pos in WALL {
+            world.spawn((
+                ActorRef(miner),
+                TaskState::Executing,
+                Blackboard {
+                    target_block: Some(pos),
+                    ..Blackboard::default()
+                },
+                TaskMineBlock,
+            ));
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((task_mine_block, on_spawn_stone).chain());
+
+        // bare handed stone takes four seconds
+        for _ in 0..5 {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs(1));
+            schedule.run(world);
+        }
+
+        for [x, y, z] in WALL {
+            assert!(world.resource::<Terrain>().get_block(x, y, z).is_empty());
+        }
+
+        // the drops landed in cells that were solid until now
+        let orphans = world
+            .query_filtered::<Entity, (With<Item>, Without<InPartition>)>()
+            .iter(world)
+            .count();
+        assert_eq!(orphans, 3);
+
+        repartition(world);
+
+        let mut stones = world.query::<(Entity, &Item, &Transform, &InPartition)>();
+        let terrain = world.resource::<Terrain>();
+        let graph = world.resource::<NavigationGraph>();
+        let stones = stones
+            .iter(world)
+            .filter(|(_, item, _, _)| item.tags == vec![ItemTag::Stone])
+            .collect::<Vec<_>>();
+        assert_eq!(stones.len(), 3);
+
+        for (entity, _, transform, in_partition) in stones {
+            assert!(graph
+                .get_partition(&in_partition.partition_id)
+                .unwrap()
+                .items
+                .contains(&entity));
+
+            let goal = transform.translation;
+            assert!(is_reachable(
+                &PartitionPathRequest {
+                    start: [1, 1, 1],
+                    goals: vec![[goal.x as u32, goal.y as u32, goal.z as u32]],
+                    flags: NavigationFlags::COLONIST,
+                },
+                terrain,
+                graph,
+            ));
+        }
+    }
+
+    #[test]
+    fn drops_come_from_the_block_table() {
+        let mut app = walled_app();
+        let world = &mut app.world;
+
+        // a log that always drops wood, and clay that drops nothing
+        let mut terrain = world.resource_mut::<Terrain>();
+        terrain.set_block(2, 1, 2, BlockType::LOG);
+        terrain.set_block(3, 1, 2, BlockType::CLAY);
+        terrain.set_block(4, 1, 2, BlockType::COAL);
+
+        let miner = world.spawn(Transform::from_xyz(1.5, 1., 1.5)).id();
+        for pos in WALL {
+            world.spawn((
+                ActorRef(miner),
+                TaskState::Executing,
+                Blackboard {
+                    target_block: Some(pos),
+                    ..Blackboard::default()
+                },
+                TaskMineBlock,
+            ));
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(task_mine_block);
+
+        for _ in 0..5 {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs(1));
+            schedule.run(world);
+        }
+
+        assert_eq!(world.resource::<Events<SpawnWoodEvent>>().len(), 1);
+        assert_eq!(world.resource::<Events<SpawnCoalEvent>>().len(), 1);
+        assert!(world.resource::<Events<SpawnStoneEvent>>().is_empty());
+        assert!(world.resource::<Events<SpawnOreEvent>>().is_empty());
+    }
+
+    #[test]
+    fn colonists_taking_turns_share_mining_progress() {
+        let mut app = walled_app();
+        let world = &mut app.world;
+        let [x, y, z] = WALL[0];
+
+        let mut tasks = [1.5, 2.5].map(|at_x| {
+            let miner = world.spawn(Transform::from_xyz(at_x, 1., 1.5)).id();
+            world
+                .spawn((
+                    ActorRef(miner),
+                    TaskState::Cancelled,
+                    Blackboard {
+                        target_block: Some(WALL[0]),
+                        ..Blackboard::default()
+                    },
+                    TaskMineBlock,
+                ))
+                .id()
+        });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(task_mine_block);
+
+        // each turn is 1.5s of bare handed work on 4s of stone, the other
+        // miner standing by with their task interrupted
+        for turn in 0..3 {
+            *world.get_mut::<TaskState>(tasks[0]).unwrap() = TaskState::Executing;
+            *world.get_mut::<TaskState>(tasks[1]).unwrap() = TaskState::Cancelled;
+
+            for _ in 0..3 {
+                world
+                    .resource_mut::<Time>()
+                    .advance_by(Duration::from_millis(500));
+                schedule.run(world);
+            }
+
+            let terrain = world.resource::<Terrain>();
+
+            if turn < 2 {
+                let work_s = terrain.block_damage[&[x, y, z]].work_s;
+                assert!((work_s - 0.375 * (turn + 1) as f32).abs() < 1e-4);
+                assert!(!terrain.get_block(x, y, z).is_empty());
+            } else {
+                assert!(terrain.get_block(x, y, z).is_empty());
+            }
+
+            tasks.swap(0, 1);
         }
 
-        task.progress += time.delta_seconds();
+        // the miner on the last turn broke the block
+        assert!(*world.get::<TaskState>(tasks[1]).unwrap() == TaskState::Success);
     }
 }
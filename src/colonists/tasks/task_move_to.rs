@@ -1,8 +1,9 @@
 use bevy::{
     ecs::{
         component::Component,
+        entity::Entity,
         query::With,
-        system::{Commands, Query, Res},
+        system::{Commands, Query, Res, ResMut},
     },
     transform::components::Transform,
 };
@@ -11,12 +12,15 @@ use task_derive::TaskBuilder;
 use crate::{
     colonists::{
         get_block_flags, get_granular_path, get_partition_path, Actor, ActorRef, Blackboard,
-        BlockMove, GranularPathRequest, NavigationFlags, NavigationGraph, PartitionPathRequest,
-        Path, TaskBuilder, TaskState,
+        BlockMove, GranularPathRequest, MovementStats, NavigationFlags, NavigationGraph,
+        PartitionPathRequest, Path, PathCache, TaskBuilder, TaskScheduler, TaskState,
     },
     Terrain,
 };
 
+/// Long enough to cross the map, a move taking longer than this is stuck
+pub const MOVE_TIMEOUT_S: f32 = 120.;
+
 #[derive(Component, Clone, TaskBuilder)]
 pub struct TaskMoveTo;
 
@@ -24,13 +28,15 @@ pub fn task_move_to(
     mut cmd: Commands,
     terrain: Res<Terrain>,
     graph: Res<NavigationGraph>,
+    mut path_cache: ResMut<PathCache>,
+    mut scheduler: ResMut<TaskScheduler>,
     mut q_paths: Query<&mut Path, With<Actor>>,
     q_movers: Query<&BlockMove, With<Actor>>,
-    q_transforms: Query<&Transform, With<Actor>>,
+    q_transforms: Query<(&Transform, Option<&MovementStats>), With<Actor>>,
     mut q_behavior: Query<(&ActorRef, &Blackboard, &mut TaskState), With<TaskMoveTo>>,
 ) {
     for (ActorRef(actor), blackboard, mut state) in q_behavior.iter_mut() {
-        let Ok(transform) = q_transforms.get(*actor) else {
+        let Ok((transform, stats)) = q_transforms.get(*actor) else {
             println!("no transform on actor, cannot move to!");
             cmd.entity(*actor).remove::<Path>();
             *state = TaskState::Failed;
@@ -54,99 +60,478 @@ pub fn task_move_to(
                 continue;
             }
 
-            let request = PartitionPathRequest {
-                start: pos,
-                goals: blackboard.move_goals.clone(),
-                flags: NavigationFlags::COLONIST,
-            };
+            if !scheduler.try_run::<TaskMoveTo>() {
+                continue;
+            }
 
-            let Some(partition_path) = get_partition_path(&request, &terrain, &graph) else {
+            let Some(path) = request_path(
+                &terrain,
+                &graph,
+                pos,
+                blackboard.move_goals.clone(),
+                NavigationFlags::COLONIST,
+            ) else {
                 *state = TaskState::Failed;
                 continue;
             };
 
-            let path = Path {
-                current_partition_idx: partition_path.goals.len() - 1,
-                goals: partition_path.goals,
-                partition_path: partition_path.path,
-                flags: request.flags,
-                blocks: vec![],
-                current_block_idx: 0,
-            };
-
             cmd.entity(*actor).insert(path);
             continue;
         };
 
-        let at_goal = path
-            .goals
-            .iter()
-            .any(|g| g[0] == pos[0] && g[1] == pos[1] && g[2] == pos[2]);
+        let stats = stats.copied().unwrap_or_default();
 
-        if at_goal {
-            cmd.entity(*actor).remove::<Path>();
-            *state = TaskState::Success;
-            continue;
+        match step_path(
+            &mut cmd,
+            &terrain,
+            &graph,
+            &mut path_cache,
+            *actor,
+            pos,
+            &stats,
+            &mut path,
+        ) {
+            PathStep::Arrived => *state = TaskState::Success,
+            PathStep::Stranded => *state = TaskState::Failed,
+            PathStep::Moving | PathStep::Repath => {}
         }
+    }
+}
 
-        // what partition are we standing in? if it's not part of the predetermined path, we stay course.
-        // if it is part of the path, we set our current index to be the path idx
-        let Some(partition_id) = terrain.get_partition_id_u32(pos[0], pos[1], pos[2]) else {
-            println!("Not standing in a partition, cannot path!");
-            cmd.entity(*actor).remove::<Path>();
-            *state = TaskState::Failed;
-            continue;
+pub enum PathStep {
+    /// The actor is standing on one of the path goals, and the path is removed
+    Arrived,
+    /// The actor was given the next block to move to
+    Moving,
+    /// The path was dropped and has to be requested again
+    Repath,
+    /// The actor is not standing in a partition, the path was dropped
+    Stranded,
+}
+
+pub fn request_path(
+    terrain: &Terrain,
+    graph: &NavigationGraph,
+    start: [u32; 3],
+    goals: Vec<[u32; 3]>,
+    flags: NavigationFlags,
+) -> Option<Path> {
+    let request = PartitionPathRequest {
+        start,
+        goals,
+        flags,
+    };
+
+    let partition_path = get_partition_path(&request, terrain, graph)?;
+
+    Some(Path {
+        current_partition_idx: partition_path.goals.len() - 1,
+        goals: partition_path.goals,
+        partition_path: partition_path.path,
+        flags: request.flags,
+        blocks: vec![],
+        current_block_idx: 0,
+    })
+}
+
+/// Advance an actor that is not currently moving one block along its path
+pub fn step_path(
+    cmd: &mut Commands,
+    terrain: &Terrain,
+    graph: &NavigationGraph,
+    path_cache: &mut PathCache,
+    actor: Entity,
+    pos: [u32; 3],
+    stats: &MovementStats,
+    path: &mut Path,
+) -> PathStep {
+    let at_goal = path
+        .goals
+        .iter()
+        .any(|g| g[0] == pos[0] && g[1] == pos[1] && g[2] == pos[2]);
+
+    if at_goal {
+        cmd.entity(actor).remove::<Path>();
+        return PathStep::Arrived;
+    }
+
+    // what partition are we standing in? if it's not part of the predetermined path, we stay course.
+    // if it is part of the path, we set our current index to be the path idx
+    let Some(partition_id) = terrain.get_partition_id_u32(pos[0], pos[1], pos[2]) else {
+        println!("Not standing in a partition, cannot path!");
+        cmd.entity(actor).remove::<Path>();
+        return PathStep::Stranded;
+    };
+
+    let partition_path_idx = path.partition_path.iter().position(|p| *p == partition_id);
+
+    if let Some(idx) = partition_path_idx {
+        path.current_partition_idx = idx;
+    };
+
+    // if current block index is zero, it means we've finished the granular path
+    if path.current_block_idx == 0 {
+        let Some(next_partition_id) = path.next_partition_id() else {
+            cmd.entity(actor).remove::<Path>();
+            return PathStep::Repath;
         };
 
-        let partition_path_idx = path.partition_path.iter().position(|p| *p == partition_id);
+        let next_partition_id = *next_partition_id;
+        let start = [pos[0] as i32, pos[1] as i32, pos[2] as i32];
 
-        if let Some(idx) = partition_path_idx {
-            path.current_partition_idx = idx;
+        // paths into the goal partition depend on the goals, only the ones
+        // between two partitions are shared
+        let is_cacheable = next_partition_id != partition_id;
+
+        let cached = if is_cacheable {
+            path_cache.get(start, partition_id, next_partition_id, path.flags)
+        } else {
+            None
         };
 
-        // if current block index is zero, it means we've finished the granular path
-        if path.current_block_idx == 0 {
-            let Some(next_partition_id) = path.next_partition_id() else {
-                cmd.entity(*actor).remove::<Path>();
-                continue;
-            };
+        let blocks = match cached {
+            Some(blocks) => blocks,
+            None => {
+                let Some(granular_path) = get_granular_path(
+                    graph,
+                    terrain,
+                    &GranularPathRequest {
+                        start: pos,
+                        goals: path.goals.clone(),
+                        goal_partition_id: next_partition_id,
+                        flags: path.flags,
+                    },
+                ) else {
+                    cmd.entity(actor).remove::<Path>();
+                    return PathStep::Repath;
+                };
 
-            let Some(granular_path) = get_granular_path(
-                &graph,
-                &terrain,
-                &GranularPathRequest {
-                    start: pos,
-                    goals: path.goals.clone(),
-                    goal_partition_id: *next_partition_id,
-                    flags: path.flags,
-                },
-            ) else {
-                cmd.entity(*actor).remove::<Path>();
-                continue;
-            };
+                let chunks = [partition_id, next_partition_id].map(|id| {
+                    graph
+                        .get_partition(&id)
+                        .map(|partition| partition.chunk_idx)
+                });
+
+                if let (true, [Some(a), Some(b)]) = (is_cacheable, chunks) {
+                    path_cache.insert(
+                        partition_id,
+                        next_partition_id,
+                        [a, b],
+                        path.flags,
+                        granular_path.blocks.clone(),
+                    );
+                }
+
+                granular_path.blocks
+            }
+        };
+
+        path.blocks = blocks;
+        path.current_block_idx = path.blocks.len() - 1;
+    }
+
+    path.current_block_idx -= 1;
+
+    let Some(next_block) = path.next_block() else {
+        cmd.entity(actor).remove::<Path>();
+        return PathStep::Repath;
+    };
+
+    let block_flags = get_block_flags(terrain, next_block[0], next_block[1], next_block[2]);
+
+    if block_flags & path.flags == NavigationFlags::NONE {
+        cmd.entity(actor).remove::<Path>();
+        return PathStep::Repath;
+    }
+
+    cmd.entity(actor).insert(BlockMove {
+        speed: stats.get_speed(block_flags),
+        target: path.blocks[path.current_block_idx],
+        look_at: true,
+    });
+
+    PathStep::Moving
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{
+        ecs::{
+            event::Events,
+            schedule::{IntoSystemConfigs, Schedule},
+            system::{CommandQueue, RunSystemOnce},
+            world::World,
+        },
+        time::Time,
+    };
+
+    use crate::{
+        colonists::{
+            block_move_system, partition, reset_task_scheduler, tick_task_timeouts, MovedEvent,
+            PartitionEvent,
+        },
+        BlockType, WorldClock,
+    };
+
+    use super::*;
+
+    /// A stone floor split by a wall the partitions don't know about yet, so
+    /// the goal looks reachable but no block path gets there
+    fn stale_wall_world() -> World {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
 
-            path.blocks = granular_path.blocks.clone();
-            path.current_block_idx = path.blocks.len() - 1;
+        for x in 0..8 {
+            for z in 0..8 {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+            }
         }
 
-        path.current_block_idx -= 1;
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<PathCache>();
+        world.init_resource::<TaskScheduler>();
+        world.init_resource::<WorldClock>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<PartitionEvent>>();
+        world.init_resource::<Events<MovedEvent>>();
+        world.send_event(PartitionEvent { chunk_idx: 0 });
+        world.run_system_once(partition);
 
-        let Some(next_block) = path.next_block() else {
-            cmd.entity(*actor).remove::<Path>();
-            continue;
+        let mut terrain = world.resource_mut::<Terrain>();
+        for y in 1..8 {
+            for z in 0..8 {
+                terrain.set_block(4, y, z, BlockType::STONE);
+            }
+        }
+
+        world
+    }
+
+    fn spawn_move(world: &mut World, timeout_s: f32) -> (Entity, Entity) {
+        let actor = world.spawn((Actor, Transform::from_xyz(1.5, 1., 1.5))).id();
+
+        let mut queue = CommandQueue::default();
+        let mut cmd = Commands::new(&mut queue, world);
+        let mut task = cmd.spawn((
+            ActorRef(actor),
+            TaskState::Executing,
+            Blackboard {
+                move_goals: vec![[6, 1, 6]],
+                ..Blackboard::default()
+            },
+        ));
+        TaskMoveTo.with_timeout(timeout_s).insert(&mut task);
+        let task = task.id();
+        queue.apply(world);
+
+        (actor, task)
+    }
+
+    fn move_schedule() -> Schedule {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                reset_task_scheduler,
+                task_move_to,
+                tick_task_timeouts,
+                block_move_system,
+            )
+                .chain(),
+        );
+        schedule
+    }
+
+    fn tick(world: &mut World, schedule: &mut Schedule) {
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(0.25));
+        schedule.run(world);
+    }
+
+    #[test]
+    fn unreachable_move_times_out() {
+        let mut world = stale_wall_world();
+        let (actor, task) = spawn_move(&mut world, 5.);
+        let mut schedule = move_schedule();
+
+        // up to the wall, then the path keeps being requested and dropped.
+        // Without the timeout this would never end
+        for _ in 0..19 {
+            tick(&mut world, &mut schedule);
+            assert!(*world.get::<TaskState>(task).unwrap() == TaskState::Executing);
+            assert!(world.get::<Transform>(actor).unwrap().translation.x < 4.);
+        }
+        assert!(!world.entity(actor).contains::<BlockMove>());
+
+        tick(&mut world, &mut schedule);
+        assert!(*world.get::<TaskState>(task).unwrap() == TaskState::Failed);
+    }
+
+    #[test]
+    fn paused_timeouts_wait() {
+        let mut world = stale_wall_world();
+        let (_, task) = spawn_move(&mut world, 5.);
+        let mut schedule = move_schedule();
+
+        world.resource_mut::<WorldClock>().is_paused = true;
+        for _ in 0..20 {
+            tick(&mut world, &mut schedule);
+        }
+        assert!(*world.get::<TaskState>(task).unwrap() == TaskState::Executing);
+
+        world.resource_mut::<WorldClock>().is_paused = false;
+        for _ in 0..20 {
+            tick(&mut world, &mut schedule);
+        }
+        assert!(*world.get::<TaskState>(task).unwrap() == TaskState::Failed);
+    }
+
+    /// Ticks of 1/256 s an actor with `speed` takes to walk 6 blocks along x
+    fn ticks_to_walk(speed: f32) -> u32 {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.fill_region([0, 0, 0], [7, 0, 7], BlockType::STONE);
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<PathCache>();
+        world.init_resource::<TaskScheduler>();
+        world.init_resource::<WorldClock>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<PartitionEvent>>();
+        world.init_resource::<Events<MovedEvent>>();
+        world.send_event(PartitionEvent { chunk_idx: 0 });
+        world.run_system_once(partition);
+
+        let stats = MovementStats {
+            speed,
+            ..MovementStats::default()
         };
+        let actor = world
+            .spawn((Actor, Transform::from_xyz(1.5, 1., 1.5), stats))
+            .id();
+        let task = world
+            .spawn((
+                ActorRef(actor),
+                TaskState::Executing,
+                TaskMoveTo,
+                Blackboard {
+                    move_goals: vec![[7, 1, 1]],
+                    ..Blackboard::default()
+                },
+            ))
+            .id();
 
-        let block_flags = get_block_flags(&terrain, next_block[0], next_block[1], next_block[2]);
+        let mut schedule = move_schedule();
 
-        if block_flags & path.flags == NavigationFlags::NONE {
-            cmd.entity(*actor).remove::<Path>();
-            continue;
+        for ticks in 1..10_000 {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f64(1. / 256.));
+            schedule.run(&mut world);
+
+            if *world.get::<TaskState>(task).unwrap() == TaskState::Success {
+                return ticks;
+            }
+        }
+
+        panic!("never arrived");
+    }
+
+    #[test]
+    fn half_the_speed_takes_twice_as_long() {
+        let fast = ticks_to_walk(8.);
+        let slow = ticks_to_walk(4.);
+
+        // each block costs one extra tick to pick the next one, which is
+        // noise at this tick rate
+        let ratio = slow as f32 / fast as f32;
+        assert!((ratio - 2.).abs() < 0.05, "{} vs {} ticks", slow, fast);
+    }
+
+    /// Runs 20 actors from the same corner of a 4 chunk floor to the far end
+    /// and counts the block path searches between partitions. With
+    /// `keep_cache` false the cache is emptied every tick, so every lookup
+    /// has to run A*.
+    fn colony_searches(keep_cache: bool) -> u64 {
+        let mut terrain = Terrain::new(4, 1, 1, 8).unwrap();
+        terrain.fill_region([0, 0, 0], [31, 0, 7], BlockType::STONE);
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<PathCache>();
+        world.init_resource::<TaskScheduler>();
+        world.init_resource::<WorldClock>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<PartitionEvent>>();
+        world.init_resource::<Events<MovedEvent>>();
+        for chunk_idx in 0..4 {
+            world.send_event(PartitionEvent { chunk_idx });
         }
+        world.run_system_once(partition);
+
+        let tasks = (0..20)
+            .map(|i| {
+                let start = Transform::from_xyz((i % 4) as f32 + 0.5, 1., (i / 4) as f32 + 0.5);
+                let actor = world.spawn((Actor, start)).id();
+
+                world
+                    .spawn((
+                        ActorRef(actor),
+                        TaskState::Executing,
+                        TaskMoveTo,
+                        Blackboard {
+                            move_goals: vec![[30, 1, 6]],
+                            ..Blackboard::default()
+                        },
+                    ))
+                    .id()
+            })
+            .collect::<Vec<_>>();
+
+        let mut schedule = move_schedule();
+        let mut searches = 0;
+
+        for _ in 0..400 {
+            tick(&mut world, &mut schedule);
+
+            if !keep_cache {
+                searches += world.resource::<PathCache>().misses;
+                world.insert_resource(PathCache::default());
+            }
+        }
+
+        assert!(tasks
+            .iter()
+            .all(|task| *world.get::<TaskState>(*task).unwrap() == TaskState::Success));
+
+        if keep_cache {
+            searches = world.resource::<PathCache>().misses;
+        }
+
+        searches
+    }
+
+    /// `cargo test path_cache_bench -- --nocapture`
+    ///
+    /// Searches are counted rather than timed, so this runs with the other
+    /// tests.
+    #[test]
+    fn path_cache_bench() {
+        let uncached = colony_searches(false);
+        let cached = colony_searches(true);
 
-        cmd.entity(*actor).insert(BlockMove {
-            speed: 4.,
-            target: path.blocks[path.current_block_idx],
-            look_at: true,
-        });
+        let saved = 1. - cached as f32 / uncached as f32;
+        println!(
+            "uncached {} searches, cached {} searches, {:.0}% fewer",
+            uncached,
+            cached,
+            saved * 100.
+        );
+        assert!(saved >= 0.2);
     }
 }
@@ -0,0 +1,120 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        query::With,
+        system::{Commands, Query, Res, ResMut},
+    },
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{
+        request_path, step_path, Actor, ActorRef, BlockMove, MovementStats, NavigationFlags,
+        NavigationGraph, Path, PathCache, PathStep, TaskBuilder, TaskState,
+    },
+    Terrain,
+};
+
+/// Walk a route of waypoints in order. Unreachable waypoints are skipped, and
+/// the task succeeds once it wraps back around to the first waypoint.
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskPatrol {
+    pub waypoints: Vec<[u32; 3]>,
+    pub current: usize,
+}
+
+impl TaskPatrol {
+    pub fn new(waypoints: Vec<[u32; 3]>) -> Self {
+        Self {
+            waypoints,
+            current: 0,
+        }
+    }
+
+    /// Move on to the next waypoint, returns true when the route is complete
+    fn advance(&mut self) -> bool {
+        self.current = (self.current + 1) % self.waypoints.len();
+        self.current == 0
+    }
+}
+
+pub fn task_patrol(
+    mut cmd: Commands,
+    terrain: Res<Terrain>,
+    graph: Res<NavigationGraph>,
+    mut path_cache: ResMut<PathCache>,
+    mut q_paths: Query<&mut Path, With<Actor>>,
+    q_movers: Query<&BlockMove, With<Actor>>,
+    q_transforms: Query<(&Transform, Option<&MovementStats>), With<Actor>>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &mut TaskPatrol)>,
+) {
+    for (ActorRef(actor), mut state, mut task) in q_behavior.iter_mut() {
+        if task.waypoints.is_empty() {
+            println!("no waypoints, cannot patrol!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        let Ok((transform, stats)) = q_transforms.get(*actor) else {
+            println!("no transform on actor, cannot patrol!");
+            cmd.entity(*actor).remove::<Path>();
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        if q_movers.contains(*actor) {
+            continue;
+        }
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        let Ok(mut path) = q_paths.get_mut(*actor) else {
+            let waypoint = task.waypoints[task.current];
+
+            match request_path(
+                &terrain,
+                &graph,
+                pos,
+                vec![waypoint],
+                NavigationFlags::COLONIST,
+            ) {
+                Some(path) => {
+                    cmd.entity(*actor).insert(path);
+                }
+                None => {
+                    println!("waypoint {:?} is unreachable, skipping", waypoint);
+                    if task.advance() {
+                        *state = TaskState::Success;
+                    }
+                }
+            }
+
+            continue;
+        };
+
+        let stats = stats.copied().unwrap_or_default();
+
+        match step_path(
+            &mut cmd,
+            &terrain,
+            &graph,
+            &mut path_cache,
+            *actor,
+            pos,
+            &stats,
+            &mut path,
+        ) {
+            PathStep::Arrived | PathStep::Stranded => {
+                if task.advance() {
+                    *state = TaskState::Success;
+                }
+            }
+            PathStep::Moving | PathStep::Repath => {}
+        }
+    }
+}
@@ -11,8 +11,8 @@ use task_derive::TaskBuilder;
 
 use crate::{
     colonists::{
-        Actor, ActorRef, Blackboard, InInventory, InPartition, Inventory, Item, NavigationGraph,
-        TaskBuilder, TaskState,
+        get_max_stack, Actor, ActorRef, Blackboard, CarryCapacity, InInventory, InPartition,
+        Inventory, Item, NavigationGraph, TaskBuilder, TaskState,
     },
     Terrain,
 };
@@ -24,8 +24,8 @@ pub fn task_pick_up_item(
     mut cmd: Commands,
     terrain: Res<Terrain>,
     mut graph: ResMut<NavigationGraph>,
-    q_items: Query<&Transform, With<Item>>,
-    mut q_actors: Query<&mut Inventory, With<Actor>>,
+    mut q_items: Query<(&Transform, &mut Item)>,
+    mut q_actors: Query<(&mut Inventory, &mut CarryCapacity), With<Actor>>,
     mut q_behavior: Query<(&ActorRef, &mut TaskState, &mut Blackboard), With<TaskPickUpItem>>,
 ) {
     for (ActorRef(actor), mut state, blackboard) in q_behavior.iter_mut() {
@@ -35,18 +35,52 @@ pub fn task_pick_up_item(
             continue;
         };
 
-        let Ok(mut inventory) = q_actors.get_mut(*actor) else {
+        // interrupted on the way, free the item up for someone else
+        if *state == TaskState::Cancelled {
+            if let Ok((_, mut item_data)) = q_items.get_mut(item) {
+                if item_data.reserved == Some(*actor) {
+                    item_data.reserved = None;
+                }
+            }
+            continue;
+        }
+
+        let Ok((mut inventory, mut capacity)) = q_actors.get_mut(*actor) else {
             println!("Actor does not have an inventory, cannot pick anything up!");
             *state = TaskState::Failed;
             continue;
         };
 
-        let Ok(item_transform) = q_items.get(item) else {
+        let Ok((item_transform, item_data)) = q_items.get(item) else {
             println!("Item does not exist, cannot pick up!");
             *state = TaskState::Failed;
             continue;
         };
 
+        if let Some(max_stack) = get_max_stack(&item_data.tags) {
+            let held = inventory
+                .items
+                .iter()
+                .filter(|e| {
+                    q_items
+                        .get(**e)
+                        .is_ok_and(|(_, held_item)| held_item.tags == item_data.tags)
+                })
+                .count() as u32;
+
+            if held >= max_stack {
+                println!("Inventory is full, cannot pick up!");
+                *state = TaskState::Failed;
+                continue;
+            }
+        }
+
+        if item_data.weight() > capacity.remaining() {
+            println!("Item is too heavy to carry, cannot pick up!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
         let item_x = item_transform.translation.x as u32;
         let item_y = item_transform.translation.y as u32;
         let item_z = item_transform.translation.z as u32;
@@ -55,25 +89,146 @@ pub fn task_pick_up_item(
             panic!("Missing partition_id?");
         };
 
-        let Some(partition) = graph.get_partition_mut(&partition_id) else {
+        if graph.get_partition(&partition_id).is_none() {
             panic!("Missing partition!? {}", partition_id);
         };
 
         println!("Removing item from partition");
-        if !partition.items.remove(&item) {
+        if !graph.remove_item(&partition_id, &item) {
             println!("Item not here!");
             *state = TaskState::Failed;
             return;
         }
 
+        if inventory
+            .add_item(item, item_data.weight(), &mut capacity)
+            .is_err()
+        {
+            println!("Inventory is full, cannot pick up!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
         let mut ecmd = cmd.entity(item);
         ecmd.remove::<InPartition>();
 
         println!("Item is now in inventory {}", item.index());
-        inventory.items.push(item);
         ecmd.insert(Visibility::Hidden);
         ecmd.insert(InInventory { holder: *actor });
 
         *state = TaskState::Success;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{
+        entity::Entity,
+        event::Events,
+        schedule::{IntoSystemConfigs, Schedule},
+        system::RunSystemOnce,
+        world::World,
+    };
+
+    use crate::{
+        colonists::{partition, partition_orphaned_items, ItemTag, PartitionEvent},
+        BlockType,
+    };
+
+    use super::*;
+
+    /// A partitioned stone floor with a stone lying on it
+    fn stone_world() -> (World, Entity) {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.fill_region([0, 0, 0], [7, 0, 7], BlockType::STONE);
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<Events<PartitionEvent>>();
+
+        let stone = world
+            .spawn((
+                Transform::from_xyz(3.5, 1., 3.5),
+                Item {
+                    tags: vec![ItemTag::Stone],
+                    reserved: None,
+                },
+            ))
+            .id();
+
+        world.send_event(PartitionEvent { chunk_idx: 0 });
+        let mut schedule = Schedule::default();
+        schedule.add_systems((partition, partition_orphaned_items).chain());
+        schedule.run(&mut world);
+
+        (world, stone)
+    }
+
+    /// A colonist already carrying two stones, 8 of `max` weight
+    fn pick_up(world: &mut World, stone: Entity, max: u32) -> (Entity, Entity) {
+        let carried = (0..2)
+            .map(|_| {
+                world
+                    .spawn(Item {
+                        tags: vec![ItemTag::Stone],
+                        reserved: None,
+                    })
+                    .id()
+            })
+            .collect::<Vec<_>>();
+        let colonist = world
+            .spawn((
+                Actor,
+                Inventory { items: carried },
+                CarryCapacity { max, current: 8 },
+            ))
+            .id();
+        let task = world
+            .spawn((
+                ActorRef(colonist),
+                TaskState::Executing,
+                Blackboard {
+                    item: Some(stone),
+                    ..Blackboard::default()
+                },
+                TaskPickUpItem,
+            ))
+            .id();
+
+        world.run_system_once(task_pick_up_item);
+
+        (colonist, task)
+    }
+
+    #[test]
+    fn full_colonist_rejects_a_pickup() {
+        let (mut world, stone) = stone_world();
+        let (colonist, task) = pick_up(&mut world, stone, 10);
+
+        assert!(*world.get::<TaskState>(task).unwrap() == TaskState::Failed);
+        assert_eq!(world.get::<Inventory>(colonist).unwrap().items.len(), 2);
+        assert_eq!(world.get::<CarryCapacity>(colonist).unwrap().current, 8);
+        assert!(world.get::<InInventory>(stone).is_none());
+
+        // still on the ground for someone else
+        let partition_id = world.get::<InPartition>(stone).unwrap().partition_id;
+        let graph = world.resource::<NavigationGraph>();
+        assert!(graph
+            .get_partition(&partition_id)
+            .unwrap()
+            .items
+            .contains(&stone));
+    }
+
+    #[test]
+    fn strong_colonist_takes_the_same_pickup() {
+        let (mut world, stone) = stone_world();
+        let (colonist, task) = pick_up(&mut world, stone, 20);
+
+        assert!(*world.get::<TaskState>(task).unwrap() == TaskState::Success);
+        assert_eq!(world.get::<Inventory>(colonist).unwrap().items.len(), 3);
+        assert_eq!(world.get::<CarryCapacity>(colonist).unwrap().current, 12);
+        assert_eq!(world.get::<InInventory>(stone).unwrap().holder, colonist);
+    }
+}
@@ -0,0 +1,80 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        event::EventWriter,
+        system::{Query, ResMut},
+    },
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{
+        job_access_points, ActorRef, DestroyItemEvent, Inventory, Item, ItemTag, JobType,
+        TaskBuilder, TaskState,
+    },
+    BlockChangedEvent, BlockType, Terrain,
+};
+
+/// Place a carried torch in the empty block `target`, standing next to it.
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskPlaceTorch {
+    pub target: [u32; 3],
+}
+
+pub fn task_place_torch(
+    mut terrain: ResMut<Terrain>,
+    q_items: Query<&Item>,
+    mut q_actors: Query<(&Transform, &mut Inventory)>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &TaskPlaceTorch)>,
+    mut ev_destroy_item: EventWriter<DestroyItemEvent>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+) {
+    for (ActorRef(actor), mut state, task) in q_behavior.iter_mut() {
+        let [x, y, z] = task.target;
+
+        if !terrain.get_block(x, y, z).is_empty() {
+            println!("Target is not empty, cannot place torch!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        let Ok((transform, mut inventory)) = q_actors.get_mut(*actor) else {
+            println!("Actor is missing transform or inventory, cannot place torch!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        if !job_access_points(task.target, JobType::Mine).contains(&pos) {
+            println!("Actor is not next to the target, cannot place torch!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        let torch = inventory.items.iter().position(|e| {
+            q_items
+                .get(*e)
+                .is_ok_and(|item| item.tags.contains(&ItemTag::Torch))
+        });
+
+        let Some(torch_idx) = torch else {
+            println!("Actor is not carrying a torch, cannot place torch!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let entity = inventory.items.remove(torch_idx);
+        ev_destroy_item.send(DestroyItemEvent { entity });
+
+        let change = terrain.set_block(x, y, z, BlockType::TORCH);
+        ev_block_changed.send(change.into());
+
+        *state = TaskState::Success;
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{With, Without},
+        system::{Commands, Query, Res, ResMut},
+    },
+    render::view::Visibility,
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{
+        Actor, ActorRef, Blackboard, InInventory, InPartition, Inventory, Item, NavigationGraph,
+        TaskBuilder, TaskState,
+    },
+    Terrain,
+};
+
+/// Let go of the blackboard item of a job that failed part way, like the
+/// load of a haul or the material of a build. The reservation is cleared,
+/// and an item that was already picked up is put down where the actor
+/// stands, so the job can be taken again instead of the item being stuck in
+/// an inventory.
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskReleaseItem;
+
+pub fn task_release_item(
+    mut cmd: Commands,
+    terrain: Res<Terrain>,
+    mut graph: ResMut<NavigationGraph>,
+    mut q_items: Query<(&mut Transform, &mut Item, Option<&InInventory>), Without<Actor>>,
+    mut q_actors: Query<(&Transform, &mut Inventory), With<Actor>>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &Blackboard), With<TaskReleaseItem>>,
+) {
+    for (ActorRef(actor), mut state, blackboard) in q_behavior.iter_mut() {
+        // nothing was reserved yet, there is nothing to let go of
+        let Some(item) = blackboard.item else {
+            *state = TaskState::Success;
+            continue;
+        };
+
+        let Ok((mut item_transform, mut item_data, held)) = q_items.get_mut(item) else {
+            *state = TaskState::Success;
+            continue;
+        };
+
+        if item_data.reserved == Some(*actor) {
+            item_data.reserved = None;
+        }
+
+        if held.is_none_or(|held| held.holder != *actor) {
+            *state = TaskState::Success;
+            continue;
+        }
+
+        let Ok((transform, mut inventory)) = q_actors.get_mut(*actor) else {
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        drop_held_item(
+            &mut cmd,
+            &terrain,
+            &mut graph,
+            item,
+            &item_data,
+            &mut item_transform,
+            transform,
+            &mut inventory,
+        );
+
+        *state = TaskState::Success;
+    }
+}
+
+/// Take `item` out of the inventory and put it down where its holder stands,
+/// back in the partition it lands in.
+pub fn drop_held_item(
+    cmd: &mut Commands,
+    terrain: &Terrain,
+    graph: &mut NavigationGraph,
+    item: Entity,
+    item_data: &Item,
+    item_transform: &mut Transform,
+    holder_transform: &Transform,
+    inventory: &mut Inventory,
+) {
+    println!("Dropping item {}", item.index());
+    inventory.items.retain(|e| *e != item);
+    item_transform.translation = holder_transform.translation;
+
+    let mut ecmd = cmd.entity(item);
+    ecmd.remove::<InInventory>();
+    ecmd.insert(Visibility::Visible);
+
+    let x = holder_transform.translation.x as u32;
+    let y = holder_transform.translation.y as u32;
+    let z = holder_transform.translation.z as u32;
+
+    if let Some(partition_id) = terrain.get_partition_id_u32(x, y, z) {
+        if graph.add_item(&partition_id, item, &item_data.tags) {
+            ecmd.insert(InPartition { partition_id });
+        }
+    }
+}
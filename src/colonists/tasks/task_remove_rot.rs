@@ -0,0 +1,77 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        event::EventWriter,
+        system::{Query, Res, ResMut},
+    },
+    time::Time,
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{job_access_points, ActorRef, Blackboard, JobType, TaskBuilder, TaskState},
+    BlockChangedEvent, BlockType, Terrain,
+};
+
+/// Seconds it takes to cut out a rotten block and set stone in its place
+const REMOVE_ROT_S: f32 = 1.5;
+
+#[derive(Component, Clone, TaskBuilder)]
+#[estimated_duration(remaining_s)]
+pub struct TaskRemoveRot {
+    pub target: [u32; 3],
+    pub progress: f32,
+}
+
+impl TaskRemoveRot {
+    fn remaining_s(&self, _blackboard: &Blackboard) -> Option<f32> {
+        Some((REMOVE_ROT_S - self.progress).max(0.))
+    }
+}
+
+pub fn task_remove_rot(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    q_actors: Query<&Transform>,
+    mut q_behavior: Query<(&ActorRef, &mut TaskState, &mut TaskRemoveRot)>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+) {
+    for (ActorRef(actor), mut state, mut task) in q_behavior.iter_mut() {
+        let [x, y, z] = task.target;
+
+        if terrain.get_block(x, y, z).block != BlockType::ROTTEN {
+            println!("Target is not rotten, cannot remove rot!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        let Ok(transform) = q_actors.get(*actor) else {
+            println!("Actor is missing transform, cannot remove rot!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let pos = [
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ];
+
+        if !job_access_points(task.target, JobType::Mine).contains(&pos) {
+            println!("Actor is not next to the rot, cannot remove it!");
+            *state = TaskState::Failed;
+            continue;
+        }
+
+        if task.progress < REMOVE_ROT_S {
+            task.progress += time.delta_seconds();
+            continue;
+        }
+
+        let change = terrain.set_block(x, y, z, BlockType::STONE);
+        ev_block_changed.send(change.into());
+
+        *state = TaskState::Success;
+    }
+}
@@ -0,0 +1,327 @@
+use std::sync::Arc;
+
+use bevy::ecs::{
+    component::Component,
+    entity::Entity,
+    system::{Commands, EntityCommands, Query},
+};
+
+use crate::colonists::{Blackboard, TaskBuilder, TaskState};
+
+/// Runs its children one after the other on the same task entity, so they
+/// share a blackboard. Succeeds once they all have, fails with the first
+/// child that fails. Children are plain tasks, composites all drive the same
+/// `TaskState` so they nest with `BehaviorNode` instead.
+#[derive(Component, Clone)]
+pub struct SequenceTask {
+    pub children: Vec<Arc<dyn TaskBuilder>>,
+    pub current: usize,
+}
+
+impl SequenceTask {
+    pub fn new(children: Vec<Arc<dyn TaskBuilder>>) -> Self {
+        Self {
+            children,
+            current: 0,
+        }
+    }
+}
+
+impl TaskBuilder for SequenceTask {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        insert_composite(cmd, Self::new(self.children.clone()), &self.children);
+    }
+
+    fn remove(&self, cmd: &mut EntityCommands) {
+        remove_composite::<Self>(cmd, &self.children);
+    }
+
+    fn label(&self) -> String {
+        composite_label("Sequence", &self.children)
+    }
+
+    fn estimated_duration(&self, blackboard: &Blackboard) -> Option<f32> {
+        self.children
+            .iter()
+            .map(|child| child.estimated_duration(blackboard))
+            .sum()
+    }
+}
+
+/// Tries its children one after the other on the same task entity until one
+/// succeeds, and fails once they all have. See `SequenceTask` for nesting.
+#[derive(Component, Clone)]
+pub struct SelectorTask {
+    pub children: Vec<Arc<dyn TaskBuilder>>,
+    pub current: usize,
+}
+
+impl SelectorTask {
+    pub fn new(children: Vec<Arc<dyn TaskBuilder>>) -> Self {
+        Self {
+            children,
+            current: 0,
+        }
+    }
+}
+
+impl TaskBuilder for SelectorTask {
+    fn insert(&self, cmd: &mut EntityCommands) {
+        insert_composite(cmd, Self::new(self.children.clone()), &self.children);
+    }
+
+    fn remove(&self, cmd: &mut EntityCommands) {
+        remove_composite::<Self>(cmd, &self.children);
+    }
+
+    fn label(&self) -> String {
+        composite_label("Selector", &self.children)
+    }
+}
+
+fn insert_composite<T: Component>(
+    cmd: &mut EntityCommands,
+    composite: T,
+    children: &[Arc<dyn TaskBuilder>],
+) {
+    cmd.insert(composite);
+
+    if let Some(first) = children.first() {
+        first.insert(cmd);
+    }
+}
+
+fn remove_composite<T: Component>(cmd: &mut EntityCommands, children: &[Arc<dyn TaskBuilder>]) {
+    // only the component knows which child is running, removing one that
+    // isn't there does nothing
+    for child in children.iter() {
+        child.remove(cmd);
+    }
+
+    cmd.remove::<T>();
+}
+
+fn composite_label(name: &str, children: &[Arc<dyn TaskBuilder>]) -> String {
+    let children = children
+        .iter()
+        .map(|child| child.label())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!("{}({})", name, children)
+}
+
+/// Swap in the next child once the current one ended with `proceed_on`, any
+/// other result ends the composite with it. Returns the composite's state.
+fn advance(
+    cmd: &mut EntityCommands,
+    children: &[Arc<dyn TaskBuilder>],
+    current: &mut usize,
+    state: TaskState,
+    proceed_on: TaskState,
+) -> TaskState {
+    let Some(child) = children.get(*current) else {
+        // without children there is nothing to wait for
+        return if state == TaskState::Executing {
+            proceed_on
+        } else {
+            state
+        };
+    };
+
+    if state != proceed_on {
+        return state;
+    }
+
+    child.remove(cmd);
+    *current += 1;
+
+    match children.get(*current) {
+        Some(next) => {
+            next.insert(cmd);
+            TaskState::Executing
+        }
+        None => proceed_on,
+    }
+}
+
+/// Runs before `behavior_system`, which only ever sees the whole sequence
+/// finish
+pub fn advance_sequence_tasks(
+    mut cmd: Commands,
+    mut q_behavior: Query<(Entity, &mut SequenceTask, &mut TaskState)>,
+) {
+    for (entity, mut task, mut state) in q_behavior.iter_mut() {
+        let task = task.as_mut();
+        let next = advance(
+            &mut cmd.entity(entity),
+            &task.children,
+            &mut task.current,
+            *state,
+            TaskState::Success,
+        );
+
+        if next != *state {
+            *state = next;
+        }
+    }
+}
+
+/// Runs before `behavior_system`, which only ever sees the whole selector
+/// finish
+pub fn advance_selector_tasks(
+    mut cmd: Commands,
+    mut q_behavior: Query<(Entity, &mut SelectorTask, &mut TaskState)>,
+) {
+    for (entity, mut task, mut state) in q_behavior.iter_mut() {
+        let task = task.as_mut();
+        let next = advance(
+            &mut cmd.entity(entity),
+            &task.children,
+            &mut task.current,
+            *state,
+            TaskState::Failed,
+        );
+
+        if next != *state {
+            *state = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{
+        schedule::{IntoSystemConfigs, Schedule},
+        system::{CommandQueue, ResMut, Resource},
+        world::World,
+    };
+    use task_derive::TaskBuilder;
+
+    use super::*;
+
+    /// Names of the test tasks in the order they ran
+    #[derive(Resource, Default)]
+    struct Ran(Vec<&'static str>);
+
+    #[derive(Component, Clone, TaskBuilder)]
+    struct TaskPass(&'static str);
+
+    #[derive(Component, Clone, TaskBuilder)]
+    struct TaskFail(&'static str);
+
+    fn task_pass(mut ran: ResMut<Ran>, mut q_behavior: Query<(&mut TaskState, &TaskPass)>) {
+        for (mut state, task) in q_behavior.iter_mut() {
+            ran.0.push(task.0);
+            *state = TaskState::Success;
+        }
+    }
+
+    fn task_fail(mut ran: ResMut<Ran>, mut q_behavior: Query<(&mut TaskState, &TaskFail)>) {
+        for (mut state, task) in q_behavior.iter_mut() {
+            ran.0.push(task.0);
+            *state = TaskState::Failed;
+        }
+    }
+
+    fn pass(name: &'static str) -> Arc<dyn TaskBuilder> {
+        Arc::new(TaskPass(name))
+    }
+
+    fn fail(name: &'static str) -> Arc<dyn TaskBuilder> {
+        Arc::new(TaskFail(name))
+    }
+
+    /// Inserts `task` like `behavior_system` would and ticks until it is
+    /// done, returning its final state and the children that ran
+    fn run(task: impl TaskBuilder) -> (TaskState, Vec<&'static str>) {
+        let mut world = World::new();
+        world.init_resource::<Ran>();
+
+        let mut queue = CommandQueue::default();
+        let mut cmd = Commands::new(&mut queue, &world);
+        let mut entity = cmd.spawn((TaskState::Executing, Blackboard::default()));
+        task.insert(&mut entity);
+        let entity = entity.id();
+        queue.apply(&mut world);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                (task_pass, task_fail),
+                (advance_sequence_tasks, advance_selector_tasks),
+            )
+                .chain(),
+        );
+
+        for _ in 0..10 {
+            schedule.run(&mut world);
+
+            let state = *world.get::<TaskState>(entity).unwrap();
+            if state != TaskState::Executing {
+                return (state, world.remove_resource::<Ran>().unwrap().0);
+            }
+        }
+
+        panic!("task never finished");
+    }
+
+    #[test]
+    fn sequence_stops_at_the_first_failure() {
+        let (state, ran) = run(SequenceTask::new(vec![pass("a"), pass("b"), pass("c")]));
+        assert!(state == TaskState::Success);
+        assert_eq!(ran, vec!["a", "b", "c"]);
+
+        let (state, ran) = run(SequenceTask::new(vec![pass("a"), fail("b"), pass("c")]));
+        assert!(state == TaskState::Failed);
+        assert_eq!(ran, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn selector_stops_at_the_first_success() {
+        let (state, ran) = run(SelectorTask::new(vec![fail("a"), pass("b"), fail("c")]));
+        assert!(state == TaskState::Success);
+        assert_eq!(ran, vec!["a", "b"]);
+
+        let (state, ran) = run(SelectorTask::new(vec![fail("a"), fail("b")]));
+        assert!(state == TaskState::Failed);
+        assert_eq!(ran, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn empty_composites_finish_at_once() {
+        let (state, ran) = run(SequenceTask::new(vec![]));
+        assert!(state == TaskState::Success);
+        assert!(ran.is_empty());
+
+        let (state, ran) = run(SelectorTask::new(vec![]));
+        assert!(state == TaskState::Failed);
+        assert!(ran.is_empty());
+    }
+
+    #[test]
+    fn labels_list_the_children() {
+        let task = SequenceTask::new(vec![pass("a"), fail("b")]);
+        assert_eq!(task.label(), "Sequence(TaskPass, TaskFail)");
+
+        let task = SelectorTask::new(vec![fail("a"), pass("b")]);
+        assert_eq!(task.label(), "Selector(TaskFail, TaskPass)");
+    }
+
+    #[test]
+    fn removing_clears_the_running_child() {
+        let task = SequenceTask::new(vec![fail("a")]);
+        let mut world = World::new();
+
+        let mut queue = CommandQueue::default();
+        let mut cmd = Commands::new(&mut queue, &world);
+        let mut entity = cmd.spawn(TaskState::Executing);
+        task.insert(&mut entity);
+        task.remove(&mut entity);
+        let entity = entity.id();
+        queue.apply(&mut world);
+
+        assert!(!world.entity(entity).contains::<TaskFail>());
+        assert!(!world.entity(entity).contains::<SequenceTask>());
+    }
+}
@@ -0,0 +1,18 @@
+use bevy::ecs::{component::Component, system::Query};
+use task_derive::TaskBuilder;
+
+use crate::colonists::{Blackboard, TaskBuilder, TaskState};
+
+/// Point the next `TaskMoveTo` at fixed goals, for behaviors that already
+/// know where they are going when they are built
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskSetMoveGoals(pub Vec<[u32; 3]>);
+
+pub fn task_set_move_goals(
+    mut q_behavior: Query<(&mut TaskState, &mut Blackboard, &TaskSetMoveGoals)>,
+) {
+    for (mut state, mut blackboard, task) in q_behavior.iter_mut() {
+        blackboard.move_goals = task.0.clone();
+        *state = TaskState::Success;
+    }
+}
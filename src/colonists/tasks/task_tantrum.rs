@@ -0,0 +1,73 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        query::With,
+        system::{Query, Res},
+    },
+    transform::components::Transform,
+};
+use task_derive::TaskBuilder;
+
+use crate::{
+    colonists::{job_access_points, Actor, ActorRef, Blackboard, JobType, TaskBuilder, TaskState},
+    Terrain,
+};
+
+/// How far an upset colonist looks for something to break
+const TANTRUM_RANGE: i32 = 8;
+
+/// Pick the nearest piece of furniture to smash, setting it as the target
+/// block and the blocks around it as move goals.
+#[derive(Component, Clone, TaskBuilder)]
+pub struct TaskTantrum;
+
+pub fn task_tantrum(
+    terrain: Res<Terrain>,
+    q_transforms: Query<&Transform, With<Actor>>,
+    mut q_behavior: Query<(&ActorRef, &mut Blackboard, &mut TaskState), With<TaskTantrum>>,
+) {
+    for (ActorRef(actor), mut blackboard, mut state) in q_behavior.iter_mut() {
+        let Ok(transform) = q_transforms.get(*actor) else {
+            println!("no transform on actor, cannot throw a tantrum!");
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        let [x, y, z] = [
+            transform.translation.x as i32,
+            transform.translation.y as i32,
+            transform.translation.z as i32,
+        ];
+
+        let mut nearest = None;
+        let mut nearest_distance = i32::MAX;
+
+        for dx in -TANTRUM_RANGE..=TANTRUM_RANGE {
+            for dy in -TANTRUM_RANGE..=TANTRUM_RANGE {
+                for dz in -TANTRUM_RANGE..=TANTRUM_RANGE {
+                    let [bx, by, bz] = [x + dx, y + dy, z + dz];
+
+                    if !terrain.get_block_i32(bx, by, bz).block.is_furniture() {
+                        continue;
+                    }
+
+                    let distance = dx.abs() + dy.abs() + dz.abs();
+
+                    if distance < nearest_distance {
+                        nearest_distance = distance;
+                        nearest = Some([bx as u32, by as u32, bz as u32]);
+                    }
+                }
+            }
+        }
+
+        let Some(target) = nearest else {
+            *state = TaskState::Failed;
+            continue;
+        };
+
+        blackboard.target_block = Some(target);
+        blackboard.move_goals = job_access_points(target, JobType::Mine);
+        *state = TaskState::Success;
+    }
+}
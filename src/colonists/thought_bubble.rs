@@ -0,0 +1,150 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        system::{Commands, Query, Res},
+    },
+    math::Vec3,
+    prelude::default,
+    render::{camera::Camera, color::Color, view::Visibility},
+    text::{Text, TextStyle},
+    time::Time,
+    transform::components::GlobalTransform,
+    ui::{node_bundles::TextBundle, PositionType, Style, Val},
+};
+
+use crate::controls::MainCamera;
+
+use super::{Behavior, Colonist, HasBehavior};
+
+/// Seconds a thought stays up after the task changes
+const THOUGHT_BUBBLE_S: f32 = 3.;
+/// Last part of `THOUGHT_BUBBLE_S` spent fading out
+const THOUGHT_BUBBLE_FADE_S: f32 = 0.5;
+/// Height above the colonist's feet the text is drawn at
+const THOUGHT_BUBBLE_HEIGHT: f32 = 2.2;
+
+/// Name of the task a colonist just started, shown over its head for a few
+/// seconds to see what it is up to without an inspector.
+#[derive(Component)]
+pub struct ThoughtBubble {
+    pub text: String,
+    /// Seconds since `text` last changed
+    pub timer: f32,
+    /// UI text entity. UI nodes can't be children of world entities, so it
+    /// is positioned by hand, see `draw_thought_bubbles`.
+    pub label: Entity,
+}
+
+#[derive(Component)]
+pub struct ThoughtBubbleLabel {
+    pub owner: Entity,
+}
+
+pub fn update_thought_bubbles(
+    mut cmd: Commands,
+    time: Res<Time>,
+    q_behaviors: Query<&Behavior>,
+    mut q_colonists: Query<
+        (Entity, Option<&HasBehavior>, Option<&mut ThoughtBubble>),
+        With<Colonist>,
+    >,
+) {
+    for (entity, has_behavior, bubble) in q_colonists.iter_mut() {
+        let text = has_behavior
+            .and_then(|b| q_behaviors.get(b.behavior_entity).ok())
+            .and_then(|behavior| behavior.tree.current_task_label())
+            .unwrap_or_default();
+
+        let Some(mut bubble) = bubble else {
+            let label = cmd
+                .spawn((
+                    ThoughtBubbleLabel { owner: entity },
+                    TextBundle {
+                        text: Text::from_section(
+                            text.clone(),
+                            TextStyle {
+                                font_size: 14.0,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ),
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        },
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                ))
+                .id();
+
+            cmd.entity(entity).insert(ThoughtBubble {
+                text,
+                timer: 0.,
+                label,
+            });
+            continue;
+        };
+
+        if bubble.text != text {
+            bubble.text = text;
+            bubble.timer = 0.;
+        } else {
+            bubble.timer += time.delta_seconds();
+        }
+    }
+}
+
+/// Places each thought over its colonist on screen and fades it out. Labels
+/// of colonists that no longer exist are despawned.
+pub fn draw_thought_bubbles(
+    mut cmd: Commands,
+    q_cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    q_colonists: Query<(&GlobalTransform, &ThoughtBubble)>,
+    mut q_labels: Query<(
+        Entity,
+        &ThoughtBubbleLabel,
+        &mut Text,
+        &mut Style,
+        &mut Visibility,
+    )>,
+) {
+    let Ok((camera, camera_transform)) = q_cameras.get_single() else {
+        return;
+    };
+
+    for (entity, label, _, _, _) in q_labels.iter() {
+        if !q_colonists.contains(label.owner) {
+            cmd.entity(entity).despawn();
+        }
+    }
+
+    for (transform, bubble) in q_colonists.iter() {
+        let Ok((_, _, mut text, mut style, mut visibility)) = q_labels.get_mut(bubble.label) else {
+            continue;
+        };
+
+        let pos = transform.translation() + Vec3::Y * THOUGHT_BUBBLE_HEIGHT;
+        let screen_pos = camera.world_to_viewport(camera_transform, pos);
+        let alpha = ((THOUGHT_BUBBLE_S - bubble.timer) / THOUGHT_BUBBLE_FADE_S).clamp(0., 1.);
+
+        let Some(screen_pos) = screen_pos.filter(|_| alpha > 0. && !bubble.text.is_empty()) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        *visibility = Visibility::Visible;
+        style.left = Val::Px(screen_pos.x);
+        style.top = Val::Px(screen_pos.y);
+
+        let section = &mut text.sections[0];
+
+        if section.value != bubble.text {
+            section.value = bubble.text.clone();
+        }
+
+        section.style.color = Color::WHITE.with_a(alpha);
+    }
+}
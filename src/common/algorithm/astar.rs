@@ -3,12 +3,13 @@ use std::collections::HashMap;
 
 use crate::common::PriorityQueue;
 
-pub struct AStarSettings<T, H, C, N, G>
+pub struct AStarSettings<T, H, C, N, I, G>
 where
     T: std::cmp::Eq + std::hash::Hash + Copy,
     H: Fn(T) -> f32,
     C: Fn(T, T) -> f32,
-    N: Fn(T) -> Vec<T>,
+    N: Fn(T) -> I,
+    I: IntoIterator<Item = T>,
     G: Fn(T) -> bool,
 {
     pub start: T,
@@ -17,6 +18,11 @@ where
     pub heuristic: H,
     pub neighbors: N,
     pub max_depth: u32,
+    /// Remember the neighbors of each node the first time they are asked
+    /// for, instead of calling `neighbors` again when a node is expanded a
+    /// second time. Worth it when `neighbors` is costly, like partition
+    /// lookups, and not for cheap ones like block offsets.
+    pub neighbors_cached: bool,
 }
 
 pub struct AStarResult<T> {
@@ -25,18 +31,20 @@ pub struct AStarResult<T> {
     pub cost: f32,
 }
 
-pub fn astar<T, H, C, N, G>(settings: AStarSettings<T, H, C, N, G>) -> AStarResult<T>
+pub fn astar<T, H, C, N, I, G>(settings: AStarSettings<T, H, C, N, I, G>) -> AStarResult<T>
 where
     H: Fn(T) -> f32,
     T: std::cmp::Eq + std::hash::Hash + Copy,
     C: Fn(T, T) -> f32,
-    N: Fn(T) -> Vec<T>,
+    N: Fn(T) -> I,
+    I: IntoIterator<Item = T>,
     G: Fn(T) -> bool,
 {
     let mut depth = 0;
     let mut open = PriorityQueue::new();
     let mut from = HashMap::new();
     let mut costs = HashMap::new();
+    let mut neighbor_cache: HashMap<T, Vec<T>> = HashMap::new();
     let mut goal: Option<T> = None;
 
     let mut result = AStarResult {
@@ -68,7 +76,14 @@ where
             break;
         }
 
-        let neighbors = (settings.neighbors)(current);
+        let neighbors: Vec<T> = if settings.neighbors_cached {
+            neighbor_cache
+                .entry(current)
+                .or_insert_with(|| (settings.neighbors)(current).into_iter().collect())
+                .clone()
+        } else {
+            (settings.neighbors)(current).into_iter().collect()
+        };
 
         for next in neighbors {
             let cost = if (settings.is_goal)(next) {
@@ -114,3 +129,54 @@ where
     // note: path is returned in reverse order
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashSet};
+
+    use super::*;
+
+    /// Searches a 12 by 12 grid with a wall that has one gap, counting how
+    /// often neighbors are asked for and for how many distinct nodes.
+    fn grid_search(neighbors_cached: bool) -> (AStarResult<[i32; 2]>, u32, usize) {
+        let calls = RefCell::new(0);
+        let asked = RefCell::new(HashSet::new());
+        let is_wall = |[x, y]: [i32; 2]| x == 6 && y != 10;
+
+        let result = astar(AStarSettings {
+            start: [0, 0],
+            is_goal: |p| p == [11, 0],
+            cost: |a: [i32; 2], b: [i32; 2]| ((a[0] - b[0]).abs() + (a[1] - b[1]).abs()) as f32,
+            heuristic: |p| ((11 - p[0]).abs() + p[1].abs()) as f32,
+            neighbors: |p: [i32; 2]| {
+                *calls.borrow_mut() += 1;
+                asked.borrow_mut().insert(p);
+
+                [[1, 0], [-1, 0], [0, 1], [0, -1]]
+                    .into_iter()
+                    .map(move |[dx, dy]| [p[0] + dx, p[1] + dy])
+                    .filter(|n| (0..12).contains(&n[0]) && (0..12).contains(&n[1]))
+                    .filter(|n| !is_wall(*n))
+            },
+            max_depth: 2000,
+            neighbors_cached,
+        });
+
+        let distinct = asked.borrow().len();
+        (result, calls.into_inner(), distinct)
+    }
+
+    #[test]
+    fn cached_neighbors_find_the_same_path() {
+        let (uncached, uncached_calls, _) = grid_search(false);
+        let (cached, cached_calls, cached_distinct) = grid_search(true);
+
+        assert!(uncached.is_success && cached.is_success);
+        assert_eq!(uncached.path, cached.path);
+        assert_eq!(uncached.cost, cached.cost);
+
+        // every node is asked for its neighbors at most once
+        assert_eq!(cached_calls as usize, cached_distinct);
+        assert!(cached_calls <= uncached_calls);
+    }
+}
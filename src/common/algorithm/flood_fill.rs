@@ -1,23 +1,23 @@
 /// Flood fill starting from `seed` location. Every point is checked
-/// against `fill`. The `fill` function needs to both check if the point
-/// should be filled (bool), and fill it in.
-#[allow(dead_code)]
-pub fn flood_fill_i32<F: FnMut([i32; 3]) -> bool>(seed: [i32; 3], mut fill: F) {
-    let mut queue = vec![seed];
+/// against `fill`, which is also given the point it was reached from, for
+/// fills where not every step between neighbors is allowed. The seed is
+/// reached from itself. `fill` needs to both check if the point should be
+/// filled (bool), and fill it in.
+pub fn flood_fill_from_i32<F: FnMut([i32; 3], [i32; 3]) -> bool>(seed: [i32; 3], mut fill: F) {
+    let mut queue = vec![(seed, seed)];
 
-    while let Some(p) = queue.pop() {
-        if fill(p) {
-            queue.push([p[0] + 1, p[1], p[2]]);
-            queue.push([p[0] - 1, p[1], p[2]]);
-            queue.push([p[0], p[1] + 1, p[2]]);
-            queue.push([p[0], p[1] - 1, p[2]]);
-            queue.push([p[0], p[1], p[2] + 1]);
-            queue.push([p[0], p[1], p[2] - 1]);
+    while let Some((from, p)) = queue.pop() {
+        if fill(from, p) {
+            queue.push((p, [p[0] + 1, p[1], p[2]]));
+            queue.push((p, [p[0] - 1, p[1], p[2]]));
+            queue.push((p, [p[0], p[1] + 1, p[2]]));
+            queue.push((p, [p[0], p[1] - 1, p[2]]));
+            queue.push((p, [p[0], p[1], p[2] + 1]));
+            queue.push((p, [p[0], p[1], p[2] - 1]));
         }
     }
 }
 
-#[allow(dead_code)]
 pub fn flood_fill<T: Copy, F: FnMut(T) -> bool, N: FnMut(T) -> Vec<T>>(
     seed: T,
     mut fill: F,
@@ -1,17 +1,3 @@
-#[allow(dead_code)]
-pub fn min_3(a: i32, b: i32, c: i32) -> i32 {
-    if a <= b && a <= c {
-        return a;
-    }
-
-    if b <= a && b <= c {
-        return b;
-    }
-
-    c
-}
-
-#[allow(dead_code)]
 pub fn max_3(a: i32, b: i32, c: i32) -> i32 {
     if a >= b && a >= c {
         return a;
@@ -24,7 +10,6 @@ pub fn max_3(a: i32, b: i32, c: i32) -> i32 {
     c
 }
 
-#[allow(dead_code)]
 pub fn sig_num(v: f32) -> i32 {
     if v > 0. {
         1
@@ -35,7 +20,6 @@ pub fn sig_num(v: f32) -> i32 {
     }
 }
 
-#[allow(dead_code)]
 pub fn min_max(a: u32, b: u32) -> [u32; 2] {
     if a > b {
         [b, a]
@@ -44,7 +28,6 @@ pub fn min_max(a: u32, b: u32) -> [u32; 2] {
     }
 }
 
-#[allow(dead_code)]
 pub fn min_max_3<T>(a: T, b: T, c: T) -> [T; 3]
 where
     T: std::cmp::PartialOrd,
@@ -1,25 +1,204 @@
 use fastnoise_lite::*;
 
+/// How the octaves of a `FractalNoise` are combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NoiseShape {
+    /// Plain rolling hills
+    #[default]
+    Fbm,
+    /// Sharp crests where the noise crosses zero, for mountain ridges
+    Ridged,
+    /// Rounded bumps with creases between them, for puffy shapes
+    Billow,
+}
+
+impl NoiseShape {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "fbm" => Some(Self::Fbm),
+            "ridged" => Some(Self::Ridged),
+            "billow" => Some(Self::Billow),
+            _ => None,
+        }
+    }
+}
+
 // https://auburn.github.io/FastNoiseLite/
 pub struct FractalNoise {
     nz: FastNoiseLite,
+    shape: NoiseShape,
 }
 
 impl FractalNoise {
     pub fn new(seed: i32, frequency: f32, octaves: i32) -> Self {
+        Self::with_shape(seed, frequency, octaves, NoiseShape::Fbm)
+    }
+
+    pub fn with_shape(seed: i32, frequency: f32, octaves: i32, shape: NoiseShape) -> Self {
+        let fractal_type = match shape {
+            NoiseShape::Fbm | NoiseShape::Billow => FractalType::FBm,
+            NoiseShape::Ridged => FractalType::Ridged,
+        };
+
         let mut nz = FastNoiseLite::with_seed(seed);
         nz.set_frequency(frequency.into());
         nz.set_fractal_octaves(octaves.into());
         nz.set_noise_type(NoiseType::OpenSimplex2.into());
-        nz.set_fractal_type(FractalType::FBm.into());
-        Self { nz }
+        nz.set_fractal_type(fractal_type.into());
+        Self { nz, shape }
+    }
+
+    /// Maps raw noise from -1..1 to 0..1. FastNoiseLite has no billow
+    /// fractal, so it is folded from fbm here.
+    fn normalize(&self, value: f32) -> f32 {
+        match self.shape {
+            NoiseShape::Billow => value.abs(),
+            NoiseShape::Fbm | NoiseShape::Ridged => (value + 1.) / 2.,
+        }
     }
 
     pub fn get_3d(&mut self, x: f32, y: f32, z: f32) -> f32 {
-        (self.nz.get_noise_3d(x, y, z) + 1.) / 2.
+        let value = self.nz.get_noise_3d(x, y, z);
+        self.normalize(value)
     }
 
     pub fn get_2d(&mut self, x: f32, y: f32) -> f32 {
-        (self.nz.get_noise_2d(x, y) + 1.) / 2.
+        let value = self.nz.get_noise_2d(x, y);
+        self.normalize(value)
+    }
+
+    /// Pushes `(x, z)` around by two samples of this noise, up to
+    /// `warp_strength` blocks along each axis. Sampling another noise at the
+    /// result (domain warping) bends its features into more organic shapes.
+    /// A strength of 0 returns the coordinates unchanged.
+    pub fn warp(&mut self, x: f32, z: f32, warp_strength: f32) -> (f32, f32) {
+        let dx = self.nz.get_noise_2d(x, z);
+        let dz = self.nz.get_noise_2d(x + 5.2, z + 1.3);
+
+        (x + dx * warp_strength, z + dz * warp_strength)
+    }
+
+    /// Like `warp`, but pushes all three axes, for warping 3d noise.
+    pub fn warp_3d(&mut self, x: f32, y: f32, z: f32, warp_strength: f32) -> (f32, f32, f32) {
+        let dx = self.nz.get_noise_3d(x, y, z);
+        let dy = self.nz.get_noise_3d(x + 5.2, y + 1.3, z + 7.1);
+        let dz = self.nz.get_noise_3d(x + 2.8, y + 9.4, z + 3.6);
+
+        (
+            x + dx * warp_strength,
+            y + dy * warp_strength,
+            z + dz * warp_strength,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_warp_keeps_coordinates() {
+        let mut noise = FractalNoise::new(586, 0.01, 4);
+
+        for (x, z) in [(0., 0.), (12.5, -3.25), (-400., 977.), (1e5, 2e5)] {
+            assert_eq!(noise.warp(x, z, 0.), (x, z));
+            assert_eq!(noise.warp_3d(x, 7., z, 0.), (x, 7., z));
+        }
+    }
+
+    #[test]
+    fn warp_stays_within_strength() {
+        let mut noise = FractalNoise::new(586, 0.01, 4);
+        let mut moved = false;
+
+        for i in 0..100 {
+            let (x, z) = (i as f32 * 13.7, i as f32 * -4.1);
+            let (wx, wz) = noise.warp(x, z, 8.);
+
+            assert!((wx - x).abs() <= 8. && (wz - z).abs() <= 8.);
+            moved |= wx != x || wz != z;
+        }
+
+        assert!(moved);
+    }
+
+    /// Seed 593 sampled every 17 blocks along x and 23 along z. Any change
+    /// to how the shapes are made, or to the noise library underneath, moves
+    /// every generated world, so it should show up here first.
+    const GOLDEN_RIDGED: [f32; 16] = [
+        1.0, 0.45686582, 0.6175271, 0.23599917, 0.3695041, 0.38245958, 0.607681, 0.7377155,
+        0.6984314, 0.73136115, 0.482202, 0.45230407, 0.39800832, 0.6254645, 0.65207595, 0.3688221,
+    ];
+    const GOLDEN_BILLOW: [f32; 16] = [
+        0.0,
+        0.45860872,
+        0.303431,
+        0.71806717,
+        0.39858153,
+        0.31808776,
+        0.23598385,
+        0.0058673136,
+        0.10926846,
+        0.15309888,
+        0.3047532,
+        0.4772661,
+        0.18835467,
+        0.33650544,
+        0.28903455,
+        0.6311779,
+    ];
+    /// Fbm of seed 593 sampled where a seed 594 warp of strength 8 moves
+    /// the same grid
+    const GOLDEN_WARPED: [f32; 16] = [
+        0.528987, 0.37597048, 0.45556968, 0.7184216, 0.3087586, 0.57683426, 0.38903007, 0.47985387,
+        0.52471155, 0.5781248, 0.35659376, 0.19915468, 0.7430351, 0.6214105, 0.49230066,
+        0.37586567,
+    ];
+
+    fn golden_grid() -> impl Iterator<Item = (f32, f32)> {
+        (0..4).flat_map(|i| (0..4).map(move |j| (i as f32 * 17., j as f32 * 23.)))
+    }
+
+    fn assert_golden(values: Vec<f32>, golden: [f32; 16]) {
+        for (value, expected) in values.iter().zip(golden) {
+            assert!(
+                (value - expected).abs() < 1e-5,
+                "{:?} != {:?}",
+                values,
+                golden
+            );
+        }
+    }
+
+    #[test]
+    fn shapes_match_golden_values() {
+        let mut ridged = FractalNoise::with_shape(593, 0.02, 4, NoiseShape::Ridged);
+        let mut billow = FractalNoise::with_shape(593, 0.02, 4, NoiseShape::Billow);
+        let mut warp = FractalNoise::new(594, 0.01, 2);
+        let mut fbm = FractalNoise::new(593, 0.02, 4);
+
+        assert_golden(
+            golden_grid().map(|(x, z)| ridged.get_2d(x, z)).collect(),
+            GOLDEN_RIDGED,
+        );
+        assert_golden(
+            golden_grid().map(|(x, z)| billow.get_2d(x, z)).collect(),
+            GOLDEN_BILLOW,
+        );
+        assert_golden(
+            golden_grid()
+                .map(|(x, z)| {
+                    let (wx, wz) = warp.warp(x, z, 8.);
+                    fbm.get_2d(wx, wz)
+                })
+                .collect(),
+            GOLDEN_WARPED,
+        );
+
+        // billow is fbm folded over at its middle
+        for (x, z) in golden_grid() {
+            let folded = (fbm.get_2d(x, z) * 2. - 1.).abs();
+            assert!((billow.get_2d(x, z) - folded).abs() < 1e-5);
+        }
     }
 }
@@ -14,7 +14,7 @@ impl<T, A: Ord + PartialEq + Eq + PartialOrd> PartialEq for PriorityQueueItem<T,
 }
 impl<T, A: Ord + PartialEq + Eq + PartialOrd> PartialOrd for PriorityQueueItem<T, A> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cost.cmp(&other.cost))
+        Some(self.cmp(other))
     }
 }
 impl<T, A: Ord + PartialEq + Eq + PartialOrd> Ord for PriorityQueueItem<T, A> {
@@ -27,7 +27,6 @@ pub struct PriorityQueue<T, A: Ord + PartialEq + Eq + PartialOrd> {
     heap: BinaryHeap<PriorityQueueItem<T, A>>,
 }
 
-#[allow(dead_code)]
 impl<T, A: Ord + PartialEq + Eq + PartialOrd> PriorityQueue<T, A> {
     pub fn new() -> Self {
         Self {
@@ -35,10 +34,6 @@ impl<T, A: Ord + PartialEq + Eq + PartialOrd> PriorityQueue<T, A> {
         }
     }
 
-    pub fn len(&self) -> usize {
-        self.heap.len()
-    }
-
     pub fn is_empty(&self) -> bool {
         self.heap.is_empty()
     }
@@ -54,12 +49,4 @@ impl<T, A: Ord + PartialEq + Eq + PartialOrd> PriorityQueue<T, A> {
 
         None
     }
-
-    pub fn peek(&self) -> Option<&T> {
-        if let Some(item) = self.heap.peek() {
-            return Some(&item.item);
-        }
-
-        None
-    }
 }
@@ -4,7 +4,7 @@ use bevy::{
     window::PrimaryWindow,
 };
 
-use crate::TerrainSlice;
+use crate::{Terrain, TerrainSlice};
 
 #[derive(Component)]
 pub struct MainCamera {
@@ -127,6 +127,29 @@ pub fn update_camera(
     ev_motion.clear();
 }
 
+/// Keeps the point the camera orbits inside the world, so panning can't
+/// lose the terrain. The camera itself still sits outside, at `radius`.
+pub fn clamp_camera_to_world(
+    terrain: Res<Terrain>,
+    mut query: Query<(&mut MainCamera, &mut Transform)>,
+) {
+    let (min, max) = terrain.world_aabb();
+
+    for (mut pan_orbit, mut transform) in query.iter_mut() {
+        let focus = pan_orbit.focus.clamp(min, max);
+
+        if focus == pan_orbit.focus {
+            continue;
+        }
+
+        pan_orbit.focus = focus;
+
+        let rot_matrix = Mat3::from_quat(transform.rotation);
+        transform.translation =
+            pan_orbit.focus + rot_matrix.mul_vec3(Vec3::new(0.0, 0.0, pan_orbit.radius));
+    }
+}
+
 fn get_primary_window_size(window: &Window) -> Vec2 {
     Vec2::new(window.width(), window.height())
 }
@@ -12,13 +12,17 @@ use crate::{ui::Ui, Block, Terrain, TerrainSlice};
 
 use super::MainCamera;
 
+/// How far from the camera the cursor picks blocks
+const CURSOR_MAX_DIST: f32 = 256.;
+
+/// The block under the mouse cursor, updated every frame by `raycast`
 #[derive(Resource)]
-pub struct Raycast {
+pub struct CursorHit {
     /// True if a block is hit
     pub is_hit: bool,
-    /// The coordinates directly under the raycast
+    /// The coordinates directly under the cursor
     pub hit_pos: [u32; 3],
-    /// The block type directly under the raycast
+    /// The block type directly under the cursor
     pub hit_block: Block,
     /// True if an adjacent block is hit
     pub is_adj_hit: bool,
@@ -32,11 +36,11 @@ pub fn raycast(
     terrain_slice: Res<TerrainSlice>,
     windows: Query<&Window, With<PrimaryWindow>>,
     ui: Res<Ui>,
-    mut raycast: ResMut<Raycast>,
+    mut cursor_hit: ResMut<CursorHit>,
 ) {
     if ui.pointer_captured {
-        raycast.is_adj_hit = false;
-        raycast.is_hit = false;
+        cursor_hit.is_adj_hit = false;
+        cursor_hit.is_hit = false;
         return;
     }
 
@@ -44,52 +48,44 @@ pub fn raycast(
 
     for window in windows.iter() {
         let Some(cursor_pos) = window.cursor_position() else {
-            raycast.is_adj_hit = false;
-            raycast.is_hit = false;
+            cursor_hit.is_adj_hit = false;
+            cursor_hit.is_hit = false;
             return;
         };
 
         let Some(ray3d) = camera.viewport_to_world(transform, cursor_pos) else {
-            raycast.is_adj_hit = false;
-            raycast.is_hit = false;
+            cursor_hit.is_adj_hit = false;
+            cursor_hit.is_hit = false;
             return;
         };
 
-        let slice_y = terrain_slice.get_value();
-        let radius = 256;
-
-        let ray = terrain.raycast(
-            ray3d.origin.x,
-            ray3d.origin.y,
-            ray3d.origin.z,
-            ray3d.direction.x,
-            ray3d.direction.y,
-            ray3d.direction.z,
-            slice_y,
-            radius,
-        );
-
-        if !ray.is_hit {
-            raycast.is_adj_hit = false;
-            raycast.is_hit = false;
+        let Some(hit) = terrain.raycast(
+            ray3d.origin,
+            *ray3d.direction,
+            CURSOR_MAX_DIST,
+            terrain_slice.get_value(),
+        ) else {
+            cursor_hit.is_adj_hit = false;
+            cursor_hit.is_hit = false;
             return;
-        }
+        };
 
-        raycast.is_hit = true;
-        raycast.hit_pos = [ray.x, ray.y, ray.z];
-        raycast.hit_block = ray.block;
+        let [x, y, z] = hit.block;
+        cursor_hit.is_hit = true;
+        cursor_hit.hit_pos = hit.block;
+        cursor_hit.hit_block = terrain.get_block(x, y, z);
 
-        let offset = ray.face.offset();
-        let new_x = ray.x as i32 + offset[0];
-        let new_y = ray.y as i32 + offset[1];
-        let new_z = ray.z as i32 + offset[2];
+        let offset = hit.face.offset();
+        let new_x = x as i32 + offset[0];
+        let new_y = y as i32 + offset[1];
+        let new_z = z as i32 + offset[2];
 
         if terrain.is_oob(new_x, new_y, new_z) {
-            raycast.is_adj_hit = false;
+            cursor_hit.is_adj_hit = false;
             return;
         }
 
-        raycast.is_adj_hit = true;
-        raycast.adj_pos = [new_x as u32, new_y as u32, new_z as u32];
+        cursor_hit.is_adj_hit = true;
+        cursor_hit.adj_pos = [new_x as u32, new_y as u32, new_z as u32];
     }
 }
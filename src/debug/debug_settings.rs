@@ -3,4 +3,5 @@ use bevy::ecs::system::Resource;
 #[derive(Resource, Default)]
 pub struct DebugSettings {
     pub path: bool,
+    pub light_test_scene: bool,
 }
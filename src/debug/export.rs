@@ -0,0 +1,259 @@
+use std::{fmt::Write as _, fs, io, path::Path};
+
+use bevy::{
+    ecs::system::Res,
+    input::{keyboard::KeyCode, ButtonInput},
+};
+
+use crate::{build_chunk_mesh, ChunkMeshLayers, Terrain, TerrainSlice};
+
+/// Tiles per row in textures/comfy.png
+const ATLAS_TILES: u32 = 8;
+const EXPORT_PATH: &str = "world_export.obj";
+
+/// Writes the terrain mesh of every chunk into a single OBJ file. When `slice`
+/// is given, faces that the terrain shader would discard are left out.
+pub fn export_world_mesh(terrain: &Terrain, slice: Option<u32>, path: &Path) -> io::Result<()> {
+    let mut obj = String::new();
+    let mut layers = ChunkMeshLayers::default();
+    let mut vertex_count: u32 = 0;
+
+    for chunk_idx in 0..terrain.chunk_count {
+        layers.clear();
+        build_chunk_mesh(terrain, chunk_idx, &mut layers);
+
+        let [ox, oy, oz] = terrain.get_chunk_offset(chunk_idx);
+        let offset = [ox as f32, oy as f32, oz as f32];
+
+        // obj has no blending, transparent faces are written like the rest
+        for data in [&layers.opaque, &layers.transparent] {
+            for quad in 0..data.face_count() as usize {
+                let range = quad * 4..quad * 4 + 4;
+                let positions = &data.positions[range.clone()];
+                let packed = data.packed[quad * 4];
+                let face = packed >> 8 & 7;
+
+                let world = positions
+                    .iter()
+                    .map(|p| [p[0] + offset[0], p[1] + offset[1], p[2] + offset[2]])
+                    .collect::<Vec<_>>();
+
+                if let Some(slice_y) = slice {
+                    if is_sliced_out(&world, face, slice_y) {
+                        continue;
+                    }
+                }
+
+                let uvs = face_uvs(&world, face, packed & 255);
+
+                for (p, n) in world.iter().zip(&data.normals[range]) {
+                    let _ = writeln!(obj, "v {} {} {}", p[0], p[1], p[2]);
+                    let _ = writeln!(obj, "vn {} {} {}", n[0], n[1], n[2]);
+                }
+
+                for uv in uvs {
+                    let _ = writeln!(obj, "vt {} {}", uv[0], uv[1]);
+                }
+
+                // reuse the mesher's triangles, they flip with the ambient occlusion
+                let base = quad as u32 * 4;
+                for tri in data.indicies[quad * 6..quad * 6 + 6].chunks(3) {
+                    let _ = write!(obj, "f");
+                    for corner in tri {
+                        let i = vertex_count + corner - base + 1;
+                        let _ = write!(obj, " {}/{}/{}", i, i, i);
+                    }
+                    let _ = writeln!(obj);
+                }
+
+                vertex_count += 4;
+            }
+        }
+    }
+
+    fs::write(path, obj)
+}
+
+/// Mirrors the discard in terrain.wgsl
+fn is_sliced_out(positions: &[[f32; 3]], face: u32, slice_y: u32) -> bool {
+    let top = positions.iter().map(|p| p[1]).fold(f32::MIN, f32::max);
+    let slice_y = slice_y as f32;
+
+    top > slice_y || (face == 3 && top >= slice_y)
+}
+
+fn face_uvs(positions: &[[f32; 3]], face: u32, tile: u32) -> Vec<[f32; 2]> {
+    // the two axes spanning the face, matching the shader's uv choice
+    let [u_axis, v_axis] = match face {
+        0 | 1 => [2, 1],
+        2 | 3 => [0, 2],
+        _ => [0, 1],
+    };
+
+    let min_u = positions.iter().map(|p| p[u_axis]).fold(f32::MAX, f32::min);
+    let min_v = positions.iter().map(|p| p[v_axis]).fold(f32::MAX, f32::min);
+    let ox = (tile % ATLAS_TILES) as f32;
+    let oy = (tile / ATLAS_TILES) as f32;
+    let count = ATLAS_TILES as f32;
+
+    positions
+        .iter()
+        .map(|p| {
+            [
+                (ox + p[u_axis] - min_u) / count,
+                // obj uvs start at the bottom of the image
+                1. - (oy + p[v_axis] - min_v) / count,
+            ]
+        })
+        .collect()
+}
+
+pub fn export_world_mesh_key(
+    input_keys: Res<ButtonInput<KeyCode>>,
+    terrain: Res<Terrain>,
+    terrain_slice: Res<TerrainSlice>,
+) {
+    if !input_keys.just_pressed(KeyCode::F4) {
+        return;
+    }
+
+    let path = Path::new(EXPORT_PATH);
+
+    match export_world_mesh(&terrain, Some(terrain_slice.get_value()), path) {
+        Ok(()) => println!("exported world mesh to {}", path.display()),
+        Err(err) => println!("failed to export world mesh: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BlockType;
+
+    #[derive(Default)]
+    struct Obj {
+        positions: Vec<[f32; 3]>,
+        normals: Vec<[f32; 3]>,
+        uvs: Vec<[f32; 2]>,
+        /// 1-based `v/vt/vn` indices of each triangle corner
+        faces: Vec<[[usize; 3]; 3]>,
+    }
+
+    fn parse_obj(text: &str) -> Obj {
+        let mut obj = Obj::default();
+
+        for line in text.lines() {
+            let mut parts = line.split_whitespace();
+            let kind = parts.next().unwrap();
+            let values = parts.collect::<Vec<_>>();
+            let floats = || values.iter().map(|v| v.parse::<f32>().unwrap());
+
+            match kind {
+                "v" => obj
+                    .positions
+                    .push(floats().collect::<Vec<_>>().try_into().unwrap()),
+                "vn" => obj
+                    .normals
+                    .push(floats().collect::<Vec<_>>().try_into().unwrap()),
+                "vt" => obj
+                    .uvs
+                    .push(floats().collect::<Vec<_>>().try_into().unwrap()),
+                "f" => obj.faces.push(
+                    values
+                        .iter()
+                        .map(|corner| {
+                            corner
+                                .split('/')
+                                .map(|i| i.parse().unwrap())
+                                .collect::<Vec<_>>()
+                                .try_into()
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                        .try_into()
+                        .unwrap(),
+                ),
+                _ => panic!("unexpected obj line {:?}", line),
+            }
+        }
+
+        obj
+    }
+
+    fn export(terrain: &Terrain, slice: Option<u32>, name: &str) -> Obj {
+        let path = std::env::temp_dir().join(format!("boris_{}_{}.obj", name, std::process::id()));
+        export_world_mesh(terrain, slice, &path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        parse_obj(&text)
+    }
+
+    #[test]
+    fn exported_obj_parses_back() {
+        // a pair in the first chunk and a lone block in the second
+        let mut terrain = Terrain::new(2, 1, 1, 4).unwrap();
+        terrain.set_block(1, 1, 1, BlockType::STONE);
+        terrain.set_block(2, 1, 1, BlockType::STONE);
+        terrain.set_block(5, 2, 1, BlockType::STONE);
+
+        let obj = export(&terrain, None, "parses_back");
+
+        // 10 + 6 quads, 4 corners and 2 triangles each
+        assert_eq!(obj.positions.len(), 64);
+        assert_eq!(obj.normals.len(), 64);
+        assert_eq!(obj.uvs.len(), 64);
+        assert_eq!(obj.faces.len(), 32);
+
+        for face in &obj.faces {
+            let [a, b, c] = face.map(|[v, vt, vn]| {
+                assert!(v == vt && v == vn);
+                assert!((1..=obj.positions.len()).contains(&v));
+                obj.positions[v - 1]
+            });
+            let normal = obj.normals[face[0][0] - 1];
+
+            // counter-clockwise around the written normal
+            let edge = |p: [f32; 3], q: [f32; 3]| [q[0] - p[0], q[1] - p[1], q[2] - p[2]];
+            let [u, v] = [edge(a, b), edge(a, c)];
+            let cross = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            let dot = cross[0] * normal[0] + cross[1] * normal[1] + cross[2] * normal[2];
+            assert!(dot > 0., "triangle {:?} faces away from {:?}", face, normal);
+        }
+
+        // the lone block lands at its world position, past the chunk offset
+        let lone = obj
+            .positions
+            .iter()
+            .filter(|p| p[0] >= 5.)
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(lone.len(), 24);
+        assert!(lone
+            .iter()
+            .all(|p| (5.0..=6.).contains(&p[0]) && (2.0..=3.).contains(&p[1])));
+
+        assert!(obj
+            .uvs
+            .iter()
+            .all(|uv| (0.0..=1.).contains(&uv[0]) && (0.0..=1.).contains(&uv[1])));
+    }
+
+    #[test]
+    fn exported_obj_leaves_out_the_sliced_faces() {
+        let mut terrain = Terrain::new(2, 1, 1, 4).unwrap();
+        terrain.set_block(1, 1, 1, BlockType::STONE);
+        terrain.set_block(5, 2, 1, BlockType::STONE);
+
+        // the slice at 2 hides the block above it, and keeps the one below
+        let obj = export(&terrain, Some(2), "sliced");
+
+        assert_eq!(obj.positions.len(), 24);
+        assert_eq!(obj.faces.len(), 12);
+        assert!(obj.positions.iter().all(|p| p[0] <= 2. && p[1] <= 2.));
+    }
+}
@@ -0,0 +1,56 @@
+use bevy::{
+    ecs::{
+        event::EventWriter,
+        system::{Local, Res, ResMut},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+};
+
+use crate::{BlockChangedEvent, BlockType, Terrain};
+
+use super::debug_settings::DebugSettings;
+
+/// Carve a sealed stone room with a single lamp in one corner. The room
+/// should render dark, except for the faces near the lamp. F8 turns the
+/// scene on, it is carved once and stays when turned off again.
+pub fn light_test_scene(
+    input_keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<DebugSettings>,
+    mut terrain: ResMut<Terrain>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+    mut is_carved: Local<bool>,
+) {
+    if input_keys.just_pressed(KeyCode::F8) {
+        settings.light_test_scene = !settings.light_test_scene;
+        println!("light test scene {}", settings.light_test_scene);
+    }
+
+    if !settings.light_test_scene || *is_carved {
+        return;
+    }
+
+    *is_carved = true;
+
+    let size = 9;
+    let height = 5;
+    let min_x = terrain.world_size_x() / 2 - size / 2;
+    let min_z = terrain.world_size_z() / 2 - size / 2;
+    let min_y = 8;
+    let max_x = min_x + size - 1;
+    let max_y = min_y + height - 1;
+    let max_z = min_z + size - 1;
+
+    let mut changes = terrain.fill_region(
+        [min_x, min_y, min_z],
+        [max_x, max_y, max_z],
+        BlockType::STONE,
+    );
+    changes.extend(terrain.fill_region(
+        [min_x + 1, min_y + 1, min_z + 1],
+        [max_x - 1, max_y - 1, max_z - 1],
+        BlockType::EMPTY,
+    ));
+    changes.push(terrain.set_block(min_x + 1, min_y + 1, min_z + 1, BlockType::LAMP));
+
+    ev_block_changed.send_batch(changes.into_iter().map(BlockChangedEvent::from));
+}
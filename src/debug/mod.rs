@@ -1,3 +1,5 @@
 pub mod debug_settings;
+pub mod export;
 pub mod fps;
+pub mod light_test;
 pub mod pathfinding;
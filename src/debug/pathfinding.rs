@@ -1,13 +1,16 @@
 use std::cmp::Ordering;
 
 use bevy::{
-    ecs::system::{Query, Res},
+    ecs::{
+        change_detection::DetectChanges,
+        system::{Query, Res},
+    },
     gizmos::gizmos::Gizmos,
     math::Vec3,
     render::color::Color,
 };
 
-use crate::colonists::Path;
+use crate::colonists::{NavigationGraph, PartitionDebug, Path, PathCache, PatrolRoute};
 
 use super::debug_settings::DebugSettings;
 
@@ -58,3 +61,87 @@ pub fn path_debug(settings: Res<DebugSettings>, mut gizmos: Gizmos, pathers: Que
         }
     }
 }
+
+/// Prints how well the path cache is doing whenever path debugging is
+/// switched on
+pub fn path_cache_stats(settings: Res<DebugSettings>, cache: Res<PathCache>) {
+    if !settings.is_changed() || !settings.path {
+        return;
+    }
+
+    let lookups = (cache.hits + cache.misses).max(1);
+
+    println!(
+        "path cache: {} hits, {} misses ({:.0}% hit)",
+        cache.hits,
+        cache.misses,
+        cache.hits as f32 * 100. / lookups as f32
+    );
+}
+
+pub fn path_follow_partition_debug(
+    debug: Res<PartitionDebug>,
+    graph: Res<NavigationGraph>,
+    mut gizmos: Gizmos,
+    pathers: Query<&Path>,
+) {
+    if !debug.show_path {
+        return;
+    }
+
+    let mid = Vec3::new(0.5, 0.5, 0.5);
+
+    for path in pathers.iter() {
+        for i in 1..path.partition_path.len() {
+            let Some(current) = graph.get_center(&path.partition_path[i - 1]) else {
+                continue;
+            };
+            let Some(next) = graph.get_center(&path.partition_path[i]) else {
+                continue;
+            };
+
+            let color = if i <= path.current_partition_idx {
+                Color::LIME_GREEN
+            } else {
+                Color::YELLOW
+            };
+
+            gizmos.line(
+                Vec3::new(current[0] as f32, current[1] as f32, current[2] as f32) + mid,
+                Vec3::new(next[0] as f32, next[1] as f32, next[2] as f32) + mid,
+                color,
+            );
+        }
+    }
+}
+
+pub fn patrol_route_debug(
+    settings: Res<DebugSettings>,
+    mut gizmos: Gizmos,
+    routes: Query<&PatrolRoute>,
+) {
+    if !settings.path {
+        return;
+    }
+
+    let mid = Vec3::new(0.5, 0.5, 0.5);
+
+    for route in routes.iter() {
+        let count = route.waypoints.len();
+
+        if count < 2 {
+            continue;
+        }
+
+        for i in 0..count {
+            let current = route.waypoints[i];
+            let next = route.waypoints[(i + 1) % count];
+
+            gizmos.line(
+                Vec3::new(current[0] as f32, current[1] as f32, current[2] as f32) + mid,
+                Vec3::new(next[0] as f32, next[1] as f32, next[2] as f32) + mid,
+                Color::GREEN,
+            );
+        }
+    }
+}
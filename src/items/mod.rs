@@ -1,5 +1,15 @@
+mod coal;
+mod food;
+mod ore;
 mod pickaxe;
 mod stone;
+mod torch;
+mod wood;
 
+pub use coal::*;
+pub use food::*;
+pub use ore::*;
 pub use pickaxe::*;
 pub use stone::*;
+pub use torch::*;
+pub use wood::*;
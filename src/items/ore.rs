@@ -0,0 +1,87 @@
+use bevy::{
+    asset::{AssetServer, Assets, Handle},
+    ecs::{
+        event::{Event, EventReader},
+        system::{Commands, Res, ResMut},
+    },
+    pbr::{MaterialMeshBundle, StandardMaterial},
+    prelude::default,
+    render::{color::Color, mesh::Mesh},
+    transform::components::Transform,
+};
+
+use crate::{
+    colonists::{Faller, InPartition, Item, ItemTag, NavigationGraph},
+    Terrain,
+};
+
+#[derive(Event)]
+pub struct SpawnOreEvent {
+    pub pos: [u32; 3],
+    /// `ItemTag::IronOre` or `ItemTag::GoldOre`
+    pub ore: ItemTag,
+}
+
+pub fn on_spawn_ore(
+    mut cmd: Commands,
+    terrain: Res<Terrain>,
+    mut graph: ResMut<NavigationGraph>,
+    mut ev_spawn_ore: EventReader<SpawnOreEvent>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    let mesh: Handle<Mesh> = asset_server.load("meshes/sphere.obj");
+    let iron_material = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.7, 0.55, 0.5),
+        unlit: true,
+        ..default()
+    });
+    let gold_material = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.95, 0.8, 0.2),
+        unlit: true,
+        ..default()
+    });
+
+    for ev in ev_spawn_ore.read() {
+        let material = match ev.ore {
+            ItemTag::IronOre => iron_material.clone(),
+            ItemTag::GoldOre => gold_material.clone(),
+            _ => {
+                println!("{} is not an ore!", ev.ore);
+                continue;
+            }
+        };
+
+        let entity = cmd
+            .spawn((
+                MaterialMeshBundle {
+                    mesh: mesh.clone(),
+                    material,
+                    transform: Transform::from_xyz(
+                        ev.pos[0] as f32 + 0.5,
+                        ev.pos[1] as f32,
+                        ev.pos[2] as f32 + 0.5,
+                    ),
+                    ..default()
+                },
+                Item {
+                    tags: vec![ev.ore],
+                    reserved: None,
+                },
+                Faller,
+            ))
+            .id();
+
+        let Some(partition_id) = terrain.get_partition_id_u32(ev.pos[0], ev.pos[1], ev.pos[2])
+        else {
+            continue;
+        };
+
+        if !graph.add_item(&partition_id, entity, &[ev.ore]) {
+            println!("Missing partition trying to insert item! {}", partition_id);
+            continue;
+        }
+
+        cmd.entity(entity).insert(InPartition { partition_id });
+    }
+}
@@ -62,12 +62,11 @@ pub fn on_spawn_pickaxe(
             continue;
         };
 
-        let Some(partition) = graph.get_partition_mut(&partition_id) else {
+        if !graph.add_item(&partition_id, entity, &[ItemTag::Pickaxe]) {
             println!("Missing partition trying to insert item! {}", partition_id);
             continue;
-        };
+        }
 
-        partition.items.insert(entity);
         cmd.entity(entity).insert(InPartition { partition_id });
     }
 }
@@ -1,9 +1,8 @@
 use bevy::{
-    animation::AnimationClip,
     asset::{AssetServer, Assets, Handle},
     ecs::{
         event::{Event, EventReader},
-        system::{Commands, Res, ResMut, Resource},
+        system::{Commands, Res, ResMut},
     },
     pbr::{MaterialMeshBundle, StandardMaterial},
     prelude::default,
@@ -16,9 +15,6 @@ use crate::{
     Terrain,
 };
 
-#[derive(Resource)]
-pub struct ColonistAnimations(pub Vec<Handle<AnimationClip>>);
-
 #[derive(Event)]
 pub struct SpawnStoneEvent {
     pub pos: [u32; 3],
@@ -65,12 +61,11 @@ pub fn on_spawn_stone(
             continue;
         };
 
-        let Some(partition) = graph.get_partition_mut(&partition_id) else {
+        if !graph.add_item(&partition_id, entity, &[ItemTag::Stone]) {
             println!("Missing partition trying to insert item! {}", partition_id);
             continue;
-        };
+        }
 
-        partition.items.insert(entity);
         cmd.entity(entity).insert(InPartition { partition_id });
     }
 }
@@ -1,27 +1,59 @@
+use bevy::pbr::wireframe::WireframePlugin;
 use bevy::prelude::*;
-use bevy::{gltf::Gltf, pbr::wireframe::WireframePlugin};
-use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_obj::ObjPlugin;
 use colonists::{
-    apply_falling, behavior_pick_system, behavior_system, block_move_system, destroy_items,
-    fatigue_system, job_accessibility, job_despawn_cancelled, job_despawn_complete,
-    on_spawn_colonist, on_spawn_job_build, on_spawn_job_mine, partition, partition_debug,
-    score_build, score_mine, score_wander, task_assign_job, task_build_block, task_check_has_item,
-    task_debug, task_find_bed, task_find_nearest_item, task_get_job_location, task_idle,
+    advance_selector_tasks, advance_sequence_tasks, apply_environmental_damage, apply_falling,
+    behavior_pick_system, behavior_system, block_move_system, check_blueprint_materials,
+    check_job_deadlines, colonist_death, destroy_items, detect_rooms, draw_thought_bubbles,
+    fatigue_system, flee_hazards, flush_partition_updates, hostile_death, interrupt_behaviors,
+    invalidate_path_cache, job_accessibility, job_despawn_cancelled, job_despawn_complete,
+    light_debug, link_colonist_animators, log_expired_jobs, log_world_stats, mine_area_gizmos,
+    on_designate_mine, on_designate_stockpile, on_spawn_colonist, on_spawn_hostile,
+    on_spawn_job_build, on_spawn_job_farm, on_spawn_job_haul, on_spawn_job_mine,
+    on_undesignate_stockpile, partition, partition_debug, partition_orphaned_items,
+    play_animation_state, prune_stockpiles, reset_task_scheduler, restore_inventories,
+    restore_relationships, scan_stockpiles, score_build, score_chop, score_cook, score_eat,
+    score_farm, score_guard, score_haul, score_light, score_mine, score_patrol, score_supply,
+    score_tantrum, score_wander, sync_job_queue, task_assign_job, task_build_block,
+    task_check_has_item, task_chop, task_clear_rubble, task_craft, task_debug, task_deliver_item,
+    task_eat, task_farm, task_find_bed, task_find_haul_item, task_find_nearest_campfire,
+    task_find_nearest_item, task_get_job_location, task_guard, task_haul, task_idle,
     task_is_target_empty, task_job_cancel, task_job_complete, task_job_unassign, task_mine_block,
-    task_move_to, task_pick_random_spot, task_pick_up_item, task_sleep, update_item_partition,
-    DestroyItemEvent, MovedEvent, NavigationGraph, PartitionDebug, PartitionEvent, ScorerPlugin,
-    SpawnColonistEvent, SpawnJobBuildEvent, SpawnJobMineEvent,
+    task_move_to, task_patrol, task_pick_random_spot, task_pick_up_item, task_place_torch,
+    task_release_item, task_remove_rot, task_set_move_goals, task_sleep, task_tantrum,
+    tick_animation_state, tick_hunger, tick_mine_areas, tick_mood, tick_relationships,
+    tick_task_estimates, tick_task_timeouts, toggle_light_debug, track_stockpile_occupancy,
+    update_carry_capacity, update_item_partition, update_thought_bubbles, validate_partitions_key,
+    ColonistAnimationClips, ColonistDiedEvent, ColonistStarvingEvent, DamagedByBlockEvent,
+    DeathCount, DesignateMineEvent, DesignateStockpileEvent, DestroyItemEvent, EnvironmentalDamage,
+    FactionRelations, JobExpiredEvent, JobQueue, MovedEvent, NavigationGraph, PartitionDebug,
+    PartitionEvent, PathCache, Rooms, ScorerPlugin, SpawnColonistEvent, SpawnHostileEvent,
+    SpawnJobBuildEvent, SpawnJobFarmEvent, SpawnJobHaulEvent, SpawnJobMineEvent, TaskScheduler,
+    TaskSchedulerSet, UndesignateStockpileEvent,
 };
 use common::Rand;
-use controls::{raycast, setup_camera, update_camera, Raycast};
-use debug::{debug_settings::DebugSettings, fps::FpsPlugin, pathfinding::path_debug};
+use controls::{clamp_camera_to_world, raycast, setup_camera, update_camera, CursorHit};
+use debug::{
+    debug_settings::DebugSettings,
+    export::export_world_mesh_key,
+    fps::FpsPlugin,
+    light_test::light_test_scene,
+    pathfinding::{path_cache_stats, path_debug, path_follow_partition_debug, patrol_route_debug},
+};
 use items::{
-    on_spawn_pickaxe, on_spawn_stone, ColonistAnimations, SpawnPickaxeEvent, SpawnStoneEvent,
+    on_spawn_coal, on_spawn_food, on_spawn_ore, on_spawn_pickaxe, on_spawn_stone, on_spawn_torch,
+    on_spawn_wood, SpawnCoalEvent, SpawnFoodEvent, SpawnOreEvent, SpawnPickaxeEvent,
+    SpawnStoneEvent, SpawnTorchEvent, SpawnWoodEvent,
+};
+use save::{
+    clear_chunk_cache, on_load_request, on_save_request, poll_save_tasks, save_load_keys,
+    spawn_loaded_entities, LoadRequest, PendingWorldEntities, SaveRequest, SaveTasks,
 };
 use terrain::*;
 use ui::{
-    setup_block_toolbar_ui, tool_system, toolbar_select, ui_capture_pointer, Tool, Toolbar, Ui,
+    colonist_distance_tool, guard_post_tool, order_colonist_tool, patrol_route_tool,
+    run_error_screen, select_colonist_tool, setup_block_toolbar_ui, stockpile_tool, tool_system,
+    toolbar_select, ui_capture_pointer, Tool, Toolbar, Ui,
 };
 
 mod colonists;
@@ -29,12 +61,31 @@ mod common;
 mod controls;
 mod debug;
 mod items;
+mod save;
 mod terrain;
 mod ui;
 
 fn main() {
+    let world = WorldGenConfig::from_args(std::env::args().skip(1))
+        .and_then(|config| Ok((config.build_terrain()?, config)));
+
+    let (terrain, config) = match world {
+        Ok(world) => world,
+        Err(err) => {
+            eprintln!("invalid world config: {}", err);
+            run_error_screen(format!("invalid world config: {}", err));
+            return;
+        }
+    };
+
+    // whatever is cached belongs to the last world that was played
+    if let Err(err) = clear_chunk_cache(&terrain.chunk_cache_dir) {
+        eprintln!("failed to clear the chunk cache: {}", err);
+    }
+
     App::new()
-        .insert_resource(Terrain::new(8, 4, 8, 16))
+        .insert_resource(terrain)
+        .insert_resource(config)
         .insert_resource(Rand::new())
         .insert_resource(DebugSettings::default())
         .insert_resource(Toolbar {
@@ -43,7 +94,7 @@ fn main() {
         .insert_resource(Ui {
             pointer_captured: false,
         })
-        .insert_resource(Raycast {
+        .insert_resource(CursorHit {
             is_hit: false,
             hit_pos: [0, 0, 0],
             is_adj_hit: false,
@@ -54,13 +105,54 @@ fn main() {
         .add_event::<SpawnPickaxeEvent>()
         .add_event::<DestroyItemEvent>()
         .add_event::<SpawnStoneEvent>()
+        .add_event::<SpawnWoodEvent>()
+        .add_event::<SpawnCoalEvent>()
+        .add_event::<SpawnTorchEvent>()
+        .add_event::<SpawnOreEvent>()
+        .add_event::<SpawnFoodEvent>()
+        .add_event::<SpawnHostileEvent>()
+        .add_event::<BlockChangedEvent>()
+        .add_event::<CaveInEvent>()
+        .add_event::<WorldGenProgress>()
         .add_event::<SpawnJobBuildEvent>()
         .add_event::<SpawnJobMineEvent>()
+        .add_event::<DesignateMineEvent>()
+        .add_event::<SpawnJobFarmEvent>()
+        .add_event::<SpawnJobHaulEvent>()
+        .add_event::<DesignateStockpileEvent>()
+        .add_event::<UndesignateStockpileEvent>()
+        .add_event::<SaveRequest>()
+        .add_event::<LoadRequest>()
+        .add_event::<JobExpiredEvent>()
+        .add_event::<ColonistDiedEvent>()
+        .add_event::<ColonistStarvingEvent>()
+        .add_event::<DamagedByBlockEvent>()
         .add_event::<MovedEvent>()
         .add_event::<TerrainSliceChanged>()
         .add_event::<PartitionEvent>()
+        .init_resource::<ChunkMeshSettings>()
+        .init_resource::<MeshStats>()
+        .init_resource::<ChunkStreaming>()
+        .init_resource::<Fires>()
+        .init_resource::<Rot>()
+        .init_resource::<Farms>()
+        .init_resource::<Fluids>()
+        .init_resource::<GravityBlocks>()
+        .init_resource::<GrassGrowth>()
+        .init_resource::<Mechanisms>()
+        .init_resource::<Rooms>()
+        .init_resource::<EditHistory>()
+        .init_resource::<PathCache>()
+        .init_resource::<SaveTasks>()
+        .init_resource::<PendingWorldEntities>()
+        .init_resource::<WorldClock>()
+        .init_resource::<EnvironmentalDamage>()
+        .init_resource::<DeathCount>()
         .init_resource::<NavigationGraph>()
         .init_resource::<PartitionDebug>()
+        .init_resource::<TaskScheduler>()
+        .init_resource::<JobQueue>()
+        .init_resource::<FactionRelations>()
         .add_plugins((DefaultPlugins, ObjPlugin))
         // .add_plugins(WorldInspectorPlugin::default())
         .add_plugins(ScorerPlugin)
@@ -90,56 +182,226 @@ fn main() {
         .add_systems(Update, draw_gizmos)
         .add_systems(Update, raycast)
         .add_systems(Update, scroll_events)
+        .add_systems(
+            Update,
+            (
+                toggle_slice_mode,
+                snap_slice_to_surface,
+                update_slice_from_camera,
+                update_slice_from_selected,
+            )
+                .chain()
+                .after(update_camera),
+        )
         // .add_systems(Update, process_dirty_chunks)
         .add_systems(Update, on_slice_changed)
+        .add_systems(Update, (tick_world_clock, update_sun_uniforms).chain())
+        .add_systems(Update, mesh_stats_report)
+        .add_systems(Update, log_world_gen_progress)
+        .add_systems(Update, compact_idle_chunks)
+        .add_systems(Update, export_world_mesh_key)
+        .add_systems(Update, light_test_scene)
+        .add_systems(
+            Update,
+            (
+                save_load_keys,
+                on_save_request,
+                on_load_request,
+                poll_save_tasks,
+                spawn_loaded_entities,
+            )
+                .chain(),
+        )
         .add_systems(Update, update_slice_mesh)
         .add_systems(Update, light_system)
-        .add_systems(Update, update_camera)
+        .add_systems(Update, propagate_fire)
+        .add_systems(Update, (track_rot, tick_rot).chain())
+        .add_systems(Update, tick_farm)
+        .add_systems(Update, tick_torches)
+        .add_systems(Update, link_pressure_plates.before(tick_mechanisms))
+        .add_systems(Update, tick_mechanisms)
+        .add_systems(Update, tick_block_damage)
+        .add_systems(Update, (activate_fluids, tick_fluids).chain())
+        .add_systems(Update, (queue_gravity_blocks, tick_gravity_blocks).chain())
+        .add_systems(Update, (detect_cave_ins, on_cave_in).chain())
+        .add_systems(Update, tick_grass)
+        .add_systems(Update, (update_camera, clamp_camera_to_world).chain())
         .add_systems(Update, toolbar_select)
-        .add_systems(Update, path_debug)
+        .add_systems(Update, (path_debug, path_cache_stats))
+        .add_systems(Update, path_follow_partition_debug)
+        .add_systems(Update, patrol_route_debug)
         .add_systems(Update, tool_system)
+        .add_systems(Update, patrol_route_tool)
+        .add_systems(Update, guard_post_tool)
+        .add_systems(Update, stockpile_tool)
+        .add_systems(
+            Update,
+            (
+                colonist_distance_tool.before(select_colonist_tool),
+                select_colonist_tool,
+                order_colonist_tool,
+            ),
+        )
+        .add_systems(
+            Update,
+            (collect_edit_history, edit_history_keys)
+                .chain()
+                .after(tool_system)
+                .after(on_designate_mine),
+        )
         .add_systems(Update, on_spawn_colonist)
         .add_systems(Update, on_spawn_pickaxe)
         .add_systems(Update, on_spawn_stone)
+        .add_systems(Update, on_spawn_wood)
+        .add_systems(Update, on_spawn_coal)
+        .add_systems(Update, on_spawn_torch)
+        .add_systems(Update, on_spawn_ore)
+        .add_systems(Update, on_spawn_food)
+        .add_systems(Update, on_spawn_hostile)
         .add_systems(
             Update,
-            (process_dirty_chunks, partition, update_item_partition).chain(),
+            (
+                stream_chunks,
+                spawn_filled_chunks,
+                update_chunk_lod,
+                process_dirty_chunks,
+                flush_partition_updates,
+                partition,
+                log_world_stats,
+                update_item_partition,
+                partition_orphaned_items,
+                detect_rooms,
+                invalidate_path_cache,
+            )
+                .chain(),
         )
         // .add_systems(Update, update_item_partition)
         .add_systems(Update, apply_falling)
         .add_systems(Update, partition_debug)
+        .add_systems(Update, (toggle_light_debug, light_debug).chain())
+        .add_systems(Update, validate_partitions_key)
+        .add_systems(Update, check_blueprint_materials.before(job_accessibility))
         .add_systems(Update, job_accessibility)
+        .add_systems(Update, (check_job_deadlines, log_expired_jobs).chain())
+        .add_systems(
+            Update,
+            (
+                apply_environmental_damage,
+                flee_hazards,
+                colonist_death,
+                hostile_death,
+            )
+                .chain(),
+        )
         .add_systems(Update, fatigue_system)
+        .add_systems(Update, tick_hunger)
+        .add_systems(Update, tick_mood)
         .add_systems(Update, destroy_items)
+        .add_systems(Update, update_carry_capacity)
         .add_systems(Update, block_move_system)
         .add_systems(PreUpdate, job_despawn_complete)
         .add_systems(PreUpdate, job_despawn_cancelled)
+        .add_systems(PreUpdate, interrupt_behaviors.before(behavior_system))
         .add_systems(PreUpdate, behavior_system)
+        .add_systems(
+            PreUpdate,
+            (advance_sequence_tasks, advance_selector_tasks).before(behavior_system),
+        )
+        .add_systems(Update, (tick_task_timeouts, tick_task_estimates))
         .add_systems(Update, on_spawn_job_build)
+        .add_systems(Update, on_designate_mine)
+        .add_systems(Update, tick_mine_areas.after(on_designate_mine))
+        .add_systems(Update, mine_area_gizmos)
+        .add_systems(
+            Update,
+            (update_thought_bubbles, draw_thought_bubbles).chain(),
+        )
         .add_systems(Update, on_spawn_job_mine)
+        .add_systems(Update, on_spawn_job_farm)
+        .add_systems(Update, on_spawn_job_haul)
+        .add_systems(
+            Update,
+            (
+                on_designate_stockpile,
+                on_undesignate_stockpile,
+                prune_stockpiles,
+                track_stockpile_occupancy,
+                scan_stockpiles,
+            )
+                .chain(),
+        )
         .add_systems(Update, behavior_pick_system)
         .add_systems(
             Update,
-            (score_wander, score_mine, score_build).before(behavior_pick_system),
+            sync_job_queue
+                .before(score_mine)
+                .before(score_farm)
+                .before(score_build),
+        )
+        .add_systems(
+            Update,
+            (
+                score_wander,
+                score_mine,
+                score_chop,
+                score_farm,
+                score_build,
+                score_cook,
+                score_eat,
+                score_patrol,
+                score_guard,
+                score_haul,
+                score_supply,
+                score_light,
+                score_tantrum,
+            )
+                .before(behavior_pick_system),
         )
         .add_systems(Update, task_assign_job)
         .add_systems(Update, task_find_bed)
         .add_systems(Update, task_sleep)
         .add_systems(Update, task_idle)
         .add_systems(Update, task_pick_random_spot)
-        .add_systems(Update, task_move_to)
+        .add_systems(Update, reset_task_scheduler.in_set(TaskSchedulerSet))
+        .add_systems(Update, task_move_to.after(TaskSchedulerSet))
+        .add_systems(Update, task_patrol)
+        .add_systems(Update, task_guard)
+        .add_systems(Update, task_deliver_item)
         .add_systems(Update, task_get_job_location)
         .add_systems(Update, task_mine_block)
+        .add_systems(Update, task_clear_rubble)
+        .add_systems(Update, task_farm)
         .add_systems(Update, task_build_block)
+        .add_systems(Update, task_chop)
+        .add_systems(Update, task_remove_rot)
+        .add_systems(Update, task_craft)
+        .add_systems(Update, task_eat)
+        .add_systems(Update, task_place_torch)
+        .add_systems(Update, task_set_move_goals)
+        .add_systems(Update, task_find_nearest_campfire)
         .add_systems(Update, task_debug)
         .add_systems(Update, task_job_unassign)
         .add_systems(Update, task_job_cancel)
         .add_systems(Update, task_job_complete)
         .add_systems(Update, task_check_has_item)
-        .add_systems(Update, task_find_nearest_item)
+        .add_systems(Update, task_find_nearest_item.after(TaskSchedulerSet))
         .add_systems(Update, task_pick_up_item)
+        .add_systems(Update, task_find_haul_item)
+        .add_systems(Update, task_haul)
+        .add_systems(Update, (restore_relationships, tick_relationships).chain())
+        .add_systems(Update, restore_inventories)
+        .add_systems(Update, task_release_item)
         .add_systems(Update, task_is_target_empty)
-        .add_systems(Update, run_animations)
+        .add_systems(Update, task_tantrum)
+        .add_systems(
+            Update,
+            (
+                link_colonist_animators,
+                tick_animation_state,
+                play_animation_state,
+            )
+                .chain(),
+        )
         .run();
 }
 
@@ -149,15 +411,6 @@ struct Cursor {}
 #[derive(Resource)]
 struct HumanGltf(Handle<Scene>);
 
-fn run_animations(
-    animations: Res<ColonistAnimations>,
-    mut colonists: Query<&mut AnimationPlayer, Added<AnimationPlayer>>,
-) {
-    for mut colonist in colonists.iter_mut() {
-        colonist.play(animations.0[2].clone_weak()).repeat();
-    }
-}
-
 fn setup(
     mut cmd: Commands,
     asset_server: Res<AssetServer>,
@@ -166,11 +419,11 @@ fn setup(
     let gltf = asset_server.load("human.gltf#Scene0");
     cmd.insert_resource(HumanGltf(gltf));
 
-    cmd.insert_resource(ColonistAnimations(vec![
-        asset_server.load("human.gltf#Animation0"),
-        asset_server.load("human.gltf#Animation1"),
-        asset_server.load("human.gltf#Animation2"),
-    ]));
+    cmd.insert_resource(ColonistAnimationClips {
+        idle: asset_server.load("human.gltf#Animation1"),
+        walking: asset_server.load("human.gltf#Animation2"),
+        mining: asset_server.load("human.gltf#Animation0"),
+    });
 
     let mesh = asset_server.load("meshes/cube_offcenter.obj");
     let material = materials.add(StandardMaterial {
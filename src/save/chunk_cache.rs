@@ -0,0 +1,134 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use crate::{BlockBuffer, BlockType};
+
+use super::{invalid_data, SaveReader, SaveWriter, SAVE_DIR};
+
+const CHUNK_CACHE_DIR: &str = "chunk_cache";
+
+/// Block type, torchlight, sunlight, flags
+pub const BLOCK_BYTES: usize = 4;
+const FLAG_MINE: u8 = 1;
+const FLAG_BLUEPRINT: u8 = 2;
+/// The fluid level sits in the spare bits above the flags. A water block
+/// stored with zero there loads as full, the same as placing new water.
+const FLUID_SHIFT: u8 = 2;
+
+/// Append every block of the chunk to `buffer`, `BLOCK_BYTES` per block.
+pub fn encode_chunk(chunk: &BlockBuffer, buffer: &mut Vec<u8>) {
+    for block_idx in 0..chunk.block_count {
+        let block = chunk.get_block(block_idx);
+        let mut flags = 0;
+
+        if block.flag_mine {
+            flags |= FLAG_MINE;
+        }
+        if block.flag_blueprint {
+            flags |= FLAG_BLUEPRINT;
+        }
+        flags |= block.fluid_level << FLUID_SHIFT;
+
+        buffer.extend_from_slice(&[block.block.0, block.light, block.sunlight, flags]);
+    }
+}
+
+/// Decoded block, the flags are split out for the caller
+pub struct EncodedBlock {
+    pub block: BlockType,
+    pub light: u8,
+    pub sunlight: u8,
+    pub flag_mine: bool,
+    pub flag_blueprint: bool,
+    pub fluid_level: u8,
+}
+
+pub fn decode_blocks(data: &[u8]) -> impl Iterator<Item = EncodedBlock> + '_ {
+    data.chunks_exact(BLOCK_BYTES).map(|b| EncodedBlock {
+        block: BlockType(b[0]),
+        light: b[1],
+        sunlight: b[2],
+        flag_mine: b[3] & FLAG_MINE != 0,
+        flag_blueprint: b[3] & FLAG_BLUEPRINT != 0,
+        fluid_level: b[3] >> FLUID_SHIFT,
+    })
+}
+
+/// Where a new world caches its unloaded chunks, next to the saves
+pub fn default_chunk_cache_dir() -> PathBuf {
+    Path::new(SAVE_DIR).join(CHUNK_CACHE_DIR)
+}
+
+/// Delete every cached chunk. The cache only makes sense for the world that
+/// wrote it, so it is cleared whenever a new world replaces the old one.
+pub fn clear_chunk_cache(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn cache_path(dir: &Path, chunk_idx: u32) -> PathBuf {
+    dir.join(format!("{}.chunk", chunk_idx))
+}
+
+pub fn write_chunk_cache(dir: &Path, chunk: &BlockBuffer) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let file = File::create(cache_path(dir, chunk.chunk_idx))?;
+    let mut w = SaveWriter::new(BufWriter::new(file));
+    let mut buffer = Vec::with_capacity(chunk.block_count as usize * BLOCK_BYTES);
+
+    encode_chunk(chunk, &mut buffer);
+
+    w.write_header()?;
+    w.write_u32(chunk.block_count)?;
+    w.write_bytes(&buffer)?;
+    w.flush()
+}
+
+/// Append the encoded blocks of a cached chunk to `buffer`, as they would
+/// be written by `encode_chunk`
+pub fn read_chunk_cache_bytes(
+    dir: &Path,
+    chunk_idx: u32,
+    block_count: u32,
+    buffer: &mut Vec<u8>,
+) -> io::Result<()> {
+    let file = File::open(cache_path(dir, chunk_idx))?;
+    let mut r = SaveReader::new(BufReader::new(file));
+
+    r.read_header()?;
+
+    if r.read_u32()? != block_count {
+        return Err(invalid_data("cached chunk has a different size"));
+    }
+
+    let start = buffer.len();
+    buffer.resize(start + block_count as usize * BLOCK_BYTES, 0);
+    r.read_bytes(&mut buffer[start..])
+}
+
+/// Fill `chunk` from the cache file of its `chunk_idx`
+pub fn read_chunk_cache(dir: &Path, chunk: &mut BlockBuffer) -> io::Result<()> {
+    let mut data = vec![];
+    read_chunk_cache_bytes(dir, chunk.chunk_idx, chunk.block_count, &mut data)?;
+
+    for (block_idx, block) in decode_blocks(&data).enumerate() {
+        let block_idx = block_idx as u32;
+        chunk.set_block_type(block_idx, block.block);
+        chunk.set_torchlight(block_idx, block.light);
+        chunk.set_sunlight(block_idx, block.sunlight);
+        chunk.set_flag_mine(block_idx, block.flag_mine);
+        chunk.set_flag_blueprint(block_idx, block.flag_blueprint);
+
+        if block.block == BlockType::WATER && block.fluid_level > 0 {
+            chunk.set_fluid_level(block_idx, block.fluid_level);
+        }
+    }
+
+    Ok(())
+}
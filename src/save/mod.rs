@@ -0,0 +1,7 @@
+mod chunk_cache;
+mod save_format;
+mod world_save;
+
+pub use chunk_cache::*;
+pub use save_format::*;
+pub use world_save::*;
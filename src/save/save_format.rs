@@ -0,0 +1,158 @@
+use std::io::{self, Read, Write};
+
+use crate::colonists::ItemTag;
+
+pub const SAVE_MAGIC: [u8; 4] = *b"BRSV";
+pub const SAVE_VERSION: u32 = 4;
+
+pub fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Little-endian writer for the save file.
+pub struct SaveWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> SaveWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    pub fn write_header(&mut self) -> io::Result<()> {
+        self.inner.write_all(&SAVE_MAGIC)?;
+        self.write_u32(SAVE_VERSION)
+    }
+
+    pub fn write_bytes(&mut self, value: &[u8]) -> io::Result<()> {
+        self.inner.write_all(value)
+    }
+
+    pub fn write_u8(&mut self, value: u8) -> io::Result<()> {
+        self.inner.write_all(&[value])
+    }
+
+    pub fn write_u32(&mut self, value: u32) -> io::Result<()> {
+        self.inner.write_all(&value.to_le_bytes())
+    }
+
+    pub fn write_i32(&mut self, value: i32) -> io::Result<()> {
+        self.inner.write_all(&value.to_le_bytes())
+    }
+
+    pub fn write_f32(&mut self, value: f32) -> io::Result<()> {
+        self.inner.write_all(&value.to_le_bytes())
+    }
+
+    pub fn write_tags(&mut self, tags: &[ItemTag]) -> io::Result<()> {
+        self.write_u8(tags.len() as u8)?;
+        for tag in tags {
+            self.write_u8(tag_id(tag))?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads back what `SaveWriter` wrote.
+pub struct SaveReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> SaveReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    pub fn read_header(&mut self) -> io::Result<u32> {
+        let mut magic = [0; 4];
+        self.inner.read_exact(&mut magic)?;
+
+        if magic != SAVE_MAGIC {
+            return Err(invalid_data("not a save file"));
+        }
+
+        let version = self.read_u32()?;
+
+        if version != SAVE_VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported save version {}",
+                version
+            )));
+        }
+
+        Ok(version)
+    }
+
+    pub fn read_bytes(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact(buf)
+    }
+
+    pub fn read_u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0; 1];
+        self.inner.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    pub fn read_u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0; 4];
+        self.inner.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    pub fn read_i32(&mut self) -> io::Result<i32> {
+        let mut buf = [0; 4];
+        self.inner.read_exact(&mut buf)?;
+        Ok(i32::from_le_bytes(buf))
+    }
+
+    pub fn read_f32(&mut self) -> io::Result<f32> {
+        let mut buf = [0; 4];
+        self.inner.read_exact(&mut buf)?;
+        Ok(f32::from_le_bytes(buf))
+    }
+
+    pub fn read_tags(&mut self) -> io::Result<Vec<ItemTag>> {
+        let count = self.read_u8()?;
+        (0..count)
+            .map(|_| {
+                let id = self.read_u8()?;
+                tag_from_id(id).ok_or_else(|| invalid_data("unknown item tag"))
+            })
+            .collect()
+    }
+}
+
+fn tag_id(tag: &ItemTag) -> u8 {
+    match tag {
+        ItemTag::Pickaxe => 0,
+        ItemTag::Stone => 1,
+        ItemTag::Wood => 2,
+        ItemTag::RawFood => 3,
+        ItemTag::CookedFood => 4,
+        ItemTag::Coal => 5,
+        ItemTag::Torch => 6,
+        ItemTag::IronOre => 7,
+        ItemTag::GoldOre => 8,
+        ItemTag::Food => 9,
+    }
+}
+
+fn tag_from_id(id: u8) -> Option<ItemTag> {
+    match id {
+        0 => Some(ItemTag::Pickaxe),
+        1 => Some(ItemTag::Stone),
+        2 => Some(ItemTag::Wood),
+        3 => Some(ItemTag::RawFood),
+        4 => Some(ItemTag::CookedFood),
+        5 => Some(ItemTag::Coal),
+        6 => Some(ItemTag::Torch),
+        7 => Some(ItemTag::IronOre),
+        8 => Some(ItemTag::GoldOre),
+        9 => Some(ItemTag::Food),
+        _ => None,
+    }
+}
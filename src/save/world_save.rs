@@ -0,0 +1,796 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    ecs::{
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::{Or, With},
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    hierarchy::DespawnRecursiveExt,
+    input::{keyboard::KeyCode, ButtonInput},
+    tasks::{block_on, futures_lite::future, IoTaskPool, Task},
+    transform::components::Transform,
+    utils::hashbrown::HashMap,
+};
+
+use crate::{
+    colonists::{
+        Colonist, HasBehavior, InInventory, Inventory, Item, ItemTag, Job, NavigationGraph,
+        Relationships, SavedInventory, SavedRelationships, SpawnColonistEvent, SpawnJobBuildEvent,
+        SpawnJobMineEvent, PLAYER_FACTION,
+    },
+    items::{
+        SpawnCoalEvent, SpawnFoodEvent, SpawnOreEvent, SpawnPickaxeEvent, SpawnStoneEvent,
+        SpawnTorchEvent, SpawnWoodEvent,
+    },
+    validate_world_shape, BlockType, Terrain, TerrainSlice, TerrainSliceChanged,
+};
+
+use super::{clear_chunk_cache, decode_blocks, invalid_data, SaveReader, SaveWriter, BLOCK_BYTES};
+
+/// Saves and the chunk cache of the world being played go in here
+pub const SAVE_DIR: &str = "saves";
+const SAVE_FILE: &str = "world.sav";
+
+pub struct ItemSave {
+    pub pos: [u32; 3],
+    pub tags: Vec<ItemTag>,
+}
+
+pub struct ColonistSave {
+    pub pos: [u32; 3],
+    pub items: Vec<Vec<ItemTag>>,
+    /// Opinions of other colonists, by their place in the colonist list
+    pub opinions: Vec<(u32, f32)>,
+}
+
+/// Everything after the chunks in a save file, copied out of the world so
+/// it can be written off the main thread. The chunks themselves are too big
+/// to copy and go straight from the terrain to the file.
+pub struct WorldSnapshot {
+    pub farm_plots: Vec<([u32; 3], u32)>,
+    pub torch_fuel: Vec<([u32; 3], u32)>,
+    pub colonists: Vec<ColonistSave>,
+    pub items: Vec<ItemSave>,
+    pub slice_y: u32,
+}
+
+/// A save file read back from disk. Chunks hold `BLOCK_BYTES` per block.
+pub struct WorldSave {
+    pub chunk_counts: [u32; 3],
+    pub chunk_size: u32,
+    pub seed: i32,
+    pub chunks: Vec<Box<[u8]>>,
+    pub farm_plots: Vec<([u32; 3], u32)>,
+    pub torch_fuel: Vec<([u32; 3], u32)>,
+    pub colonists: Vec<ColonistSave>,
+    pub items: Vec<ItemSave>,
+    pub slice_y: u32,
+}
+
+fn to_block_pos(transform: &Transform) -> [u32; 3] {
+    [
+        transform.translation.x as u32,
+        transform.translation.y as u32,
+        transform.translation.z as u32,
+    ]
+}
+
+fn write_pos<W: io::Write>(w: &mut SaveWriter<W>, pos: [u32; 3]) -> io::Result<()> {
+    w.write_u32(pos[0])?;
+    w.write_u32(pos[1])?;
+    w.write_u32(pos[2])
+}
+
+fn read_pos<R: io::Read>(r: &mut SaveReader<R>) -> io::Result<[u32; 3]> {
+    Ok([r.read_u32()?, r.read_u32()?, r.read_u32()?])
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut temp = OsString::from(path.as_os_str());
+    temp.push(".tmp");
+    PathBuf::from(temp)
+}
+
+/// The save dir may not exist yet on the first save
+fn create_temp(temp: &Path) -> io::Result<File> {
+    if let Some(dir) = temp.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    File::create(temp)
+}
+
+fn finish_save(mut w: SaveWriter<BufWriter<File>>, temp: &Path, path: &Path) -> io::Result<()> {
+    w.flush()?;
+    drop(w);
+    fs::rename(temp, path)
+}
+
+/// Writes the header and every chunk. Chunks are encoded one at a time into
+/// a single buffer, unloaded ones are copied from the chunk cache.
+pub fn write_terrain<W: io::Write>(w: &mut SaveWriter<W>, terrain: &Terrain) -> io::Result<()> {
+    w.write_header()?;
+    w.write_u32(terrain.chunk_count_x)?;
+    w.write_u32(terrain.chunk_count_y)?;
+    w.write_u32(terrain.chunk_count_z)?;
+    w.write_u32(terrain.chunk_size)?;
+    w.write_i32(terrain.seed)?;
+
+    let mut buffer = vec![];
+
+    for chunk_idx in 0..terrain.chunk_count {
+        buffer.clear();
+        terrain.encode_chunk_or_cached(chunk_idx, &mut buffer)?;
+        w.write_bytes(&buffer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes everything that follows the chunks
+pub fn write_entities<W: io::Write>(
+    w: &mut SaveWriter<W>,
+    snapshot: &WorldSnapshot,
+) -> io::Result<()> {
+    w.write_u32(snapshot.farm_plots.len() as u32)?;
+    for (pos, ticks) in snapshot.farm_plots.iter() {
+        write_pos(w, *pos)?;
+        w.write_u32(*ticks)?;
+    }
+
+    w.write_u32(snapshot.torch_fuel.len() as u32)?;
+    for (pos, fuel) in snapshot.torch_fuel.iter() {
+        write_pos(w, *pos)?;
+        w.write_u32(*fuel)?;
+    }
+
+    w.write_u32(snapshot.colonists.len() as u32)?;
+    for colonist in snapshot.colonists.iter() {
+        write_pos(w, colonist.pos)?;
+        w.write_u32(colonist.items.len() as u32)?;
+        for tags in colonist.items.iter() {
+            w.write_tags(tags)?;
+        }
+        w.write_u32(colonist.opinions.len() as u32)?;
+        for (other_idx, opinion) in colonist.opinions.iter() {
+            w.write_u32(*other_idx)?;
+            w.write_f32(*opinion)?;
+        }
+    }
+
+    w.write_u32(snapshot.items.len() as u32)?;
+    for item in snapshot.items.iter() {
+        write_pos(w, item.pos)?;
+        w.write_tags(&item.tags)?;
+    }
+
+    w.write_u32(snapshot.slice_y)
+}
+
+pub fn load_world(path: &Path) -> io::Result<WorldSave> {
+    let mut r = SaveReader::new(BufReader::new(File::open(path)?));
+
+    r.read_header()?;
+    let chunk_counts = [r.read_u32()?, r.read_u32()?, r.read_u32()?];
+    let chunk_size = r.read_u32()?;
+    let seed = r.read_i32()?;
+
+    // the sizes below come straight from the file, so check them before
+    // allocating anything
+    validate_world_shape(chunk_counts, chunk_size).map_err(|err| invalid_data(&err.to_string()))?;
+
+    let chunk_count = chunk_counts
+        .iter()
+        .try_fold(1usize, |count, n| count.checked_mul(*n as usize))
+        .ok_or_else(|| invalid_data("too many chunks"))?;
+    let chunk_bytes = chunk_size
+        .checked_pow(3)
+        .and_then(|blocks| (blocks as usize).checked_mul(BLOCK_BYTES))
+        .ok_or_else(|| invalid_data("chunks are too large"))?;
+
+    let chunks = (0..chunk_count)
+        .map(|_| {
+            let mut blocks = vec![0; chunk_bytes].into_boxed_slice();
+            r.read_bytes(&mut blocks)?;
+            Ok(blocks)
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let farm_count = r.read_u32()?;
+    let farm_plots = (0..farm_count)
+        .map(|_| Ok((read_pos(&mut r)?, r.read_u32()?)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let torch_count = r.read_u32()?;
+    let torch_fuel = (0..torch_count)
+        .map(|_| Ok((read_pos(&mut r)?, r.read_u32()?)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let colonist_count = r.read_u32()?;
+    let colonists = (0..colonist_count)
+        .map(|_| {
+            let pos = read_pos(&mut r)?;
+            let item_count = r.read_u32()?;
+            let items = (0..item_count)
+                .map(|_| r.read_tags())
+                .collect::<io::Result<Vec<_>>>()?;
+            let opinion_count = r.read_u32()?;
+            let opinions = (0..opinion_count)
+                .map(|_| Ok((r.read_u32()?, r.read_f32()?)))
+                .collect::<io::Result<Vec<_>>>()?;
+            Ok(ColonistSave {
+                pos,
+                items,
+                opinions,
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let item_count = r.read_u32()?;
+    let items = (0..item_count)
+        .map(|_| {
+            Ok(ItemSave {
+                pos: read_pos(&mut r)?,
+                tags: r.read_tags()?,
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let slice_y = r.read_u32()?;
+
+    Ok(WorldSave {
+        chunk_counts,
+        chunk_size,
+        seed,
+        chunks,
+        farm_plots,
+        torch_fuel,
+        colonists,
+        items,
+        slice_y,
+    })
+}
+
+impl WorldSave {
+    /// Builds a fresh terrain from the save. Designations are not restored
+    /// here, they are returned so their jobs can be spawned again.
+    pub fn build_terrain(&self) -> io::Result<(Terrain, Vec<[u32; 3]>, Vec<[u32; 3]>)> {
+        let [cx, cy, cz] = self.chunk_counts;
+        let mut terrain = Terrain::new(cx, cy, cz, self.chunk_size)
+            .map_err(|err| invalid_data(&err.to_string()))?;
+        terrain.seed = self.seed;
+
+        let mut mines = vec![];
+        let mut blueprints = vec![];
+
+        for (chunk_idx, blocks) in self.chunks.iter().enumerate() {
+            let chunk_idx = chunk_idx as u32;
+            terrain.init_chunk(chunk_idx);
+
+            for (block_idx, data) in decode_blocks(blocks).enumerate() {
+                let block_idx = block_idx as u32;
+                let local = terrain
+                    .get_chunk(chunk_idx)
+                    .and_then(|chunk| chunk.get_block_xyz(block_idx));
+
+                let Some([bx, by, bz]) = local else {
+                    return Err(invalid_data("block out of range"));
+                };
+
+                let [ox, oy, oz] = terrain.get_chunk_offset(chunk_idx);
+                let [x, y, z] = [ox + bx, oy + by, oz + bz];
+
+                terrain.init_block(x, y, z, data.block);
+
+                let Some(chunk) = terrain.get_chunk_mut(chunk_idx) else {
+                    return Err(invalid_data("chunk out of range"));
+                };
+                chunk.set_torchlight(block_idx, data.light);
+                chunk.set_sunlight(block_idx, data.sunlight);
+
+                if data.block == BlockType::WATER && data.fluid_level > 0 {
+                    chunk.set_fluid_level(block_idx, data.fluid_level);
+                }
+
+                if data.flag_mine {
+                    mines.push([x, y, z]);
+                }
+                if data.flag_blueprint {
+                    blueprints.push([x, y, z]);
+                }
+            }
+        }
+
+        for (pos, ticks) in self.farm_plots.iter() {
+            terrain.farm_plots.insert(*pos, *ticks);
+        }
+
+        for (pos, fuel) in self.torch_fuel.iter() {
+            terrain.torch_fuel.insert(*pos, *fuel);
+        }
+
+        terrain.cache_surface_heights();
+
+        Ok((terrain, mines, blueprints))
+    }
+}
+
+#[derive(Event)]
+pub struct SaveRequest {
+    pub path: PathBuf,
+}
+
+#[derive(Event)]
+pub struct LoadRequest {
+    pub path: PathBuf,
+}
+
+#[derive(Resource, Default)]
+pub struct SaveTasks {
+    saving: Vec<Task<io::Result<PathBuf>>>,
+    loading: Option<Task<io::Result<WorldSave>>>,
+}
+
+/// Entities and jobs from a loaded save. They wait until the new terrain has
+/// been partitioned, so they land in the navigation graph.
+#[derive(Resource, Default)]
+pub struct PendingWorldEntities {
+    colonists: Vec<ColonistSave>,
+    items: Vec<ItemSave>,
+    mines: Vec<[u32; 3]>,
+    blueprints: Vec<[u32; 3]>,
+    is_pending: bool,
+}
+
+pub fn save_load_keys(
+    input_keys: Res<ButtonInput<KeyCode>>,
+    mut ev_save: EventWriter<SaveRequest>,
+    mut ev_load: EventWriter<LoadRequest>,
+) {
+    if input_keys.just_pressed(KeyCode::F5) {
+        ev_save.send(SaveRequest {
+            path: Path::new(SAVE_DIR).join(SAVE_FILE),
+        });
+    }
+
+    if input_keys.just_pressed(KeyCode::F9) {
+        ev_load.send(LoadRequest {
+            path: Path::new(SAVE_DIR).join(SAVE_FILE),
+        });
+    }
+}
+
+pub fn on_save_request(
+    terrain: Res<Terrain>,
+    terrain_slice: Res<TerrainSlice>,
+    mut tasks: ResMut<SaveTasks>,
+    mut ev_save: EventReader<SaveRequest>,
+    q_colonists: Query<(Entity, &Transform, &Inventory, Option<&Relationships>), With<Colonist>>,
+    q_items: Query<(&Transform, &Item, Option<&InInventory>)>,
+) {
+    for ev in ev_save.read() {
+        let save_idxs = q_colonists
+            .iter()
+            .enumerate()
+            .map(|(idx, (entity, _, _, _))| (entity, idx as u32))
+            .collect::<HashMap<_, _>>();
+
+        let colonists = q_colonists
+            .iter()
+            .map(|(_, transform, inventory, relationships)| ColonistSave {
+                pos: to_block_pos(transform),
+                items: inventory
+                    .items
+                    .iter()
+                    .filter_map(|e| q_items.get(*e).ok())
+                    .map(|(_, item, _)| item.tags.clone())
+                    .collect(),
+                opinions: relationships
+                    .iter()
+                    .flat_map(|relationships| relationships.opinions.iter())
+                    .filter_map(|(other, opinion)| Some((*save_idxs.get(other)?, *opinion)))
+                    .collect(),
+            })
+            .collect();
+
+        let items = q_items
+            .iter()
+            .filter(|(_, _, in_inventory)| in_inventory.is_none())
+            .map(|(transform, item, _)| ItemSave {
+                pos: to_block_pos(transform),
+                tags: item.tags.clone(),
+            })
+            .collect();
+
+        let snapshot = WorldSnapshot {
+            farm_plots: terrain
+                .farm_plots
+                .iter()
+                .map(|(pos, ticks)| (*pos, *ticks))
+                .collect(),
+            torch_fuel: terrain
+                .torch_fuel
+                .iter()
+                .map(|(pos, fuel)| (*pos, *fuel))
+                .collect(),
+            colonists,
+            items,
+            slice_y: terrain_slice.y,
+        };
+
+        // the chunks are written here, while the terrain can't change under
+        // them, and the rest on the io pool
+        let path = ev.path.clone();
+        let temp = temp_path(&path);
+        let mut w = match create_temp(&temp) {
+            Ok(file) => SaveWriter::new(BufWriter::new(file)),
+            Err(err) => {
+                println!("failed to save world: {}", err);
+                continue;
+            }
+        };
+
+        if let Err(err) = write_terrain(&mut w, &terrain) {
+            println!("failed to save world: {}", err);
+            continue;
+        }
+
+        let task = IoTaskPool::get().spawn(async move {
+            write_entities(&mut w, &snapshot)?;
+            finish_save(w, &temp, &path)?;
+            Ok(path)
+        });
+
+        tasks.saving.push(task);
+    }
+}
+
+pub fn on_load_request(mut tasks: ResMut<SaveTasks>, mut ev_load: EventReader<LoadRequest>) {
+    for ev in ev_load.read() {
+        if tasks.loading.is_some() {
+            println!("already loading a save!");
+            continue;
+        }
+
+        let path = ev.path.clone();
+        tasks.loading = Some(IoTaskPool::get().spawn(async move { load_world(&path) }));
+    }
+}
+
+pub fn poll_save_tasks(
+    mut cmd: Commands,
+    mut tasks: ResMut<SaveTasks>,
+    mut terrain: ResMut<Terrain>,
+    mut graph: ResMut<NavigationGraph>,
+    mut pending: ResMut<PendingWorldEntities>,
+    mut terrain_slice: ResMut<TerrainSlice>,
+    mut ev_terrain_slice: EventWriter<TerrainSliceChanged>,
+    q_world_entities: Query<Entity, Or<(With<Colonist>, With<Item>, With<Job>)>>,
+    q_behaviors: Query<&HasBehavior>,
+) {
+    tasks.saving.retain_mut(|task| {
+        let Some(result) = block_on(future::poll_once(task)) else {
+            return true;
+        };
+
+        match result {
+            Ok(path) => println!("saved world to {}", path.display()),
+            Err(err) => println!("failed to save world: {}", err),
+        }
+
+        false
+    });
+
+    let Some(task) = tasks.loading.as_mut() else {
+        return;
+    };
+
+    let Some(result) = block_on(future::poll_once(task)) else {
+        return;
+    };
+
+    tasks.loading = None;
+
+    let save = match result {
+        Ok(save) => save,
+        Err(err) => {
+            println!("failed to load world: {}", err);
+            return;
+        }
+    };
+
+    // chunk meshes are set up for the current world size
+    if save.chunk_counts
+        != [
+            terrain.chunk_count_x,
+            terrain.chunk_count_y,
+            terrain.chunk_count_z,
+        ]
+        || save.chunk_size != terrain.chunk_size
+    {
+        println!("failed to load world: save has a different world size");
+        return;
+    }
+
+    let (loaded, mines, blueprints) = match save.build_terrain() {
+        Ok(built) => built,
+        Err(err) => {
+            println!("failed to load world: {}", err);
+            return;
+        }
+    };
+
+    for entity in q_world_entities.iter() {
+        if let Ok(behavior) = q_behaviors.get(entity) {
+            cmd.entity(behavior.behavior_entity).despawn_recursive();
+        }
+        cmd.entity(entity).despawn_recursive();
+    }
+
+    if let Err(err) = clear_chunk_cache(&terrain.chunk_cache_dir) {
+        println!("failed to clear the chunk cache: {}", err);
+    }
+
+    *terrain = loaded;
+    *graph = NavigationGraph::default();
+    terrain_slice.set_value(save.slice_y as i32);
+    ev_terrain_slice.send(TerrainSliceChanged);
+    *pending = PendingWorldEntities {
+        colonists: save.colonists,
+        items: save.items,
+        mines,
+        blueprints,
+        is_pending: true,
+    };
+
+    println!("loaded world, waiting for partitions..");
+}
+
+pub fn spawn_loaded_entities(
+    terrain: Res<Terrain>,
+    mut pending: ResMut<PendingWorldEntities>,
+    mut ev_spawn_colonist: EventWriter<SpawnColonistEvent>,
+    mut ev_spawn_pickaxe: EventWriter<SpawnPickaxeEvent>,
+    mut ev_spawn_stone: EventWriter<SpawnStoneEvent>,
+    mut ev_spawn_wood: EventWriter<SpawnWoodEvent>,
+    mut ev_spawn_food: EventWriter<SpawnFoodEvent>,
+    mut ev_spawn_coal: EventWriter<SpawnCoalEvent>,
+    mut ev_spawn_torch: EventWriter<SpawnTorchEvent>,
+    mut ev_spawn_ore: EventWriter<SpawnOreEvent>,
+    mut ev_spawn_job_mine: EventWriter<SpawnJobMineEvent>,
+    mut ev_spawn_job_build: EventWriter<SpawnJobBuildEvent>,
+) {
+    if !pending.is_pending {
+        return;
+    }
+
+    if (0..terrain.chunk_count).any(|chunk_idx| terrain.get_chunk_dirty(chunk_idx)) {
+        return;
+    }
+
+    let pending = std::mem::take(pending.as_mut());
+
+    // carried items are spawned at their colonist, which picks them back up
+    // with `restore_inventories`
+    let carried = pending.colonists.iter().flat_map(|colonist| {
+        colonist.items.iter().map(|tags| ItemSave {
+            pos: colonist.pos,
+            tags: tags.clone(),
+        })
+    });
+
+    for item in pending.items.into_iter().chain(carried) {
+        let pos = item.pos;
+
+        if item.tags.contains(&ItemTag::Pickaxe) {
+            ev_spawn_pickaxe.send(SpawnPickaxeEvent { pos });
+        } else if item.tags.contains(&ItemTag::Stone) {
+            ev_spawn_stone.send(SpawnStoneEvent { pos });
+        } else if item.tags.contains(&ItemTag::Wood) {
+            ev_spawn_wood.send(SpawnWoodEvent { pos });
+        } else if item.tags.contains(&ItemTag::RawFood) {
+            ev_spawn_food.send(SpawnFoodEvent {
+                pos,
+                is_cooked: false,
+            });
+        } else if item.tags.contains(&ItemTag::CookedFood) {
+            ev_spawn_food.send(SpawnFoodEvent {
+                pos,
+                is_cooked: true,
+            });
+        } else if item.tags.contains(&ItemTag::Coal) {
+            ev_spawn_coal.send(SpawnCoalEvent { pos });
+        } else if item.tags.contains(&ItemTag::Torch) {
+            ev_spawn_torch.send(SpawnTorchEvent { pos });
+        } else if let Some(ore) = item
+            .tags
+            .iter()
+            .find(|tag| matches!(tag, ItemTag::IronOre | ItemTag::GoldOre))
+        {
+            ev_spawn_ore.send(SpawnOreEvent { pos, ore: *ore });
+        }
+    }
+
+    for (save_idx, colonist) in pending.colonists.iter().enumerate() {
+        ev_spawn_colonist.send(SpawnColonistEvent {
+            pos: colonist.pos,
+            faction: PLAYER_FACTION,
+            relationships: Some(SavedRelationships {
+                save_idx: save_idx as u32,
+                opinions: colonist.opinions.clone(),
+            }),
+            inventory: Some(SavedInventory {
+                pos: colonist.pos,
+                items: colonist.items.clone(),
+            }),
+        });
+    }
+
+    for pos in pending.mines {
+        ev_spawn_job_mine.send(SpawnJobMineEvent { pos });
+    }
+
+    for pos in pending.blueprints {
+        let block = terrain.get_block(pos[0], pos[1], pos[2]).block;
+        ev_spawn_job_build.send(SpawnJobBuildEvent { pos, block });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_save(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("boris-{}-{}.sav", name, std::process::id()))
+    }
+
+    /// `on_save_request` in one go, without splitting it across the io pool
+    fn save_world(path: &Path, terrain: &Terrain, snapshot: &WorldSnapshot) -> io::Result<()> {
+        let temp = temp_path(path);
+        let mut w = SaveWriter::new(BufWriter::new(create_temp(&temp)?));
+
+        write_terrain(&mut w, terrain)?;
+        write_entities(&mut w, snapshot)?;
+        finish_save(w, &temp, path)
+    }
+
+    fn test_terrain() -> Terrain {
+        let mut terrain = Terrain::new(2, 2, 2, 4).unwrap();
+        terrain.seed = 42;
+
+        for x in 0..8 {
+            for z in 0..8 {
+                for y in 0..(x + z) % 8 {
+                    let value =
+                        [BlockType::STONE, BlockType::DIRT, BlockType::WATER][(x + y) as usize % 3];
+                    terrain.set_block(x, y, z, value);
+                }
+            }
+        }
+
+        for chunk_idx in 0..terrain.chunk_count {
+            let chunk = terrain.get_chunk_mut(chunk_idx).unwrap();
+
+            for block_idx in 0..chunk.block_count {
+                chunk.set_torchlight(block_idx, (block_idx % 16) as u8);
+                chunk.set_sunlight(block_idx, (block_idx % 11) as u8);
+
+                if chunk.get_block(block_idx).block == BlockType::WATER {
+                    chunk.set_fluid_level(block_idx, (block_idx % 7) as u8 + 1);
+                }
+            }
+        }
+
+        terrain.set_flag_mine(1, 0, 1, true);
+        terrain.set_flag_blueprint(2, 7, 2, true);
+        terrain.farm_plots.insert([3, 4, 5], 17);
+        terrain.torch_fuel.insert([6, 1, 2], 90);
+
+        terrain
+    }
+
+    fn test_snapshot(terrain: &Terrain) -> WorldSnapshot {
+        WorldSnapshot {
+            farm_plots: terrain.farm_plots.iter().map(|(p, t)| (*p, *t)).collect(),
+            torch_fuel: terrain.torch_fuel.iter().map(|(p, f)| (*p, *f)).collect(),
+            colonists: vec![
+                ColonistSave {
+                    pos: [1, 2, 3],
+                    items: vec![
+                        vec![ItemTag::Pickaxe],
+                        vec![ItemTag::RawFood, ItemTag::Food],
+                    ],
+                    opinions: vec![(1, 0.5)],
+                },
+                ColonistSave {
+                    pos: [4, 5, 6],
+                    items: vec![],
+                    opinions: vec![(0, -0.25)],
+                },
+            ],
+            items: vec![ItemSave {
+                pos: [7, 0, 7],
+                tags: vec![ItemTag::IronOre],
+            }],
+            slice_y: 6,
+        }
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let terrain = test_terrain();
+        let snapshot = test_snapshot(&terrain);
+        let path = temp_save("round-trip");
+
+        save_world(&path, &terrain, &snapshot).unwrap();
+        let save = load_world(&path);
+        fs::remove_file(&path).unwrap();
+        let save = save.unwrap();
+
+        assert_eq!(save.chunk_counts, [2, 2, 2]);
+        assert_eq!(save.chunk_size, 4);
+        assert_eq!(save.seed, 42);
+        assert_eq!(save.farm_plots, snapshot.farm_plots);
+        assert_eq!(save.torch_fuel, snapshot.torch_fuel);
+        assert_eq!(save.slice_y, 6);
+        assert_eq!(save.colonists.len(), 2);
+        for (loaded, saved) in save.colonists.iter().zip(snapshot.colonists.iter()) {
+            assert_eq!(loaded.pos, saved.pos);
+            assert_eq!(loaded.items, saved.items);
+            assert_eq!(loaded.opinions, saved.opinions);
+        }
+        assert_eq!(save.items.len(), 1);
+        assert_eq!(save.items[0].pos, [7, 0, 7]);
+        assert_eq!(save.items[0].tags, vec![ItemTag::IronOre]);
+
+        let (loaded, mines, blueprints) = save.build_terrain().unwrap();
+
+        assert_eq!(mines, vec![[1, 0, 1]]);
+        assert_eq!(blueprints, vec![[2, 7, 2]]);
+        assert_eq!(loaded.farm_plots, terrain.farm_plots);
+        assert_eq!(loaded.torch_fuel, terrain.torch_fuel);
+
+        for chunk_idx in 0..terrain.chunk_count {
+            let expected = terrain.get_chunk(chunk_idx).unwrap();
+            let actual = loaded.get_chunk(chunk_idx).unwrap();
+
+            for block_idx in 0..expected.block_count {
+                let (a, b) = (expected.get_block(block_idx), actual.get_block(block_idx));
+                assert_eq!(
+                    (a.block, a.light, a.sunlight, a.fluid_level),
+                    (b.block, b.light, b.sunlight, b.fluid_level),
+                    "chunk {} block {}",
+                    chunk_idx,
+                    block_idx
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_header_is_rejected() {
+        let path = temp_save("oversized");
+        let mut w = SaveWriter::new(BufWriter::new(File::create(&path).unwrap()));
+
+        w.write_header().unwrap();
+        for count in [u32::MAX, u32::MAX, 2] {
+            w.write_u32(count).unwrap();
+        }
+        w.write_u32(64).unwrap();
+        w.write_i32(0).unwrap();
+        w.flush().unwrap();
+        drop(w);
+
+        let result = load_world(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
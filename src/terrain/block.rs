@@ -1,11 +1,16 @@
+use crate::{FluidDepth, FLUID_MAX};
+
 #[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
 pub struct Block {
     pub block: BlockType,
     pub light: u8,
     pub sunlight: u8,
+    pub temperature: u8,
     pub partition_id: Option<u32>,
     pub flag_mine: bool,
     pub flag_blueprint: bool,
+    /// Amount of water in the block, 0 for anything but water
+    pub fluid_level: u8,
 }
 
 impl Default for Block {
@@ -14,9 +19,11 @@ impl Default for Block {
             block: BlockType::EMPTY,
             light: 0,
             sunlight: 0,
+            temperature: 0,
             partition_id: None,
             flag_mine: false,
             flag_blueprint: false,
+            fluid_level: 0,
         }
     }
 }
@@ -26,9 +33,11 @@ impl Block {
         block: BlockType::OOB,
         light: 0,
         sunlight: 0,
+        temperature: 0,
         partition_id: None,
         flag_mine: false,
         flag_blueprint: false,
+        fluid_level: 0,
     };
 
     pub fn is_oob(&self) -> bool {
@@ -36,42 +45,68 @@ impl Block {
     }
 
     pub fn is_rendered(&self) -> bool {
+        self.flag_blueprint || self.block.properties().is_filled
+    }
+
+    pub fn is_walkable(&self) -> bool {
+        !self.flag_blueprint && self.block.properties().is_walkable
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.flag_blueprint || self.block == BlockType::EMPTY
+    }
+
+    /// Empty, rubble, or water shallow enough to wade through
+    pub fn is_passable(&self) -> bool {
+        self.is_empty()
+            || self.block == BlockType::RUBBLE
+            || self.fluid_depth() == FluidDepth::Shallow
+    }
+
+    pub fn fluid_depth(&self) -> FluidDepth {
+        FluidDepth::from_level(self.fluid_level)
+    }
+
+    /// Height of the top face, partially filled water and rubble sit lower
+    pub fn top_height(&self) -> f32 {
         if self.flag_blueprint {
-            return true;
+            1.
+        } else if self.block == BlockType::WATER {
+            self.fluid_level as f32 / FLUID_MAX as f32
+        } else if self.block == BlockType::RUBBLE {
+            0.5
+        } else {
+            1.
         }
-
-        !matches!(self.block, BlockType::OOB | BlockType::EMPTY)
     }
 
-    pub fn is_walkable(&self) -> bool {
+    /// Built stairs only, a stair blueprint is walked through like air
+    pub fn stair_direction(&self) -> Option<[i32; 2]> {
         if self.flag_blueprint {
-            return false;
+            None
+        } else {
+            self.block.stair_direction()
         }
+    }
 
-        !matches!(
-            self.block,
-            BlockType::OOB | BlockType::EMPTY | BlockType::LADDER | BlockType::MAGMA
-        )
+    pub fn is_opaque(&self) -> bool {
+        !self.block.properties().is_translucent
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.flag_blueprint || matches!(self.block, BlockType::EMPTY)
+    /// Filled, so it hides the faces of other transparent blocks, but drawn
+    /// blended so the faces behind it show through. Blueprints are drawn
+    /// with their hatch instead.
+    pub fn is_transparent(&self) -> bool {
+        !self.flag_blueprint && self.block.properties().is_transparent
     }
 
-    pub fn is_opaque(&self) -> bool {
-        match self.block {
-            BlockType::OOB => true,
-            BlockType::EMPTY => false,
-            _ => true,
-        }
+    /// Glows through the darkness, ignoring sun and torch light
+    pub fn is_emissive(&self) -> bool {
+        !self.flag_blueprint && self.block.properties().is_emissive
     }
 
     pub fn get_light_level(&self) -> u8 {
-        match self.block {
-            BlockType::LAMP => 12,
-            BlockType::MAGMA => 6,
-            _ => 0,
-        }
+        self.block.get_light_level()
     }
 
     pub fn is_light(&self) -> bool {
@@ -79,33 +114,27 @@ impl Block {
     }
 
     pub fn texture_idx(&self) -> u32 {
-        match self.block {
-            BlockType::DIRT => 1,
-            BlockType::GRASS => 2,
-            BlockType::STONE => 3,
-            BlockType::ASHLAR_LARGE => 4,
-            BlockType::ASHLAR => 5,
-            BlockType::MAGMA => 6,
-            BlockType::LADDER => 7,
-            BlockType::LAMP => 8,
-            _ => 0,
+        self.block.properties().texture_idx
+    }
+
+    /// Picks one of the block type's texture variants from a hash of the
+    /// block position, so the choice is stable across remeshes.
+    pub fn texture_variant(&self, pos: [u32; 3]) -> u32 {
+        let variants = self.block.texture_variants();
+
+        if variants.is_empty() {
+            return self.texture_idx();
         }
+
+        let hash = pos[0].wrapping_mul(73856093)
+            ^ pos[1].wrapping_mul(19349663)
+            ^ pos[2].wrapping_mul(83492791);
+
+        variants[(hash % variants.len() as u32) as usize]
     }
 
     pub fn name(&self) -> String {
-        match self.block {
-            BlockType::OOB => String::from("out of bounds"),
-            BlockType::EMPTY => String::from("empty"),
-            BlockType::DIRT => String::from("dirt"),
-            BlockType::GRASS => String::from("grass"),
-            BlockType::STONE => String::from("stone"),
-            BlockType::LAMP => String::from("lamp"),
-            BlockType::MAGMA => String::from("magma"),
-            BlockType::ASHLAR_LARGE => String::from("ashlar (large)"),
-            BlockType::ASHLAR => String::from("ashlar"),
-            BlockType::LADDER => String::from("ladder"),
-            _ => String::from("unknown"),
-        }
+        self.block.name()
     }
 }
 
@@ -123,38 +152,110 @@ impl BlockType {
     pub const ASHLAR_LARGE: Self = Self(7);
     pub const ASHLAR: Self = Self(8);
     pub const LADDER: Self = Self(9);
-    pub const BLUEPRINT: Self = Self(10);
+    pub const LOG: Self = Self(11);
+    pub const LEAVES: Self = Self(12);
+    pub const CAMPFIRE: Self = Self(13);
+    pub const FARM_SOIL: Self = Self(14);
+    pub const SOIL_SEEDED: Self = Self(15);
+    pub const SOIL_GROWING: Self = Self(16);
+    pub const SOIL_RIPE: Self = Self(17);
+    pub const SAND: Self = Self(18);
+    pub const GRAVEL: Self = Self(19);
+    pub const CLAY: Self = Self(20);
+    pub const WATER: Self = Self(21);
+    pub const WOOD: Self = Self(22);
+    pub const TORCH: Self = Self(23);
+    pub const COAL: Self = Self(24);
+    pub const IRON: Self = Self(25);
+    pub const GOLD: Self = Self(26);
+    pub const GLASS: Self = Self(27);
+    pub const MECHANISM: Self = Self(28);
+    pub const PRESSURE_PLATE: Self = Self(29);
+    pub const PLATE_ACTIVE: Self = Self(30);
+    pub const ROTTEN: Self = Self(31);
+    pub const STAIRS_NORTH: Self = Self(32);
+    pub const STAIRS_SOUTH: Self = Self(33);
+    pub const STAIRS_EAST: Self = Self(34);
+    pub const STAIRS_WEST: Self = Self(35);
+    pub const REBAR_STONE: Self = Self(36);
+    pub const RUBBLE: Self = Self(37);
 }
 
 impl BlockType {
     pub fn get_light_level(&self) -> u8 {
-        match *self {
-            Self::LAMP => 12,
-            Self::MAGMA => 6,
-            _ => 0,
-        }
+        self.properties().light_level
     }
 
     pub fn is_light(&self) -> bool {
         self.get_light_level() > 0
     }
 
-    pub fn name(&self) -> String {
+    /// Atlas tiles this block type can be drawn with. Empty means the block
+    /// only has its base `texture_idx`.
+    pub fn texture_variants(&self) -> &'static [u32] {
+        self.properties().texture_variants
+    }
+
+    /// Temperature this block radiates into the cells next to it
+    pub fn get_heat_level(&self) -> u8 {
+        self.properties().heat_level
+    }
+
+    pub fn is_heat_source(&self) -> bool {
+        self.get_heat_level() > 0
+    }
+
+    pub fn has_gravity(&self) -> bool {
+        self.properties().has_gravity
+    }
+
+    /// Placed blocks an upset colonist might smash
+    pub fn is_furniture(&self) -> bool {
+        matches!(*self, Self::LAMP | Self::CAMPFIRE | Self::TORCH)
+    }
+
+    /// Farm soil in any of its growth stages
+    pub fn is_farm_soil(&self) -> bool {
+        matches!(
+            *self,
+            Self::FARM_SOIL | Self::SOIL_SEEDED | Self::SOIL_GROWING | Self::SOIL_RIPE
+        )
+    }
+
+    /// Horizontal `[x, z]` step a stair climbs towards, north being -z.
+    /// None for anything that isn't a stair.
+    pub fn stair_direction(&self) -> Option<[i32; 2]> {
         match *self {
-            Self::OOB => String::from("out of bounds"),
-            Self::EMPTY => String::from("empty"),
-            Self::DIRT => String::from("dirt"),
-            Self::GRASS => String::from("grass"),
-            Self::STONE => String::from("stone"),
-            Self::LAMP => String::from("lamp"),
-            Self::MAGMA => String::from("magma"),
-            Self::ASHLAR_LARGE => String::from("ashlar (large)"),
-            Self::ASHLAR => String::from("ashlar"),
-            Self::LADDER => String::from("ladder"),
-            Self::BLUEPRINT => String::from("blueprint"),
-            _ => String::from("unknown"),
+            Self::STAIRS_NORTH => Some([0, -1]),
+            Self::STAIRS_SOUTH => Some([0, 1]),
+            Self::STAIRS_EAST => Some([1, 0]),
+            Self::STAIRS_WEST => Some([-1, 0]),
+            _ => None,
         }
     }
+
+    pub fn is_stairs(&self) -> bool {
+        self.stair_direction().is_some()
+    }
+
+    /// How much this block holds up the blocks next to it, see
+    /// `detect_cave_ins`. Loose and passable blocks hold nothing up.
+    pub fn support_points(&self) -> u32 {
+        if *self == Self::REBAR_STONE {
+            2
+        } else if !self.properties().is_filled
+            || self.has_gravity()
+            || matches!(*self, Self::RUBBLE | Self::WATER)
+        {
+            0
+        } else {
+            1
+        }
+    }
+
+    pub fn name(&self) -> String {
+        String::from(self.properties().name)
+    }
 }
 
 impl Default for BlockType {
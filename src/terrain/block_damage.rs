@@ -0,0 +1,95 @@
+use bevy::{
+    ecs::system::{Local, Res, ResMut},
+    time::Time,
+};
+
+use crate::Terrain;
+
+/// Stages of cracks drawn over a damaged block, see `pack_block`
+pub const DAMAGE_STAGES: u32 = 4;
+const DAMAGE_TICK_S: f32 = 1.;
+/// Seconds a damaged block has to be left alone before it is whole again
+const DAMAGE_RESET_S: f32 = 30.;
+
+/// Mining work put into a block, shared by everyone who mines it
+#[derive(Clone, Copy, Default)]
+pub struct BlockDamage {
+    /// Seconds of mining so far, the block breaks at its `mine_time_s`
+    pub work_s: f32,
+    /// Seconds since anyone last worked on it
+    pub idle_s: f32,
+}
+
+fn damage_stage(work_s: f32, mine_time_s: f32) -> u32 {
+    if work_s <= 0. || mine_time_s <= 0. {
+        return 0;
+    }
+
+    let stage = (work_s / mine_time_s * DAMAGE_STAGES as f32) as u32;
+
+    stage.clamp(1, DAMAGE_STAGES - 1)
+}
+
+impl Terrain {
+    /// Put `work_s` seconds of mining into a block. Returns true once the
+    /// work adds up to the block's `mine_time_s`, removing the block is left
+    /// to the caller. The chunk is remeshed whenever the cracks grow.
+    pub fn add_block_damage(&mut self, x: u32, y: u32, z: u32, work_s: f32) -> bool {
+        let mine_time_s = self.get_block(x, y, z).block.properties().mine_time_s;
+        let damage = self.block_damage.entry([x, y, z]).or_default();
+        let previous_stage = damage_stage(damage.work_s, mine_time_s);
+
+        damage.work_s += work_s;
+        damage.idle_s = 0.;
+
+        let work_s = damage.work_s;
+
+        if damage_stage(work_s, mine_time_s) != previous_stage {
+            let [chunk_idx, _] = self.get_block_indexes(x, y, z);
+            self.set_chunk_dirty(chunk_idx, true);
+        }
+
+        work_s >= mine_time_s
+    }
+
+    /// Crack stage to draw on a block, 0 when it is undamaged
+    pub fn get_damage_stage(&self, x: u32, y: u32, z: u32) -> u32 {
+        let Some(damage) = self.block_damage.get(&[x, y, z]) else {
+            return 0;
+        };
+
+        let mine_time_s = self.get_block(x, y, z).block.properties().mine_time_s;
+
+        damage_stage(damage.work_s, mine_time_s)
+    }
+}
+
+/// Heals blocks nobody has worked on for a while, e.g. after their mining
+/// job was cancelled.
+pub fn tick_block_damage(time: Res<Time>, mut terrain: ResMut<Terrain>, mut timer: Local<f32>) {
+    *timer += time.delta_seconds();
+
+    if *timer < DAMAGE_TICK_S {
+        return;
+    }
+
+    let elapsed = *timer;
+    *timer = 0.;
+
+    let mut healed = vec![];
+
+    for (pos, damage) in terrain.block_damage.iter_mut() {
+        damage.idle_s += elapsed;
+
+        if damage.idle_s >= DAMAGE_RESET_S {
+            healed.push(*pos);
+        }
+    }
+
+    for [x, y, z] in healed {
+        terrain.block_damage.remove(&[x, y, z]);
+
+        let [chunk_idx, _] = terrain.get_block_indexes(x, y, z);
+        terrain.set_chunk_dirty(chunk_idx, true);
+    }
+}
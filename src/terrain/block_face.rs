@@ -9,6 +9,15 @@ pub enum BlockFace {
 }
 
 impl BlockFace {
+    pub const ALL: [BlockFace; 6] = [
+        BlockFace::PosX,
+        BlockFace::NegX,
+        BlockFace::PosY,
+        BlockFace::NegY,
+        BlockFace::PosZ,
+        BlockFace::NegZ,
+    ];
+
     pub fn bit(&self) -> u32 {
         match self {
             BlockFace::PosX => 0,
@@ -0,0 +1,264 @@
+use crate::BlockType;
+
+const PALETTE_MAX_TYPES: usize = 16;
+
+/// Stores the block type of every block in a chunk. Chunks with few distinct
+/// types keep a small palette and a 4-bit index per block, falling back to a
+/// direct array once a 17th type shows up. `shrink` packs it again once the
+/// chunk is back down to 16 types.
+#[derive(Clone)]
+pub enum BlockPalette {
+    Packed {
+        types: Vec<BlockType>,
+        /// Blocks using each palette entry, an entry at zero is free to reuse
+        counts: Vec<u32>,
+        indices: Box<[u8]>,
+        /// Blocks stored, `indices` holds two per byte so it can't tell
+        len: usize,
+    },
+    Direct(Box<[BlockType]>),
+}
+
+impl BlockPalette {
+    pub fn new(size: usize, fill: BlockType) -> Self {
+        Self::Packed {
+            types: vec![fill],
+            counts: vec![size as u32],
+            indices: vec![0; size.div_ceil(2)].into_boxed_slice(),
+            len: size,
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> BlockType {
+        match self {
+            Self::Packed { types, indices, .. } => {
+                let local = (indices[idx / 2] >> ((idx % 2) * 4)) & 0xF;
+                types[local as usize]
+            }
+            Self::Direct(blocks) => blocks[idx],
+        }
+    }
+
+    pub fn set(&mut self, idx: usize, value: BlockType) {
+        match self {
+            Self::Packed {
+                types,
+                counts,
+                indices,
+                ..
+            } => {
+                let shift = (idx % 2) * 4;
+                let byte = &mut indices[idx / 2];
+                let previous = ((*byte >> shift) & 0xF) as usize;
+
+                if types[previous] == value {
+                    return;
+                }
+
+                let local = match types.iter().position(|t| *t == value) {
+                    Some(local) => local,
+                    None => match counts.iter().position(|count| *count == 0) {
+                        Some(free) => {
+                            types[free] = value;
+                            free
+                        }
+                        None if types.len() < PALETTE_MAX_TYPES => {
+                            types.push(value);
+                            counts.push(0);
+                            types.len() - 1
+                        }
+                        None => {
+                            self.expand();
+                            self.set(idx, value);
+                            return;
+                        }
+                    },
+                };
+
+                counts[previous] -= 1;
+                counts[local] += 1;
+                *byte = (*byte & !(0xF << shift)) | ((local as u8) << shift);
+            }
+            Self::Direct(blocks) => blocks[idx] = value,
+        }
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        match self {
+            Self::Packed {
+                types,
+                counts,
+                indices,
+                ..
+            } => {
+                std::mem::size_of_val(types.as_slice())
+                    + std::mem::size_of_val(counts.as_slice())
+                    + indices.len()
+            }
+            Self::Direct(blocks) => std::mem::size_of_val(blocks.as_ref()),
+        }
+    }
+
+    /// Drop unused palette entries, and go back to the packed form if a
+    /// direct array is down to 16 types or less. Indices are rewritten, so
+    /// this is meant for idle chunks rather than every edit.
+    pub fn shrink(&mut self) {
+        let len = self.size();
+        let mut types: Vec<BlockType> = vec![];
+        let mut counts: Vec<u32> = vec![];
+        let mut indices = vec![0; len.div_ceil(2)].into_boxed_slice();
+
+        for idx in 0..len {
+            let value = self.get(idx);
+            let local = match types.iter().position(|t| *t == value) {
+                Some(local) => local,
+                None if types.len() < PALETTE_MAX_TYPES => {
+                    types.push(value);
+                    counts.push(0);
+                    types.len() - 1
+                }
+                // too many types to pack, keep what we have
+                None => return,
+            };
+
+            counts[local] += 1;
+            indices[idx / 2] |= (local as u8) << ((idx % 2) * 4);
+        }
+
+        *self = Self::Packed {
+            types,
+            counts,
+            indices,
+            len,
+        };
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Self::Packed { len, .. } => *len,
+            Self::Direct(blocks) => blocks.len(),
+        }
+    }
+
+    fn expand(&mut self) {
+        let blocks = (0..self.size()).map(|idx| self.get(idx)).collect();
+        *self = Self::Direct(blocks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPES: [BlockType; 17] = [
+        BlockType::EMPTY,
+        BlockType::STONE,
+        BlockType::DIRT,
+        BlockType::GRASS,
+        BlockType::LEAVES,
+        BlockType::LOG,
+        BlockType::SAND,
+        BlockType::GRAVEL,
+        BlockType::CLAY,
+        BlockType::WATER,
+        BlockType::MAGMA,
+        BlockType::GLASS,
+        BlockType::TORCH,
+        BlockType::LAMP,
+        BlockType::CAMPFIRE,
+        BlockType::FARM_SOIL,
+        BlockType::RUBBLE,
+    ];
+
+    fn pattern(idx: usize) -> BlockType {
+        TYPES[(idx * 7 + idx / 3) % TYPES.len()]
+    }
+
+    #[test]
+    fn odd_sizes_keep_their_length() {
+        for size in [1, 3, 27, 125] {
+            let mut palette = BlockPalette::new(size, BlockType::STONE);
+            assert_eq!(palette.size(), size);
+
+            palette.expand();
+            assert_eq!(palette.size(), size);
+        }
+    }
+
+    #[test]
+    fn pack_expand_round_trip() {
+        let size = 125;
+        let mut palette = BlockPalette::new(size, BlockType::EMPTY);
+
+        for idx in 0..size {
+            palette.set(idx, pattern(idx));
+        }
+
+        // a 17th type doesn't fit in 4 bits
+        assert!(matches!(palette, BlockPalette::Direct(_)));
+        assert_eq!(palette.size(), size);
+
+        // drop back to 16 types and pack again
+        for idx in 0..size {
+            if palette.get(idx) == BlockType::RUBBLE {
+                palette.set(idx, BlockType::STONE);
+            }
+        }
+
+        let expected = (0..size).map(|idx| palette.get(idx)).collect::<Vec<_>>();
+        palette.shrink();
+
+        assert!(matches!(palette, BlockPalette::Packed { .. }));
+        assert_eq!(palette.size(), size);
+        assert_eq!(
+            (0..size).map(|idx| palette.get(idx)).collect::<Vec<_>>(),
+            expected
+        );
+
+        palette.expand();
+        assert_eq!(
+            (0..size).map(|idx| palette.get(idx)).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn removed_types_free_their_entry() {
+        let size = 27;
+        let mut palette = BlockPalette::new(size, BlockType::EMPTY);
+
+        // cycle far more than 16 types through a single block
+        for value in TYPES.iter().cycle().take(100) {
+            palette.set(13, *value);
+        }
+
+        assert!(matches!(palette, BlockPalette::Packed { .. }));
+        assert_eq!(palette.get(13), TYPES[99 % TYPES.len()]);
+        assert_eq!(palette.get(12), BlockType::EMPTY);
+
+        let BlockPalette::Packed { counts, .. } = &palette else {
+            unreachable!();
+        };
+        assert_eq!(counts.iter().sum::<u32>(), size as u32);
+    }
+
+    #[test]
+    fn shrink_drops_unused_types() {
+        let size = 64;
+        let mut palette = BlockPalette::new(size, BlockType::STONE);
+
+        for (idx, value) in TYPES.iter().take(10).enumerate() {
+            palette.set(idx, *value);
+        }
+        for idx in 0..10 {
+            palette.set(idx, BlockType::STONE);
+        }
+
+        palette.shrink();
+
+        let BlockPalette::Packed { types, .. } = &palette else {
+            unreachable!();
+        };
+        assert_eq!(types, &vec![BlockType::STONE]);
+    }
+}
@@ -0,0 +1,462 @@
+use crate::{colonists::ItemTag, BlockType};
+
+/// Everything about a block type that doesn't change per block.
+pub struct BlockProperties {
+    pub name: &'static str,
+    /// Atlas tile in textures/comfy.png
+    pub texture_idx: u32,
+    /// Extra atlas tiles the block can be drawn with, see `Block::texture_variant`
+    pub texture_variants: &'static [u32],
+    /// Takes up space, i.e. not EMPTY or OOB
+    pub is_filled: bool,
+    pub is_walkable: bool,
+    /// Lets light through even though it is filled
+    pub is_translucent: bool,
+    /// Drawn see-through in the blended pass, see `Block::is_transparent`
+    pub is_transparent: bool,
+    /// Sunlight loses strength going down through it, instead of passing
+    /// straight through at full strength
+    pub dims_sunlight: bool,
+    /// Drawn at full brightness no matter the light around it
+    pub is_emissive: bool,
+    /// Hurts whatever stands next to it, pathing steers around it
+    pub is_hazard: bool,
+    /// Falls when there is nothing underneath, see `tick_gravity_blocks`
+    pub has_gravity: bool,
+    /// Living plant matter that rot spreads into, see `tick_rot`
+    pub is_organic: bool,
+    /// Seconds it takes to mine the block
+    pub mine_time_s: f32,
+    /// Item dropped when the block is mined, and the odds of it dropping
+    pub drops: Option<(ItemTag, f32)>,
+    pub light_level: u8,
+    /// Temperature this block radiates into the cells next to it
+    pub heat_level: u8,
+}
+
+const SOLID: BlockProperties = BlockProperties {
+    name: "unknown",
+    texture_idx: 0,
+    texture_variants: &[],
+    is_filled: true,
+    is_walkable: true,
+    is_translucent: false,
+    is_transparent: false,
+    dims_sunlight: false,
+    is_emissive: false,
+    is_hazard: false,
+    has_gravity: false,
+    is_organic: false,
+    mine_time_s: 1.,
+    drops: None,
+    light_level: 0,
+    heat_level: 0,
+};
+
+const UNKNOWN: BlockProperties = SOLID;
+
+/// Indexed by `BlockType`
+const BLOCK_PROPERTIES: [BlockProperties; 38] = [
+    // OOB
+    BlockProperties {
+        name: "out of bounds",
+        is_filled: false,
+        is_walkable: false,
+        ..SOLID
+    },
+    // EMPTY
+    BlockProperties {
+        name: "empty",
+        is_filled: false,
+        is_walkable: false,
+        is_translucent: true,
+        ..SOLID
+    },
+    // DIRT
+    BlockProperties {
+        name: "dirt",
+        texture_idx: 1,
+        texture_variants: &[1, 52],
+        mine_time_s: 0.5,
+        ..SOLID
+    },
+    // STONE
+    BlockProperties {
+        name: "stone",
+        texture_idx: 3,
+        texture_variants: &[3, 58, 59],
+        drops: Some((ItemTag::Stone, 1.)),
+        ..SOLID
+    },
+    // GRASS
+    BlockProperties {
+        name: "grass",
+        texture_idx: 2,
+        is_organic: true,
+        mine_time_s: 0.5,
+        ..SOLID
+    },
+    // LAMP
+    BlockProperties {
+        name: "lamp",
+        texture_idx: 8,
+        light_level: 12,
+        ..SOLID
+    },
+    // MAGMA
+    BlockProperties {
+        name: "magma",
+        texture_idx: 6,
+        is_walkable: false,
+        is_emissive: true,
+        is_hazard: true,
+        mine_time_s: 2.,
+        light_level: 6,
+        ..SOLID
+    },
+    // ASHLAR_LARGE
+    BlockProperties {
+        name: "ashlar (large)",
+        texture_idx: 4,
+        mine_time_s: 1.5,
+        ..SOLID
+    },
+    // ASHLAR
+    BlockProperties {
+        name: "ashlar",
+        texture_idx: 5,
+        mine_time_s: 1.5,
+        ..SOLID
+    },
+    // LADDER
+    BlockProperties {
+        name: "ladder",
+        texture_idx: 7,
+        is_walkable: false,
+        mine_time_s: 0.5,
+        ..SOLID
+    },
+    // 10, unused, blueprints are a block flag
+    BlockProperties {
+        name: "blueprint",
+        ..SOLID
+    },
+    // LOG
+    BlockProperties {
+        name: "log",
+        texture_idx: 9,
+        is_organic: true,
+        drops: Some((ItemTag::Wood, 1.)),
+        ..SOLID
+    },
+    // LEAVES
+    BlockProperties {
+        name: "leaves",
+        texture_idx: 10,
+        is_translucent: true,
+        dims_sunlight: true,
+        is_organic: true,
+        mine_time_s: 0.25,
+        ..SOLID
+    },
+    // CAMPFIRE
+    BlockProperties {
+        name: "campfire",
+        texture_idx: 11,
+        light_level: 12,
+        heat_level: 8,
+        ..SOLID
+    },
+    // FARM_SOIL
+    BlockProperties {
+        name: "farm soil",
+        texture_idx: 52,
+        mine_time_s: 0.5,
+        ..SOLID
+    },
+    // SOIL_SEEDED
+    BlockProperties {
+        name: "farm soil (seeded)",
+        texture_idx: 52,
+        mine_time_s: 0.5,
+        ..SOLID
+    },
+    // SOIL_GROWING
+    BlockProperties {
+        name: "farm soil (growing)",
+        texture_idx: 2,
+        mine_time_s: 0.5,
+        ..SOLID
+    },
+    // SOIL_RIPE
+    BlockProperties {
+        name: "farm soil (ripe)",
+        texture_idx: 10,
+        mine_time_s: 0.5,
+        ..SOLID
+    },
+    // SAND
+    BlockProperties {
+        name: "sand",
+        texture_idx: 27,
+        has_gravity: true,
+        mine_time_s: 0.4,
+        ..SOLID
+    },
+    // GRAVEL
+    BlockProperties {
+        name: "gravel",
+        texture_idx: 26,
+        has_gravity: true,
+        mine_time_s: 0.6,
+        drops: Some((ItemTag::Stone, 0.1)),
+        ..SOLID
+    },
+    // CLAY
+    BlockProperties {
+        name: "clay",
+        texture_idx: 47,
+        mine_time_s: 0.6,
+        ..SOLID
+    },
+    // WATER
+    BlockProperties {
+        name: "water",
+        texture_idx: 29,
+        is_walkable: false,
+        is_translucent: true,
+        dims_sunlight: true,
+        ..SOLID
+    },
+    // WOOD
+    BlockProperties {
+        name: "wood",
+        texture_idx: 13,
+        mine_time_s: 0.75,
+        drops: Some((ItemTag::Wood, 1.)),
+        ..SOLID
+    },
+    // TORCH
+    BlockProperties {
+        name: "torch",
+        texture_idx: 8,
+        is_walkable: false,
+        is_translucent: true,
+        mine_time_s: 0.25,
+        light_level: 10,
+        ..SOLID
+    },
+    // COAL
+    BlockProperties {
+        name: "coal",
+        texture_idx: 56,
+        mine_time_s: 1.25,
+        drops: Some((ItemTag::Coal, 1.)),
+        ..SOLID
+    },
+    // IRON
+    BlockProperties {
+        name: "iron",
+        texture_idx: 57,
+        mine_time_s: 2.,
+        drops: Some((ItemTag::IronOre, 1.)),
+        ..SOLID
+    },
+    // GOLD
+    BlockProperties {
+        name: "gold",
+        texture_idx: 34,
+        mine_time_s: 2.5,
+        drops: Some((ItemTag::GoldOre, 1.)),
+        ..SOLID
+    },
+    // GLASS
+    BlockProperties {
+        name: "glass",
+        texture_idx: 28,
+        is_translucent: true,
+        is_transparent: true,
+        mine_time_s: 0.5,
+        ..SOLID
+    },
+    // MECHANISM
+    BlockProperties {
+        name: "mechanism",
+        texture_idx: 13,
+        mine_time_s: 1.5,
+        ..SOLID
+    },
+    // PRESSURE_PLATE
+    BlockProperties {
+        name: "pressure plate",
+        texture_idx: 5,
+        mine_time_s: 1.,
+        ..SOLID
+    },
+    // PLATE_ACTIVE
+    BlockProperties {
+        name: "pressure plate (active)",
+        texture_idx: 4,
+        mine_time_s: 1.,
+        ..SOLID
+    },
+    // ROTTEN
+    BlockProperties {
+        name: "rotten",
+        texture_idx: 52,
+        mine_time_s: 0.5,
+        ..SOLID
+    },
+    // STAIRS_NORTH
+    BlockProperties {
+        name: "stairs (north)",
+        texture_idx: 5,
+        is_translucent: true,
+        dims_sunlight: true,
+        mine_time_s: 1.,
+        ..SOLID
+    },
+    // STAIRS_SOUTH
+    BlockProperties {
+        name: "stairs (south)",
+        texture_idx: 5,
+        is_translucent: true,
+        dims_sunlight: true,
+        mine_time_s: 1.,
+        ..SOLID
+    },
+    // STAIRS_EAST
+    BlockProperties {
+        name: "stairs (east)",
+        texture_idx: 5,
+        is_translucent: true,
+        dims_sunlight: true,
+        mine_time_s: 1.,
+        ..SOLID
+    },
+    // STAIRS_WEST
+    BlockProperties {
+        name: "stairs (west)",
+        texture_idx: 5,
+        is_translucent: true,
+        dims_sunlight: true,
+        mine_time_s: 1.,
+        ..SOLID
+    },
+    // REBAR_STONE
+    BlockProperties {
+        name: "rebar stone",
+        texture_idx: 4,
+        mine_time_s: 2.,
+        drops: Some((ItemTag::Stone, 0.25)),
+        ..SOLID
+    },
+    // RUBBLE
+    BlockProperties {
+        name: "rubble",
+        texture_idx: 3,
+        texture_variants: &[3, 58, 59],
+        is_translucent: true,
+        dims_sunlight: true,
+        mine_time_s: 0.5,
+        ..SOLID
+    },
+];
+
+impl BlockType {
+    pub fn properties(&self) -> &'static BlockProperties {
+        BLOCK_PROPERTIES.get(self.0 as usize).unwrap_or(&UNKNOWN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        colonists::{get_block_flags, NavigationFlags},
+        Block, Terrain,
+    };
+
+    use super::*;
+
+    fn block(block_type: BlockType) -> Block {
+        Block {
+            block: block_type,
+            ..Block::default()
+        }
+    }
+
+    #[test]
+    fn rows_line_up_with_block_types() {
+        let named = [
+            (BlockType::OOB, "out of bounds"),
+            (BlockType::EMPTY, "empty"),
+            (BlockType::STONE, "stone"),
+            (BlockType::LOG, "log"),
+            (BlockType::SAND, "sand"),
+            (BlockType::WATER, "water"),
+            (BlockType::GOLD, "gold"),
+            (BlockType::RUBBLE, "rubble"),
+        ];
+
+        for (block_type, name) in named {
+            assert_eq!(block_type.name(), name);
+        }
+
+        assert_eq!(BLOCK_PROPERTIES.len(), BlockType::RUBBLE.0 as usize + 1);
+        assert_eq!(BlockType(200).name(), "unknown");
+    }
+
+    #[test]
+    fn block_helpers_read_the_table() {
+        assert!(!block(BlockType::EMPTY).is_rendered());
+        assert!(!block(BlockType::OOB).is_rendered());
+
+        let sand = block(BlockType::SAND);
+        assert!(sand.is_rendered() && sand.is_walkable() && sand.is_opaque());
+        assert!(BlockType::SAND.has_gravity());
+
+        let water = block(BlockType::WATER);
+        assert!(water.is_rendered() && !water.is_walkable() && !water.is_opaque());
+
+        assert!(block(BlockType::GLASS).is_transparent());
+        assert!(!block(BlockType::STONE).is_transparent());
+        assert_eq!(BlockType::MAGMA.get_light_level(), 6);
+        assert!(block(BlockType::MAGMA).is_emissive());
+
+        // a blueprint is drawn with its hatch and walked through like air
+        let blueprint = Block {
+            flag_blueprint: true,
+            ..block(BlockType::GLASS)
+        };
+        assert!(blueprint.is_rendered() && !blueprint.is_transparent());
+    }
+
+    #[test]
+    fn mine_time_decides_when_a_block_breaks() {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.set_block(1, 1, 1, BlockType::SAND);
+        terrain.set_block(2, 1, 1, BlockType::GOLD);
+
+        assert!(!terrain.add_block_damage(1, 1, 1, 0.3));
+        assert!(terrain.add_block_damage(1, 1, 1, 0.1));
+
+        assert!(!terrain.add_block_damage(2, 1, 1, 2.4));
+        assert!(terrain.add_block_damage(2, 1, 1, 0.1));
+    }
+
+    #[test]
+    fn block_flags_follow_walkable_and_hazard() {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.set_block(1, 0, 1, BlockType::SAND);
+        terrain.set_block(3, 0, 1, BlockType::WATER);
+        terrain.set_block(5, 0, 1, BlockType::SAND);
+        terrain.set_block(6, 1, 1, BlockType::MAGMA);
+
+        let above_sand = get_block_flags(&terrain, 1, 1, 1);
+        assert!(above_sand != NavigationFlags::NONE);
+        assert!(!above_sand.contains(NavigationFlags::HAZARD));
+
+        assert_eq!(get_block_flags(&terrain, 3, 1, 1), NavigationFlags::NONE);
+        assert!(get_block_flags(&terrain, 5, 1, 1).contains(NavigationFlags::HAZARD));
+    }
+}
@@ -0,0 +1,243 @@
+use bevy::{
+    ecs::{
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        system::{Commands, Query, Res, ResMut},
+    },
+    transform::components::Transform,
+};
+
+use crate::{
+    colonists::{spawn_mine_area, DamagedByBlockEvent, Health},
+    BlockChangedEvent, BlockType, Terrain,
+};
+
+/// Support points a block needs from the blocks beside it to hang on its own
+const SUPPORT_NEEDED: u32 = 2;
+/// A column only caves in with more than this many unsupported blocks
+const CAVE_IN_THRESHOLD: u32 = 2;
+/// Damage dealt to anything standing under a collapsing column
+const CAVE_IN_DAMAGE: f32 = 40.;
+
+/// The column above `pos` lost its footing. The `height` blocks above it
+/// drop one block down, into `pos`, and turn to rubble.
+#[derive(Event)]
+pub struct CaveInEvent {
+    pub pos: [u32; 3],
+    pub height: u32,
+}
+
+/// Checks the column above every block that was just cleared. Blocks held
+/// up by their sides keep the rest of the column above them in place, see
+/// `BlockType::support_points`.
+pub fn detect_cave_ins(
+    terrain: Res<Terrain>,
+    mut ev_block_changed: EventReader<BlockChangedEvent>,
+    mut ev_cave_in: EventWriter<CaveInEvent>,
+) {
+    for ev in ev_block_changed.read() {
+        if ev.value != BlockType::EMPTY {
+            continue;
+        }
+
+        let [x, y, z] = ev.pos;
+        let mut height = 0;
+
+        for (cy, block) in terrain.get_column(x, z).skip(y as usize + 1) {
+            if block.is_empty() || block.block.support_points() == 0 {
+                break;
+            }
+
+            let support = [[-1, 0], [1, 0], [0, -1], [0, 1]]
+                .iter()
+                .map(|[dx, dz]| {
+                    terrain
+                        .get_block_i32(x as i32 + dx, cy as i32, z as i32 + dz)
+                        .block
+                        .support_points()
+                })
+                .sum::<u32>();
+
+            if support >= SUPPORT_NEEDED {
+                break;
+            }
+
+            height += 1;
+        }
+
+        if height > CAVE_IN_THRESHOLD {
+            ev_cave_in.send(CaveInEvent {
+                pos: ev.pos,
+                height,
+            });
+        }
+    }
+}
+
+/// Drops caved in columns as rubble, hurts whatever was standing under them,
+/// and flags the rubble so colonists come clear it. Nobody asked for the
+/// flags, so unlike a designation they stay out of the edit history.
+pub fn on_cave_in(
+    mut cmd: Commands,
+    mut terrain: ResMut<Terrain>,
+    mut q_health: Query<(Entity, &Transform, &mut Health)>,
+    mut ev_cave_in: EventReader<CaveInEvent>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+    mut ev_damaged: EventWriter<DamagedByBlockEvent>,
+) {
+    for ev in ev_cave_in.read() {
+        let [x, y, z] = ev.pos;
+        let top = y + ev.height;
+
+        println!("Cave-in at {:?}, {} blocks fell!", ev.pos, ev.height);
+
+        let changes = terrain.set_block_column(x, z, y..top, BlockType::RUBBLE);
+        ev_block_changed.send_batch(changes.into_iter().map(BlockChangedEvent::from));
+
+        let change = terrain.set_block(x, top, z, BlockType::EMPTY);
+        terrain.set_flag_mine(x, top, z, false);
+        ev_block_changed.send(change.into());
+
+        for (entity, transform, mut health) in q_health.iter_mut() {
+            let pos = transform.translation;
+
+            if pos.x as u32 != x || pos.z as u32 != z || (pos.y as u32) < y || pos.y as u32 > top {
+                continue;
+            }
+
+            health.current -= CAVE_IN_DAMAGE;
+            ev_damaged.send(DamagedByBlockEvent {
+                entity,
+                block: BlockType::RUBBLE,
+                amount: CAVE_IN_DAMAGE,
+            });
+        }
+
+        let (min, max) = ([x, y, z], [x, top - 1, z]);
+        let positions = terrain.set_mine_flag_region(min, max, true);
+        spawn_mine_area(&mut cmd, min, max, positions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{
+        event::Events,
+        schedule::{IntoSystemConfigs, Schedule},
+        world::World,
+    };
+
+    use crate::colonists::MineAreaQueue;
+
+    use super::*;
+
+    const COLUMN: [u32; 2] = [3, 3];
+
+    /// A stone floor with a free standing stone column on it, five high
+    fn column_world() -> World {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+
+        for x in 0..8 {
+            for z in 0..8 {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+            }
+        }
+
+        for y in 1..6 {
+            terrain.set_block(COLUMN[0], y, COLUMN[1], BlockType::STONE);
+        }
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<Events<BlockChangedEvent>>();
+        world.init_resource::<Events<CaveInEvent>>();
+        world.init_resource::<Events<DamagedByBlockEvent>>();
+        world
+    }
+
+    /// Mine the bottom block of the column and let it settle
+    fn mine_column_base(world: &mut World) {
+        let [x, z] = COLUMN;
+        let change = world
+            .resource_mut::<Terrain>()
+            .set_block(x, 1, z, BlockType::EMPTY);
+        world.send_event(BlockChangedEvent::from(change));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((detect_cave_ins, on_cave_in).chain());
+        schedule.run(world);
+    }
+
+    fn column(world: &World) -> Vec<BlockType> {
+        let terrain = world.resource::<Terrain>();
+        (1..6)
+            .map(|y| terrain.get_block(COLUMN[0], y, COLUMN[1]).block)
+            .collect()
+    }
+
+    #[test]
+    fn unsupported_column_caves_in() {
+        let mut world = column_world();
+        let colonist = world
+            .spawn((Transform::from_xyz(3.5, 1., 3.5), Health::new(100.)))
+            .id();
+        let bystander = world
+            .spawn((Transform::from_xyz(5.5, 1., 3.5), Health::new(100.)))
+            .id();
+
+        mine_column_base(&mut world);
+
+        assert_eq!(
+            column(&world),
+            [
+                BlockType::RUBBLE,
+                BlockType::RUBBLE,
+                BlockType::RUBBLE,
+                BlockType::RUBBLE,
+                BlockType::EMPTY
+            ]
+        );
+        assert_eq!(
+            world.get::<Health>(colonist).unwrap().current,
+            100. - CAVE_IN_DAMAGE
+        );
+        assert_eq!(world.get::<Health>(bystander).unwrap().current, 100.);
+
+        // the rubble is flagged for clearing, but there's nothing to undo
+        let terrain = world.resource::<Terrain>();
+        assert!((1..5).all(|y| terrain.get_block(COLUMN[0], y, COLUMN[1]).flag_mine));
+        assert!(world
+            .resource_mut::<Terrain>()
+            .take_recorded_batches()
+            .is_empty());
+
+        let mut q_areas = world.query::<&MineAreaQueue>();
+        let areas = q_areas.iter(&world).collect::<Vec<_>>();
+        assert_eq!(areas.len(), 1);
+        assert_eq!(areas[0].pending.len(), 4);
+    }
+
+    #[test]
+    fn rebar_holds_the_column_up() {
+        let mut world = column_world();
+
+        {
+            let mut terrain = world.resource_mut::<Terrain>();
+            terrain.set_block(COLUMN[0] + 1, 2, COLUMN[1], BlockType::REBAR_STONE);
+        }
+
+        mine_column_base(&mut world);
+
+        assert_eq!(
+            column(&world),
+            [
+                BlockType::EMPTY,
+                BlockType::STONE,
+                BlockType::STONE,
+                BlockType::STONE,
+                BlockType::STONE
+            ]
+        );
+        assert!(world.resource::<Events<CaveInEvent>>().is_empty());
+    }
+}
@@ -1,7 +1,7 @@
-use bevy::{asset::Handle, ecs::component::Component, render::mesh::Mesh};
+use bevy::{asset::Handle, ecs::component::Component, render::mesh::Mesh, utils::HashMap};
 use ndshape::{AbstractShape, RuntimeShape};
 
-use crate::{Block, BlockType};
+use crate::{Block, BlockPalette, BlockType, FLUID_MAX};
 
 #[derive(Component)]
 pub struct Chunk {
@@ -9,14 +9,126 @@ pub struct Chunk {
     pub world_x: u32,
     pub world_y: u32,
     pub world_z: u32,
+    /// Faces in the last built mesh, used to size buffers for the next rebuild.
+    pub face_count: u32,
+    /// Block stride the mesh was built at, 1 is full detail.
+    pub lod: u32,
+    /// Set when the mesh has to be rebuilt without the blocks changing, e.g.
+    /// when the chunk switches LOD.
+    pub needs_remesh: bool,
     pub mesh_handle: Handle<Mesh>,
+    /// Mesh of the transparent blocks, drawn blended by a child entity
+    pub transparent_mesh_handle: Handle<Mesh>,
+}
+
+/// Per-block state other than the block type, which lives in the palette.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+struct BlockData {
+    light: u8,
+    sunlight: u8,
+    temperature: u8,
+    partition_id: Option<u32>,
+    flag_mine: bool,
+    flag_blueprint: bool,
+    fluid_level: u8,
+}
+
+/// Storage for the per-block state. Chunks that are being edited keep one
+/// `BlockData` per block, idle chunks are compacted to their unique states
+/// and a 16-bit index per block. Writing to a compacted chunk expands it.
+#[derive(Clone)]
+enum BlockDataStore {
+    Dense(Box<[BlockData]>),
+    Paletted {
+        states: Vec<BlockData>,
+        indices: Box<[u16]>,
+    },
+}
+
+impl BlockDataStore {
+    fn get(&self, idx: usize) -> Option<&BlockData> {
+        match self {
+            Self::Dense(blocks) => blocks.get(idx),
+            Self::Paletted { states, indices } => {
+                indices.get(idx).map(|local| &states[*local as usize])
+            }
+        }
+    }
+
+    fn get_mut(&mut self, idx: usize) -> &mut BlockData {
+        self.expand();
+
+        let Self::Dense(blocks) = self else {
+            unreachable!("expanded above");
+        };
+
+        &mut blocks[idx]
+    }
+
+    fn expand(&mut self) {
+        let Self::Paletted { states, indices } = self else {
+            return;
+        };
+
+        let blocks = indices
+            .iter()
+            .map(|local| states[*local as usize])
+            .collect::<Box<[_]>>();
+
+        *self = Self::Dense(blocks);
+    }
+
+    /// Returns false if the chunk has too many unique states to index.
+    fn compact(&mut self) -> bool {
+        let Self::Dense(blocks) = self else {
+            return true;
+        };
+
+        let mut states = vec![];
+        let mut lookup = HashMap::new();
+        let mut indices = Vec::with_capacity(blocks.len());
+
+        for data in blocks.iter() {
+            let local = *lookup.entry(*data).or_insert_with(|| {
+                states.push(*data);
+                states.len() - 1
+            });
+
+            if local > u16::MAX as usize {
+                return false;
+            }
+
+            indices.push(local as u16);
+        }
+
+        *self = Self::Paletted {
+            states,
+            indices: indices.into_boxed_slice(),
+        };
+
+        true
+    }
+
+    fn memory_bytes(&self) -> usize {
+        match self {
+            Self::Dense(blocks) => std::mem::size_of_val(blocks.as_ref()),
+            Self::Paletted { states, indices } => {
+                std::mem::size_of_val(states.as_slice()) + std::mem::size_of_val(indices.as_ref())
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct BlockBuffer {
     pub shape: RuntimeShape<u32, 3>,
-    pub blocks: Box<[Block]>,
+    palette: BlockPalette,
+    blocks: BlockDataStore,
     pub block_count: u32,
+    /// Number of blocks that are neither EMPTY nor OOB.
+    pub filled_count: u32,
+    /// Number of blocks of each type, types with no blocks are left out.
+    type_counts: HashMap<BlockType, u32>,
     pub chunk_idx: u32,
     pub chunk_size: u32,
     pub world_x: u32,
@@ -28,8 +140,13 @@ pub struct BlockBuffer {
 impl BlockBuffer {
     pub fn new(shape: RuntimeShape<u32, 3>) -> Self {
         Self {
-            blocks: vec![Block::default(); shape.size() as usize].into_boxed_slice(),
+            palette: BlockPalette::new(shape.size() as usize, BlockType::EMPTY),
+            blocks: BlockDataStore::Dense(
+                vec![BlockData::default(); shape.size() as usize].into_boxed_slice(),
+            ),
             block_count: shape.size(),
+            filled_count: 0,
+            type_counts: HashMap::from([(BlockType::EMPTY, shape.size())]),
             shape,
             chunk_idx: 0,
             chunk_size: 0,
@@ -41,24 +158,122 @@ impl BlockBuffer {
     }
 
     pub fn set_block_type(&mut self, block_idx: u32, value: BlockType) {
-        self.blocks[block_idx as usize].block = value;
+        let previous = self.palette.get(block_idx as usize);
+        let was_filled = is_filled_type(previous);
+        let is_filled = is_filled_type(value);
+
+        if previous != value {
+            if let Some(count) = self.type_counts.get_mut(&previous) {
+                *count -= 1;
+
+                if *count == 0 {
+                    self.type_counts.remove(&previous);
+                }
+            }
+
+            *self.type_counts.entry(value).or_insert(0) += 1;
+        }
+
+        if was_filled && !is_filled {
+            self.filled_count -= 1;
+        } else if !was_filled && is_filled {
+            self.filled_count += 1;
+        }
+
+        self.palette.set(block_idx as usize, value);
+
+        // new water starts out full, anything else holds none
+        let fluid_level = self.get_fluid_level(block_idx);
+        let new_level = match value {
+            BlockType::WATER if fluid_level == 0 => FLUID_MAX,
+            BlockType::WATER => fluid_level,
+            _ => 0,
+        };
+
+        if new_level != fluid_level {
+            self.blocks.get_mut(block_idx as usize).fluid_level = new_level;
+        }
+
         self.is_dirty = true;
     }
 
     pub fn get_block(&self, block_idx: u32) -> Block {
-        if let Some(block) = self.blocks.get(block_idx as usize) {
-            return *block;
+        if let Some(data) = self.blocks.get(block_idx as usize) {
+            return Block {
+                block: self.palette.get(block_idx as usize),
+                light: data.light,
+                sunlight: data.sunlight,
+                temperature: data.temperature,
+                partition_id: data.partition_id,
+                flag_mine: data.flag_mine,
+                flag_blueprint: data.flag_blueprint,
+                fluid_level: data.fluid_level,
+            };
         }
 
         Block::OOB
     }
 
+    /// Position of the block within this chunk, or `None` if the index is
+    /// past the end of the buffer.
+    pub fn get_block_xyz(&self, block_idx: u32) -> Option<[u32; 3]> {
+        if block_idx >= self.block_count {
+            return None;
+        }
+
+        Some(self.shape.delinearize(block_idx))
+    }
+
+    /// Switch idle block state to the paletted form and drop block types the
+    /// chunk no longer uses. Returns false if the chunk stays dense.
+    pub fn compact(&mut self) -> bool {
+        self.palette.shrink();
+        self.blocks.compact()
+    }
+
+    pub fn is_compact(&self) -> bool {
+        matches!(self.blocks, BlockDataStore::Paletted { .. })
+    }
+
+    /// Bytes held by the buffer, its block types, per-block state and
+    /// type counts
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.palette.memory_bytes()
+            + self.blocks.memory_bytes()
+            + self.type_counts.capacity() * std::mem::size_of::<(BlockType, u32)>()
+    }
+
+    /// `(block_idx, old, new)` for every block that differs between this
+    /// buffer and `other`, the newer one. Both are expected to have the same
+    /// shape, blocks past the end of the smaller one are left out.
+    pub fn diff(&self, other: &BlockBuffer) -> Vec<(u32, Block, Block)> {
+        (0..self.block_count.min(other.block_count))
+            .filter_map(|block_idx| {
+                let old = self.get_block(block_idx);
+                let new = other.get_block(block_idx);
+
+                (old != new).then_some((block_idx, old, new))
+            })
+            .collect()
+    }
+
+    pub fn count_blocks_of_type(&self, block_type: BlockType) -> u32 {
+        self.type_counts.get(&block_type).copied().unwrap_or(0)
+    }
+
+    pub fn has_rendered_blocks(&self) -> bool {
+        (0..self.block_count).any(|block_idx| self.get_block(block_idx).is_rendered())
+    }
+
     pub fn set_partition_id(&mut self, block_idx: u32, value: u32) {
-        self.blocks[block_idx as usize].partition_id = Some(value);
+        self.blocks.get_mut(block_idx as usize).partition_id = Some(value);
     }
 
     pub fn unset_partition_id(&mut self, block_idx: u32) {
-        self.blocks[block_idx as usize].partition_id = None;
+        if self.get_partition_id(block_idx).is_some() {
+            self.blocks.get_mut(block_idx as usize).partition_id = None;
+        }
     }
 
     pub fn get_partition_id(&self, block_idx: u32) -> Option<u32> {
@@ -68,46 +283,87 @@ impl BlockBuffer {
     }
 
     pub fn get_sunlight(&self, block_idx: u32) -> u8 {
-        self.get_block(block_idx).sunlight
+        self.blocks
+            .get(block_idx as usize)
+            .map_or(0, |data| data.sunlight)
     }
 
     pub fn get_torchlight(&self, block_idx: u32) -> u8 {
-        self.get_block(block_idx).light
+        self.blocks
+            .get(block_idx as usize)
+            .map_or(0, |data| data.light)
+    }
+
+    pub fn get_temperature(&self, block_idx: u32) -> u8 {
+        self.blocks
+            .get(block_idx as usize)
+            .map_or(0, |data| data.temperature)
+    }
+
+    pub fn set_temperature(&mut self, block_idx: u32, value: u8) {
+        if self.get_temperature(block_idx) != value {
+            self.blocks.get_mut(block_idx as usize).temperature = value;
+        }
     }
 
     pub fn set_flag_blueprint(&mut self, block_idx: u32, value: bool) -> bool {
-        let block = self.blocks[block_idx as usize];
-        let is_changed = block.flag_blueprint != value;
-        self.blocks[block_idx as usize].flag_blueprint = value;
+        let is_changed = self.blocks.get(block_idx as usize).unwrap().flag_blueprint != value;
         if is_changed {
+            self.blocks.get_mut(block_idx as usize).flag_blueprint = value;
             self.is_dirty = true;
         }
         is_changed
     }
 
     pub fn set_flag_mine(&mut self, block_idx: u32, value: bool) -> bool {
-        let block = self.blocks[block_idx as usize];
-        let is_changed = block.flag_mine != value;
-        self.blocks[block_idx as usize].flag_mine = value;
+        let is_changed = self.blocks.get(block_idx as usize).unwrap().flag_mine != value;
         if is_changed {
+            self.blocks.get_mut(block_idx as usize).flag_mine = value;
+            self.is_dirty = true;
+        }
+        is_changed
+    }
+
+    pub fn get_fluid_level(&self, block_idx: u32) -> u8 {
+        self.blocks
+            .get(block_idx as usize)
+            .map_or(0, |data| data.fluid_level)
+    }
+
+    pub fn set_fluid_level(&mut self, block_idx: u32, value: u8) -> bool {
+        let is_changed = self.blocks.get(block_idx as usize).unwrap().fluid_level != value;
+        if is_changed {
+            self.blocks.get_mut(block_idx as usize).fluid_level = value;
             self.is_dirty = true;
         }
         is_changed
     }
 
     #[inline]
-    pub fn set_sunlight(&mut self, block_idx: u32, value: u8) {
-        self.blocks[block_idx as usize].sunlight = value;
-        self.is_dirty = true;
+    pub fn set_sunlight(&mut self, block_idx: u32, value: u8) -> bool {
+        let is_changed = self.blocks.get(block_idx as usize).unwrap().sunlight != value;
+        if is_changed {
+            self.blocks.get_mut(block_idx as usize).sunlight = value;
+            self.is_dirty = true;
+        }
+        is_changed
     }
 
     #[inline]
-    pub fn set_torchlight(&mut self, block_idx: u32, value: u8) {
-        self.blocks[block_idx as usize].light = value;
-        self.is_dirty = true;
+    pub fn set_torchlight(&mut self, block_idx: u32, value: u8) -> bool {
+        let is_changed = self.blocks.get(block_idx as usize).unwrap().light != value;
+        if is_changed {
+            self.blocks.get_mut(block_idx as usize).light = value;
+            self.is_dirty = true;
+        }
+        is_changed
     }
 }
 
+fn is_filled_type(block: BlockType) -> bool {
+    block.properties().is_filled
+}
+
 pub struct Neighbor(pub u8);
 
 impl Neighbor {
@@ -142,3 +398,139 @@ impl Neighbor {
         self.0 as usize
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPES: [BlockType; 5] = [
+        BlockType::STONE,
+        BlockType::DIRT,
+        BlockType::EMPTY,
+        BlockType::WATER,
+        BlockType::GRASS,
+    ];
+
+    /// A chunk with every kind of per-block state set somewhere
+    fn varied_buffer() -> BlockBuffer {
+        let mut chunk = BlockBuffer::new(RuntimeShape::<u32, 3>::new([8, 8, 8]));
+
+        for block_idx in 0..chunk.block_count {
+            let i = block_idx as usize;
+            chunk.set_block_type(block_idx, TYPES[i % TYPES.len()]);
+            chunk.set_torchlight(block_idx, (i % 16) as u8);
+            chunk.set_sunlight(block_idx, (i % 13) as u8);
+            chunk.set_temperature(block_idx, (i % 7) as u8);
+            chunk.set_flag_mine(block_idx, i.is_multiple_of(3));
+            chunk.set_flag_blueprint(block_idx, i.is_multiple_of(5));
+
+            if i.is_multiple_of(4) {
+                chunk.set_partition_id(block_idx, block_idx / 64);
+            }
+
+            if chunk.get_block(block_idx).block == BlockType::WATER {
+                chunk.set_fluid_level(block_idx, (i % FLUID_MAX as usize) as u8 + 1);
+            }
+        }
+
+        chunk
+    }
+
+    fn blocks(chunk: &BlockBuffer) -> Vec<Block> {
+        (0..chunk.block_count)
+            .map(|block_idx| chunk.get_block(block_idx))
+            .collect()
+    }
+
+    #[test]
+    fn compact_round_trip_is_lossless() {
+        let mut chunk = varied_buffer();
+        let dense = blocks(&chunk);
+
+        assert!(!chunk.is_compact());
+        assert!(chunk.compact());
+        assert!(chunk.is_compact());
+        assert_eq!(blocks(&chunk), dense);
+
+        // any write expands the chunk again
+        chunk.set_temperature(0, 99);
+        assert!(!chunk.is_compact());
+
+        let mut expected = dense;
+        expected[0].temperature = 99;
+        assert_eq!(blocks(&chunk), expected);
+    }
+
+    #[test]
+    fn compact_uniform_chunk_is_smaller() {
+        let mut chunk = BlockBuffer::new(RuntimeShape::<u32, 3>::new([16, 16, 16]));
+
+        for block_idx in 0..chunk.block_count {
+            chunk.set_block_type(block_idx, BlockType::STONE);
+        }
+
+        let dense_bytes = chunk.memory_bytes();
+        chunk.compact();
+
+        assert!(chunk.memory_bytes() < dense_bytes / 2);
+        assert!((0..chunk.block_count).all(|idx| chunk.get_block(idx).block == BlockType::STONE));
+    }
+
+    #[test]
+    fn diff_lists_changed_blocks() {
+        let before = varied_buffer();
+        let mut after = before.clone();
+
+        after.set_block_type(3, BlockType::LOG);
+        after.set_flag_mine(10, !before.get_block(10).flag_mine);
+
+        let diff = before.diff(&after);
+
+        assert_eq!(
+            diff.iter().map(|(idx, _, _)| *idx).collect::<Vec<_>>(),
+            vec![3, 10]
+        );
+        assert_eq!(diff[0].1, before.get_block(3));
+        assert_eq!(diff[0].2.block, BlockType::LOG);
+        assert!(after.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn block_xyz_stops_at_the_end_of_the_buffer() {
+        let chunk = BlockBuffer::new(RuntimeShape::<u32, 3>::new([8, 8, 8]));
+
+        assert_eq!(chunk.get_block_xyz(0), Some([0, 0, 0]));
+        assert_eq!(chunk.get_block_xyz(chunk.block_count - 1), Some([7, 7, 7]));
+        assert_eq!(chunk.get_block_xyz(chunk.block_count), None);
+        assert_eq!(chunk.get_block_xyz(u32::MAX), None);
+    }
+
+    #[test]
+    fn empty_chunk_counts_only_air() {
+        let chunk = BlockBuffer::new(RuntimeShape::<u32, 3>::new([8, 8, 8]));
+
+        assert_eq!(chunk.count_blocks_of_type(BlockType::EMPTY), 512);
+        assert_eq!(chunk.count_blocks_of_type(BlockType::STONE), 0);
+    }
+
+    #[test]
+    fn full_chunk_counts_every_block() {
+        let mut chunk = BlockBuffer::new(RuntimeShape::<u32, 3>::new([8, 8, 8]));
+
+        for block_idx in 0..chunk.block_count {
+            chunk.set_block_type(block_idx, BlockType::STONE);
+        }
+
+        assert_eq!(chunk.count_blocks_of_type(BlockType::STONE), 512);
+        assert_eq!(chunk.count_blocks_of_type(BlockType::EMPTY), 0);
+
+        // setting a block to what it already is counts nothing twice
+        chunk.set_block_type(7, BlockType::STONE);
+        assert_eq!(chunk.count_blocks_of_type(BlockType::STONE), 512);
+
+        chunk.compact();
+        chunk.set_block_type(7, BlockType::DIRT);
+        assert_eq!(chunk.count_blocks_of_type(BlockType::STONE), 511);
+        assert_eq!(chunk.count_blocks_of_type(BlockType::DIRT), 1);
+    }
+}
@@ -0,0 +1,271 @@
+use bevy::{
+    asset::Assets,
+    ecs::{
+        entity::Entity,
+        query::With,
+        system::{Commands, Local, Query, Res, ResMut, Resource},
+    },
+    hierarchy::DespawnRecursiveExt,
+    render::mesh::Mesh,
+    time::Time,
+    transform::components::GlobalTransform,
+};
+
+use crate::{
+    colonists::{InPartition, NavigationGraph},
+    controls::MainCamera,
+    Chunk, MeshStats, Terrain,
+};
+
+#[derive(Resource)]
+pub struct ChunkStreaming {
+    /// Chunks further than this from the camera (in chunks, along x and z)
+    /// are unloaded
+    pub radius: u32,
+    /// Loads and unloads done per frame, they hit the disk
+    pub max_per_frame: usize,
+}
+
+impl Default for ChunkStreaming {
+    fn default() -> Self {
+        Self {
+            radius: 8,
+            max_per_frame: 4,
+        }
+    }
+}
+
+/// Loads the chunks around the camera and unloads the rest. Unloaded chunks
+/// lose their entity and their partitions, loaded ones get an entity back
+/// from `spawn_filled_chunks` and are partitioned again.
+pub fn stream_chunks(
+    mut cmd: Commands,
+    mut terrain: ResMut<Terrain>,
+    mut graph: ResMut<NavigationGraph>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut stats: ResMut<MeshStats>,
+    settings: Res<ChunkStreaming>,
+    cameras: Query<&GlobalTransform, With<MainCamera>>,
+    chunks: Query<(Entity, &Chunk)>,
+) {
+    let Ok(camera) = cameras.get_single() else {
+        return;
+    };
+
+    let pos = camera.translation();
+    let size = terrain.chunk_size as f32;
+    let camera_chunk = [(pos.x / size).floor() as i32, (pos.z / size).floor() as i32];
+    let radius = settings.radius as i32;
+
+    let mut budget = settings.max_per_frame;
+
+    for chunk_idx in 0..terrain.chunk_count {
+        if budget == 0 {
+            break;
+        }
+
+        let [ox, _, oz] = terrain.get_chunk_offset(chunk_idx);
+        let cx = (ox / terrain.chunk_size) as i32;
+        let cz = (oz / terrain.chunk_size) as i32;
+        let is_near =
+            (cx - camera_chunk[0]).abs() <= radius && (cz - camera_chunk[1]).abs() <= radius;
+        let is_loaded = terrain.is_chunk_loaded(chunk_idx);
+
+        if is_near == is_loaded {
+            continue;
+        }
+
+        budget -= 1;
+
+        let result = if is_near {
+            terrain.load_chunk(chunk_idx)
+        } else {
+            terrain.unload_chunk(chunk_idx)
+        };
+
+        match result {
+            Ok(()) if !is_near => forget_chunk_partitions(&mut cmd, &mut graph, chunk_idx),
+            Ok(()) => {}
+            Err(err) => println!("failed to stream chunk {}: {}", chunk_idx, err),
+        }
+    }
+
+    for (entity, chunk) in chunks.iter() {
+        if terrain.is_chunk_loaded(chunk.chunk_idx) {
+            continue;
+        }
+
+        meshes.remove(chunk.mesh_handle.clone());
+        meshes.remove(chunk.transparent_mesh_handle.clone());
+        cmd.entity(entity).despawn_recursive();
+        stats.forget(chunk.chunk_idx);
+    }
+}
+
+/// Nothing can path through an unloaded chunk. Its items leave the graph
+/// with its partitions and `partition_orphaned_items` puts them back once
+/// the chunk is loaded and partitioned again.
+fn forget_chunk_partitions(cmd: &mut Commands, graph: &mut NavigationGraph, chunk_idx: u32) {
+    for partition in graph.delete_partitions_for_chunk(chunk_idx) {
+        for item in partition.items.iter() {
+            if let Some(mut item) = cmd.get_entity(*item) {
+                item.remove::<InPartition>();
+            }
+        }
+    }
+}
+
+/// Seconds between passes compacting idle chunks
+const COMPACT_INTERVAL_S: f32 = 1.;
+/// Chunks compacted per pass, each one hashes every block
+const COMPACT_PER_PASS: usize = 8;
+
+/// Compacts the block state of loaded chunks with nothing waiting to be
+/// meshed. A chunk that is written to again expands on its own. Once every
+/// chunk is compact the default world drops from about 16.6 MiB to 2.6 MiB.
+pub fn compact_idle_chunks(time: Res<Time>, mut timer: Local<f32>, mut terrain: ResMut<Terrain>) {
+    *timer += time.delta_seconds();
+
+    if *timer < COMPACT_INTERVAL_S {
+        return;
+    }
+
+    *timer = 0.;
+
+    let mut budget = COMPACT_PER_PASS;
+
+    for chunk_idx in 0..terrain.chunk_count {
+        if budget == 0 {
+            break;
+        }
+
+        if terrain.get_chunk_dirty(chunk_idx) {
+            continue;
+        }
+
+        let Some(chunk) = terrain.get_chunk_mut(chunk_idx) else {
+            continue;
+        };
+
+        if chunk.is_compact() {
+            continue;
+        }
+
+        chunk.compact();
+        budget -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::{
+            event::Events,
+            schedule::{IntoSystemConfigs, Schedule},
+            system::RunSystemOnce,
+            world::World,
+        },
+        math::Vec3,
+        transform::components::Transform,
+    };
+
+    use crate::{
+        colonists::{partition, partition_orphaned_items, Item, ItemTag, PartitionEvent},
+        save::clear_chunk_cache,
+        BlockType,
+    };
+
+    use super::*;
+
+    /// Two chunks side by side along x with a floor across both
+    fn two_chunk_world() -> World {
+        let mut terrain = Terrain::new(2, 1, 1, 8).unwrap();
+        terrain.chunk_cache_dir =
+            std::env::temp_dir().join(format!("boris-chunk-cache-{}", std::process::id()));
+
+        for x in 0..16 {
+            for z in 0..8 {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+            }
+        }
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<Events<PartitionEvent>>();
+        world.init_resource::<Assets<Mesh>>();
+        world.init_resource::<MeshStats>();
+        world.insert_resource(ChunkStreaming {
+            radius: 0,
+            max_per_frame: 4,
+        });
+        world
+    }
+
+    fn repartition(world: &mut World) {
+        for chunk_idx in 0..2 {
+            world.send_event(PartitionEvent { chunk_idx });
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((partition, partition_orphaned_items).chain());
+        schedule.run(world);
+    }
+
+    fn stones(world: &World) -> usize {
+        world
+            .resource::<NavigationGraph>()
+            .items_of_type(&[ItemTag::Stone], 10)
+            .len()
+    }
+
+    #[test]
+    fn unloaded_chunks_leave_the_navigation_graph() {
+        let mut world = two_chunk_world();
+        let item = world
+            .spawn((
+                Item {
+                    tags: vec![ItemTag::Stone],
+                    reserved: None,
+                },
+                Transform::from_xyz(12.5, 1., 3.5),
+            ))
+            .id();
+        let camera = world
+            .spawn((
+                MainCamera::default(),
+                GlobalTransform::from_translation(Vec3::new(2., 10., 2.)),
+            ))
+            .id();
+
+        repartition(&mut world);
+        assert!(world.resource::<NavigationGraph>().chunk_partition_count(1) > 0);
+        assert_eq!(stones(&world), 1);
+
+        world.run_system_once(stream_chunks);
+
+        assert!(!world.resource::<Terrain>().is_chunk_loaded(1));
+        assert_eq!(
+            world.resource::<NavigationGraph>().chunk_partition_count(1),
+            0
+        );
+        assert!(!world.entity(item).contains::<InPartition>());
+        assert_eq!(stones(&world), 0);
+
+        // walk the camera over and load it again
+        world.resource_mut::<ChunkStreaming>().radius = 1;
+        world
+            .entity_mut(camera)
+            .insert(GlobalTransform::from_translation(Vec3::new(12., 10., 2.)));
+        world.run_system_once(stream_chunks);
+        repartition(&mut world);
+
+        assert!(world.resource::<Terrain>().is_chunk_loaded(1));
+        assert!(world.entity(item).contains::<InPartition>());
+        assert_eq!(stones(&world), 1);
+
+        let dir = world.resource::<Terrain>().chunk_cache_dir.clone();
+        clear_chunk_cache(&dir).unwrap();
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    ecs::{
+        event::EventWriter,
+        system::{Res, ResMut, Resource},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+};
+
+use crate::{
+    colonists::SpawnJobMineEvent, BlockChange, BlockChangedEvent, BlockEdit, EditRecord, Terrain,
+};
+
+/// Batches of player edits that can be undone and redone. Each batch is
+/// everything one input action changed, e.g. a whole dragged fill.
+#[derive(Resource)]
+pub struct EditHistory {
+    /// Oldest batches are dropped past this many
+    pub max_batches: usize,
+    undo_stack: VecDeque<Vec<EditRecord>>,
+    redo_stack: Vec<Vec<EditRecord>>,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self {
+            max_batches: 64,
+            undo_stack: VecDeque::new(),
+            redo_stack: vec![],
+        }
+    }
+}
+
+/// What an undo or redo did. Blocks flagged for mining again are not applied
+/// here, they go through `SpawnJobMineEvent` so their job comes back too.
+#[derive(Default)]
+pub struct HistoryStep {
+    pub changes: Vec<BlockChange>,
+    pub mine_flags: Vec<[u32; 3]>,
+}
+
+impl HistoryStep {
+    fn apply(&mut self, terrain: &mut Terrain, pos: [u32; 3], edit: BlockEdit) {
+        if edit == BlockEdit::FlagMine(true) {
+            self.mine_flags.push(pos);
+            return;
+        }
+
+        if let Some(change) = terrain.apply_edit(pos, edit) {
+            self.changes.push(change);
+        }
+    }
+}
+
+impl EditHistory {
+    /// A new edit makes the redo stack meaningless, so it is cleared.
+    pub fn push(&mut self, batch: Vec<EditRecord>) {
+        self.redo_stack.clear();
+        self.undo_stack.push_back(batch);
+
+        while self.undo_stack.len() > self.max_batches {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    /// Revert the latest batch, last edit first.
+    pub fn undo(&mut self, terrain: &mut Terrain) -> Option<HistoryStep> {
+        let batch = self.undo_stack.pop_back()?;
+        let mut step = HistoryStep::default();
+
+        for record in batch.iter().rev() {
+            step.apply(terrain, record.pos, record.previous);
+        }
+
+        self.redo_stack.push(batch);
+        Some(step)
+    }
+
+    /// Apply the latest undone batch again.
+    pub fn redo(&mut self, terrain: &mut Terrain) -> Option<HistoryStep> {
+        let batch = self.redo_stack.pop()?;
+        let mut step = HistoryStep::default();
+
+        for record in batch.iter() {
+            step.apply(terrain, record.pos, record.value);
+        }
+
+        self.undo_stack.push_back(batch);
+        Some(step)
+    }
+}
+
+/// Moves the batches recorded by `Terrain::record_batch` into the history.
+pub fn collect_edit_history(mut terrain: ResMut<Terrain>, mut history: ResMut<EditHistory>) {
+    for batch in terrain.take_recorded_batches() {
+        history.push(batch);
+    }
+}
+
+/// Ctrl+Z undoes, Ctrl+Y or Ctrl+Shift+Z redoes.
+pub fn edit_history_keys(
+    input_keys: Res<ButtonInput<KeyCode>>,
+    mut terrain: ResMut<Terrain>,
+    mut history: ResMut<EditHistory>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+    mut ev_spawn_job_mine: EventWriter<SpawnJobMineEvent>,
+) {
+    if !input_keys.pressed(KeyCode::ControlLeft) {
+        return;
+    }
+
+    let is_shift = input_keys.pressed(KeyCode::ShiftLeft);
+
+    let step = if input_keys.just_pressed(KeyCode::KeyY)
+        || (is_shift && input_keys.just_pressed(KeyCode::KeyZ))
+    {
+        history.redo(&mut terrain)
+    } else if input_keys.just_pressed(KeyCode::KeyZ) {
+        history.undo(&mut terrain)
+    } else {
+        return;
+    };
+
+    let Some(step) = step else {
+        println!("nothing to undo or redo");
+        return;
+    };
+
+    ev_block_changed.send_batch(step.changes.into_iter().map(BlockChangedEvent::from));
+    ev_spawn_job_mine.send_batch(
+        step.mine_flags
+            .into_iter()
+            .map(|pos| SpawnJobMineEvent { pos }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Block, BlockType};
+
+    fn chunk_blocks(terrain: &Terrain) -> Vec<Vec<Block>> {
+        terrain
+            .chunks
+            .iter()
+            .flatten()
+            .map(|chunk| {
+                (0..chunk.block_count)
+                    .map(|idx| chunk.get_block(idx))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn types_and_flags(terrain: &Terrain) -> Vec<(BlockType, bool)> {
+        chunk_blocks(terrain)
+            .into_iter()
+            .flatten()
+            .map(|block| (block.block, block.flag_mine))
+            .collect()
+    }
+
+    #[test]
+    fn undo_restores_previous_blocks() {
+        let mut terrain = Terrain::new(2, 1, 1, 4).unwrap();
+        terrain.fill_region([0, 0, 0], [7, 1, 3], BlockType::STONE);
+
+        let before = terrain.chunks[0].clone().unwrap();
+        let before_types = types_and_flags(&terrain);
+        let mut history = EditHistory::default();
+
+        // straddles the chunk border, and sets one block twice
+        terrain.record_batch(|terrain| {
+            terrain.fill_region([2, 1, 1], [5, 2, 2], BlockType::DIRT);
+            terrain.set_block(2, 1, 1, BlockType::LOG);
+            terrain.set_mine_flag_region([0, 0, 0], [1, 0, 0], true);
+        });
+        collect_batches(&mut terrain, &mut history);
+
+        let after_types = types_and_flags(&terrain);
+        assert_ne!(after_types, before_types);
+
+        let step = history.undo(&mut terrain).unwrap();
+        assert!(step.mine_flags.is_empty());
+        assert_eq!(types_and_flags(&terrain), before_types);
+
+        // a diff of the chunk against its old self only sees lighting
+        let undone = terrain.chunks[0].as_ref().unwrap();
+        assert!(before
+            .diff(undone)
+            .iter()
+            .all(|(_, old, new)| old.block == new.block && old.flag_mine == new.flag_mine));
+
+        // the mine flags come back through their jobs on redo
+        let step = history.redo(&mut terrain).unwrap();
+        assert_eq!(step.mine_flags, vec![[0, 0, 0], [1, 0, 0]]);
+        for [x, y, z] in step.mine_flags {
+            terrain.set_flag_mine(x, y, z, true);
+        }
+        assert_eq!(types_and_flags(&terrain), after_types);
+    }
+
+    #[test]
+    fn unchanged_batches_are_not_recorded() {
+        let mut terrain = Terrain::new(1, 1, 1, 4).unwrap();
+        let mut history = EditHistory::default();
+
+        terrain.record_batch(|terrain| {
+            terrain.set_block(1, 1, 1, BlockType::STONE);
+            terrain.set_block(1, 1, 1, BlockType::EMPTY);
+        });
+        collect_batches(&mut terrain, &mut history);
+
+        assert!(history.undo(&mut terrain).is_none());
+    }
+
+    fn collect_batches(terrain: &mut Terrain, history: &mut EditHistory) {
+        for batch in terrain.take_recorded_batches() {
+            history.push(batch);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use bevy::{
+    ecs::{
+        event::EventWriter,
+        system::{Res, ResMut, Resource},
+    },
+    time::Time,
+};
+
+use crate::{colonists::SpawnJobFarmEvent, BlockType, Terrain};
+
+const FARM_TICK_S: f32 = 1.;
+/// Ticks a seeded plot needs before it starts growing, and before it is ripe.
+const FARM_GROWING_TICKS: u32 = 20;
+const FARM_RIPE_TICKS: u32 = 60;
+
+#[derive(Resource)]
+pub struct Farms {
+    pub tick_timer: f32,
+    /// Plots only grow while watered. There is no water block yet, so rain
+    /// is the only source of water for now.
+    pub is_raining: bool,
+}
+
+impl Default for Farms {
+    fn default() -> Self {
+        Self {
+            tick_timer: 0.,
+            is_raining: true,
+        }
+    }
+}
+
+pub fn tick_farm(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    mut farms: ResMut<Farms>,
+    mut ev_spawn_job_farm: EventWriter<SpawnJobFarmEvent>,
+) {
+    farms.tick_timer += time.delta_seconds();
+
+    if farms.tick_timer < FARM_TICK_S {
+        return;
+    }
+
+    farms.tick_timer -= FARM_TICK_S;
+
+    if !farms.is_raining {
+        return;
+    }
+
+    let plots = terrain
+        .farm_plots
+        .iter()
+        .map(|(pos, ticks)| (*pos, *ticks))
+        .collect::<Vec<_>>();
+
+    for ([x, y, z], ticks) in plots {
+        let current = terrain.get_block(x, y, z).block;
+
+        if current == BlockType::SOIL_RIPE {
+            continue;
+        }
+
+        let ticks = ticks + 1;
+        terrain.farm_plots.insert([x, y, z], ticks);
+
+        // bare soil is planted right away
+        let next = if ticks >= FARM_RIPE_TICKS {
+            BlockType::SOIL_RIPE
+        } else if ticks >= FARM_GROWING_TICKS {
+            BlockType::SOIL_GROWING
+        } else {
+            BlockType::SOIL_SEEDED
+        };
+
+        if next == current {
+            continue;
+        }
+
+        terrain.set_block(x, y, z, next);
+
+        if next == BlockType::SOIL_RIPE {
+            ev_spawn_job_farm.send(SpawnJobFarmEvent { pos: [x, y, z] });
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use bevy::{
+    ecs::{
+        event::EventWriter,
+        system::{Res, ResMut, Resource},
+    },
+    time::Time,
+};
+
+use crate::{
+    common::Rand, items::SpawnCoalEvent, BlockChangedEvent, BlockFace, BlockType, Terrain,
+};
+
+const FIRE_SPREAD_INTERVAL_S: f32 = 1.;
+const FIRE_SPREAD_CHANCE: f32 = 0.25;
+const FIRE_BURN_DURATION_S: f32 = 4.;
+/// Odds of a burnt out fire leaving a lump of coal behind
+const FIRE_COAL_CHANCE: f32 = 0.25;
+
+pub struct BurningBlock {
+    pub pos: [u32; 3],
+    pub remaining_s: f32,
+}
+
+/// Leaves that caught fire. They burn as a campfire for a short while and
+/// then go out, sometimes leaving a lump of coal behind.
+#[derive(Resource, Default)]
+pub struct Fires {
+    pub burning: Vec<BurningBlock>,
+    pub spread_timer: f32,
+}
+
+pub fn propagate_fire(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    mut fires: ResMut<Fires>,
+    mut rand: ResMut<Rand>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+    mut ev_spawn_coal: EventWriter<SpawnCoalEvent>,
+) {
+    let delta = time.delta_seconds();
+    let mut burnt_out = vec![];
+
+    fires.burning.retain_mut(|burning| {
+        burning.remaining_s -= delta;
+
+        if burning.remaining_s <= 0. {
+            burnt_out.push(burning.pos);
+            return false;
+        }
+
+        true
+    });
+
+    for [x, y, z] in burnt_out {
+        if terrain.get_block(x, y, z).block != BlockType::CAMPFIRE {
+            continue;
+        }
+
+        let change = terrain.set_block(x, y, z, BlockType::EMPTY);
+        ev_block_changed.send(change.into());
+
+        if rand.bool(FIRE_COAL_CHANCE) {
+            ev_spawn_coal.send(SpawnCoalEvent { pos: [x, y, z] });
+        }
+    }
+
+    fires.spread_timer += delta;
+
+    if fires.spread_timer < FIRE_SPREAD_INTERVAL_S {
+        return;
+    }
+
+    fires.spread_timer = 0.;
+
+    let sources = terrain
+        .heat_sources
+        .iter()
+        .filter(|[x, y, z]| terrain.get_block(*x, *y, *z).block == BlockType::CAMPFIRE)
+        .copied()
+        .collect::<Vec<_>>();
+
+    for [x, y, z] in sources {
+        for face in BlockFace::ALL {
+            let [dx, dy, dz] = face.offset();
+            let [nx, ny, nz] = [x as i32 + dx, y as i32 + dy, z as i32 + dz];
+
+            if terrain.get_block_i32(nx, ny, nz).block != BlockType::LEAVES {
+                continue;
+            }
+
+            if !rand.bool(FIRE_SPREAD_CHANCE) {
+                continue;
+            }
+
+            let pos = [nx as u32, ny as u32, nz as u32];
+
+            let change = terrain.set_block(pos[0], pos[1], pos[2], BlockType::CAMPFIRE);
+            fires.burning.push(BurningBlock {
+                pos,
+                remaining_s: FIRE_BURN_DURATION_S,
+            });
+            ev_block_changed.send(change.into());
+        }
+    }
+}
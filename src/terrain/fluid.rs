@@ -0,0 +1,472 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    ecs::{
+        event::{EventReader, EventWriter},
+        system::{Res, ResMut, Resource},
+    },
+    time::Time,
+    utils::HashSet,
+};
+
+use crate::{BlockChange, BlockChangedEvent, BlockType, Terrain};
+
+/// Water in a completely full block
+pub const FLUID_MAX: u8 = 8;
+/// Water at or below this level can be waded through
+pub const FLUID_SHALLOW: u8 = 2;
+const FLUID_TICK_S: f32 = 0.25;
+/// Most water blocks `level_pool` evens out in one go
+const POOL_MAX_BLOCKS: usize = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FluidDepth {
+    Dry,
+    Shallow,
+    Deep,
+}
+
+impl FluidDepth {
+    pub fn from_level(level: u8) -> Self {
+        match level {
+            0 => Self::Dry,
+            l if l <= FLUID_SHALLOW => Self::Shallow,
+            _ => Self::Deep,
+        }
+    }
+}
+
+/// Chunks that may still have moving water. Water that has settled drops out
+/// of the set, and block changes next to water put its chunk back in.
+#[derive(Resource)]
+pub struct Fluids {
+    /// How many active chunks are stepped each tick, the rest wait their turn
+    pub chunks_per_tick: usize,
+    pub tick_timer: f32,
+    active: VecDeque<u32>,
+    queued: HashSet<u32>,
+}
+
+impl Default for Fluids {
+    fn default() -> Self {
+        Self {
+            chunks_per_tick: 4,
+            tick_timer: 0.,
+            active: VecDeque::new(),
+            queued: HashSet::new(),
+        }
+    }
+}
+
+impl Fluids {
+    pub fn activate(&mut self, chunk_idx: u32) {
+        if self.queued.insert(chunk_idx) {
+            self.active.push_back(chunk_idx);
+        }
+    }
+
+    /// Activate the chunks of a block and its six neighbors.
+    fn activate_around(&mut self, terrain: &Terrain, [x, y, z]: [u32; 3]) {
+        let [x, y, z] = [x as i32, y as i32, z as i32];
+
+        for [dx, dy, dz] in NEIGHBORS.iter().chain([[0, 0, 0]].iter()) {
+            let [nx, ny, nz] = [x + dx, y + dy, z + dz];
+
+            if terrain.is_oob(nx, ny, nz) {
+                continue;
+            }
+
+            let [chunk_idx, _] = terrain.get_block_indexes(nx as u32, ny as u32, nz as u32);
+            self.activate(chunk_idx);
+        }
+    }
+
+    fn pop(&mut self) -> Option<u32> {
+        let chunk_idx = self.active.pop_front()?;
+        self.queued.remove(&chunk_idx);
+        Some(chunk_idx)
+    }
+}
+
+const NEIGHBORS: [[i32; 3]; 6] = [
+    [0, -1, 0],
+    [0, 1, 0],
+    [-1, 0, 0],
+    [1, 0, 0],
+    [0, 0, -1],
+    [0, 0, 1],
+];
+
+const SIDEWAYS: [[i32; 2]; 4] = [[-1, 0], [1, 0], [0, -1], [0, 1]];
+
+/// Wakes up the water touching any changed block, e.g. a mined out dam wall.
+pub fn activate_fluids(
+    terrain: Res<Terrain>,
+    mut fluids: ResMut<Fluids>,
+    mut ev_block_changed: EventReader<BlockChangedEvent>,
+) {
+    for ev in ev_block_changed.read() {
+        let [x, y, z] = ev.pos;
+
+        let touches_water = ev.previous == BlockType::WATER
+            || ev.value == BlockType::WATER
+            || NEIGHBORS.iter().any(|[dx, dy, dz]| {
+                terrain
+                    .get_block_i32(x as i32 + dx, y as i32 + dy, z as i32 + dz)
+                    .block
+                    == BlockType::WATER
+            });
+
+        if touches_water {
+            fluids.activate_around(&terrain, ev.pos);
+        }
+    }
+}
+
+/// Steps the water in a few active chunks. Water falls first, then spreads
+/// one level at a time to lower neighbors, so a block at level 1 no longer
+/// spreads and a pool settles once its neighbors are within a level.
+pub fn tick_fluids(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    mut fluids: ResMut<Fluids>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+) {
+    fluids.tick_timer += time.delta_seconds();
+
+    if fluids.tick_timer < FLUID_TICK_S {
+        return;
+    }
+
+    fluids.tick_timer -= FLUID_TICK_S;
+
+    let mut changed = vec![];
+
+    for _ in 0..fluids.chunks_per_tick {
+        let Some(chunk_idx) = fluids.pop() else {
+            break;
+        };
+
+        let mut changes = vec![];
+        changed.extend(step_chunk(&mut terrain, chunk_idx, &mut changes));
+
+        for change in changes {
+            ev_block_changed.send(change.into());
+        }
+    }
+
+    // anything that moved may move again next tick
+    for pos in changed {
+        fluids.activate_around(&terrain, pos);
+    }
+}
+
+/// Moves the water in one chunk, bottom up so it falls a block per step,
+/// then evens out the pools in it. Returns every position whose level
+/// changed.
+fn step_chunk(
+    terrain: &mut Terrain,
+    chunk_idx: u32,
+    changes: &mut Vec<BlockChange>,
+) -> Vec<[u32; 3]> {
+    let has_water = terrain
+        .get_chunk(chunk_idx)
+        .is_some_and(|chunk| chunk.count_blocks_of_type(BlockType::WATER) > 0);
+
+    if !has_water {
+        return vec![];
+    }
+
+    let [ox, oy, oz] = terrain.get_chunk_offset(chunk_idx);
+    let mut changed = vec![];
+
+    for y in oy..oy + terrain.chunk_size {
+        for x in ox..ox + terrain.chunk_size {
+            for z in oz..oz + terrain.chunk_size {
+                let block = terrain.get_block(x, y, z);
+
+                if block.block != BlockType::WATER {
+                    continue;
+                }
+
+                let mut level = block.fluid_level;
+
+                if y > 0 {
+                    let below = terrain.get_block(x, y - 1, z);
+
+                    let flow = match below.block {
+                        BlockType::EMPTY => level,
+                        BlockType::WATER => level.min(FLUID_MAX - below.fluid_level),
+                        _ => 0,
+                    };
+
+                    if flow > 0 {
+                        let pos = [x, y - 1, z];
+                        set_level(terrain, pos, below.fluid_level + flow, changes);
+                        changed.push(pos);
+                        level -= flow;
+                    }
+                }
+
+                for [dx, dz] in SIDEWAYS {
+                    if level <= 1 {
+                        break;
+                    }
+
+                    let [nx, nz] = [x as i32 + dx, z as i32 + dz];
+                    let neighbor = terrain.get_block_i32(nx, y as i32, nz);
+
+                    let neighbor_level = match neighbor.block {
+                        BlockType::EMPTY => 0,
+                        BlockType::WATER => neighbor.fluid_level,
+                        _ => continue,
+                    };
+
+                    if neighbor_level + 1 < level {
+                        let pos = [nx as u32, y, nz as u32];
+                        set_level(terrain, pos, neighbor_level + 1, changes);
+                        changed.push(pos);
+                        level -= 1;
+                    }
+                }
+
+                if level != block.fluid_level {
+                    set_level(terrain, [x, y, z], level, changes);
+                    changed.push([x, y, z]);
+                }
+            }
+        }
+    }
+
+    let mut visited = HashSet::new();
+
+    for y in oy..oy + terrain.chunk_size {
+        for x in ox..ox + terrain.chunk_size {
+            for z in oz..oz + terrain.chunk_size {
+                if !visited.contains(&[x, y, z]) && is_resting_water(terrain, [x, y, z]) {
+                    changed.extend(level_pool(terrain, [x, y, z], &mut visited, changes));
+                }
+            }
+        }
+    }
+
+    changed
+}
+
+/// Water that has nowhere left to fall.
+fn is_resting_water(terrain: &Terrain, [x, y, z]: [u32; 3]) -> bool {
+    if terrain.get_block(x, y, z).block != BlockType::WATER {
+        return false;
+    }
+
+    if y == 0 {
+        return true;
+    }
+
+    let below = terrain.get_block(x, y - 1, z);
+
+    match below.block {
+        BlockType::EMPTY => false,
+        BlockType::WATER => below.fluid_level == FLUID_MAX,
+        _ => true,
+    }
+}
+
+/// Spreading a level at a time leaves a slope one level per block wide, so
+/// share the water of a resting pool out evenly across it instead. The pool
+/// is everything reachable sideways from `start` through resting water.
+fn level_pool(
+    terrain: &mut Terrain,
+    start: [u32; 3],
+    visited: &mut HashSet<[u32; 3]>,
+    changes: &mut Vec<BlockChange>,
+) -> Vec<[u32; 3]> {
+    let mut pool = vec![];
+    let mut queue = VecDeque::from([start]);
+    visited.insert(start);
+
+    while let Some(pos @ [x, y, z]) = queue.pop_front() {
+        pool.push(pos);
+
+        if pool.len() >= POOL_MAX_BLOCKS {
+            break;
+        }
+
+        for [dx, dz] in SIDEWAYS {
+            let [nx, nz] = [x as i32 + dx, z as i32 + dz];
+
+            if terrain.is_oob(nx, y as i32, nz) {
+                continue;
+            }
+
+            let npos = [nx as u32, y, nz as u32];
+
+            if !visited.contains(&npos) && is_resting_water(terrain, npos) {
+                visited.insert(npos);
+                queue.push_back(npos);
+            }
+        }
+    }
+
+    let levels = pool
+        .iter()
+        .map(|[x, y, z]| terrain.get_block(*x, *y, *z).fluid_level as u32)
+        .collect::<Vec<_>>();
+
+    let (Some(min), Some(max)) = (levels.iter().min(), levels.iter().max()) else {
+        return vec![];
+    };
+
+    if max - min <= 1 {
+        return vec![];
+    }
+
+    let total: u32 = levels.iter().sum();
+    let share = total / pool.len() as u32;
+    let remainder = (total % pool.len() as u32) as usize;
+    let mut changed = vec![];
+
+    for (i, (pos, level)) in pool.iter().zip(levels).enumerate() {
+        let new_level = share + u32::from(i < remainder);
+
+        if new_level != level {
+            set_level(terrain, *pos, new_level as u8, changes);
+            changed.push(*pos);
+        }
+    }
+
+    changed
+}
+
+/// Fill a block to `level`, turning it into water or back into air as needed.
+fn set_level(
+    terrain: &mut Terrain,
+    [x, y, z]: [u32; 3],
+    level: u8,
+    changes: &mut Vec<BlockChange>,
+) {
+    let is_water = terrain.get_block(x, y, z).block == BlockType::WATER;
+
+    if level == 0 {
+        if is_water {
+            changes.push(terrain.set_block(x, y, z, BlockType::EMPTY));
+        }
+        return;
+    }
+
+    if !is_water {
+        changes.push(terrain.set_block(x, y, z, BlockType::WATER));
+    }
+
+    terrain.set_fluid_level(x, y, z, level);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{
+        event::Events,
+        schedule::{IntoSystemConfigs, Schedule},
+        world::World,
+    };
+
+    use super::*;
+
+    const BASIN_X: std::ops::RangeInclusive<u32> = 1..=10;
+    const DAM_X: u32 = 11;
+    const RESERVOIR_X: std::ops::RangeInclusive<u32> = 12..=21;
+    const ROWS_Z: std::ops::RangeInclusive<u32> = 1..=10;
+
+    /// A dry 10x10 basin, a stone dam, and a 10x10 reservoir of full water,
+    /// all inside a two block high wall.
+    fn dammed_terrain() -> Terrain {
+        let mut terrain = Terrain::new(3, 1, 2, 8).unwrap();
+
+        for x in 0..=22 {
+            for z in 0..=11 {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+
+                let inside = x != 0 && x != 22 && ROWS_Z.contains(&z);
+
+                for y in 1..=2 {
+                    if !inside || x == DAM_X {
+                        terrain.set_block(x, y, z, BlockType::STONE);
+                    }
+                }
+
+                if inside && RESERVOIR_X.contains(&x) {
+                    terrain.set_block(x, 1, z, BlockType::WATER);
+                }
+            }
+        }
+
+        terrain
+    }
+
+    fn levels(terrain: &Terrain, xs: std::ops::RangeInclusive<u32>) -> Vec<u8> {
+        xs.flat_map(|x| ROWS_Z.map(move |z| [x, z]))
+            .map(|[x, z]| terrain.get_block(x, 1, z).fluid_level)
+            .collect()
+    }
+
+    #[test]
+    fn breached_dam_floods_the_basin() {
+        let mut world = World::new();
+        world.insert_resource(dammed_terrain());
+        world.init_resource::<Fluids>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<BlockChangedEvent>>();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((activate_fluids, tick_fluids).chain());
+
+        // nothing moves while the dam holds
+        for _ in 0..10 {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(FLUID_TICK_S));
+            schedule.run(&mut world);
+        }
+        assert!(levels(world.resource::<Terrain>(), BASIN_X)
+            .iter()
+            .all(|level| *level == 0));
+
+        for z in ROWS_Z {
+            let change = world
+                .resource_mut::<Terrain>()
+                .set_block(DAM_X, 1, z, BlockType::EMPTY);
+            world.send_event::<BlockChangedEvent>(change.into());
+        }
+
+        let mut ticks = 0;
+
+        loop {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(FLUID_TICK_S));
+            schedule.run(&mut world);
+            world.resource_mut::<Events<BlockChangedEvent>>().update();
+            ticks += 1;
+
+            if world.resource::<Fluids>().active.is_empty() {
+                break;
+            }
+            assert!(ticks < 1000, "water never settled");
+        }
+
+        let terrain = world.resource::<Terrain>();
+        let basin = levels(terrain, BASIN_X);
+        let reservoir = levels(terrain, RESERVOIR_X);
+        let dam = levels(terrain, DAM_X..=DAM_X);
+
+        let all = [basin, dam, reservoir].concat();
+
+        // water is moved, never made or lost
+        let total: u32 = all.iter().map(|level| *level as u32).sum();
+        assert_eq!(total, 100 * FLUID_MAX as u32);
+
+        // 800 levels over 210 blocks, so the whole floor ends up at 3 or 4
+        assert!(all.iter().all(|level| (3..=4).contains(level)), "{:?}", all);
+    }
+}
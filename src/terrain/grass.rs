@@ -0,0 +1,198 @@
+use bevy::{
+    ecs::{
+        event::EventWriter,
+        system::{Res, ResMut, Resource},
+    },
+    time::Time,
+};
+
+use crate::{common::Rand, BlockChangedEvent, BlockType, Terrain};
+
+/// Tuning for grass spreading onto sunlit dirt and dying off under cover.
+/// Only a random sample of columns is looked at each tick, so the cost does
+/// not grow with the size of the world.
+#[derive(Resource)]
+pub struct GrassGrowth {
+    pub tick_s: f32,
+    pub tick_timer: f32,
+    pub columns_per_tick: u32,
+    /// Odds of a sampled dirt block turning to grass when grass is nearby
+    pub spread_chance: f32,
+    /// Odds of a sampled covered grass block turning back to dirt
+    pub decay_chance: f32,
+    /// How far away, in blocks, grass can spread from
+    pub spread_radius: i32,
+    /// Sunlight the block above the dirt needs for grass to take
+    pub min_sunlight: u8,
+}
+
+impl Default for GrassGrowth {
+    fn default() -> Self {
+        Self {
+            tick_s: 0.5,
+            tick_timer: 0.,
+            columns_per_tick: 64,
+            spread_chance: 0.1,
+            decay_chance: 0.2,
+            spread_radius: 1,
+            min_sunlight: 8,
+        }
+    }
+}
+
+pub fn tick_grass(
+    time: Res<Time>,
+    mut rand: ResMut<Rand>,
+    mut terrain: ResMut<Terrain>,
+    mut grass: ResMut<GrassGrowth>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+) {
+    grass.tick_timer += time.delta_seconds();
+
+    if grass.tick_timer < grass.tick_s {
+        return;
+    }
+
+    grass.tick_timer = 0.;
+
+    for _ in 0..grass.columns_per_tick {
+        let x = rand.range_n(0, terrain.world_size_x() as i32) as u32;
+        let z = rand.range_n(0, terrain.world_size_z() as i32) as u32;
+
+        let Some(y) = terrain.get_surface_y(x, z) else {
+            continue;
+        };
+
+        let surface = terrain.get_block(x, y, z);
+
+        if surface.block == BlockType::DIRT
+            && y + 1 < terrain.world_size_y()
+            && terrain.get_sunlight_xyz(x, y + 1, z) >= grass.min_sunlight
+            && has_grass_nearby(&terrain, [x, y, z], grass.spread_radius)
+            && rand.bool(grass.spread_chance)
+        {
+            let change = terrain.set_block(x, y, z, BlockType::GRASS);
+            ev_block_changed.send(change.into());
+            continue;
+        }
+
+        // the surface block covers whatever is under it, grass buried by a
+        // wall or a pile of sand is found there
+        if y == 0 || !surface.block.properties().is_filled {
+            continue;
+        }
+
+        if terrain.get_block(x, y - 1, z).block == BlockType::GRASS && rand.bool(grass.decay_chance)
+        {
+            let change = terrain.set_block(x, y - 1, z, BlockType::DIRT);
+            ev_block_changed.send(change.into());
+        }
+    }
+}
+
+fn has_grass_nearby(terrain: &Terrain, [x, y, z]: [u32; 3], radius: i32) -> bool {
+    let [x, y, z] = [x as i32, y as i32, z as i32];
+
+    for dx in -radius..=radius {
+        for dy in -1..=1 {
+            for dz in -radius..=radius {
+                if terrain.get_block_i32(x + dx, y + dy, z + dz).block == BlockType::GRASS {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{event::Events, schedule::Schedule, world::World};
+
+    use crate::{light_system, propagate_light, LightChannel, MAX_LIGHT};
+
+    use super::*;
+
+    const TRENCH_X: u32 = 5;
+
+    /// Stone, then dirt, then a grass surface at y = 3, open to the sky.
+    fn meadow() -> Terrain {
+        let mut terrain = Terrain::new(2, 1, 2, 8).unwrap();
+
+        for x in 0..terrain.world_size_x() {
+            for z in 0..terrain.world_size_z() {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+                terrain.set_block(x, 1, z, BlockType::DIRT);
+                terrain.set_block(x, 2, z, BlockType::DIRT);
+                terrain.set_block(x, 3, z, BlockType::GRASS);
+
+                for y in 4..terrain.world_size_y() {
+                    terrain.add_sunlight(x, y, z, MAX_LIGHT);
+                }
+            }
+        }
+
+        propagate_light(&mut terrain, LightChannel::Sun);
+        terrain
+    }
+
+    fn trench(terrain: &Terrain) -> Vec<BlockType> {
+        (0..terrain.world_size_z())
+            .map(|z| terrain.get_block(TRENCH_X, 3, z).block)
+            .collect()
+    }
+
+    #[test]
+    fn refilled_trench_grows_grass_back() {
+        let mut terrain = meadow();
+
+        // dig a trench across the meadow and fill it back in with dirt
+        for z in 0..terrain.world_size_z() {
+            terrain.set_block(TRENCH_X, 3, z, BlockType::EMPTY);
+            terrain.set_block(TRENCH_X, 2, z, BlockType::EMPTY);
+        }
+        for z in 0..terrain.world_size_z() {
+            terrain.set_block(TRENCH_X, 2, z, BlockType::DIRT);
+            terrain.set_block(TRENCH_X, 3, z, BlockType::DIRT);
+        }
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.insert_resource(Rand::seed(586));
+        world.init_resource::<GrassGrowth>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<BlockChangedEvent>>();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((light_system, tick_grass));
+
+        let tick_s = world.resource::<GrassGrowth>().tick_s;
+        let mut ticks = 0;
+
+        while trench(world.resource::<Terrain>())
+            .iter()
+            .any(|block| *block != BlockType::GRASS)
+        {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(tick_s));
+            schedule.run(&mut world);
+            ticks += 1;
+
+            // takes 184 ticks with this seed
+            assert!(ticks < 1000, "trench still bare after {} ticks", ticks);
+        }
+
+        // the rest of the meadow was never covered, so it is all still grass
+        let terrain = world.resource::<Terrain>();
+        for x in 0..terrain.world_size_x() {
+            for z in 0..terrain.world_size_z() {
+                assert_eq!(terrain.get_block(x, 3, z).block, BlockType::GRASS);
+                assert_eq!(terrain.get_block(x, 2, z).block, BlockType::DIRT);
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use bevy::{
+    ecs::{
+        event::{EventReader, EventWriter},
+        system::{Res, ResMut, Resource},
+    },
+    time::Time,
+    utils::HashSet,
+};
+
+use crate::{BlockChangedEvent, BlockType, Terrain};
+
+const GRAVITY_TICK_S: f32 = 0.2;
+
+/// Sand and gravel waiting to be checked for support. Filled from block
+/// changes, so a column only starts falling once something around it changes.
+#[derive(Resource, Default)]
+pub struct GravityBlocks {
+    pub tick_timer: f32,
+    pending: HashSet<[u32; 3]>,
+}
+
+/// Queues gravity blocks that may have lost their support: a block that was
+/// just placed, or the one sitting on top of a block that was just cleared.
+pub fn queue_gravity_blocks(
+    terrain: Res<Terrain>,
+    mut gravity: ResMut<GravityBlocks>,
+    mut ev_block_changed: EventReader<BlockChangedEvent>,
+) {
+    for ev in ev_block_changed.read() {
+        let [x, y, z] = ev.pos;
+
+        if ev.value.has_gravity() {
+            gravity.pending.insert(ev.pos);
+        }
+
+        if ev.value != BlockType::EMPTY || y + 1 >= terrain.world_size_y() {
+            continue;
+        }
+
+        if terrain.get_block(x, y + 1, z).block.has_gravity() {
+            gravity.pending.insert([x, y + 1, z]);
+        }
+    }
+}
+
+/// Drops every pending gravity block with air underneath by one block. The
+/// moves fire block change events, which queue the block again in its new
+/// spot and the one that was resting on it, so a column collapses one block
+/// per tick until it lands.
+pub fn tick_gravity_blocks(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    mut gravity: ResMut<GravityBlocks>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+) {
+    gravity.tick_timer += time.delta_seconds();
+
+    if gravity.tick_timer < GRAVITY_TICK_S {
+        return;
+    }
+
+    gravity.tick_timer -= GRAVITY_TICK_S;
+
+    // lowest first, so a block never lands on one that is about to move
+    let mut pending = gravity.pending.drain().collect::<Vec<_>>();
+    pending.sort_by_key(|[_, y, _]| *y);
+
+    for [x, y, z] in pending {
+        let block = terrain.get_block(x, y, z).block;
+
+        if !block.has_gravity() || y == 0 {
+            continue;
+        }
+
+        // OOB below, including unloaded chunks, counts as support
+        if terrain.get_block(x, y - 1, z).block != BlockType::EMPTY {
+            continue;
+        }
+
+        let lifted = terrain.set_block(x, y, z, BlockType::EMPTY);
+        let landed = terrain.set_block(x, y - 1, z, block);
+
+        ev_block_changed.send(lifted.into());
+        ev_block_changed.send(landed.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{
+        event::Events,
+        schedule::{IntoSystemConfigs, Schedule},
+        world::World,
+    };
+
+    use super::*;
+
+    fn column(terrain: &Terrain) -> Vec<BlockType> {
+        (1..=6).map(|y| terrain.get_block(2, y, 2).block).collect()
+    }
+
+    #[test]
+    fn sand_column_collapses_onto_the_floor() {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+
+        for x in 0..8 {
+            for z in 0..8 {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+            }
+        }
+
+        terrain.set_block(2, 1, 2, BlockType::STONE);
+
+        for y in 2..=6 {
+            terrain.set_block(2, y, 2, BlockType::SAND);
+        }
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<GravityBlocks>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<BlockChangedEvent>>();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((queue_gravity_blocks, tick_gravity_blocks).chain());
+
+        // mine out the block holding the column up
+        let change = world
+            .resource_mut::<Terrain>()
+            .set_block(2, 1, 2, BlockType::EMPTY);
+        world.send_event::<BlockChangedEvent>(change.into());
+
+        for tick in 1..=5 {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(GRAVITY_TICK_S));
+            schedule.run(&mut world);
+            world.resource_mut::<Events<BlockChangedEvent>>().update();
+
+            // one block comes down each tick, leaving a gap that climbs up
+            let mut expected = vec![BlockType::SAND; 6];
+            expected[tick] = BlockType::EMPTY;
+            assert_eq!(
+                column(world.resource::<Terrain>()),
+                expected,
+                "tick {}",
+                tick
+            );
+        }
+
+        // flush with the floor, so another tick leaves it alone
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(GRAVITY_TICK_S));
+        schedule.run(&mut world);
+
+        let mut expected = vec![BlockType::SAND; 6];
+        expected[5] = BlockType::EMPTY;
+        assert_eq!(column(world.resource::<Terrain>()), expected);
+    }
+}
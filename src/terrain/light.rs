@@ -1,6 +1,8 @@
+use std::collections::VecDeque;
+
 use bevy::ecs::system::ResMut;
 
-use crate::Terrain;
+use crate::{Block, Terrain};
 
 pub struct LightNode {
     pub x: u32,
@@ -9,197 +11,435 @@ pub struct LightNode {
     pub value: u8,
 }
 
+/// Brightest a light level gets, full daylight
+pub const MAX_LIGHT: u8 = 15;
+/// Nodes processed per frame for each channel, removal and spreading
+/// together. Digging into a large cave or roofing over a wide courtyard
+/// relights it over a few frames instead of stalling one.
+const LIGHT_PASSES_PER_FRAME: u32 = 1000;
+
+/// The two kinds of light a block stores. Both spread the same way, losing
+/// one level per block and stopping at opaque blocks, with a few rules of
+/// their own on top.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LightChannel {
+    /// Light from blocks like torches and lamps. Sources keep their level
+    /// when the light around them is removed.
+    Torch,
+    /// Light from the sky. Full sunlight falls straight down without
+    /// losing strength, unless something dims it.
+    Sun,
+}
+
+impl LightChannel {
+    fn get(self, block: &Block) -> u8 {
+        match self {
+            LightChannel::Torch => block.light,
+            LightChannel::Sun => block.sunlight,
+        }
+    }
+
+    fn set(self, terrain: &mut Terrain, x: u32, y: u32, z: u32, value: u8) {
+        match self {
+            LightChannel::Torch => terrain.set_torchlight(x, y, z, value),
+            LightChannel::Sun => terrain.set_sunlight(x, y, z, value),
+        }
+    }
+
+    fn add(self, terrain: &mut Terrain, x: u32, y: u32, z: u32, value: u8) {
+        match self {
+            LightChannel::Torch => terrain.add_light(x, y, z, value),
+            LightChannel::Sun => terrain.add_sunlight(x, y, z, value),
+        }
+    }
+
+    fn add_queue(self, terrain: &mut Terrain) -> &mut VecDeque<LightNode> {
+        match self {
+            LightChannel::Torch => &mut terrain.lights_queue_add,
+            LightChannel::Sun => &mut terrain.sunlight_queue_add,
+        }
+    }
+
+    fn remove_queue(self, terrain: &mut Terrain) -> &mut VecDeque<LightNode> {
+        match self {
+            LightChannel::Torch => &mut terrain.lights_queue_remove,
+            LightChannel::Sun => &mut terrain.sunlight_queue_remove,
+        }
+    }
+}
+
 pub fn light_system(mut terrain: ResMut<Terrain>) {
-    let max_sunlight_passes = 1000;
-    let mut sunlight_passes = 0;
-
-    while !terrain.lights_queue_remove.is_empty() {
-        let node = terrain.lights_queue_remove.remove(0);
-
-        let world_x = node.x as i32;
-        let world_y = node.y as i32;
-        let world_z = node.z as i32;
-
-        let neighbors = [
-            [world_x + 1, world_y, world_z],
-            [world_x - 1, world_y, world_z],
-            [world_x, world_y + 1, world_z],
-            [world_x, world_y - 1, world_z],
-            [world_x, world_y, world_z - 1],
-            [world_x, world_y, world_z + 1],
-        ];
+    propagate_light(&mut terrain, LightChannel::Torch);
+    propagate_light(&mut terrain, LightChannel::Sun);
+}
+
+/// Works through the queued light changes of one channel. Light that lost
+/// its source is cleared first, then light spreads from the queued nodes.
+/// Spreading waits until the removal is done, since spreading from cells
+/// about to be cleared would leave stale light behind. Chunks are marked
+/// dirty by `Terrain::set_sunlight` and `Terrain::set_torchlight`.
+pub fn propagate_light(terrain: &mut Terrain, channel: LightChannel) {
+    let mut passes = 0;
+
+    while passes < LIGHT_PASSES_PER_FRAME {
+        let Some(node) = channel.remove_queue(terrain).pop_front() else {
+            break;
+        };
 
-        for [n_x, n_y, n_z] in neighbors {
-            let n_block = terrain.get_block_i32(n_x, n_y, n_z);
+        passes += 1;
+        unspread_light(terrain, channel, &node);
+    }
 
-            if n_block.is_oob() {
+    if !channel.remove_queue(terrain).is_empty() {
+        return;
+    }
+
+    while passes < LIGHT_PASSES_PER_FRAME {
+        let Some(node) = channel.add_queue(terrain).pop_front() else {
+            break;
+        };
+
+        passes += 1;
+        spread_light(terrain, channel, &node);
+    }
+}
+
+fn neighbors(node: &LightNode) -> [[i32; 3]; 6] {
+    let x = node.x as i32;
+    let y = node.y as i32;
+    let z = node.z as i32;
+
+    [
+        [x + 1, y, z],
+        [x - 1, y, z],
+        [x, y + 1, z],
+        [x, y - 1, z],
+        [x, y, z - 1],
+        [x, y, z + 1],
+    ]
+}
+
+/// Clears the neighbors that were lit by `node`, and queues the ones lit by
+/// something else to spread back into the gap.
+fn unspread_light(terrain: &mut Terrain, channel: LightChannel, node: &LightNode) {
+    for [n_x, n_y, n_z] in neighbors(node) {
+        let n_block = terrain.get_block_i32(n_x, n_y, n_z);
+
+        if n_block.is_oob() {
+            continue;
+        }
+
+        let n_x_u32 = n_x as u32;
+        let n_y_u32 = n_y as u32;
+        let n_z_u32 = n_z as u32;
+        let n_level = channel.get(&n_block);
+
+        // full sunlight below came straight down through this node
+        let is_column =
+            channel == LightChannel::Sun && n_level == MAX_LIGHT && n_y == node.y as i32 - 1;
+
+        if is_column || (n_level != 0 && n_level < node.value) {
+            if channel == LightChannel::Torch && n_block.is_light() {
+                let level = n_block.get_light_level();
+                channel.add(terrain, n_x_u32, n_y_u32, n_z_u32, level);
                 continue;
             }
 
-            if n_block.light != 0 && n_block.light < node.value {
-                let n_x_u32 = n_x as u32;
-                let n_y_u32 = n_y as u32;
-                let n_z_u32 = n_z as u32;
-
-                if n_block.is_light() {
-                    terrain.add_light(n_x_u32, n_y_u32, n_z_u32, n_block.get_light_level());
-                } else {
-                    terrain.set_torchlight(n_x_u32, n_y_u32, n_z_u32, 0);
-
-                    terrain.lights_queue_remove.push(LightNode {
-                        x: n_x_u32,
-                        y: n_y_u32,
-                        z: n_z_u32,
-                        value: n_block.light,
-                    });
+            channel.set(terrain, n_x_u32, n_y_u32, n_z_u32, 0);
+            channel.remove_queue(terrain).push_back(LightNode {
+                x: n_x_u32,
+                y: n_y_u32,
+                z: n_z_u32,
+                value: n_level,
+            });
+        } else if n_level >= node.value {
+            channel.add_queue(terrain).push_back(LightNode {
+                x: n_x_u32,
+                y: n_y_u32,
+                z: n_z_u32,
+                value: n_level,
+            });
+        }
+    }
+}
+
+/// Lights the neighbors of `node` that are more than a level darker
+fn spread_light(terrain: &mut Terrain, channel: LightChannel, node: &LightNode) {
+    let block = terrain.get_block(node.x, node.y, node.z);
+
+    // light sources like lamps can be opaque themselves
+    if channel == LightChannel::Sun && block.is_opaque() {
+        return;
+    }
+
+    let current = channel.get(&block);
+
+    for [n_x, n_y, n_z] in neighbors(node) {
+        let n_block = terrain.get_block_i32(n_x, n_y, n_z);
+
+        if n_block.is_opaque() {
+            continue;
+        }
+
+        let n_x_u32 = n_x as u32;
+        let n_y_u32 = n_y as u32;
+        let n_z_u32 = n_z as u32;
+        let n_level = channel.get(&n_block);
+
+        if channel == LightChannel::Sun && current == MAX_LIGHT {
+            let is_direct = n_y == node.y as i32 - 1 && !n_block.block.properties().dims_sunlight;
+
+            if is_direct {
+                if n_level != MAX_LIGHT {
+                    channel.add(terrain, n_x_u32, n_y_u32, n_z_u32, MAX_LIGHT);
                 }
-            } else if n_block.light >= node.value {
-                let n_x_u32 = n_x as u32;
-                let n_y_u32 = n_y as u32;
-                let n_z_u32 = n_z as u32;
-
-                terrain.lights_queue_add.push(LightNode {
-                    x: n_x_u32,
-                    y: n_y_u32,
-                    z: n_z_u32,
-                    value: n_block.light,
-                });
+                continue;
             }
+
+            if n_y == node.y as i32 + 1 {
+                continue;
+            }
+        }
+
+        if n_level + 2 <= current {
+            channel.add(terrain, n_x_u32, n_y_u32, n_z_u32, current - 1);
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashMap;
+
+    use super::*;
+    use crate::BlockType;
+
+    /// Two chunks wide, so light has to cross the boundary at x = 8
+    fn empty_world() -> Terrain {
+        Terrain::new(2, 1, 2, 8).unwrap()
+    }
 
-    while !terrain.lights_queue_add.is_empty() {
-        let node = terrain.lights_queue_add.remove(0);
+    fn settle(terrain: &mut Terrain) {
+        let mut frames = 0;
 
-        let world_x = node.x as i32;
-        let world_y = node.y as i32;
-        let world_z = node.z as i32;
+        while !terrain.lights_queue_add.is_empty()
+            || !terrain.lights_queue_remove.is_empty()
+            || !terrain.sunlight_queue_add.is_empty()
+            || !terrain.sunlight_queue_remove.is_empty()
+        {
+            propagate_light(terrain, LightChannel::Torch);
+            propagate_light(terrain, LightChannel::Sun);
 
-        let neighbors = [
-            [world_x + 1, world_y, world_z],
-            [world_x - 1, world_y, world_z],
-            [world_x, world_y + 1, world_z],
-            [world_x, world_y - 1, world_z],
-            [world_x, world_y, world_z - 1],
-            [world_x, world_y, world_z + 1],
+            frames += 1;
+            assert!(frames < 1000, "light never settled");
+        }
+    }
+
+    fn cells(terrain: &Terrain) -> impl Iterator<Item = [u32; 3]> {
+        let [sx, sy, sz] = [
+            terrain.world_size_x(),
+            terrain.world_size_y(),
+            terrain.world_size_z(),
         ];
 
-        let current_light = terrain.get_torchlight_xyz(node.x, node.y, node.z);
+        (0..sx).flat_map(move |x| (0..sy).flat_map(move |y| (0..sz).map(move |z| [x, y, z])))
+    }
+
+    /// Light levels worked out from scratch for the whole world, what the
+    /// incremental updates have to end up at
+    fn relit(terrain: &Terrain, channel: LightChannel) -> HashMap<[u32; 3], u8> {
+        let mut levels = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        for [x, y, z] in cells(terrain) {
+            let block = terrain.get_block(x, y, z);
+            let level = match channel {
+                LightChannel::Torch => block.get_light_level(),
+                // open to the sky all the way up
+                LightChannel::Sun => {
+                    let is_sky = (y..terrain.world_size_y()).all(|above| {
+                        let block = terrain.get_block(x, above, z);
+                        !block.is_opaque() && !block.block.properties().dims_sunlight
+                    });
+
+                    if is_sky {
+                        MAX_LIGHT
+                    } else {
+                        0
+                    }
+                }
+            };
+
+            if level > 0 {
+                levels.insert([x, y, z], level);
+                queue.push_back([x, y, z]);
+            }
+        }
 
-        for [n_x, n_y, n_z] in neighbors {
-            let n_block = terrain.get_block_i32(n_x, n_y, n_z);
+        while let Some([x, y, z]) = queue.pop_front() {
+            let level = levels[&[x, y, z]];
+            let node = LightNode {
+                x,
+                y,
+                z,
+                value: level,
+            };
 
-            if n_block.is_opaque() {
+            if channel == LightChannel::Sun && terrain.get_block(x, y, z).is_opaque() {
                 continue;
             }
 
-            if (n_block.light + 2) <= current_light {
-                let n_x_u32 = n_x as u32;
-                let n_y_u32 = n_y as u32;
-                let n_z_u32 = n_z as u32;
+            for [nx, ny, nz] in neighbors(&node) {
+                let n_block = terrain.get_block_i32(nx, ny, nz);
 
-                terrain.add_light(n_x_u32, n_y_u32, n_z_u32, current_light - 1);
+                if n_block.is_oob() || n_block.is_opaque() {
+                    continue;
+                }
+
+                let n_pos = [nx as u32, ny as u32, nz as u32];
+                let n_level = levels.get(&n_pos).copied().unwrap_or(0);
+
+                if level - 1 > n_level {
+                    levels.insert(n_pos, level - 1);
+                    queue.push_back(n_pos);
+                }
             }
         }
+
+        levels
     }
 
-    while !terrain.sunlight_queue_remove.is_empty() {
-        let node = terrain.sunlight_queue_remove.remove(0);
+    fn assert_relit(terrain: &Terrain, channel: LightChannel) {
+        let expected = relit(terrain, channel);
 
-        let world_x = node.x as i32;
-        let world_y = node.y as i32;
-        let world_z = node.z as i32;
+        for [x, y, z] in cells(terrain) {
+            let level = channel.get(&terrain.get_block(x, y, z));
+            let want = expected.get(&[x, y, z]).copied().unwrap_or(0);
+            assert_eq!(level, want, "light at {:?}", [x, y, z]);
+        }
+    }
 
-        let neighbors = [
-            [world_x + 1, world_y, world_z],
-            [world_x - 1, world_y, world_z],
-            [world_x, world_y + 1, world_z],
-            [world_x, world_y - 1, world_z],
-            [world_x, world_y, world_z - 1],
-            [world_x, world_y, world_z + 1],
-        ];
+    fn light_the_sky(terrain: &mut Terrain) {
+        let y = terrain.world_size_y() - 1;
 
-        for [n_x, n_y, n_z] in neighbors {
-            let n_block = terrain.get_block_i32(n_x, n_y, n_z);
-
-            if (n_block.sunlight == 15 && n_y == world_y - 1)
-                || (n_block.sunlight != 0 && n_block.sunlight < node.value)
-            {
-                let n_x_u32 = n_x as u32;
-                let n_y_u32 = n_y as u32;
-                let n_z_u32 = n_z as u32;
-
-                terrain.set_sunlight(n_x_u32, n_y_u32, n_z_u32, 0);
-                terrain.sunlight_queue_remove.push(LightNode {
-                    x: n_x_u32,
-                    y: n_y_u32,
-                    z: n_z_u32,
-                    value: n_block.sunlight,
-                });
-            } else if n_block.sunlight >= node.value {
-                let n_x_u32 = n_x as u32;
-                let n_y_u32 = n_y as u32;
-                let n_z_u32 = n_z as u32;
-
-                terrain.sunlight_queue_add.push(LightNode {
-                    x: n_x_u32,
-                    y: n_y_u32,
-                    z: n_z_u32,
-                    value: 0,
-                });
+        for x in 0..terrain.world_size_x() {
+            for z in 0..terrain.world_size_z() {
+                terrain.add_sunlight(x, y, z, MAX_LIGHT);
             }
         }
     }
 
-    while !terrain.sunlight_queue_add.is_empty() {
-        sunlight_passes += 1;
-        if sunlight_passes > max_sunlight_passes {
-            break;
-        }
+    #[test]
+    fn overlapping_torches_light_their_union() {
+        let mut terrain = empty_world();
+        terrain.set_block(5, 2, 6, BlockType::TORCH);
+        terrain.set_block(10, 4, 7, BlockType::TORCH);
+        settle(&mut terrain);
 
-        let node = terrain.sunlight_queue_add.remove(0);
+        assert_relit(&terrain, LightChannel::Torch);
+        assert!(terrain.get_torchlight_xyz(8, 3, 6) > terrain.get_torchlight_xyz(0, 3, 6));
+    }
 
-        let block_detail = terrain.get_block(node.x, node.y, node.z);
+    #[test]
+    fn removing_one_torch_keeps_the_other_lit() {
+        let mut terrain = empty_world();
+        terrain.set_block(5, 2, 6, BlockType::TORCH);
+        terrain.set_block(10, 4, 7, BlockType::TORCH);
+        settle(&mut terrain);
+
+        terrain.set_block(10, 4, 7, BlockType::EMPTY);
+        settle(&mut terrain);
+        assert_relit(&terrain, LightChannel::Torch);
+
+        // and back again
+        terrain.set_block(10, 4, 7, BlockType::TORCH);
+        settle(&mut terrain);
+        assert_relit(&terrain, LightChannel::Torch);
+    }
 
-        if block_detail.is_opaque() {
-            continue;
+    #[test]
+    fn a_new_roof_shades_the_ground() {
+        let mut terrain = empty_world();
+
+        for x in 0..terrain.world_size_x() {
+            for z in 0..terrain.world_size_z() {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+            }
         }
 
-        let world_x = node.x as i32;
-        let world_y = node.y as i32;
-        let world_z = node.z as i32;
+        light_the_sky(&mut terrain);
+        settle(&mut terrain);
+        assert_relit(&terrain, LightChannel::Sun);
+        assert_eq!(terrain.get_sunlight_xyz(7, 1, 7), MAX_LIGHT);
+
+        // a roof over both chunks
+        terrain.fill_region([3, 4, 3], [12, 4, 12], BlockType::STONE);
+        settle(&mut terrain);
+        assert_relit(&terrain, LightChannel::Sun);
+        assert!(terrain.get_sunlight_xyz(7, 1, 7) < MAX_LIGHT);
+
+        terrain.fill_region([3, 4, 3], [12, 4, 12], BlockType::EMPTY);
+        settle(&mut terrain);
+        assert_relit(&terrain, LightChannel::Sun);
+        assert_eq!(terrain.get_sunlight_xyz(7, 1, 7), MAX_LIGHT);
+    }
 
-        let neighbors = [
-            [world_x + 1, world_y, world_z],
-            [world_x - 1, world_y, world_z],
-            [world_x, world_y + 1, world_z],
-            [world_x, world_y - 1, world_z],
-            [world_x, world_y, world_z - 1],
-            [world_x, world_y, world_z + 1],
-        ];
+    #[test]
+    fn a_wall_splits_a_lit_room() {
+        let mut terrain = empty_world();
 
-        for [n_x, n_y, n_z] in neighbors {
-            let n_block = terrain.get_block_i32(n_x, n_y, n_z);
+        // a closed stone room, 1..15 by 1..4 by 1..7
+        for [x, y, z] in cells(&terrain).collect::<Vec<_>>() {
+            let is_inside = (1..15).contains(&x) && (1..4).contains(&y) && (1..7).contains(&z);
 
-            if n_block.is_opaque() {
-                continue;
+            if !is_inside {
+                terrain.set_block(x, y, z, BlockType::STONE);
             }
+        }
 
-            if n_block.sunlight + 2 <= block_detail.sunlight
-                || (block_detail.sunlight == 15 && n_block.sunlight != 15 && n_y == world_y - 1)
-            {
-                let n_x_u32 = n_x as u32;
-                let n_y_u32 = n_y as u32;
-                let n_z_u32 = n_z as u32;
+        terrain.set_block(4, 1, 3, BlockType::TORCH);
+        settle(&mut terrain);
+        assert_relit(&terrain, LightChannel::Torch);
+        assert!(terrain.get_torchlight_xyz(10, 1, 3) > 0);
+
+        // the wall sits on the chunk boundary
+        terrain.fill_region([8, 1, 1], [8, 3, 6], BlockType::STONE);
+        settle(&mut terrain);
+        assert_relit(&terrain, LightChannel::Torch);
+        assert_eq!(terrain.get_torchlight_xyz(10, 1, 3), 0);
+        assert!(terrain.get_torchlight_xyz(7, 1, 3) > 0);
+    }
 
-                if block_detail.sunlight == 15 && n_y == world_y - 1 {
-                    terrain.add_sunlight(n_x_u32, n_y_u32, n_z_u32, block_detail.sunlight);
-                } else if block_detail.sunlight == 15 && n_y == world_y + 1 {
-                    continue;
-                } else {
-                    terrain.add_sunlight(n_x_u32, n_y_u32, n_z_u32, block_detail.sunlight - 1);
-                }
-            }
-        }
+    #[test]
+    fn a_shaft_lets_the_sun_into_a_cave() {
+        let mut terrain = empty_world();
+
+        // solid ground up to the sky layer, with a closed cave 2..=13 by 1..=2
+        terrain.fill_region([0, 0, 0], [15, 6, 15], BlockType::STONE);
+        terrain.fill_region([2, 1, 2], [13, 2, 13], BlockType::EMPTY);
+        light_the_sky(&mut terrain);
+        settle(&mut terrain);
+        assert_relit(&terrain, LightChannel::Sun);
+        assert_eq!(terrain.get_sunlight_xyz(7, 1, 7), 0);
+
+        // a 1-wide shaft down into the cave roof
+        terrain.fill_region([7, 3, 7], [7, 6, 7], BlockType::EMPTY);
+        settle(&mut terrain);
+        assert_relit(&terrain, LightChannel::Sun);
+
+        // straight down the shaft at full strength, then one less per step
+        // across the cave floor, past the chunk boundary at x = 8
+        assert_eq!(terrain.get_sunlight_xyz(7, 4, 7), MAX_LIGHT);
+        assert_eq!(terrain.get_sunlight_xyz(7, 1, 7), MAX_LIGHT);
+        assert_eq!(terrain.get_sunlight_xyz(10, 1, 7), MAX_LIGHT - 3);
+        assert_eq!(terrain.get_sunlight_xyz(7, 1, 2), MAX_LIGHT - 5);
+        assert_eq!(terrain.get_sunlight_xyz(13, 1, 13), MAX_LIGHT - 12);
+        assert_eq!(terrain.get_sunlight_xyz(13, 2, 13), MAX_LIGHT - 12);
+
+        // filling the shaft back in puts the cave out again
+        terrain.set_block(7, 6, 7, BlockType::STONE);
+        settle(&mut terrain);
+        assert_relit(&terrain, LightChannel::Sun);
+        assert_eq!(terrain.get_sunlight_xyz(7, 1, 7), 0);
     }
 }
@@ -0,0 +1,230 @@
+use bevy::{
+    ecs::{
+        event::{EventReader, EventWriter},
+        query::With,
+        system::{Local, Query, Res, ResMut, Resource},
+    },
+    time::Time,
+    transform::components::Transform,
+    utils::HashMap,
+};
+
+use crate::{colonists::Colonist, BlockChangedEvent, BlockType, Terrain};
+
+const MECHANISM_TICK_S: f32 = 0.25;
+/// Seconds a stepped on plate stays down before it resets
+const PLATE_RESET_S: f32 = 5.;
+/// A new plate opens every mechanism gate within this many blocks of it
+const PLATE_LINK_RANGE: u32 = 4;
+
+/// What a pressure plate does when stepped on: every target block is
+/// changed to its type, e.g. a `MECHANISM` gate to `EMPTY`. The targets get
+/// their old types back when the plate resets.
+#[derive(Clone)]
+pub struct MechanismChain {
+    pub trigger: [u32; 3],
+    pub targets: Vec<([u32; 3], BlockType)>,
+}
+
+struct ActivePlate {
+    timer_s: f32,
+    /// Targets, the types they had before the plate went down, and the
+    /// types the plate set
+    restore: Vec<([u32; 3], BlockType, BlockType)>,
+}
+
+/// Every `MechanismChain` in the world, and the plates that are currently
+/// held down
+#[derive(Resource, Default)]
+pub struct Mechanisms {
+    pub chains: Vec<MechanismChain>,
+    active: HashMap<[u32; 3], ActivePlate>,
+}
+
+fn is_plate(block: BlockType) -> bool {
+    block == BlockType::PRESSURE_PLATE || block == BlockType::PLATE_ACTIVE
+}
+
+/// Chains a newly placed plate to the `MECHANISM` gates around it, which
+/// open while it is down. Removing a plate drops its chain. Plates going
+/// down and back up are left alone.
+pub fn link_pressure_plates(
+    terrain: Res<Terrain>,
+    mut mechanisms: ResMut<Mechanisms>,
+    mut ev_block_changed: EventReader<BlockChangedEvent>,
+) {
+    for ev in ev_block_changed.read() {
+        if is_plate(ev.previous) == is_plate(ev.value) {
+            continue;
+        }
+
+        mechanisms.chains.retain(|chain| chain.trigger != ev.pos);
+
+        if !is_plate(ev.value) {
+            continue;
+        }
+
+        let [x, y, z] = ev.pos;
+        let mut targets = vec![];
+
+        for tx in x.saturating_sub(PLATE_LINK_RANGE)..=x + PLATE_LINK_RANGE {
+            for ty in y.saturating_sub(PLATE_LINK_RANGE)..=y + PLATE_LINK_RANGE {
+                for tz in z.saturating_sub(PLATE_LINK_RANGE)..=z + PLATE_LINK_RANGE {
+                    if !terrain.is_oob_u32(tx, ty, tz)
+                        && terrain.get_block(tx, ty, tz).block == BlockType::MECHANISM
+                    {
+                        targets.push(([tx, ty, tz], BlockType::EMPTY));
+                    }
+                }
+            }
+        }
+
+        if !targets.is_empty() {
+            mechanisms.chains.push(MechanismChain {
+                trigger: ev.pos,
+                targets,
+            });
+        }
+    }
+}
+
+/// Pushes down the pressure plates colonists stand on, firing their chains,
+/// and lets plates back up after `PLATE_RESET_S`.
+pub fn tick_mechanisms(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    mut mechanisms: ResMut<Mechanisms>,
+    mut timer: Local<f32>,
+    q_colonists: Query<&Transform, With<Colonist>>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+) {
+    *timer += time.delta_seconds();
+
+    if *timer < MECHANISM_TICK_S {
+        return;
+    }
+
+    let elapsed = *timer;
+    *timer = 0.;
+
+    let mut reset = vec![];
+
+    for (pos, plate) in mechanisms.active.iter_mut() {
+        plate.timer_s += elapsed;
+
+        if plate.timer_s >= PLATE_RESET_S {
+            reset.push(*pos);
+        }
+    }
+
+    for pos in reset {
+        let Some(plate) = mechanisms.active.remove(&pos) else {
+            continue;
+        };
+
+        for ([x, y, z], previous, value) in plate.restore {
+            // leave targets alone that were changed since, e.g. built over
+            if terrain.get_block(x, y, z).block != value {
+                continue;
+            }
+
+            let change = terrain.set_block(x, y, z, previous);
+            ev_block_changed.send(change.into());
+        }
+
+        let [x, y, z] = pos;
+
+        // the plate may have been mined out in the meantime
+        if terrain.get_block(x, y, z).block == BlockType::PLATE_ACTIVE {
+            let change = terrain.set_block(x, y, z, BlockType::PRESSURE_PLATE);
+            ev_block_changed.send(change.into());
+        }
+    }
+
+    for transform in q_colonists.iter() {
+        let x = transform.translation.x as u32;
+        let y = transform.translation.y as u32;
+        let z = transform.translation.z as u32;
+
+        if y == 0 || terrain.get_block(x, y - 1, z).block != BlockType::PRESSURE_PLATE {
+            continue;
+        }
+
+        let pos = [x, y - 1, z];
+        let change = terrain.set_block(x, y - 1, z, BlockType::PLATE_ACTIVE);
+        ev_block_changed.send(change.into());
+
+        let mut restore = vec![];
+
+        for chain in mechanisms.chains.iter().filter(|c| c.trigger == pos) {
+            for ([tx, ty, tz], value) in chain.targets.iter().copied() {
+                let change = terrain.set_block(tx, ty, tz, value);
+
+                if change.previous != value {
+                    restore.push(([tx, ty, tz], change.previous, value));
+                }
+
+                ev_block_changed.send(change.into());
+            }
+        }
+
+        mechanisms.active.insert(
+            pos,
+            ActivePlate {
+                timer_s: 0.,
+                restore,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{
+        event::Events,
+        schedule::{IntoSystemConfigs, Schedule},
+        world::World,
+    };
+
+    use super::*;
+
+    #[test]
+    fn stepping_on_a_plate_opens_a_gate() {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+
+        for x in 0..8 {
+            for z in 0..8 {
+                terrain.set_block(x, 0, z, BlockType::STONE);
+            }
+        }
+
+        terrain.set_block(4, 1, 1, BlockType::MECHANISM);
+        let plate = terrain.set_block(1, 0, 1, BlockType::PRESSURE_PLATE);
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<Mechanisms>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<BlockChangedEvent>>();
+        world.send_event(BlockChangedEvent::from(plate));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((link_pressure_plates, tick_mechanisms).chain());
+        schedule.run(&mut world);
+
+        world.spawn((Colonist::default(), Transform::from_xyz(1.5, 1., 1.5)));
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(MECHANISM_TICK_S));
+        schedule.run(&mut world);
+
+        let terrain = world.resource::<Terrain>();
+        assert_eq!(terrain.get_block(1, 0, 1).block, BlockType::PLATE_ACTIVE);
+        assert_eq!(terrain.get_block(4, 1, 1).block, BlockType::EMPTY);
+
+        // the plate going down didn't chain it twice
+        assert_eq!(world.resource::<Mechanisms>().chains.len(), 1);
+    }
+}
@@ -12,11 +12,20 @@ use bevy::{
     },
 };
 
-use crate::{Block, BlockFace, ATTRIBUTE_BLOCK_LIGHT, ATTRIBUTE_BLOCK_PACKED};
+use crate::{Block, BlockFace, ATTRIBUTE_BLOCK_PACKED};
 
 #[derive(Resource)]
 pub struct ChunkMaterialRes {
     pub handle: Handle<ChunkMaterial>,
+    /// Same as `handle` but blended, for the transparent chunk meshes
+    pub transparent_handle: Handle<ChunkMaterial>,
+}
+
+impl ChunkMaterialRes {
+    /// Both materials, uniforms have to be kept the same across them
+    pub fn handles(&self) -> [&Handle<ChunkMaterial>; 2] {
+        [&self.handle, &self.transparent_handle]
+    }
 }
 
 #[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
@@ -30,6 +39,15 @@ pub struct ChunkMaterial {
     pub texture_count: u32,
     #[uniform[4]]
     pub terrain_slice_y: u32,
+    /// The minimum light level (0-1) applied to faces that receive no light
+    #[uniform[5]]
+    pub ambient_light: f32,
+    /// Scales sunlight only, torchlight is unaffected by the time of day
+    #[uniform[6]]
+    pub sun_intensity: f32,
+    #[uniform[7]]
+    pub sun_color: Color,
+    pub alpha_mode: AlphaMode,
 }
 
 impl Material for ChunkMaterial {
@@ -42,7 +60,7 @@ impl Material for ChunkMaterial {
     }
 
     fn alpha_mode(&self) -> AlphaMode {
-        AlphaMode::Mask(0.5)
+        self.alpha_mode
     }
 
     fn specialize(
@@ -54,25 +72,44 @@ impl Material for ChunkMaterial {
         let vertex_layout = layout.get_layout(&[
             Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
             ATTRIBUTE_BLOCK_PACKED.at_shader_location(1),
-            ATTRIBUTE_BLOCK_LIGHT.at_shader_location(2),
         ])?;
         descriptor.vertex.buffers = vec![vertex_layout];
         Ok(())
     }
 }
 
-pub fn pack_block(block: Block, dir: BlockFace, ao: VertexCornerCount) -> u32 {
-    let t_id = block.texture_idx(); // four bits, 0-15
+/// Pack a block face into a single u32. `tile` is the atlas tile to draw,
+/// see `Block::texture_variant`. `cracks` is the damage stage, see
+/// `Terrain::get_damage_stage`. `light` is the (empty) block that the face
+/// is exposed to, which is where the light values are sampled from.
+pub fn pack_block(
+    tile: u32,
+    block: Block,
+    cracks: u32,
+    dir: BlockFace,
+    ao: VertexCornerCount,
+    light: Block,
+) -> u32 {
     let f_id = dir.bit(); // three bits, 0-7
     let ao_id = ao.bit(); // two bits, 0-3
     let mine_bit = if block.flag_mine { 1 } else { 0 }; // one bit;
     let blueprint_bit = if block.flag_blueprint { 1 } else { 0 }; // one bit;
+    let transparent_bit = if block.is_transparent() { 1 } else { 0 }; // one bit;
+    let emissive_bit = if block.is_emissive() { 1 } else { 0 }; // one bit;
+    let torchlight = light.light as u32; // four bits, 0-15
+    let sunlight = light.sunlight as u32; // four bits, 0-15
+    let crack_id = cracks; // two bits, 0-3
 
-    (t_id & 15)
-        | ((f_id & 7) << 4)
-        | ((ao_id & 3) << 7)
-        | ((mine_bit & 1) << 9)
-        | ((blueprint_bit & 1) << 10)
+    (tile & 255)
+        | ((f_id & 7) << 8)
+        | ((ao_id & 3) << 11)
+        | ((mine_bit & 1) << 13)
+        | ((blueprint_bit & 1) << 14)
+        | ((torchlight & 15) << 15)
+        | ((sunlight & 15) << 19)
+        | ((transparent_bit & 1) << 23)
+        | ((crack_id & 3) << 24)
+        | ((emissive_bit & 1) << 26)
 }
 
 pub enum VertexCornerCount {
@@ -2,24 +2,23 @@ use bevy::{
     math::Vec3A,
     prelude::*,
     render::{
-        mesh::{Indices, MeshVertexAttribute, PrimitiveTopology},
+        mesh::{Indices, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues},
         primitives::Aabb,
         render_asset::RenderAssetUsages,
         render_resource::VertexFormat,
         texture::{ImageLoaderSettings, ImageSampler},
     },
+    utils::{Duration, HashMap, HashSet, Instant},
 };
 use ndshape::AbstractShape;
 
 use crate::{
-    colonists::PartitionEvent, pack_block, Block, BlockFace, Chunk, ChunkMaterial,
+    controls::MainCamera, pack_block, Block, BlockFace, BlockType, Chunk, ChunkMaterial,
     ChunkMaterialRes, Neighbor, Terrain, TerrainSlice, TerrainSliceChanged, VertexCornerCount,
 };
 
 pub const ATTRIBUTE_BLOCK_PACKED: MeshVertexAttribute =
     MeshVertexAttribute::new("BlockPacked", 9985136798, VertexFormat::Uint32);
-pub const ATTRIBUTE_BLOCK_LIGHT: MeshVertexAttribute =
-    MeshVertexAttribute::new("BlockLight", 98218357661, VertexFormat::Uint32);
 
 pub fn setup_chunk_meshes(
     mut cmd: Commands,
@@ -28,106 +27,405 @@ pub fn setup_chunk_meshes(
     mut materials: ResMut<Assets<ChunkMaterial>>,
     terrain: Res<Terrain>,
     slice: Res<TerrainSlice>,
+    mut stats: ResMut<MeshStats>,
 ) {
     let settings = |s: &mut ImageLoaderSettings| s.sampler = ImageSampler::nearest();
     let terrain_texture: Handle<Image> =
         asset_server.load_with_settings("textures/comfy.png", settings);
 
-    let chunk_material = materials.add(ChunkMaterial {
+    let material = ChunkMaterial {
         color: Color::YELLOW_GREEN,
         texture: terrain_texture,
         texture_count: 8,
         terrain_slice_y: slice.get_value(),
+        ambient_light: 0.1,
+        sun_intensity: 1.,
+        sun_color: Color::rgb(1., 0.91, 0.56),
+        alpha_mode: AlphaMode::Mask(0.5),
+    };
+    let transparent_material = materials.add(ChunkMaterial {
+        alpha_mode: AlphaMode::Blend,
+        ..material.clone()
     });
+    let chunk_material = materials.add(material);
 
-    cmd.insert_resource(ChunkMaterialRes {
-        handle: chunk_material.clone(),
-    });
+    let chunk_material_res = ChunkMaterialRes {
+        handle: chunk_material,
+        transparent_handle: transparent_material,
+    };
 
     for chunk_idx in 0..terrain.chunk_count {
-        let chunk_pos = terrain.shape.delinearize(chunk_idx);
-        let x = chunk_pos[0] * terrain.chunk_size;
-        let y = chunk_pos[1] * terrain.chunk_size;
-        let z = chunk_pos[2] * terrain.chunk_size;
-        let mesh_data = ChunkMeshData::default();
-        // let mesh_data = build_chunk_mesh(&terrain, chunk_idx);
-        let mesh = Mesh::new(
-            PrimitiveTopology::TriangleList,
-            RenderAssetUsages::default(),
-        )
-        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, mesh_data.positions)
-        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, mesh_data.normals)
-        .with_inserted_attribute(ATTRIBUTE_BLOCK_PACKED, mesh_data.packed)
-        .with_inserted_attribute(ATTRIBUTE_BLOCK_LIGHT, mesh_data.light)
-        .with_inserted_indices(Indices::U32(mesh_data.indicies));
-
-        let mesh_handle = meshes.add(mesh);
-        let x_f32 = x as f32;
-        let y_f32 = y as f32;
-        let z_f32 = z as f32;
-        let size = terrain.chunk_size as f32 / 2.;
-
-        cmd.spawn((
-            Chunk {
-                chunk_idx,
-                mesh_handle: mesh_handle.clone(),
-                world_x: x,
-                world_y: y,
-                world_z: z,
-            },
+        // all-air chunks get an entity once something is placed in them
+        if terrain.is_chunk_air(chunk_idx) {
+            continue;
+        }
+
+        spawn_chunk(
+            &mut cmd,
+            &mut meshes,
+            &chunk_material_res,
+            &terrain,
+            chunk_idx,
+        );
+        stats.record(chunk_idx, &ChunkMeshLayers::default(), Duration::ZERO);
+    }
+
+    cmd.insert_resource(chunk_material_res);
+}
+
+fn empty_chunk_mesh() -> Mesh {
+    let mesh_data = ChunkMeshData::default();
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, mesh_data.positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, mesh_data.normals)
+    .with_inserted_attribute(ATTRIBUTE_BLOCK_PACKED, mesh_data.packed)
+    .with_inserted_indices(Indices::U32(mesh_data.indicies))
+}
+
+fn spawn_chunk(
+    cmd: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &ChunkMaterialRes,
+    terrain: &Terrain,
+    chunk_idx: u32,
+) {
+    let chunk_pos = terrain.shape.delinearize(chunk_idx);
+    let x = chunk_pos[0] * terrain.chunk_size;
+    let y = chunk_pos[1] * terrain.chunk_size;
+    let z = chunk_pos[2] * terrain.chunk_size;
+
+    let mesh_handle = meshes.add(empty_chunk_mesh());
+    let transparent_mesh_handle = meshes.add(empty_chunk_mesh());
+    let x_f32 = x as f32;
+    let y_f32 = y as f32;
+    let z_f32 = z as f32;
+    let size = terrain.chunk_size as f32 / 2.;
+    let aabb = Aabb {
+        center: Vec3A::new(size, size, size),
+        half_extents: Vec3A::new(size, size, size),
+    };
+
+    cmd.spawn((
+        Chunk {
+            chunk_idx,
+            mesh_handle: mesh_handle.clone(),
+            transparent_mesh_handle: transparent_mesh_handle.clone(),
+            world_x: x,
+            world_y: y,
+            world_z: z,
+            face_count: 0,
+            lod: 1,
+            needs_remesh: false,
+        },
+        MaterialMeshBundle {
+            mesh: mesh_handle.clone(),
+            material: materials.handle.clone(),
+            transform: Transform::from_xyz(x_f32, y_f32, z_f32),
+            ..default()
+        },
+        aabb,
+    ))
+    .with_children(|parent| {
+        parent.spawn((
             MaterialMeshBundle {
-                mesh: mesh_handle.clone(),
-                material: chunk_material.clone(),
-                transform: Transform::from_xyz(x_f32, y_f32, z_f32),
+                mesh: transparent_mesh_handle,
+                material: materials.transparent_handle.clone(),
                 ..default()
             },
-            Aabb {
-                center: Vec3A::new(size, size, size),
-                half_extents: Vec3A::new(size, size, size),
-            },
+            aabb,
         ));
+    });
+}
+
+/// Spawns entities for dirty chunks that had no blocks until now. Dirty chunks
+/// that are still all air have nothing to mesh, but still need partitioning.
+pub fn spawn_filled_chunks(
+    mut cmd: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut terrain: ResMut<Terrain>,
+    chunk_material_res: Res<ChunkMaterialRes>,
+    chunks: Query<&Chunk>,
+) {
+    let spawned = chunks
+        .iter()
+        .map(|chunk| chunk.chunk_idx)
+        .collect::<HashSet<_>>();
+
+    for chunk_idx in 0..terrain.chunk_count {
+        if !terrain.get_chunk_dirty(chunk_idx) || spawned.contains(&chunk_idx) {
+            continue;
+        }
+
+        let is_air = terrain.is_chunk_air(chunk_idx);
+
+        if is_air {
+            terrain.set_chunk_dirty(chunk_idx, false);
+            continue;
+        }
+
+        spawn_chunk(
+            &mut cmd,
+            &mut meshes,
+            &chunk_material_res,
+            &terrain,
+            chunk_idx,
+        );
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct ChunkMeshCounts {
+    pub vertices: usize,
+    pub indices: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct MeshStats {
+    pub chunks: HashMap<u32, ChunkMeshCounts>,
+    pub total_vertices: usize,
+    pub total_indices: usize,
+    pub rebuilds_this_frame: u32,
+    pub rebuilds_total: u64,
+    pub rebuild_time: Duration,
+}
+
+impl MeshStats {
+    pub fn record(&mut self, chunk_idx: u32, data: &ChunkMeshLayers, elapsed: Duration) {
+        let counts = ChunkMeshCounts {
+            vertices: data.opaque.positions.len() + data.transparent.positions.len(),
+            indices: data.opaque.indicies.len() + data.transparent.indicies.len(),
+        };
+
+        if let Some(previous) = self.chunks.insert(chunk_idx, counts) {
+            self.total_vertices -= previous.vertices;
+            self.total_indices -= previous.indices;
+        }
+
+        self.total_vertices += counts.vertices;
+        self.total_indices += counts.indices;
+        self.rebuilds_this_frame += 1;
+        self.rebuilds_total += 1;
+        self.rebuild_time += elapsed;
+    }
+
+    pub fn forget(&mut self, chunk_idx: u32) {
+        if let Some(previous) = self.chunks.remove(&chunk_idx) {
+            self.total_vertices -= previous.vertices;
+            self.total_indices -= previous.indices;
+        }
+    }
+
+    pub fn report(&self) -> String {
+        let average_ms = if self.rebuilds_total > 0 {
+            self.rebuild_time.as_secs_f64() * 1000. / self.rebuilds_total as f64
+        } else {
+            0.
+        };
+
+        format!(
+            "chunks={} vertices={} indices={} rebuilds(frame)={} rebuilds(total)={} rebuild_time={:.2}ms avg={:.3}ms",
+            self.chunks.len(),
+            self.total_vertices,
+            self.total_indices,
+            self.rebuilds_this_frame,
+            self.rebuilds_total,
+            self.rebuild_time.as_secs_f64() * 1000.,
+            average_ms,
+        )
+    }
+}
+
+pub fn mesh_stats_report(
+    stats: Res<MeshStats>,
+    terrain: Res<Terrain>,
+    input_keys: Res<ButtonInput<KeyCode>>,
+) {
+    if input_keys.just_pressed(KeyCode::F3) {
+        println!("{}", stats.report());
+
+        let (bytes, compact) = terrain.chunk_memory();
+        println!(
+            "chunk storage {} KiB, {} chunks compacted",
+            bytes / 1024,
+            compact
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct ChunkMeshSettings {
+    /// How many dirty chunks can be rebuilt in a single frame. The rest
+    /// stay dirty and are picked up on following frames.
+    pub max_rebuilds_per_frame: usize,
+    /// Camera distance past which chunks are meshed at 2x and 4x block stride.
+    pub lod_distances: [f32; 2],
+    /// Extra distance the camera has to cross before a chunk changes LOD, so
+    /// chunks sitting right on a threshold don't flicker between meshes.
+    pub lod_hysteresis: f32,
+}
+
+impl Default for ChunkMeshSettings {
+    fn default() -> Self {
+        Self {
+            max_rebuilds_per_frame: 4,
+            lod_distances: [96., 160.],
+            lod_hysteresis: 8.,
+        }
+    }
+}
+
+impl ChunkMeshSettings {
+    pub fn get_lod(&self, current: u32, distance: f32) -> u32 {
+        let mut lod = 1;
+
+        for (i, threshold) in self.lod_distances.iter().enumerate() {
+            let candidate = 2 << i;
+            let bias = if candidate > current {
+                self.lod_hysteresis
+            } else {
+                -self.lod_hysteresis
+            };
+
+            if distance > threshold + bias {
+                lod = candidate;
+            }
+        }
+
+        lod
+    }
+}
+
+pub fn update_chunk_lod(
+    settings: Res<ChunkMeshSettings>,
+    terrain: Res<Terrain>,
+    mut chunks: Query<&mut Chunk>,
+    cameras: Query<&GlobalTransform, With<MainCamera>>,
+) {
+    let Ok(camera) = cameras.get_single() else {
+        return;
+    };
+
+    let camera_pos = camera.translation();
+    let half_size = terrain.chunk_size as f32 / 2.;
+
+    for mut chunk in chunks.iter_mut() {
+        let center = Vec3::new(
+            chunk.world_x as f32 + half_size,
+            chunk.world_y as f32 + half_size,
+            chunk.world_z as f32 + half_size,
+        );
+        let lod = settings.get_lod(chunk.lod, camera_pos.distance(center));
+
+        if lod != chunk.lod {
+            chunk.lod = lod;
+            chunk.needs_remesh = true;
+        }
     }
 }
 
 pub fn process_dirty_chunks(
+    mut cmd: Commands,
     mut terrain: ResMut<Terrain>,
     mut meshes: ResMut<Assets<Mesh>>,
-    chunks: Query<&Chunk>,
+    settings: Res<ChunkMeshSettings>,
+    terrain_slice: Res<TerrainSlice>,
+    mut chunks: Query<(Entity, &mut Chunk)>,
+    mut mesh_data: Local<ChunkMeshLayers>,
+    mut stats: ResMut<MeshStats>,
+    cameras: Query<&GlobalTransform, With<MainCamera>>,
     mut ev_terrain_slice: EventWriter<TerrainSliceChanged>,
-    mut ev_partition: EventWriter<PartitionEvent>,
 ) {
-    let maximum = 1;
-    let mut cur = 0;
     let mut update_slice = false;
+    stats.rebuilds_this_frame = 0;
+
+    let camera_pos = cameras
+        .get_single()
+        .map(|transform| transform.translation())
+        .unwrap_or(Vec3::ZERO);
+    let slice_y = terrain_slice.get_value();
+    let half_size = terrain.chunk_size as f32 / 2.;
+
+    // nearest chunks first, chunks that are entirely sliced out go last
+    let mut dirty_chunks = chunks
+        .iter()
+        .filter(|(_, chunk)| chunk.needs_remesh || terrain.get_chunk_dirty(chunk.chunk_idx))
+        .map(|(entity, chunk)| {
+            let center = Vec3::new(
+                chunk.world_x as f32 + half_size,
+                chunk.world_y as f32 + half_size,
+                chunk.world_z as f32 + half_size,
+            );
+            let is_sliced_out = chunk.world_y >= slice_y;
+
+            (entity, is_sliced_out, camera_pos.distance_squared(center))
+        })
+        .collect::<Vec<_>>();
+
+    dirty_chunks.sort_by(|a, b| a.1.cmp(&b.1).then(a.2.total_cmp(&b.2)));
+
+    let rebuilds = dirty_chunks
+        .into_iter()
+        .take(settings.max_rebuilds_per_frame)
+        .map(|(entity, _, _)| entity)
+        .collect::<Vec<_>>();
+
+    for entity in rebuilds {
+        let Ok((_, mut chunk)) = chunks.get_mut(entity) else {
+            continue;
+        };
+
+        // the chunk was emptied, drop its entity until something fills it again
+        if terrain.is_chunk_air(chunk.chunk_idx) {
+            meshes.remove(chunk.mesh_handle.clone());
+            meshes.remove(chunk.transparent_mesh_handle.clone());
+            cmd.entity(entity).despawn_recursive();
+            stats.forget(chunk.chunk_idx);
+            update_slice = true;
+
+            terrain.set_chunk_dirty(chunk.chunk_idx, false);
+            continue;
+        }
 
-    chunks.iter().for_each(|chunk| {
-        let is_dirty = terrain.get_chunk_dirty(chunk.chunk_idx);
+        let is_unchanged = meshes
+            .get(chunk.mesh_handle.clone())
+            .is_none_or(|mesh| mesh.count_vertices() == 0)
+            && meshes
+                .get(chunk.transparent_mesh_handle.clone())
+                .is_none_or(|mesh| mesh.count_vertices() == 0)
+            && terrain
+                .get_chunk(chunk.chunk_idx)
+                .is_some_and(|c| !c.has_rendered_blocks());
+
+        if !is_unchanged {
+            let start = Instant::now();
+            mesh_data.clear();
+            mesh_data.opaque.reserve(chunk.face_count as usize);
+            if chunk.lod > 1 {
+                build_chunk_mesh_lod(terrain.as_ref(), chunk.chunk_idx, chunk.lod, &mut mesh_data);
+            } else {
+                build_chunk_mesh(terrain.as_ref(), chunk.chunk_idx, &mut mesh_data);
+            }
+            chunk.face_count = mesh_data.opaque.face_count();
+            stats.record(chunk.chunk_idx, &mesh_data, start.elapsed());
 
-        if !is_dirty {
-            return;
-        }
+            if let Some(mesh) = meshes.get_mut(chunk.mesh_handle.clone()) {
+                mesh_data.opaque.write_into(mesh);
+            }
 
-        cur += 1;
-        if cur > maximum {
-            return;
+            if let Some(mesh) = meshes.get_mut(chunk.transparent_mesh_handle.clone()) {
+                mesh_data.transparent.write_into(mesh);
+            }
         }
 
-        if let Some(mesh) = meshes.get_mut(chunk.mesh_handle.clone()) {
-            let mesh_data = build_chunk_mesh(terrain.as_ref(), chunk.chunk_idx);
-            mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, mesh_data.positions);
-            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, mesh_data.normals);
-            mesh.insert_attribute(ATTRIBUTE_BLOCK_PACKED, mesh_data.packed);
-            mesh.insert_attribute(ATTRIBUTE_BLOCK_LIGHT, mesh_data.light);
-            mesh.insert_indices(Indices::U32(mesh_data.indicies));
-        }
+        chunk.needs_remesh = false;
+        update_slice = true;
 
         terrain.set_chunk_dirty(chunk.chunk_idx, false);
-
-        update_slice = true;
-        ev_partition.send(PartitionEvent {
-            chunk_idx: chunk.chunk_idx,
-        });
-    });
+    }
 
     if update_slice {
         ev_terrain_slice.send(TerrainSliceChanged);
@@ -146,23 +444,222 @@ pub fn on_slice_changed(
 
     ev_slice_changed.clear();
 
-    if let Some(material) = terrain_material.get_mut(chunk_material_res.handle.clone()) {
-        material.terrain_slice_y = terrain_slice.get_value();
+    for handle in chunk_material_res.handles() {
+        if let Some(material) = terrain_material.get_mut(handle.clone()) {
+            material.terrain_slice_y = terrain_slice.get_value();
+        }
     }
 }
 
 #[derive(Default)]
-struct ChunkMeshData {
+pub struct ChunkMeshData {
     pub positions: Vec<[f32; 3]>,
     pub normals: Vec<[f32; 3]>,
     pub indicies: Vec<u32>,
     pub packed: Vec<u32>,
-    pub light: Vec<u32>,
 }
 
-fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
-    let mut data = ChunkMeshData::default();
-    let mut idx = 0;
+impl ChunkMeshData {
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.normals.clear();
+        self.indicies.clear();
+        self.packed.clear();
+    }
+
+    pub fn reserve(&mut self, face_count: usize) {
+        self.positions.reserve(face_count * 4);
+        self.normals.reserve(face_count * 4);
+        self.packed.reserve(face_count * 4);
+        self.indicies.reserve(face_count * 6);
+    }
+
+    pub fn face_count(&self) -> u32 {
+        (self.positions.len() / 4) as u32
+    }
+
+    /// Moves the built buffers into the mesh, and takes the mesh's previous
+    /// buffers back so the next rebuild can reuse their allocations.
+    pub fn swap_into(&mut self, mesh: &mut Mesh) {
+        let positions = std::mem::take(&mut self.positions);
+        let normals = std::mem::take(&mut self.normals);
+        let packed = std::mem::take(&mut self.packed);
+        let indicies = std::mem::take(&mut self.indicies);
+
+        if let Some(VertexAttributeValues::Float32x3(old)) =
+            mesh.remove_attribute(Mesh::ATTRIBUTE_POSITION)
+        {
+            self.positions = old;
+        }
+        if let Some(VertexAttributeValues::Float32x3(old)) =
+            mesh.remove_attribute(Mesh::ATTRIBUTE_NORMAL)
+        {
+            self.normals = old;
+        }
+        if let Some(VertexAttributeValues::Uint32(old)) =
+            mesh.remove_attribute(ATTRIBUTE_BLOCK_PACKED)
+        {
+            self.packed = old;
+        }
+        if let Some(Indices::U32(old)) = mesh.remove_indices() {
+            self.indicies = old;
+        }
+
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(ATTRIBUTE_BLOCK_PACKED, packed);
+        mesh.insert_indices(Indices::U32(indicies));
+
+        self.clear();
+    }
+
+    /// Puts the built buffers into the mesh. An empty build only clears the
+    /// mesh indices, keeping the vertex buffers around for reuse.
+    pub fn write_into(&mut self, mesh: &mut Mesh) {
+        if self.face_count() > 0 {
+            self.swap_into(mesh);
+        } else if let Some(Indices::U32(indicies)) = mesh.indices_mut() {
+            indicies.clear();
+        }
+    }
+}
+
+/// Mesher output, split by render pass. Transparent blocks are drawn after
+/// everything else with blending.
+#[derive(Default)]
+pub struct ChunkMeshLayers {
+    pub opaque: ChunkMeshData,
+    pub transparent: ChunkMeshData,
+}
+
+impl ChunkMeshLayers {
+    pub fn clear(&mut self) {
+        self.opaque.clear();
+        self.transparent.clear();
+    }
+
+    fn get_mut(&mut self, block: &Block) -> &mut ChunkMeshData {
+        if block.is_transparent() {
+            &mut self.transparent
+        } else {
+            &mut self.opaque
+        }
+    }
+}
+
+/// Whether the face of `block` toward `neighbor` can be seen. Opaque faces
+/// show through transparent blocks, transparent blocks hide each other's
+/// faces so a wall of glass reads as one surface.
+fn is_face_visible(block: Block, neighbor: Block) -> bool {
+    !neighbor.is_rendered()
+        || (neighbor.is_transparent() && !block.is_transparent())
+        || neighbor.stair_direction().is_some()
+        || (neighbor.block == BlockType::RUBBLE && block.block != BlockType::RUBBLE)
+}
+
+/// Quads of a stair climbing towards +x, as the face, the box the face is
+/// taken from, and whether the quad lies on the edge of the cell. Each step
+/// is a tread and a riser, the sides and back close the stair off.
+const STAIR_QUADS: [(BlockFace, [f32; 3], [f32; 3], bool); 10] = [
+    // low step
+    (BlockFace::PosY, [0., 0., 0.], [0.5, 0.5, 1.], false),
+    (BlockFace::NegX, [0., 0., 0.], [1., 0.5, 1.], true),
+    // high step
+    (BlockFace::PosY, [0.5, 0.5, 0.], [1., 1., 1.], true),
+    (BlockFace::NegX, [0.5, 0.5, 0.], [1., 1., 1.], false),
+    // sides, back and bottom
+    (BlockFace::PosZ, [0., 0., 0.], [1., 0.5, 1.], true),
+    (BlockFace::PosZ, [0.5, 0.5, 0.], [1., 1., 1.], true),
+    (BlockFace::NegZ, [0., 0., 0.], [1., 0.5, 1.], true),
+    (BlockFace::NegZ, [0.5, 0.5, 0.], [1., 1., 1.], true),
+    (BlockFace::PosX, [0., 0., 0.], [1., 1., 1.], true),
+    (BlockFace::NegY, [0., 0., 0.], [1., 0.5, 1.], true),
+];
+
+/// Turns a point or offset of a stair climbing towards +x around the middle
+/// of the cell, so it climbs towards `dir` instead.
+fn rotate_stair([x, y, z]: [f32; 3], [dx, dz]: [i32; 2], pivot: f32) -> [f32; 3] {
+    let (dx, dz) = (dx as f32, dz as f32);
+    let (a, b) = (x - pivot, z - pivot);
+
+    [a * dx - b * dz + pivot, y, a * dz + b * dx + pivot]
+}
+
+/// Stairs don't fill their cell, so they get their own shape instead of the
+/// cube faces. Quads on the edge of the cell are hidden by the neighbor like
+/// any face, the inner tread and riser are always drawn and lit by the stair
+/// cell itself.
+fn build_stair_mesh(
+    terrain: &Terrain,
+    data: &mut ChunkMeshData,
+    block: Block,
+    [wx, wy, wz]: [u32; 3],
+    [x, y, z]: [u32; 3],
+    dir: [i32; 2],
+    cracks: u32,
+) {
+    let tile = block.texture_variant([wx, wy, wz]);
+    let mut idx = data.positions.len() as u32;
+
+    for (local_face, min, max, is_edge) in STAIR_QUADS {
+        let [ox, oy, oz] = local_face.offset();
+        let [nx, _, nz] = rotate_stair([ox as f32, oy as f32, oz as f32], dir, 0.);
+        let offset = [nx.round() as i32, oy, nz.round() as i32];
+        let Some(face) = BlockFace::ALL.into_iter().find(|f| f.offset() == offset) else {
+            continue;
+        };
+
+        let light = if is_edge {
+            let neighbor = terrain.get_block_i32(
+                wx as i32 + offset[0],
+                wy as i32 + offset[1],
+                wz as i32 + offset[2],
+            );
+
+            if !is_face_visible(block, neighbor) {
+                continue;
+            }
+
+            neighbor
+        } else {
+            block
+        };
+
+        let [ox, oy, oz] = offset;
+
+        for [cx, cy, cz] in lod_face_corners(local_face) {
+            let local = [
+                min[0] + cx * (max[0] - min[0]),
+                min[1] + cy * (max[1] - min[1]),
+                min[2] + cz * (max[2] - min[2]),
+            ];
+            let [px, py, pz] = rotate_stair(local, dir, 0.5);
+
+            data.positions
+                .push([x as f32 + px, y as f32 + py, z as f32 + pz]);
+            data.normals.push([ox as f32, oy as f32, oz as f32]);
+            data.packed.push(pack_block(
+                tile,
+                block,
+                cracks,
+                face,
+                VertexCornerCount::None,
+                light,
+            ));
+        }
+
+        data.indicies.push(idx);
+        data.indicies.push(idx + 2);
+        data.indicies.push(idx + 1);
+        data.indicies.push(idx);
+        data.indicies.push(idx + 3);
+        data.indicies.push(idx + 2);
+
+        idx += 4;
+    }
+}
+
+pub(crate) fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32, layers: &mut ChunkMeshLayers) {
     let chunk_offset = terrain.get_chunk_offset(chunk_idx);
 
     for x in 0..terrain.chunk_size {
@@ -177,36 +674,49 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                     continue;
                 }
 
+                let tile = block.texture_variant([wx, wy, wz]);
+                let cracks = terrain.get_damage_stage(wx, wy, wz);
+                let data = layers.get_mut(&block);
+
+                if let Some(dir) = block.stair_direction() {
+                    build_stair_mesh(terrain, data, block, [wx, wy, wz], [x, y, z], dir, cracks);
+                    continue;
+                }
+
+                let mut idx = data.positions.len() as u32;
+
                 let fx = x as f32;
                 let fy = y as f32;
                 let fz = z as f32;
+                let top = block.top_height();
 
                 let neighbors = terrain.get_neighbors_detail(wx, wy, wz);
 
-                if !neighbors[Neighbor::ABOVE.idx()].is_rendered() {
+                // partly filled water shows its surface even under a ceiling
+                if is_face_visible(block, neighbors[Neighbor::ABOVE.idx()]) || top < 1. {
                     // add face above
-                    data.positions.push([fx, fy + 1., fz + 1.]); // behind left
+                    data.positions.push([fx, fy + top, fz + 1.]); // behind left
                     let f1_ao = vert_ao(
                         neighbors[Neighbor::ABOVE_LEFT.idx()],
                         neighbors[Neighbor::ABOVE_BEHIND.idx()],
                         neighbors[Neighbor::ABOVE_BEHIND_LEFT.idx()],
                     );
 
-                    data.positions.push([fx, fy + 1., fz]); // forward left
+                    data.positions.push([fx, fy + top, fz]); // forward left
                     let f2_ao = vert_ao(
                         neighbors[Neighbor::ABOVE_FORWARD.idx()],
                         neighbors[Neighbor::ABOVE_LEFT.idx()],
                         neighbors[Neighbor::ABOVE_FORWARD_LEFT.idx()],
                     );
 
-                    data.positions.push([fx + 1., fy + 1., fz]); // forward right
+                    data.positions.push([fx + 1., fy + top, fz]); // forward right
                     let f3_ao = vert_ao(
                         neighbors[Neighbor::ABOVE_FORWARD.idx()],
                         neighbors[Neighbor::ABOVE_RIGHT.idx()],
                         neighbors[Neighbor::ABOVE_FORWARD_RIGHT.idx()],
                     );
 
-                    data.positions.push([fx + 1., fy + 1., fz + 1.]); // behind right
+                    data.positions.push([fx + 1., fy + top, fz + 1.]); // behind right
                     let f4_ao = vert_ao(
                         neighbors[Neighbor::ABOVE_RIGHT.idx()],
                         neighbors[Neighbor::ABOVE_BEHIND.idx()],
@@ -229,18 +739,16 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                         data.indicies.push(idx + 2);
                     }
 
-                    data.packed.push(pack_block(block, BlockFace::PosY, f1_ao));
-                    data.packed.push(pack_block(block, BlockFace::PosY, f2_ao));
-                    data.packed.push(pack_block(block, BlockFace::PosY, f3_ao));
-                    data.packed.push(pack_block(block, BlockFace::PosY, f4_ao));
-
                     let n = neighbors[Neighbor::ABOVE.idx()];
-                    let light = ((n.light & 0xf) | ((n.sunlight << 4) & 0xf0)) as u32;
 
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosY, f1_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosY, f2_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosY, f3_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosY, f4_ao, n));
 
                     data.normals.push([0., 1., 0.]);
                     data.normals.push([0., 1., 0.]);
@@ -250,7 +758,7 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                     idx += 4;
                 }
 
-                if !neighbors[Neighbor::FORWARD.idx()].is_rendered() {
+                if is_face_visible(block, neighbors[Neighbor::FORWARD.idx()]) {
                     // add face in front
                     data.positions.push([fx + 1., fy, fz]); // bottom right
                     let f1_ao = vert_ao(
@@ -259,14 +767,14 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                         neighbors[Neighbor::BELOW_FORWARD_RIGHT.idx()],
                     );
 
-                    data.positions.push([fx + 1., fy + 1., fz]); // above right
+                    data.positions.push([fx + 1., fy + top, fz]); // above right
                     let f2_ao = vert_ao(
                         neighbors[Neighbor::FORWARD_RIGHT.idx()],
                         neighbors[Neighbor::ABOVE_FORWARD.idx()],
                         neighbors[Neighbor::ABOVE_FORWARD_RIGHT.idx()],
                     );
 
-                    data.positions.push([fx, fy + 1., fz]); // above left
+                    data.positions.push([fx, fy + top, fz]); // above left
                     let f3_ao = vert_ao(
                         neighbors[Neighbor::FORWARD_LEFT.idx()],
                         neighbors[Neighbor::ABOVE_FORWARD.idx()],
@@ -296,18 +804,16 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                         data.indicies.push(idx + 2);
                     }
 
-                    data.packed.push(pack_block(block, BlockFace::NegZ, f1_ao));
-                    data.packed.push(pack_block(block, BlockFace::NegZ, f2_ao));
-                    data.packed.push(pack_block(block, BlockFace::NegZ, f3_ao));
-                    data.packed.push(pack_block(block, BlockFace::NegZ, f4_ao));
-
                     let n = neighbors[Neighbor::FORWARD.idx()];
-                    let light = ((n.light & 0xf) | ((n.sunlight << 4) & 0xf0)) as u32;
 
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegZ, f1_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegZ, f2_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegZ, f3_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegZ, f4_ao, n));
 
                     data.normals.push([0., 0., -1.]);
                     data.normals.push([0., 0., -1.]);
@@ -317,7 +823,7 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                     idx += 4;
                 }
 
-                if !neighbors[Neighbor::RIGHT.idx()].is_rendered() {
+                if is_face_visible(block, neighbors[Neighbor::RIGHT.idx()]) {
                     // add face right
                     data.positions.push([fx + 1., fy, fz + 1.]); // bottom back
                     let f1_ao = vert_ao(
@@ -326,14 +832,14 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                         neighbors[Neighbor::BELOW_BEHIND_RIGHT.idx()],
                     );
 
-                    data.positions.push([fx + 1., fy + 1., fz + 1.]); // above back
+                    data.positions.push([fx + 1., fy + top, fz + 1.]); // above back
                     let f2_ao = vert_ao(
                         neighbors[Neighbor::ABOVE_RIGHT.idx()],
                         neighbors[Neighbor::BEHIND_RIGHT.idx()],
                         neighbors[Neighbor::ABOVE_BEHIND_RIGHT.idx()],
                     );
 
-                    data.positions.push([fx + 1., fy + 1., fz]); // above forward
+                    data.positions.push([fx + 1., fy + top, fz]); // above forward
                     let f3_ao = vert_ao(
                         neighbors[Neighbor::ABOVE_RIGHT.idx()],
                         neighbors[Neighbor::FORWARD_RIGHT.idx()],
@@ -363,18 +869,16 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                         data.indicies.push(idx + 2);
                     }
 
-                    data.packed.push(pack_block(block, BlockFace::PosX, f1_ao));
-                    data.packed.push(pack_block(block, BlockFace::PosX, f2_ao));
-                    data.packed.push(pack_block(block, BlockFace::PosX, f3_ao));
-                    data.packed.push(pack_block(block, BlockFace::PosX, f4_ao));
-
                     let n = neighbors[Neighbor::RIGHT.idx()];
-                    let light = ((n.light & 0xf) | ((n.sunlight << 4) & 0xf0)) as u32;
 
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosX, f1_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosX, f2_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosX, f3_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosX, f4_ao, n));
 
                     data.normals.push([1., 0., 0.]);
                     data.normals.push([1., 0., 0.]);
@@ -384,7 +888,7 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                     idx += 4;
                 }
 
-                if !neighbors[Neighbor::BEHIND.idx()].is_rendered() {
+                if is_face_visible(block, neighbors[Neighbor::BEHIND.idx()]) {
                     // add face behind
                     data.positions.push([fx, fy, fz + 1.]); // bottom left
                     let f1_ao = vert_ao(
@@ -393,14 +897,14 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                         neighbors[Neighbor::BELOW_BEHIND_LEFT.idx()],
                     );
 
-                    data.positions.push([fx, fy + 1., fz + 1.]); // above left
+                    data.positions.push([fx, fy + top, fz + 1.]); // above left
                     let f2_ao = vert_ao(
                         neighbors[Neighbor::BEHIND_LEFT.idx()],
                         neighbors[Neighbor::ABOVE_BEHIND.idx()],
                         neighbors[Neighbor::ABOVE_BEHIND_LEFT.idx()],
                     );
 
-                    data.positions.push([fx + 1., fy + 1., fz + 1.]); // above right
+                    data.positions.push([fx + 1., fy + top, fz + 1.]); // above right
                     let f3_ao = vert_ao(
                         neighbors[Neighbor::BEHIND_RIGHT.idx()],
                         neighbors[Neighbor::ABOVE_BEHIND.idx()],
@@ -430,18 +934,16 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                         data.indicies.push(idx + 2);
                     }
 
-                    data.packed.push(pack_block(block, BlockFace::PosZ, f1_ao));
-                    data.packed.push(pack_block(block, BlockFace::PosZ, f2_ao));
-                    data.packed.push(pack_block(block, BlockFace::PosZ, f3_ao));
-                    data.packed.push(pack_block(block, BlockFace::PosZ, f4_ao));
-
                     let n = neighbors[Neighbor::BEHIND.idx()];
-                    let light = ((n.light & 0xf) | ((n.sunlight << 4) & 0xf0)) as u32;
 
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosZ, f1_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosZ, f2_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosZ, f3_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::PosZ, f4_ao, n));
 
                     data.normals.push([0., 0., 1.]);
                     data.normals.push([0., 0., 1.]);
@@ -451,7 +953,7 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                     idx += 4;
                 }
 
-                if !neighbors[Neighbor::LEFT.idx()].is_rendered() {
+                if is_face_visible(block, neighbors[Neighbor::LEFT.idx()]) {
                     // add face left
                     data.positions.push([fx, fy, fz]); // below forward
                     let f1_ao = vert_ao(
@@ -460,14 +962,14 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                         neighbors[Neighbor::BELOW_FORWARD_LEFT.idx()],
                     );
 
-                    data.positions.push([fx, fy + 1., fz]); // above forward
+                    data.positions.push([fx, fy + top, fz]); // above forward
                     let f2_ao = vert_ao(
                         neighbors[Neighbor::ABOVE_LEFT.idx()],
                         neighbors[Neighbor::FORWARD_LEFT.idx()],
                         neighbors[Neighbor::ABOVE_FORWARD_LEFT.idx()],
                     );
 
-                    data.positions.push([fx, fy + 1., fz + 1.]); // above behind
+                    data.positions.push([fx, fy + top, fz + 1.]); // above behind
                     let f3_ao = vert_ao(
                         neighbors[Neighbor::ABOVE_LEFT.idx()],
                         neighbors[Neighbor::BEHIND_LEFT.idx()],
@@ -497,18 +999,16 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                         data.indicies.push(idx + 2);
                     }
 
-                    data.packed.push(pack_block(block, BlockFace::NegX, f1_ao));
-                    data.packed.push(pack_block(block, BlockFace::NegX, f2_ao));
-                    data.packed.push(pack_block(block, BlockFace::NegX, f3_ao));
-                    data.packed.push(pack_block(block, BlockFace::NegX, f4_ao));
-
                     let n = neighbors[Neighbor::LEFT.idx()];
-                    let light = ((n.light & 0xf) | ((n.sunlight << 4) & 0xf0)) as u32;
 
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegX, f1_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegX, f2_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegX, f3_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegX, f4_ao, n));
 
                     data.normals.push([-1., 0., 0.]);
                     data.normals.push([-1., 0., 0.]);
@@ -518,7 +1018,7 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                     idx += 4;
                 }
 
-                if !neighbors[Neighbor::BELOW.idx()].is_rendered() {
+                if is_face_visible(block, neighbors[Neighbor::BELOW.idx()]) {
                     // add face below
                     data.positions.push([fx + 1., fy, fz + 1.]); // behind right
                     let f1_ao = vert_ao(
@@ -564,31 +1064,152 @@ fn build_chunk_mesh(terrain: &Terrain, chunk_idx: u32) -> ChunkMeshData {
                         data.indicies.push(idx + 2);
                     }
 
-                    data.packed.push(pack_block(block, BlockFace::NegY, f1_ao));
-                    data.packed.push(pack_block(block, BlockFace::NegY, f2_ao));
-                    data.packed.push(pack_block(block, BlockFace::NegY, f3_ao));
-                    data.packed.push(pack_block(block, BlockFace::NegY, f4_ao));
-
                     let n = neighbors[Neighbor::BELOW.idx()];
-                    let light = ((n.light & 0xf) | ((n.sunlight << 4) & 0xf0)) as u32;
 
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
-                    data.light.push(light);
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegY, f1_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegY, f2_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegY, f3_ao, n));
+                    data.packed
+                        .push(pack_block(tile, block, cracks, BlockFace::NegY, f4_ao, n));
 
                     data.normals.push([0., -1., 0.]);
                     data.normals.push([0., -1., 0.]);
                     data.normals.push([0., -1., 0.]);
                     data.normals.push([0., -1., 0.]);
+                }
+            }
+        }
+    }
+}
+
+/// Builds a decimated mesh where every `stride`^3 cell of blocks becomes a
+/// single cube of the cell's most common block type.
+fn build_chunk_mesh_lod(
+    terrain: &Terrain,
+    chunk_idx: u32,
+    stride: u32,
+    layers: &mut ChunkMeshLayers,
+) {
+    let chunk_offset = terrain.get_chunk_offset(chunk_idx);
+    let s = stride as i32;
+    let fs = stride as f32;
+
+    for x in (0..terrain.chunk_size).step_by(stride as usize) {
+        for y in (0..terrain.chunk_size).step_by(stride as usize) {
+            for z in (0..terrain.chunk_size).step_by(stride as usize) {
+                let wx = (chunk_offset[0] + x) as i32;
+                let wy = (chunk_offset[1] + y) as i32;
+                let wz = (chunk_offset[2] + z) as i32;
+                let block = sample_lod_cell(terrain, [wx, wy, wz], stride);
+
+                if !block.is_rendered() {
+                    continue;
+                }
+
+                let tile = block.texture_variant([wx as u32, wy as u32, wz as u32]);
+                let data = layers.get_mut(&block);
+                let mut idx = data.positions.len() as u32;
+
+                let fx = x as f32;
+                let fy = y as f32;
+                let fz = z as f32;
+
+                for face in BlockFace::ALL {
+                    let [dx, dy, dz] = face.offset();
+                    let neighbor =
+                        sample_lod_cell(terrain, [wx + dx * s, wy + dy * s, wz + dz * s], stride);
+
+                    if !is_face_visible(block, neighbor) {
+                        continue;
+                    }
+
+                    let light = terrain.get_block_i32(
+                        wx + s / 2 + dx * (s / 2 + 1),
+                        wy + s / 2 + dy * (s / 2 + 1),
+                        wz + s / 2 + dz * (s / 2 + 1),
+                    );
+
+                    for [cx, cy, cz] in lod_face_corners(face) {
+                        data.positions
+                            .push([fx + cx * fs, fy + cy * fs, fz + cz * fs]);
+                        data.normals.push([dx as f32, dy as f32, dz as f32]);
+                        data.packed.push(pack_block(
+                            tile,
+                            block,
+                            0,
+                            face,
+                            VertexCornerCount::None,
+                            light,
+                        ));
+                    }
+
+                    data.indicies.push(idx);
+                    data.indicies.push(idx + 2);
+                    data.indicies.push(idx + 1);
+                    data.indicies.push(idx);
+                    data.indicies.push(idx + 3);
+                    data.indicies.push(idx + 2);
 
                     idx += 4;
                 }
             }
         }
     }
+}
+
+/// The most common rendered block type in a cell, or empty if less than half
+/// of the cell is filled.
+fn sample_lod_cell(terrain: &Terrain, origin: [i32; 3], stride: u32) -> Block {
+    if terrain.is_oob(origin[0], origin[1], origin[2]) {
+        return Block::OOB;
+    }
+
+    let s = stride as i32;
+    let mut counts: Vec<(Block, u32)> = vec![];
+    let mut rendered = 0;
+
+    for x in origin[0]..origin[0] + s {
+        for y in origin[1]..origin[1] + s {
+            for z in origin[2]..origin[2] + s {
+                let block = terrain.get_block_i32(x, y, z);
+
+                if !block.is_rendered() {
+                    continue;
+                }
+
+                rendered += 1;
 
-    data
+                match counts.iter_mut().find(|(b, _)| b.block == block.block) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((block, 1)),
+                }
+            }
+        }
+    }
+
+    if rendered * 2 < stride * stride * stride {
+        return Block::default();
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map_or(Block::default(), |(block, _)| block)
+}
+
+/// Unit quad corners for a face, wound the same as the full detail mesh.
+fn lod_face_corners(face: BlockFace) -> [[f32; 3]; 4] {
+    match face {
+        BlockFace::PosX => [[1., 0., 1.], [1., 1., 1.], [1., 1., 0.], [1., 0., 0.]],
+        BlockFace::NegX => [[0., 0., 0.], [0., 1., 0.], [0., 1., 1.], [0., 0., 1.]],
+        BlockFace::PosY => [[0., 1., 1.], [0., 1., 0.], [1., 1., 0.], [1., 1., 1.]],
+        BlockFace::NegY => [[1., 0., 1.], [1., 0., 0.], [0., 0., 0.], [0., 0., 1.]],
+        BlockFace::PosZ => [[0., 0., 1.], [0., 1., 1.], [1., 1., 1.], [1., 0., 1.]],
+        BlockFace::NegZ => [[1., 0., 0.], [1., 1., 0.], [0., 1., 0.], [0., 0., 0.]],
+    }
 }
 
 fn vert_ao(side1: Block, side2: Block, corner: Block) -> VertexCornerCount {
@@ -613,3 +1234,597 @@ fn vert_ao(side1: Block, side2: Block, corner: Block) -> VertexCornerCount {
 
     VertexCornerCount::from_bit(vao)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        colonists::{flush_partition_updates, PartitionEvent},
+        common::Rand,
+    };
+
+    struct Quad {
+        positions: [[f32; 3]; 4],
+        normal: [f32; 3],
+        packed: [u32; 4],
+    }
+
+    fn mesh_chunk(terrain: &Terrain, chunk_idx: u32) -> Vec<Quad> {
+        let mut layers = ChunkMeshLayers::default();
+        build_chunk_mesh(terrain, chunk_idx, &mut layers);
+
+        assert_winding(&layers.opaque);
+        assert_eq!(layers.transparent.face_count(), 0);
+
+        let data = layers.opaque;
+
+        (0..data.face_count() as usize)
+            .map(|face| {
+                let v = face * 4;
+
+                assert!(data.normals[v..v + 4].iter().all(|n| *n == data.normals[v]));
+
+                Quad {
+                    positions: [
+                        data.positions[v],
+                        data.positions[v + 1],
+                        data.positions[v + 2],
+                        data.positions[v + 3],
+                    ],
+                    normal: data.normals[v],
+                    packed: [
+                        data.packed[v],
+                        data.packed[v + 1],
+                        data.packed[v + 2],
+                        data.packed[v + 3],
+                    ],
+                }
+            })
+            .collect()
+    }
+
+    /// Every triangle faces the way its vertex normals say, and stays
+    /// within its own quad.
+    fn assert_winding(data: &ChunkMeshData) {
+        assert_eq!(data.indicies.len(), data.face_count() as usize * 6);
+
+        for tri in data.indicies.chunks(3) {
+            let [a, b, c] =
+                [tri[0], tri[1], tri[2]].map(|i| Vec3::from(data.positions[i as usize]));
+            let normal = Vec3::from(data.normals[tri[0] as usize]);
+
+            assert_eq!(tri[0] / 4, tri[1] / 4);
+            assert_eq!(tri[0] / 4, tri[2] / 4);
+            assert_eq!((b - a).cross(c - a).normalize(), normal);
+        }
+    }
+
+    fn face_of(normal: [f32; 3]) -> BlockFace {
+        match normal {
+            [1., 0., 0.] => BlockFace::PosX,
+            [-1., 0., 0.] => BlockFace::NegX,
+            [0., 1., 0.] => BlockFace::PosY,
+            [0., -1., 0.] => BlockFace::NegY,
+            [0., 0., 1.] => BlockFace::PosZ,
+            [0., 0., -1.] => BlockFace::NegZ,
+            _ => panic!("not an axis normal: {:?}", normal),
+        }
+    }
+
+    /// The world position of the block a quad belongs to
+    fn quad_block(terrain: &Terrain, chunk_idx: u32, quad: &Quad) -> [i32; 3] {
+        let offset = terrain.get_chunk_offset(chunk_idx);
+        let center = quad
+            .positions
+            .iter()
+            .fold(Vec3::ZERO, |sum, p| sum + Vec3::from(*p))
+            / 4.;
+        let inside = (center - Vec3::from(quad.normal) * 0.5).floor();
+
+        [
+            inside.x as i32 + offset[0] as i32,
+            inside.y as i32 + offset[1] as i32,
+            inside.z as i32 + offset[2] as i32,
+        ]
+    }
+
+    fn ao_bits(quad: &Quad) -> [u32; 4] {
+        quad.packed.map(|packed| (packed >> 11) & 3)
+    }
+
+    fn terrain_with(chunk_counts: [u32; 3], blocks: &[[u32; 3]]) -> Terrain {
+        let [x, y, z] = chunk_counts;
+        let mut terrain = Terrain::new(x, y, z, 4).unwrap();
+
+        for [x, y, z] in blocks {
+            terrain.set_block(*x, *y, *z, BlockType::STONE);
+        }
+
+        terrain
+    }
+
+    #[test]
+    fn single_block() {
+        let terrain = terrain_with([1, 1, 1], &[[1, 1, 1]]);
+        let quads = mesh_chunk(&terrain, 0);
+        let tile = terrain.get_block(1, 1, 1).texture_variant([1, 1, 1]);
+
+        let expected = [
+            (
+                BlockFace::PosY,
+                [[1., 2., 2.], [1., 2., 1.], [2., 2., 1.], [2., 2., 2.]],
+            ),
+            (
+                BlockFace::NegZ,
+                [[2., 1., 1.], [2., 2., 1.], [1., 2., 1.], [1., 1., 1.]],
+            ),
+            (
+                BlockFace::PosX,
+                [[2., 1., 2.], [2., 2., 2.], [2., 2., 1.], [2., 1., 1.]],
+            ),
+            (
+                BlockFace::PosZ,
+                [[1., 1., 2.], [1., 2., 2.], [2., 2., 2.], [2., 1., 2.]],
+            ),
+            (
+                BlockFace::NegX,
+                [[1., 1., 1.], [1., 2., 1.], [1., 2., 2.], [1., 1., 2.]],
+            ),
+            (
+                BlockFace::NegY,
+                [[2., 1., 2.], [2., 1., 1.], [1., 1., 1.], [1., 1., 2.]],
+            ),
+        ];
+
+        assert_eq!(quads.len(), expected.len());
+
+        for (quad, (face, positions)) in quads.iter().zip(expected) {
+            let offset = face.offset();
+
+            assert_eq!(quad.positions, positions);
+            assert_eq!(quad.normal, offset.map(|v| v as f32));
+            // nothing around to occlude or light it
+            assert_eq!(quad.packed, [tile | (face.bit() << 8); 4]);
+        }
+    }
+
+    #[test]
+    fn faces_use_the_table_texture() {
+        let mut terrain = terrain_with([1, 1, 1], &[]);
+        terrain.set_block(1, 1, 1, BlockType::SAND);
+        let quads = mesh_chunk(&terrain, 0);
+        assert_eq!(quads.len(), 6);
+        assert!(quads
+            .iter()
+            .all(|quad| quad.packed.iter().all(|p| p & 255 == 27)));
+
+        // dirt picks one of its variants
+        terrain.set_block(1, 1, 1, BlockType::DIRT);
+        let quads = mesh_chunk(&terrain, 0);
+        assert!(quads
+            .iter()
+            .all(|quad| quad.packed.iter().all(|p| [1, 52].contains(&(p & 255)))));
+    }
+
+    #[test]
+    fn texture_variants_survive_a_remesh() {
+        // a stone wall, across the whole chunk
+        let wall = (0..16).map(|i| [i % 4, i / 4, 1]).collect::<Vec<_>>();
+        let mut terrain = terrain_with([1, 1, 1], &wall);
+
+        let tiles = |terrain: &Terrain| {
+            mesh_chunk(terrain, 0)
+                .iter()
+                .map(|quad| {
+                    let tile = quad.packed[0] & 255;
+                    assert!(quad.packed.iter().all(|p| p & 255 == tile));
+                    (
+                        (quad_block(terrain, 0, quad), face_of(quad.normal).bit()),
+                        tile,
+                    )
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        let first = tiles(&terrain);
+        let variants = first.values().copied().collect::<HashSet<_>>();
+        assert!(variants.len() > 1, "one tile for the whole wall");
+        assert!(variants.iter().all(|tile| [3, 58, 59].contains(tile)));
+
+        // the same build again, and one after digging a hole in the wall,
+        // keep every face that is still there on its tile
+        assert_eq!(tiles(&terrain), first);
+
+        terrain.set_block(1, 1, 1, BlockType::EMPTY);
+        let dug = tiles(&terrain);
+        assert!(dug.len() > first.len() - 2);
+
+        for (face, tile) in dug.iter() {
+            if let Some(before) = first.get(face) {
+                assert_eq!(tile, before, "{:?} changed tile", face);
+            }
+        }
+    }
+
+    #[test]
+    fn pair_hides_shared_faces() {
+        let terrain = terrain_with([1, 1, 1], &[[1, 1, 1], [2, 1, 1]]);
+        let quads = mesh_chunk(&terrain, 0);
+
+        assert_eq!(quads.len(), 10);
+
+        let blocks = quads
+            .iter()
+            .map(|quad| (quad_block(&terrain, 0, quad), face_of(quad.normal)))
+            .collect::<Vec<_>>();
+
+        assert!(!blocks.contains(&([1, 1, 1], BlockFace::PosX)));
+        assert!(!blocks.contains(&([2, 1, 1], BlockFace::NegX)));
+        assert!(blocks.contains(&([1, 1, 1], BlockFace::NegX)));
+        assert!(blocks.contains(&([2, 1, 1], BlockFace::PosX)));
+
+        // side by side blocks don't shade each other's top
+        assert!(quads.iter().all(|quad| ao_bits(quad) == [0; 4]));
+    }
+
+    #[test]
+    fn mesh_stats_count_known_chunks() {
+        let mut terrain = terrain_with([2, 1, 1], &[[1, 1, 1], [2, 1, 1], [5, 1, 1]]);
+        let mut stats = MeshStats::default();
+
+        let record = |terrain: &Terrain, stats: &mut MeshStats, chunk_idx: u32| {
+            let mut layers = ChunkMeshLayers::default();
+            build_chunk_mesh(terrain, chunk_idx, &mut layers);
+            stats.record(chunk_idx, &layers, Duration::ZERO);
+        };
+
+        // the pair shows 10 faces, 4 vertices and 2 triangles each
+        record(&terrain, &mut stats, 0);
+        assert_eq!(stats.chunks[&0].vertices, 40);
+        assert_eq!(stats.chunks[&0].indices / 3, 20);
+
+        // a lone block in the next chunk shows all 6
+        record(&terrain, &mut stats, 1);
+        assert_eq!(stats.chunks[&1].vertices, 24);
+        assert_eq!(stats.chunks[&1].indices / 3, 12);
+        assert_eq!((stats.total_vertices, stats.total_indices), (64, 96));
+
+        // a remesh replaces the chunk's counts rather than adding to them
+        terrain.set_block(2, 1, 1, BlockType::EMPTY);
+        record(&terrain, &mut stats, 0);
+        assert_eq!((stats.total_vertices, stats.total_indices), (48, 72));
+        assert_eq!(stats.rebuilds_total, 3);
+
+        stats.forget(1);
+        assert_eq!((stats.total_vertices, stats.total_indices), (24, 36));
+        assert_eq!(stats.chunks.len(), 1);
+    }
+
+    #[test]
+    fn l_shape_occludes_corners() {
+        // a block with another one stacked on its right neighbor
+        let terrain = terrain_with([1, 1, 1], &[[1, 1, 1], [2, 1, 1], [2, 2, 1]]);
+        let quads = mesh_chunk(&terrain, 0);
+
+        assert_eq!(quads.len(), 14);
+
+        let top = quads
+            .iter()
+            .find(|quad| {
+                quad_block(&terrain, 0, quad) == [1, 1, 1]
+                    && face_of(quad.normal) == BlockFace::PosY
+            })
+            .unwrap();
+
+        // the two corners against the wall are darkened
+        assert_eq!(top.positions[2], [2., 2., 1.]);
+        assert_eq!(top.positions[3], [2., 2., 2.]);
+        assert_eq!(ao_bits(top), [0, 0, 1, 1]);
+
+        // the wall itself sees only open air in front of it
+        let wall = quads
+            .iter()
+            .find(|quad| {
+                quad_block(&terrain, 0, quad) == [2, 2, 1]
+                    && face_of(quad.normal) == BlockFace::NegX
+            })
+            .unwrap();
+        assert_eq!(ao_bits(wall), [1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn chunk_border_faces_are_culled() {
+        // the blocks meet across the border between two chunks
+        let terrain = terrain_with([2, 1, 1], &[[3, 1, 1], [4, 1, 1], [4, 2, 1]]);
+        let left = mesh_chunk(&terrain, 0);
+        let right = mesh_chunk(&terrain, 1);
+
+        assert_eq!(left.len(), 5);
+        assert!(left
+            .iter()
+            .all(|quad| face_of(quad.normal) != BlockFace::PosX));
+
+        // positions are local to the chunk
+        let right_top = right
+            .iter()
+            .find(|quad| face_of(quad.normal) == BlockFace::PosY)
+            .unwrap();
+        assert_eq!(right_top.positions[0], [0., 3., 2.]);
+        assert_eq!(quad_block(&terrain, 1, right_top), [4, 2, 1]);
+
+        // occlusion reaches across the border as well
+        let left_top = left
+            .iter()
+            .find(|quad| face_of(quad.normal) == BlockFace::PosY)
+            .unwrap();
+        assert_eq!(ao_bits(left_top), [0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn faces_match_exposed_neighbors() {
+        let mut rand = Rand::seed(569);
+
+        for _ in 0..8 {
+            let mut terrain = Terrain::new(2, 2, 2, 4).unwrap();
+            let fill = rand.random();
+
+            for x in 0..8 {
+                for y in 0..8 {
+                    for z in 0..8 {
+                        if rand.bool(fill) {
+                            terrain.set_block(x, y, z, BlockType::STONE);
+                        }
+                    }
+                }
+            }
+
+            let mut expected = 0;
+
+            for x in 0..8 {
+                for y in 0..8 {
+                    for z in 0..8 {
+                        if !terrain.get_block(x, y, z).is_rendered() {
+                            continue;
+                        }
+
+                        expected += BlockFace::ALL
+                            .iter()
+                            .filter(|face| {
+                                let [dx, dy, dz] = face.offset();
+                                !terrain
+                                    .get_block_i32(x as i32 + dx, y as i32 + dy, z as i32 + dz)
+                                    .is_rendered()
+                            })
+                            .count();
+                    }
+                }
+            }
+
+            let mut count = 0;
+
+            for chunk_idx in 0..terrain.chunk_count {
+                for quad in mesh_chunk(&terrain, chunk_idx) {
+                    let [x, y, z] = quad_block(&terrain, chunk_idx, &quad);
+                    let [dx, dy, dz] = face_of(quad.normal).offset();
+
+                    assert!(terrain.get_block_i32(x, y, z).is_rendered());
+                    assert!(!terrain.get_block_i32(x + dx, y + dy, z + dz).is_rendered());
+                    count += 1;
+                }
+            }
+
+            assert_eq!(count, expected);
+        }
+    }
+
+    /// 500 chunks with a block each, every one of them dirty, and the camera
+    /// off in one corner of the world
+    fn dirty_world(max_rebuilds_per_frame: usize) -> World {
+        let mut world = World::new();
+        let mut terrain = Terrain::new(10, 5, 10, 4).unwrap();
+
+        for chunk_idx in 0..terrain.chunk_count {
+            let [x, y, z] = terrain.get_chunk_offset(chunk_idx);
+            terrain.set_block(x + 1, y + 1, z + 1, BlockType::STONE);
+
+            world.spawn(Chunk {
+                chunk_idx,
+                world_x: x,
+                world_y: y,
+                world_z: z,
+                face_count: 0,
+                lod: 1,
+                needs_remesh: false,
+                mesh_handle: Handle::default(),
+                transparent_mesh_handle: Handle::default(),
+            });
+        }
+
+        let world_y = terrain.world_size_y();
+        world.insert_resource(TerrainSlice::new(world_y, world_y, Handle::default()));
+        world.insert_resource(terrain);
+        world.insert_resource(ChunkMeshSettings {
+            max_rebuilds_per_frame,
+            ..default()
+        });
+        world.init_resource::<Assets<Mesh>>();
+        world.init_resource::<MeshStats>();
+        world.init_resource::<Events<TerrainSliceChanged>>();
+        world.spawn((
+            MainCamera::default(),
+            GlobalTransform::from_translation(Vec3::new(-5., 30., -5.)),
+        ));
+
+        world
+    }
+
+    #[test]
+    fn dirty_chunks_rebuild_nearest_first() {
+        let max_rebuilds = 16;
+        let mut world = dirty_world(max_rebuilds);
+        let mut schedule = Schedule::default();
+        schedule.add_systems(process_dirty_chunks);
+
+        let camera = Vec3::new(-5., 30., -5.);
+        let distances = world
+            .query::<&Chunk>()
+            .iter(&world)
+            .map(|chunk| {
+                let center = Vec3::new(
+                    chunk.world_x as f32 + 2.,
+                    chunk.world_y as f32 + 2.,
+                    chunk.world_z as f32 + 2.,
+                );
+                (chunk.chunk_idx, camera.distance_squared(center))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let dirty = |world: &World| {
+            let terrain = world.resource::<Terrain>();
+            (0..terrain.chunk_count)
+                .filter(|idx| terrain.get_chunk_dirty(*idx))
+                .collect::<HashSet<_>>()
+        };
+
+        let mut remaining = dirty(&world);
+        assert_eq!(remaining.len(), 500);
+
+        let mut frames = 0;
+
+        while !remaining.is_empty() {
+            schedule.run(&mut world);
+            frames += 1;
+
+            let now_dirty = dirty(&world);
+            let rebuilt = remaining
+                .difference(&now_dirty)
+                .copied()
+                .collect::<Vec<_>>();
+
+            assert_eq!(rebuilt.len(), max_rebuilds.min(remaining.len()));
+            assert_eq!(
+                world.resource::<MeshStats>().rebuilds_this_frame,
+                rebuilt.len() as u32
+            );
+
+            // nothing left waiting is closer than what was just rebuilt
+            let farthest_rebuilt = rebuilt.iter().map(|idx| distances[idx]).fold(0., f32::max);
+            assert!(now_dirty
+                .iter()
+                .all(|idx| distances[idx] >= farthest_rebuilt));
+
+            remaining = now_dirty;
+        }
+
+        assert_eq!(frames, 500_usize.div_ceil(max_rebuilds));
+    }
+
+    #[test]
+    fn one_edit_remeshes_and_repartitions_once() {
+        let mut world = dirty_world(16);
+        world.init_resource::<Events<PartitionEvent>>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems((process_dirty_chunks, flush_partition_updates));
+
+        // let the world settle first
+        for _ in 0..500_usize.div_ceil(16) {
+            schedule.run(&mut world);
+        }
+        world.resource_mut::<Events<PartitionEvent>>().clear();
+
+        // away from the chunk's faces, so no neighbor is touched
+        let mut terrain = world.resource_mut::<Terrain>();
+        let [x, y, z] = terrain.get_chunk_offset(123);
+        terrain.set_block(x + 2, y + 1, z + 1, BlockType::STONE);
+
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<MeshStats>().rebuilds_this_frame, 1);
+        let partitioned = world
+            .resource_mut::<Events<PartitionEvent>>()
+            .drain()
+            .map(|ev| ev.chunk_idx)
+            .collect::<Vec<_>>();
+        assert_eq!(partitioned, vec![123]);
+
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<MeshStats>().rebuilds_this_frame, 0);
+        assert!(world.resource::<Events<PartitionEvent>>().is_empty());
+    }
+
+    /// `cargo test remesh_allocation_bench -- --ignored --nocapture`
+    ///
+    /// Remeshes one 16 block chunk 1000 times, digging one block out between
+    /// rebuilds. Each rebuild either builds into fresh buffers, the way meshes
+    /// used to be made, or into the buffers `swap_into` hands back from the
+    /// previous mesh. Counts the rebuilds that had to grow a buffer.
+    #[test]
+    #[ignore]
+    fn remesh_allocation_bench() {
+        let half_filled = || {
+            let mut rand = Rand::seed(564);
+            let mut terrain = Terrain::new(1, 1, 1, 16).unwrap();
+
+            for x in 0..16 {
+                for y in 0..16 {
+                    for z in 0..16 {
+                        if rand.bool(0.5) {
+                            terrain.set_block(x, y, z, BlockType::STONE);
+                        }
+                    }
+                }
+            }
+
+            terrain
+        };
+
+        let mut rand = Rand::seed(565);
+        let digs = (0..1000)
+            .map(|_| {
+                [
+                    rand.range_n(0, 16) as u32,
+                    rand.range_n(0, 16) as u32,
+                    rand.range_n(0, 16) as u32,
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let remesh = |reuse: bool| {
+            let mut terrain = half_filled();
+            let mut mesh = empty_chunk_mesh();
+            let mut reused = ChunkMeshLayers::default();
+            let mut allocations = 0;
+            let start = Instant::now();
+
+            for [x, y, z] in digs.iter() {
+                terrain.init_block(*x, *y, *z, BlockType::EMPTY);
+
+                let mut fresh = ChunkMeshLayers::default();
+                let layers = if reuse { &mut reused } else { &mut fresh };
+                let capacity = layers.opaque.positions.capacity();
+
+                layers.clear();
+                build_chunk_mesh(&terrain, 0, layers);
+
+                if layers.opaque.positions.capacity() > capacity {
+                    allocations += 1;
+                }
+
+                layers.opaque.write_into(&mut mesh);
+            }
+
+            (allocations, start.elapsed())
+        };
+
+        let (fresh_allocations, fresh_time) = remesh(false);
+        let (reused_allocations, reused_time) = remesh(true);
+
+        println!(
+            "1000 remeshes: fresh buffers {} allocations in {:?}, swapped buffers {} allocations in {:?}",
+            fresh_allocations, fresh_time, reused_allocations, reused_time
+        );
+        assert_eq!(fresh_allocations, 1000);
+        // only the first couple of rebuilds, until both the mesh and the
+        // mesher hold a buffer big enough
+        assert!(reused_allocations <= 2);
+    }
+}
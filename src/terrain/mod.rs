@@ -1,17 +1,48 @@
 mod block;
+mod block_damage;
 mod block_face;
+mod block_palette;
+mod block_properties;
+mod cave_in;
 mod chunk;
+mod chunk_streaming;
+mod edit_history;
+mod farm;
+mod fire;
+mod fluid;
+mod grass;
+mod gravity;
 mod light;
+mod mechanism;
 mod mesh;
+mod rot;
 mod slice;
 mod terrain;
 mod terrain_gen;
+mod torch;
+mod world_clock;
+mod world_gen_config;
 
 pub use block::*;
+pub use block_damage::*;
 pub use block_face::*;
+pub use block_palette::*;
+pub use cave_in::*;
 pub use chunk::*;
+pub use chunk_streaming::*;
+pub use edit_history::*;
+pub use farm::*;
+pub use fire::*;
+pub use fluid::*;
+pub use grass::*;
+pub use gravity::*;
 pub use light::*;
+pub use mechanism::*;
 pub use mesh::*;
+pub use rot::*;
 pub use slice::*;
 pub use terrain::*;
 pub use terrain_gen::*;
+pub use torch::*;
+pub use world_clock::*;
+pub use world_gen_config::*;
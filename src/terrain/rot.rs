@@ -0,0 +1,186 @@
+use bevy::{
+    ecs::{
+        event::{EventReader, EventWriter},
+        system::{Res, ResMut, Resource},
+    },
+    time::Time,
+    utils::HashMap,
+};
+
+use crate::{common::Rand, BlockChangedEvent, BlockFace, BlockType, Terrain};
+
+const ROT_TICK_S: f32 = 2.;
+/// Odds of an organic block catching rot each tick, per rotten block next
+/// to it
+const ROT_SPREAD_CHANCE: f32 = 0.05;
+/// Ticks a rotten block lasts before it breaks down into dirt
+const ROT_TICKS: u32 = 15;
+
+pub struct RottingBlock {
+    pub pos: [u32; 3],
+    pub ticks: u32,
+}
+
+/// Rotten blocks, spreading into the grass, logs and leaves around them
+/// until they break down into dirt.
+#[derive(Resource, Default)]
+pub struct Rot {
+    pub rotting: Vec<RottingBlock>,
+    pub tick_timer: f32,
+}
+
+/// Picks up rotten blocks placed by anything other than `tick_rot`, like the
+/// toolbar or a load.
+pub fn track_rot(mut rot: ResMut<Rot>, mut ev_block_changed: EventReader<BlockChangedEvent>) {
+    for ev in ev_block_changed.read() {
+        if ev.value != BlockType::ROTTEN || rot.rotting.iter().any(|r| r.pos == ev.pos) {
+            continue;
+        }
+
+        rot.rotting.push(RottingBlock {
+            pos: ev.pos,
+            ticks: 0,
+        });
+    }
+}
+
+pub fn tick_rot(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    mut rot: ResMut<Rot>,
+    mut rand: ResMut<Rand>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+) {
+    rot.tick_timer += time.delta_seconds();
+
+    if rot.tick_timer < ROT_TICK_S {
+        return;
+    }
+
+    rot.tick_timer = 0.;
+
+    // removed or replaced since, e.g. by `TaskRemoveRot`
+    rot.rotting
+        .retain(|r| terrain.get_block(r.pos[0], r.pos[1], r.pos[2]).block == BlockType::ROTTEN);
+
+    // organic blocks touching rot, and how many rotten faces they touch
+    let mut exposed: HashMap<[u32; 3], u32> = HashMap::new();
+
+    for rotting in rot.rotting.iter() {
+        let [x, y, z] = rotting.pos;
+
+        for face in BlockFace::ALL {
+            let [dx, dy, dz] = face.offset();
+            let [nx, ny, nz] = [x as i32 + dx, y as i32 + dy, z as i32 + dz];
+
+            if !terrain
+                .get_block_i32(nx, ny, nz)
+                .block
+                .properties()
+                .is_organic
+            {
+                continue;
+            }
+
+            *exposed
+                .entry([nx as u32, ny as u32, nz as u32])
+                .or_insert(0) += 1;
+        }
+    }
+
+    let mut decayed = vec![];
+
+    for rotting in rot.rotting.iter_mut() {
+        rotting.ticks += 1;
+
+        if rotting.ticks >= ROT_TICKS {
+            decayed.push(rotting.pos);
+        }
+    }
+
+    rot.rotting.retain(|r| r.ticks < ROT_TICKS);
+
+    for [x, y, z] in decayed {
+        let change = terrain.set_block(x, y, z, BlockType::DIRT);
+        ev_block_changed.send(change.into());
+    }
+
+    for (pos, count) in exposed {
+        if !rand.bool(ROT_SPREAD_CHANCE * count as f32) {
+            continue;
+        }
+
+        let change = terrain.set_block(pos[0], pos[1], pos[2], BlockType::ROTTEN);
+        rot.rotting.push(RottingBlock { pos, ticks: 0 });
+        ev_block_changed.send(change.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::{event::Events, system::RunSystemOnce, world::World};
+
+    use super::*;
+
+    fn count_blocks(terrain: &Terrain, block_type: BlockType) -> usize {
+        let mut count = 0;
+
+        for x in 0..terrain.world_size_x() {
+            for y in 0..terrain.world_size_y() {
+                for z in 0..terrain.world_size_z() {
+                    if terrain.get_block(x, y, z).block == block_type {
+                        count += 1;
+                    }
+                }
+            }
+        }
+
+        count
+    }
+
+    #[test]
+    fn isolated_rot_stays_isolated() {
+        // rot on a stone floor, with a log two blocks away that it never
+        // touches
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.fill_region([0, 0, 0], [7, 0, 7], BlockType::STONE);
+        terrain.set_block(5, 1, 3, BlockType::LOG);
+        let change = terrain.set_block(3, 1, 3, BlockType::ROTTEN);
+
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.insert_resource(Rand::seed(601));
+        world.init_resource::<Rot>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<BlockChangedEvent>>();
+        world.send_event::<BlockChangedEvent>(change.into());
+        world.run_system_once(track_rot);
+        world.resource_mut::<Events<BlockChangedEvent>>().clear();
+
+        for _ in 1..ROT_TICKS {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(ROT_TICK_S));
+            world.run_system_once(tick_rot);
+
+            let terrain = world.resource::<Terrain>();
+            assert_eq!(count_blocks(terrain, BlockType::ROTTEN), 1);
+            assert_eq!(terrain.get_block(5, 1, 3).block, BlockType::LOG);
+            assert!(world.resource::<Events<BlockChangedEvent>>().is_empty());
+        }
+
+        // and then breaks down on its own
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(ROT_TICK_S));
+        world.run_system_once(tick_rot);
+
+        let terrain = world.resource::<Terrain>();
+        assert_eq!(count_blocks(terrain, BlockType::ROTTEN), 0);
+        assert_eq!(terrain.get_block(3, 1, 3).block, BlockType::DIRT);
+        assert_eq!(world.resource::<Events<BlockChangedEvent>>().len(), 1);
+        assert!(world.resource::<Rot>().rotting.is_empty());
+    }
+}
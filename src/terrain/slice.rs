@@ -2,7 +2,8 @@ use bevy::{
     asset::{Asset, AssetServer, Assets, Handle},
     ecs::{
         event::{Event, EventReader, EventWriter},
-        system::{Commands, Res, ResMut, Resource},
+        query::With,
+        system::{Commands, Query, Res, ResMut, Resource},
     },
     input::{keyboard::KeyCode, mouse::MouseWheel, ButtonInput},
     pbr::{Material, MaterialMeshBundle, MaterialPipeline, MaterialPipelineKey},
@@ -18,9 +19,17 @@ use bevy::{
         texture::{Image, ImageLoaderSettings, ImageSampler},
         view::NoFrustumCulling,
     },
+    transform::components::Transform,
 };
 
-use crate::{pack_block, Terrain, ATTRIBUTE_BLOCK_PACKED};
+use crate::{
+    colonists::Selected,
+    controls::{CursorHit, MainCamera},
+    pack_block, Terrain, ATTRIBUTE_BLOCK_PACKED,
+};
+
+/// Slice step while holding shift, holding tab steps a whole chunk
+const SLICE_STEP_FAST: i32 = 5;
 
 #[derive(Resource)]
 pub struct TerrainSlice {
@@ -32,11 +41,30 @@ pub struct TerrainSlice {
 }
 
 impl TerrainSlice {
+    /// Enabled at `y`, and free to move from the bottom of the world up to
+    /// `max`
+    pub fn new(y: u32, max: u32, mesh_handle: Handle<Mesh>) -> Self {
+        Self {
+            y,
+            max,
+            min: 0,
+            is_enabled: true,
+            mesh_handle,
+        }
+    }
+
     pub fn set_value(&mut self, v: i32) -> u32 {
         self.y = v.clamp(self.min as i32, self.max as i32) as u32;
         self.get_value()
     }
 
+    /// Clamped like `set_value`, returns true if the slice actually moved.
+    pub fn set_y(&mut self, v: i32) -> bool {
+        let previous = self.y;
+        self.set_value(v);
+        self.y != previous
+    }
+
     pub fn get_value(&self) -> u32 {
         if self.is_enabled {
             self.y
@@ -88,13 +116,8 @@ pub fn setup_terrain_slice(
         NoFrustumCulling,
     ));
 
-    cmd.insert_resource(TerrainSlice {
-        y: initial_slice,
-        max,
-        min: 0,
-        is_enabled: true,
-        mesh_handle,
-    });
+    cmd.insert_resource(TerrainSlice::new(initial_slice, max, mesh_handle));
+    cmd.insert_resource(TerrainSliceMode::Manual(initial_slice as f32));
 }
 
 pub fn update_slice_mesh(
@@ -122,26 +145,165 @@ pub fn update_slice_mesh(
 #[derive(Event)]
 pub struct TerrainSliceChanged;
 
+#[derive(Resource, Clone, Copy, PartialEq)]
+pub enum TerrainSliceMode {
+    /// Hold the slice at the given height, moved by scrolling.
+    Manual(f32),
+    /// Keep the slice at the camera's height, shifted by `offset` blocks.
+    FollowCamera { offset: f32 },
+    /// Keep the slice one above the selected colonist.
+    FollowSelected,
+}
+
+/// Leaving a follow mode holds the slice wherever it was following to.
+pub fn toggle_slice_mode(
+    input_keys: Res<ButtonInput<KeyCode>>,
+    terrain_slice: Res<TerrainSlice>,
+    mut slice_mode: ResMut<TerrainSliceMode>,
+) {
+    let manual = TerrainSliceMode::Manual(terrain_slice.y as f32);
+
+    if input_keys.just_pressed(KeyCode::KeyF) {
+        *slice_mode = match *slice_mode {
+            TerrainSliceMode::FollowCamera { .. } => manual,
+            _ => TerrainSliceMode::FollowCamera { offset: 0. },
+        };
+    }
+
+    if input_keys.just_pressed(KeyCode::KeyT) {
+        *slice_mode = match *slice_mode {
+            TerrainSliceMode::FollowSelected => manual,
+            _ => TerrainSliceMode::FollowSelected,
+        };
+    }
+}
+
+pub fn update_slice_from_camera(
+    slice_mode: Res<TerrainSliceMode>,
+    q_camera: Query<&Transform, With<MainCamera>>,
+    mut terrain_slice: ResMut<TerrainSlice>,
+    mut ev_terrain_slice: EventWriter<TerrainSliceChanged>,
+) {
+    let TerrainSliceMode::FollowCamera { offset } = *slice_mode else {
+        return;
+    };
+
+    let Ok(transform) = q_camera.get_single() else {
+        return;
+    };
+
+    let target = (transform.translation.y + offset).floor() as i32;
+
+    if terrain_slice.set_y(target) {
+        ev_terrain_slice.send(TerrainSliceChanged);
+    }
+}
+
+pub fn update_slice_from_selected(
+    slice_mode: Res<TerrainSliceMode>,
+    q_selected: Query<&Transform, With<Selected>>,
+    mut terrain_slice: ResMut<TerrainSlice>,
+    mut ev_terrain_slice: EventWriter<TerrainSliceChanged>,
+) {
+    if *slice_mode != TerrainSliceMode::FollowSelected {
+        return;
+    }
+
+    let Ok(transform) = q_selected.get_single() else {
+        return;
+    };
+
+    let target = transform.translation.y.floor() as i32 + 1;
+
+    if terrain_slice.set_y(target) {
+        ev_terrain_slice.send(TerrainSliceChanged);
+    }
+}
+
+/// Puts the slice just above the top block of the column under the cursor.
+pub fn snap_slice_to_surface(
+    input_keys: Res<ButtonInput<KeyCode>>,
+    cursor_hit: Res<CursorHit>,
+    terrain: Res<Terrain>,
+    mut slice_mode: ResMut<TerrainSliceMode>,
+    mut terrain_slice: ResMut<TerrainSlice>,
+    mut ev_terrain_slice: EventWriter<TerrainSliceChanged>,
+) {
+    if !input_keys.just_pressed(KeyCode::KeyG) || !cursor_hit.is_hit {
+        return;
+    }
+
+    let [x, _, z] = cursor_hit.hit_pos;
+
+    let Some(surface_y) = terrain.get_surface_y(x, z) else {
+        return;
+    };
+
+    *slice_mode = TerrainSliceMode::Manual((surface_y + 1) as f32);
+
+    if terrain_slice.set_y(surface_y as i32 + 1) {
+        ev_terrain_slice.send(TerrainSliceChanged);
+    }
+}
+
+/// Blocks moved per scroll line or key press, bigger with a modifier held
+fn slice_step(input_keys: &ButtonInput<KeyCode>, chunk_size: u32) -> i32 {
+    if input_keys.pressed(KeyCode::Tab) {
+        chunk_size as i32
+    } else if input_keys.pressed(KeyCode::ShiftLeft) {
+        SLICE_STEP_FAST
+    } else {
+        1
+    }
+}
+
 pub fn scroll_events(
     mut scroll_evt: EventReader<MouseWheel>,
     input_keys: Res<ButtonInput<KeyCode>>,
+    terrain: Res<Terrain>,
     mut terrain_slice: ResMut<TerrainSlice>,
+    mut slice_mode: ResMut<TerrainSliceMode>,
     mut ev_terrain_slice: EventWriter<TerrainSliceChanged>,
 ) {
+    let step = slice_step(&input_keys, terrain.chunk_size);
+    let mut delta = 0;
+
     for ev in scroll_evt.read() {
         match ev.unit {
             bevy::input::mouse::MouseScrollUnit::Line => {
                 if input_keys.pressed(KeyCode::ControlLeft) {
                     continue;
                 }
-                let scroll = ev.y as i32;
-                let slice = terrain_slice.y as i32;
-                terrain_slice.set_value(slice + scroll);
-                ev_terrain_slice.send(TerrainSliceChanged);
+                delta += ev.y as i32 * step;
             }
             bevy::input::mouse::MouseScrollUnit::Pixel => {}
         }
     }
+
+    if input_keys.just_pressed(KeyCode::PageUp) {
+        delta += step;
+    }
+
+    if input_keys.just_pressed(KeyCode::PageDown) {
+        delta -= step;
+    }
+
+    if delta == 0 {
+        return;
+    }
+
+    match slice_mode.as_mut() {
+        TerrainSliceMode::FollowCamera { offset } => *offset += delta as f32,
+        TerrainSliceMode::FollowSelected => {}
+        TerrainSliceMode::Manual(y) => {
+            let is_moved = terrain_slice.set_y(*y as i32 + delta);
+            *y = terrain_slice.y as f32;
+
+            if is_moved {
+                ev_terrain_slice.send(TerrainSliceChanged);
+            }
+        }
+    }
 }
 
 #[derive(Default)]
@@ -176,9 +338,12 @@ fn build_slice_mesh(terrain: &Terrain, slice_y: u32) -> SliceMeshData {
             }
 
             let packed = pack_block(
+                below.texture_variant([x, slice_y - 1, z]),
                 below,
+                0,
                 crate::BlockFace::PosY,
                 crate::VertexCornerCount::None,
+                block,
             );
 
             let fx = x as f32;
@@ -254,3 +419,120 @@ impl Material for SliceMaterial {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::{entity::Entity, event::Events, system::RunSystemOnce, world::World},
+        input::mouse::MouseScrollUnit,
+    };
+
+    use crate::{Block, BlockType};
+
+    use super::*;
+
+    /// Two chunks of 8 high, the slice starting at 10 in manual mode
+    fn slice_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(Terrain::new(1, 2, 1, 8).unwrap());
+        world.insert_resource(TerrainSlice::new(10, 16, Handle::default()));
+        world.insert_resource(TerrainSliceMode::Manual(10.));
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<Events<MouseWheel>>();
+        world.init_resource::<Events<TerrainSliceChanged>>();
+        world
+    }
+
+    /// Scrolls `lines` with `modifier` held, returns the slice and how many
+    /// change events went out
+    fn scroll(world: &mut World, lines: f32, modifier: Option<KeyCode>) -> (u32, usize) {
+        let mut input_keys = world.resource_mut::<ButtonInput<KeyCode>>();
+        input_keys.reset_all();
+        if let Some(key) = modifier {
+            input_keys.press(key);
+        }
+
+        world.send_event(MouseWheel {
+            unit: MouseScrollUnit::Line,
+            x: 0.,
+            y: lines,
+            window: Entity::PLACEHOLDER,
+        });
+        world.run_system_once(scroll_events);
+        world.resource_mut::<Events<MouseWheel>>().clear();
+
+        (world.resource::<TerrainSlice>().y, drain_changes(world))
+    }
+
+    fn drain_changes(world: &mut World) -> usize {
+        world
+            .resource_mut::<Events<TerrainSliceChanged>>()
+            .drain()
+            .count()
+    }
+
+    #[test]
+    fn modifiers_set_the_scroll_step() {
+        let mut world = slice_world();
+
+        assert_eq!(scroll(&mut world, -1., None), (9, 1));
+        assert_eq!(scroll(&mut world, -1., Some(KeyCode::ShiftLeft)), (4, 1));
+        assert_eq!(scroll(&mut world, 1., Some(KeyCode::Tab)), (12, 1));
+
+        // clamped to the top, and no event once it stops moving
+        assert_eq!(scroll(&mut world, 1., Some(KeyCode::Tab)), (16, 1));
+        assert_eq!(scroll(&mut world, 1., Some(KeyCode::Tab)), (16, 0));
+        assert!(*world.resource::<TerrainSliceMode>() == TerrainSliceMode::Manual(16.));
+    }
+
+    #[test]
+    fn snap_puts_the_slice_above_the_surface() {
+        let mut world = slice_world();
+        world
+            .resource_mut::<Terrain>()
+            .set_block(2, 5, 3, BlockType::STONE);
+        world.insert_resource(CursorHit {
+            is_hit: true,
+            hit_pos: [2, 5, 3],
+            hit_block: Block::OOB,
+            is_adj_hit: false,
+            adj_pos: [0, 0, 0],
+        });
+        world.insert_resource(TerrainSliceMode::FollowSelected);
+        world
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyG);
+
+        world.run_system_once(snap_slice_to_surface);
+
+        assert_eq!(world.resource::<TerrainSlice>().y, 6);
+        assert!(*world.resource::<TerrainSliceMode>() == TerrainSliceMode::Manual(6.));
+        assert_eq!(drain_changes(&mut world), 1);
+    }
+
+    #[test]
+    fn slice_follows_the_selected_colonist() {
+        let mut world = slice_world();
+        let colonist = world
+            .spawn((Selected, Transform::from_xyz(0., 3.5, 0.)))
+            .id();
+
+        // held in manual mode
+        world.run_system_once(update_slice_from_selected);
+        assert_eq!(world.resource::<TerrainSlice>().y, 10);
+
+        world.insert_resource(TerrainSliceMode::FollowSelected);
+        world.run_system_once(update_slice_from_selected);
+        assert_eq!(world.resource::<TerrainSlice>().y, 4);
+        assert_eq!(drain_changes(&mut world), 1);
+
+        // standing still sends nothing
+        world.run_system_once(update_slice_from_selected);
+        assert_eq!(drain_changes(&mut world), 0);
+
+        world.get_mut::<Transform>(colonist).unwrap().translation.y = 7.2;
+        world.run_system_once(update_slice_from_selected);
+        assert_eq!(world.resource::<TerrainSlice>().y, 8);
+        assert_eq!(drain_changes(&mut world), 1);
+    }
+}
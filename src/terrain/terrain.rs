@@ -1,10 +1,28 @@
-use bevy::ecs::system::Resource;
+use std::{collections::VecDeque, io, ops::Range, path::PathBuf};
+
+use bevy::{
+    ecs::{event::Event, system::Resource},
+    math::Vec3,
+    utils::{HashMap, HashSet},
+};
+use itertools::Itertools;
 use ndshape::{RuntimeShape, Shape};
 
-use crate::{common::sig_num, Block, BlockBuffer, BlockFace, BlockType, LightNode};
+use crate::{
+    colonists::{get_block_flags, NavigationFlags},
+    common::{flood_fill_from_i32, sig_num},
+    save::{
+        default_chunk_cache_dir, encode_chunk, read_chunk_cache, read_chunk_cache_bytes,
+        write_chunk_cache,
+    },
+    validate_world_shape, Block, BlockBuffer, BlockDamage, BlockFace, BlockType, FluidDepth,
+    LightNode, WorldGenConfigError, TORCH_FUEL_S,
+};
 
 #[derive(Resource)]
 pub struct Terrain {
+    /// Seed the world was generated with
+    pub seed: i32,
     pub chunk_count_x: u32,
     pub chunk_count_y: u32,
     pub chunk_count_z: u32,
@@ -12,21 +30,97 @@ pub struct Terrain {
     pub chunk_count: u32,
     pub shape: RuntimeShape<u32, 3>,
     pub chunk_shape: RuntimeShape<u32, 3>,
-    pub chunks: Vec<BlockBuffer>,
-    pub lights_queue_add: Vec<LightNode>,
-    pub lights_queue_remove: Vec<LightNode>,
-    pub sunlight_queue_add: Vec<LightNode>,
-    pub sunlight_queue_remove: Vec<LightNode>,
+    /// Chunks that were streamed out are `None`, see `unload_chunk`
+    pub chunks: Vec<Option<BlockBuffer>>,
+    /// Where `unload_chunk` writes chunks, and `load_chunk` reads them back
+    pub chunk_cache_dir: PathBuf,
+    /// Blocks `flood_fill_blocks` gives up after, so a fill that leaks into
+    /// a huge open cave can't run away
+    pub flood_fill_max_blocks: usize,
+    pub lights_queue_add: VecDeque<LightNode>,
+    pub lights_queue_remove: VecDeque<LightNode>,
+    pub sunlight_queue_add: VecDeque<LightNode>,
+    pub sunlight_queue_remove: VecDeque<LightNode>,
+    pub heat_sources: HashSet<[u32; 3]>,
+    /// Growth ticks of every farm soil block.
+    pub farm_plots: HashMap<[u32; 3], u32>,
+    /// Seconds of fuel left in every torch
+    pub torch_fuel: HashMap<[u32; 3], u32>,
+    /// Mining progress of partly mined blocks, see `add_block_damage`
+    pub block_damage: HashMap<[u32; 3], BlockDamage>,
+    /// Surface y per (x, z) column, filled by `cache_surface_heights` and
+    /// kept current one column at a time by `set_block`.
+    surface_cache: Box<[u16]>,
+    /// Chunks whose navigation needs rebuilding, drained by
+    /// `flush_partition_updates`.
+    partition_queue: Vec<PendingPartitionUpdate>,
+    /// Set while `record_batch` runs, holds each chunk as it was before the
+    /// batch first touched it
+    recording: Option<HashMap<u32, BlockBuffer>>,
+    /// Recorded batches waiting for `collect_edit_history`
+    recorded_batches: Vec<Vec<EditRecord>>,
 }
 
-pub struct RayResult {
-    pub is_hit: bool,
-    pub block: Block,
-    pub x: u32,
-    pub y: u32,
-    pub z: u32,
-    pub attempts: u32,
+/// Default for `Terrain::flood_fill_max_blocks`
+const FLOOD_FILL_MAX_BLOCKS: usize = 1 << 16;
+
+const SURFACE_UNKNOWN: u16 = u16::MAX;
+const SURFACE_NONE: u16 = u16::MAX - 1;
+
+#[derive(Event)]
+pub struct BlockChangedEvent {
+    pub pos: [u32; 3],
+    pub previous: BlockType,
+    pub value: BlockType,
+}
+
+/// The result of `Terrain::set_block`, keeps the old value around so the
+/// change can be undone.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockChange {
+    pub pos: [u32; 3],
+    pub previous: BlockType,
+    pub value: BlockType,
+}
+
+impl From<BlockChange> for BlockChangedEvent {
+    fn from(change: BlockChange) -> Self {
+        Self {
+            pos: change.pos,
+            previous: change.previous,
+            value: change.value,
+        }
+    }
+}
+
+/// A single change made by `Terrain::edit_region`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BlockEdit {
+    Type(BlockType),
+    FlagMine(bool),
+}
+
+/// One recorded edit, applying `previous` at `pos` reverts it
+#[derive(Clone, Copy, Debug)]
+pub struct EditRecord {
+    pub pos: [u32; 3],
+    pub previous: BlockEdit,
+    pub value: BlockEdit,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PendingPartitionUpdate {
+    pub chunk_idx: u32,
+}
+
+/// The first block a ray ran into, see `Terrain::raycast`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RayHit {
+    pub block: [u32; 3],
+    /// The face the ray came in through
     pub face: BlockFace,
+    /// How far along the ray the block starts
+    pub distance: f32,
 }
 
 impl Terrain {
@@ -35,35 +129,168 @@ impl Terrain {
         chunk_count_y: u32,
         chunk_count_z: u32,
         chunk_size: u32,
-    ) -> Self {
+    ) -> Result<Self, WorldGenConfigError> {
+        validate_world_shape([chunk_count_x, chunk_count_y, chunk_count_z], chunk_size)?;
+
         let shape = RuntimeShape::<u32, 3>::new([chunk_count_x, chunk_count_y, chunk_count_z]);
         let chunk_shape = RuntimeShape::<u32, 3>::new([chunk_size, chunk_size, chunk_size]);
+        // a fresh world has no partitions yet
+        let partition_queue = (0..shape.size())
+            .map(|chunk_idx| PendingPartitionUpdate { chunk_idx })
+            .collect();
 
-        Self {
+        let mut terrain = Self {
+            seed: 0,
             chunk_count_x,
             chunk_count_y,
             chunk_count_z,
             chunk_size,
             chunk_count: shape.size(),
             chunk_shape: chunk_shape.clone(),
-            chunks: vec![BlockBuffer::new(chunk_shape); shape.size() as usize],
+            chunks: vec![Some(BlockBuffer::new(chunk_shape)); shape.size() as usize],
             shape,
-            lights_queue_add: vec![],
-            lights_queue_remove: vec![],
-            sunlight_queue_add: vec![],
-            sunlight_queue_remove: vec![],
+            chunk_cache_dir: default_chunk_cache_dir(),
+            flood_fill_max_blocks: FLOOD_FILL_MAX_BLOCKS,
+            lights_queue_add: VecDeque::new(),
+            lights_queue_remove: VecDeque::new(),
+            sunlight_queue_add: VecDeque::new(),
+            sunlight_queue_remove: VecDeque::new(),
+            heat_sources: HashSet::new(),
+            farm_plots: HashMap::new(),
+            torch_fuel: HashMap::new(),
+            block_damage: HashMap::new(),
+            surface_cache: vec![
+                SURFACE_UNKNOWN;
+                (chunk_count_x * chunk_size * chunk_count_z * chunk_size) as usize
+            ]
+            .into_boxed_slice(),
+            partition_queue,
+            recording: None,
+            recorded_batches: vec![],
+        };
+
+        // chunks know where they are, the chunk cache is keyed by index
+        for chunk_idx in 0..terrain.chunk_count {
+            terrain.init_chunk(chunk_idx);
+        }
+
+        Ok(terrain)
+    }
+
+    /// Run `f` and record every block and mine flag change it makes as one
+    /// batch for the edit history. Anything outside of this, like world
+    /// generation or colonists digging, is not recorded.
+    ///
+    /// Each chunk is copied the first time `f` changes it, and the batch is
+    /// the diff of those copies against the chunks afterwards, so a block
+    /// that ends up the way it started isn't recorded at all.
+    pub fn record_batch<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.recording = Some(HashMap::new());
+        let result = f(self);
+
+        let Some(snapshots) = self.recording.take() else {
+            return result;
+        };
+
+        let mut batch = vec![];
+
+        for chunk_idx in snapshots.keys().copied().sorted() {
+            let Some(chunk) = self.get_chunk(chunk_idx) else {
+                continue;
+            };
+
+            for (block_idx, old, new) in snapshots[&chunk_idx].diff(chunk) {
+                let pos = self.get_block_world_pos(chunk_idx, block_idx);
+
+                if old.block != new.block {
+                    batch.push(EditRecord {
+                        pos,
+                        previous: BlockEdit::Type(old.block),
+                        value: BlockEdit::Type(new.block),
+                    });
+                }
+
+                if old.flag_mine != new.flag_mine {
+                    batch.push(EditRecord {
+                        pos,
+                        previous: BlockEdit::FlagMine(old.flag_mine),
+                        value: BlockEdit::FlagMine(new.flag_mine),
+                    });
+                }
+            }
+        }
+
+        if !batch.is_empty() {
+            self.recorded_batches.push(batch);
+        }
+
+        result
+    }
+
+    pub fn take_recorded_batches(&mut self) -> Vec<Vec<EditRecord>> {
+        std::mem::take(&mut self.recorded_batches)
+    }
+
+    /// Keep a copy of the chunk before a recorded batch first changes it
+    fn snapshot_chunk(&mut self, chunk_idx: u32) {
+        let Some(snapshots) = self.recording.as_mut() else {
+            return;
+        };
+
+        if snapshots.contains_key(&chunk_idx) {
+            return;
+        }
+
+        if let Some(Some(chunk)) = self.chunks.get(chunk_idx as usize) {
+            snapshots.insert(chunk_idx, chunk.clone());
+        }
+    }
+
+    /// Apply a recorded edit through `set_block` or `set_flag_mine`. Returns
+    /// the block change when the type was set.
+    pub fn apply_edit(&mut self, [x, y, z]: [u32; 3], edit: BlockEdit) -> Option<BlockChange> {
+        match edit {
+            BlockEdit::Type(value) => Some(self.set_block(x, y, z, value)),
+            BlockEdit::FlagMine(value) => {
+                self.set_flag_mine(x, y, z, value);
+                None
+            }
         }
     }
 
     pub fn init_chunk(&mut self, chunk_idx: u32) {
         let chunk_pos = self.shape.delinearize(chunk_idx);
-        let chunk = self.chunks.get_mut(chunk_idx as usize).unwrap();
+        let chunk_size = self.chunk_size;
+        let chunk = self.get_chunk_mut(chunk_idx).unwrap();
 
         chunk.chunk_idx = chunk_idx;
-        chunk.world_x = self.chunk_size * chunk_pos[0];
-        chunk.world_y = self.chunk_size * chunk_pos[1];
-        chunk.world_z = self.chunk_size * chunk_pos[2];
-        chunk.chunk_size = self.chunk_size;
+        chunk.world_x = chunk_size * chunk_pos[0];
+        chunk.world_y = chunk_size * chunk_pos[1];
+        chunk.world_z = chunk_size * chunk_pos[2];
+        chunk.chunk_size = chunk_size;
+    }
+
+    /// FNV-1a hash over every block type, for checking that generation is
+    /// deterministic.
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+
+        for chunk in self.chunks.iter().flatten() {
+            for block_idx in 0..chunk.block_count {
+                hash ^= chunk.get_block(block_idx).block.0 as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+
+        hash
+    }
+
+    pub fn count_blocks_of_type_global(&self, block_type: BlockType) -> u32 {
+        self.chunks
+            .iter()
+            .flatten()
+            .map(|chunk| chunk.count_blocks_of_type(block_type))
+            .sum()
     }
 
     pub fn world_size_x(&self) -> u32 {
@@ -78,6 +305,14 @@ impl Terrain {
         self.chunk_count_z * self.chunk_size
     }
 
+    /// Blocks in the whole world, loaded or not. Large worlds don't fit in
+    /// a u32.
+    pub fn total_block_count(&self) -> u64 {
+        self.world_size_x() as u64 * self.world_size_y() as u64 * self.world_size_z() as u64
+    }
+
+    /// Checks the sign before anything is cast, so `y - 1` at the bottom of
+    /// the world is out of bounds rather than wrapping around.
     pub fn is_oob(&self, x: i32, y: i32, z: i32) -> bool {
         x < 0
             || y < 0
@@ -87,25 +322,150 @@ impl Terrain {
             || z >= self.world_size_z() as i32
     }
 
+    /// Past the far edges the chunk math would land in some other chunk, so
+    /// anything that indexes by u32 checks this first.
+    pub fn is_oob_u32(&self, x: u32, y: u32, z: u32) -> bool {
+        x >= self.world_size_x() || y >= self.world_size_y() || z >= self.world_size_z()
+    }
+
+    /// World bounds as `(min, max)`, from the corner of the first block to
+    /// the far corner of the last one.
+    pub fn world_aabb(&self) -> (Vec3, Vec3) {
+        let max = Vec3::new(
+            self.world_size_x() as f32,
+            self.world_size_y() as f32,
+            self.world_size_z() as f32,
+        );
+
+        (Vec3::ZERO, max)
+    }
+
+    /// Whether a world position, e.g. a click, lands inside a block of the
+    /// world. The far faces belong to the next block over, so they are out.
+    pub fn contains_world_pos(&self, x: f32, y: f32, z: f32) -> bool {
+        let (min, max) = self.world_aabb();
+
+        x >= min.x && y >= min.y && z >= min.z && x < max.x && y < max.y && z < max.z
+    }
+
     pub fn get_chunk(&self, chunk_idx: u32) -> Option<&BlockBuffer> {
-        return self.chunks.get(chunk_idx as usize);
+        self.chunks.get(chunk_idx as usize)?.as_ref()
+    }
+
+    pub fn is_chunk_loaded(&self, chunk_idx: u32) -> bool {
+        self.get_chunk(chunk_idx).is_some()
+    }
+
+    /// Bytes held by the loaded chunks, and how many of them are compacted
+    pub fn chunk_memory(&self) -> (usize, u32) {
+        self.chunks
+            .iter()
+            .flatten()
+            .fold((0, 0), |(bytes, compact), chunk| {
+                (
+                    bytes + chunk.memory_bytes(),
+                    compact + chunk.is_compact() as u32,
+                )
+            })
     }
 
     pub fn get_chunk_dirty(&self, chunk_idx: u32) -> bool {
-        if let Some(chunk) = self.chunks.get(chunk_idx as usize) {
+        if let Some(chunk) = self.get_chunk(chunk_idx) {
             return chunk.is_dirty;
         }
         false
     }
 
+    /// True when the chunk has nothing to draw.
+    pub fn is_chunk_air(&self, chunk_idx: u32) -> bool {
+        self.get_chunk(chunk_idx)
+            .is_none_or(|chunk| chunk.filled_count == 0 && !chunk.has_rendered_blocks())
+    }
+
     pub fn set_chunk_dirty(&mut self, chunk_idx: u32, value: bool) {
-        if let Some(chunk) = self.chunks.get_mut(chunk_idx as usize) {
+        if let Some(chunk) = self.get_chunk_mut(chunk_idx) {
             chunk.is_dirty = value;
         }
     }
 
     pub fn get_chunk_mut(&mut self, chunk_idx: u32) -> Option<&mut BlockBuffer> {
-        return self.chunks.get_mut(chunk_idx as usize);
+        self.chunks.get_mut(chunk_idx as usize)?.as_mut()
+    }
+
+    /// Write the chunk to the chunk cache and drop it from memory. Until it is
+    /// loaded again every block in it reads as OOB.
+    pub fn unload_chunk(&mut self, chunk_idx: u32) -> io::Result<()> {
+        let Some(chunk) = self.get_chunk(chunk_idx) else {
+            return Ok(());
+        };
+
+        write_chunk_cache(&self.chunk_cache_dir, chunk)?;
+        self.chunks[chunk_idx as usize] = None;
+        self.touch_chunk_neighbors(chunk_idx);
+
+        Ok(())
+    }
+
+    /// Read a chunk dropped by `unload_chunk` back from the chunk cache
+    pub fn load_chunk(&mut self, chunk_idx: u32) -> io::Result<()> {
+        if chunk_idx >= self.chunk_count || self.is_chunk_loaded(chunk_idx) {
+            return Ok(());
+        }
+
+        let mut chunk = BlockBuffer::new(self.chunk_shape.clone());
+        chunk.chunk_idx = chunk_idx;
+        read_chunk_cache(&self.chunk_cache_dir, &mut chunk)?;
+
+        self.chunks[chunk_idx as usize] = Some(chunk);
+        self.init_chunk(chunk_idx);
+        self.set_chunk_dirty(chunk_idx, true);
+        self.queue_partition_update(chunk_idx);
+        self.touch_chunk_neighbors(chunk_idx);
+
+        Ok(())
+    }
+
+    /// Append the chunk in the save format, as it is in memory or as it was
+    /// cached when unloaded
+    pub fn encode_chunk_or_cached(&self, chunk_idx: u32, buffer: &mut Vec<u8>) -> io::Result<()> {
+        match self.get_chunk(chunk_idx) {
+            Some(chunk) => {
+                encode_chunk(chunk, buffer);
+                Ok(())
+            }
+            None => read_chunk_cache_bytes(
+                &self.chunk_cache_dir,
+                chunk_idx,
+                self.chunk_shape.size(),
+                buffer,
+            ),
+        }
+    }
+
+    /// Remesh and repartition the chunks sharing a face with this one, their
+    /// border blocks see it appear or disappear.
+    fn touch_chunk_neighbors(&mut self, chunk_idx: u32) {
+        let [cx, cy, cz] = self.shape.delinearize(chunk_idx);
+        let counts = [self.chunk_count_x, self.chunk_count_y, self.chunk_count_z];
+
+        for face in BlockFace::ALL {
+            let [dx, dy, dz] = face.offset();
+            let [nx, ny, nz] = [cx as i32 + dx, cy as i32 + dy, cz as i32 + dz];
+
+            if nx < 0
+                || ny < 0
+                || nz < 0
+                || nx >= counts[0] as i32
+                || ny >= counts[1] as i32
+                || nz >= counts[2] as i32
+            {
+                continue;
+            }
+
+            let neighbor_idx = self.shape.linearize([nx as u32, ny as u32, nz as u32]);
+            self.set_chunk_dirty(neighbor_idx, true);
+            self.queue_partition_update(neighbor_idx);
+        }
     }
 
     pub fn get_chunk_offset(&self, chunk_idx: u32) -> [u32; 3] {
@@ -146,20 +506,248 @@ impl Terrain {
         [chunk_idx, block_idx]
     }
 
-    pub fn set_block_type(&mut self, x: u32, y: u32, z: u32, value: BlockType) {
+    /// Change a block and everything that depends on it: lighting, heat,
+    /// farm plots, the surface cache, the meshes of the chunk and any chunk
+    /// it borders, and a queued partition update for each of those chunks.
+    pub fn set_block(&mut self, x: u32, y: u32, z: u32, value: BlockType) -> BlockChange {
+        if !self.is_oob_u32(x, y, z) {
+            self.snapshot_chunk(self.get_block_indexes(x, y, z)[0]);
+        }
+
+        let previous = self.apply_block_type(x, y, z, value);
+
+        if !self.is_oob_u32(x, y, z) {
+            let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
+            self.mark_dirty_with_neighbors(chunk_idx, block_idx);
+            self.queue_partition_updates(chunk_idx, block_idx);
+        }
+
+        BlockChange {
+            pos: [x, y, z],
+            previous,
+            value,
+        }
+    }
+
+    /// Apply `f` to every block in the box from `min` to `max` (inclusive).
+    /// Dirty flags and partition updates are gathered up and applied once per
+    /// touched chunk, instead of once per block. Returns the positions of the
+    /// blocks that changed.
+    pub fn edit_region(
+        &mut self,
+        min: [u32; 3],
+        max: [u32; 3],
+        mut f: impl FnMut(u32, u32, u32, Block) -> Option<BlockEdit>,
+    ) -> Vec<[u32; 3]> {
+        let max = [
+            max[0].min(self.world_size_x() - 1),
+            max[1].min(self.world_size_y() - 1),
+            max[2].min(self.world_size_z() - 1),
+        ];
+
+        let mut edited = vec![];
+        let mut dirty = HashSet::new();
+        let mut repartition = HashSet::new();
+
+        for x in min[0]..=max[0] {
+            for y in min[1]..=max[1] {
+                for z in min[2]..=max[2] {
+                    let block = self.get_block(x, y, z);
+
+                    let Some(edit) = f(x, y, z, block) else {
+                        continue;
+                    };
+
+                    let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
+                    self.snapshot_chunk(chunk_idx);
+
+                    let (is_changed, is_nav_changed) = match edit {
+                        BlockEdit::Type(value) => {
+                            let is_changed = block.block != value;
+                            if is_changed {
+                                self.apply_block_type(x, y, z, value);
+                            }
+                            (is_changed, is_changed)
+                        }
+                        BlockEdit::FlagMine(value) => {
+                            let is_changed = self
+                                .get_chunk_mut(chunk_idx)
+                                .is_some_and(|chunk| chunk.set_flag_mine(block_idx, value));
+                            (is_changed, false)
+                        }
+                    };
+
+                    if !is_changed {
+                        continue;
+                    }
+
+                    edited.push([x, y, z]);
+
+                    for idx in self.get_touching_chunks(chunk_idx, block_idx) {
+                        dirty.insert(idx);
+
+                        if is_nav_changed {
+                            repartition.insert(idx);
+                        }
+                    }
+                }
+            }
+        }
+
+        for chunk_idx in dirty {
+            self.set_chunk_dirty(chunk_idx, true);
+        }
+
+        for chunk_idx in repartition {
+            self.queue_partition_update(chunk_idx);
+        }
+
+        edited
+    }
+
+    pub fn fill_region(
+        &mut self,
+        min: [u32; 3],
+        max: [u32; 3],
+        value: BlockType,
+    ) -> Vec<BlockChange> {
+        let mut changes = vec![];
+
+        self.edit_region(min, max, |x, y, z, block| {
+            if block.block != value {
+                changes.push(BlockChange {
+                    pos: [x, y, z],
+                    previous: block.block,
+                    value,
+                });
+            }
+
+            Some(BlockEdit::Type(value))
+        });
+
+        changes
+    }
+
+    pub fn clear_region(&mut self, min: [u32; 3], max: [u32; 3]) -> Vec<BlockChange> {
+        self.fill_region(min, max, BlockType::EMPTY)
+    }
+
+    /// `fill_region` for a single column, e.g. a shaft or a pillar
+    pub fn set_block_column(
+        &mut self,
+        x: u32,
+        z: u32,
+        y_range: Range<u32>,
+        value: BlockType,
+    ) -> Vec<BlockChange> {
+        if y_range.is_empty() {
+            return vec![];
+        }
+
+        self.fill_region([x, y_range.start, z], [x, y_range.end - 1, z], value)
+    }
+
+    /// Every `(y, block)` in a column, bottom to top
+    pub fn get_column(&self, x: u32, z: u32) -> impl Iterator<Item = (u32, Block)> + '_ {
+        (0..self.world_size_y()).map(move |y| (y, self.get_block(x, y, z)))
+    }
+
+    /// Flags or unflags every non-empty block in the box for mining
+    pub fn set_mine_flag_region(
+        &mut self,
+        min: [u32; 3],
+        max: [u32; 3],
+        value: bool,
+    ) -> Vec<[u32; 3]> {
+        self.edit_region(min, max, |_, _, _, block| {
+            if block.is_empty() {
+                None
+            } else {
+                Some(BlockEdit::FlagMine(value))
+            }
+        })
+    }
+
+    /// Set the block type and update lighting, heat, farm plots, torches, and
+    /// the surface cache. Returns the previous type.
+    fn apply_block_type(&mut self, x: u32, y: u32, z: u32, value: BlockType) -> BlockType {
+        let mut previous = BlockType::OOB;
+
+        if self.is_oob_u32(x, y, z) {
+            return previous;
+        }
+
         let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
 
         if let Some(chunk) = self.get_chunk_mut(chunk_idx) {
+            previous = chunk.get_block(block_idx).block;
             chunk.set_block_type(block_idx, value);
             self.remove_sunlight(x, y, z);
+            // always clear first, a dimmer light replacing a brighter one
+            // would otherwise leave the old halo lit
+            self.remove_light(x, y, z);
 
             if value.is_light() {
                 self.add_light(x, y, z, value.get_light_level());
-            } else {
-                self.remove_light(x, y, z);
             }
+
+            if previous.is_heat_source() || value.is_heat_source() {
+                self.update_heat_source(x, y, z, value);
+            }
+
+            if previous.is_farm_soil() || value.is_farm_soil() {
+                self.update_farm_plot(x, y, z, value);
+            }
+
+            if previous == BlockType::TORCH || value == BlockType::TORCH {
+                self.update_torch(x, y, z, value);
+            }
+
+            if previous != value {
+                self.block_damage.remove(&[x, y, z]);
+            }
+
+            self.update_surface_y(x, y, z);
+        }
+
+        previous
+    }
+
+    /// Marks the chunk as dirty. Blocks on the edge of a chunk are sampled by
+    /// the meshes of the chunks next to them, so if the block sits on a chunk
+    /// boundary (face, edge, or corner), the up-to-3 adjacent chunks are
+    /// marked dirty as well.
+    pub fn mark_dirty_with_neighbors(&mut self, chunk_idx: u32, block_idx: u32) {
+        for idx in self.get_touching_chunks(chunk_idx, block_idx) {
+            self.set_chunk_dirty(idx, true);
         }
+    }
 
+    /// Queue a partition update for every chunk the block touches, skipping
+    /// chunks that are already queued.
+    pub fn queue_partition_updates(&mut self, chunk_idx: u32, block_idx: u32) {
+        for idx in self.get_touching_chunks(chunk_idx, block_idx) {
+            self.queue_partition_update(idx);
+        }
+    }
+
+    pub fn queue_partition_update(&mut self, chunk_idx: u32) {
+        let update = PendingPartitionUpdate { chunk_idx };
+
+        if !self.partition_queue.contains(&update) {
+            self.partition_queue.push(update);
+        }
+    }
+
+    pub fn take_partition_updates(&mut self) -> Vec<PendingPartitionUpdate> {
+        std::mem::take(&mut self.partition_queue)
+    }
+
+    /// The chunk a block is in, plus the up-to-3 chunks it borders.
+    fn get_touching_chunks(&self, chunk_idx: u32, block_idx: u32) -> Vec<u32> {
+        let mut chunks = vec![chunk_idx];
+
+        let [x, y, z] = self.get_block_world_pos(chunk_idx, block_idx);
         let local_x = x % self.chunk_size;
         let local_y = y % self.chunk_size;
         let local_z = z % self.chunk_size;
@@ -171,32 +759,34 @@ impl Terrain {
         if local_x == 0 && x > 0 {
             // update chunk left
             let left_chunk_idx = self.shape.linearize([chunk_x - 1, chunk_y, chunk_z]);
-            self.set_chunk_dirty(left_chunk_idx, true);
+            chunks.push(left_chunk_idx);
         } else if local_x == self.chunk_size - 1 && x < self.world_size_x() - 1 {
             // update chunk right
             let right_chunk_idx = self.shape.linearize([chunk_x + 1, chunk_y, chunk_z]);
-            self.set_chunk_dirty(right_chunk_idx, true);
+            chunks.push(right_chunk_idx);
         }
 
         if local_y == 0 && y > 0 {
             // update chunk below
             let below_chunk_idx = self.shape.linearize([chunk_x, chunk_y - 1, chunk_z]);
-            self.set_chunk_dirty(below_chunk_idx, true);
+            chunks.push(below_chunk_idx);
         } else if local_y == self.chunk_size - 1 && y < self.world_size_y() - 1 {
             // update chunk above
             let above_chunk_idx = self.shape.linearize([chunk_x, chunk_y + 1, chunk_z]);
-            self.set_chunk_dirty(above_chunk_idx, true);
+            chunks.push(above_chunk_idx);
         }
 
         if local_z == 0 && z > 0 {
             // update chunk forward
             let forward_chunk_idx = self.shape.linearize([chunk_x, chunk_y, chunk_z - 1]);
-            self.set_chunk_dirty(forward_chunk_idx, true);
+            chunks.push(forward_chunk_idx);
         } else if local_z == self.chunk_size - 1 && z < self.world_size_z() - 1 {
             // update chunk behind
             let behind_chunk_idx = self.shape.linearize([chunk_x, chunk_y, chunk_z + 1]);
-            self.set_chunk_dirty(behind_chunk_idx, true);
+            chunks.push(behind_chunk_idx);
         }
+
+        chunks
     }
 
     pub fn init_block(&mut self, x: u32, y: u32, z: u32, value: BlockType) {
@@ -205,12 +795,175 @@ impl Terrain {
         let chunk = self.get_chunk_mut(chunk_idx).unwrap();
         chunk.set_block_type(block_idx, value);
 
+        self.init_block_state(x, y, z, value);
+    }
+
+    /// Lights, heat, farm plots and torch fuel for a block that was written
+    /// straight into its chunk, as chunk parallel generation does.
+    pub fn init_block_state(&mut self, x: u32, y: u32, z: u32, value: BlockType) {
         if value.is_light() {
             self.add_light(x, y, z, value.get_light_level());
         }
+
+        if value.is_heat_source() {
+            self.update_heat_source(x, y, z, value);
+        }
+
+        if value.is_farm_soil() {
+            self.update_farm_plot(x, y, z, value);
+        }
+
+        if value == BlockType::TORCH {
+            self.update_torch(x, y, z, value);
+        }
+    }
+
+    /// Bare soil starts growing from zero, the growth stages keep their ticks.
+    fn update_farm_plot(&mut self, x: u32, y: u32, z: u32, value: BlockType) {
+        if value == BlockType::FARM_SOIL {
+            self.farm_plots.insert([x, y, z], 0);
+        } else if value.is_farm_soil() {
+            self.farm_plots.entry([x, y, z]).or_insert(0);
+        } else {
+            self.farm_plots.remove(&[x, y, z]);
+        }
+    }
+
+    /// New torches start with a full tank, see `tick_torches`.
+    fn update_torch(&mut self, x: u32, y: u32, z: u32, value: BlockType) {
+        if value == BlockType::TORCH {
+            self.torch_fuel.entry([x, y, z]).or_insert(TORCH_FUEL_S);
+        } else {
+            self.torch_fuel.remove(&[x, y, z]);
+        }
+    }
+
+    fn get_column_idx(&self, x: u32, z: u32) -> usize {
+        (z * self.world_size_x() + x) as usize
+    }
+
+    /// Returns the y of the highest non-empty block in the column, or None if
+    /// the whole column is empty.
+    pub fn get_surface_y(&self, x: u32, z: u32) -> Option<u32> {
+        if x >= self.world_size_x() || z >= self.world_size_z() {
+            return None;
+        }
+
+        match self.surface_cache[self.get_column_idx(x, z)] {
+            SURFACE_UNKNOWN => self.scan_surface_y(x, z),
+            SURFACE_NONE => None,
+            y => Some(y as u32),
+        }
+    }
+
+    /// Returns the y of the highest cell in the column something can stand in
+    /// or climb through, or None if there is no such cell.
+    pub fn get_top_navigable_y(&self, x: u32, z: u32) -> Option<u32> {
+        let surface = self.get_surface_y(x, z)?;
+        // the cell above the surface, or the one above that when climbing
+        let start = (surface + 2).min(self.world_size_y() - 1);
+
+        (0..=start)
+            .rev()
+            .find(|y| get_block_flags(self, x as i32, *y as i32, z as i32) != NavigationFlags::NONE)
+    }
+
+    fn scan_surface_y(&self, x: u32, z: u32) -> Option<u32> {
+        (0..self.world_size_y())
+            .rev()
+            .find(|y| !self.get_block(x, *y, z).is_empty())
+    }
+
+    /// Fix up the cached surface of a column after the block at y changed.
+    /// Only digging out the surface block needs a scan, and only below it.
+    fn update_surface_y(&mut self, x: u32, y: u32, z: u32) {
+        let column_idx = self.get_column_idx(x, z);
+        let cached = self.surface_cache[column_idx];
+
+        if cached == SURFACE_UNKNOWN {
+            return;
+        }
+
+        let is_empty = self.get_block(x, y, z).is_empty();
+
+        if !is_empty && (cached == SURFACE_NONE || y > cached as u32) {
+            self.surface_cache[column_idx] = y as u16;
+        } else if is_empty && y == cached as u32 {
+            self.surface_cache[column_idx] = (0..y)
+                .rev()
+                .find(|y| !self.get_block(x, *y, z).is_empty())
+                .map_or(SURFACE_NONE, |y| y as u16);
+        }
+    }
+
+    pub fn cache_surface_heights(&mut self) {
+        for x in 0..self.world_size_x() {
+            for z in 0..self.world_size_z() {
+                let column_idx = self.get_column_idx(x, z);
+                self.surface_cache[column_idx] = match self.scan_surface_y(x, z) {
+                    Some(y) => y as u16,
+                    None => SURFACE_NONE,
+                };
+            }
+        }
+    }
+
+    fn update_heat_source(&mut self, x: u32, y: u32, z: u32, value: BlockType) {
+        if value.is_heat_source() {
+            self.heat_sources.insert([x, y, z]);
+        } else {
+            self.heat_sources.remove(&[x, y, z]);
+        }
+
+        // every cell next to the source takes the hottest of its own neighbors,
+        // so overlapping sources don't cool each other down when one goes out
+        let cells = BlockFace::ALL
+            .iter()
+            .map(|face| face.offset())
+            .chain([[0, 0, 0]])
+            .map(|[dx, dy, dz]| [x as i32 + dx, y as i32 + dy, z as i32 + dz])
+            .collect::<Vec<_>>();
+
+        for [cx, cy, cz] in cells {
+            if self.is_oob(cx, cy, cz) {
+                continue;
+            }
+
+            let temperature = BlockFace::ALL
+                .iter()
+                .map(|face| {
+                    let [dx, dy, dz] = face.offset();
+                    self.get_block_i32(cx + dx, cy + dy, cz + dz)
+                        .block
+                        .get_heat_level()
+                })
+                .max()
+                .unwrap_or(0);
+
+            self.set_temperature(cx as u32, cy as u32, cz as u32, temperature);
+        }
+    }
+
+    pub fn set_temperature(&mut self, x: u32, y: u32, z: u32, value: u8) {
+        let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
+
+        if let Some(chunk) = self.get_chunk_mut(chunk_idx) {
+            chunk.set_temperature(block_idx, value);
+        }
+    }
+
+    pub fn get_temperature_xyz(&self, x: u32, y: u32, z: u32) -> u8 {
+        let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
+
+        self.get_chunk(chunk_idx)
+            .map_or(0, |chunk| chunk.get_temperature(block_idx))
     }
 
     pub fn get_block(&self, x: u32, y: u32, z: u32) -> Block {
+        if self.is_oob_u32(x, y, z) {
+            return Block::OOB;
+        }
+
         let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
 
         self.get_block_by_idx(chunk_idx, block_idx)
@@ -226,32 +979,40 @@ impl Terrain {
 
     pub fn add_light(&mut self, x: u32, y: u32, z: u32, value: u8) {
         self.set_torchlight(x, y, z, value);
-        self.lights_queue_add.push(LightNode { x, y, z, value });
+        self.lights_queue_add
+            .push_back(LightNode { x, y, z, value });
     }
 
     pub fn remove_light(&mut self, x: u32, y: u32, z: u32) {
         let value = self.get_torchlight_xyz(x, y, z);
         self.set_torchlight(x, y, z, 0);
-        self.lights_queue_remove.push(LightNode { x, y, z, value });
+        self.lights_queue_remove
+            .push_back(LightNode { x, y, z, value });
     }
 
     pub fn add_sunlight(&mut self, x: u32, y: u32, z: u32, value: u8) {
         self.set_sunlight(x, y, z, value);
-        self.sunlight_queue_add.push(LightNode { x, y, z, value });
+        self.sunlight_queue_add
+            .push_back(LightNode { x, y, z, value });
     }
 
     pub fn remove_sunlight(&mut self, x: u32, y: u32, z: u32) {
         let value = self.get_sunlight_xyz(x, y, z);
         self.set_sunlight(x, y, z, 0);
         self.sunlight_queue_remove
-            .push(LightNode { x, y, z, value });
+            .push_back(LightNode { x, y, z, value });
     }
 
     pub fn set_flag_blueprint(&mut self, x: u32, y: u32, z: u32, value: bool) -> bool {
         let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
 
         if let Some(chunk) = self.get_chunk_mut(chunk_idx) {
-            return chunk.set_flag_blueprint(block_idx, value);
+            if chunk.set_flag_blueprint(block_idx, value) {
+                // blueprints are walkable, so navigation changes with them
+                self.mark_dirty_with_neighbors(chunk_idx, block_idx);
+                self.queue_partition_updates(chunk_idx, block_idx);
+                return true;
+            }
         }
 
         false
@@ -259,9 +1020,13 @@ impl Terrain {
 
     pub fn set_flag_mine(&mut self, x: u32, y: u32, z: u32, value: bool) -> bool {
         let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
+        self.snapshot_chunk(chunk_idx);
 
         if let Some(chunk) = self.get_chunk_mut(chunk_idx) {
-            return chunk.set_flag_mine(block_idx, value);
+            if chunk.set_flag_mine(block_idx, value) {
+                self.mark_dirty_with_neighbors(chunk_idx, block_idx);
+                return true;
+            }
         }
 
         false
@@ -271,7 +1036,9 @@ impl Terrain {
         let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
 
         if let Some(chunk) = self.get_chunk_mut(chunk_idx) {
-            chunk.set_sunlight(block_idx, value);
+            if chunk.set_sunlight(block_idx, value) {
+                self.mark_dirty_with_neighbors(chunk_idx, block_idx);
+            }
         }
     }
 
@@ -279,10 +1046,41 @@ impl Terrain {
         let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
 
         if let Some(chunk) = self.get_chunk_mut(chunk_idx) {
-            chunk.set_torchlight(block_idx, value);
+            if chunk.set_torchlight(block_idx, value) {
+                self.mark_dirty_with_neighbors(chunk_idx, block_idx);
+            }
         }
     }
 
+    /// Set the amount of water in a water block. Crossing between shallow
+    /// and deep changes where colonists can wade, so the partitions are
+    /// rebuilt.
+    pub fn set_fluid_level(&mut self, x: u32, y: u32, z: u32, value: u8) -> bool {
+        if self.is_oob_u32(x, y, z) {
+            return false;
+        }
+
+        let [chunk_idx, block_idx] = self.get_block_indexes(x, y, z);
+
+        let Some(chunk) = self.get_chunk_mut(chunk_idx) else {
+            return false;
+        };
+
+        let previous = FluidDepth::from_level(chunk.get_fluid_level(block_idx));
+
+        if !chunk.set_fluid_level(block_idx, value) {
+            return false;
+        }
+
+        self.mark_dirty_with_neighbors(chunk_idx, block_idx);
+
+        if previous != FluidDepth::from_level(value) {
+            self.queue_partition_updates(chunk_idx, block_idx);
+        }
+
+        true
+    }
+
     pub fn get_sunlight(&self, chunk_idx: u32, block_idx: u32) -> u8 {
         if let Some(chunk) = self.get_chunk(chunk_idx) {
             return chunk.get_sunlight(block_idx);
@@ -319,6 +1117,59 @@ impl Terrain {
         self.get_block(x as u32, y as u32, z as u32)
     }
 
+    /// Every block connected to `start` through the six faces that matches
+    /// `predicate`, `start` included. Out of bounds blocks never match. Stops
+    /// after `flood_fill_max_blocks`.
+    pub fn flood_fill_blocks(
+        &self,
+        start: [u32; 3],
+        predicate: impl Fn(Block) -> bool,
+    ) -> Vec<[u32; 3]> {
+        self.flood_fill_steps(start, self.flood_fill_max_blocks, |_, _, block| {
+            predicate(block)
+        })
+    }
+
+    /// Like `flood_fill_blocks`, for fills that care where a block is or
+    /// which block it is reached from. `is_step` is asked about every step
+    /// from a filled block into a face neighbor that isn't filled yet, and
+    /// once for `start` reached from itself. Stops after `max_blocks`.
+    pub fn flood_fill_steps(
+        &self,
+        start: [u32; 3],
+        max_blocks: usize,
+        mut is_step: impl FnMut([u32; 3], [u32; 3], Block) -> bool,
+    ) -> Vec<[u32; 3]> {
+        let mut visited = HashSet::new();
+        let mut blocks = vec![];
+        let [sx, sy, sz] = start;
+
+        flood_fill_from_i32([sx as i32, sy as i32, sz as i32], |from, [x, y, z]| {
+            if blocks.len() >= max_blocks || visited.contains(&[x, y, z]) {
+                return false;
+            }
+
+            let block = self.get_block_i32(x, y, z);
+
+            if block.is_oob() {
+                return false;
+            }
+
+            let from = [from[0] as u32, from[1] as u32, from[2] as u32];
+            let pos = [x as u32, y as u32, z as u32];
+
+            if !is_step(from, pos, block) {
+                return false;
+            }
+
+            visited.insert([x, y, z]);
+            blocks.push(pos);
+            true
+        });
+
+        blocks
+    }
+
     pub fn unset_partition_id(&mut self, chunk_idx: u32, block_idx: u32) {
         if let Some(chunk) = self.get_chunk_mut(chunk_idx) {
             chunk.unset_partition_id(block_idx);
@@ -390,140 +1241,111 @@ impl Terrain {
         ]
     }
 
+    /// Walks the blocks along the ray, DDA style, and returns the first one
+    /// that is drawn. Blocks at or above `slice_y` are cut away and can't be
+    /// hit. A ray starting inside a block hits it right away, on the face
+    /// it would have come in through.
     pub fn raycast(
         &self,
-        origin_x: f32,
-        origin_y: f32,
-        origin_z: f32,
-        direction_x: f32,
-        direction_y: f32,
-        direction_z: f32,
+        origin: Vec3,
+        direction: Vec3,
+        max_dist: f32,
         slice_y: u32,
-        radius: u32,
-    ) -> RayResult {
-        let mut x = (origin_x).floor() as i32;
-        let mut y = (origin_y).floor() as i32;
-        let mut z = (origin_z).floor() as i32;
-
-        let step_x = sig_num(direction_x);
-        let step_y = sig_num(direction_y);
-        let step_z = sig_num(direction_z);
-
-        let mut t_max_x = int_bound(origin_x, direction_x);
-        let mut t_max_y = int_bound(origin_y, direction_y);
-        let mut t_max_z = int_bound(origin_z, direction_z);
-
-        let t_delta_x = step_x as f32 / direction_x;
-        let t_delta_y = step_y as f32 / direction_y;
-        let t_delta_z = step_z as f32 / direction_z;
-
-        let mut face = BlockFace::PosY;
-
-        if direction_x == 0. && direction_y == 0. && direction_z == 0. {
-            return RayResult {
-                is_hit: false,
-                block: Block::OOB,
-                x: 0,
-                y: 0,
-                z: 0,
-                attempts: 0,
-                face,
-            };
-        }
-
-        let r = radius as f32
-            / f32::sqrt(
-                direction_x * direction_x + direction_y * direction_y + direction_z * direction_z,
-            );
+    ) -> Option<RayHit> {
+        let direction = direction.try_normalize()?;
+
+        let mut pos = origin.floor().as_ivec3().to_array();
+        let step = [
+            sig_num(direction.x),
+            sig_num(direction.y),
+            sig_num(direction.z),
+        ];
+        // distance along the ray to the next boundary on each axis, and
+        // between two boundaries. Axes the ray doesn't move along never
+        // reach one.
+        let mut t_max = [
+            int_bound(origin.x, direction.x),
+            int_bound(origin.y, direction.y),
+            int_bound(origin.z, direction.z),
+        ];
+        let t_delta = [
+            1. / direction.x.abs(),
+            1. / direction.y.abs(),
+            1. / direction.z.abs(),
+        ];
 
-        let wx = self.world_size_x() as i32;
-        let wy = self.world_size_y() as i32;
-        let wz = self.world_size_z() as i32;
-        let mut attempts = 0;
-
-        while (if step_x > 0 { x < wx } else { x >= 0 }
-            && if step_y > 0 { y < wy } else { y >= 0 }
-            && if step_z > 0 { z < wz } else { z >= 0 }
-            && attempts < 1000)
-        {
-            attempts += 1;
-            if !(y >= slice_y as i32 || x < 0 || y < 0 || z < 0 || x > wx || y > wy || z > wz) {
-                let b = self.get_block(x as u32, y as u32, z as u32);
-                if b.is_rendered() {
-                    return RayResult {
-                        is_hit: true,
-                        block: b,
-                        x: x as u32,
-                        y: y as u32,
-                        z: z as u32,
-                        attempts,
-                        face,
-                    };
+        // the face of the first block is the one facing back along the
+        // ray's main axis
+        let abs = direction.abs();
+        let mut axis = if abs.x >= abs.y && abs.x >= abs.z {
+            0
+        } else if abs.y >= abs.z {
+            1
+        } else {
+            2
+        };
+        let mut distance = 0.;
+
+        loop {
+            let [x, y, z] = pos;
+
+            if y < slice_y as i32 {
+                let block = self.get_block_i32(x, y, z);
+
+                if !block.is_oob() && block.is_rendered() {
+                    return Some(RayHit {
+                        block: [x as u32, y as u32, z as u32],
+                        face: entry_face(axis, step[axis]),
+                        distance,
+                    });
                 }
             }
 
-            if t_max_x < t_max_y {
-                if t_max_x < t_max_z {
-                    if t_max_x > r {
-                        break;
-                    }
-                    x += step_x;
-                    t_max_x += t_delta_x;
-                    face = if step_x > 0 {
-                        BlockFace::NegX
-                    } else {
-                        BlockFace::PosX
-                    };
+            // once outside the world and heading away from it, nothing
+            // else can be hit
+            let size = [
+                self.world_size_x() as i32,
+                self.world_size_y() as i32,
+                self.world_size_z() as i32,
+            ];
+            let is_leaving =
+                (0..3).any(|i| (step[i] >= 0 && pos[i] >= size[i]) || (step[i] <= 0 && pos[i] < 0));
+
+            if is_leaving {
+                return None;
+            }
+
+            axis = if t_max[0] < t_max[1] {
+                if t_max[0] < t_max[2] {
+                    0
                 } else {
-                    if t_max_x > r {
-                        break;
-                    }
-                    z += step_z;
-                    t_max_z += t_delta_z;
-                    face = if step_z > 0 {
-                        BlockFace::NegZ
-                    } else {
-                        BlockFace::PosZ
-                    };
+                    2
                 }
-            } else if t_max_y < t_max_z {
-                if t_max_y > r {
-                    break;
-                }
-                y += step_y;
-                t_max_y += t_delta_y;
-                face = if step_y > 0 {
-                    BlockFace::NegY
-                } else {
-                    BlockFace::PosY
-                };
+            } else if t_max[1] < t_max[2] {
+                1
             } else {
-                if t_max_z > r {
-                    break;
-                }
-                z += step_z;
-                t_max_z += t_delta_z;
-                face = if step_z > 0 {
-                    BlockFace::NegZ
-                } else {
-                    BlockFace::PosZ
-                };
+                2
+            };
+
+            distance = t_max[axis];
+
+            if distance > max_dist {
+                return None;
             }
-        }
 
-        RayResult {
-            is_hit: false,
-            block: Block::OOB,
-            x: 0,
-            y: 0,
-            z: 0,
-            attempts,
-            face: BlockFace::PosY,
+            pos[axis] += step[axis];
+            t_max[axis] += t_delta[axis];
         }
     }
 }
 
+/// Distance along a ray from `s` moving `ds` per unit to the next whole
+/// number. Never for a ray that doesn't move, -0 included.
 fn int_bound(s: f32, ds: f32) -> f32 {
+    if ds == 0. {
+        return f32::INFINITY;
+    }
+
     if ds < 0. {
         return int_bound(-s, -ds);
     }
@@ -532,3 +1354,570 @@ fn int_bound(s: f32, ds: f32) -> f32 {
 
     (1. - m) / ds
 }
+
+/// The face of a block a ray moving `step` along `axis` enters through
+fn entry_face(axis: usize, step: i32) -> BlockFace {
+    match (axis, step > 0) {
+        (0, true) => BlockFace::NegX,
+        (0, false) => BlockFace::PosX,
+        (1, true) => BlockFace::NegY,
+        (1, false) => BlockFace::PosY,
+        (2, true) => BlockFace::NegZ,
+        _ => BlockFace::PosZ,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::colonists::{get_block_flags, NavigationFlags};
+
+    use super::*;
+
+    /// 2x2x2 chunks of 4 blocks, with no chunk dirty
+    fn clean_terrain() -> Terrain {
+        let mut terrain = Terrain::new(2, 2, 2, 4).unwrap();
+
+        for chunk_idx in 0..terrain.chunk_count {
+            terrain.set_chunk_dirty(chunk_idx, false);
+        }
+
+        terrain
+    }
+
+    fn touching_chunks(terrain: &Terrain, pos: [u32; 3]) -> Vec<[u32; 3]> {
+        let [chunk_idx, block_idx] = terrain.get_block_indexes(pos[0], pos[1], pos[2]);
+
+        terrain
+            .get_touching_chunks(chunk_idx, block_idx)
+            .into_iter()
+            .map(|idx| terrain.shape.delinearize(idx))
+            .sorted()
+            .collect()
+    }
+
+    fn dirty_chunks(terrain: &Terrain) -> Vec<[u32; 3]> {
+        (0..terrain.chunk_count)
+            .filter(|idx| terrain.get_chunk_dirty(*idx))
+            .map(|idx| terrain.shape.delinearize(idx))
+            .sorted()
+            .collect()
+    }
+
+    #[test]
+    fn interior_block_touches_its_own_chunk() {
+        let terrain = clean_terrain();
+
+        assert_eq!(touching_chunks(&terrain, [1, 2, 1]), vec![[0, 0, 0]]);
+        // the outer edge of the world has no chunk beyond it
+        assert_eq!(touching_chunks(&terrain, [0, 0, 0]), vec![[0, 0, 0]]);
+        assert_eq!(touching_chunks(&terrain, [7, 7, 7]), vec![[1, 1, 1]]);
+    }
+
+    #[test]
+    fn face_block_touches_one_neighbor() {
+        let terrain = clean_terrain();
+
+        assert_eq!(
+            touching_chunks(&terrain, [3, 1, 2]),
+            vec![[0, 0, 0], [1, 0, 0]]
+        );
+        assert_eq!(
+            touching_chunks(&terrain, [5, 4, 6]),
+            vec![[1, 0, 1], [1, 1, 1]]
+        );
+    }
+
+    #[test]
+    fn edge_block_touches_two_neighbors() {
+        let terrain = clean_terrain();
+
+        assert_eq!(
+            touching_chunks(&terrain, [3, 3, 1]),
+            vec![[0, 0, 0], [0, 1, 0], [1, 0, 0]]
+        );
+    }
+
+    #[test]
+    fn corner_block_touches_three_neighbors() {
+        let mut terrain = clean_terrain();
+
+        assert_eq!(
+            touching_chunks(&terrain, [4, 4, 3]),
+            vec![[0, 1, 0], [1, 0, 0], [1, 1, 0], [1, 1, 1]]
+        );
+
+        terrain.set_block(3, 3, 3, BlockType::STONE);
+
+        assert_eq!(
+            dirty_chunks(&terrain),
+            vec![[0, 0, 0], [0, 0, 1], [0, 1, 0], [1, 0, 0]]
+        );
+    }
+
+    /// `cargo test region_edit_bench -- --ignored --nocapture`
+    ///
+    /// Fills a 32 block cube across 27 chunks of 16, one `set_block` at a
+    /// time and with one `fill_region`. Counts the dirty flag and partition
+    /// queue updates each way, and times both.
+    #[test]
+    #[ignore]
+    fn region_edit_bench() {
+        let fresh = || {
+            let mut terrain = Terrain::new(3, 3, 3, 16).unwrap();
+
+            for chunk_idx in 0..terrain.chunk_count {
+                terrain.set_chunk_dirty(chunk_idx, false);
+            }
+            terrain.take_partition_updates();
+
+            terrain
+        };
+        let (min, max) = ([8, 8, 8], [39, 39, 39]);
+
+        let mut per_block = fresh();
+        let mut per_block_updates = 0;
+        let start = Instant::now();
+        for x in min[0]..=max[0] {
+            for y in min[1]..=max[1] {
+                for z in min[2]..=max[2] {
+                    let [chunk_idx, block_idx] = per_block.get_block_indexes(x, y, z);
+                    per_block_updates += per_block.get_touching_chunks(chunk_idx, block_idx).len();
+                    per_block.set_block(x, y, z, BlockType::STONE);
+                }
+            }
+        }
+        let per_block_time = start.elapsed();
+
+        let mut region = fresh();
+        let start = Instant::now();
+        region.fill_region(min, max, BlockType::STONE);
+        let region_time = start.elapsed();
+        let region_updates = dirty_chunks(&region).len();
+
+        println!(
+            "set_block: {} chunk updates in {:?}, fill_region: {} in {:?}",
+            per_block_updates, per_block_time, region_updates, region_time
+        );
+
+        // the same chunks end up dirty and queued, each touched chunk once
+        assert_eq!(region_updates, 27);
+        assert_eq!(dirty_chunks(&region), dirty_chunks(&per_block));
+        let queued = |terrain: &mut Terrain| {
+            terrain
+                .take_partition_updates()
+                .into_iter()
+                .map(|update| update.chunk_idx)
+                .sorted()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(queued(&mut region), queued(&mut per_block));
+        // every block once, plus once more for each of the 4 chunk boundary
+        // planes per axis the cube crosses
+        assert_eq!(per_block_updates, 32 * 32 * 32 + 3 * 4 * 32 * 32);
+    }
+
+    #[test]
+    fn empty_column_has_no_surface() {
+        let mut terrain = clean_terrain();
+        terrain.set_block(2, 5, 2, BlockType::STONE);
+
+        assert_eq!(terrain.get_surface_y(1, 1), None);
+        assert_eq!(terrain.get_surface_y(2, 2), Some(5));
+
+        terrain.cache_surface_heights();
+
+        assert_eq!(terrain.get_surface_y(1, 1), None);
+        assert_eq!(terrain.get_surface_y(2, 2), Some(5));
+        // outside the world
+        assert_eq!(terrain.get_surface_y(8, 0), None);
+    }
+
+    fn scan_column(terrain: &Terrain, x: u32, z: u32) -> Option<u32> {
+        (0..terrain.world_size_y())
+            .rev()
+            .find(|y| terrain.get_block(x, *y, z).block != BlockType::EMPTY)
+    }
+
+    fn assert_surface_matches_scan(terrain: &Terrain) {
+        for x in 0..terrain.world_size_x() {
+            for z in 0..terrain.world_size_z() {
+                assert_eq!(
+                    terrain.get_surface_y(x, z),
+                    scan_column(terrain, x, z),
+                    "column {} {}",
+                    x,
+                    z
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn surface_cache_follows_block_changes() {
+        // 16x8x16 blocks
+        let mut terrain = Terrain::new(4, 2, 4, 4).unwrap();
+
+        for x in 0..16 {
+            for z in 0..16 {
+                let height = (x * 3 + z * 5) % 9;
+
+                for y in 0..height {
+                    terrain.set_block(x, y, z, BlockType::STONE);
+                }
+            }
+        }
+
+        terrain.cache_surface_heights();
+        assert_surface_matches_scan(&terrain);
+
+        for i in 0..200u32 {
+            let x = (i * 7) % 16;
+            let z = (i * 11) % 16;
+            let y = (i * 5) % 8;
+            let value = if i.is_multiple_of(3) {
+                BlockType::EMPTY
+            } else {
+                BlockType::DIRT
+            };
+
+            terrain.set_block(x, y, z, value);
+
+            // digging out the surface of a column
+            if let Some(surface) = scan_column(&terrain, z, x) {
+                terrain.set_block(z, surface, x, BlockType::EMPTY);
+            }
+        }
+
+        assert_surface_matches_scan(&terrain);
+    }
+
+    #[test]
+    fn removing_the_surface_reveals_the_block_below() {
+        let mut terrain = clean_terrain();
+        terrain.set_block(3, 1, 3, BlockType::STONE);
+        terrain.set_block(3, 6, 3, BlockType::STONE);
+        terrain.cache_surface_heights();
+
+        assert_eq!(terrain.get_surface_y(3, 3), Some(6));
+        assert_eq!(terrain.get_top_navigable_y(3, 3), Some(7));
+
+        terrain.set_block(3, 6, 3, BlockType::EMPTY);
+        assert_eq!(terrain.get_surface_y(3, 3), Some(1));
+        assert_eq!(terrain.get_top_navigable_y(3, 3), Some(2));
+
+        terrain.set_block(3, 1, 3, BlockType::EMPTY);
+        assert_eq!(terrain.get_surface_y(3, 3), None);
+        assert_eq!(terrain.get_top_navigable_y(3, 3), None);
+    }
+
+    /// Solid stone with a 3x3x3 room carved out at 2..5, and a one block
+    /// shaft up to the sky from its corner, climbed by a ladder in that
+    /// corner
+    fn room_with_shaft() -> Terrain {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+
+        for x in 0..8 {
+            for y in 0..8 {
+                for z in 0..8 {
+                    let is_room = (2..5).contains(&x) && (2..5).contains(&y) && (2..5).contains(&z);
+                    let is_shaft = x == 2 && z == 2 && y >= 5;
+
+                    if !is_room && !is_shaft {
+                        terrain.set_block(x, y, z, BlockType::STONE);
+                    }
+                }
+            }
+        }
+
+        terrain.set_block(2, 4, 2, BlockType::LADDER);
+        terrain
+    }
+
+    #[test]
+    fn flood_fill_finds_the_room_behind_a_ladder() {
+        let terrain = room_with_shaft();
+        let room = terrain.flood_fill_blocks([3, 3, 3], |block| block.is_empty());
+
+        assert_eq!(room.len(), 26);
+        assert!(room
+            .iter()
+            .all(|[x, y, z]| (2..5).contains(x) && (2..5).contains(y) && (2..5).contains(z)));
+
+        // the ladder joins the shaft to the room
+        let open = terrain.flood_fill_blocks([2, 7, 2], |block| {
+            block.is_empty() || block.block == BlockType::LADDER
+        });
+        assert_eq!(open.len(), 30);
+    }
+
+    #[test]
+    fn flood_fill_stops_at_the_limit() {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.flood_fill_max_blocks = 100;
+
+        let open = terrain.flood_fill_blocks([0, 0, 0], |block| block.is_empty());
+        assert_eq!(open.len(), 100);
+
+        let stone = terrain.flood_fill_blocks([0, 0, 0], |block| block.block == BlockType::STONE);
+        assert!(stone.is_empty());
+    }
+
+    #[test]
+    fn impossible_shapes_are_refused() {
+        assert!(matches!(
+            Terrain::new(1, 1, 1, 6),
+            Err(WorldGenConfigError::ChunkSize(6))
+        ));
+        assert!(matches!(
+            Terrain::new(0, 1, 1, 8),
+            Err(WorldGenConfigError::ChunkCount([0, 1, 1]))
+        ));
+        assert!(matches!(
+            Terrain::new(4096, 4096, 4096, 64),
+            Err(WorldGenConfigError::WorldTooLarge { .. })
+        ));
+    }
+
+    /// An empty 8x8x8 world with a single stone block at 3,3,3
+    fn single_block() -> Terrain {
+        let mut terrain = Terrain::new(1, 1, 1, 8).unwrap();
+        terrain.set_block(3, 3, 3, BlockType::STONE);
+        terrain
+    }
+
+    #[test]
+    fn rays_hit_every_face() {
+        let terrain = single_block();
+        let center = Vec3::new(3.5, 3.5, 3.5);
+
+        for face in BlockFace::ALL {
+            let [dx, dy, dz] = face.offset();
+            let normal = Vec3::new(dx as f32, dy as f32, dz as f32);
+            let origin = center + normal * 3.;
+
+            let hit = terrain.raycast(origin, -normal, 10., 8);
+            assert_eq!(
+                hit,
+                Some(RayHit {
+                    block: [3, 3, 3],
+                    face,
+                    distance: 2.5,
+                }),
+                "{:?}",
+                face
+            );
+        }
+    }
+
+    #[test]
+    fn rays_along_z_reach_as_far_as_asked() {
+        let terrain = single_block();
+        let origin = Vec3::new(3.5, 3.5, 0.5);
+
+        let hit = terrain.raycast(origin, Vec3::Z, 10., 8).unwrap();
+        assert_eq!(hit.block, [3, 3, 3]);
+        assert_eq!(hit.face, BlockFace::NegZ);
+
+        assert_eq!(terrain.raycast(origin, Vec3::Z, 2., 8), None);
+        assert_eq!(terrain.raycast(origin, -Vec3::Z, 10., 8), None);
+    }
+
+    #[test]
+    fn grazing_rays_slip_past_edges() {
+        let terrain = single_block();
+
+        // along the top edge of the block, the ray is in the row above it
+        assert_eq!(
+            terrain.raycast(Vec3::new(0.5, 4., 4.), Vec3::X, 10., 8),
+            None
+        );
+
+        // along the bottom edge, the ray is in the block's own row
+        let hit = terrain
+            .raycast(Vec3::new(0.5, 3., 3.), Vec3::X, 10., 8)
+            .unwrap();
+        assert_eq!(hit.block, [3, 3, 3]);
+        assert_eq!(hit.face, BlockFace::NegX);
+        assert_eq!(hit.distance, 2.5);
+
+        // exactly through the corner shared with the block
+        let hit = terrain
+            .raycast(Vec3::new(1., 1., 3.5), Vec3::new(1., 1., 0.), 10., 8)
+            .unwrap();
+        assert_eq!(hit.block, [3, 3, 3]);
+    }
+
+    #[test]
+    fn rays_starting_inside_a_block_hit_it() {
+        let terrain = single_block();
+
+        let hit = terrain
+            .raycast(Vec3::new(3.5, 3.2, 3.5), Vec3::new(0.2, -1., 0.), 10., 8)
+            .unwrap();
+        assert_eq!(
+            hit,
+            RayHit {
+                block: [3, 3, 3],
+                face: BlockFace::PosY,
+                distance: 0.,
+            }
+        );
+    }
+
+    #[test]
+    fn rays_pass_through_the_cut_away_slice() {
+        let mut terrain = single_block();
+        terrain.set_block(3, 1, 3, BlockType::STONE);
+        let origin = Vec3::new(3.5, 7.5, 3.5);
+
+        let hit = terrain.raycast(origin, -Vec3::Y, 10., 8).unwrap();
+        assert_eq!(hit.block, [3, 3, 3]);
+
+        let hit = terrain.raycast(origin, -Vec3::Y, 10., 3).unwrap();
+        assert_eq!(hit.block, [3, 1, 3]);
+        assert_eq!(hit.face, BlockFace::PosY);
+        assert_eq!(hit.distance, 5.5);
+
+        // from above the world, the way the camera looks at it
+        let hit = terrain
+            .raycast(Vec3::new(3.5, 20., 3.5), -Vec3::Y, 30., 8)
+            .unwrap();
+        assert_eq!(hit.block, [3, 3, 3]);
+    }
+
+    /// Every in-bounds cell one step in from a face, paired with the cell one
+    /// step past it, for all six faces of an 8 block world
+    fn face_steps() -> Vec<([i32; 3], [i32; 3])> {
+        let mut steps = vec![];
+
+        for axis in 0..3 {
+            for (inside, outside) in [(0, -1), (7, 8)] {
+                let mut a = [3, 4, 5];
+                let mut b = a;
+                a[axis] = inside;
+                b[axis] = outside;
+                steps.push((a, b));
+            }
+        }
+
+        steps
+    }
+
+    #[test]
+    fn bounds_on_every_face() {
+        let mut terrain = clean_terrain();
+
+        for ([ax, ay, az], [bx, by, bz]) in face_steps() {
+            terrain.set_block(ax as u32, ay as u32, az as u32, BlockType::STONE);
+
+            assert!(!terrain.is_oob(ax, ay, az));
+            assert!(terrain.is_oob(bx, by, bz));
+            assert_eq!(terrain.get_block_i32(ax, ay, az).block, BlockType::STONE);
+            assert!(terrain.get_block_i32(bx, by, bz).is_oob());
+
+            // past the far faces, u32 lookups must not wrap into another chunk
+            if bx >= 0 && by >= 0 && bz >= 0 {
+                assert!(terrain.is_oob_u32(bx as u32, by as u32, bz as u32));
+                assert!(terrain.get_block(bx as u32, by as u32, bz as u32).is_oob());
+            }
+        }
+    }
+
+    #[test]
+    fn bounds_at_every_corner() {
+        let mut terrain = clean_terrain();
+
+        for corner in (0..8).map(|i| [(i & 1) * 7, ((i >> 1) & 1) * 7, ((i >> 2) & 1) * 7]) {
+            let [x, y, z] = corner;
+            terrain.set_block(x as u32, y as u32, z as u32, BlockType::DIRT);
+
+            assert!(!terrain.is_oob(x, y, z));
+            assert_eq!(terrain.get_block_i32(x, y, z).block, BlockType::DIRT);
+
+            // one step out along any axis, or along all of them at once
+            let out = corner.map(|c| if c == 0 { -1 } else { 8 });
+
+            for axis in 0..3 {
+                let mut p = corner;
+                p[axis] = out[axis];
+                assert!(terrain.is_oob(p[0], p[1], p[2]));
+                assert!(terrain.get_block_i32(p[0], p[1], p[2]).is_oob());
+            }
+
+            assert!(terrain.is_oob(out[0], out[1], out[2]));
+            assert!(terrain.get_block_i32(out[0], out[1], out[2]).is_oob());
+        }
+    }
+
+    #[test]
+    fn block_flags_at_the_bounds() {
+        let mut terrain = clean_terrain();
+
+        // nothing under the bottom layer to stand on
+        assert_eq!(get_block_flags(&terrain, 2, 0, 2), NavigationFlags::NONE);
+
+        // a ladder on the bottom layer, and the cell above it, climb fine
+        // with out of bounds below
+        terrain.set_block(1, 0, 1, BlockType::LADDER);
+        assert_eq!(get_block_flags(&terrain, 1, 0, 1), NavigationFlags::LADDER);
+        assert_eq!(get_block_flags(&terrain, 1, 1, 1), NavigationFlags::LADDER);
+
+        // standing in a corner, the out of bounds neighbors are no hazard
+        terrain.set_block(0, 0, 0, BlockType::STONE);
+        assert_eq!(
+            get_block_flags(&terrain, 0, 1, 0),
+            NavigationFlags::SOLID_GROUND | NavigationFlags::TALL
+        );
+        terrain.set_block(7, 0, 7, BlockType::STONE);
+        assert_eq!(
+            get_block_flags(&terrain, 7, 1, 7),
+            NavigationFlags::SOLID_GROUND | NavigationFlags::TALL
+        );
+
+        // the top layer has no headroom
+        terrain.set_block(4, 6, 4, BlockType::STONE);
+        assert_eq!(
+            get_block_flags(&terrain, 4, 7, 4),
+            NavigationFlags::SOLID_GROUND
+        );
+
+        // out of bounds cells themselves are never walked
+        for (_, [x, y, z]) in face_steps() {
+            assert_eq!(get_block_flags(&terrain, x, y, z), NavigationFlags::NONE);
+        }
+    }
+
+    #[test]
+    fn world_aabb_spans_every_block() {
+        let terrain = Terrain::new(2, 1, 3, 4).unwrap();
+
+        assert_eq!(terrain.world_aabb(), (Vec3::ZERO, Vec3::new(8., 4., 12.)));
+    }
+
+    #[test]
+    fn contains_world_pos_at_the_edges() {
+        let terrain = Terrain::new(2, 1, 3, 4).unwrap();
+        let (_, max) = terrain.world_aabb();
+        let inside = [1.5, 2.5, 3.5];
+
+        for axis in 0..3 {
+            let contains = |v: f32| {
+                let mut p = inside;
+                p[axis] = v;
+                terrain.contains_world_pos(p[0], p[1], p[2])
+            };
+
+            // the near faces are in, the far faces belong to the next block
+            assert!(contains(0.));
+            assert!(!contains(-0.01));
+            assert!(contains(max[axis] - 0.01));
+            assert!(!contains(max[axis]));
+        }
+
+        assert!(terrain.contains_world_pos(0., 0., 0.));
+        assert!(terrain.contains_world_pos(7.99, 3.99, 11.99));
+        assert!(!terrain.contains_world_pos(8., 4., 12.));
+    }
+}
@@ -1,19 +1,171 @@
-use std::cmp::min;
+use std::{
+    cmp::{min, Reverse},
+    collections::BinaryHeap,
+};
 
-use crate::{common::FractalNoise, BlockType, Terrain};
-use bevy::ecs::system::ResMut;
+use crate::{
+    common::{FractalNoise, Rand},
+    propagate_light, BlockBuffer, BlockFace, BlockType, LightChannel, OreConfig, Terrain,
+    WorldGenConfig, MAX_LIGHT,
+};
+use bevy::{
+    ecs::{
+        event::{Event, EventReader, EventWriter},
+        system::{Res, ResMut},
+    },
+    tasks::{ComputeTaskPool, TaskPool},
+};
+use ndshape::AbstractShape;
 
-pub fn setup_terrain(mut terrain: ResMut<Terrain>) {
-    let seed = 3;
-    let mut height = FractalNoise::new(seed, 0.01, 8);
-    let mut caverns = FractalNoise::new(seed + 1, 0.01, 4);
-    let mut caves = FractalNoise::new(seed + 1, 0.02, 3);
+/// Cave noise just above the carve threshold turns into gravel, lining the
+/// cave walls
+const CAVE_GRAVEL_BAND: f32 = 0.03;
+/// Sealed air pockets smaller than this many blocks don't get an entrance
+const MIN_CAVE_SIZE: usize = 64;
+/// Chance that a step of an entrance tunnel also climbs a block
+const TUNNEL_CLIMB_CHANCE: f32 = 0.6;
+/// Chance that an entrance tunnel turns before taking a step
+const TUNNEL_TURN_CHANCE: f32 = 0.25;
 
-    let top = terrain.world_size_y() - 1;
-    let mountain_height = min(top - 4, 49);
-    let magma_level = 3;
-    let dirt_depth = 3;
-    let cavern_depth = 0.35;
+/// Sent as chunks finish generating, for a loading screen to show
+#[derive(Event, Clone, Copy, Debug)]
+pub struct WorldGenProgress {
+    pub done: u32,
+    pub total: u32,
+}
+
+pub fn setup_terrain(
+    mut terrain: ResMut<Terrain>,
+    config: Res<WorldGenConfig>,
+    mut ev_progress: EventWriter<WorldGenProgress>,
+) {
+    generate_terrain(&mut terrain, &config, |progress| {
+        ev_progress.send(progress);
+    });
+}
+
+/// Generation runs inside a startup system, so by the time anything can
+/// show progress the world is done. Logs the last update until there is a
+/// loading screen to draw it.
+pub fn log_world_gen_progress(mut ev_progress: EventReader<WorldGenProgress>) {
+    if let Some(progress) = ev_progress.read().last() {
+        println!("generated {}/{} chunks", progress.done, progress.total);
+    }
+}
+
+/// The noise shaped part of generation, which only ever looks at the block
+/// being generated. Shared by the chunk tasks, each of which builds its own
+/// noise since sampling needs `&mut`.
+struct ChunkGen<'a> {
+    config: &'a WorldGenConfig,
+    top: u32,
+    mountain_height: u32,
+    world_size_y: u32,
+}
+
+impl<'a> ChunkGen<'a> {
+    fn new(terrain: &Terrain, config: &'a WorldGenConfig) -> Self {
+        let top = terrain.world_size_y() - 1;
+
+        Self {
+            config,
+            top,
+            mountain_height: min(top - 4, config.mountain_height),
+            world_size_y: terrain.world_size_y(),
+        }
+    }
+
+    fn generate(&self, chunk: &mut BlockBuffer) {
+        let config = self.config;
+        let seed = config.seed;
+        let mut height = FractalNoise::with_shape(
+            seed,
+            config.height_frequency,
+            config.height_octaves,
+            config.height_noise,
+        );
+        let mut height_warp = FractalNoise::new(seed + 3, config.height_frequency, 2);
+        let mut caverns =
+            FractalNoise::new(seed + 1, config.cavern_frequency, config.cavern_octaves);
+        let mut cavern_warp = FractalNoise::new(seed + 4, config.cavern_warp_frequency, 2);
+        let mut caves = FractalNoise::new(seed + 1, config.cave_frequency, config.cave_octaves);
+
+        let has_sea = config.sea_level > 0;
+        let c_depth = config.cavern_depth * self.world_size_y as f32;
+
+        for lx in 0..chunk.chunk_size {
+            for lz in 0..chunk.chunk_size {
+                let x = chunk.world_x + lx;
+                let z = chunk.world_z + lz;
+                let x_f32 = x as f32;
+                let z_f32 = z as f32;
+                let (warp_x, warp_z) = height_warp.warp(x_f32, z_f32, config.height_warp);
+                let h = height.get_2d(warp_x, warp_z);
+
+                let surface =
+                    self.top - (((h.clamp(0.1, 0.5)) * (self.mountain_height) as f32) as u32); // 0 to 28
+
+                for ly in 0..chunk.chunk_size {
+                    let y = chunk.world_y + ly;
+                    let block_idx = chunk.shape.linearize([lx, ly, lz]);
+
+                    let value = if y > surface {
+                        // above ground
+                        BlockType::EMPTY
+                    } else if y <= config.magma_level {
+                        BlockType::MAGMA
+                    } else {
+                        // below ground
+                        let y_f32 = y as f32;
+                        let (warp_x, warp_y, warp_z) =
+                            cavern_warp.warp_3d(x_f32, y_f32, z_f32, config.cavern_warp);
+                        let c = caverns.get_3d(warp_x, warp_y, warp_z);
+                        let depth = ((c_depth - (y + 6) as f32) / c_depth).abs();
+
+                        let mut is_cave = false;
+                        let mut is_cave_wall = false;
+
+                        if c > depth {
+                            let cave = caves.get_3d(x_f32, y_f32, z_f32);
+                            is_cave = cave < config.cave_threshold;
+                            is_cave_wall = cave < config.cave_threshold + CAVE_GRAVEL_BAND;
+                        }
+
+                        if is_cave {
+                            BlockType::EMPTY
+                        } else if y == surface {
+                            // the shore is anything under or just above the sea
+                            if has_sea && surface <= config.sea_level + 1 {
+                                BlockType::SAND
+                            } else {
+                                BlockType::GRASS
+                            }
+                        } else if y > surface - config.dirt_depth {
+                            BlockType::DIRT
+                        } else if is_cave_wall {
+                            BlockType::GRAVEL
+                        } else {
+                            BlockType::STONE
+                        }
+                    };
+
+                    chunk.set_block_type(block_idx, value);
+                }
+            }
+        }
+    }
+}
+
+/// Builds the world from the config. Chunks are shaped in parallel on the
+/// compute task pool, then everything that reaches across chunks (ore veins,
+/// sea, lakes, trees, sunlight) runs serially, so a seed always gives the
+/// same world no matter how many threads there are.
+pub fn generate_terrain(
+    terrain: &mut Terrain,
+    config: &WorldGenConfig,
+    mut on_progress: impl FnMut(WorldGenProgress),
+) {
+    terrain.seed = config.seed;
 
     for chunk_idx in 0..terrain.chunk_count {
         terrain.init_chunk(chunk_idx);
@@ -21,56 +173,981 @@ pub fn setup_terrain(mut terrain: ResMut<Terrain>) {
 
     println!("generating world..");
 
+    let chunk_gen = ChunkGen::new(terrain, config);
+    let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let batch_size = pool.thread_num().max(1) * 4;
+    let total = terrain.chunk_count;
+    let mut done = 0;
+
+    on_progress(WorldGenProgress { done, total });
+
+    for batch in terrain.chunks.chunks_mut(batch_size) {
+        pool.scope(|s| {
+            for chunk in batch.iter_mut().flatten() {
+                let chunk_gen = &chunk_gen;
+                s.spawn(async move { chunk_gen.generate(chunk) });
+            }
+        });
+
+        done += batch.len() as u32;
+        on_progress(WorldGenProgress { done, total });
+    }
+
+    finish_terrain(terrain, config);
+}
+
+/// The serial passes after every chunk has been shaped.
+fn finish_terrain(terrain: &mut Terrain, config: &WorldGenConfig) {
+    // magma lights and heats its surroundings, the same as if each block had
+    // gone through `init_block`
     for x in 0..terrain.world_size_x() {
         for y in 0..terrain.world_size_y() {
             for z in 0..terrain.world_size_z() {
-                let x_f32 = x as f32;
-                let y_f32 = y as f32;
-                let z_f32 = z as f32;
-                let h = height.get_2d(x_f32, z_f32);
+                let value = terrain.get_block(x, y, z).block;
+                terrain.init_block_state(x, y, z, value);
+            }
+        }
+    }
 
-                let surface = top - (((h.clamp(0.1, 0.5)) * (mountain_height) as f32) as u32); // 0 to 28
+    let mut rand = Rand::seed(config.seed as u64);
 
-                // above ground
-                if y > surface {
-                    terrain.init_block(x, y, z, BlockType::EMPTY);
-                    if y == surface + 1 {
-                        terrain.add_sunlight(x, y, z, 15);
-                    } else {
-                        terrain.set_sunlight(x, y, z, 15);
-                    }
+    for ore in config.ores() {
+        place_ore_veins(terrain, ore, &mut rand);
+    }
+
+    terrain.cache_surface_heights();
+    carve_cave_entrances(terrain, config.cave_entrances, &mut rand);
+    terrain.cache_surface_heights();
+    fill_sea(terrain, config.sea_level);
+    terrain.cache_surface_heights();
+    fill_lakes(terrain, config.min_lake_size);
+    terrain.cache_surface_heights();
+    plant_trees(terrain, config);
+    terrain.cache_surface_heights();
+    init_sunlight(terrain);
+
+    println!(
+        "..done generating world (checksum {:016x})",
+        terrain.checksum()
+    );
+}
+
+/// Random walks through stone, each one turning the stone it crosses into ore.
+/// Steps that would leave the depth band or the world are skipped.
+fn place_ore_veins(terrain: &mut Terrain, ore: &OreConfig, rand: &mut Rand) {
+    let height = terrain.world_size_y() as f32;
+    let min_y = (ore.min_height * height) as i32;
+    let max_y = ((ore.max_height * height) as i32).min(terrain.world_size_y() as i32 - 1);
+    let vein_count = (ore.veins_per_chunk * terrain.chunk_count as f32).round() as u32;
+
+    if max_y < min_y {
+        return;
+    }
+
+    for _ in 0..vein_count {
+        let mut pos = [
+            rand.range_n(0, terrain.world_size_x() as i32),
+            rand.range_n(min_y, max_y + 1),
+            rand.range_n(0, terrain.world_size_z() as i32),
+        ];
+
+        for _ in 0..ore.vein_length {
+            let [x, y, z] = pos;
+
+            if terrain.get_block_i32(x, y, z).block == BlockType::STONE {
+                terrain.init_block(x as u32, y as u32, z as u32, ore.block);
+            }
+
+            let [dx, dy, dz] = rand.pick(&BlockFace::ALL).offset();
+            let next = [x + dx, y + dy, z + dz];
+
+            if (min_y..=max_y).contains(&next[1])
+                && !terrain.get_block_i32(next[0], next[1], next[2]).is_oob()
+            {
+                pos = next;
+            }
+        }
+    }
+}
+
+/// Digs a winding tunnel up to the surface from each of the largest cave
+/// systems that have no opening, so they can be reached without digging
+/// blind. Tunnels start from the cave block closest to the surface.
+fn carve_cave_entrances(terrain: &mut Terrain, max_entrances: u32, rand: &mut Rand) {
+    if max_entrances == 0 {
+        return;
+    }
+
+    for start in find_sealed_caves(terrain)
+        .into_iter()
+        .take(max_entrances as usize)
+    {
+        carve_tunnel(terrain, start, rand);
+    }
+}
+
+/// Below the surface of its column, and empty
+fn is_cave_air(terrain: &Terrain, [x, y, z]: [i32; 3]) -> bool {
+    terrain.get_block_i32(x, y, z).block == BlockType::EMPTY
+        && terrain
+            .get_surface_y(x as u32, z as u32)
+            .is_some_and(|surface| (y as u32) < surface)
+}
+
+/// Flood fills the air under the surface into cave systems, and returns the
+/// floor block closest to the surface of each one with no opening to the
+/// sky, largest cave first.
+fn find_sealed_caves(terrain: &Terrain) -> Vec<[i32; 3]> {
+    let size_x = terrain.world_size_x();
+    let size_y = terrain.world_size_y();
+    let size_z = terrain.world_size_z();
+    let block_idx =
+        |[x, y, z]: [i32; 3]| ((y as u32 * size_z + z as u32) * size_x + x as u32) as usize;
+
+    let mut visited = vec![false; (size_x * size_y * size_z) as usize];
+    let mut caves = vec![];
+
+    for x in 0..size_x as i32 {
+        for y in 0..size_y as i32 {
+            for z in 0..size_z as i32 {
+                let pos = [x, y, z];
+
+                if visited[block_idx(pos)] || !is_cave_air(terrain, pos) {
                     continue;
                 }
 
-                if y <= magma_level {
-                    terrain.init_block(x, y, z, BlockType::MAGMA);
-                    continue;
+                visited[block_idx(pos)] = true;
+
+                let mut stack = vec![pos];
+                let mut size = 0;
+                let mut is_open = false;
+                let mut nearest = (u32::MAX, pos);
+
+                while let Some([cx, cy, cz]) = stack.pop() {
+                    size += 1;
+
+                    let depth = terrain
+                        .get_surface_y(cx as u32, cz as u32)
+                        .map_or(0, |surface| surface - cy as u32);
+                    // the tunnel has to start somewhere a colonist can stand
+                    let has_floor = terrain.get_block_i32(cx, cy - 1, cz).is_walkable();
+
+                    if has_floor && depth < nearest.0 {
+                        nearest = (depth, [cx, cy, cz]);
+                    }
+
+                    for face in BlockFace::ALL {
+                        let [dx, dy, dz] = face.offset();
+                        let next = [cx + dx, cy + dy, cz + dz];
+
+                        if terrain.get_block_i32(next[0], next[1], next[2]).is_oob()
+                            || visited[block_idx(next)]
+                        {
+                            continue;
+                        }
+
+                        if is_cave_air(terrain, next) {
+                            visited[block_idx(next)] = true;
+                            stack.push(next);
+                        } else if terrain.get_block_i32(next[0], next[1], next[2]).block
+                            == BlockType::EMPTY
+                        {
+                            // air above the surface, the cave already opens up
+                            is_open = true;
+                        }
+                    }
                 }
 
-                // below ground
-                let c = caverns.get_3d(x_f32, y_f32, z_f32);
+                if !is_open && size >= MIN_CAVE_SIZE && nearest.0 != u32::MAX {
+                    caves.push((size, nearest.1));
+                }
+            }
+        }
+    }
+
+    // stable, so caves of the same size keep their scan order
+    caves.sort_by_key(|(size, _)| Reverse(*size));
+    caves.into_iter().map(|(_, start)| start).collect()
+}
+
+/// A random walk that wanders sideways and climbs most steps, digging out a
+/// two block tall passage until it breaks through the surface. Every climb
+/// moves sideways too, so the tunnel is a walkable staircase.
+fn carve_tunnel(terrain: &mut Terrain, start: [i32; 3], rand: &mut Rand) {
+    const DIRECTIONS: [[i32; 2]; 4] = [[1, 0], [-1, 0], [0, 1], [0, -1]];
+
+    let size_x = terrain.world_size_x() as i32;
+    let size_y = terrain.world_size_y() as i32;
+    let size_z = terrain.world_size_z() as i32;
+    let max_steps = size_y * 4;
+
+    let mut pos = start;
+    let mut dir = rand.pick(&DIRECTIONS);
+
+    for _ in 0..max_steps {
+        let [x, y, z] = pos;
+
+        let is_outside = terrain
+            .get_surface_y(x as u32, z as u32)
+            .is_none_or(|surface| y > surface as i32);
+
+        if is_outside {
+            break;
+        }
+
+        if rand.bool(TUNNEL_TURN_CHANCE) {
+            dir = rand.pick(&DIRECTIONS);
+        }
+
+        let climb = if rand.bool(TUNNEL_CLIMB_CHANCE) { 1 } else { 0 };
+        let next = [x + dir[0], y + climb, z + dir[1]];
+
+        if next[0] < 0 || next[0] >= size_x || next[2] < 0 || next[2] >= size_z {
+            dir = [-dir[0], -dir[1]];
+            continue;
+        }
+
+        if next[1] + 1 >= size_y {
+            break;
+        }
+
+        // never step out over a drop, e.g. back above the hole just dug
+        if !terrain
+            .get_block_i32(next[0], next[1] - 1, next[2])
+            .is_walkable()
+        {
+            dir = rand.pick(&DIRECTIONS);
+            continue;
+        }
+
+        // stepping up needs a block of headroom over the colonist's head
+        for [bx, by, bz] in [
+            [x, y + 1, z],
+            [x, y + 1 + climb, z],
+            next,
+            [next[0], next[1] + 1, next[2]],
+        ] {
+            let block = terrain.get_block_i32(bx, by, bz).block;
+
+            if block.properties().is_filled && block != BlockType::MAGMA {
+                terrain.init_block(bx as u32, by as u32, bz as u32, BlockType::EMPTY);
+            }
+        }
+
+        pos = next;
+    }
+}
+
+/// Flood the open air at or below sea level, starting from the world edge so
+/// sealed caves stay dry.
+fn fill_sea(terrain: &mut Terrain, sea_level: u32) {
+    if sea_level == 0 {
+        return;
+    }
+
+    let max_x = terrain.world_size_x() - 1;
+    let max_z = terrain.world_size_z() - 1;
+    let mut queue = vec![];
 
-                let c_depth = cavern_depth * terrain.world_size_y() as f32;
-                let depth = ((c_depth - (y + 6) as f32) / c_depth).abs();
+    for x in 0..=max_x {
+        for z in 0..=max_z {
+            if x != 0 && x != max_x && z != 0 && z != max_z {
+                continue;
+            }
 
-                if c > depth {
-                    let cave = caves.get_3d(x_f32, y_f32, z_f32);
-                    if cave < 0.5 {
-                        terrain.init_block(x, y, z, BlockType::EMPTY);
+            for y in 0..=sea_level {
+                queue.push([x as i32, y as i32, z as i32]);
+            }
+        }
+    }
+
+    while let Some([x, y, z]) = queue.pop() {
+        if y > sea_level as i32 || terrain.get_block_i32(x, y, z).block != BlockType::EMPTY {
+            continue;
+        }
+
+        terrain.init_block(x as u32, y as u32, z as u32, BlockType::WATER);
+
+        for face in BlockFace::ALL {
+            let [dx, dy, dz] = face.offset();
+            queue.push([x + dx, y + dy, z + dz]);
+        }
+    }
+}
+
+/// Fill closed basins up to the lowest point of their rim. Spill levels come
+/// from a priority flood over the surface heights, inward from the world
+/// edge. Basins smaller than `min_size` columns are left dry.
+fn fill_lakes(terrain: &mut Terrain, min_size: u32) {
+    if min_size == 0 {
+        return;
+    }
+
+    let size_x = terrain.world_size_x();
+    let size_z = terrain.world_size_z();
+    let column_idx = |x: u32, z: u32| (z * size_x + x) as usize;
+    let column_count = (size_x * size_z) as usize;
+
+    let mut surfaces = vec![0; column_count];
+    let mut levels = vec![0; column_count];
+    let mut visited = vec![false; column_count];
+    let mut heap = BinaryHeap::new();
+
+    for x in 0..size_x {
+        for z in 0..size_z {
+            let idx = column_idx(x, z);
+            surfaces[idx] = terrain.get_surface_y(x, z).unwrap_or(0);
+
+            if x == 0 || z == 0 || x == size_x - 1 || z == size_z - 1 {
+                levels[idx] = surfaces[idx];
+                visited[idx] = true;
+                heap.push(Reverse((surfaces[idx], x, z)));
+            }
+        }
+    }
+
+    let neighbors = |x: u32, z: u32| {
+        [[-1, 0], [1, 0], [0, -1], [0, 1]]
+            .into_iter()
+            .map(move |[dx, dz]| [x as i32 + dx, z as i32 + dz])
+            .filter(move |[nx, nz]| {
+                *nx >= 0 && *nz >= 0 && *nx < size_x as i32 && *nz < size_z as i32
+            })
+            .map(|[nx, nz]| [nx as u32, nz as u32])
+    };
+
+    while let Some(Reverse((level, x, z))) = heap.pop() {
+        for [nx, nz] in neighbors(x, z) {
+            let idx = column_idx(nx, nz);
+
+            if visited[idx] {
+                continue;
+            }
+
+            visited[idx] = true;
+            levels[idx] = level.max(surfaces[idx]);
+            heap.push(Reverse((levels[idx], nx, nz)));
+        }
+    }
+
+    // group the flooded columns into basins
+    let mut seen = vec![false; column_count];
+
+    for x in 0..size_x {
+        for z in 0..size_z {
+            let idx = column_idx(x, z);
+
+            if seen[idx] || levels[idx] <= surfaces[idx] {
+                continue;
+            }
+
+            seen[idx] = true;
+            let mut basin = vec![[x, z]];
+            let mut stack = vec![[x, z]];
+
+            while let Some([cx, cz]) = stack.pop() {
+                for [nx, nz] in neighbors(cx, cz) {
+                    let n_idx = column_idx(nx, nz);
+
+                    if seen[n_idx] || levels[n_idx] <= surfaces[n_idx] {
                         continue;
                     }
+
+                    seen[n_idx] = true;
+                    basin.push([nx, nz]);
+                    stack.push([nx, nz]);
                 }
+            }
+
+            if (basin.len() as u32) < min_size {
+                continue;
+            }
 
-                if y == surface {
-                    terrain.init_block(x, y, z, BlockType::GRASS);
-                } else if y > surface - dirt_depth {
-                    terrain.init_block(x, y, z, BlockType::DIRT);
-                } else {
-                    terrain.init_block(x, y, z, BlockType::STONE);
+            for [bx, bz] in basin {
+                let b_idx = column_idx(bx, bz);
+
+                for y in surfaces[b_idx] + 1..=levels[b_idx] {
+                    if terrain.get_block(bx, y, bz).block == BlockType::EMPTY {
+                        terrain.init_block(bx, y, bz, BlockType::WATER);
+                    }
                 }
             }
         }
     }
+}
+
+fn plant_trees(terrain: &mut Terrain, config: &WorldGenConfig) {
+    let canopy_radius = 2_i32;
+    let margin = canopy_radius as u32;
+    let seed = config.seed;
+    let mut forests = FractalNoise::new(seed + 2, config.tree_frequency, 2);
 
-    println!("..done generating world");
+    if terrain.world_size_x() <= 2 * margin || terrain.world_size_z() <= 2 * margin {
+        return;
+    }
+
+    // the canopy never reaches past the world edge
+    for x in margin..terrain.world_size_x() - margin {
+        for z in margin..terrain.world_size_z() - margin {
+            let hash = (x.wrapping_mul(73856093) ^ z.wrapping_mul(19349663))
+                .wrapping_add(seed as u32)
+                .wrapping_mul(2654435761);
+            let roll = (hash >> 16) as f32 / 65536.;
+            // forests are twice as dense as the average, clearings are bare
+            let chance = config.tree_density * 2. * forests.get_2d(x as f32, z as f32);
+
+            if roll >= chance {
+                continue;
+            }
+
+            let Some(surface) = terrain.get_surface_y(x, z) else {
+                continue;
+            };
+
+            if terrain.get_block(x, surface, z).block != BlockType::GRASS {
+                continue;
+            }
+
+            let is_steep =
+                [[x - 1, z], [x + 1, z], [x, z - 1], [x, z + 1]]
+                    .iter()
+                    .any(|[nx, nz]| {
+                        terrain
+                            .get_surface_y(*nx, *nz)
+                            .is_none_or(|y| y.abs_diff(surface) > 1)
+                    });
+
+            if is_steep {
+                continue;
+            }
+
+            let trunk_height = 4 + (hash & 0xff) % 3;
+            let top = surface + trunk_height + canopy_radius as u32;
+
+            if top >= terrain.world_size_y() {
+                continue;
+            }
+
+            let is_clear = (surface + 1..=surface + trunk_height)
+                .all(|y| terrain.get_block(x, y, z).is_empty());
+
+            if !is_clear {
+                continue;
+            }
+
+            for y in surface + 1..=surface + trunk_height {
+                terrain.init_block(x, y, z, BlockType::LOG);
+            }
+
+            let canopy_y = (surface + trunk_height) as i32;
+
+            for dx in -canopy_radius..=canopy_radius {
+                for dy in -1..=canopy_radius {
+                    for dz in -canopy_radius..=canopy_radius {
+                        if dx * dx + dy * dy + dz * dz > canopy_radius * canopy_radius + 1 {
+                            continue;
+                        }
+
+                        let lx = (x as i32 + dx) as u32;
+                        let ly = (canopy_y + dy) as u32;
+                        let lz = (z as i32 + dz) as u32;
+
+                        if terrain.get_block(lx, ly, lz).is_empty() {
+                            terrain.init_block(lx, ly, lz, BlockType::LEAVES);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Light every column from the sky down to the first block that stops or
+/// dims sunlight, then spread it sideways with the same propagation the game
+/// runs, so overhangs, cave mouths and the cells under a canopy get the
+/// falloff they would get if the blocks were placed later.
+fn init_sunlight(terrain: &mut Terrain) {
+    for x in 0..terrain.world_size_x() {
+        for z in 0..terrain.world_size_z() {
+            for y in (0..terrain.world_size_y()).rev() {
+                let block = terrain.get_block(x, y, z);
+
+                if block.is_opaque() || block.block.properties().dims_sunlight {
+                    break;
+                }
+
+                terrain.set_sunlight(x, y, z, MAX_LIGHT);
+            }
+        }
+    }
+
+    // only the edges of the lit columns can spread any further, queueing
+    // every lit cell would flood the queue with nodes that light nothing
+    for x in 0..terrain.world_size_x() {
+        for z in 0..terrain.world_size_z() {
+            for y in (0..terrain.world_size_y()).rev() {
+                if terrain.get_sunlight_xyz(x, y, z) != MAX_LIGHT {
+                    break;
+                }
+
+                if has_unlit_neighbor(terrain, x, y, z) {
+                    terrain.add_sunlight(x, y, z, MAX_LIGHT);
+                }
+            }
+        }
+    }
+
+    while !terrain.sunlight_queue_add.is_empty() || !terrain.sunlight_queue_remove.is_empty() {
+        propagate_light(terrain, LightChannel::Sun);
+    }
+}
+
+/// True if a dark cell sunlight could reach sits beside or below the cell
+fn has_unlit_neighbor(terrain: &Terrain, x: u32, y: u32, z: u32) -> bool {
+    let (x, y, z) = (x as i32, y as i32, z as i32);
+
+    [
+        [x + 1, y, z],
+        [x - 1, y, z],
+        [x, y - 1, z],
+        [x, y, z + 1],
+        [x, y, z - 1],
+    ]
+    .iter()
+    .any(|[nx, ny, nz]| {
+        let block = terrain.get_block_i32(*nx, *ny, *nz);
+
+        !block.is_oob() && !block.is_opaque() && block.sunlight == 0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::{event::Events, system::RunSystemOnce, world::World},
+        utils::HashSet,
+    };
+
+    use crate::colonists::{
+        get_block_flags, is_reachable, partition, NavigationFlags, NavigationGraph, PartitionEvent,
+        PartitionPathRequest,
+    };
+
+    use super::*;
+
+    fn small_config() -> WorldGenConfig {
+        WorldGenConfig {
+            chunk_counts: [4, 2, 4],
+            ..WorldGenConfig::default()
+        }
+    }
+
+    /// FNV-1a of the block types in each y layer, bottom up. Stable across
+    /// platforms and Rust versions, unlike `DefaultHasher`, so the hashes of a
+    /// seed can be written down and compared after worldgen changes to see
+    /// which layers moved.
+    fn layer_hashes(terrain: &Terrain) -> Vec<u64> {
+        const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+
+        (0..terrain.world_size_y())
+            .map(|y| {
+                let mut hash = FNV_OFFSET;
+
+                for z in 0..terrain.world_size_z() {
+                    for x in 0..terrain.world_size_x() {
+                        hash ^= terrain.get_block(x, y, z).block.0 as u64;
+                        hash = hash.wrapping_mul(FNV_PRIME);
+                    }
+                }
+
+                hash
+            })
+            .collect()
+    }
+
+    /// What `generate_terrain` did before chunks were shaped in parallel
+    fn generate_serial(terrain: &mut Terrain, config: &WorldGenConfig) {
+        terrain.seed = config.seed;
+
+        for chunk_idx in 0..terrain.chunk_count {
+            terrain.init_chunk(chunk_idx);
+        }
+
+        let chunk_gen = ChunkGen::new(terrain, config);
+
+        for chunk in terrain.chunks.iter_mut().flatten() {
+            chunk_gen.generate(chunk);
+        }
+
+        finish_terrain(terrain, config);
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let config = small_config();
+
+        let mut serial = config.build_terrain().unwrap();
+        generate_serial(&mut serial, &config);
+
+        let mut progress = vec![];
+        let mut parallel = config.build_terrain().unwrap();
+        generate_terrain(&mut parallel, &config, |p| progress.push(p.done));
+
+        assert_eq!(parallel.checksum(), serial.checksum());
+        assert_eq!(layer_hashes(&parallel), layer_hashes(&serial));
+
+        // starts at nothing, only goes up, and ends with every chunk
+        assert_eq!(progress.first(), Some(&0));
+        assert!(progress.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(progress.last(), Some(&parallel.chunk_count));
+    }
+
+    const SNAPSHOT_SEEDS: [i32; 3] = [1, 2, 3];
+
+    /// One `seed <seed> layer <y> <hash>` line per layer of each snapshot
+    /// seed, so a diff shows which layers a worldgen change moved
+    fn worldgen_snapshot() -> String {
+        SNAPSHOT_SEEDS
+            .iter()
+            .flat_map(|seed| {
+                let terrain = generated(&WorldGenConfig {
+                    seed: *seed,
+                    ..small_config()
+                });
+
+                layer_hashes(&terrain)
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(y, hash)| format!("seed {} layer {} {:016x}\n", seed, y, hash))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn worldgen_matches_snapshot() {
+        let expected = include_str!("snapshots/worldgen.txt");
+        let actual = worldgen_snapshot();
+
+        let moved = expected
+            .lines()
+            .zip(actual.lines())
+            .filter(|(expected, actual)| expected != actual)
+            .map(|(expected, _)| expected.rsplit_once(' ').unwrap().0)
+            .collect::<Vec<_>>();
+
+        assert_eq!(expected.lines().count(), actual.lines().count());
+        assert!(
+            moved.is_empty(),
+            "worldgen output changed, if that was intended run the ignored \
+             regenerate_worldgen_snapshot test. Moved: {:?}",
+            moved
+        );
+    }
+
+    /// Rewrites the snapshot after a deliberate worldgen change, check the
+    /// diff before committing it.
+    /// `cargo test regenerate_worldgen_snapshot -- --ignored`
+    #[test]
+    #[ignore]
+    fn regenerate_worldgen_snapshot() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/terrain/snapshots/worldgen.txt"
+        );
+        std::fs::write(path, worldgen_snapshot()).unwrap();
+    }
+
+    /// The sunlight of one z slice, one row per depth from the top down,
+    /// a hex digit per cell and `#` for opaque blocks
+    fn sunlight_rows(terrain: &Terrain, z: u32) -> String {
+        (0..terrain.world_size_y())
+            .rev()
+            .map(|y| {
+                let row = (0..terrain.world_size_x())
+                    .map(|x| {
+                        if terrain.get_block(x, y, z).is_opaque() {
+                            '#'
+                        } else {
+                            std::char::from_digit(terrain.get_sunlight_xyz(x, y, z) as u32, 16)
+                                .unwrap()
+                        }
+                    })
+                    .collect::<String>();
+
+                format!("y {} {}\n", y, row)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sunlight_reaches_under_an_overhang() {
+        let mut terrain = Terrain::new(2, 1, 2, 8).unwrap();
+
+        // ground, a ledge over the left half and a leaf over the right
+        terrain.fill_region([0, 0, 0], [15, 1, 15], BlockType::STONE);
+        terrain.fill_region([0, 5, 0], [7, 5, 15], BlockType::STONE);
+        terrain.set_block(12, 6, 8, BlockType::LEAVES);
+        init_sunlight(&mut terrain);
+
+        let expected = "\
+y 7 ffffffffffffffff
+y 6 ffffffffffffefff
+y 5 ########ffffefff
+y 4 789abcdeffffefff
+y 3 789abcdeffffefff
+y 2 789abcdeffffefff
+y 1 ################
+y 0 ################
+";
+        assert_eq!(sunlight_rows(&terrain, 8), expected);
+    }
+
+    fn generated(config: &WorldGenConfig) -> Terrain {
+        let mut terrain = config.build_terrain().unwrap();
+        generate_terrain(&mut terrain, config, |_| {});
+        terrain
+    }
+
+    /// Every air block of the cave `start` is in
+    fn cave_blocks(terrain: &Terrain, start: [i32; 3]) -> Vec<[i32; 3]> {
+        let mut cave = HashSet::from([start]);
+        let mut stack = vec![start];
+
+        while let Some([x, y, z]) = stack.pop() {
+            for face in BlockFace::ALL {
+                let [dx, dy, dz] = face.offset();
+                let next = [x + dx, y + dy, z + dz];
+
+                if !terrain.get_block_i32(next[0], next[1], next[2]).is_oob()
+                    && is_cave_air(terrain, next)
+                    && cave.insert(next)
+                {
+                    stack.push(next);
+                }
+            }
+        }
+
+        cave.into_iter().collect()
+    }
+
+    /// Partitions the whole world, then checks whether a colonist standing
+    /// on the surface in the middle of the map can walk to any of `goals`
+    fn reachable_from_surface(terrain: Terrain, goals: &[[i32; 3]]) -> bool {
+        let mut world = World::new();
+        world.insert_resource(terrain);
+        world.init_resource::<NavigationGraph>();
+        world.init_resource::<Events<PartitionEvent>>();
+
+        for chunk_idx in 0..world.resource::<Terrain>().chunk_count {
+            world.send_event(PartitionEvent { chunk_idx });
+        }
+        world.run_system_once(partition);
+
+        let terrain = world.resource::<Terrain>();
+        let graph = world.resource::<NavigationGraph>();
+        let [x, z] = [terrain.world_size_x() / 2, terrain.world_size_z() / 2];
+        let spawn = [x, terrain.get_surface_y(x, z).unwrap() + 1, z];
+
+        let request = PartitionPathRequest {
+            start: spawn,
+            goals: goals
+                .iter()
+                .map(|[x, y, z]| [*x as u32, *y as u32, *z as u32])
+                .filter(|[x, y, z]| {
+                    get_block_flags(terrain, *x as i32, *y as i32, *z as i32)
+                        .intersects(NavigationFlags::COLONIST)
+                })
+                .collect(),
+            flags: NavigationFlags::COLONIST,
+        };
+
+        assert!(!request.goals.is_empty(), "nowhere to stand in the cave");
+        is_reachable(&request, terrain, graph)
+    }
+
+    #[test]
+    fn largest_cave_gets_an_entrance() {
+        let sealed = generated(&WorldGenConfig {
+            cave_entrances: 0,
+            ..small_config()
+        });
+        let caves = find_sealed_caves(&sealed);
+        assert!(!caves.is_empty(), "seed has no sealed cave to test with");
+
+        let largest = cave_blocks(&sealed, caves[0]);
+        assert!(!reachable_from_surface(sealed, &largest));
+
+        let config = small_config();
+        assert!(config.cave_entrances > 0);
+        let terrain = generated(&config);
+        assert!(reachable_from_surface(terrain, &largest));
+    }
+
+    #[test]
+    fn default_world_compacts() {
+        let config = WorldGenConfig::default();
+        let mut terrain = config.build_terrain().unwrap();
+        generate_terrain(&mut terrain, &config, |_| {});
+
+        let checksum = terrain.checksum();
+        let (dense_bytes, _) = terrain.chunk_memory();
+
+        for chunk in terrain.chunks.iter_mut().flatten() {
+            chunk.compact();
+        }
+
+        let (compact_bytes, compact) = terrain.chunk_memory();
+        println!(
+            "default world: {} KiB dense, {} KiB with {}/{} chunks compact",
+            dense_bytes / 1024,
+            compact_bytes / 1024,
+            compact,
+            terrain.chunk_count
+        );
+
+        assert_eq!(terrain.checksum(), checksum);
+        assert!(compact_bytes < dense_bytes);
+    }
+
+    /// The bottom log and height of every tree trunk
+    fn trunks(terrain: &Terrain) -> Vec<([u32; 3], u32)> {
+        let mut trunks = vec![];
+
+        for x in 0..terrain.world_size_x() {
+            for z in 0..terrain.world_size_z() {
+                for (y, block) in terrain.get_column(x, z) {
+                    let is_base = block.block == BlockType::LOG
+                        && (y == 0 || terrain.get_block(x, y - 1, z).block != BlockType::LOG);
+
+                    if is_base {
+                        let height = (y..terrain.world_size_y())
+                            .take_while(|y| terrain.get_block(x, *y, z).block == BlockType::LOG)
+                            .count() as u32;
+                        trunks.push(([x, y, z], height));
+                    }
+                }
+            }
+        }
+
+        trunks
+    }
+
+    #[test]
+    fn seed_grows_known_trees() {
+        let terrain = generated(&small_config());
+        let trunks = trunks(&terrain);
+
+        assert_eq!(trunks.len(), 33);
+        assert_eq!(trunks[0], ([3, 23, 19], 5));
+        assert_eq!(terrain.get_block(3, 22, 19).block, BlockType::GRASS);
+        assert_eq!(terrain.get_block(3, 28, 19).block, BlockType::LEAVES);
+
+        for ([x, y, z], height) in trunks {
+            assert!((4..=6).contains(&height), "trunk at {:?}", [x, y, z]);
+            assert_eq!(terrain.get_block(x, y - 1, z).block, BlockType::GRASS);
+        }
+    }
+
+    #[test]
+    fn ores_stay_in_their_depth_bands() {
+        let config = small_config();
+        let terrain = generated(&config);
+        let height = terrain.world_size_y() as f32;
+
+        let counts = config.ores().map(|ore| {
+            let min_y = (ore.min_height * height) as u32;
+            let max_y = (ore.max_height * height) as u32;
+            let most =
+                (ore.veins_per_chunk * terrain.chunk_count as f32).round() as u32 * ore.vein_length;
+            let mut count = 0;
+
+            for x in 0..terrain.world_size_x() {
+                for z in 0..terrain.world_size_z() {
+                    for (y, block) in terrain.get_column(x, z) {
+                        if block.block == ore.block {
+                            assert!((min_y..=max_y).contains(&y), "ore at {:?}", [x, y, z]);
+                            count += 1;
+                        }
+                    }
+                }
+            }
+
+            assert!(count > 0 && count <= most, "{} ore blocks", count);
+            count
+        });
+
+        // coal, iron, gold, commonest first
+        assert_eq!(counts, [116, 83, 6]);
+    }
+
+    #[test]
+    fn seed_fills_a_known_lake() {
+        let terrain = generated(&WorldGenConfig {
+            seed: 5,
+            ..small_config()
+        });
+
+        let lake_columns = (0..terrain.world_size_x())
+            .flat_map(|x| (0..terrain.world_size_z()).map(move |z| [x, z]))
+            .filter(|[x, z]| {
+                terrain
+                    .get_column(*x, *z)
+                    .any(|(_, block)| block.block == BlockType::WATER)
+            })
+            .count();
+        assert_eq!(lake_columns, 31);
+
+        // filled from the basin floor up, open to the sky above
+        let water = terrain
+            .get_column(23, 24)
+            .filter(|(_, block)| block.block == BlockType::WATER)
+            .map(|(y, _)| y)
+            .collect::<Vec<_>>();
+        let (bottom, top) = (water[0], *water.last().unwrap());
+        assert_eq!(water.len() as u32, top - bottom + 1);
+        assert!(terrain.get_block(23, bottom - 1, 24).is_walkable());
+        assert!(terrain.get_block(23, top + 1, 24).is_empty());
+    }
+
+    #[test]
+    fn sea_leaves_sealed_caves_dry() {
+        let config = WorldGenConfig {
+            seed: 1,
+            cave_entrances: 0,
+            ..small_config()
+        };
+        let dry = generated(&config);
+        let wet = generated(&WorldGenConfig {
+            sea_level: 16,
+            ..config
+        });
+
+        let [max_x, max_z] = [dry.world_size_x() as i32 - 1, dry.world_size_z() as i32 - 1];
+        let inland_caves = find_sealed_caves(&dry)
+            .into_iter()
+            .map(|start| cave_blocks(&dry, start))
+            .filter(|cave| {
+                !cave
+                    .iter()
+                    .any(|[x, _, z]| *x == 0 || *z == 0 || *x == max_x || *z == max_z)
+            })
+            .collect::<Vec<_>>();
+        assert!(
+            !inland_caves.is_empty(),
+            "seed has no inland cave to test with"
+        );
+        assert!(inland_caves.iter().flatten().any(|[_, y, _]| *y <= 16));
+
+        assert!(wet.count_blocks_of_type_global(BlockType::WATER) > 0);
+
+        for [x, y, z] in inland_caves.into_iter().flatten() {
+            assert_eq!(
+                wet.get_block_i32(x, y, z).block,
+                BlockType::EMPTY,
+                "flooded cave at {:?}",
+                [x, y, z]
+            );
+        }
+    }
 }
@@ -0,0 +1,50 @@
+use bevy::{
+    ecs::{
+        event::EventWriter,
+        system::{Local, Res, ResMut},
+    },
+    time::Time,
+};
+
+use crate::{BlockChangedEvent, BlockType, Terrain};
+
+const TORCH_TICK_S: f32 = 1.;
+/// Seconds a freshly placed torch burns for
+pub const TORCH_FUEL_S: u32 = 1000;
+
+/// Burns one second of fuel from every torch, and puts out the ones that run
+/// dry. Removing the block takes its light with it.
+pub fn tick_torches(
+    time: Res<Time>,
+    mut terrain: ResMut<Terrain>,
+    mut timer: Local<f32>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
+) {
+    *timer += time.delta_seconds();
+
+    if *timer < TORCH_TICK_S {
+        return;
+    }
+
+    *timer -= TORCH_TICK_S;
+
+    let mut burnt_out = vec![];
+
+    for (pos, fuel) in terrain.torch_fuel.iter_mut() {
+        *fuel = fuel.saturating_sub(1);
+
+        if *fuel == 0 {
+            burnt_out.push(*pos);
+        }
+    }
+
+    for [x, y, z] in burnt_out {
+        if terrain.get_block(x, y, z).block != BlockType::TORCH {
+            terrain.torch_fuel.remove(&[x, y, z]);
+            continue;
+        }
+
+        let change = terrain.set_block(x, y, z, BlockType::EMPTY);
+        ev_block_changed.send(change.into());
+    }
+}
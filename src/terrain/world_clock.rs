@@ -0,0 +1,127 @@
+use bevy::{
+    asset::Assets,
+    ecs::{
+        change_detection::DetectChanges,
+        system::{Res, ResMut, Resource},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+    render::color::Color,
+    time::Time,
+};
+
+use crate::{ChunkMaterial, ChunkMaterialRes};
+
+const DAWN_START: f32 = 5.;
+const DAWN_END: f32 = 7.;
+const DUSK_START: f32 = 18.;
+const DUSK_END: f32 = 20.;
+const NIGHT_INTENSITY: f32 = 0.15;
+const SCRUB_HOURS_PER_SECOND: f32 = 4.;
+
+const DAY_COLOR: Color = Color::rgb(1., 0.91, 0.56);
+const TWILIGHT_COLOR: Color = Color::rgb(1., 0.6, 0.35);
+const NIGHT_COLOR: Color = Color::rgb(0.35, 0.4, 0.7);
+
+#[derive(Resource)]
+pub struct WorldClock {
+    /// Hour of the day, 0-24
+    pub hour: f32,
+    /// Real seconds it takes for a full day to pass
+    pub day_length_s: f32,
+    pub is_paused: bool,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self {
+            hour: 8.,
+            day_length_s: 600.,
+            is_paused: false,
+        }
+    }
+}
+
+impl WorldClock {
+    pub fn advance_hours(&mut self, hours: f32) {
+        self.hour = (self.hour + hours).rem_euclid(24.);
+    }
+
+    /// How strong sunlight is right now, 0-1
+    pub fn sun_intensity(&self) -> f32 {
+        NIGHT_INTENSITY + (1. - NIGHT_INTENSITY) * self.daylight()
+    }
+
+    pub fn sun_color(&self) -> Color {
+        let daylight = self.daylight();
+
+        // twilight sits halfway between night and day
+        if daylight < 0.5 {
+            lerp_color(NIGHT_COLOR, TWILIGHT_COLOR, daylight * 2.)
+        } else {
+            lerp_color(TWILIGHT_COLOR, DAY_COLOR, (daylight - 0.5) * 2.)
+        }
+    }
+
+    /// 0 at night, 1 during the day, ramping through dawn and dusk.
+    fn daylight(&self) -> f32 {
+        let h = self.hour;
+
+        if !(DAWN_START..DUSK_END).contains(&h) {
+            0.
+        } else if h < DAWN_END {
+            smoothstep((h - DAWN_START) / (DAWN_END - DAWN_START))
+        } else if h < DUSK_START {
+            1.
+        } else {
+            1. - smoothstep((h - DUSK_START) / (DUSK_END - DUSK_START))
+        }
+    }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    let t = t.clamp(0., 1.);
+    t * t * (3. - 2. * t)
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    Color::rgb(
+        a.r() + (b.r() - a.r()) * t,
+        a.g() + (b.g() - a.g()) * t,
+        a.b() + (b.b() - a.b()) * t,
+    )
+}
+
+/// Advances the clock, hold `[` or `]` to scrub time back and forth.
+pub fn tick_world_clock(
+    time: Res<Time>,
+    input_keys: Res<ButtonInput<KeyCode>>,
+    mut clock: ResMut<WorldClock>,
+) {
+    let delta = time.delta_seconds();
+
+    if input_keys.pressed(KeyCode::BracketRight) {
+        clock.advance_hours(SCRUB_HOURS_PER_SECOND * delta);
+    } else if input_keys.pressed(KeyCode::BracketLeft) {
+        clock.advance_hours(-SCRUB_HOURS_PER_SECOND * delta);
+    } else if !clock.is_paused {
+        let hours = 24. * delta / clock.day_length_s;
+        clock.advance_hours(hours);
+    }
+}
+
+pub fn update_sun_uniforms(
+    clock: Res<WorldClock>,
+    chunk_material_res: Res<ChunkMaterialRes>,
+    mut terrain_material: ResMut<Assets<ChunkMaterial>>,
+) {
+    if !clock.is_changed() {
+        return;
+    }
+
+    for handle in chunk_material_res.handles() {
+        if let Some(material) = terrain_material.get_mut(handle.clone()) {
+            material.sun_intensity = clock.sun_intensity();
+            material.sun_color = clock.sun_color();
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::ecs::system::Resource;
+
+use crate::{common::NoiseShape, BlockType, Terrain};
+
+/// Veins of one ore, walked through stone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OreConfig {
+    pub block: BlockType,
+    /// Lowest and highest y a vein can reach, as fractions of world height
+    pub min_height: f32,
+    pub max_height: f32,
+    /// Average number of veins started in each chunk
+    pub veins_per_chunk: f32,
+    /// Steps of the random walk, each step can turn one stone block
+    pub vein_length: u32,
+}
+
+/// Everything that shapes a generated world. Two runs with the same config
+/// produce the same terrain.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct WorldGenConfig {
+    pub seed: i32,
+    /// World size in chunks along x, y, and z
+    pub chunk_counts: [u32; 3],
+    pub chunk_size: u32,
+    pub height_frequency: f32,
+    pub height_octaves: i32,
+    /// Ridged noise gives the mountains crests, fbm rolling hills
+    pub height_noise: NoiseShape,
+    /// How far, in blocks, the height noise is domain warped. 0 turns
+    /// warping off.
+    pub height_warp: f32,
+    pub cavern_frequency: f32,
+    pub cavern_octaves: i32,
+    /// How far, in blocks, the cavern noise is domain warped, bending the
+    /// caverns into winding shapes. 0 turns warping off.
+    pub cavern_warp: f32,
+    pub cavern_warp_frequency: f32,
+    pub cave_frequency: f32,
+    pub cave_octaves: i32,
+    /// Tallest a mountain can rise above the lowest surface
+    pub mountain_height: u32,
+    /// Everything at or below this y is magma
+    pub magma_level: u32,
+    pub dirt_depth: u32,
+    /// Where the caverns are densest, as a fraction of world height
+    pub cavern_depth: f32,
+    /// Cave noise below this value is carved out
+    pub cave_threshold: f32,
+    /// How many of the largest sealed cave systems get a tunnel dug up to
+    /// the surface. 0 leaves caves as the noise made them.
+    pub cave_entrances: u32,
+    /// Open air at or below this y that reaches the world edge is flooded,
+    /// with sand along the shore. 0 leaves the world without a sea.
+    pub sea_level: u32,
+    /// Closed basins covering at least this many columns fill with water up
+    /// to their rim. 0 turns lakes off.
+    pub min_lake_size: u32,
+    /// Average chance of a tree on any grass column, from 0 to 1
+    pub tree_density: f32,
+    /// Scale of the noise that groups trees into forests and clearings
+    pub tree_frequency: f32,
+    pub coal: OreConfig,
+    pub iron: OreConfig,
+    pub gold: OreConfig,
+}
+
+impl Default for WorldGenConfig {
+    fn default() -> Self {
+        Self {
+            seed: 3,
+            chunk_counts: [8, 4, 8],
+            chunk_size: 16,
+            height_frequency: 0.01,
+            height_octaves: 8,
+            height_noise: NoiseShape::Ridged,
+            height_warp: 0.,
+            cavern_frequency: 0.01,
+            cavern_octaves: 4,
+            cavern_warp: 8.,
+            cavern_warp_frequency: 0.02,
+            cave_frequency: 0.02,
+            cave_octaves: 3,
+            mountain_height: 49,
+            magma_level: 3,
+            dirt_depth: 3,
+            cavern_depth: 0.35,
+            cave_threshold: 0.5,
+            cave_entrances: 3,
+            sea_level: 0,
+            min_lake_size: 12,
+            tree_density: 0.01,
+            tree_frequency: 0.03,
+            coal: OreConfig {
+                block: BlockType::COAL,
+                min_height: 0.3,
+                max_height: 0.8,
+                veins_per_chunk: 1.,
+                vein_length: 12,
+            },
+            iron: OreConfig {
+                block: BlockType::IRON,
+                min_height: 0.15,
+                max_height: 0.55,
+                veins_per_chunk: 0.5,
+                vein_length: 8,
+            },
+            gold: OreConfig {
+                block: BlockType::GOLD,
+                min_height: 0.,
+                max_height: 0.3,
+                veins_per_chunk: 0.2,
+                vein_length: 5,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WorldGenConfigError {
+    UnknownArg(String),
+    MissingValue(String),
+    InvalidValue { arg: String, value: String },
+    ChunkSize(u32),
+    ChunkCount([u32; 3]),
+    WorldTooLarge { blocks: u64, max: u64 },
+    WorldTooShort { height: u32, min: u32 },
+    WorldTooTall { height: u32, max: u32 },
+    SeaLevelTooHigh { sea_level: u32, max: u32 },
+    TreeDensity(f32),
+    OreBand { ore: String, min: f32, max: f32 },
+}
+
+impl Display for WorldGenConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownArg(arg) => write!(f, "unknown argument {}", arg),
+            Self::MissingValue(arg) => write!(f, "missing value for {}", arg),
+            Self::InvalidValue { arg, value } => {
+                write!(f, "invalid value {:?} for {}", value, arg)
+            }
+            Self::ChunkSize(size) => write!(
+                f,
+                "chunk size must be a power of two between {} and {}, got {}",
+                MIN_CHUNK_SIZE, MAX_CHUNK_SIZE, size
+            ),
+            Self::ChunkCount(counts) => {
+                write!(
+                    f,
+                    "world must be at least one chunk in each axis, got {:?}",
+                    counts
+                )
+            }
+            Self::WorldTooLarge { blocks, max } => write!(
+                f,
+                "world has {} blocks, at most {} are supported",
+                blocks, max
+            ),
+            Self::WorldTooShort { height, min } => write!(
+                f,
+                "world is {} blocks tall, needs at least {} for the magma, dirt, and sky layers",
+                height, min
+            ),
+            Self::WorldTooTall { height, max } => {
+                write!(
+                    f,
+                    "world is {} blocks tall, at most {} is supported",
+                    height, max
+                )
+            }
+            Self::SeaLevelTooHigh { sea_level, max } => write!(
+                f,
+                "sea level {} is above the world, at most {} is allowed",
+                sea_level, max
+            ),
+            Self::TreeDensity(density) => {
+                write!(f, "tree density must be between 0 and 1, got {}", density)
+            }
+            Self::OreBand { ore, min, max } => write!(
+                f,
+                "{} band must satisfy 0 <= min <= max <= 1, got {} to {}",
+                ore, min, max
+            ),
+        }
+    }
+}
+
+const MIN_CHUNK_SIZE: u32 = 4;
+const MAX_CHUNK_SIZE: u32 = 64;
+/// Surface heights are cached as u16 with two sentinel values on top.
+const MAX_WORLD_HEIGHT: u32 = u16::MAX as u32 - 2;
+/// Block positions and indexes are u32 all over
+const MAX_WORLD_BLOCKS: u64 = u32::MAX as u64;
+
+/// Whether a terrain of this shape can exist at all, regardless of how it
+/// is generated. `Terrain::new` fails with it, so saves go through it as well.
+pub fn validate_world_shape(
+    chunk_counts: [u32; 3],
+    chunk_size: u32,
+) -> Result<(), WorldGenConfigError> {
+    if !chunk_size.is_power_of_two() || !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+        return Err(WorldGenConfigError::ChunkSize(chunk_size));
+    }
+
+    if chunk_counts.contains(&0) {
+        return Err(WorldGenConfigError::ChunkCount(chunk_counts));
+    }
+
+    // counts straight from a save file can overflow even a u64
+    let blocks = chunk_counts
+        .iter()
+        .try_fold(1u64, |blocks, count| {
+            blocks.checked_mul(*count as u64 * chunk_size as u64)
+        })
+        .unwrap_or(u64::MAX);
+
+    if blocks > MAX_WORLD_BLOCKS {
+        return Err(WorldGenConfigError::WorldTooLarge {
+            blocks,
+            max: MAX_WORLD_BLOCKS,
+        });
+    }
+
+    Ok(())
+}
+
+impl WorldGenConfig {
+    /// Reads `--key value` pairs on top of the defaults, e.g.
+    /// `--seed 7 --world-size 8,4,8 --chunk-size 16`.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, WorldGenConfigError> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| WorldGenConfigError::MissingValue(arg.clone()))?;
+            let invalid = || WorldGenConfigError::InvalidValue {
+                arg: arg.clone(),
+                value: value.clone(),
+            };
+
+            match arg.as_str() {
+                "--seed" => config.seed = value.parse().map_err(|_| invalid())?,
+                "--world-size" => {
+                    let counts = value
+                        .split(',')
+                        .map(|v| v.trim().parse::<u32>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| invalid())?;
+                    config.chunk_counts = counts.try_into().map_err(|_| invalid())?;
+                }
+                "--chunk-size" => config.chunk_size = value.parse().map_err(|_| invalid())?,
+                "--height-frequency" => {
+                    config.height_frequency = value.parse().map_err(|_| invalid())?
+                }
+                "--height-octaves" => {
+                    config.height_octaves = value.parse().map_err(|_| invalid())?
+                }
+                "--height-noise" => {
+                    config.height_noise = NoiseShape::parse(&value).ok_or_else(invalid)?
+                }
+                "--height-warp" => config.height_warp = value.parse().map_err(|_| invalid())?,
+                "--cavern-frequency" => {
+                    config.cavern_frequency = value.parse().map_err(|_| invalid())?
+                }
+                "--cavern-octaves" => {
+                    config.cavern_octaves = value.parse().map_err(|_| invalid())?
+                }
+                "--cavern-warp" => config.cavern_warp = value.parse().map_err(|_| invalid())?,
+                "--cavern-warp-frequency" => {
+                    config.cavern_warp_frequency = value.parse().map_err(|_| invalid())?
+                }
+                "--cave-frequency" => {
+                    config.cave_frequency = value.parse().map_err(|_| invalid())?
+                }
+                "--cave-octaves" => config.cave_octaves = value.parse().map_err(|_| invalid())?,
+                "--mountain-height" => {
+                    config.mountain_height = value.parse().map_err(|_| invalid())?
+                }
+                "--magma-level" => config.magma_level = value.parse().map_err(|_| invalid())?,
+                "--dirt-depth" => config.dirt_depth = value.parse().map_err(|_| invalid())?,
+                "--cavern-depth" => config.cavern_depth = value.parse().map_err(|_| invalid())?,
+                "--cave-threshold" => {
+                    config.cave_threshold = value.parse().map_err(|_| invalid())?
+                }
+                "--cave-entrances" => {
+                    config.cave_entrances = value.parse().map_err(|_| invalid())?
+                }
+                "--sea-level" => config.sea_level = value.parse().map_err(|_| invalid())?,
+                "--min-lake-size" => config.min_lake_size = value.parse().map_err(|_| invalid())?,
+                "--tree-density" => config.tree_density = value.parse().map_err(|_| invalid())?,
+                "--tree-frequency" => {
+                    config.tree_frequency = value.parse().map_err(|_| invalid())?
+                }
+                "--coal-veins" => {
+                    config.coal.veins_per_chunk = value.parse().map_err(|_| invalid())?
+                }
+                "--iron-veins" => {
+                    config.iron.veins_per_chunk = value.parse().map_err(|_| invalid())?
+                }
+                "--gold-veins" => {
+                    config.gold.veins_per_chunk = value.parse().map_err(|_| invalid())?
+                }
+                _ => return Err(WorldGenConfigError::UnknownArg(arg.clone())),
+            }
+        }
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), WorldGenConfigError> {
+        validate_world_shape(self.chunk_counts, self.chunk_size)?;
+
+        let height = self.world_height();
+        // the lowest surface sits about halfway up, and still needs room for
+        // the magma and dirt layers below it
+        let min = 2 * (self.magma_level + self.dirt_depth) + 8;
+
+        if height < min {
+            return Err(WorldGenConfigError::WorldTooShort { height, min });
+        }
+
+        if height > MAX_WORLD_HEIGHT {
+            return Err(WorldGenConfigError::WorldTooTall {
+                height,
+                max: MAX_WORLD_HEIGHT,
+            });
+        }
+
+        if self.sea_level >= height {
+            return Err(WorldGenConfigError::SeaLevelTooHigh {
+                sea_level: self.sea_level,
+                max: height - 1,
+            });
+        }
+
+        if !(0. ..=1.).contains(&self.tree_density) {
+            return Err(WorldGenConfigError::TreeDensity(self.tree_density));
+        }
+
+        for ore in self.ores() {
+            let is_valid =
+                0. <= ore.min_height && ore.min_height <= ore.max_height && ore.max_height <= 1.;
+
+            if !is_valid {
+                return Err(WorldGenConfigError::OreBand {
+                    ore: ore.block.name(),
+                    min: ore.min_height,
+                    max: ore.max_height,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn ores(&self) -> [&OreConfig; 3] {
+        [&self.coal, &self.iron, &self.gold]
+    }
+
+    pub fn world_height(&self) -> u32 {
+        self.chunk_counts[1] * self.chunk_size
+    }
+
+    pub fn build_terrain(&self) -> Result<Terrain, WorldGenConfigError> {
+        let [x, y, z] = self.chunk_counts;
+        Terrain::new(x, y, z, self.chunk_size)
+    }
+}
@@ -259,6 +259,146 @@ pub fn setup_block_toolbar_ui(mut cmd: Commands) {
                 ));
             });
 
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(48.0),
+                        height: Val::Px(48.0),
+                        justify_content: JustifyContent::Center,
+                        align_content: AlignContent::Center,
+                        ..default()
+                    },
+                    background_color: BTN_NONE.into(),
+                    ..default()
+                },
+                BtnTool {
+                    tool: Tool::SpawnFood,
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "food",
+                    TextStyle {
+                        font_size: 18.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ));
+            });
+
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(48.0),
+                        height: Val::Px(48.0),
+                        justify_content: JustifyContent::Center,
+                        align_content: AlignContent::Center,
+                        ..default()
+                    },
+                    background_color: BTN_NONE.into(),
+                    ..default()
+                },
+                BtnTool {
+                    tool: Tool::SpawnHostile,
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "hostile",
+                    TextStyle {
+                        font_size: 18.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ));
+            });
+
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(48.0),
+                        height: Val::Px(48.0),
+                        justify_content: JustifyContent::Center,
+                        align_content: AlignContent::Center,
+                        ..default()
+                    },
+                    background_color: BTN_NONE.into(),
+                    ..default()
+                },
+                BtnTool {
+                    tool: Tool::PatrolRoute,
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "patrol",
+                    TextStyle {
+                        font_size: 18.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ));
+            });
+
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(48.0),
+                        height: Val::Px(48.0),
+                        justify_content: JustifyContent::Center,
+                        align_content: AlignContent::Center,
+                        ..default()
+                    },
+                    background_color: BTN_NONE.into(),
+                    ..default()
+                },
+                BtnTool {
+                    tool: Tool::GuardPost,
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "guard",
+                    TextStyle {
+                        font_size: 18.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ));
+            });
+
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(48.0),
+                        height: Val::Px(48.0),
+                        justify_content: JustifyContent::Center,
+                        align_content: AlignContent::Center,
+                        ..default()
+                    },
+                    background_color: BTN_NONE.into(),
+                    ..default()
+                },
+                BtnTool {
+                    tool: Tool::Stockpile,
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "stock",
+                    TextStyle {
+                        font_size: 18.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ));
+            });
+
         vec![
             BlockType::GRASS,
             BlockType::DIRT,
@@ -268,6 +408,23 @@ pub fn setup_block_toolbar_ui(mut cmd: Commands) {
             BlockType::LAMP,
             BlockType::MAGMA,
             BlockType::LADDER,
+            BlockType::CAMPFIRE,
+            BlockType::FARM_SOIL,
+            BlockType::SAND,
+            BlockType::GRAVEL,
+            BlockType::CLAY,
+            BlockType::WATER,
+            BlockType::WOOD,
+            BlockType::TORCH,
+            BlockType::GLASS,
+            BlockType::MECHANISM,
+            BlockType::PRESSURE_PLATE,
+            BlockType::ROTTEN,
+            BlockType::STAIRS_NORTH,
+            BlockType::STAIRS_SOUTH,
+            BlockType::STAIRS_EAST,
+            BlockType::STAIRS_WEST,
+            BlockType::REBAR_STONE,
         ]
         .into_iter()
         .for_each(|block: BlockType| {
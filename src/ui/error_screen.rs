@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+
+/// Why the game couldn't start, shown by `setup_error_screen` in place of
+/// the world
+#[derive(Resource)]
+pub struct StartupError(pub String);
+
+pub fn setup_error_screen(mut cmd: Commands, error: Res<StartupError>) {
+    cmd.spawn(Camera2dBundle::default());
+
+    cmd.spawn(NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(12.0),
+            ..default()
+        },
+        background_color: Color::rgb(0.1, 0.1, 0.1).into(),
+        ..default()
+    })
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            "The world could not be created",
+            TextStyle {
+                font_size: 32.0,
+                color: Color::ORANGE_RED,
+                ..default()
+            },
+        ));
+        parent.spawn(TextBundle::from_section(
+            error.0.clone(),
+            TextStyle {
+                font_size: 18.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+                ..default()
+            },
+        ));
+    });
+}
+
+/// Opens a window with nothing but the error in it
+pub fn run_error_screen(error: String) {
+    App::new()
+        .insert_resource(StartupError(error))
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup_error_screen)
+        .run();
+}
@@ -1,7 +1,9 @@
 mod block_toolbar;
+mod error_screen;
 mod pointer_capture;
 mod tool;
 
 pub use block_toolbar::*;
+pub use error_screen::*;
 pub use pointer_capture::*;
 pub use tool::*;
@@ -1,24 +1,30 @@
+use std::sync::Arc;
+
 use bevy::{
     ecs::{
+        entity::Entity,
         event::EventWriter,
         query::With,
-        system::{Local, Query, Res, ResMut},
+        system::{Commands, Local, Query, Res, ResMut, SystemParam},
     },
-    input::{mouse::MouseButton, ButtonInput},
+    input::{keyboard::KeyCode, mouse::MouseButton, ButtonInput},
     math::Vec3,
     transform::components::Transform,
 };
 
 use crate::{
     colonists::{
-        Job, NavigationGraph, PartitionDebug, SpawnColonistEvent, SpawnJobBuildEvent,
-        SpawnJobMineEvent,
+        Behavior, BehaviorNode, Colonist, DesignateMineEvent, DesignateStockpileEvent,
+        FactionRelations, GuardPost, InterruptBehavior, Job, NavigationGraph, PartitionDebug,
+        PatrolRoute, Selected, SpawnColonistEvent, SpawnHostileEvent, SpawnJobBuildEvent,
+        TaskBuilder, TaskMoveTo, TaskSetMoveGoals, UndesignateStockpileEvent, MOVE_TIMEOUT_S,
+        PLAYER_FACTION, RIVAL_FACTION,
     },
     common::min_max,
-    controls::Raycast,
+    controls::CursorHit,
     debug::debug_settings::DebugSettings,
-    items::SpawnPickaxeEvent,
-    BlockType, Cursor, Terrain,
+    items::{SpawnFoodEvent, SpawnPickaxeEvent},
+    BlockChangedEvent, BlockType, Cursor, Terrain,
 };
 
 use super::Toolbar;
@@ -30,11 +36,26 @@ pub enum Tool {
     ClearBlocks,
     SpawnColonist,
     SpawnPickaxe,
+    SpawnFood,
+    SpawnHostile,
+    PatrolRoute,
+    GuardPost,
+    Stockpile,
     BuildStone,
     BlockInfo,
     Mine,
 }
 
+/// Events of the spawn tools, bundled to keep `tool_system` within the
+/// system parameter limit
+#[derive(SystemParam)]
+pub struct SpawnEvents<'w> {
+    colonist: EventWriter<'w, SpawnColonistEvent>,
+    pickaxe: EventWriter<'w, SpawnPickaxeEvent>,
+    food: EventWriter<'w, SpawnFoodEvent>,
+    hostile: EventWriter<'w, SpawnHostileEvent>,
+}
+
 #[derive(Default)]
 pub struct ToolState {
     is_dragging: bool,
@@ -43,19 +64,21 @@ pub struct ToolState {
 
 pub fn tool_system(
     toolbar: Res<Toolbar>,
-    raycast: Res<Raycast>,
+    cursor_hit: Res<CursorHit>,
     graph: Res<NavigationGraph>,
     mut terrain: ResMut<Terrain>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     mut state: Local<ToolState>,
     mut cursor_query: Query<&mut Transform, With<Cursor>>,
-    mut ev_spawn_colonist: EventWriter<SpawnColonistEvent>,
-    mut ev_spawn_pickaxe: EventWriter<SpawnPickaxeEvent>,
+    mut ev_spawn: SpawnEvents,
     mut ev_spawn_job_build: EventWriter<SpawnJobBuildEvent>,
-    mut ev_spawn_job_mine: EventWriter<SpawnJobMineEvent>,
+    mut ev_designate_mine: EventWriter<DesignateMineEvent>,
+    mut ev_block_changed: EventWriter<BlockChangedEvent>,
     mut partition_debug: ResMut<PartitionDebug>,
     mut debug_settings: ResMut<DebugSettings>,
     q_jobs: Query<&Job>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut relations: ResMut<FactionRelations>,
 ) {
     match toolbar.tool {
         Tool::PlaceBlocks(block) => {
@@ -68,38 +91,35 @@ pub fn tool_system(
             }
 
             if mouse_input.just_released(MouseButton::Left) {
-                if !raycast.is_adj_hit {
+                if !cursor_hit.is_adj_hit {
                     state.is_dragging = false;
                     return;
                 }
 
                 if !state.is_dragging {
                     state.is_dragging = true;
-                    state.start = raycast.adj_pos;
+                    state.start = cursor_hit.adj_pos;
                     return;
                 }
 
                 state.is_dragging = false;
 
-                let [min_x, max_x] = min_max(state.start[0], raycast.adj_pos[0]);
-                let [min_y, max_y] = min_max(state.start[1], raycast.adj_pos[1]);
-                let [min_z, max_z] = min_max(state.start[2], raycast.adj_pos[2]);
+                let [min_x, max_x] = min_max(state.start[0], cursor_hit.adj_pos[0]);
+                let [min_y, max_y] = min_max(state.start[1], cursor_hit.adj_pos[1]);
+                let [min_z, max_z] = min_max(state.start[2], cursor_hit.adj_pos[2]);
 
                 cursor.scale = Vec3::ZERO;
 
-                for x in min_x..=max_x {
-                    for y in min_y..=max_y {
-                        for z in min_z..=max_z {
-                            terrain.set_block_type(x, y, z, block);
-                        }
-                    }
-                }
+                let changes = terrain.record_batch(|terrain| {
+                    terrain.fill_region([min_x, min_y, min_z], [max_x, max_y, max_z], block)
+                });
+                ev_block_changed.send_batch(changes.into_iter().map(BlockChangedEvent::from));
             }
 
             if state.is_dragging {
-                let [min_x, max_x] = min_max(state.start[0], raycast.adj_pos[0]);
-                let [min_y, max_y] = min_max(state.start[1], raycast.adj_pos[1]);
-                let [min_z, max_z] = min_max(state.start[2], raycast.adj_pos[2]);
+                let [min_x, max_x] = min_max(state.start[0], cursor_hit.adj_pos[0]);
+                let [min_y, max_y] = min_max(state.start[1], cursor_hit.adj_pos[1]);
+                let [min_z, max_z] = min_max(state.start[2], cursor_hit.adj_pos[2]);
 
                 let scale = Vec3::new(
                     ((max_x - min_x) + 1) as f32,
@@ -120,9 +140,9 @@ pub fn tool_system(
             }
 
             if state.is_dragging {
-                let [min_x, max_x] = min_max(state.start[0], raycast.hit_pos[0]);
-                let [min_y, max_y] = min_max(state.start[1], raycast.hit_pos[1]);
-                let [min_z, max_z] = min_max(state.start[2], raycast.hit_pos[2]);
+                let [min_x, max_x] = min_max(state.start[0], cursor_hit.hit_pos[0]);
+                let [min_y, max_y] = min_max(state.start[1], cursor_hit.hit_pos[1]);
+                let [min_z, max_z] = min_max(state.start[2], cursor_hit.hit_pos[2]);
 
                 let scale = Vec3::new(
                     ((max_x - min_x) + 1) as f32,
@@ -134,61 +154,94 @@ pub fn tool_system(
             }
 
             if mouse_input.just_released(MouseButton::Left) {
-                if !raycast.is_hit {
+                if !cursor_hit.is_hit {
                     state.is_dragging = false;
                     return;
                 }
 
                 if !state.is_dragging {
                     state.is_dragging = true;
-                    state.start = raycast.hit_pos;
+                    state.start = cursor_hit.hit_pos;
                     return;
                 }
 
                 state.is_dragging = false;
 
-                let [min_x, max_x] = min_max(state.start[0], raycast.hit_pos[0]);
-                let [min_y, max_y] = min_max(state.start[1], raycast.hit_pos[1]);
-                let [min_z, max_z] = min_max(state.start[2], raycast.hit_pos[2]);
+                let [min_x, max_x] = min_max(state.start[0], cursor_hit.hit_pos[0]);
+                let [min_y, max_y] = min_max(state.start[1], cursor_hit.hit_pos[1]);
+                let [min_z, max_z] = min_max(state.start[2], cursor_hit.hit_pos[2]);
 
                 cursor.scale = Vec3::ZERO;
 
-                for x in min_x..=max_x {
-                    for y in min_y..=max_y {
-                        for z in min_z..=max_z {
-                            terrain.set_block_type(x, y, z, BlockType::EMPTY);
-                        }
-                    }
-                }
+                let changes = terrain.record_batch(|terrain| {
+                    terrain.clear_region([min_x, min_y, min_z], [max_x, max_y, max_z])
+                });
+                ev_block_changed.send_batch(changes.into_iter().map(BlockChangedEvent::from));
             }
         }
         Tool::SpawnColonist => {
             if mouse_input.just_released(MouseButton::Left) {
-                if !raycast.is_adj_hit {
+                if !cursor_hit.is_adj_hit {
                     return;
                 }
 
-                ev_spawn_colonist.send(SpawnColonistEvent {
-                    pos: raycast.adj_pos,
+                let faction = if keys.pressed(KeyCode::ShiftLeft) {
+                    relations.set_hostile(PLAYER_FACTION, RIVAL_FACTION, true);
+                    RIVAL_FACTION
+                } else {
+                    PLAYER_FACTION
+                };
+
+                ev_spawn.colonist.send(SpawnColonistEvent {
+                    pos: cursor_hit.adj_pos,
+                    faction,
+                    relationships: None,
+                    inventory: None,
                 });
             }
         }
         Tool::BlockInfo => {
             if mouse_input.just_released(MouseButton::Left) {
-                if !raycast.is_adj_hit {
+                if !cursor_hit.is_adj_hit {
                     return;
                 }
 
                 let count = q_jobs.iter().len();
                 println!("JOB COUNT {}", count);
 
-                let hit = raycast.hit_block;
+                let hit = cursor_hit.hit_block;
                 println!("block {}. blueprint={}", hit.name(), hit.flag_blueprint);
+                println!(
+                    "{} in world={}",
+                    hit.name(),
+                    terrain.count_blocks_of_type_global(hit.block)
+                );
+                println!(
+                    "temperature={}",
+                    terrain.get_temperature_xyz(
+                        cursor_hit.adj_pos[0],
+                        cursor_hit.adj_pos[1],
+                        cursor_hit.adj_pos[2]
+                    )
+                );
+                println!(
+                    "surface y={:?}, top navigable y={:?}",
+                    terrain.get_surface_y(cursor_hit.adj_pos[0], cursor_hit.adj_pos[2]),
+                    terrain.get_top_navigable_y(cursor_hit.adj_pos[0], cursor_hit.adj_pos[2])
+                );
+
+                let pocket =
+                    terrain.flood_fill_blocks(cursor_hit.adj_pos, |block| block.is_empty());
+                if pocket.len() >= terrain.flood_fill_max_blocks {
+                    println!("open air={}+", pocket.len());
+                } else {
+                    println!("open air={}", pocket.len());
+                }
 
                 let [chunk_idx, block_idx] = terrain.get_block_indexes(
-                    raycast.adj_pos[0],
-                    raycast.adj_pos[1],
-                    raycast.adj_pos[2],
+                    cursor_hit.adj_pos[0],
+                    cursor_hit.adj_pos[1],
+                    cursor_hit.adj_pos[2],
                 );
 
                 let Some(partition_id) = terrain.get_partition_id(chunk_idx, block_idx) else {
@@ -222,9 +275,9 @@ pub fn tool_system(
             }
 
             if state.is_dragging {
-                let [min_x, max_x] = min_max(state.start[0], raycast.hit_pos[0]);
-                let [min_y, max_y] = min_max(state.start[1], raycast.hit_pos[1]);
-                let [min_z, max_z] = min_max(state.start[2], raycast.hit_pos[2]);
+                let [min_x, max_x] = min_max(state.start[0], cursor_hit.hit_pos[0]);
+                let [min_y, max_y] = min_max(state.start[1], cursor_hit.hit_pos[1]);
+                let [min_z, max_z] = min_max(state.start[2], cursor_hit.hit_pos[2]);
 
                 let scale = Vec3::new(
                     ((max_x - min_x) + 1) as f32,
@@ -236,62 +289,335 @@ pub fn tool_system(
             }
 
             if mouse_input.just_released(MouseButton::Left) {
-                if !raycast.is_hit {
+                if !cursor_hit.is_hit {
                     state.is_dragging = false;
                     return;
                 }
 
                 if !state.is_dragging {
                     state.is_dragging = true;
-                    state.start = raycast.hit_pos;
+                    state.start = cursor_hit.hit_pos;
                     return;
                 }
 
                 state.is_dragging = false;
 
-                let [min_x, max_x] = min_max(state.start[0], raycast.hit_pos[0]);
-                let [min_y, max_y] = min_max(state.start[1], raycast.hit_pos[1]);
-                let [min_z, max_z] = min_max(state.start[2], raycast.hit_pos[2]);
+                let [min_x, max_x] = min_max(state.start[0], cursor_hit.hit_pos[0]);
+                let [min_y, max_y] = min_max(state.start[1], cursor_hit.hit_pos[1]);
+                let [min_z, max_z] = min_max(state.start[2], cursor_hit.hit_pos[2]);
 
                 cursor.scale = Vec3::ZERO;
 
-                for x in min_x..=max_x {
-                    for y in min_y..=max_y {
-                        for z in min_z..=max_z {
-                            if !terrain.get_block(x, y, z).is_empty() {
-                                ev_spawn_job_mine.send(SpawnJobMineEvent { pos: [x, y, z] });
-                            }
-                        }
-                    }
-                }
+                ev_designate_mine.send(DesignateMineEvent {
+                    min: [min_x, min_y, min_z],
+                    max: [max_x, max_y, max_z],
+                });
             }
         }
         Tool::TogglePathDebug => {
             if mouse_input.just_released(MouseButton::Left) {
                 debug_settings.path = !debug_settings.path;
+                partition_debug.show_path = debug_settings.path;
             }
         }
         Tool::SpawnPickaxe => {
-            if !raycast.is_adj_hit {
+            if !cursor_hit.is_adj_hit {
                 return;
             }
 
             if mouse_input.just_released(MouseButton::Left) {
-                ev_spawn_pickaxe.send(SpawnPickaxeEvent {
-                    pos: raycast.adj_pos,
+                ev_spawn.pickaxe.send(SpawnPickaxeEvent {
+                    pos: cursor_hit.adj_pos,
+                });
+            }
+        }
+        Tool::PatrolRoute | Tool::GuardPost | Tool::Stockpile => {}
+        Tool::SpawnFood => {
+            if !cursor_hit.is_adj_hit {
+                return;
+            }
+
+            if mouse_input.just_released(MouseButton::Left) {
+                ev_spawn.food.send(SpawnFoodEvent {
+                    pos: cursor_hit.adj_pos,
+                    is_cooked: false,
+                });
+            }
+        }
+        Tool::SpawnHostile => {
+            if !cursor_hit.is_adj_hit {
+                return;
+            }
+
+            if mouse_input.just_released(MouseButton::Left) {
+                ev_spawn.hostile.send(SpawnHostileEvent {
+                    pos: cursor_hit.adj_pos,
                 });
             }
         }
         Tool::BuildStone => {
-            if !raycast.is_adj_hit {
+            if !cursor_hit.is_adj_hit {
                 return;
             }
 
             if mouse_input.just_released(MouseButton::Left) {
                 ev_spawn_job_build.send(SpawnJobBuildEvent {
-                    pos: raycast.adj_pos,
+                    pos: cursor_hit.adj_pos,
+                    block: BlockType::STONE,
                 });
             }
         }
     }
 }
+
+/// Left click adds a waypoint to every colonist's patrol route, right click
+/// clears the routes.
+pub fn patrol_route_tool(
+    mut cmd: Commands,
+    toolbar: Res<Toolbar>,
+    cursor_hit: Res<CursorHit>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut q_colonists: Query<(Entity, Option<&mut PatrolRoute>), With<Colonist>>,
+) {
+    if toolbar.tool != Tool::PatrolRoute {
+        return;
+    }
+
+    if mouse_input.just_released(MouseButton::Right) {
+        for (entity, _) in q_colonists.iter() {
+            cmd.entity(entity).remove::<PatrolRoute>();
+        }
+        return;
+    }
+
+    if !mouse_input.just_released(MouseButton::Left) || !cursor_hit.is_adj_hit {
+        return;
+    }
+
+    for (entity, route) in q_colonists.iter_mut() {
+        match route {
+            Some(mut route) => route.waypoints.push(cursor_hit.adj_pos),
+            None => {
+                cmd.entity(entity).insert(PatrolRoute {
+                    waypoints: vec![cursor_hit.adj_pos],
+                });
+            }
+        }
+    }
+}
+
+/// Left click sets every colonist's guard post, right click clears it.
+pub fn guard_post_tool(
+    mut cmd: Commands,
+    toolbar: Res<Toolbar>,
+    cursor_hit: Res<CursorHit>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    q_colonists: Query<Entity, With<Colonist>>,
+) {
+    if toolbar.tool != Tool::GuardPost {
+        return;
+    }
+
+    if mouse_input.just_released(MouseButton::Right) {
+        for entity in q_colonists.iter() {
+            cmd.entity(entity).remove::<GuardPost>();
+        }
+        return;
+    }
+
+    if !mouse_input.just_released(MouseButton::Left) || !cursor_hit.is_adj_hit {
+        return;
+    }
+
+    for entity in q_colonists.iter() {
+        cmd.entity(entity).insert(GuardPost {
+            pos: cursor_hit.adj_pos,
+        });
+    }
+}
+
+/// Clicking with the info tool while a colonist is selected prints how far
+/// it would have to walk there. Runs before `select_colonist_tool` changes
+/// the selection.
+pub fn colonist_distance_tool(
+    toolbar: Res<Toolbar>,
+    cursor_hit: Res<CursorHit>,
+    terrain: Res<Terrain>,
+    mut graph: ResMut<NavigationGraph>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    q_selected: Query<&Transform, (With<Colonist>, With<Selected>)>,
+) {
+    if toolbar.tool != Tool::BlockInfo
+        || !mouse_input.just_released(MouseButton::Left)
+        || !cursor_hit.is_adj_hit
+    {
+        return;
+    }
+
+    let [x, y, z] = cursor_hit.adj_pos;
+    let Some(to) = terrain.get_partition_id_u32(x, y, z) else {
+        return;
+    };
+
+    for transform in q_selected.iter() {
+        let Some(from) = terrain.get_partition_id_u32(
+            transform.translation.x as u32,
+            transform.translation.y as u32,
+            transform.translation.z as u32,
+        ) else {
+            continue;
+        };
+
+        match graph.shortest_path_length(from, to) {
+            Some(length) => println!("~{:.0} steps away", length),
+            None => println!("out of reach"),
+        }
+    }
+}
+
+/// Clicking next to a colonist with the info tool selects it, clicking
+/// anywhere else clears the selection.
+pub fn select_colonist_tool(
+    mut cmd: Commands,
+    toolbar: Res<Toolbar>,
+    cursor_hit: Res<CursorHit>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    q_colonists: Query<(Entity, &Transform), With<Colonist>>,
+    q_selected: Query<Entity, With<Selected>>,
+) {
+    if toolbar.tool != Tool::BlockInfo
+        || !mouse_input.just_released(MouseButton::Left)
+        || !cursor_hit.is_adj_hit
+    {
+        return;
+    }
+
+    for entity in q_selected.iter() {
+        cmd.entity(entity).remove::<Selected>();
+    }
+
+    let [x, y, z] = cursor_hit.adj_pos;
+    let clicked = Vec3::new(x as f32 + 0.5, y as f32, z as f32 + 0.5);
+
+    let nearest = q_colonists
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation.distance(clicked)))
+        .filter(|(_, distance)| *distance <= 1.5)
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+
+    if let Some((entity, _)) = nearest {
+        cmd.entity(entity).insert(Selected);
+    }
+}
+
+/// Right clicking with the info tool orders the selected colonist to walk
+/// there, dropping whatever it was doing.
+pub fn order_colonist_tool(
+    mut cmd: Commands,
+    toolbar: Res<Toolbar>,
+    cursor_hit: Res<CursorHit>,
+    terrain: Res<Terrain>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    q_selected: Query<Entity, (With<Colonist>, With<Selected>)>,
+) {
+    if toolbar.tool != Tool::BlockInfo
+        || !mouse_input.just_released(MouseButton::Right)
+        || !cursor_hit.is_adj_hit
+    {
+        return;
+    }
+
+    // the block next to a face on the world's edge is outside of it
+    let [x, y, z] = cursor_hit.adj_pos;
+    if !terrain.contains_world_pos(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5) {
+        return;
+    }
+
+    for entity in q_selected.iter() {
+        let order = Behavior::new(
+            "Ordered",
+            BehaviorNode::Sequence(vec![
+                BehaviorNode::Task(Arc::new(TaskSetMoveGoals(vec![cursor_hit.adj_pos]))),
+                BehaviorNode::Task(Arc::new(TaskMoveTo.with_timeout(MOVE_TIMEOUT_S))),
+            ]),
+        );
+
+        cmd.entity(entity)
+            .insert(InterruptBehavior::new("ordered to move", Some(order)));
+    }
+}
+
+/// Drag a box over the floor to designate a stockpile for any item. A box
+/// overlapping an existing stockpile grows it, holding shift when the drag
+/// ends clears the box out of the stockpiles instead.
+pub fn stockpile_tool(
+    toolbar: Res<Toolbar>,
+    cursor_hit: Res<CursorHit>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut state: Local<ToolState>,
+    mut cursor_query: Query<&mut Transform, With<Cursor>>,
+    mut ev_designate_stockpile: EventWriter<DesignateStockpileEvent>,
+    mut ev_undesignate_stockpile: EventWriter<UndesignateStockpileEvent>,
+) {
+    if toolbar.tool != Tool::Stockpile {
+        return;
+    }
+
+    let mut cursor = cursor_query.get_single_mut().unwrap();
+
+    if mouse_input.just_released(MouseButton::Right) {
+        state.is_dragging = false;
+        cursor.scale = Vec3::ZERO;
+        return;
+    }
+
+    if state.is_dragging {
+        let [min_x, max_x] = min_max(state.start[0], cursor_hit.adj_pos[0]);
+        let [min_y, max_y] = min_max(state.start[1], cursor_hit.adj_pos[1]);
+        let [min_z, max_z] = min_max(state.start[2], cursor_hit.adj_pos[2]);
+
+        cursor.scale = Vec3::new(
+            ((max_x - min_x) + 1) as f32,
+            ((max_y - min_y) + 1) as f32,
+            ((max_z - min_z) + 1) as f32,
+        );
+        cursor.translation = Vec3::new(min_x as f32, min_y as f32, min_z as f32);
+    }
+
+    if !mouse_input.just_released(MouseButton::Left) {
+        return;
+    }
+
+    if !cursor_hit.is_adj_hit {
+        state.is_dragging = false;
+        return;
+    }
+
+    if !state.is_dragging {
+        state.is_dragging = true;
+        state.start = cursor_hit.adj_pos;
+        return;
+    }
+
+    state.is_dragging = false;
+    cursor.scale = Vec3::ZERO;
+
+    let [min_x, max_x] = min_max(state.start[0], cursor_hit.adj_pos[0]);
+    let [min_y, max_y] = min_max(state.start[1], cursor_hit.adj_pos[1]);
+    let [min_z, max_z] = min_max(state.start[2], cursor_hit.adj_pos[2]);
+
+    if keys.pressed(KeyCode::ShiftLeft) {
+        ev_undesignate_stockpile.send(UndesignateStockpileEvent {
+            min: [min_x, min_y, min_z],
+            max: [max_x, max_y, max_z],
+        });
+        return;
+    }
+
+    ev_designate_stockpile.send(DesignateStockpileEvent {
+        min: [min_x, min_y, min_z],
+        max: [max_x, max_y, max_z],
+        accepts: vec![],
+    });
+}
@@ -2,7 +2,7 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, DeriveInput, Ident};
 
-#[proc_macro_derive(TaskBuilder)]
+#[proc_macro_derive(TaskBuilder, attributes(estimated_duration))]
 pub fn task_builder_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -13,12 +13,14 @@ pub fn task_builder_derive(input: proc_macro::TokenStream) -> proc_macro::TokenS
     let label_method = label_method(&component_name);
     let insert_method = insert_method(&component_name, &ty_generics);
     let remove_method = remove_method(&component_name);
+    let estimated_duration_method = estimated_duration_method(&input.attrs);
 
     let gen = quote! {
         impl #impl_generics TaskBuilder for #component_name #ty_generics #where_clause {
             #label_method
             #insert_method
             #remove_method
+            #estimated_duration_method
         }
     };
 
@@ -50,3 +52,26 @@ fn remove_method(component_name: &Ident) -> TokenStream {
         }
     }
 }
+
+/// `#[estimated_duration(method)]` forwards to an inherent
+/// `fn method(&self, &Blackboard) -> Option<f32>` on the task, without it
+/// the trait default is used.
+fn estimated_duration_method(attrs: &[syn::Attribute]) -> TokenStream {
+    let Some(attr) = attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("estimated_duration"))
+    else {
+        return quote! {};
+    };
+
+    let method = match attr.parse_args::<Ident>() {
+        Ok(method) => method,
+        Err(err) => return err.to_compile_error(),
+    };
+
+    quote! {
+        fn estimated_duration(&self, blackboard: &crate::colonists::Blackboard) -> Option<f32> {
+            self.#method(blackboard)
+        }
+    }
+}